@@ -1,7 +1,10 @@
 use anyhow::{Context, Result, anyhow};
 use serde::{Deserialize, Serialize};
 use serde_json::json;
-use super::{secrets, settings};
+use super::ai_queue::{self, RequestPriority};
+use super::redact::{redact, redact_error};
+use super::commit_lint::{self, CommitValidation};
+use super::{auth, git, http, secrets, settings, snippets, telemetry, terminal, terminal_history, workspace};
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct AiRunResult {
@@ -9,6 +12,57 @@ pub struct AiRunResult {
     pub updated_content: Option<String>,
 }
 
+const CHAT_SYSTEM_PROMPT: &str = "You are a coding assistant inside an editor. Be direct and helpful. IMPORTANT: Respond ONLY with a single valid JSON object (no markdown, no code fences). Schema: {\"assistant_message\": string, \"edits\": [{\"op\": \"write\"|\"patch\"|\"delete\"|\"rename\"|\"run\", \"path\"?: string, \"content\"?: string, \"from\"?: string, \"to\"?: string}], \"summary\"?: string }. Never put code in assistant_message; code must only appear inside edits[].content. If you have no edits, return {\"assistant_message\": <answer>, \"edits\": []}.";
+// For DeepSeek prefix completion, whose reply continues the trailing assistant message as plain
+// text; the JSON envelope above would contradict the text being continued.
+const PREFIX_SYSTEM_PROMPT: &str = "You are a coding assistant inside an editor. Continue the last assistant message exactly where it stops.";
+
+// DeepSeek continues a trailing assistant message instead of answering it (prefix completion).
+fn is_prefix_completion(provider: &str, messages: &[ChatMessage]) -> bool {
+    provider == "deepseek" && messages.last().is_some_and(|m| m.role == "assistant")
+}
+
+// Untrusted workspaces never get shell commands proposed as runnable edits.
+fn trusted_edits(edits: Option<Vec<AiEditOp>>, s: &settings::AppSettings) -> Option<Vec<AiEditOp>> {
+    if workspace::is_trusted(s) {
+        return edits;
+    }
+    edits.map(|e| e.into_iter().filter(|op| !op.op.trim().eq_ignore_ascii_case("run")).collect())
+}
+
+/// Appends the open workspace's instructions file (`.pompora/instructions.md`, `AGENTS.md`,
+/// `.cursorrules`) and the user's snippets to a system prompt.
+fn with_workspace_instructions(base: &str, s: &settings::AppSettings) -> String {
+    let mut out = base.to_string();
+    let root = s
+        .workspace_root
+        .as_deref()
+        .map(|v| v.trim())
+        .filter(|v| !v.is_empty());
+    // Instruction files are repository content; don't feed them to the model unless trusted.
+    if let (Some(root), true) = (root, workspace::is_trusted(s)) {
+        if let Some((name, text)) = workspace::load_instructions(std::path::Path::new(root)) {
+            out.push_str(&format!("\n\nProject instructions (from {name}):\n{text}"));
+        }
+    }
+    if let Some(section) = snippets::prompt_section(s) {
+        out.push_str("\n\n");
+        out.push_str(&section);
+    }
+    out
+}
+
+/// The open workspace's pinned provider, falling back to the globally active one.
+fn active_provider(s: &settings::AppSettings) -> Result<String> {
+    s.workspace_key_override()
+        .and_then(|o| o.provider.as_deref())
+        .or(s.active_provider.as_deref())
+        .map(|v| v.trim())
+        .filter(|v| !v.is_empty())
+        .map(|v| v.to_string())
+        .ok_or_else(|| anyhow!("no provider is configured"))
+}
+
 fn messages_to_plain_input(messages: &[ChatMessage]) -> String {
     let mut out: Vec<String> = Vec::with_capacity(messages.len());
     for m in messages {
@@ -68,27 +122,33 @@ pub async fn ai_chat_with_model(
     encryption_password: Option<&str>,
     model_override: Option<&str>,
     thinking: Option<&str>,
+    priority: RequestPriority,
 ) -> Result<AiChatResult> {
     let s = settings::load()?;
     if s.offline_mode {
         return Err(anyhow!("offline mode is enabled"));
     }
 
-    let provider = s
-        .active_provider
-        .as_deref()
-        .map(|v| v.trim())
-        .filter(|v| !v.is_empty())
-        .ok_or_else(|| anyhow!("no provider is configured"))?;
+    let provider = active_provider(&s)?;
 
+    let prefix_mode = is_prefix_completion(&provider, &messages);
+    let system = if prefix_mode { PREFIX_SYSTEM_PROMPT } else { CHAT_SYSTEM_PROMPT };
     let mut msgs: Vec<ChatMessage> = vec![];
     msgs.push(ChatMessage {
         role: "system".to_string(),
-        content: "You are a coding assistant inside an editor. Be direct and helpful. IMPORTANT: Respond ONLY with a single valid JSON object (no markdown, no code fences). Schema: {\"assistant_message\": string, \"edits\": [{\"op\": \"write\"|\"patch\"|\"delete\"|\"rename\"|\"run\", \"path\"?: string, \"content\"?: string, \"from\"?: string, \"to\"?: string}], \"summary\"?: string }. Never put code in assistant_message; code must only appear inside edits[].content. If you have no edits, return {\"assistant_message\": <answer>, \"edits\": []}.".to_string(),
+        content: with_workspace_instructions(system, &s),
     });
     msgs.extend(messages);
 
-    let text = request_chat_completion(provider, encryption_password, msgs, 0.4, model_override, thinking).await?;
+    let completion = request_chat_completion(&provider, encryption_password, msgs, 0.4, model_override, thinking, priority).await?;
+    let text = completion.text;
+    if prefix_mode {
+        return Ok(AiChatResult {
+            output: text,
+            edits: None,
+            reasoning: completion.reasoning,
+        });
+    }
 
     let direct = serde_json::from_str::<StructuredChatOut>(&text).ok();
     let extracted = extract_first_json_object(&text)
@@ -109,23 +169,72 @@ pub async fn ai_chat_with_model(
         }
         return Ok(AiChatResult {
             output: msg,
-            edits: parsed.edits,
+            edits: trusted_edits(parsed.edits, &s),
+            reasoning: completion.reasoning,
         });
     }
 
     Ok(AiChatResult {
         output: text,
         edits: None,
+        reasoning: completion.reasoning,
     })
 }
 
+pub async fn ai_chat_compare(
+    messages: Vec<ChatMessage>,
+    models: Vec<String>,
+    encryption_password: Option<&str>,
+    thinking: Option<&str>,
+) -> Result<Vec<AiCompareEntry>> {
+    let mut seen: Vec<String> = vec![];
+    for m in models {
+        let t = m.trim();
+        if !t.is_empty() && !seen.iter().any(|x| x == t) {
+            seen.push(t.to_string());
+        }
+    }
+    if seen.len() < 2 {
+        return Err(anyhow!("compare needs at least 2 distinct models"));
+    }
+    if seen.len() > 3 {
+        return Err(anyhow!("compare supports at most 3 models"));
+    }
+
+    let runs = seen.into_iter().map(|model| {
+        let messages = messages.clone();
+        async move {
+            let started = std::time::Instant::now();
+            let res = ai_chat_with_model(messages, encryption_password, Some(&model), thinking, RequestPriority::Interactive).await;
+            let elapsed_ms = started.elapsed().as_millis() as u64;
+            match res {
+                Ok(result) => AiCompareEntry {
+                    model,
+                    result: Some(result),
+                    error: None,
+                    elapsed_ms,
+                },
+                Err(e) => AiCompareEntry {
+                    model,
+                    result: None,
+                    error: Some(e.to_string()),
+                    elapsed_ms,
+                },
+            }
+        }
+    });
+
+    Ok(futures_util::future::join_all(runs).await)
+}
+
 pub async fn openrouter_list_models() -> Result<Vec<OpenRouterModelInfo>> {
-    let client = reqwest::Client::new();
+    let client = http::client()?;
     let url = "https://openrouter.ai/api/v1/models";
     let response = client
         .get(url)
         .send()
         .await
+        .map_err(|e| anyhow!(redact_error(e)))
         .with_context(|| format!("OpenRouter models request failed to: {url}"))?;
 
     let status = response.status();
@@ -135,11 +244,14 @@ pub async fn openrouter_list_models() -> Result<Vec<OpenRouterModelInfo>> {
         .with_context(|| "Failed to read OpenRouter models response")?;
 
     if !status.is_success() {
-        return Err(anyhow!("OpenRouter models request failed (status {status}): {body}"));
+        return Err(anyhow!(
+            "OpenRouter models request failed (status {status}): {}",
+            shorten_for_error(&body)
+        ));
     }
 
     let parsed: OpenRouterModelsResponse = serde_json::from_str(&body)
-        .with_context(|| format!("Invalid OpenRouter models JSON response: {body}"))?;
+        .with_context(|| format!("Invalid OpenRouter models JSON response: {}", shorten_for_error(&body)))?;
     Ok(parsed.data)
 }
 
@@ -167,6 +279,29 @@ pub struct AiChatResult {
     pub output: String,
     #[serde(default)]
     pub edits: Option<Vec<AiEditOp>>,
+    #[serde(default)]
+    pub reasoning: Option<String>,
+}
+
+/// Raw provider reply: the visible answer plus any separately returned reasoning/thinking text.
+struct ChatCompletion {
+    text: String,
+    reasoning: Option<String>,
+}
+
+impl ChatCompletion {
+    fn plain(text: String) -> Self {
+        let (text, reasoning) = split_think_tags(&text);
+        Self { text, reasoning }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AiCompareEntry {
+    pub model: String,
+    pub result: Option<AiChatResult>,
+    pub error: Option<String>,
+    pub elapsed_ms: u64,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -198,6 +333,16 @@ struct StructuredChatOut {
     summary: Option<String>,
 }
 
+// Commands from the terminal history included when explaining terminal output.
+const RECENT_COMMANDS: usize = 10;
+// Staged diff sent when writing a commit message; bigger diffs are cut off.
+const COMMIT_DIFF_BYTES: usize = 60_000;
+// Tries at a commit message that passes the commit rules before the last one is returned as is.
+const COMMIT_MESSAGE_ATTEMPTS: usize = 3;
+
+const DEEPSEEK_REASONER_MODEL: &str = "deepseek-reasoner";
+const DEEPSEEK_BETA_BASE_URL: &str = "https://api.deepseek.com/beta";
+
 fn get_provider_info(provider: &str) -> Result<(String, String, bool)> {
     match provider {
         "openai" => Ok(("https://api.openai.com/v1".to_string(), "gpt-4o-mini".to_string(), true)),
@@ -205,7 +350,7 @@ fn get_provider_info(provider: &str) -> Result<(String, String, bool)> {
         "groq" => Ok(("https://api.groq.com/openai/v1".to_string(), "llama-3.1-70b-versatile".to_string(), true)),
         "deepseek" => Ok(("https://api.deepseek.com/v1".to_string(), "deepseek-chat".to_string(), true)),
         "gemini" => Ok(("https://generativelanguage.googleapis.com/v1beta".to_string(), "gemini-flash-latest".to_string(), true)),
-        "pompora" => Ok(("https://ai.pompora.dev/v1".to_string(), "pompora".to_string(), true)),
+        "pompora" => Ok((auth::ai_base_url()?, "pompora".to_string(), true)),
         "ollama" => Ok(("http://127.0.0.1:11434/v1".to_string(), "llama3.2".to_string(), false)),
         "lmstudio" => Ok(("http://127.0.0.1:1234/v1".to_string(), "local-model".to_string(), false)),
         "custom" => Ok(("https://api.openai.com/v1".to_string(), "gpt-4o-mini".to_string(), true)),
@@ -275,8 +420,10 @@ fn extract_first_json_object(s: &str) -> Option<String> {
     None
 }
 
+/// Trims and redacts a response body for inclusion in an error message.
 fn shorten_for_error(s: &str) -> String {
-    let t = s.trim();
+    let redacted = redact(s);
+    let t = redacted.trim();
     if t.is_empty() {
         return "<empty response body>".to_string();
     }
@@ -310,6 +457,9 @@ fn extract_openai_message_content(message: &serde_json::Value) -> Option<String>
                 continue;
             }
             if let Some(obj) = part.as_object() {
+                if is_reasoning_part(part) {
+                    continue;
+                }
                 if let Some(text) = obj.get("text").and_then(|t| t.as_str()) {
                     let t = text.trim();
                     if !t.is_empty() {
@@ -327,24 +477,122 @@ fn extract_openai_message_content(message: &serde_json::Value) -> Option<String>
     None
 }
 
+fn is_reasoning_part(part: &serde_json::Value) -> bool {
+    matches!(
+        part.get("type").and_then(|t| t.as_str()),
+        Some("thinking") | Some("reasoning") | Some("redacted_thinking")
+    )
+}
+
+fn extract_openai_message_reasoning(message: &serde_json::Value) -> Option<String> {
+    // DeepSeek uses `reasoning_content`, OpenRouter normalizes to `reasoning`.
+    for key in ["reasoning_content", "reasoning"] {
+        if let Some(s) = message.get(key).and_then(|v| v.as_str()) {
+            let t = s.trim();
+            if !t.is_empty() {
+                return Some(t.to_string());
+            }
+        }
+    }
+
+    // Claude extended thinking: [{"type":"thinking","thinking":"..."}, {"type":"text","text":"..."}]
+    let parts = message.get("content").and_then(|c| c.as_array())?;
+    let mut out: Vec<String> = vec![];
+    for part in parts {
+        if !is_reasoning_part(part) {
+            continue;
+        }
+        let text = part
+            .get("thinking")
+            .or_else(|| part.get("text"))
+            .and_then(|t| t.as_str())
+            .map(|t| t.trim())
+            .unwrap_or("");
+        if !text.is_empty() {
+            out.push(text.to_string());
+        }
+    }
+    if out.is_empty() {
+        return None;
+    }
+    Some(out.join("\n\n"))
+}
+
+/// Models like DeepSeek-R1 served through Ollama/Groq inline their reasoning as a leading
+/// `<think>...</think>` block. Split it off so it doesn't leak into the answer.
+fn split_think_tags(text: &str) -> (String, Option<String>) {
+    let t = text.trim_start();
+    let Some(rest) = t.strip_prefix("<think>") else {
+        return (text.to_string(), None);
+    };
+    let Some(end) = rest.find("</think>") else {
+        return (text.to_string(), None);
+    };
+    let reasoning = rest[..end].trim();
+    let answer = rest[end + "</think>".len()..].trim().to_string();
+    if reasoning.is_empty() {
+        return (answer, None);
+    }
+    (answer, Some(reasoning.to_string()))
+}
+
+// Every AI request goes through here; it is timed, and how it failed noted, for telemetry.
 async fn request_chat_completion(
+    provider: &str,
+    encryption_password: Option<&str>,
+    messages: Vec<ChatMessage>,
+    temperature: f32,
+    model_override: Option<&str>,
+    thinking: Option<&str>,
+    priority: RequestPriority,
+) -> Result<ChatCompletion> {
+    let started = std::time::Instant::now();
+    let res = send_chat_completion(provider, encryption_password, messages, temperature, model_override, thinking, priority).await;
+    telemetry::record_latency(&format!("ai:{provider}"), started.elapsed());
+    if let Err(e) = &res {
+        telemetry::record_error(&format!("ai:{}", telemetry::error_category(&e.to_string())));
+    }
+    res
+}
+
+async fn send_chat_completion(
     provider: &str,
     _encryption_password: Option<&str>,
     messages: Vec<ChatMessage>,
     temperature: f32,
     model_override: Option<&str>,
     thinking: Option<&str>,
-) -> Result<String> {
-    let (base_url, mut model, needs_auth) = get_provider_info(provider)?;
+    priority: RequestPriority,
+) -> Result<ChatCompletion> {
+    let (mut base_url, mut model, needs_auth) = get_provider_info(provider)?;
+    let mut model_overridden = false;
     if let Some(m) = model_override {
         let t = m.trim();
         if !t.is_empty() {
             model = t.to_string();
+            model_overridden = true;
         }
     }
-    
+
+    // DeepSeek: "reasoning" thinking selects the R1 reasoner, and a trailing assistant message
+    // switches to beta prefix completion (the model continues that message).
+    let mut deepseek_prefix: Option<String> = None;
+    if provider == "deepseek" {
+        if !model_overridden && thinking.map(|t| t.trim()) == Some("reasoning") {
+            model = DEEPSEEK_REASONER_MODEL.to_string();
+        }
+        if is_prefix_completion(provider, &messages) {
+            deepseek_prefix = messages.last().map(|m| m.content.clone());
+            base_url = DEEPSEEK_BETA_BASE_URL.to_string();
+        }
+    }
+
     let api_key = if needs_auth {
-        match secrets::provider_key_get(provider, _encryption_password) {
+        // A credential pinned by the open workspace takes precedence over the provider's active one.
+        let credential = settings::load()
+            .ok()
+            .and_then(|s| secrets::workspace_credential(&s, provider));
+        match secrets::provider_key_get(provider, _encryption_password, credential.as_deref(), "ai_request") {
             Ok(key) => key,
             Err(e) => return Err(anyhow!("Failed to get API key: {}", e)),
         }
@@ -352,7 +600,10 @@ async fn request_chat_completion(
         String::new()
     };
 
-    let client = reqwest::Client::new();
+    // Held until the response is fully read, so queued requests start as soon as a slot frees.
+    let _permit = ai_queue::acquire(priority).await;
+
+    let client = http::client()?;
 
     if provider == "pompora" {
         let url = format!("{}/ai", base_url.trim_end_matches('/'));
@@ -377,6 +628,7 @@ async fn request_chat_completion(
         let response = request
             .send()
             .await
+            .map_err(|e| anyhow!(redact_error(e)))
             .with_context(|| format!("Pompora AI request failed to: {url}"))?;
 
         let status = response.status();
@@ -392,7 +644,7 @@ async fn request_chat_completion(
                     if let Some(raw) = response_json.get("raw").and_then(|v| v.as_str()) {
                         let t = raw.trim();
                         if !t.is_empty() {
-                            return Ok(t.to_string());
+                            return Ok(ChatCompletion::plain(t.to_string()));
                         }
                     }
                 }
@@ -413,7 +665,7 @@ async fn request_chat_completion(
         }
 
         if let Some(out) = extract_pompora_output(&response_json) {
-            return Ok(out);
+            return Ok(ChatCompletion::plain(out));
         }
 
         return Err(anyhow!(
@@ -424,7 +676,8 @@ async fn request_chat_completion(
 
     let response_text = if provider == "gemini" {
         // Gemini uses different API format
-        let url = format!("{}/models/{}:generateContent?key={}", base_url, model, api_key);
+        // The key goes in a header rather than `?key=` so it never ends up in a URL we report.
+        let url = format!("{}/models/{}:generateContent", base_url, model);
         
         let gemini_messages: Vec<serde_json::Value> = messages.iter().map(|msg| {
             json!({
@@ -443,9 +696,11 @@ async fn request_chat_completion(
 
         let response = client
             .post(&url)
+            .header("x-goog-api-key", api_key.trim())
             .json(&request_body)
             .send()
             .await
+            .map_err(|e| anyhow!(redact_error(e)))
             .with_context(|| format!("Gemini API request failed to: {url}"))?;
 
         let status = response.status();
@@ -456,20 +711,41 @@ async fn request_chat_completion(
 
         if !status.is_success() {
             return Err(anyhow!(
-                "Gemini API request failed (status {status}): {url}\n{body}"
+                "Gemini API request failed (status {status}): {url}\n{}",
+                shorten_for_error(&body)
             ));
         }
 
         body
     } else {
         // OpenAI-compatible format
-        let request_body = json!({
+        let mut request_body = json!({
             "model": model,
             "messages": messages,
             "temperature": temperature,
             "max_tokens": 4096
         });
 
+        if provider == "deepseek" {
+            if model == DEEPSEEK_REASONER_MODEL {
+                // The reasoner ignores sampling params and its chain of thought counts
+                // against max_tokens.
+                if let Some(obj) = request_body.as_object_mut() {
+                    obj.remove("temperature");
+                    obj.insert("max_tokens".to_string(), json!(16384));
+                }
+            }
+            if deepseek_prefix.is_some() {
+                if let Some(last) = request_body
+                    .get_mut("messages")
+                    .and_then(|m| m.as_array_mut())
+                    .and_then(|a| a.last_mut())
+                {
+                    last["prefix"] = json!(true);
+                }
+            }
+        }
+
         let url = format!("{}/chat/completions", base_url.trim_end_matches('/'));
         
         let mut request = client.post(&url).json(&request_body);
@@ -488,6 +764,7 @@ async fn request_chat_completion(
         let response = request
             .send()
             .await
+            .map_err(|e| anyhow!(redact_error(e)))
             .with_context(|| format!("API request failed to: {url}"))?;
 
         let status = response.status();
@@ -497,7 +774,10 @@ async fn request_chat_completion(
             .with_context(|| "Failed to read response text")?;
 
         if !status.is_success() {
-            return Err(anyhow!("API request failed (status {status}): {url}\n{body}"));
+            return Err(anyhow!(
+                "API request failed (status {status}): {url}\n{}",
+                shorten_for_error(&body)
+            ));
         }
 
         body
@@ -506,17 +786,32 @@ async fn request_chat_completion(
     // Parse response based on provider
     if provider == "gemini" {
         let response_json: serde_json::Value = serde_json::from_str(&response_text)
-            .with_context(|| format!("Invalid Gemini JSON response: {response_text}"))?;
+            .with_context(|| format!("Invalid Gemini JSON response: {}", shorten_for_error(&response_text)))?;
 
         if let Some(candidates) = response_json.get("candidates").and_then(|c| c.as_array()) {
             if let Some(first_candidate) = candidates.first() {
                 if let Some(content) = first_candidate.get("content") {
                     if let Some(parts) = content.get("parts").and_then(|p| p.as_array()) {
-                        if let Some(first_part) = parts.first() {
-                            if let Some(text) = first_part.get("text").and_then(|t| t.as_str()) {
-                                return Ok(text.to_string());
+                        // Thinking models mark their thought summaries with `"thought": true`.
+                        let mut text_parts: Vec<&str> = vec![];
+                        let mut thought_parts: Vec<&str> = vec![];
+                        for part in parts {
+                            let Some(text) = part.get("text").and_then(|t| t.as_str()) else {
+                                continue;
+                            };
+                            if part.get("thought").and_then(|t| t.as_bool()).unwrap_or(false) {
+                                thought_parts.push(text.trim());
+                            } else {
+                                text_parts.push(text);
                             }
                         }
+                        if !text_parts.is_empty() {
+                            let reasoning = thought_parts.join("\n\n");
+                            return Ok(ChatCompletion {
+                                text: text_parts.join(""),
+                                reasoning: Some(reasoning).filter(|r| !r.trim().is_empty()),
+                            });
+                        }
                     }
                 }
             }
@@ -527,89 +822,122 @@ async fn request_chat_completion(
             shorten_for_error(&response_text)
         ))
     } else {
-        // OpenAI-compatible response parsing
-        let response_json: serde_json::Value = serde_json::from_str(&response_text)
-            .with_context(|| format!("Invalid JSON response: {response_text}"))?;
+        match deepseek_prefix {
+            Some(prefix) => parse_prefix_continuation(&response_text, &prefix),
+            None => parse_openai_completion(&response_text),
+        }
+    }
+}
 
-        if let Some(choices) = response_json.get("choices").and_then(|c| c.as_array()) {
-            if let Some(first_choice) = choices.first() {
-                if let Some(message) = first_choice.get("message") {
-                    if let Some(content) = extract_openai_message_content(message) {
-                        return Ok(content);
-                    }
+// Prefix completion only returns the continuation; callers get the whole message. The
+// continuation is taken untrimmed, since the spaces and line breaks where it meets the prefix
+// are part of the text, and only the joined message is trimmed at its end.
+fn parse_prefix_continuation(response_text: &str, prefix: &str) -> Result<ChatCompletion> {
+    let response_json: serde_json::Value = serde_json::from_str(response_text)
+        .with_context(|| format!("Invalid JSON response: {}", shorten_for_error(response_text)))?;
+    let message = response_json
+        .get("choices")
+        .and_then(|c| c.get(0))
+        .and_then(|c| c.get("message"))
+        .ok_or_else(|| anyhow!("No content found in API response: {}", shorten_for_error(response_text)))?;
+    let continuation = message.get("content").and_then(|c| c.as_str()).unwrap_or_default();
+    Ok(ChatCompletion {
+        text: format!("{prefix}{continuation}").trim_end().to_string(),
+        reasoning: extract_openai_message_reasoning(message),
+    })
+}
+
+fn parse_openai_completion(response_text: &str) -> Result<ChatCompletion> {
+    let response_json: serde_json::Value = serde_json::from_str(response_text)
+        .with_context(|| format!("Invalid JSON response: {}", shorten_for_error(response_text)))?;
 
-                    // Some providers/models return tool calls with empty content.
-                    // In that case, the structured JSON is often inside tool_calls[].function.arguments.
-                    if let Some(tool_calls) = message.get("tool_calls").and_then(|t| t.as_array()) {
-                        for tc in tool_calls {
-                            if let Some(args) = tc
-                                .get("function")
-                                .and_then(|f| f.get("arguments"))
-                                .and_then(|a| a.as_str())
-                            {
-                                if !args.trim().is_empty() {
-                                    return Ok(args.to_string());
-                                }
+    if let Some(choices) = response_json.get("choices").and_then(|c| c.as_array()) {
+        if let Some(first_choice) = choices.first() {
+            if let Some(message) = first_choice.get("message") {
+                if let Some(content) = extract_openai_message_content(message) {
+                    let (text, inline_reasoning) = split_think_tags(&content);
+                    return Ok(ChatCompletion {
+                        text,
+                        reasoning: extract_openai_message_reasoning(message).or(inline_reasoning),
+                    });
+                }
+
+                // Some providers/models return tool calls with empty content.
+                // In that case, the structured JSON is often inside tool_calls[].function.arguments.
+                if let Some(tool_calls) = message.get("tool_calls").and_then(|t| t.as_array()) {
+                    for tc in tool_calls {
+                        if let Some(args) = tc
+                            .get("function")
+                            .and_then(|f| f.get("arguments"))
+                            .and_then(|a| a.as_str())
+                        {
+                            if !args.trim().is_empty() {
+                                return Ok(ChatCompletion::plain(args.to_string()));
                             }
                         }
                     }
+                }
 
-                    // Legacy function_call shape.
-                    if let Some(args) = message
-                        .get("function_call")
-                        .and_then(|fc| fc.get("arguments"))
-                        .and_then(|a| a.as_str())
-                    {
-                        if !args.trim().is_empty() {
-                            return Ok(args.to_string());
-                        }
+                // Legacy function_call shape.
+                if let Some(args) = message
+                    .get("function_call")
+                    .and_then(|fc| fc.get("arguments"))
+                    .and_then(|a| a.as_str())
+                {
+                    if !args.trim().is_empty() {
+                        return Ok(ChatCompletion::plain(args.to_string()));
                     }
                 }
+            }
 
-                // Some providers still return completion-style responses.
-                if let Some(text) = first_choice.get("text").and_then(|t| t.as_str()) {
-                    if !text.trim().is_empty() {
-                        return Ok(text.to_string());
-                    }
+            // Some providers still return completion-style responses.
+            if let Some(text) = first_choice.get("text").and_then(|t| t.as_str()) {
+                if !text.trim().is_empty() {
+                    return Ok(ChatCompletion::plain(text.to_string()));
                 }
             }
         }
-
-        Err(anyhow!(
-            "No content found in API response: {}",
-            shorten_for_error(&response_text)
-        ))
     }
+
+    Err(anyhow!(
+        "No content found in API response: {}",
+        shorten_for_error(response_text)
+    ))
 }
 
 pub async fn ai_chat(
     messages: Vec<ChatMessage>,
     encryption_password: Option<&str>,
     thinking: Option<&str>,
+    priority: RequestPriority,
 ) -> Result<AiChatResult> {
     let s = settings::load()?;
-    #[cfg(debug_assertions)]
-    println!("DEBUG: ai_chat loaded settings - offline_mode: {}, active_provider: {:?}", s.offline_mode, s.active_provider);
+    tracing::debug!("ai_chat: offline_mode={}, active_provider={:?}", s.offline_mode, s.active_provider);
     
     if s.offline_mode {
         return Err(anyhow!("offline mode is enabled"));
     }
 
-    let provider = s
-        .active_provider
-        .as_deref()
-        .map(|v| v.trim())
-        .filter(|v| !v.is_empty())
-        .ok_or_else(|| anyhow!("no provider is configured"))?;
+    let provider = active_provider(&s)?;
 
+    let prefix_mode = is_prefix_completion(&provider, &messages);
+    let system = if prefix_mode { PREFIX_SYSTEM_PROMPT } else { CHAT_SYSTEM_PROMPT };
     let mut msgs: Vec<ChatMessage> = vec![];
     msgs.push(ChatMessage {
         role: "system".to_string(),
-        content: "You are a coding assistant inside an editor. Be direct and helpful. IMPORTANT: Respond ONLY with a single valid JSON object (no markdown, no code fences). Schema: {\"assistant_message\": string, \"edits\": [{\"op\": \"write\"|\"patch\"|\"delete\"|\"rename\"|\"run\", \"path\"?: string, \"content\"?: string, \"from\"?: string, \"to\"?: string}], \"summary\"?: string }. Never put code in assistant_message; code must only appear inside edits[].content. If you have no edits, return {\"assistant_message\": <answer>, \"edits\": []}.".to_string(),
+        content: with_workspace_instructions(system, &s),
     });
     msgs.extend(messages);
 
-    let text = request_chat_completion(provider, encryption_password, msgs, 0.4, None, thinking).await?;
+    let completion = request_chat_completion(&provider, encryption_password, msgs, 0.4, None, thinking, priority).await?;
+    let text = completion.text;
+    if prefix_mode {
+        return Ok(AiChatResult {
+            output: text,
+            edits: None,
+            reasoning: completion.reasoning,
+        });
+    }
 
     let direct = serde_json::from_str::<StructuredChatOut>(&text).ok();
     let extracted = extract_first_json_object(&text)
@@ -630,16 +958,139 @@ pub async fn ai_chat(
         }
         return Ok(AiChatResult {
             output: msg,
-            edits: parsed.edits,
+            edits: trusted_edits(parsed.edits, &s),
+            reasoning: completion.reasoning,
         });
     }
 
     Ok(AiChatResult {
         output: text,
         edits: None,
+        reasoning: completion.reasoning,
     })
 }
 
+pub async fn ai_explain_terminal(
+    id: &str,
+    last_n_lines: usize,
+    encryption_password: Option<&str>,
+    thinking: Option<&str>,
+) -> Result<AiChatResult> {
+    let output = terminal::terminal_recent_output(id, last_n_lines).map_err(|e| anyhow!(e))?;
+    if output.trim().is_empty() {
+        return Err(anyhow!("terminal has no recent output"));
+    }
+
+    // What the user ran lately, oldest first, so the output can be read in context.
+    let recent = terminal_history::terminal_history("", RECENT_COMMANDS).unwrap_or_default();
+    let history = recent
+        .iter()
+        .rev()
+        .map(|e| match e.exit_code {
+            Some(code) => format!("$ {} (exit {code})", redact(&e.command)),
+            None => format!("$ {}", redact(&e.command)),
+        })
+        .collect::<Vec<_>>()
+        .join("\n");
+    let history = if history.is_empty() {
+        String::new()
+    } else {
+        format!("\n\nCommands recently run in this workspace:\n{history}")
+    };
+
+    let user = ChatMessage {
+        role: "user".to_string(),
+        content: format!(
+            "Explain the following terminal output. If it shows an error, identify the likely cause and propose fixes as edits: use op \"write\" or \"patch\" for file changes and op \"run\" (command in content) for shell commands to try.\n\nTerminal output (last {last_n_lines} lines):\n{output}{history}"
+        ),
+    };
+
+    ai_chat(vec![user], encryption_password, thinking, RequestPriority::Interactive).await
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct AiCommitMessage {
+    pub message: String,
+    /// How `message` fares against the commit rules; only invalid when every attempt was.
+    pub validation: CommitValidation,
+}
+
+// The model's reply without the code fence or quotes some models wrap a message in.
+fn bare_commit_message(raw: &str) -> String {
+    let text = raw.trim();
+    let text = match text.strip_prefix("```") {
+        Some(rest) => rest.split_once('\n').map(|(_, body)| body).unwrap_or_default().trim_end().trim_end_matches("```"),
+        None => text,
+    };
+    text.trim().trim_matches('"').trim().to_string()
+}
+
+/// Writes a Conventional Commits message for the staged changes of the repository holding
+/// `root`, checked with the commit rules (the repository's commitlint config included). A
+/// message that breaks them is sent back with the problems to be fixed.
+pub async fn ai_commit_message(
+    root: Option<&str>,
+    encryption_password: Option<&str>,
+    thinking: Option<&str>,
+) -> Result<AiCommitMessage> {
+    let s = settings::load()?;
+    if s.offline_mode {
+        return Err(anyhow!("offline mode is enabled"));
+    }
+    let provider = active_provider(&s)?;
+    let diff = git::staged_diff(root, COMMIT_DIFF_BYTES)?;
+    if diff.trim().is_empty() {
+        return Err(anyhow!("nothing is staged to write a commit message for"));
+    }
+    let dir = git::repo_workdir(root)?;
+    let config_note = commit_lint::config_in(&dir)
+        .map(|c| format!(" The repository's {c} adds its own commitlint rules, which are checked too."))
+        .unwrap_or_default();
+
+    let sys = ChatMessage {
+        role: "system".to_string(),
+        content: with_workspace_instructions(
+            "You write git commit messages following Conventional Commits. Reply with the commit message only: no code fences, quotes or commentary.",
+            &s,
+        ),
+    };
+    let mut messages = vec![
+        sys,
+        ChatMessage {
+            role: "user".to_string(),
+            content: format!(
+                "Write a commit message for these staged changes. Header: `type(optional scope): subject`, with type one of feat, fix, docs, style, refactor, perf, test, build, ci, chore or revert, a lower-case imperative subject without a trailing period, at most 72 characters in all. Add `!` after the type for breaking changes. Then a blank line and a short body, wrapped at 72 columns, saying what changed and why.{config_note}\n\n{}",
+                redact(&diff)
+            ),
+        },
+    ];
+
+    let mut last = None;
+    for _ in 0..COMMIT_MESSAGE_ATTEMPTS {
+        let raw = request_chat_completion(&provider, encryption_password, messages.clone(), 0.2, None, thinking, RequestPriority::Interactive)
+            .await?
+            .text;
+        let message = bare_commit_message(&raw);
+        let validation = commit_lint::validate_in(&dir, &message);
+        if validation.valid {
+            return Ok(AiCommitMessage { message, validation });
+        }
+        messages.push(ChatMessage {
+            role: "assistant".to_string(),
+            content: message.clone(),
+        });
+        messages.push(ChatMessage {
+            role: "user".to_string(),
+            content: format!(
+                "That message breaks these commit rules:\n{}\n\nReply with the corrected commit message only.",
+                commit_lint::describe_problems(&validation)
+            ),
+        });
+        last = Some(AiCommitMessage { message, validation });
+    }
+    last.ok_or_else(|| anyhow!("no commit message was written"))
+}
+
 pub async fn ai_run_action(
     action: &str,
     rel_path: Option<&str>,
@@ -647,22 +1098,21 @@ pub async fn ai_run_action(
     selection: Option<&str>,
     encryption_password: Option<&str>,
     thinking: Option<&str>,
+    priority: RequestPriority,
 ) -> Result<AiRunResult> {
     let s = settings::load()?;
     if s.offline_mode {
         return Err(anyhow!("offline mode is enabled"));
     }
 
-    let provider = s
-        .active_provider
-        .as_deref()
-        .map(|v| v.trim())
-        .filter(|v| !v.is_empty())
-        .ok_or_else(|| anyhow!("no provider is configured"))?;
+    let provider = active_provider(&s)?;
 
     let sys = ChatMessage {
         role: "system".to_string(),
-        content: "You are a precise coding assistant inside an editor. Follow the user instructions exactly.".to_string(),
+        content: with_workspace_instructions(
+            "You are a precise coding assistant inside an editor. Follow the user instructions exactly.",
+            &s,
+        ),
     };
 
     let path_line = rel_path.map(|p| format!("File: {p}\n")).unwrap_or_default();
@@ -734,7 +1184,9 @@ pub async fn ai_run_action(
         content: user_content,
     };
 
-    let raw = request_chat_completion(provider, encryption_password, vec![sys, user], 0.2, None, thinking).await?;
+    let raw = request_chat_completion(&provider, encryption_password, vec![sys, user], 0.2, None, thinking, priority)
+        .await?
+        .text;
 
     if action == "fix" || action == "refactor" {
         let direct = serde_json::from_str::<StructuredOut>(&raw).ok();
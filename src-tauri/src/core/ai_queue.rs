@@ -0,0 +1,123 @@
+use once_cell::sync::Lazy;
+use serde::{Deserialize, Serialize};
+use std::sync::Mutex;
+use tokio::sync::Notify;
+
+// Total in-flight provider requests across the whole app.
+const MAX_CONCURRENT: usize = 4;
+// Background work (inline completion, agent sub-steps) never takes the last slots, so the
+// user's active chat always has room.
+const MAX_BACKGROUND: usize = 2;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum RequestPriority {
+    Interactive,
+    Background,
+}
+
+impl RequestPriority {
+    pub fn parse(v: Option<&str>) -> Self {
+        match v.map(|s| s.trim().to_lowercase()).as_deref() {
+            Some("background") => RequestPriority::Background,
+            _ => RequestPriority::Interactive,
+        }
+    }
+}
+
+#[derive(Default)]
+struct QueueState {
+    active: usize,
+    active_background: usize,
+    waiting_interactive: usize,
+}
+
+struct RequestQueue {
+    state: Mutex<QueueState>,
+    notify: Notify,
+}
+
+static QUEUE: Lazy<RequestQueue> = Lazy::new(|| RequestQueue {
+    state: Mutex::new(QueueState::default()),
+    notify: Notify::new(),
+});
+
+/// Held for the duration of a provider request; frees the slot on drop.
+pub struct QueuePermit {
+    priority: RequestPriority,
+}
+
+impl Drop for QueuePermit {
+    fn drop(&mut self) {
+        if let Ok(mut st) = QUEUE.state.lock() {
+            st.active = st.active.saturating_sub(1);
+            if self.priority == RequestPriority::Background {
+                st.active_background = st.active_background.saturating_sub(1);
+            }
+        }
+        QUEUE.notify.notify_waiters();
+    }
+}
+
+fn try_take(st: &mut QueueState, priority: RequestPriority) -> bool {
+    if st.active >= MAX_CONCURRENT {
+        return false;
+    }
+    if priority == RequestPriority::Background
+        && (st.waiting_interactive > 0 || st.active_background >= MAX_BACKGROUND)
+    {
+        return false;
+    }
+    st.active += 1;
+    if priority == RequestPriority::Background {
+        st.active_background += 1;
+    }
+    true
+}
+
+// Counts an interactive request as queued; dropping it (acquired or the caller went away)
+// lets background work proceed again.
+struct InteractiveWaiter;
+
+impl InteractiveWaiter {
+    fn register(st: &mut QueueState) -> Self {
+        st.waiting_interactive += 1;
+        InteractiveWaiter
+    }
+}
+
+impl Drop for InteractiveWaiter {
+    fn drop(&mut self) {
+        if let Ok(mut st) = QUEUE.state.lock() {
+            st.waiting_interactive = st.waiting_interactive.saturating_sub(1);
+        }
+        QUEUE.notify.notify_waiters();
+    }
+}
+
+pub async fn acquire(priority: RequestPriority) -> QueuePermit {
+    let mut waiter: Option<InteractiveWaiter> = None;
+    loop {
+        let notified = QUEUE.notify.notified();
+        tokio::pin!(notified);
+        // Register interest before checking state so a release between the check and the
+        // await can't be missed.
+        notified.as_mut().enable();
+
+        let acquired = {
+            let mut st = QUEUE.state.lock().unwrap_or_else(|e| e.into_inner());
+            let ok = try_take(&mut st, priority);
+            if !ok && priority == RequestPriority::Interactive && waiter.is_none() {
+                waiter = Some(InteractiveWaiter::register(&mut st));
+            }
+            ok
+        };
+
+        if acquired {
+            drop(waiter);
+            return QueuePermit { priority };
+        }
+
+        notified.await;
+    }
+}
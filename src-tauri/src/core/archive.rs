@@ -0,0 +1,177 @@
+use anyhow::{anyhow, Context, Result};
+use ignore::overrides::OverrideBuilder;
+use ignore::WalkBuilder;
+use serde::Serialize;
+use std::fs;
+use std::io::{self, BufWriter};
+use std::path::PathBuf;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+use tauri::{AppHandle, Emitter};
+use zip::write::SimpleFileOptions;
+use zip::{CompressionMethod, ZipWriter};
+
+use super::workspace::{self, OpenRoot};
+
+pub const EXPORT_PROGRESS_EVENT: &str = "workspace:export-progress";
+
+// Progress is reported at most this often; every file would flood the webview on big trees.
+const PROGRESS_INTERVAL: Duration = Duration::from_millis(100);
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ExportProgress {
+    pub files_done: usize,
+    pub files_total: usize,
+    pub bytes_done: u64,
+    pub bytes_total: u64,
+    /// Workspace path of the file being added.
+    pub current: String,
+}
+
+struct Entry {
+    abs: PathBuf,
+    /// Name inside the archive: the file's workspace path.
+    name: String,
+    size: u64,
+}
+
+/// Files to archive from one root: `.gitignore`/`.ignore` rules apply, hidden files are kept
+/// (except `.git`), and the globs narrow things further.
+fn collect(root: &OpenRoot, include: &[String], exclude: &[String], skip: Option<&PathBuf>) -> Result<Vec<Entry>> {
+    let mut overrides = OverrideBuilder::new(&root.path);
+    for g in include.iter().map(|g| g.trim()).filter(|g| !g.is_empty()) {
+        overrides.add(g).with_context(|| format!("invalid include glob: {g}"))?;
+    }
+    for g in exclude.iter().map(|g| g.trim()).filter(|g| !g.is_empty()) {
+        overrides.add(&format!("!{g}")).with_context(|| format!("invalid exclude glob: {g}"))?;
+    }
+    overrides.add("!.git/").context("build ignore rules")?;
+
+    let walker = WalkBuilder::new(&root.path)
+        .hidden(false)
+        .git_ignore(true)
+        .git_global(false)
+        .git_exclude(true)
+        .require_git(false)
+        .overrides(overrides.build().context("build ignore rules")?)
+        .build();
+
+    let mut out = vec![];
+    for entry in walker {
+        let entry = entry.context("walk workspace")?;
+        if !entry.file_type().is_some_and(|t| t.is_file()) {
+            continue;
+        }
+        let abs = entry.path().to_path_buf();
+        if skip.is_some_and(|s| s == &abs) {
+            continue;
+        }
+        let Ok(rel) = abs.strip_prefix(&root.path) else {
+            continue;
+        };
+        let rel = rel.to_string_lossy().replace('\\', "/");
+        out.push(Entry {
+            size: entry.metadata().map(|m| m.len()).unwrap_or(0),
+            name: root.workspace_path(&rel),
+            abs,
+        });
+    }
+    Ok(out)
+}
+
+fn default_file_name(roots: &[OpenRoot]) -> String {
+    let base = roots
+        .first()
+        .and_then(|r| r.path.file_name())
+        .map(|n| n.to_string_lossy().to_string())
+        .unwrap_or_else(|| "workspace".to_string());
+    let ts = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0);
+    format!("{base}-{ts}.zip")
+}
+
+/// Writes a zip of the workspace to `dest` (a file path, or a folder to put a timestamped
+/// archive in) and returns the archive path. Entries are named by workspace path.
+pub fn workspace_export_zip(
+    app: &AppHandle,
+    dest: &str,
+    include_globs: &[String],
+    exclude_globs: &[String],
+) -> Result<String> {
+    let roots = workspace::open_roots()?;
+    let dest = PathBuf::from(dest.trim());
+    if dest.as_os_str().is_empty() {
+        return Err(anyhow!("choose where to save the archive"));
+    }
+    let dest = if dest.is_dir() { dest.join(default_file_name(&roots)) } else { dest };
+    // Resolved up front so an archive saved inside the workspace doesn't include itself.
+    let dest_abs = dest
+        .parent()
+        .and_then(|p| p.canonicalize().ok())
+        .zip(dest.file_name())
+        .map(|(p, n)| p.join(n));
+
+    let mut entries = vec![];
+    for root in &roots {
+        let skip = dest_abs.as_ref().and_then(|d| {
+            let canon = root.path.canonicalize().ok()?;
+            d.strip_prefix(&canon).ok().map(|rel| root.path.join(rel))
+        });
+        entries.extend(collect(root, include_globs, exclude_globs, skip.as_ref())?);
+    }
+    if entries.is_empty() {
+        return Err(anyhow!("no files match; nothing to export"));
+    }
+
+    let files_total = entries.len();
+    let bytes_total: u64 = entries.iter().map(|e| e.size).sum();
+    // Written under a temporary name so a failed export never leaves a truncated archive.
+    let partial = dest.with_extension("zip.partial");
+    let file = fs::File::create(&partial).with_context(|| format!("create {}", partial.display()))?;
+    let result = (|| -> Result<()> {
+        let mut zip = ZipWriter::new(BufWriter::new(file));
+        let mut bytes_done = 0u64;
+        let mut last_emit: Option<Instant> = None;
+        for (i, e) in entries.iter().enumerate() {
+            if last_emit.is_none_or(|t| t.elapsed() >= PROGRESS_INTERVAL) {
+                last_emit = Some(Instant::now());
+                let _ = app.emit(
+                    EXPORT_PROGRESS_EVENT,
+                    ExportProgress {
+                        files_done: i,
+                        files_total,
+                        bytes_done,
+                        bytes_total,
+                        current: e.name.clone(),
+                    },
+                );
+            }
+            let opts = SimpleFileOptions::default()
+                .compression_method(CompressionMethod::Deflated)
+                .large_file(e.size >= u32::MAX as u64);
+            zip.start_file(e.name.as_str(), opts)
+                .with_context(|| format!("add {}", e.name))?;
+            let mut src = fs::File::open(&e.abs).with_context(|| format!("read {}", e.name))?;
+            bytes_done += io::copy(&mut src, &mut zip).with_context(|| format!("compress {}", e.name))?;
+        }
+        zip.finish().context("finish archive")?;
+        let _ = app.emit(
+            EXPORT_PROGRESS_EVENT,
+            ExportProgress {
+                files_done: files_total,
+                files_total,
+                bytes_done,
+                bytes_total,
+                current: String::new(),
+            },
+        );
+        Ok(())
+    })();
+    if let Err(e) = result {
+        let _ = fs::remove_file(&partial);
+        return Err(e);
+    }
+    fs::rename(&partial, &dest).with_context(|| format!("save {}", dest.display()))?;
+    Ok(dest.to_string_lossy().to_string())
+}
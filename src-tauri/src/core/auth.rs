@@ -1,19 +1,42 @@
 use anyhow::{anyhow, Context, Result};
+use base64::Engine as _;
 use rand::RngCore;
 use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
 use std::collections::HashMap;
 use std::fs;
 use std::io::{Read, Write};
-use std::net::{TcpListener, TcpStream};
+use std::net::{SocketAddr, TcpListener, TcpStream};
 use std::path::PathBuf;
-use std::sync::Mutex;
-use std::time::Duration;
-
-use once_cell::sync::Lazy;
-
-use super::secrets;
-
-#[derive(Debug, Clone, Serialize, Deserialize)]
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use once_cell::sync::{Lazy, OnceCell};
+use serde_json::json;
+use tauri::{AppHandle, Emitter};
+
+use super::redact::{redact, redact_error};
+use super::{http, private_fs, secrets, settings};
+
+// Backend used unless `backend_base_url` is set, and its AI API.
+const DEFAULT_BACKEND: &str = "https://pompora.dev";
+const DEFAULT_AI_BASE: &str = "https://ai.pompora.dev/v1";
+// How long `test_backend` waits for each answer.
+const BACKEND_TEST_TIMEOUT: Duration = Duration::from_secs(10);
+
+// Where the refresh token handed out at login is kept, next to the "pompora" API key.
+const REFRESH_TOKEN_SLOT: &str = "pompora-refresh";
+// A key is refreshed once it is this close to expiring.
+const REFRESH_MARGIN_MS: u64 = 5 * 60 * 1000;
+// How often `watch` looks at the stored key's expiry and the cached credits' age.
+const EXPIRY_CHECK_INTERVAL: Duration = Duration::from_secs(60);
+// Cached credits are fetched again in the background once they are this old.
+const CREDITS_REFRESH_MS: u64 = 5 * 60 * 1000;
+// Cached credits stand in for fetched ones for this long, e.g. while offline.
+const CREDITS_MAX_AGE_MS: u64 = 60 * 60 * 1000;
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct AuthProfile {
     pub user_id: String,
     pub email: String,
@@ -24,6 +47,19 @@ pub struct AuthProfile {
     pub first_name: String,
     #[serde(default)]
     pub last_name: String,
+    /// When the API key stops working, in ms since the Unix epoch; `None` if it doesn't expire.
+    #[serde(default)]
+    pub expires_at: Option<u64>,
+}
+
+/// Payload of `auth:expired`, sent once per key when it is about to expire or has expired and
+/// couldn't be refreshed, so the UI can ask the user to sign in again.
+#[derive(Debug, Clone, Serialize)]
+pub struct AuthExpiredEvent {
+    /// When the key expires or expired, in ms since the Unix epoch; `None` if the server turned
+    /// down a key that wasn't known to expire.
+    pub expires_at: Option<u64>,
+    pub reason: String,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -54,26 +90,141 @@ pub struct CreditsFast {
     pub period_day: Option<String>,
 }
 
+/// Payload of `credits:updated`, sent whenever credits have been fetched.
+#[derive(Debug, Clone, Serialize)]
+pub struct CreditsUpdatedEvent {
+    pub credits: CreditsResponse,
+    /// When they were fetched, in ms since the Unix epoch.
+    pub fetched_at: u64,
+}
+
+// Credits as last fetched, kept on disk for the account they belong to.
+#[derive(Serialize, Deserialize)]
+struct CachedCredits {
+    user_id: String,
+    fetched_at: u64,
+    credits: CreditsResponse,
+}
+
+// The login in progress; starting another one cancels it.
+/// How the backend answered `test_backend`.
+#[derive(Debug, Clone, Serialize)]
+pub struct BackendTest {
+    pub url: String,
+    pub ai_url: String,
+    /// HTTP status of the desktop API and of the AI API; any status means it answered.
+    pub status: u16,
+    pub ai_status: u16,
+    pub latency_ms: u64,
+}
+
+// The login in progress; starting another one cancels it.
 struct PendingLogin {
-    receiver: tokio::sync::oneshot::Receiver<AuthProfile>,
+    state: String,
+    // Taken by `wait_login`.
+    receiver: Option<tokio::sync::oneshot::Receiver<Result<AuthProfile, String>>>,
+    // Port of the callback listener, and whether its thread should give up.
+    port: u16,
+    canceled: Arc<AtomicBool>,
+}
+
+static PENDING: Lazy<Mutex<Option<PendingLogin>>> = Lazy::new(|| Mutex::new(None));
+
+static APP: OnceCell<AppHandle> = OnceCell::new();
+
+fn now_ms() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_millis() as u64)
+        .unwrap_or(0)
+}
+
+/// Base URL of the Pompora site and desktop API: `backend_base_url`, or pompora.dev.
+pub fn backend_url() -> Result<String> {
+    let s = settings::load()?;
+    match s.backend_base_url.as_deref().filter(|v| !v.trim().is_empty()) {
+        Some(url) => settings::normalize_backend_url(url),
+        None => Ok(DEFAULT_BACKEND.to_string()),
+    }
+}
+
+// The AI API of the backend at `backend`.
+fn ai_base_for(backend: &str) -> String {
+    if backend == DEFAULT_BACKEND {
+        DEFAULT_AI_BASE.to_string()
+    } else {
+        format!("{backend}/v1")
+    }
+}
+
+/// Base URL of the Pompora AI API (OpenAI-compatible).
+pub fn ai_base_url() -> Result<String> {
+    Ok(ai_base_for(&backend_url()?))
 }
 
-static PENDING: Lazy<Mutex<HashMap<String, PendingLogin>>> = Lazy::new(|| Mutex::new(HashMap::new()));
+fn emit_expired(expires_at: Option<u64>, reason: String) {
+    if let Some(app) = APP.get() {
+        let _ = app.emit("auth:expired", AuthExpiredEvent { expires_at, reason });
+    }
+}
 
 fn auth_path() -> Result<PathBuf> {
     let base = dirs::config_dir().context("missing config dir")?;
     Ok(base.join("Pompora").join("auth.json"))
 }
 
+// Sends `auth:login` when the signed-in account changes to `next`, `auth:profile_updated`
+// when the same account's profile does, and `auth:logout` when nobody is signed in any more.
+fn emit_profile_change(previous: Option<&AuthProfile>, next: Option<&AuthProfile>) {
+    let Some(app) = APP.get() else {
+        return;
+    };
+    let _ = match (previous, next) {
+        (Some(_), None) => app.emit("auth:logout", ()),
+        (Some(prev), Some(next)) if prev == next => Ok(()),
+        (Some(prev), Some(next)) if prev.user_id == next.user_id => app.emit("auth:profile_updated", next),
+        (_, Some(next)) => app.emit("auth:login", next),
+        (None, None) => Ok(()),
+    };
+}
+
 fn store_profile(p: &AuthProfile) -> Result<()> {
+    let previous = load_profile().ok().flatten();
     let path = auth_path()?;
     if let Some(parent) = path.parent() {
         fs::create_dir_all(parent).with_context(|| format!("create auth dir: {}", parent.display()))?;
     }
     let tmp = path.with_extension("json.tmp");
-    fs::write(&tmp, serde_json::to_string_pretty(p).context("serialize auth profile")?)
+    private_fs::write_private(&tmp, serde_json::to_string_pretty(p).context("serialize auth profile")?.as_bytes())
         .with_context(|| format!("write auth tmp: {}", tmp.display()))?;
     fs::rename(&tmp, &path).with_context(|| format!("replace auth: {}", path.display()))?;
+    emit_profile_change(previous.as_ref(), Some(p));
+    Ok(())
+}
+
+fn credits_path() -> Result<PathBuf> {
+    let base = dirs::config_dir().context("missing config dir")?;
+    Ok(base.join("Pompora").join("credits.json"))
+}
+
+// The signed-in account's cached credits, if any.
+fn cached_credits() -> Option<CachedCredits> {
+    let profile = load_profile().ok().flatten()?;
+    let raw = fs::read_to_string(credits_path().ok()?).ok()?;
+    serde_json::from_str::<CachedCredits>(&raw)
+        .ok()
+        .filter(|c| c.user_id == profile.user_id)
+}
+
+fn store_credits(cached: &CachedCredits) -> Result<()> {
+    let path = credits_path()?;
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent).with_context(|| format!("create auth dir: {}", parent.display()))?;
+    }
+    let tmp = path.with_extension("json.tmp");
+    fs::write(&tmp, serde_json::to_string(cached).context("serialize credits")?)
+        .with_context(|| format!("write credits tmp: {}", tmp.display()))?;
+    fs::rename(&tmp, &path).with_context(|| format!("replace credits: {}", path.display()))?;
     Ok(())
 }
 
@@ -90,7 +241,9 @@ pub fn load_profile() -> Result<Option<AuthProfile>> {
 pub fn clear_profile() -> Result<()> {
     let path = auth_path()?;
     if path.exists() {
+        let previous = load_profile().ok().flatten();
         let _ = fs::remove_file(&path);
+        emit_profile_change(previous.as_ref(), None);
     }
     Ok(())
 }
@@ -159,7 +312,14 @@ fn write_http_response(stream: &mut TcpStream, status: &str, body: &str) {
     let _ = stream.flush();
 }
 
-fn handle_callback_request(state_expected: &str, req: &str) -> Result<AuthProfile> {
+// PKCE (RFC 7636): the login URL carries the hash of a secret verifier, and the code the
+// callback brings is only exchanged for a key together with that verifier. A callback forged by
+// another local process can't get us to store a key of its choosing.
+fn code_challenge(verifier: &str) -> String {
+    base64::engine::general_purpose::URL_SAFE_NO_PAD.encode(Sha256::digest(verifier.as_bytes()))
+}
+
+async fn handle_callback_request(backend: &str, state_expected: &str, verifier: &str, req: &str) -> Result<AuthProfile> {
     let first_line = req.lines().next().unwrap_or("");
     let mut parts = first_line.split_whitespace();
     let _method = parts.next().unwrap_or("");
@@ -182,27 +342,51 @@ fn handle_callback_request(state_expected: &str, req: &str) -> Result<AuthProfil
         return Err(anyhow!("state mismatch"));
     }
 
-    let api_key = qp.get("apiKey").map(|s| s.trim()).unwrap_or("");
+    let code = qp.get("code").map(|s| s.trim()).unwrap_or("");
+    if code.is_empty() {
+        return Err(anyhow!("missing code"));
+    }
+
+    let client = http::client()?;
+    let res = client
+        .post(format!("{backend}/api/desktop/token"))
+        .json(&json!({ "code": code, "codeVerifier": verifier }))
+        .send()
+        .await
+        .map_err(|e| anyhow!(redact_error(e)))
+        .context("token request")?;
+
+    let status = res.status();
+    let text = res.text().await.context("token response text")?;
+    if !status.is_success() {
+        return Err(anyhow!("token request failed (status {status}): {}", redact(&text)));
+    }
+    let parsed = serde_json::from_str::<TokenResponse>(&text)
+        .with_context(|| format!("invalid token json: {}", redact(&text)))?;
+    let api_key = parsed.api_key.trim();
     if api_key.is_empty() {
-        return Err(anyhow!("missing apiKey"));
+        return Err(anyhow!("token response has no apiKey"));
     }
 
-    let plan = qp.get("plan").cloned().unwrap_or_else(|| "starter".to_string());
-    let email = qp.get("email").cloned().unwrap_or_else(|| "".to_string());
-    let user_id = qp.get("userId").cloned().unwrap_or_else(|| "".to_string());
-    let avatar_url = qp.get("avatarUrl").cloned().unwrap_or_else(|| "".to_string());
-    let first_name = qp.get("firstName").cloned().unwrap_or_else(|| "".to_string());
-    let last_name = qp.get("lastName").cloned().unwrap_or_else(|| "".to_string());
+    // Seconds rather than a timestamp, so a wrong clock on either side doesn't matter.
+    let expires_at = parsed.expires_in.map(|secs| now_ms() + secs * 1000);
 
-    secrets::provider_key_set("pompora", api_key, None).map_err(|e| anyhow!(e))?;
+    secrets::provider_key_set("pompora", api_key, None, None, "auth_login").map_err(|e| anyhow!(e))?;
+    match parsed.refresh_token.as_deref().map(str::trim).filter(|s| !s.is_empty()) {
+        Some(token) => secrets::provider_key_set(REFRESH_TOKEN_SLOT, token, None, None, "auth_login").map_err(|e| anyhow!(e))?,
+        None => {
+            let _ = secrets::provider_key_clear(REFRESH_TOKEN_SLOT, None, "auth_login");
+        }
+    }
 
     let profile = AuthProfile {
-        user_id,
-        email,
-        plan,
-        avatar_url,
-        first_name,
-        last_name,
+        user_id: parsed.user_id,
+        email: parsed.email,
+        plan: parsed.plan.filter(|p| !p.is_empty()).unwrap_or_else(|| "starter".to_string()),
+        avatar_url: parsed.avatar_url,
+        first_name: parsed.first_name,
+        last_name: parsed.last_name,
+        expires_at,
     };
 
     store_profile(&profile)?;
@@ -210,108 +394,354 @@ fn handle_callback_request(state_expected: &str, req: &str) -> Result<AuthProfil
     Ok(profile)
 }
 
+// Makes the callback thread of `pending` give up, which also ends its `wait_login`.
+fn stop_login(pending: PendingLogin) {
+    pending.canceled.store(true, Ordering::SeqCst);
+    // The thread sits in `accept`; a connection of our own wakes it up.
+    let addr = SocketAddr::from(([127, 0, 0, 1], pending.port));
+    let _ = TcpStream::connect_timeout(&addr, Duration::from_secs(1));
+}
+
+// Forgets the login with `state` if it is still the pending one.
+fn take_pending(state: &str) -> Option<PendingLogin> {
+    let mut pending = PENDING.lock().unwrap_or_else(|e| e.into_inner());
+    if pending.as_ref().is_some_and(|p| p.state == state) {
+        pending.take()
+    } else {
+        None
+    }
+}
+
+/// Starts a login and returns the URL to open and its state. A login still in progress is canceled.
 pub async fn begin_login() -> Result<(String, String)> {
+    let backend = backend_url()?;
     let state = random_state();
+    let verifier = format!("{}{}", random_state(), random_state());
+    let challenge = code_challenge(&verifier);
 
     let state_for_thread = state.clone();
+    let backend_for_thread = backend.clone();
 
     let listener = TcpListener::bind("127.0.0.1:0").context("bind callback server")?;
     let addr = listener.local_addr().context("callback server addr")?;
     let port = addr.port();
 
-    let (tx, rx) = tokio::sync::oneshot::channel::<AuthProfile>();
-
-    {
-        let mut map = PENDING.lock().map_err(|_| anyhow!("auth lock poisoned"))?;
-        map.insert(
-            state.clone(),
-            PendingLogin {
-                receiver: rx,
-            },
-        );
+    let (tx, rx) = tokio::sync::oneshot::channel::<Result<AuthProfile, String>>();
+    let canceled = Arc::new(AtomicBool::new(false));
+    let canceled_for_thread = canceled.clone();
+
+    let previous = {
+        let mut pending = PENDING.lock().map_err(|_| anyhow!("auth lock poisoned"))?;
+        pending.replace(PendingLogin {
+            state: state.clone(),
+            receiver: Some(rx),
+            port,
+            canceled,
+        })
+    };
+    if let Some(previous) = previous {
+        stop_login(previous);
     }
 
     std::thread::spawn(move || {
         let accept = listener.accept();
+        if canceled_for_thread.load(Ordering::SeqCst) {
+            return;
+        }
         match accept {
             Ok((mut stream, _)) => {
                 let req = read_http_request(&mut stream);
-                match req.and_then(|r| handle_callback_request(&state_for_thread, &r)) {
+                let profile = req.and_then(|r| {
+                    tauri::async_runtime::block_on(handle_callback_request(
+                        &backend_for_thread,
+                        &state_for_thread,
+                        &verifier,
+                        &r,
+                    ))
+                });
+                match profile {
                     Ok(profile) => {
                         write_http_response(
                             &mut stream,
                             "200 OK",
                             "<html><body>Signed in. You can close this window.</body></html>",
                         );
-                        let _ = tx.send(profile);
+                        let _ = tx.send(Ok(profile));
                     }
-                    Err(_) => {
+                    Err(e) => {
                         write_http_response(
                             &mut stream,
                             "400 Bad Request",
                             "<html><body>Login failed. You can close this window.</body></html>",
                         );
+                        let _ = tx.send(Err(redact_error(e)));
                     }
                 }
             }
-            Err(_) => {
+            Err(e) => {
+                let _ = tx.send(Err(e.to_string()));
             }
         }
+        take_pending(&state_for_thread);
     });
 
     let redirect = format!("http://127.0.0.1:{port}/callback");
     let url = format!(
-        "https://pompora.dev/desktop/login?redirect={}&state={}",
+        "{backend}/desktop/login?redirect={}&state={}&code_challenge={}&code_challenge_method=S256",
         urlencoding::encode(&redirect),
-        urlencoding::encode(&state)
+        urlencoding::encode(&state),
+        challenge
     );
 
     Ok((url, state))
 }
 
 pub async fn wait_login(state: &str) -> Result<AuthProfile> {
-    let pending = {
-        let mut map = PENDING.lock().map_err(|_| anyhow!("auth lock poisoned"))?;
-        map.remove(state)
+    let receiver = {
+        let mut pending = PENDING.lock().map_err(|_| anyhow!("auth lock poisoned"))?;
+        pending
+            .as_mut()
+            .filter(|p| p.state == state)
+            .and_then(|p| p.receiver.take())
     };
 
-    let mut pending = pending.ok_or_else(|| anyhow!("login not started"))?;
+    let receiver = receiver.ok_or_else(|| anyhow!("login not started"))?;
 
-    let profile = tokio::time::timeout(Duration::from_secs(180), pending.receiver)
-        .await
+    let result = tokio::time::timeout(Duration::from_secs(180), receiver).await;
+    if result.is_err() {
+        cancel_login(state);
+    }
+    let profile = result
         .map_err(|_| anyhow!("login timeout"))
         .context("wait login")
-        .and_then(|r| r.map_err(|_| anyhow!("login canceled")))?;
+        .and_then(|r| r.map_err(|_| anyhow!("login canceled")))?
+        .map_err(|e| anyhow!("login failed: {e}"))?;
 
     Ok(profile)
 }
 
+/// Cancels the login with `state`, closing its callback listener; its `wait_login` fails with
+/// "login canceled". Nothing happens if it already finished or was superseded.
+pub fn cancel_login(state: &str) {
+    if let Some(pending) = take_pending(state) {
+        stop_login(pending);
+    }
+}
+
 pub async fn fetch_credits() -> Result<CreditsResponse> {
-    let api_key = secrets::provider_key_get("pompora", None).map_err(|e| anyhow!(e))?;
+    let api_key = secrets::provider_key_get("pompora", None, None, "auth_fetch_credits").map_err(|e| anyhow!(e))?;
 
-    let client = reqwest::Client::new();
+    let client = http::client()?;
     let res = client
-        .get("https://pompora.dev/api/desktop/credits")
+        .get(format!("{}/api/desktop/credits", backend_url()?))
         .bearer_auth(api_key.trim())
         .send()
         .await
+        .map_err(|e| anyhow!(redact_error(e)))
         .context("credits request")?;
 
     let status = res.status();
     let text = res.text().await.context("credits response text")?;
 
+    if status == reqwest::StatusCode::UNAUTHORIZED {
+        let expires_at = load_profile().ok().flatten().and_then(|p| p.expires_at);
+        emit_expired(expires_at, "The Pompora API key was rejected.".to_string());
+    }
     if !status.is_success() {
-        return Err(anyhow!("credits request failed (status {status}): {text}"));
+        return Err(anyhow!("credits request failed (status {status}): {}", redact(&text)));
     }
 
     let parsed = serde_json::from_str::<CreditsResponse>(&text)
-        .with_context(|| format!("invalid credits json: {text}"))?;
+        .with_context(|| format!("invalid credits json: {}", redact(&text)))?;
 
     Ok(parsed)
 }
 
+// What the backend trades a login code for.
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct TokenResponse {
+    api_key: String,
+    #[serde(default)]
+    user_id: String,
+    #[serde(default)]
+    email: String,
+    #[serde(default)]
+    plan: Option<String>,
+    #[serde(default)]
+    avatar_url: String,
+    #[serde(default)]
+    first_name: String,
+    #[serde(default)]
+    last_name: String,
+    #[serde(default)]
+    expires_in: Option<u64>,
+    #[serde(default)]
+    refresh_token: Option<String>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct RefreshResponse {
+    api_key: String,
+    #[serde(default)]
+    expires_in: Option<u64>,
+    /// A new refresh token when the server rotates them.
+    #[serde(default)]
+    refresh_token: Option<String>,
+}
+
+/// Trades the refresh token from the last login for a new API key, and records its expiry.
+pub async fn refresh() -> Result<AuthProfile> {
+    let mut profile = load_profile()?.ok_or_else(|| anyhow!("not signed in"))?;
+    let token = secrets::provider_key_get(REFRESH_TOKEN_SLOT, None, None, "auth_refresh")
+        .map_err(|_| anyhow!("no refresh token stored; sign in again"))?;
+
+    let client = http::client()?;
+    let res = client
+        .post(format!("{}/api/desktop/refresh", backend_url()?))
+        .json(&json!({ "refreshToken": token.trim() }))
+        .send()
+        .await
+        .map_err(|e| anyhow!(redact_error(e)))
+        .context("refresh request")?;
+
+    let status = res.status();
+    let text = res.text().await.context("refresh response text")?;
+    if !status.is_success() {
+        return Err(anyhow!("refresh request failed (status {status}): {}", redact(&text)));
+    }
+    let parsed = serde_json::from_str::<RefreshResponse>(&text)
+        .with_context(|| format!("invalid refresh json: {}", redact(&text)))?;
+    if parsed.api_key.trim().is_empty() {
+        return Err(anyhow!("refresh response has no apiKey"));
+    }
+
+    secrets::provider_key_set("pompora", parsed.api_key.trim(), None, None, "auth_refresh").map_err(|e| anyhow!(e))?;
+    if let Some(token) = parsed.refresh_token.as_deref().map(str::trim).filter(|t| !t.is_empty()) {
+        secrets::provider_key_set(REFRESH_TOKEN_SLOT, token, None, None, "auth_refresh").map_err(|e| anyhow!(e))?;
+    }
+    profile.expires_at = parsed.expires_in.map(|secs| now_ms() + secs * 1000);
+    store_profile(&profile)?;
+    Ok(profile)
+}
+
+/// Keeps the stored API key fresh in the background: shortly before it expires it is refreshed,
+/// and if that fails `auth:expired` is sent. Cached credits are refreshed the same way.
+pub fn watch(app: AppHandle) {
+    if APP.set(app).is_err() {
+        return;
+    }
+    tauri::async_runtime::spawn(async {
+        // Expiry of the key `auth:expired` was last sent for, so it goes out once per key.
+        let mut announced = None;
+        loop {
+            check_expiry(&mut announced).await;
+            let signed_in = load_profile().ok().flatten().is_some();
+            let due = cached_credits().is_none_or(|c| now_ms().saturating_sub(c.fetched_at) >= CREDITS_REFRESH_MS);
+            if signed_in && due {
+                let _ = refresh_credits().await;
+            }
+            tokio::time::sleep(EXPIRY_CHECK_INTERVAL).await;
+        }
+    });
+}
+
+async fn check_expiry(announced: &mut Option<u64>) {
+    let Some(expires_at) = load_profile().ok().flatten().and_then(|p| p.expires_at) else {
+        return;
+    };
+    if now_ms() + REFRESH_MARGIN_MS < expires_at {
+        return;
+    }
+    let reason = match refresh().await {
+        Ok(_) => return,
+        Err(e) => e.to_string(),
+    };
+    if *announced != Some(expires_at) {
+        *announced = Some(expires_at);
+        tracing::warn!("API key refresh failed: {reason}");
+        emit_expired(Some(expires_at), reason);
+    }
+}
+
+/// Fetches the credits, caches them and sends `credits:updated`.
+pub async fn refresh_credits() -> Result<CreditsResponse> {
+    let credits = fetch_credits().await?;
+    let fetched_at = now_ms();
+    if let Some(profile) = load_profile().ok().flatten() {
+        let cached = CachedCredits {
+            user_id: profile.user_id,
+            fetched_at,
+            credits: credits.clone(),
+        };
+        let _ = store_credits(&cached);
+    }
+    if let Some(app) = APP.get() {
+        let _ = app.emit("credits:updated", CreditsUpdatedEvent { credits: credits.clone(), fetched_at });
+    }
+    Ok(credits)
+}
+
+/// The signed-in account's credits. Cached ones are returned straight away while they are
+/// recent, and refreshed in the background once they are `CREDITS_REFRESH_MS` old; otherwise,
+/// or with `force`, they are fetched, falling back to the cache if that fails.
+pub async fn get_credits(force: bool) -> Result<CreditsResponse> {
+    let cached = cached_credits().filter(|c| now_ms().saturating_sub(c.fetched_at) < CREDITS_MAX_AGE_MS);
+    if let (false, Some(c)) = (force, &cached) {
+        if now_ms().saturating_sub(c.fetched_at) >= CREDITS_REFRESH_MS {
+            tauri::async_runtime::spawn(async {
+                let _ = refresh_credits().await;
+            });
+        }
+        return Ok(c.credits.clone());
+    }
+    match refresh_credits().await {
+        Ok(credits) => Ok(credits),
+        Err(e) => cached.map(|c| c.credits).ok_or(e),
+    }
+}
+
 pub fn logout() -> Result<()> {
-    let _ = secrets::provider_key_clear("pompora");
+    let _ = secrets::provider_key_clear("pompora", None, "auth_logout");
+    let _ = secrets::provider_key_clear(REFRESH_TOKEN_SLOT, None, "auth_logout");
+    if let Ok(path) = credits_path() {
+        let _ = fs::remove_file(path);
+    }
     let _ = clear_profile();
     Ok(())
 }
+
+/// Checks that the backend at `url`, or the configured one, answers on both its desktop API
+/// and its AI API.
+pub async fn test_backend(url: Option<String>) -> Result<BackendTest> {
+    let url = match url.as_deref().filter(|v| !v.trim().is_empty()) {
+        Some(url) => settings::normalize_backend_url(url)?,
+        None => backend_url()?,
+    };
+    let ai_url = ai_base_for(&url);
+    let client = http::client()?;
+    let started = std::time::Instant::now();
+    // Without a key both should answer 401, which is enough to know they are there.
+    let status = client
+        .get(format!("{url}/api/desktop/credits"))
+        .timeout(BACKEND_TEST_TIMEOUT)
+        .send()
+        .await
+        .map_err(|e| anyhow!("{url} did not answer: {}", redact_error(e)))?
+        .status();
+    let ai_status = client
+        .get(format!("{ai_url}/models"))
+        .timeout(BACKEND_TEST_TIMEOUT)
+        .send()
+        .await
+        .map_err(|e| anyhow!("{ai_url} did not answer: {}", redact_error(e)))?
+        .status();
+    Ok(BackendTest {
+        url,
+        ai_url,
+        status: status.as_u16(),
+        ai_status: ai_status.as_u16(),
+        latency_ms: started.elapsed().as_millis() as u64,
+    })
+}
@@ -0,0 +1,219 @@
+use anyhow::{anyhow, Context, Result};
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use super::{fsops, workspace};
+
+// Where exports go when no destination is given, relative to the first workspace root.
+const DEFAULT_DIR: &str = ".pompora/chats";
+
+#[derive(Debug, Clone, Copy, Default, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ExportFormat {
+    #[default]
+    Markdown,
+    Json,
+}
+
+impl ExportFormat {
+    fn extension(self) -> &'static str {
+        match self {
+            ExportFormat::Markdown => "md",
+            ExportFormat::Json => "json",
+        }
+    }
+}
+
+/// A file change the assistant proposed.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TranscriptEdit {
+    /// `write`, `delete` or `rename`.
+    pub kind: String,
+    pub path: String,
+    #[serde(default)]
+    pub added: Option<u32>,
+    #[serde(default)]
+    pub removed: Option<u32>,
+    #[serde(default)]
+    pub applied: bool,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TranscriptMessage {
+    /// `user` or `assistant`.
+    pub role: String,
+    pub content: String,
+    /// Edits proposed with this message.
+    #[serde(default)]
+    pub edits: Vec<TranscriptEdit>,
+}
+
+/// A chat session as the webview keeps it; sessions only live there, so the export is given
+/// the session rather than looking it up.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ChatTranscript {
+    pub id: String,
+    pub title: String,
+    /// Milliseconds since the epoch.
+    pub created_at: u64,
+    pub updated_at: u64,
+    #[serde(default)]
+    pub provider: Option<String>,
+    /// Model, or Pompora thinking mode, the chat was answered with.
+    #[serde(default)]
+    pub model: Option<String>,
+    pub messages: Vec<TranscriptMessage>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct TranscriptUsage {
+    pub user_messages: u32,
+    pub assistant_messages: u32,
+    pub edits_proposed: u32,
+    pub edits_applied: u32,
+}
+
+impl TranscriptUsage {
+    fn of(t: &ChatTranscript) -> Self {
+        let edits = t.messages.iter().flat_map(|m| &m.edits);
+        TranscriptUsage {
+            user_messages: t.messages.iter().filter(|m| m.role == "user").count() as u32,
+            assistant_messages: t.messages.iter().filter(|m| m.role == "assistant").count() as u32,
+            edits_proposed: edits.clone().count() as u32,
+            edits_applied: edits.filter(|e| e.applied).count() as u32,
+        }
+    }
+}
+
+#[derive(Serialize)]
+struct JsonExport<'a> {
+    format: &'static str,
+    app_version: &'static str,
+    exported_at: u64,
+    usage: TranscriptUsage,
+    #[serde(flatten)]
+    chat: &'a ChatTranscript,
+}
+
+// `2026-10-16 14:03 UTC` for milliseconds since the epoch.
+fn utc_time(ms: u64) -> String {
+    let secs = ms / 1000;
+    let (days, rem) = ((secs / 86_400) as i64, secs % 86_400);
+    // Days to a civil date, after Howard Hinnant's `civil_from_days`.
+    let z = days + 719_468;
+    let era = z.div_euclid(146_097);
+    let doe = z - era * 146_097;
+    let yoe = (doe - doe / 1460 + doe / 36_524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = yoe + era * 400 + i64::from(month <= 2);
+    format!("{year:04}-{month:02}-{day:02} {:02}:{:02} UTC", rem / 3600, rem % 3600 / 60)
+}
+
+fn render_markdown(t: &ChatTranscript, exported_at: u64) -> String {
+    let usage = TranscriptUsage::of(t);
+    let title = if t.title.trim().is_empty() { "Chat" } else { t.title.trim() };
+    let mut out = format!("# {title}\n\n");
+    out.push_str(&format!("- Started: {}\n", utc_time(t.created_at)));
+    out.push_str(&format!("- Last activity: {}\n", utc_time(t.updated_at)));
+    out.push_str(&format!("- Exported: {} from Pompora {}\n", utc_time(exported_at), env!("CARGO_PKG_VERSION")));
+    if let Some(provider) = t.provider.as_deref().filter(|p| !p.is_empty()) {
+        out.push_str(&format!("- Provider: {provider}\n"));
+    }
+    if let Some(model) = t.model.as_deref().filter(|m| !m.is_empty()) {
+        out.push_str(&format!("- Model: {model}\n"));
+    }
+    out.push_str(&format!(
+        "- Messages: {} from the user, {} from the assistant\n",
+        usage.user_messages, usage.assistant_messages
+    ));
+    out.push_str(&format!("- Edits: {} proposed, {} applied\n", usage.edits_proposed, usage.edits_applied));
+
+    for m in &t.messages {
+        let who = if m.role == "user" { "User" } else { "Assistant" };
+        out.push_str(&format!("\n## {who}\n\n"));
+        if !m.content.trim().is_empty() {
+            out.push_str(m.content.trim_end());
+            out.push('\n');
+        }
+        if m.edits.is_empty() {
+            continue;
+        }
+        out.push_str("\n**Proposed edits:**\n\n");
+        for e in &m.edits {
+            let lines = match (e.added, e.removed) {
+                (Some(a), Some(r)) => format!(" (+{a} −{r})"),
+                _ => String::new(),
+            };
+            let status = if e.applied { "applied" } else { "not applied" };
+            out.push_str(&format!("- {} `{}`{lines}, {status}\n", e.kind, e.path));
+        }
+    }
+    out
+}
+
+// Title as a file name: lowercase words joined with `-`.
+fn slug(title: &str) -> String {
+    let words: Vec<String> = title
+        .split(|c: char| !c.is_alphanumeric())
+        .filter(|w| !w.is_empty())
+        .take(8)
+        .map(str::to_lowercase)
+        .collect();
+    if words.is_empty() {
+        "chat".to_string()
+    } else {
+        words.join("-")
+    }
+}
+
+/// Writes the chat as Markdown or JSON and returns the path written. `dest` is a workspace
+/// path, or an absolute file or folder path (e.g. from a save dialog); without it the file
+/// goes in `.pompora/chats/` in the workspace.
+pub fn chat_export(transcript: &ChatTranscript, format: ExportFormat, dest: Option<&str>) -> Result<String> {
+    let exported_at = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_millis() as u64)
+        .unwrap_or(0);
+    let contents = match format {
+        ExportFormat::Markdown => render_markdown(transcript, exported_at),
+        ExportFormat::Json => {
+            let export = JsonExport {
+                format: "pompora-chat",
+                app_version: env!("CARGO_PKG_VERSION"),
+                exported_at,
+                usage: TranscriptUsage::of(transcript),
+                chat: transcript,
+            };
+            serde_json::to_string_pretty(&export).context("serialize chat")? + "\n"
+        }
+    };
+    let file_name = format!("{}-{}.{}", slug(&transcript.title), exported_at / 1000, format.extension());
+
+    match dest.map(str::trim).filter(|d| !d.is_empty()) {
+        Some(d) if Path::new(d).is_absolute() => {
+            let path = PathBuf::from(d);
+            let path = if path.is_dir() { path.join(file_name) } else { path };
+            let previous = fs::metadata(&path).ok().filter(|m| m.is_file());
+            fsops::write_atomic(&path, contents.as_bytes(), previous.as_ref())?;
+            Ok(path.to_string_lossy().to_string())
+        }
+        Some(rel) => {
+            fsops::workspace_write_file(rel, &contents, None, false, false)?;
+            Ok(rel.to_string())
+        }
+        None => {
+            let root = workspace::open_roots()?
+                .into_iter()
+                .next()
+                .ok_or_else(|| anyhow!("no workspace is open"))?;
+            let rel = root.workspace_path(&format!("{DEFAULT_DIR}/{file_name}"));
+            fsops::workspace_write_file(&rel, &contents, None, false, false)?;
+            Ok(rel)
+        }
+    }
+}
@@ -0,0 +1,293 @@
+// Static checks on shell commands the AI proposes, run before anyone confirms one. It reads the
+// text only and never runs anything, so it is a second pair of eyes rather than a guarantee.
+use once_cell::sync::Lazy;
+use regex::Regex;
+use serde::Serialize;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Risk {
+    None,
+    Low,
+    Medium,
+    High,
+    Critical,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct CommandFinding {
+    /// What was found, e.g. `rm-recursive` or `pipe-to-shell`.
+    pub rule: &'static str,
+    pub risk: Risk,
+    pub message: String,
+    /// The flagged part of the command, as UTF-16 offsets so the UI can slice the string it sent.
+    pub start: usize,
+    pub end: usize,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct CommandAnalysis {
+    /// The highest risk among the findings; `none` when there are none.
+    pub risk: Risk,
+    /// By position in the command.
+    pub findings: Vec<CommandFinding>,
+}
+
+struct Rule {
+    id: &'static str,
+    risk: Risk,
+    message: &'static str,
+    pattern: &'static str,
+}
+
+const SHELLS: &str = r"(?:sh|bash|zsh|dash|ksh|fish|python[0-9.]*|perl|ruby|node|php|pwsh|powershell)";
+
+static RULES: Lazy<Vec<(Rule, Regex)>> = Lazy::new(|| {
+    let rules = [
+        Rule {
+            id: "pipe-to-shell",
+            risk: Risk::High,
+            message: "Runs a script straight from the network, without a chance to read it",
+            pattern: r"(?i)\b(?:curl|wget|fetch|iwr|irm|invoke-webrequest|invoke-restmethod)\b[^|;&\n]*\|\s*(?:sudo\s+(?:-\S+\s+)*)?(?:env\s+)?(?:SHELLS|iex|invoke-expression)\b",
+        },
+        Rule {
+            id: "pipe-to-shell",
+            risk: Risk::High,
+            message: "Runs a script straight from the network, without a chance to read it",
+            pattern: r#"(?i)\bSHELLS\s+(?:-\S+\s+)*(?:<\(\s*|["']?\$\(\s*)(?:curl|wget)\b"#,
+        },
+        Rule {
+            id: "pipe-to-shell",
+            risk: Risk::High,
+            message: "Runs a script straight from the network, without a chance to read it",
+            pattern: r"(?i)\b(?:iex|invoke-expression)\b\s*\(?\s*\(?\s*(?:iwr|irm|invoke-webrequest|invoke-restmethod|new-object\s+(?:system\.)?net\.webclient)\b",
+        },
+        Rule {
+            id: "decode-to-shell",
+            risk: Risk::High,
+            message: "Runs an encoded script whose contents can't be seen here",
+            pattern: r"(?i)\bbase64\s+(?:-d|-D|--decode)\b[^;&\n]*\|\s*(?:sudo\s+)?SHELLS\b",
+        },
+        Rule {
+            id: "fork-bomb",
+            risk: Risk::Critical,
+            message: "Fork bomb: starts copies of itself until the machine stops responding",
+            pattern: r"[\w:]+\s*\(\s*\)\s*\{\s*[\w:]+\s*\|\s*[\w:]+\s*&\s*;?\s*\}",
+        },
+        Rule {
+            id: "fork-bomb",
+            risk: Risk::Critical,
+            message: "Fork bomb: starts copies of itself until the machine stops responding",
+            pattern: r"%0\s*\|\s*%0",
+        },
+        Rule {
+            id: "disk-overwrite",
+            risk: Risk::Critical,
+            message: "Writes straight to a disk device, destroying what is on it",
+            pattern: r"(?i)(?:\bdd\b[^;&|\n]*\bof=|>\s*)/dev/(?:sd|hd|vd|xvd|nvme|mmcblk|disk|rdisk)\w*",
+        },
+        Rule {
+            id: "disk-overwrite",
+            risk: Risk::Critical,
+            message: "Formats or wipes a disk or partition",
+            pattern: r"(?i)\b(?:mkfs(?:\.\w+)?|wipefs|format\s+[a-z]:)",
+        },
+        Rule {
+            id: "disk-overwrite",
+            risk: Risk::High,
+            message: "Changes disk partitions",
+            pattern: r"(?i)\b(?:fdisk|sfdisk|parted|diskpart)\b",
+        },
+        Rule {
+            id: "reverse-shell",
+            risk: Risk::Critical,
+            message: "Hands a shell to a remote host",
+            pattern: r"(?i)/dev/(?:tcp|udp)/|\b(?:nc|ncat|netcat)\b[^;&|\n]*\s-\w*[ec]\b",
+        },
+        Rule {
+            id: "shutdown",
+            risk: Risk::High,
+            message: "Shuts down or restarts the machine",
+            pattern: r"(?i)(?:^|[\s;&|(])(?:shutdown|reboot|poweroff|halt|init\s+[06])(?:\s|$|[;&|)])",
+        },
+        Rule {
+            id: "system-file",
+            risk: Risk::High,
+            message: "Overwrites a system file",
+            pattern: r">\s*/(?:etc|boot|usr|bin|sbin|lib)/\S+",
+        },
+        Rule {
+            id: "permissions",
+            risk: Risk::High,
+            message: "Changes ownership or permissions of everything under the root or home folder",
+            pattern: r"(?i)\b(?:chmod|chown|chgrp)\s+(?:\S+\s+)*?-\w*R\w*\s+(?:\S+\s+)*?(?:/\*?|~/?|\$HOME/?)(?:\s|$|[;&|])",
+        },
+        Rule {
+            id: "permissions",
+            risk: Risk::Medium,
+            message: "Makes files writable by everyone",
+            pattern: r"(?i)\bchmod\s+(?:-\S+\s+)*(?:0?777|a\+rwx|ugo\+rwx)\b",
+        },
+        Rule {
+            id: "delete-recursive",
+            risk: Risk::High,
+            message: "Deletes folders and everything in them",
+            pattern: r"(?i)\bremove-item\b[^;|\n]*\s-recurse\b|\b(?:rd|rmdir)\s+/s\b|\bdel\s+(?:/\w\s+)*/s\b",
+        },
+        Rule {
+            id: "delete-recursive",
+            risk: Risk::Medium,
+            message: "Deletes every file `find` matches",
+            pattern: r"\bfind\b[^;&|\n]*\s(?:-delete\b|-exec\s+rm\b)",
+        },
+        Rule {
+            id: "git-discard",
+            risk: Risk::Medium,
+            message: "Discards git history or uncommitted work",
+            pattern: r"\bgit\s+(?:push\b[^;&|\n]*\s(?:--force\b|-f\b)|reset\s+--hard\b|clean\s+-\w*f|checkout\s+(?:--\s+)?\.(?:\s|$))",
+        },
+        Rule {
+            id: "privileged",
+            risk: Risk::Medium,
+            message: "Runs with administrator rights",
+            pattern: r"\b(?:sudo|doas|pkexec)\b|\bsu\s+-|(?i)-verb\s+runas\b",
+        },
+        Rule {
+            id: "network-upload",
+            risk: Risk::Medium,
+            message: "Sends local data over the network",
+            pattern: r"\bcurl\b[^;&|\n]*\s(?:-T|--upload-file|-F|--form|-d|--data(?:-binary|-raw|-urlencode)?)\s|\b(?:scp|sftp)\b",
+        },
+        Rule {
+            id: "history",
+            risk: Risk::Low,
+            message: "Clears or turns off shell history",
+            pattern: r"\bhistory\s+-c\b|\bunset\s+HISTFILE\b|\bHISTFILE=/dev/null\b",
+        },
+    ];
+    rules
+        .into_iter()
+        .map(|r| {
+            let re = Regex::new(&r.pattern.replace("SHELLS", SHELLS)).expect("valid command safety regex");
+            (r, re)
+        })
+        .collect()
+});
+
+// An `rm` as a command of its own, rather than e.g. `docker run --rm`; up to the end of that command.
+static RM: Lazy<Regex> = Lazy::new(|| Regex::new(r"(?:^|[\s;&|(`])(rm\s+[^;&|\n)`]*)").expect("valid command safety regex"));
+
+// Files and variables holding credentials.
+static CREDENTIALS: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(
+        r"(?i)(?:~|\$\{?HOME\}?)?/?\.(?:ssh|aws|gnupg|kube|docker|azure|config/gcloud)/\S*|\bid_(?:rsa|dsa|ecdsa|ed25519)\b\S*|\.(?:netrc|npmrc|pypirc|git-credentials|pgpass)\b|/etc/(?:shadow|sudoers)\b|\$\{?\w*(?:TOKEN|SECRET|PASSWORD|PASSWD|API_KEY|ACCESS_KEY|PRIVATE_KEY)\w*\}?|\B\.env(?:\.local)?\b|\b(?:env|printenv)\s*(?:$|[|>;&])",
+    )
+    .expect("valid command safety regex")
+});
+
+// Anything that can send data off the machine.
+static NETWORK: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(r"(?i)\b(?:curl|wget|nc|ncat|netcat|socat|scp|sftp|ftp|telnet|rsync|iwr|irm|invoke-webrequest|invoke-restmethod)\b|/dev/(?:tcp|udp)/|\bhttps?://")
+        .expect("valid command safety regex")
+});
+
+// Targets for which `rm -rf` takes out far more than a project folder.
+fn is_sweeping_target(arg: &str) -> bool {
+    let arg = arg.trim_matches(|c| c == '"' || c == '\'');
+    if matches!(arg, "*" | "." | "./" | ".." | "../" | "~" | "~/" | "~/*" | "$HOME" | "${HOME}" | "$HOME/" | "$HOME/*") {
+        return true;
+    }
+    // `/`, `/*`, and folders right under the root such as `/usr` or `/home/`.
+    let rest = arg.trim_end_matches('*').trim_end_matches('/');
+    arg.starts_with('/') && !rest.trim_start_matches('/').contains('/')
+}
+
+fn rm_findings(command: &str) -> Vec<(usize, usize, Risk, String)> {
+    let mut out = vec![];
+    for caps in RM.captures_iter(command) {
+        let m = caps.get(1).expect("rm group");
+        let (mut recursive, mut force, mut sweeping) = (false, false, vec![]);
+        let mut options_done = false;
+        for arg in m.as_str().split_whitespace().skip(1) {
+            if !options_done && arg == "--" {
+                options_done = true;
+            } else if !options_done && arg.starts_with("--") {
+                recursive |= arg == "--recursive";
+                force |= arg == "--force";
+            } else if !options_done && arg.starts_with('-') && arg.len() > 1 {
+                recursive |= arg.contains(['r', 'R']);
+                force |= arg.contains('f');
+            } else if is_sweeping_target(arg) {
+                sweeping.push(arg);
+            }
+        }
+        if !recursive {
+            continue;
+        }
+        let (risk, message) = if !sweeping.is_empty() {
+            (Risk::Critical, format!("Deletes everything under {}", sweeping.join(" ")))
+        } else if force {
+            (Risk::High, "Deletes folders and everything in them, without asking".to_string())
+        } else {
+            (Risk::Medium, "Deletes folders and everything in them".to_string())
+        };
+        out.push((m.start(), m.end(), risk, message));
+    }
+    out
+}
+
+fn utf16_offset(s: &str, byte: usize) -> usize {
+    s[..byte].encode_utf16().count()
+}
+
+/// Looks `command` over for things worth a second look before it runs: recursive deletes,
+/// scripts piped in from the network, fork bombs, disk writes, credentials sent out and the
+/// like. The overall risk is that of the worst finding.
+pub fn analyze(command: &str) -> CommandAnalysis {
+    let mut found: Vec<(usize, usize, &'static str, Risk, String)> = vec![];
+    for (rule, re) in RULES.iter() {
+        for m in re.find_iter(command) {
+            found.push((m.start(), m.end(), rule.id, rule.risk, rule.message.to_string()));
+        }
+    }
+    for (start, end, risk, message) in rm_findings(command) {
+        found.push((start, end, "rm-recursive", risk, message));
+    }
+
+    // Credentials on their own are often harmless (`cat .env`); next to something that talks to
+    // the network they are how secrets leave the machine.
+    let network: Vec<_> = NETWORK.find_iter(command).collect();
+    for m in CREDENTIALS.find_iter(command) {
+        if network.is_empty() {
+            found.push((m.start(), m.end(), "credentials", Risk::Low, "Reads credentials or secrets".to_string()));
+        } else {
+            found.push((m.start(), m.end(), "credential-exfiltration", Risk::Critical, "Reads credentials in a command that also sends data over the network".to_string()));
+        }
+    }
+    if let (Some(net), true) = (network.first(), found.iter().any(|f| f.2 == "credential-exfiltration")) {
+        found.push((net.start(), net.end(), "credential-exfiltration", Risk::Critical, "Could send the credentials it reads off the machine".to_string()));
+    }
+
+    // Patterns that need a separator around a word take it in; leave it out of the span.
+    for f in found.iter_mut() {
+        let text = &command[f.0..f.1];
+        f.1 = f.0 + text.trim_end_matches(|c: char| c.is_whitespace() || matches!(c, ';' | '&' | '|' | '>' | ')')).len();
+        f.0 += text.len() - text.trim_start_matches(|c: char| c.is_whitespace() || matches!(c, ';' | '&' | '|' | '(')).len();
+    }
+    found.sort_by_key(|f| (f.0, std::cmp::Reverse(f.3)));
+    found.dedup_by(|a, b| a.0 == b.0 && a.1 == b.1 && a.2 == b.2);
+    let findings: Vec<CommandFinding> = found
+        .into_iter()
+        .map(|(start, end, rule, risk, message)| CommandFinding {
+            rule,
+            risk,
+            message,
+            start: utf16_offset(command, start),
+            end: utf16_offset(command, end),
+        })
+        .collect();
+    CommandAnalysis {
+        risk: findings.iter().map(|f| f.risk).max().unwrap_or(Risk::None),
+        findings,
+    }
+}
@@ -0,0 +1,397 @@
+use anyhow::Result;
+use once_cell::sync::Lazy;
+use regex::Regex;
+use serde::Serialize;
+use serde_json::{json, Value as Json};
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::Path;
+
+use super::git;
+
+// Where commitlint looks for its config, in its order. Only the JSON ones can be read here.
+const CONFIG_FILES: &[&str] = &[
+    "package.json",
+    ".commitlintrc",
+    ".commitlintrc.json",
+    ".commitlintrc.yaml",
+    ".commitlintrc.yml",
+    ".commitlintrc.js",
+    ".commitlintrc.cjs",
+    ".commitlintrc.mjs",
+    ".commitlintrc.ts",
+    "commitlint.config.js",
+    "commitlint.config.cjs",
+    "commitlint.config.mjs",
+    "commitlint.config.ts",
+];
+
+#[derive(Debug, Clone, Serialize)]
+pub struct CommitHeader {
+    #[serde(rename = "type")]
+    pub kind: String,
+    pub scope: Option<String>,
+    /// Marked with `!` after the type or scope, or a `BREAKING CHANGE:` footer.
+    pub breaking: bool,
+    pub subject: String,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct CommitProblem {
+    /// The commitlint rule broken, e.g. `type-enum`.
+    pub rule: String,
+    /// `error` or `warning`.
+    pub level: String,
+    pub message: String,
+    /// 1-based line of the message it is about.
+    pub line: usize,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct CommitValidation {
+    /// No errors; warnings are allowed.
+    pub valid: bool,
+    /// Merge, revert, `fixup!` and `squash!` messages, which aren't checked.
+    pub ignored: bool,
+    pub header: Option<CommitHeader>,
+    pub problems: Vec<CommitProblem>,
+    /// The commitlint config applied over the Conventional Commits defaults, relative to the
+    /// repository.
+    pub config: Option<String>,
+    /// Why a commitlint config that was found wasn't applied.
+    pub config_error: Option<String>,
+}
+
+#[derive(Debug, Clone)]
+struct Rule {
+    // 0 off, 1 warning, 2 error.
+    level: u64,
+    always: bool,
+    value: Json,
+}
+
+type Rules = BTreeMap<String, Rule>;
+
+static HEADER: Lazy<Regex> =
+    Lazy::new(|| Regex::new(r"^(\w*)(?:\(([^()\r\n]*)\))?(!)?: (.*)$").expect("valid commit header regex"));
+static FOOTER: Lazy<Regex> =
+    Lazy::new(|| Regex::new(r"^(?:BREAKING[ -]CHANGE|[\w-]+)(?:: | #)").expect("valid commit footer regex"));
+static IGNORED: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(r#"^(?:Merge (?:pull request|branch|remote-tracking branch|tag) |Merged? .+ into |Revert "|revert: "|(?:fixup|squash|amend)! |Automatic merge)"#)
+        .expect("valid commit ignore regex")
+});
+
+fn parse_rules(value: &Json, into: &mut Rules) {
+    let Some(map) = value.as_object() else {
+        return;
+    };
+    for (name, config) in map {
+        let Some(parts) = config.as_array() else {
+            continue;
+        };
+        let level = parts.first().and_then(Json::as_u64).unwrap_or(0);
+        let always = parts.get(1).and_then(Json::as_str) != Some("never");
+        let value = parts.get(2).cloned().unwrap_or(Json::Null);
+        into.insert(name.clone(), Rule { level, always, value });
+    }
+}
+
+// The rules of `@commitlint/config-conventional`.
+fn conventional_rules() -> Rules {
+    let mut rules = Rules::new();
+    parse_rules(
+        &json!({
+            "body-leading-blank": [1, "always"],
+            "body-max-line-length": [2, "always", 100],
+            "footer-leading-blank": [1, "always"],
+            "footer-max-line-length": [2, "always", 100],
+            "header-max-length": [2, "always", 100],
+            "header-trim": [2, "always"],
+            "subject-case": [2, "never", ["sentence-case", "start-case", "pascal-case", "upper-case"]],
+            "subject-empty": [2, "never"],
+            "subject-full-stop": [2, "never", "."],
+            "type-case": [2, "always", "lower-case"],
+            "type-empty": [2, "never"],
+            "type-enum": [2, "always", ["build", "chore", "ci", "docs", "feat", "fix", "perf", "refactor", "revert", "style", "test"]],
+        }),
+        &mut rules,
+    );
+    rules
+}
+
+// The repository's commitlint config: its name, its rules over the defaults, whether default
+// ignores apply, and why it couldn't be used.
+fn load_config(dir: &Path) -> (Option<String>, Rules, bool, Option<String>) {
+    let mut rules = conventional_rules();
+    for name in CONFIG_FILES {
+        let path = dir.join(name);
+        let Ok(text) = fs::read_to_string(&path) else {
+            continue;
+        };
+        let parsed = if *name == "package.json" {
+            match serde_json::from_str::<Json>(&text).ok().and_then(|p| p.get("commitlint").cloned()) {
+                Some(config) => Ok(config),
+                None => continue,
+            }
+        } else if name.ends_with(".json") || *name == ".commitlintrc" {
+            serde_json::from_str::<Json>(&text).map_err(|e| format!("{name} isn't valid JSON: {e}"))
+        } else {
+            Err(format!("{name} can't be read here; only JSON configs (.commitlintrc.json, package.json) are"))
+        };
+        return match parsed {
+            Ok(config) => {
+                if let Some(own) = config.get("rules") {
+                    parse_rules(own, &mut rules);
+                }
+                let default_ignores = config.get("defaultIgnores").and_then(Json::as_bool).unwrap_or(true);
+                (Some(name.to_string()), rules, default_ignores, None)
+            }
+            Err(e) => (None, rules, true, Some(e)),
+        };
+    }
+    (None, rules, true, None)
+}
+
+fn words(text: &str) -> impl Iterator<Item = &str> {
+    text.split(|c: char| c.is_whitespace()).filter(|w| !w.is_empty())
+}
+
+fn starts_upper(text: &str) -> bool {
+    text.chars().find(|c| c.is_alphabetic()).is_some_and(char::is_uppercase)
+}
+
+// Whether `text` is written in `case`, one of commitlint's case names.
+fn is_case(text: &str, case: &str) -> bool {
+    let no_separators = !text.contains(|c: char| c.is_whitespace() || c == '-' || c == '_');
+    match case {
+        "lower-case" | "lowercase" => text == text.to_lowercase(),
+        "upper-case" | "uppercase" => text == text.to_uppercase(),
+        "sentence-case" | "sentencecase" => starts_upper(text),
+        "start-case" => words(text).all(starts_upper),
+        "pascal-case" => no_separators && starts_upper(text),
+        "camel-case" => no_separators && !starts_upper(text),
+        "kebab-case" => text == text.to_lowercase() && !text.contains(|c: char| c.is_whitespace() || c == '_'),
+        "snake-case" => text == text.to_lowercase() && !text.contains(|c: char| c.is_whitespace() || c == '-'),
+        _ => true,
+    }
+}
+
+fn strings(value: &Json) -> Vec<String> {
+    match value {
+        Json::String(s) => vec![s.clone()],
+        Json::Array(a) => a.iter().filter_map(Json::as_str).map(str::to_string).collect(),
+        // `{ cases: [...] }`, as `scope-case` also takes.
+        Json::Object(o) => o.get("cases").map(strings).unwrap_or_default(),
+        _ => vec![],
+    }
+}
+
+struct Checker<'a> {
+    rules: &'a Rules,
+    problems: Vec<CommitProblem>,
+}
+
+impl Checker<'_> {
+    // Applies rule `name` when it is on: `holds` says whether the message has the property the
+    // rule names, which `always` requires and `never` forbids.
+    fn check(&mut self, name: &str, line: usize, holds: impl FnOnce(&Json) -> bool, describe: impl FnOnce(bool, &Json) -> String) {
+        let Some(rule) = self.rules.get(name).filter(|r| r.level > 0) else {
+            return;
+        };
+        if holds(&rule.value) == rule.always {
+            return;
+        }
+        self.problems.push(CommitProblem {
+            rule: name.to_string(),
+            level: if rule.level >= 2 { "error" } else { "warning" }.to_string(),
+            message: describe(rule.always, &rule.value),
+            line,
+        });
+    }
+
+    fn max_length(&mut self, name: &str, what: &str, line: usize, len: usize) {
+        self.check(
+            name,
+            line,
+            |v| v.as_u64().is_none_or(|max| len as u64 <= max),
+            |_, v| format!("{what} is {len} characters; keep it to {}", v.as_u64().unwrap_or_default()),
+        );
+    }
+
+    fn min_length(&mut self, name: &str, what: &str, line: usize, len: usize) {
+        self.check(
+            name,
+            line,
+            |v| v.as_u64().is_none_or(|min| len as u64 >= min),
+            |_, v| format!("{what} is {len} characters; make it at least {}", v.as_u64().unwrap_or_default()),
+        );
+    }
+
+    fn case(&mut self, name: &str, what: &str, line: usize, text: &str) {
+        // Numbers and symbols have no case either way.
+        if !text.contains(char::is_alphabetic) {
+            return;
+        }
+        self.check(
+            name,
+            line,
+            |v| strings(v).iter().any(|c| is_case(text, c)),
+            |always, v| format!("{what} must {}be {}", if always { "" } else { "not " }, strings(v).join(", ")),
+        );
+    }
+
+    fn empty(&mut self, name: &str, what: &str, line: usize, text: &str) {
+        self.check(
+            name,
+            line,
+            |_| text.trim().is_empty(),
+            |always, _| if always { format!("{what} must be empty") } else { format!("{what} is missing") },
+        );
+    }
+}
+
+/// Checks `message` against the Conventional Commits rules of `@commitlint/config-conventional`,
+/// with the `rules` of the repository's commitlint config (in `dir`) applied over them.
+pub fn validate_in(dir: &Path, message: &str) -> CommitValidation {
+    let (config, rules, default_ignores, config_error) = load_config(dir);
+    let lines: Vec<&str> = message.trim_end().lines().collect();
+    let raw_header = lines.first().copied().unwrap_or_default();
+    let header_line = raw_header.trim();
+
+    if default_ignores && IGNORED.is_match(header_line) {
+        return CommitValidation {
+            valid: true,
+            ignored: true,
+            header: None,
+            problems: vec![],
+            config,
+            config_error,
+        };
+    }
+
+    // Paragraphs after the header, split into the body and the trailing footer lines.
+    let rest: &[&str] = lines.get(1..).unwrap_or_default();
+    let last_paragraph = rest.iter().rposition(|l| l.trim().is_empty()).map_or(0, |i| i + 1);
+    let footer_start = (last_paragraph..rest.len()).find(|&i| FOOTER.is_match(rest[i])).unwrap_or(rest.len());
+    let (body, footer) = rest.split_at(footer_start);
+    let body_text = body.join("\n");
+    let footer_breaking = footer.iter().any(|l| l.starts_with("BREAKING CHANGE") || l.starts_with("BREAKING-CHANGE"));
+
+    let caps = HEADER.captures(header_line);
+    let header = caps.as_ref().map(|c| CommitHeader {
+        kind: c[1].to_string(),
+        scope: c.get(2).map(|s| s.as_str().to_string()),
+        breaking: c.get(3).is_some() || footer_breaking,
+        subject: c[4].trim().to_string(),
+    });
+    let (kind, scope, subject) = match &header {
+        Some(h) => (h.kind.as_str(), h.scope.as_deref().unwrap_or_default(), h.subject.as_str()),
+        None => ("", "", ""),
+    };
+
+    let mut c = Checker { rules: &rules, problems: vec![] };
+    c.check(
+        "header-trim",
+        1,
+        |_| raw_header == raw_header.trim(),
+        |_, _| "the header has whitespace at the start or end".to_string(),
+    );
+    c.max_length("header-max-length", "the header", 1, header_line.chars().count());
+    c.min_length("header-min-length", "the header", 1, header_line.chars().count());
+
+    c.empty("type-empty", "the type (as in `feat: …`)", 1, kind);
+    c.case("type-case", "the type", 1, kind);
+    if !kind.is_empty() {
+        c.check(
+            "type-enum",
+            1,
+            |v| strings(v).iter().any(|t| t == kind),
+            |_, v| format!("type `{kind}` must be one of {}", strings(v).join(", ")),
+        );
+    }
+    c.max_length("type-max-length", "the type", 1, kind.chars().count());
+
+    c.empty("scope-empty", "the scope (as in `feat(parser): …`)", 1, scope);
+    c.case("scope-case", "the scope", 1, scope);
+    if !scope.is_empty() {
+        c.check(
+            "scope-enum",
+            1,
+            |v| {
+                let allowed = strings(v);
+                allowed.is_empty() || scope.split([',', '/']).map(str::trim).all(|s| allowed.iter().any(|a| a == s))
+            },
+            |_, v| format!("scope `{scope}` must be one of {}", strings(v).join(", ")),
+        );
+    }
+
+    c.empty("subject-empty", "the subject (after `type: `)", 1, subject);
+    c.case("subject-case", "the subject", 1, subject);
+    if !subject.is_empty() {
+        c.check(
+            "subject-full-stop",
+            1,
+            |v| subject.ends_with(v.as_str().unwrap_or(".")),
+            |always, v| format!("the subject must {}end with `{}`", if always { "" } else { "not " }, v.as_str().unwrap_or(".")),
+        );
+    }
+    c.max_length("subject-max-length", "the subject", 1, subject.chars().count());
+    c.min_length("subject-min-length", "the subject", 1, subject.chars().count());
+
+    if !body.is_empty() && !body_text.trim().is_empty() {
+        c.check(
+            "body-leading-blank",
+            2,
+            |_| body.first().is_some_and(|l| l.trim().is_empty()),
+            |_, _| "leave a blank line between the header and the body".to_string(),
+        );
+    }
+    c.empty("body-empty", "the body", 2, &body_text);
+    c.min_length("body-min-length", "the body", 2, body_text.trim().chars().count());
+    for (i, line) in body.iter().enumerate() {
+        c.max_length("body-max-line-length", &format!("body line {}", i + 2), i + 2, line.chars().count());
+    }
+
+    let footer_line = footer_start + 2;
+    if !footer.is_empty() {
+        c.check(
+            "footer-leading-blank",
+            footer_line,
+            |_| footer_start > 0 && rest[footer_start - 1].trim().is_empty(),
+            |_, _| "leave a blank line before the footer".to_string(),
+        );
+    }
+    for (i, line) in footer.iter().enumerate() {
+        let n = footer_line + i;
+        c.max_length("footer-max-line-length", &format!("footer line {n}"), n, line.chars().count());
+    }
+
+    let problems = c.problems;
+    CommitValidation {
+        valid: !problems.iter().any(|p| p.level == "error"),
+        ignored: false,
+        header,
+        problems,
+        config,
+        config_error,
+    }
+}
+
+/// The commitlint config in `dir` that `validate_in` applies, if there is one it can read.
+pub fn config_in(dir: &Path) -> Option<String> {
+    load_config(dir).0
+}
+
+/// `validate_in` for the repository holding the workspace root.
+pub fn git_commit_validate(message: &str, root: Option<&str>) -> Result<CommitValidation> {
+    Ok(validate_in(&git::repo_workdir(root)?, message))
+}
+
+/// The problems of a validation as one line each, for error messages and AI prompts.
+pub fn describe_problems(v: &CommitValidation) -> String {
+    v.problems
+        .iter()
+        .map(|p| format!("{} ({}, {}): {}", p.rule, p.level, if p.line == 1 { "header".to_string() } else { format!("line {}", p.line) }, p.message))
+        .collect::<Vec<_>>()
+        .join("\n")
+}
@@ -0,0 +1,499 @@
+use anyhow::{anyhow, Context, Result};
+use futures_util::stream::{self, StreamExt};
+use serde::Serialize;
+use serde_json::Value as Json;
+use std::collections::HashMap;
+use std::fs;
+use std::path::Path;
+use toml::Value as Toml;
+
+use super::redact::redact_error;
+use super::{http, settings, workspace};
+
+const MANIFEST_NAMES: &[&str] = &["Cargo.toml", "package.json", "pyproject.toml", "go.mod"];
+// Folders of installed or vendored packages, whose manifests aren't the workspace's own even
+// when nothing ignores them.
+const SKIPPED_DIRS: &[&str] = &["node_modules", "target", "vendor", ".venv", "venv", "site-packages", "__pycache__"];
+const MAX_MANIFESTS: usize = 200;
+// Registry lookups in flight at once.
+const MAX_CONCURRENT_LOOKUPS: usize = 8;
+
+#[derive(Debug, Clone, Serialize)]
+pub struct Dependency {
+    /// Name as the manifest lists it.
+    pub name: String,
+    /// Name in the registry, when the manifest renames or aliases it.
+    pub package: Option<String>,
+    /// Requirement as written, e.g. `^1.2`, `>=2,<3` or `v1.4.0`; empty when none is given.
+    pub version: String,
+    /// `normal`, `dev`, `build`, `peer`, `optional` or `indirect`.
+    pub kind: String,
+    /// `path`, `git`, `url` or `workspace` for a dependency that doesn't come from the
+    /// registry; its version isn't checked.
+    pub source: Option<String>,
+    /// 1-based line it is declared on, when it could be found.
+    pub line: Option<u32>,
+    /// Newest stable release in the registry, when updates were checked.
+    pub latest: Option<String>,
+    /// Whether `latest` is newer than the version the requirement names. A range that
+    /// already allows `latest`, like `^1.2` for 1.5, still counts: the manifest can be raised.
+    pub outdated: Option<bool>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct Manifest {
+    /// Workspace path of the manifest file.
+    pub path: String,
+    /// `cargo`, `npm`, `pypi` or `go`.
+    pub ecosystem: String,
+    pub dependencies: Vec<Dependency>,
+    /// Why the manifest couldn't be read, in which case it lists no dependencies.
+    pub error: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct DependencyList {
+    pub manifests: Vec<Manifest>,
+    /// Registry lookups that failed, one line each.
+    pub errors: Vec<String>,
+}
+
+fn dependency(name: &str, version: &str, kind: &str) -> Dependency {
+    Dependency {
+        name: name.to_string(),
+        package: None,
+        version: version.trim().to_string(),
+        kind: kind.to_string(),
+        source: None,
+        line: None,
+        latest: None,
+        outdated: None,
+    }
+}
+
+// First line declaring `name`: as a key (`name = `, `"name":`), a quoted list item or a
+// `[dependencies.name]` table.
+fn find_line(content: &str, name: &str) -> Option<u32> {
+    content
+        .lines()
+        .position(|line| {
+            let t = line.trim_start();
+            let rest = t
+                .strip_prefix(name)
+                .or_else(|| t.strip_prefix('"').and_then(|r| r.strip_prefix(name)))
+                .or_else(|| t.strip_prefix('\'').and_then(|r| r.strip_prefix(name)));
+            let table = t.starts_with('[') && t.trim_end().ends_with(&format!(".{name}]"));
+            table || rest.is_some_and(|r| r.starts_with([' ', '=', '"', '\'', '.', '[', '<', '>', '~', '^', '!', ';', ':']) || r.is_empty())
+        })
+        .map(|i| i as u32 + 1)
+}
+
+fn cargo_table(deps: &toml::Table, kind: &str, out: &mut Vec<Dependency>) {
+    for (name, spec) in deps {
+        let mut d = dependency(name, "", kind);
+        match spec {
+            Toml::String(v) => d.version = v.clone(),
+            Toml::Table(t) => {
+                d.version = t.get("version").and_then(Toml::as_str).unwrap_or_default().to_string();
+                d.package = t.get("package").and_then(Toml::as_str).map(str::to_string);
+                if t.get("optional").and_then(Toml::as_bool) == Some(true) {
+                    d.kind = "optional".to_string();
+                }
+                d.source = if t.get("workspace").and_then(Toml::as_bool) == Some(true) {
+                    Some("workspace")
+                } else if t.contains_key("path") {
+                    Some("path")
+                } else if t.contains_key("git") {
+                    Some("git")
+                } else {
+                    None
+                }
+                .map(str::to_string);
+            }
+            _ => continue,
+        }
+        out.push(d);
+    }
+}
+
+fn cargo_sections(t: &toml::Table, out: &mut Vec<Dependency>) {
+    for (key, kind) in [("dependencies", "normal"), ("dev-dependencies", "dev"), ("build-dependencies", "build")] {
+        if let Some(deps) = t.get(key).and_then(Toml::as_table) {
+            cargo_table(deps, kind, out);
+        }
+    }
+}
+
+fn parse_cargo(content: &str) -> Result<Vec<Dependency>> {
+    let doc: toml::Table = content.parse().context("parse Cargo.toml")?;
+    let mut out = vec![];
+    cargo_sections(&doc, &mut out);
+    for target in doc.get("target").and_then(Toml::as_table).into_iter().flat_map(|t| t.values()) {
+        if let Some(t) = target.as_table() {
+            cargo_sections(t, &mut out);
+        }
+    }
+    if let Some(deps) = doc.get("workspace").and_then(|w| w.get("dependencies")).and_then(Toml::as_table) {
+        cargo_table(deps, "normal", &mut out);
+    }
+    Ok(out)
+}
+
+fn npm_dependency(name: &str, spec: &str, kind: &str) -> Dependency {
+    let mut d = dependency(name, spec, kind);
+    let spec = spec.trim();
+    if let Some(alias) = spec.strip_prefix("npm:") {
+        // `npm:real-name@^1.0`; a scoped name starts with its own `@`.
+        match alias.rfind('@').filter(|i| *i > 0) {
+            Some(i) => {
+                d.package = Some(alias[..i].to_string());
+                d.version = alias[i + 1..].to_string();
+            }
+            None => {
+                d.package = Some(alias.to_string());
+                d.version = String::new();
+            }
+        }
+    } else if spec.starts_with("file:") || spec.starts_with("link:") || spec.starts_with('.') || spec.starts_with('/') {
+        d.source = Some("path".to_string());
+    } else if spec.starts_with("workspace:") {
+        d.source = Some("workspace".to_string());
+    } else if spec.starts_with("http://") || spec.starts_with("https://") {
+        d.source = Some("url".to_string());
+    } else if spec.contains(':') || spec.contains('/') {
+        // `git+https://…`, `github:user/repo` or the `user/repo` shorthand.
+        d.source = Some("git".to_string());
+    }
+    d
+}
+
+fn parse_npm(content: &str) -> Result<Vec<Dependency>> {
+    let doc: Json = serde_json::from_str(content).context("parse package.json")?;
+    let mut out = vec![];
+    for (key, kind) in [
+        ("dependencies", "normal"),
+        ("devDependencies", "dev"),
+        ("peerDependencies", "peer"),
+        ("optionalDependencies", "optional"),
+    ] {
+        for (name, spec) in doc.get(key).and_then(Json::as_object).into_iter().flatten() {
+            out.push(npm_dependency(name, spec.as_str().unwrap_or_default(), kind));
+        }
+    }
+    Ok(out)
+}
+
+// A PEP 508 requirement: `name[extra] >=1.0; python_version < "3.11"` or `name @ https://…`.
+fn pep508(req: &str, kind: &str) -> Option<Dependency> {
+    let req = req.split(';').next().unwrap_or_default().trim();
+    let end = req
+        .find(|c: char| !(c.is_ascii_alphanumeric() || "._-".contains(c)))
+        .unwrap_or(req.len());
+    let name = &req[..end];
+    if name.is_empty() {
+        return None;
+    }
+    let mut rest = req[end..].trim_start();
+    if rest.starts_with('[') {
+        rest = rest.split_once(']').map(|(_, r)| r.trim_start()).unwrap_or_default();
+    }
+    let mut d = dependency(name, "", kind);
+    match rest.strip_prefix('@') {
+        Some(url) => {
+            d.source = Some(if url.trim_start().starts_with("git+") { "git" } else { "url" }.to_string());
+        }
+        None => d.version = rest.trim_start_matches('(').trim_end_matches(')').trim().to_string(),
+    }
+    Some(d)
+}
+
+fn poetry_table(deps: &toml::Table, kind: &str, out: &mut Vec<Dependency>) {
+    for (name, spec) in deps.iter().filter(|(name, _)| name.as_str() != "python") {
+        let mut d = dependency(name, "", kind);
+        match spec {
+            Toml::String(v) => d.version = v.clone(),
+            Toml::Table(t) => {
+                d.version = t.get("version").and_then(Toml::as_str).unwrap_or_default().to_string();
+                if t.get("optional").and_then(Toml::as_bool) == Some(true) {
+                    d.kind = "optional".to_string();
+                }
+                d.source = ["path", "git", "url"].into_iter().find(|k| t.contains_key(*k)).map(str::to_string);
+            }
+            _ => continue,
+        }
+        out.push(d);
+    }
+}
+
+fn parse_pyproject(content: &str) -> Result<Vec<Dependency>> {
+    let doc: toml::Table = content.parse().context("parse pyproject.toml")?;
+    let mut out = vec![];
+    let strings = |v: Option<&Toml>| -> Vec<String> {
+        v.and_then(Toml::as_array)
+            .into_iter()
+            .flatten()
+            .filter_map(|r| r.as_str().map(str::to_string))
+            .collect()
+    };
+    if let Some(project) = doc.get("project") {
+        out.extend(strings(project.get("dependencies")).iter().filter_map(|r| pep508(r, "normal")));
+        for group in project.get("optional-dependencies").and_then(Toml::as_table).into_iter().flat_map(|t| t.values()) {
+            out.extend(strings(Some(group)).iter().filter_map(|r| pep508(r, "optional")));
+        }
+    }
+    // PEP 735 groups, used for development tools; entries can also include other groups.
+    for group in doc.get("dependency-groups").and_then(Toml::as_table).into_iter().flat_map(|t| t.values()) {
+        out.extend(strings(Some(group)).iter().filter_map(|r| pep508(r, "dev")));
+    }
+    if let Some(poetry) = doc.get("tool").and_then(|t| t.get("poetry")) {
+        if let Some(deps) = poetry.get("dependencies").and_then(Toml::as_table) {
+            poetry_table(deps, "normal", &mut out);
+        }
+        if let Some(deps) = poetry.get("dev-dependencies").and_then(Toml::as_table) {
+            poetry_table(deps, "dev", &mut out);
+        }
+        for group in poetry.get("group").and_then(Toml::as_table).into_iter().flat_map(|t| t.values()) {
+            if let Some(deps) = group.get("dependencies").and_then(Toml::as_table) {
+                poetry_table(deps, "dev", &mut out);
+            }
+        }
+    }
+    Ok(out)
+}
+
+fn parse_go(content: &str) -> Vec<Dependency> {
+    let mut out = vec![];
+    let mut in_block = false;
+    for (i, line) in content.lines().enumerate() {
+        let (code, comment) = line.split_once("//").unwrap_or((line, ""));
+        let code = code.trim();
+        let spec = if in_block {
+            if code == ")" {
+                in_block = false;
+                continue;
+            }
+            code
+        } else if let Some(rest) = code.strip_prefix("require") {
+            let rest = rest.trim();
+            if rest == "(" {
+                in_block = true;
+                continue;
+            }
+            rest
+        } else {
+            continue;
+        };
+        let mut parts = spec.split_whitespace();
+        let (Some(module), Some(version)) = (parts.next(), parts.next()) else {
+            continue;
+        };
+        let kind = if comment.trim() == "indirect" { "indirect" } else { "normal" };
+        let mut d = dependency(module, version, kind);
+        d.line = Some(i as u32 + 1);
+        out.push(d);
+    }
+    out
+}
+
+fn ecosystem(file_name: &str) -> &'static str {
+    match file_name {
+        "Cargo.toml" => "cargo",
+        "package.json" => "npm",
+        "pyproject.toml" => "pypi",
+        _ => "go",
+    }
+}
+
+fn read_manifest(path: &str, abs: &Path, file_name: &str) -> Manifest {
+    let ecosystem = ecosystem(file_name);
+    let parsed = fs::read_to_string(abs).context("read manifest").and_then(|content| {
+        let mut deps = match ecosystem {
+            "cargo" => parse_cargo(&content)?,
+            "npm" => parse_npm(&content)?,
+            "pypi" => parse_pyproject(&content)?,
+            _ => parse_go(&content),
+        };
+        for d in deps.iter_mut().filter(|d| d.line.is_none()) {
+            d.line = find_line(&content, &d.name);
+        }
+        Ok(deps)
+    });
+    let (dependencies, error) = match parsed {
+        Ok(deps) => (deps, None),
+        Err(e) => (vec![], Some(format!("{e:#}"))),
+    };
+    Manifest {
+        path: path.to_string(),
+        ecosystem: ecosystem.to_string(),
+        dependencies,
+        error,
+    }
+}
+
+/// Dependencies declared in the workspace's `Cargo.toml`, `package.json`, `pyproject.toml`
+/// and `go.mod` files, by manifest. Ignored folders and installed packages aren't searched.
+pub fn dependencies_list() -> Result<DependencyList> {
+    let mut manifests = vec![];
+    'roots: for root in workspace::open_roots()? {
+        for entry in root.walk(false, false).filter_map(|e| e.ok()) {
+            let name = entry.file_name().to_string_lossy().to_string();
+            if !MANIFEST_NAMES.contains(&name.as_str()) || !entry.file_type().is_some_and(|t| t.is_file()) {
+                continue;
+            }
+            let Ok(rel) = entry.path().strip_prefix(&root.path) else {
+                continue;
+            };
+            if rel.components().any(|c| SKIPPED_DIRS.contains(&c.as_os_str().to_string_lossy().as_ref())) {
+                continue;
+            }
+            let rel = rel.to_string_lossy().replace('\\', "/");
+            manifests.push(read_manifest(&root.workspace_path(&rel), entry.path(), &name));
+            if manifests.len() >= MAX_MANIFESTS {
+                break 'roots;
+            }
+        }
+    }
+    manifests.sort_by(|a, b| a.path.cmp(&b.path));
+    Ok(DependencyList { manifests, errors: vec![] })
+}
+
+// Numeric parts of the first version in a requirement: `>=1.2, <2` is `[1, 2]`.
+fn version_parts(v: &str) -> Option<Vec<u64>> {
+    let start = v.find(|c: char| c.is_ascii_digit())?;
+    let version = v[start..].split(|c: char| !(c.is_ascii_digit() || c == '.')).next()?;
+    let parts: Vec<u64> = version.split('.').filter(|p| !p.is_empty()).filter_map(|p| p.parse().ok()).collect();
+    (!parts.is_empty()).then_some(parts)
+}
+
+fn is_newer(latest: &str, declared: &str) -> Option<bool> {
+    let (mut latest, mut declared) = (version_parts(latest)?, version_parts(declared)?);
+    let len = latest.len().max(declared.len());
+    latest.resize(len, 0);
+    declared.resize(len, 0);
+    Some(latest > declared)
+}
+
+fn is_prerelease(v: &str) -> bool {
+    semver::Version::parse(v.trim_start_matches('v')).map_or_else(
+        |_| v.chars().any(|c| c.is_ascii_alphabetic() && c != 'v'),
+        |v| !v.pre.is_empty(),
+    )
+}
+
+// Where crates.io's sparse index keeps a crate.
+fn crates_index_path(name: &str) -> String {
+    let name = name.to_lowercase();
+    match name.len() {
+        1 => format!("1/{name}"),
+        2 => format!("2/{name}"),
+        3 => format!("3/{}/{name}", &name[..1]),
+        _ => format!("{}/{}/{name}", &name[..2], &name[2..4]),
+    }
+}
+
+// Go module paths are escaped for the proxy: each capital letter becomes `!` and the letter.
+fn go_escape(module: &str) -> String {
+    module
+        .chars()
+        .flat_map(|c| if c.is_ascii_uppercase() { vec!['!', c.to_ascii_lowercase()] } else { vec![c] })
+        .collect()
+}
+
+async fn get(client: &reqwest::Client, url: &str) -> Result<reqwest::Response> {
+    let res = client
+        .get(url)
+        .header(
+            reqwest::header::USER_AGENT,
+            concat!("Pompora/", env!("CARGO_PKG_VERSION"), " (https://github.com/mitrashkov/pompora)"),
+        )
+        .send()
+        .await
+        .map_err(|e| anyhow!(redact_error(e)))?;
+    if !res.status().is_success() {
+        return Err(anyhow!("{} returned {}", url, res.status()));
+    }
+    Ok(res)
+}
+
+async fn latest_version(client: &reqwest::Client, ecosystem: &str, package: &str) -> Result<String> {
+    let latest = match ecosystem {
+        "cargo" => {
+            let text = get(client, &format!("https://index.crates.io/{}", crates_index_path(package)))
+                .await?
+                .text()
+                .await?;
+            text.lines()
+                .filter_map(|l| serde_json::from_str::<Json>(l).ok())
+                .filter(|v| v.get("yanked").and_then(Json::as_bool) != Some(true))
+                .filter_map(|v| semver::Version::parse(v.get("vers")?.as_str()?).ok())
+                .filter(|v| v.pre.is_empty())
+                .max()
+                .map(|v| v.to_string())
+        }
+        "npm" => {
+            let url = format!("https://registry.npmjs.org/-/package/{}/dist-tags", package.replace('/', "%2F"));
+            let tags: Json = get(client, &url).await?.json().await?;
+            tags.get("latest").and_then(Json::as_str).map(str::to_string)
+        }
+        "pypi" => {
+            let doc: Json = get(client, &format!("https://pypi.org/pypi/{package}/json")).await?.json().await?;
+            doc.pointer("/info/version").and_then(Json::as_str).map(str::to_string)
+        }
+        _ => {
+            let url = format!("https://proxy.golang.org/{}/@latest", go_escape(package));
+            let doc: Json = get(client, &url).await?.json().await?;
+            doc.get("Version").and_then(Json::as_str).map(str::to_string)
+        }
+    };
+    latest.filter(|v| !is_prerelease(v) || ecosystem == "go").ok_or_else(|| anyhow!("no stable release found"))
+}
+
+/// Looks up the newest release of every registry dependency in `list` and fills in `latest`
+/// and `outdated`. Each package is looked up once however many manifests declare it.
+pub async fn check_updates(list: &mut DependencyList) -> Result<()> {
+    if settings::load()?.offline_mode {
+        return Err(anyhow!("offline mode is enabled; versions weren't checked"));
+    }
+    let client = http::client()?;
+    let mut wanted: Vec<(String, String)> = list
+        .manifests
+        .iter()
+        .flat_map(|m| {
+            m.dependencies
+                .iter()
+                .filter(|d| d.source.is_none())
+                .map(|d| (m.ecosystem.clone(), d.package.clone().unwrap_or_else(|| d.name.clone())))
+        })
+        .collect();
+    wanted.sort();
+    wanted.dedup();
+
+    let found: HashMap<(String, String), Result<String>> = stream::iter(wanted)
+        .map(|key| {
+            let client = client.clone();
+            async move {
+                let latest = latest_version(&client, &key.0, &key.1).await;
+                (key, latest)
+            }
+        })
+        .buffer_unordered(MAX_CONCURRENT_LOOKUPS)
+        .collect()
+        .await;
+
+    for ((ecosystem, package), result) in &found {
+        if let Err(e) = result {
+            list.errors.push(format!("{ecosystem} {package}: {e}"));
+        }
+    }
+    list.errors.sort();
+    for m in &mut list.manifests {
+        for d in m.dependencies.iter_mut().filter(|d| d.source.is_none()) {
+            let key = (m.ecosystem.clone(), d.package.clone().unwrap_or_else(|| d.name.clone()));
+            if let Some(Ok(latest)) = found.get(&key) {
+                d.outdated = is_newer(latest, &d.version);
+                d.latest = Some(latest.clone());
+            }
+        }
+    }
+    Ok(())
+}
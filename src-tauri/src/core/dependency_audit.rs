@@ -0,0 +1,557 @@
+use anyhow::{anyhow, Context, Result};
+use serde::Serialize;
+use serde_json::{json, Value as Json};
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+use tauri::{AppHandle, Emitter};
+
+use super::dependencies::{self, Manifest};
+use super::problem_matchers::{Diagnostic, DiagnosticsReport, Severity, DIAGNOSTICS_REPORT_EVENT};
+use super::redact::redact_error;
+use super::{fsops, http, settings, workspace};
+
+const DIAGNOSTICS_SOURCE: &str = "audit:dependencies";
+const OSV_API: &str = "https://api.osv.dev/v1";
+// Advisories whose details are fetched from OSV per audit; the rest are listed by id only.
+const MAX_OSV_DETAILS: usize = 200;
+
+#[derive(Debug, Clone, Serialize)]
+pub struct Vulnerability {
+    /// Advisory id, e.g. `RUSTSEC-2023-0001`, `GHSA-…`, `PYSEC-…` or `GO-…`.
+    pub id: String,
+    /// Other ids for it, usually CVEs.
+    pub aliases: Vec<String>,
+    pub package: String,
+    /// Version in the lockfile or environment, or the affected range when only that is known.
+    pub version: Option<String>,
+    /// `cargo`, `npm`, `pypi` or `go`.
+    pub ecosystem: String,
+    /// `low`, `moderate`, `high` or `critical`, when the advisory rates it.
+    pub severity: Option<String>,
+    pub summary: String,
+    /// Versions, or ranges like `>=1.2.3`, that fix it; empty when there is no fix.
+    pub fixed: Vec<String>,
+    pub url: Option<String>,
+    /// Workspace path of the manifest it was found through.
+    pub manifest: String,
+    /// Line of the manifest declaring the package, for a direct dependency.
+    pub line: Option<u32>,
+}
+
+/// How one manifest was audited.
+#[derive(Debug, Clone, Serialize)]
+pub struct AuditSource {
+    pub manifest: String,
+    pub ecosystem: String,
+    /// `cargo-audit`, `npm-audit`, `pip-audit` or `osv`; `None` when it couldn't be audited.
+    pub tool: Option<String>,
+    pub error: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct AuditReport {
+    pub vulnerabilities: Vec<Vulnerability>,
+    pub sources: Vec<AuditSource>,
+}
+
+// A package and version to look up in OSV.
+struct Locked {
+    name: String,
+    version: String,
+}
+
+// What a tool or OSV found, before it is tied to a manifest.
+struct Finding {
+    id: String,
+    aliases: Vec<String>,
+    package: String,
+    version: Option<String>,
+    severity: Option<String>,
+    summary: String,
+    fixed: Vec<String>,
+    url: Option<String>,
+}
+
+// Why a tool didn't give a result: it isn't installed, so OSV is asked instead, or it failed.
+enum ToolError {
+    Missing,
+    Failed(String),
+}
+
+fn strings(v: Option<&Json>) -> Vec<String> {
+    v.and_then(Json::as_array)
+        .into_iter()
+        .flatten()
+        .filter_map(|s| s.as_str().map(str::to_string))
+        .collect()
+}
+
+fn str_of(v: &Json, pointer: &str) -> Option<String> {
+    v.pointer(pointer).and_then(Json::as_str).map(str::to_string)
+}
+
+fn run_tool(program: &str, args: &[&str], dir: &Path) -> Result<Json, ToolError> {
+    let mut cmd = Command::new(program);
+    cmd.args(args).current_dir(dir).stdin(Stdio::null());
+    #[cfg(windows)]
+    {
+        use std::os::windows::process::CommandExt;
+        const CREATE_NO_WINDOW: u32 = 0x0800_0000;
+        cmd.creation_flags(CREATE_NO_WINDOW);
+    }
+    let out = match cmd.output() {
+        Ok(out) => out,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Err(ToolError::Missing),
+        Err(e) => return Err(ToolError::Failed(format!("could not start {program}: {e}"))),
+    };
+    let stderr = String::from_utf8_lossy(&out.stderr);
+    // `cargo` without the cargo-audit subcommand.
+    if stderr.contains("no such command") {
+        return Err(ToolError::Missing);
+    }
+    // Findings make the exit status fail, so the output decides.
+    serde_json::from_slice(&out.stdout).map_err(|_| {
+        let last = stderr.lines().rev().find(|l| !l.trim().is_empty()).unwrap_or_default().trim();
+        ToolError::Failed(if last.is_empty() { format!("{program} failed ({})", out.status) } else { last.to_string() })
+    })
+}
+
+fn cargo_audit(dir: &Path) -> Result<Vec<Finding>, ToolError> {
+    let out = run_tool("cargo", &["audit", "--json"], dir)?;
+    let list = out.pointer("/vulnerabilities/list").and_then(Json::as_array).cloned().unwrap_or_default();
+    Ok(list
+        .iter()
+        .map(|v| {
+            let id = str_of(v, "/advisory/id").unwrap_or_default();
+            Finding {
+                url: str_of(v, "/advisory/url").or_else(|| Some(format!("https://rustsec.org/advisories/{id}"))),
+                id,
+                aliases: strings(v.pointer("/advisory/aliases")),
+                package: str_of(v, "/package/name").unwrap_or_default(),
+                version: str_of(v, "/package/version"),
+                severity: None,
+                summary: str_of(v, "/advisory/title").unwrap_or_default(),
+                fixed: strings(v.pointer("/versions/patched")),
+            }
+        })
+        .collect())
+}
+
+fn npm_audit(dir: &Path) -> Result<Vec<Finding>, ToolError> {
+    let npm = if cfg!(windows) { "npm.cmd" } else { "npm" };
+    let out = run_tool(npm, &["audit", "--json"], dir)?;
+    if let Some(e) = str_of(&out, "/error/summary") {
+        return Err(ToolError::Failed(e));
+    }
+    let mut found: Vec<Finding> = vec![];
+    for (name, entry) in out.get("vulnerabilities").and_then(Json::as_object).into_iter().flatten() {
+        let fix = entry.get("fixAvailable");
+        let fixed: Vec<String> = match fix.and_then(|f| f.get("name").zip(f.get("version"))) {
+            // The fix is upgrading a package that depends on this one.
+            Some((n, v)) if n.as_str() == Some(name) => v.as_str().map(|v| vec![v.to_string()]).unwrap_or_default(),
+            Some((n, v)) => vec![format!("{}@{}", n.as_str().unwrap_or_default(), v.as_str().unwrap_or_default())],
+            None if fix.and_then(Json::as_bool) == Some(true) => vec!["npm audit fix".to_string()],
+            None => vec![],
+        };
+        // `via` names advisories, or other vulnerable packages this one pulls in.
+        for via in entry.get("via").and_then(Json::as_array).into_iter().flatten().filter(|v| v.is_object()) {
+            let url = str_of(via, "/url");
+            let id = url
+                .as_deref()
+                .and_then(|u| u.rsplit('/').next())
+                .map(str::to_string)
+                .or_else(|| via.get("source").map(|s| s.to_string()))
+                .unwrap_or_default();
+            if found.iter().any(|f| f.id == id && f.package == *name) {
+                continue;
+            }
+            found.push(Finding {
+                id,
+                aliases: vec![],
+                package: name.clone(),
+                version: str_of(via, "/range"),
+                severity: str_of(via, "/severity"),
+                summary: str_of(via, "/title").unwrap_or_default(),
+                fixed: fixed.clone(),
+                url,
+            });
+        }
+    }
+    Ok(found)
+}
+
+fn pip_audit(dir: &Path, requirements: &Path) -> Result<Vec<Finding>, ToolError> {
+    let req = requirements.to_string_lossy();
+    let out = run_tool("pip-audit", &["-r", &req, "-f", "json", "--progress-spinner", "off"], dir)?;
+    // Older versions print the dependency list alone.
+    let deps = out.get("dependencies").unwrap_or(&out).as_array().cloned().unwrap_or_default();
+    let mut found = vec![];
+    for dep in &deps {
+        for v in dep.get("vulns").and_then(Json::as_array).into_iter().flatten() {
+            let id = str_of(v, "/id").unwrap_or_default();
+            found.push(Finding {
+                url: Some(format!("https://osv.dev/vulnerability/{id}")),
+                id,
+                aliases: strings(v.get("aliases")),
+                package: str_of(dep, "/name").unwrap_or_default(),
+                version: str_of(dep, "/version"),
+                severity: None,
+                summary: str_of(v, "/description").unwrap_or_default(),
+                fixed: strings(v.get("fix_versions")),
+            });
+        }
+    }
+    Ok(found)
+}
+
+// Registry packages in a Cargo.lock.
+fn cargo_lock(path: &Path) -> Result<Vec<Locked>> {
+    let lock: toml::Table = fs::read_to_string(path)?.parse().context("parse Cargo.lock")?;
+    Ok(lock
+        .get("package")
+        .and_then(toml::Value::as_array)
+        .into_iter()
+        .flatten()
+        .filter(|p| p.get("source").and_then(toml::Value::as_str).is_some_and(|s| s.starts_with("registry+")))
+        .filter_map(|p| {
+            Some(Locked {
+                name: p.get("name")?.as_str()?.to_string(),
+                version: p.get("version")?.as_str()?.to_string(),
+            })
+        })
+        .collect())
+}
+
+// Installed packages in a package-lock.json: `packages` from lockfile version 2 on, else the
+// nested `dependencies` of version 1.
+fn npm_lock(path: &Path) -> Result<Vec<Locked>> {
+    let lock: Json = serde_json::from_str(&fs::read_to_string(path)?).context("parse package-lock.json")?;
+    let mut out = vec![];
+    if let Some(packages) = lock.get("packages").and_then(Json::as_object) {
+        for (key, p) in packages {
+            let Some((_, name)) = key.rsplit_once("node_modules/") else {
+                continue;
+            };
+            if p.get("link").and_then(Json::as_bool) == Some(true) {
+                continue;
+            }
+            if let Some(version) = p.get("version").and_then(Json::as_str) {
+                out.push(Locked {
+                    name: name.to_string(),
+                    version: version.to_string(),
+                });
+            }
+        }
+        return Ok(out);
+    }
+    let mut stack: Vec<&Json> = lock.get("dependencies").into_iter().collect();
+    while let Some(deps) = stack.pop() {
+        for (name, d) in deps.as_object().into_iter().flatten() {
+            if let Some(version) = d.get("version").and_then(Json::as_str) {
+                out.push(Locked {
+                    name: name.clone(),
+                    version: version.to_string(),
+                });
+            }
+            stack.extend(d.get("dependencies"));
+        }
+    }
+    Ok(out)
+}
+
+// `name==1.2.3` pins in a requirements file; anything looser can't be looked up.
+fn requirement_pins(path: &Path) -> Vec<Locked> {
+    fs::read_to_string(path)
+        .unwrap_or_default()
+        .lines()
+        .filter_map(|l| {
+            let l = l.split(['#', ';']).next()?.trim();
+            let (name, version) = l.split_once("==")?;
+            let name = name.split('[').next()?.trim();
+            Some(Locked {
+                name: name.to_string(),
+                version: version.trim().to_string(),
+            })
+        })
+        .filter(|p| !p.name.is_empty() && !p.version.is_empty())
+        .collect()
+}
+
+// What OSV can be asked about for a manifest: locked versions, or else exact ones it declares.
+fn osv_packages(manifest: &Manifest, dir: &Path) -> Result<Vec<Locked>> {
+    let declared_exact = |strip: &[char]| -> Vec<Locked> {
+        manifest
+            .dependencies
+            .iter()
+            .filter(|d| d.source.is_none())
+            .filter_map(|d| {
+                let v = d.version.trim_start_matches(strip).trim();
+                let exact = !v.is_empty() && v.chars().all(|c| c.is_ascii_alphanumeric() || ".-+".contains(c));
+                exact.then(|| Locked {
+                    name: d.package.clone().unwrap_or_else(|| d.name.clone()),
+                    version: v.trim_start_matches('v').to_string(),
+                })
+            })
+            .collect()
+    };
+    match manifest.ecosystem.as_str() {
+        "cargo" => cargo_lock(&dir.join("Cargo.lock")),
+        "npm" if dir.join("package-lock.json").is_file() => npm_lock(&dir.join("package-lock.json")),
+        "npm" => Err(anyhow!("no package-lock.json to read installed versions from")),
+        "pypi" if dir.join("requirements.txt").is_file() => Ok(requirement_pins(&dir.join("requirements.txt"))),
+        "pypi" => Ok(declared_exact(&['='])),
+        // go.mod requirements are exact versions, prefixed with `v`.
+        _ => Ok(declared_exact(&[])),
+    }
+}
+
+fn osv_ecosystem(ecosystem: &str) -> &'static str {
+    match ecosystem {
+        "cargo" => "crates.io",
+        "npm" => "npm",
+        "pypi" => "PyPI",
+        _ => "Go",
+    }
+}
+
+fn go_version(ecosystem: &str, version: &str) -> String {
+    if ecosystem == "go" && !version.starts_with('v') {
+        format!("v{version}")
+    } else {
+        version.to_string()
+    }
+}
+
+// Advisory details from OSV, by id; `details` is shared so each is fetched once per audit.
+async fn osv_vuln(client: &reqwest::Client, id: &str, details: &mut HashMap<String, Option<Json>>) -> Option<Json> {
+    if let Some(d) = details.get(id) {
+        return d.clone();
+    }
+    let fetched = if details.len() < MAX_OSV_DETAILS {
+        match client.get(format!("{OSV_API}/vulns/{id}")).send().await {
+            Ok(res) if res.status().is_success() => res.json::<Json>().await.ok(),
+            _ => None,
+        }
+    } else {
+        None
+    };
+    details.insert(id.to_string(), fetched.clone());
+    fetched
+}
+
+async fn osv_audit(
+    client: &reqwest::Client,
+    ecosystem: &str,
+    packages: &[Locked],
+    details: &mut HashMap<String, Option<Json>>,
+) -> Result<Vec<Finding>> {
+    if packages.is_empty() {
+        return Ok(vec![]);
+    }
+    let queries: Vec<Json> = packages
+        .iter()
+        .map(|p| json!({ "package": { "name": p.name, "ecosystem": osv_ecosystem(ecosystem) }, "version": p.version }))
+        .collect();
+    let mut results = vec![];
+    // OSV takes at most 1000 queries per batch.
+    for chunk in queries.chunks(1000) {
+        let res = client
+            .post(format!("{OSV_API}/querybatch"))
+            .json(&json!({ "queries": chunk }))
+            .send()
+            .await
+            .map_err(|e| anyhow!(redact_error(e)))
+            .context("query OSV")?;
+        if !res.status().is_success() {
+            return Err(anyhow!("OSV returned {}", res.status()));
+        }
+        let body: Json = res.json().await.context("read OSV response")?;
+        results.extend(body.get("results").and_then(Json::as_array).cloned().unwrap_or_default());
+    }
+
+    let mut found = vec![];
+    for (package, result) in packages.iter().zip(&results) {
+        for v in result.get("vulns").and_then(Json::as_array).into_iter().flatten() {
+            let Some(id) = v.get("id").and_then(Json::as_str) else {
+                continue;
+            };
+            let detail = osv_vuln(client, id, details).await.unwrap_or(Json::Null);
+            let summary = str_of(&detail, "/summary")
+                .or_else(|| str_of(&detail, "/details").map(|d| d.lines().next().unwrap_or_default().to_string()))
+                .unwrap_or_default();
+            let fixed = detail
+                .get("affected")
+                .and_then(Json::as_array)
+                .into_iter()
+                .flatten()
+                .filter(|a| str_of(a, "/package/name").as_deref() == Some(package.name.as_str()))
+                .flat_map(|a| a.get("ranges").and_then(Json::as_array).cloned().unwrap_or_default())
+                .flat_map(|r| r.get("events").and_then(Json::as_array).cloned().unwrap_or_default())
+                .filter_map(|e| str_of(&e, "/fixed"))
+                .collect();
+            found.push(Finding {
+                id: id.to_string(),
+                aliases: strings(detail.get("aliases")),
+                package: package.name.clone(),
+                version: Some(package.version.clone()),
+                severity: str_of(&detail, "/database_specific/severity").map(|s| s.to_lowercase()),
+                summary,
+                fixed,
+                url: Some(format!("https://osv.dev/vulnerability/{id}")),
+            });
+        }
+    }
+    Ok(found)
+}
+
+// Runs the ecosystem's audit tool when allowed and installed. `Ok(None)` means it isn't
+// installed or doesn't apply, and OSV should be asked instead.
+fn audit_with_tool(manifest: &Manifest, dir: &Path) -> Result<Option<(&'static str, Vec<Finding>)>, String> {
+    let result = match manifest.ecosystem.as_str() {
+        "cargo" => cargo_audit(dir).map(|f| ("cargo-audit", f)),
+        "npm" if dir.join("package-lock.json").is_file() => npm_audit(dir).map(|f| ("npm-audit", f)),
+        "pypi" if dir.join("requirements.txt").is_file() => pip_audit(dir, &dir.join("requirements.txt")).map(|f| ("pip-audit", f)),
+        _ => return Ok(None),
+    };
+    match result {
+        Ok(found) => Ok(Some(found)),
+        Err(ToolError::Missing) => Ok(None),
+        Err(ToolError::Failed(e)) => Err(e),
+    }
+}
+
+fn severity(v: &Vulnerability) -> Severity {
+    match v.severity.as_deref() {
+        Some("critical" | "high") => Severity::Error,
+        Some("low") => Severity::Info,
+        _ => Severity::Warning,
+    }
+}
+
+fn diagnostic(v: &Vulnerability) -> Diagnostic {
+    let version = v.version.as_deref().map(|x| format!(" {x}")).unwrap_or_default();
+    let fix = if v.fixed.is_empty() {
+        "no fixed version".to_string()
+    } else {
+        format!("fixed in {}", v.fixed.join(", "))
+    };
+    Diagnostic {
+        path: v.manifest.clone(),
+        line: v.line.unwrap_or(1),
+        column: None,
+        severity: severity(v),
+        message: format!("{}{version}: {} ({fix})", v.package, v.summary),
+        code: Some(v.id.clone()),
+        matcher: "audit",
+    }
+}
+
+/// Audits the dependencies of the workspace's manifests for known vulnerabilities, with
+/// `cargo audit`, `npm audit` or `pip-audit` where installed and the workspace is trusted,
+/// and otherwise with OSV for the versions in `Cargo.lock`, `package-lock.json`,
+/// `requirements.txt` pins or `go.mod`. Cargo workspace members are audited through the
+/// workspace's lockfile. Findings are also reported as `diagnostics:report` for the source
+/// `audit:dependencies`, on the manifest.
+pub async fn dependencies_audit(app: &AppHandle) -> Result<AuditReport> {
+    if settings::load()?.offline_mode {
+        return Err(anyhow!("offline mode is enabled; dependencies can't be audited"));
+    }
+    let trusted = workspace::is_trusted(&settings::load()?);
+    let list = tokio::task::spawn_blocking(dependencies::dependencies_list).await??;
+    let client = http::client()?;
+    let mut details = HashMap::new();
+    let mut vulnerabilities = vec![];
+    let mut sources = vec![];
+
+    for manifest in &list.manifests {
+        let (_, _, abs) = fsops::resolve(&manifest.path, false)?;
+        let dir: PathBuf = abs.parent().map(Path::to_path_buf).unwrap_or_default();
+        let mut source = AuditSource {
+            manifest: manifest.path.clone(),
+            ecosystem: manifest.ecosystem.clone(),
+            tool: None,
+            error: manifest.error.clone(),
+        };
+        if manifest.ecosystem == "cargo" && !dir.join("Cargo.lock").is_file() {
+            // A workspace member, audited with the workspace's lockfile, or a crate never built.
+            if dir.ancestors().skip(1).any(|d| d.join("Cargo.lock").is_file()) {
+                continue;
+            }
+            source.error = Some("no Cargo.lock to read dependency versions from".to_string());
+            sources.push(source);
+            continue;
+        }
+
+        let from_tool = if trusted {
+            let (m, d) = (manifest.clone(), dir.clone());
+            tokio::task::spawn_blocking(move || audit_with_tool(&m, &d)).await?
+        } else {
+            Ok(None)
+        };
+        let found = match from_tool {
+            Ok(Some((tool, found))) => {
+                source.tool = Some(tool.to_string());
+                found
+            }
+            Ok(None) => {
+                let packages = {
+                    let (m, d) = (manifest.clone(), dir.clone());
+                    tokio::task::spawn_blocking(move || osv_packages(&m, &d)).await?
+                };
+                match packages {
+                    Ok(packages) => match osv_audit(&client, &manifest.ecosystem, &packages, &mut details).await {
+                        Ok(found) => {
+                            source.tool = Some("osv".to_string());
+                            found
+                        }
+                        Err(e) => {
+                            source.error = Some(format!("{e:#}"));
+                            vec![]
+                        }
+                    },
+                    Err(e) => {
+                        source.error = Some(format!("{e:#}"));
+                        vec![]
+                    }
+                }
+            }
+            Err(e) => {
+                source.error = Some(e);
+                vec![]
+            }
+        };
+        sources.push(source);
+
+        for f in found {
+            let line = manifest
+                .dependencies
+                .iter()
+                .find(|d| d.package.as_deref().unwrap_or(&d.name) == f.package)
+                .and_then(|d| d.line);
+            vulnerabilities.push(Vulnerability {
+                id: f.id,
+                aliases: f.aliases,
+                package: f.package,
+                version: f.version.map(|v| go_version(&manifest.ecosystem, &v)),
+                ecosystem: manifest.ecosystem.clone(),
+                severity: f.severity,
+                summary: f.summary,
+                fixed: f.fixed,
+                url: f.url,
+                manifest: manifest.path.clone(),
+                line,
+            });
+        }
+    }
+
+    let _ = app.emit(
+        DIAGNOSTICS_REPORT_EVENT,
+        DiagnosticsReport {
+            source: DIAGNOSTICS_SOURCE.to_string(),
+            diagnostics: vulnerabilities.iter().map(diagnostic).collect(),
+        },
+    );
+    Ok(AuditReport { vulnerabilities, sources })
+}
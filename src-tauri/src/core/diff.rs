@@ -0,0 +1,137 @@
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+use similar::{Algorithm, DiffTag};
+use std::borrow::Cow;
+use std::time::{Duration, Instant};
+
+use super::fsops;
+use super::git::{DiffHunk, DiffLine};
+
+// Past this a diff stops looking for the smallest edit and finishes with a coarser one.
+pub const DIFF_DEADLINE: Duration = Duration::from_secs(2);
+const DEFAULT_CONTEXT: u32 = 3;
+
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum DiffAlgorithm {
+    #[default]
+    Myers,
+    /// Matches unique lines first, which keeps moved blocks and reordered functions readable.
+    Patience,
+}
+
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default)]
+pub struct TextDiffOptions {
+    pub algorithm: DiffAlgorithm,
+    /// Unchanged lines around each change; 3 when not given.
+    pub context: Option<u32>,
+    /// Treat lines that differ only in whitespace, line endings included, as equal.
+    pub ignore_whitespace: bool,
+}
+
+/// Hunks in the shape `git_diff_file` returns them, so both render the same way.
+#[derive(Debug, Clone, Serialize)]
+pub struct TextDiff {
+    pub hunks: Vec<DiffHunk>,
+    pub added: u32,
+    pub removed: u32,
+}
+
+fn lines(text: &str) -> Vec<&str> {
+    text.split_inclusive('\n').collect()
+}
+
+fn key<'a>(line: &'a str, ignore_whitespace: bool) -> Cow<'a, str> {
+    if ignore_whitespace {
+        Cow::Owned(line.split_whitespace().collect())
+    } else {
+        Cow::Borrowed(line)
+    }
+}
+
+fn diff_line(origin: char, line: &str, old_lineno: Option<usize>, new_lineno: Option<usize>) -> DiffLine {
+    DiffLine {
+        origin: origin.to_string(),
+        content: line.trim_end_matches(['\n', '\r']).to_string(),
+        old_lineno: old_lineno.map(|n| n as u32 + 1),
+        new_lineno: new_lineno.map(|n| n as u32 + 1),
+    }
+}
+
+// Unified diff convention: an empty side starts at the line before it.
+fn start_of(start: usize, len: usize) -> u32 {
+    if len == 0 {
+        start as u32
+    } else {
+        start as u32 + 1
+    }
+}
+
+/// Line diff of two texts. A last line that only gained or lost its newline counts as changed.
+/// Lines that are context on both sides are shown as they are in `new`.
+pub fn diff_texts(old: &str, new: &str, options: &TextDiffOptions) -> TextDiff {
+    let (old_lines, new_lines) = (lines(old), lines(new));
+    let old_keys: Vec<Cow<str>> = old_lines.iter().map(|l| key(l, options.ignore_whitespace)).collect();
+    let new_keys: Vec<Cow<str>> = new_lines.iter().map(|l| key(l, options.ignore_whitespace)).collect();
+    let algorithm = match options.algorithm {
+        DiffAlgorithm::Myers => Algorithm::Myers,
+        DiffAlgorithm::Patience => Algorithm::Patience,
+    };
+    let ops = similar::capture_diff_slices_deadline(
+        algorithm,
+        &old_keys,
+        &new_keys,
+        Some(Instant::now() + DIFF_DEADLINE),
+    );
+    let context = options.context.unwrap_or(DEFAULT_CONTEXT) as usize;
+
+    let mut out = TextDiff {
+        hunks: vec![],
+        added: 0,
+        removed: 0,
+    };
+    for group in similar::group_diff_ops(ops, context) {
+        let (Some(first), Some(last)) = (group.first(), group.last()) else {
+            continue;
+        };
+        let old_range = first.old_range().start..last.old_range().end;
+        let new_range = first.new_range().start..last.new_range().end;
+        let mut lines = vec![];
+        for op in &group {
+            let (tag, old_r, new_r) = op.as_tag_tuple();
+            if tag == DiffTag::Equal {
+                for (o, n) in old_r.zip(new_r) {
+                    lines.push(diff_line(' ', new_lines[n], Some(o), Some(n)));
+                }
+                continue;
+            }
+            for o in old_r {
+                lines.push(diff_line('-', old_lines[o], Some(o), None));
+                out.removed += 1;
+            }
+            for n in new_r {
+                lines.push(diff_line('+', new_lines[n], None, Some(n)));
+                out.added += 1;
+            }
+        }
+        let (old_start, new_start) = (start_of(old_range.start, old_range.len()), start_of(new_range.start, new_range.len()));
+        let (old_count, new_count) = (old_range.len() as u32, new_range.len() as u32);
+        out.hunks.push(DiffHunk {
+            header: format!("@@ -{old_start},{old_count} +{new_start},{new_count} @@"),
+            old_start,
+            old_lines: old_count,
+            new_start,
+            new_lines: new_count,
+            lines,
+        });
+    }
+    out
+}
+
+/// Diff of two workspace files, `a_rel` being the old side.
+pub fn diff_files(a_rel: &str, b_rel: &str, options: &TextDiffOptions) -> Result<TextDiff> {
+    let old = fsops::workspace_read_file(a_rel)?.file.content;
+    let new = fsops::workspace_read_file(b_rel)?.file.content;
+    Ok(diff_texts(&old, &new, options))
+}
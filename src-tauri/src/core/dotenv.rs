@@ -0,0 +1,254 @@
+use anyhow::{anyhow, Context, Result};
+use serde::Serialize;
+use std::fs;
+use std::path::Path;
+
+use super::{fsops, settings, workspace};
+
+// Shown instead of every value until it is revealed; the same for all, so lengths don't show.
+const MASK: &str = "••••••••";
+const MAX_ENV_FILES: usize = 200;
+
+#[derive(Debug, Clone, Serialize)]
+pub struct EnvEntry {
+    pub key: String,
+    /// `MASK`, or empty when the value is empty.
+    pub value: String,
+    /// 1-based line the entry starts on.
+    pub line: u32,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct EnvFile {
+    pub path: String,
+    /// In file order; a key defined twice is listed once, at its last definition, which wins.
+    pub entries: Vec<EnvEntry>,
+}
+
+// A `KEY=value` line, or several for a quoted value with line breaks.
+struct Parsed {
+    key: String,
+    value: String,
+    // Lines it spans, 0-based, end exclusive.
+    start: usize,
+    end: usize,
+}
+
+/// Whether a file name is an env file: `.env`, `.env.local` and the like, or `*.env`.
+pub fn is_env_file_name(name: &str) -> bool {
+    name == ".env" || name.starts_with(".env.") || (name.ends_with(".env") && name.len() > 4)
+}
+
+fn is_key(s: &str) -> bool {
+    let mut chars = s.chars();
+    chars.next().is_some_and(|c| c.is_ascii_alphabetic() || c == '_')
+        && chars.all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '.' || c == '-')
+}
+
+// `"..."` with `\n`, `\t`, `\"` and `\\` escapes, across lines until the closing quote.
+fn double_quoted(first: &str, lines: &[&str], i: &mut usize) -> Option<String> {
+    let mut out = String::new();
+    let mut text = first.to_string();
+    loop {
+        let mut chars = text.chars();
+        while let Some(c) = chars.next() {
+            match c {
+                '"' => return Some(out),
+                '\\' => match chars.next() {
+                    Some('n') => out.push('\n'),
+                    Some('r') => out.push('\r'),
+                    Some('t') => out.push('\t'),
+                    Some(other) => out.push(other),
+                    None => out.push('\\'),
+                },
+                _ => out.push(c),
+            }
+        }
+        *i += 1;
+        text = lines.get(*i)?.to_string();
+        out.push('\n');
+    }
+}
+
+fn parse(content: &str) -> Vec<Parsed> {
+    let lines: Vec<&str> = content.lines().collect();
+    let mut out = vec![];
+    let mut i = 0;
+    while i < lines.len() {
+        let start = i;
+        let line = lines[i].trim();
+        let line = line.strip_prefix("export ").map(str::trim_start).unwrap_or(line);
+        let Some((key, rest)) = line.split_once('=').filter(|_| !line.starts_with('#')) else {
+            i += 1;
+            continue;
+        };
+        let key = key.trim();
+        if !is_key(key) {
+            i += 1;
+            continue;
+        }
+        let rest = rest.trim_start();
+        let value = if let Some(q) = rest.strip_prefix('"') {
+            match double_quoted(q, &lines, &mut i) {
+                Some(v) => v,
+                // Unclosed: taken as written, up to the end of the line.
+                None => {
+                    i = start;
+                    rest.to_string()
+                }
+            }
+        } else if let Some(q) = rest.strip_prefix('\'') {
+            q.split_once('\'').map(|(v, _)| v).unwrap_or(q).to_string()
+        } else {
+            // Unquoted values end at a ` #` comment.
+            let v = rest.find(" #").map(|n| &rest[..n]).unwrap_or(rest);
+            v.trim_end().to_string()
+        };
+        i += 1;
+        out.push(Parsed {
+            key: key.to_string(),
+            value,
+            start,
+            end: i,
+        });
+    }
+    out
+}
+
+fn read(rel: &str) -> Result<String> {
+    Ok(fsops::workspace_read_file(rel)?.file.content)
+}
+
+/// Variables of an env file, as tasks and terminals load them.
+pub fn load(path: &Path) -> Result<Vec<(String, String)>> {
+    let content = fs::read_to_string(path).with_context(|| format!("read env file: {}", path.display()))?;
+    Ok(parse(&content).into_iter().map(|p| (p.key, p.value)).collect())
+}
+
+/// Variables of the env file at workspace path `rel`, for a terminal or task to start with.
+pub fn load_workspace(rel: &str) -> Result<Vec<(String, String)>> {
+    let (_, _, abs) = fsops::resolve(rel, false)?;
+    load(&abs)
+}
+
+/// Variables of the `env_file` setting's file, or none when it isn't set or can't be read.
+pub fn default_vars() -> Vec<(String, String)> {
+    let Some(rel) = settings::load().ok().and_then(|s| s.env_file).filter(|f| !f.trim().is_empty()) else {
+        return vec![];
+    };
+    match fsops::resolve(&rel, false) {
+        Ok((_, _, abs)) if abs.is_file() => load(&abs).unwrap_or_else(|e| {
+            tracing::warn!("env file {rel}: {e}");
+            vec![]
+        }),
+        _ => vec![],
+    }
+}
+
+/// Env files in the workspace, by path. They are found in every folder the file list shows,
+/// even when ignored themselves as they usually are.
+pub fn env_files_list() -> Result<Vec<String>> {
+    let mut out = vec![];
+    for root in workspace::open_roots()? {
+        for entry in root.walk(false, true).filter_map(|e| e.ok()) {
+            if !entry.file_type().is_some_and(|t| t.is_dir()) {
+                continue;
+            }
+            let Ok(dir) = fs::read_dir(entry.path()) else {
+                continue;
+            };
+            for file in dir.filter_map(|e| e.ok()) {
+                let name = file.file_name().to_string_lossy().to_string();
+                if !is_env_file_name(&name) || !file.file_type().is_ok_and(|t| t.is_file()) {
+                    continue;
+                }
+                let Ok(rel) = file.path().strip_prefix(&root.path).map(|p| p.to_string_lossy().replace('\\', "/")) else {
+                    continue;
+                };
+                out.push(root.workspace_path(&rel));
+                if out.len() >= MAX_ENV_FILES {
+                    out.sort();
+                    return Ok(out);
+                }
+            }
+        }
+    }
+    out.sort();
+    Ok(out)
+}
+
+/// The keys of an env file with their values masked; `env_file_reveal` gets one value.
+pub fn env_file_read(rel: &str) -> Result<EnvFile> {
+    let parsed = parse(&read(rel)?);
+    let mut entries: Vec<EnvEntry> = vec![];
+    for p in parsed {
+        entries.retain(|e| e.key != p.key);
+        entries.push(EnvEntry {
+            value: if p.value.is_empty() { String::new() } else { MASK.to_string() },
+            key: p.key,
+            line: p.start as u32 + 1,
+        });
+    }
+    entries.sort_by_key(|e| e.line);
+    Ok(EnvFile {
+        path: rel.to_string(),
+        entries,
+    })
+}
+
+pub fn env_file_reveal(rel: &str, key: &str) -> Result<String> {
+    parse(&read(rel)?)
+        .into_iter()
+        .rev()
+        .find(|p| p.key == key)
+        .map(|p| p.value)
+        .ok_or_else(|| anyhow!("{key} is not set in {rel}"))
+}
+
+fn quote(value: &str) -> String {
+    if value.chars().all(|c| c.is_ascii_alphanumeric() || "_-./:@%+,=".contains(c)) {
+        return value.to_string();
+    }
+    let escaped = value
+        .replace('\\', "\\\\")
+        .replace('"', "\\\"")
+        .replace('\n', "\\n")
+        .replace('\r', "\\r");
+    format!("\"{escaped}\"")
+}
+
+/// Sets `key` to `value`, or removes it when `value` is `None`, keeping the rest of the file
+/// as written. A key defined more than once keeps only its last definition; a new one is
+/// added at the end. Creates the file if needed.
+pub fn env_file_set(rel: &str, key: &str, value: Option<&str>) -> Result<EnvFile> {
+    let key = key.trim();
+    if !is_key(key) {
+        return Err(anyhow!("invalid variable name: {key}"));
+    }
+    let (_, _, abs) = fsops::resolve(rel, false)?;
+    let content = if abs.exists() { read(rel)? } else { String::new() };
+    let eol = if content.contains("\r\n") { "\r\n" } else { "\n" };
+    let mut lines: Vec<Option<String>> = content.lines().map(|l| Some(l.to_string())).collect();
+    let defs: Vec<Parsed> = parse(&content).into_iter().filter(|p| p.key == key).collect();
+    let new_line = value.map(|v| format!("{key}={}", quote(v)));
+    for (n, def) in defs.iter().enumerate() {
+        for line in &mut lines[def.start..def.end] {
+            *line = None;
+        }
+        if n == defs.len() - 1 {
+            lines[def.start] = new_line.clone();
+        }
+    }
+    if defs.is_empty() {
+        match new_line {
+            Some(l) => lines.push(Some(l)),
+            None => return env_file_read(rel),
+        }
+    }
+    let mut out = lines.into_iter().flatten().collect::<Vec<_>>().join(eol);
+    if !out.is_empty() {
+        out.push_str(eol);
+    }
+    fsops::workspace_write_file(rel, &out, None, false, false)?;
+    env_file_read(rel)
+}
@@ -0,0 +1,110 @@
+use anyhow::{anyhow, Result};
+use encoding_rs::{Encoding, UTF_16BE, UTF_16LE, UTF_8};
+use serde::{Deserialize, Serialize};
+
+/// A text file decoded to UTF-8, with what is needed to write it back byte-compatible.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TextFile {
+    pub content: String,
+    /// WHATWG encoding name, e.g. `UTF-8`, `UTF-16LE`, `windows-1252`, `Shift_JIS`.
+    pub encoding: String,
+    /// Whether the file started with a byte order mark.
+    pub bom: bool,
+}
+
+// UTF-16 text without a BOM shows up as ASCII with every other byte zero.
+fn sniff_utf16(bytes: &[u8]) -> Option<&'static Encoding> {
+    if bytes.len() < 4 || !bytes.len().is_multiple_of(2) {
+        return None;
+    }
+    let pairs = bytes.len() / 2;
+    let even_zero = bytes.iter().step_by(2).filter(|b| **b == 0).count();
+    let odd_zero = bytes.iter().skip(1).step_by(2).filter(|b| **b == 0).count();
+    if odd_zero * 10 >= pairs * 4 && even_zero == 0 {
+        Some(UTF_16LE)
+    } else if even_zero * 10 >= pairs * 4 && odd_zero == 0 {
+        Some(UTF_16BE)
+    } else {
+        None
+    }
+}
+
+/// Detects the encoding (BOM first, then UTF-8 validity, then statistical guessing) and
+/// returns the text as UTF-8. Files with NUL bytes that aren't UTF-16 are treated as binary.
+pub fn decode(bytes: &[u8]) -> Result<TextFile> {
+    if let Some((enc, bom_len)) = Encoding::for_bom(bytes) {
+        let (text, _) = enc.decode_without_bom_handling(&bytes[bom_len..]);
+        return Ok(TextFile {
+            content: text.into_owned(),
+            encoding: enc.name().to_string(),
+            bom: true,
+        });
+    }
+    if let Ok(text) = std::str::from_utf8(bytes) {
+        if !text.contains('\0') {
+            return Ok(TextFile {
+                content: text.to_string(),
+                encoding: UTF_8.name().to_string(),
+                bom: false,
+            });
+        }
+    }
+    if let Some(enc) = sniff_utf16(bytes) {
+        let (text, had_errors) = enc.decode_without_bom_handling(bytes);
+        if !had_errors {
+            return Ok(TextFile {
+                content: text.into_owned(),
+                encoding: enc.name().to_string(),
+                bom: false,
+            });
+        }
+    }
+    if bytes.contains(&0) {
+        return Err(anyhow!("not a text file"));
+    }
+
+    let mut detector = chardetng::EncodingDetector::new();
+    detector.feed(bytes, true);
+    let enc = detector.guess(None, true);
+    let (text, _) = enc.decode_without_bom_handling(bytes);
+    Ok(TextFile {
+        content: text.into_owned(),
+        encoding: enc.name().to_string(),
+        bom: false,
+    })
+}
+
+/// Encodes `text` for writing. Fails rather than substituting when a character has no
+/// representation in the target encoding.
+pub fn encode(text: &str, encoding: &str, bom: bool) -> Result<Vec<u8>> {
+    let enc = Encoding::for_label(encoding.trim().as_bytes()).ok_or_else(|| anyhow!("unknown encoding: {encoding}"))?;
+
+    // encoding_rs only decodes UTF-16, so it is encoded by hand.
+    if enc == UTF_16LE || enc == UTF_16BE {
+        let le = enc == UTF_16LE;
+        let mut out = Vec::with_capacity(text.len() * 2 + 2);
+        let units = bom.then_some(0xFEFF_u16).into_iter().chain(text.encode_utf16());
+        for unit in units {
+            out.extend_from_slice(&if le { unit.to_le_bytes() } else { unit.to_be_bytes() });
+        }
+        return Ok(out);
+    }
+
+    if enc == UTF_8 {
+        let mut out = Vec::with_capacity(text.len() + 3);
+        if bom {
+            out.extend_from_slice(b"\xEF\xBB\xBF");
+        }
+        out.extend_from_slice(text.as_bytes());
+        return Ok(out);
+    }
+
+    let (bytes, _, had_errors) = enc.encode(text);
+    if had_errors {
+        return Err(anyhow!(
+            "the file contains characters that cannot be saved as {}; save it as UTF-8 instead",
+            enc.name()
+        ));
+    }
+    Ok(bytes.into_owned())
+}
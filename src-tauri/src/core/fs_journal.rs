@@ -0,0 +1,245 @@
+use anyhow::{anyhow, Context, Result};
+use once_cell::sync::Lazy;
+use serde::Serialize;
+use std::collections::VecDeque;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use super::fsops;
+
+// Oldest entries are dropped past either limit.
+const MAX_ENTRIES: usize = 200;
+const MAX_TOTAL_BYTES: usize = 128 * 1024 * 1024;
+/// Largest file or folder whose contents are kept so deleting or overwriting it can be undone.
+pub const MAX_SNAPSHOT_BYTES: u64 = 8 * 1024 * 1024;
+// Saves of one file this close together share an entry, so autosave doesn't push everything
+// else out of reach and undo goes back to before the burst.
+const COALESCE_MS: u64 = 10_000;
+
+#[derive(Debug, Clone, Serialize)]
+pub struct FsHistoryEntry {
+    pub id: u64,
+    /// `write`, `create_dir`, `rename`, `delete` or `batch`.
+    pub kind: String,
+    /// Workspace paths involved; `from` then `to` for a rename.
+    pub paths: Vec<String>,
+    /// Unix time in milliseconds.
+    pub at: u64,
+    /// False when the old contents were too large to keep.
+    pub undoable: bool,
+}
+
+/// One step that takes back part of a change.
+pub enum Inverse {
+    /// Put back a file's previous bytes.
+    Restore { path: PathBuf, bytes: Vec<u8> },
+    /// Remove a file the change created.
+    RemoveFile(PathBuf),
+    /// Remove directories the change created (outermost first) if they are still empty.
+    RemoveDirs(Vec<PathBuf>),
+    Rename { from: PathBuf, to: PathBuf },
+    /// Recreate a deleted file or folder from a snapshot.
+    Recreate { dirs: Vec<PathBuf>, files: Vec<(PathBuf, Vec<u8>)> },
+}
+
+impl Inverse {
+    pub fn apply(&self) -> Result<()> {
+        match self {
+            Inverse::Restore { path, bytes } => fsops::write_atomic(path, bytes, fs::metadata(path).ok().as_ref()),
+            Inverse::RemoveFile(path) => {
+                fs::remove_file(path).with_context(|| format!("delete file: {}", path.display()))
+            }
+            Inverse::RemoveDirs(dirs) => {
+                for d in dirs.iter().rev() {
+                    let _ = fs::remove_dir(d);
+                }
+                Ok(())
+            }
+            Inverse::Rename { from, to } => {
+                if to.exists() {
+                    return Err(anyhow!("{} exists again", to.display()));
+                }
+                fs::rename(from, to).with_context(|| format!("rename {} -> {}", from.display(), to.display()))
+            }
+            Inverse::Recreate { dirs, files } => {
+                if let Some((p, _)) = files.iter().find(|(p, _)| p.exists()) {
+                    return Err(anyhow!("{} exists again", p.display()));
+                }
+                for d in dirs {
+                    fs::create_dir_all(d).with_context(|| format!("create dir: {}", d.display()))?;
+                }
+                for (p, bytes) in files {
+                    if let Some(parent) = p.parent() {
+                        fs::create_dir_all(parent).with_context(|| format!("create dir: {}", parent.display()))?;
+                    }
+                    fsops::write_atomic(p, bytes, None)?;
+                }
+                Ok(())
+            }
+        }
+    }
+
+    fn size(&self) -> usize {
+        match self {
+            Inverse::Restore { bytes, .. } => bytes.len(),
+            Inverse::Recreate { files, .. } => files.iter().map(|(_, b)| b.len()).sum(),
+            _ => 0,
+        }
+    }
+}
+
+/// Something that must still hold for an undo to be safe.
+pub enum Guard {
+    /// Nothing else has written the file since (its version is unchanged).
+    Version(PathBuf, String),
+    /// Nothing has been created at the path since.
+    Absent(PathBuf),
+}
+
+impl Guard {
+    fn check(&self) -> Result<()> {
+        match self {
+            Guard::Version(path, version) => {
+                let now = fs::metadata(path).ok().map(|m| fsops::file_version(&m));
+                if now.as_deref() != Some(version.as_str()) {
+                    return Err(anyhow!("{} has changed since", path.display()));
+                }
+            }
+            Guard::Absent(path) => {
+                if path.exists() || path.is_symlink() {
+                    return Err(anyhow!("{} exists again", path.display()));
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+struct Recorded {
+    entry: FsHistoryEntry,
+    /// Applied last to first. `None` when the change can't be reverted.
+    undo: Option<Vec<Inverse>>,
+    guards: Vec<Guard>,
+    bytes: usize,
+}
+
+static JOURNAL: Lazy<Mutex<(u64, VecDeque<Recorded>)>> = Lazy::new(|| Mutex::new((0, VecDeque::new())));
+
+fn now_ms() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_millis() as u64)
+        .unwrap_or(0)
+}
+
+/// Adds a change to the journal. `undo` holds the steps that revert it, in the order they
+/// were taken, or `None` if it can't be reverted.
+pub fn record(kind: &str, paths: Vec<String>, undo: Option<Vec<Inverse>>, guards: Vec<Guard>) {
+    let at = now_ms();
+    let mut journal = JOURNAL.lock().unwrap_or_else(|e| e.into_inner());
+    let (next_id, entries) = &mut *journal;
+
+    if kind == "write" {
+        if let Some(last) = entries.back_mut() {
+            if last.entry.kind == "write" && last.entry.paths == paths && at.saturating_sub(last.entry.at) < COALESCE_MS {
+                // Keep the older contents; only the guard moves to the newest save.
+                last.entry.at = at;
+                last.guards = guards;
+                return;
+            }
+        }
+    }
+
+    *next_id += 1;
+    let bytes = undo.as_ref().map(|u| u.iter().map(Inverse::size).sum()).unwrap_or(0);
+    entries.push_back(Recorded {
+        entry: FsHistoryEntry {
+            id: *next_id,
+            kind: kind.to_string(),
+            paths,
+            at,
+            undoable: undo.is_some(),
+        },
+        undo,
+        guards,
+        bytes,
+    });
+    let mut total: usize = entries.iter().map(|r| r.bytes).sum();
+    while entries.len() > MAX_ENTRIES || (total > MAX_TOTAL_BYTES && entries.len() > 1) {
+        if let Some(old) = entries.pop_front() {
+            total -= old.bytes;
+        }
+    }
+}
+
+/// Contents of the file or folder at `src`, as a step that recreates it at `at`. `None` if it
+/// is larger than `MAX_SNAPSHOT_BYTES` or contains symlinks, which can't be restored faithfully.
+pub fn snapshot(src: &Path, at: &Path) -> Option<Inverse> {
+    let meta = fs::symlink_metadata(src).ok()?;
+    if meta.is_file() {
+        if meta.len() > MAX_SNAPSHOT_BYTES {
+            return None;
+        }
+        return Some(Inverse::Recreate {
+            dirs: vec![],
+            files: vec![(at.to_path_buf(), fs::read(src).ok()?)],
+        });
+    }
+    if !meta.is_dir() {
+        return None;
+    }
+
+    let mut dirs = vec![at.to_path_buf()];
+    let mut files = vec![];
+    let mut total = 0u64;
+    let mut stack = vec![src.to_path_buf()];
+    while let Some(dir) = stack.pop() {
+        for e in fs::read_dir(&dir).ok()? {
+            let e = e.ok()?;
+            let ft = e.file_type().ok()?;
+            let dest = at.join(e.path().strip_prefix(src).ok()?);
+            if ft.is_dir() {
+                dirs.push(dest);
+                stack.push(e.path());
+            } else if ft.is_file() {
+                total += e.metadata().ok()?.len();
+                if total > MAX_SNAPSHOT_BYTES {
+                    return None;
+                }
+                files.push((dest, fs::read(e.path()).ok()?));
+            } else {
+                return None;
+            }
+        }
+    }
+    Some(Inverse::Recreate { dirs, files })
+}
+
+/// Most recent changes first.
+pub fn fsops_history(limit: usize) -> Vec<FsHistoryEntry> {
+    let journal = JOURNAL.lock().unwrap_or_else(|e| e.into_inner());
+    journal.1.iter().rev().take(limit).map(|r| r.entry.clone()).collect()
+}
+
+/// Reverts the most recent change and returns it. The entry leaves the history either way;
+/// if it can't be undone (too large, or the files changed since) the error says why.
+pub fn fsops_undo_last() -> Result<FsHistoryEntry> {
+    let rec = {
+        let mut journal = JOURNAL.lock().unwrap_or_else(|e| e.into_inner());
+        journal.1.pop_back().ok_or_else(|| anyhow!("nothing to undo"))?
+    };
+    let label = format!("{} {}", rec.entry.kind.replace('_', " "), rec.entry.paths.join(" -> "));
+
+    let Some(undo) = rec.undo else {
+        return Err(anyhow!("can't undo {label}: the old contents were too large to keep"));
+    };
+    for g in &rec.guards {
+        g.check().map_err(|e| anyhow!("can't undo {label}: {e}"))?;
+    }
+    for step in undo.iter().rev() {
+        step.apply().map_err(|e| anyhow!("undoing {label} stopped partway: {e:#}"))?;
+    }
+    Ok(rec.entry)
+}
@@ -0,0 +1,302 @@
+use anyhow::{Context, Result};
+use notify::event::{ModifyKind, RenameMode};
+use notify::{EventKind, RecursiveMode, Watcher};
+use once_cell::sync::{Lazy, OnceCell};
+use serde::Serialize;
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::mpsc::{self, RecvTimeoutError};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+use tauri::{Emitter, Listener};
+
+use super::fsops;
+use super::search_index;
+use super::settings;
+use super::todos;
+use super::workspace::{self, OpenRoot};
+
+pub const FS_CREATED_EVENT: &str = "fs:created";
+pub const FS_MODIFIED_EVENT: &str = "fs:modified";
+pub const FS_DELETED_EVENT: &str = "fs:deleted";
+pub const FS_RENAMED_EVENT: &str = "fs:renamed";
+
+// Events for a path are held until it has been quiet this long, so a save that truncates,
+// writes and chmods shows up as a single `fs:modified`.
+const DEBOUNCE: Duration = Duration::from_millis(200);
+// Upper bound on how long a busy tree (a build writing constantly) can delay delivery.
+const MAX_DELAY: Duration = Duration::from_secs(1);
+
+#[derive(Debug, Clone, Serialize)]
+pub struct FsPathEvent {
+    /// Workspace path, `rootName/rel` in a multi-root workspace.
+    pub path: String,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct FsRenameEvent {
+    pub from: String,
+    pub to: String,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Change {
+    Created,
+    Modified,
+    Deleted,
+}
+
+// Net effect of two changes to the same path inside one debounce window; `None` means the
+// path came and went and nothing needs reporting.
+fn merge(prev: Change, next: Change) -> Option<Change> {
+    match (prev, next) {
+        (Change::Created, Change::Deleted) => None,
+        (Change::Created, _) => Some(Change::Created),
+        (Change::Deleted, Change::Created) => Some(Change::Modified),
+        (_, next) => Some(next),
+    }
+}
+
+struct WatchedRoot {
+    root: OpenRoot,
+    // Some backends (FSEvents) report canonical paths, so match against both spellings.
+    canonical: Option<PathBuf>,
+}
+
+impl WatchedRoot {
+    fn workspace_path(&self, abs: &Path) -> Option<String> {
+        let rel = abs
+            .strip_prefix(&self.root.path)
+            .ok()
+            .or_else(|| self.canonical.as_ref().and_then(|c| abs.strip_prefix(c).ok()))?;
+        if rel.components().any(|c| {
+            let s = c.as_os_str().to_string_lossy();
+            s == "node_modules" || s == ".git" || s == "dist" || s == "target" || fsops::is_scratch_name(&s)
+        }) {
+            return None;
+        }
+        let rel = rel.to_string_lossy().replace('\\', "/");
+        if rel.is_empty() {
+            return None;
+        }
+        Some(self.root.workspace_path(&rel))
+    }
+}
+
+fn to_workspace_path(roots: &[WatchedRoot], abs: &Path) -> Option<String> {
+    roots.iter().find_map(|r| r.workspace_path(abs))
+}
+
+static APP: OnceCell<tauri::AppHandle> = OnceCell::new();
+// Dropping the watchers closes the channel, which ends the debounce thread of the old set.
+static WATCHERS: Lazy<Mutex<Vec<notify::RecommendedWatcher>>> = Lazy::new(|| Mutex::new(vec![]));
+
+/// Starts watching the open workspace and keeps following it as roots are set, added or
+/// removed. Called once from app setup.
+pub fn watch(app: tauri::AppHandle) -> Result<()> {
+    let _ = APP.set(app.clone());
+    app.listen_any(settings::SETTINGS_CHANGED_EVENT, |event| {
+        let keys = settings::changed_keys_in(event.payload());
+        if keys.iter().any(|k| k == "workspace_root" || k == "workspace_extra_roots") {
+            // Listeners run inside `emit`, which may still hold the settings lock.
+            std::thread::spawn(|| {
+                let _ = restart();
+            });
+        }
+    });
+    restart()
+}
+
+/// Replaces the current watchers with ones for the workspace roots now in settings. With no
+/// workspace open this just stops watching.
+pub fn restart() -> Result<()> {
+    let mut watchers = WATCHERS.lock().unwrap_or_else(|e| e.into_inner());
+    watchers.clear();
+
+    let Some(app) = APP.get().cloned() else {
+        return Ok(());
+    };
+    let roots = workspace::open_roots().unwrap_or_default();
+    if roots.is_empty() {
+        return Ok(());
+    }
+
+    let (tx, rx) = mpsc::channel::<notify::Event>();
+    for root in &roots {
+        let tx = tx.clone();
+        let mut watcher = notify::recommended_watcher(move |res: notify::Result<notify::Event>| {
+            if let Ok(ev) = res {
+                let _ = tx.send(ev);
+            }
+        })
+        .context("create workspace watcher")?;
+        watcher
+            .watch(&root.path, RecursiveMode::Recursive)
+            .with_context(|| format!("watch workspace root: {}", root.path.display()))?;
+        watchers.push(watcher);
+    }
+
+    let roots: Vec<WatchedRoot> = roots
+        .into_iter()
+        .map(|root| WatchedRoot {
+            canonical: root.path.canonicalize().ok(),
+            root,
+        })
+        .collect();
+    std::thread::spawn(move || debounce_loop(app, roots, rx));
+    Ok(())
+}
+
+#[derive(Default)]
+struct Pending {
+    // Insertion order is kept so events go out in the order they happened.
+    order: Vec<String>,
+    changes: HashMap<String, Change>,
+    renames: Vec<FsRenameEvent>,
+    since: Option<Instant>,
+}
+
+impl Pending {
+    fn push(&mut self, path: String, change: Change) {
+        self.since.get_or_insert_with(Instant::now);
+        match self.changes.get(&path).copied() {
+            Some(prev) => match merge(prev, change) {
+                Some(c) => {
+                    self.changes.insert(path, c);
+                }
+                None => {
+                    self.changes.remove(&path);
+                    self.order.retain(|p| p != &path);
+                }
+            },
+            None => {
+                self.order.push(path.clone());
+                self.changes.insert(path, change);
+            }
+        }
+    }
+
+    fn rename(&mut self, from: String, to: String) {
+        self.since.get_or_insert_with(Instant::now);
+        self.renames.push(FsRenameEvent { from, to });
+    }
+
+    fn is_empty(&self) -> bool {
+        self.order.is_empty() && self.renames.is_empty()
+    }
+
+    fn flush(&mut self, app: &tauri::AppHandle) {
+        let pending = std::mem::take(self);
+        if !pending.renames.is_empty() || pending.changes.values().any(|c| !matches!(c, Change::Modified)) {
+            super::fuzzy::invalidate();
+        }
+        let (index_loaded, todos_loaded) = (search_index::any_loaded(), todos::is_loaded());
+        if index_loaded || todos_loaded {
+            let mut modified = vec![];
+            let mut removed = vec![];
+            for path in &pending.order {
+                match pending.changes.get(path) {
+                    Some(Change::Deleted) => removed.push(path.clone()),
+                    Some(_) => modified.push(path.clone()),
+                    None => {}
+                }
+            }
+            for rename in &pending.renames {
+                removed.push(rename.from.clone());
+                modified.push(rename.to.clone());
+            }
+            // Off this thread: re-indexing reads files and may wait for a running search.
+            if todos_loaded {
+                let (app, modified, removed) = (app.clone(), modified.clone(), removed.clone());
+                std::thread::spawn(move || todos::note_changes(&app, modified, removed));
+            }
+            if index_loaded {
+                std::thread::spawn(move || search_index::note_changes(modified, removed));
+            }
+        }
+        for rename in pending.renames {
+            let _ = app.emit(FS_RENAMED_EVENT, rename);
+        }
+        for path in pending.order {
+            let Some(change) = pending.changes.get(&path) else {
+                continue;
+            };
+            let name = match change {
+                Change::Created => FS_CREATED_EVENT,
+                Change::Modified => FS_MODIFIED_EVENT,
+                Change::Deleted => FS_DELETED_EVENT,
+            };
+            let _ = app.emit(name, FsPathEvent { path });
+        }
+    }
+}
+
+fn record(pending: &mut Pending, roots: &[WatchedRoot], ev: notify::Event) {
+    let change = match ev.kind {
+        EventKind::Create(_) => Change::Created,
+        EventKind::Remove(_) => Change::Deleted,
+        EventKind::Modify(ModifyKind::Name(RenameMode::Both)) if ev.paths.len() == 2 => {
+            let from = to_workspace_path(roots, &ev.paths[0]);
+            let to = to_workspace_path(roots, &ev.paths[1]);
+            // An atomic save renaming its temp file over the real one.
+            let saved = ev.paths[0]
+                .file_name()
+                .is_some_and(|n| n.to_string_lossy().ends_with(fsops::WRITE_TEMP_SUFFIX));
+            match (from, to) {
+                (Some(from), Some(to)) => pending.rename(from, to),
+                (None, Some(to)) if saved => pending.push(to, Change::Modified),
+                // Moved in from (or out to) an ignored or unwatched location.
+                (None, Some(to)) => pending.push(to, Change::Created),
+                (Some(from), None) => pending.push(from, Change::Deleted),
+                (None, None) => {}
+            }
+            return;
+        }
+        // Unpaired halves of a rename: the other side is outside the workspace.
+        EventKind::Modify(ModifyKind::Name(RenameMode::From)) => Change::Deleted,
+        EventKind::Modify(ModifyKind::Name(RenameMode::To)) => Change::Created,
+        // Backends that cannot tell which side they saw: check the disk.
+        EventKind::Modify(ModifyKind::Name(_)) => {
+            for p in &ev.paths {
+                if let Some(wp) = to_workspace_path(roots, p) {
+                    pending.push(wp, if p.exists() { Change::Created } else { Change::Deleted });
+                }
+            }
+            return;
+        }
+        EventKind::Modify(_) => Change::Modified,
+        EventKind::Access(_) | EventKind::Any | EventKind::Other => return,
+    };
+    for p in &ev.paths {
+        if let Some(wp) = to_workspace_path(roots, p) {
+            pending.push(wp, change);
+        }
+    }
+}
+
+fn debounce_loop(app: tauri::AppHandle, roots: Vec<WatchedRoot>, rx: mpsc::Receiver<notify::Event>) {
+    let mut pending = Pending::default();
+    loop {
+        match rx.recv_timeout(DEBOUNCE) {
+            Ok(ev) => {
+                record(&mut pending, &roots, ev);
+                if pending.since.is_some_and(|t| t.elapsed() >= MAX_DELAY) {
+                    pending.flush(&app);
+                }
+            }
+            Err(RecvTimeoutError::Timeout) => {
+                if !pending.is_empty() {
+                    pending.flush(&app);
+                } else {
+                    pending.since = None;
+                }
+            }
+            Err(RecvTimeoutError::Disconnected) => {
+                if !pending.is_empty() {
+                    pending.flush(&app);
+                }
+                return;
+            }
+        }
+    }
+}
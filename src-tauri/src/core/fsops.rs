@@ -1,37 +1,41 @@
 use anyhow::{anyhow, Context, Result};
+use base64::Engine as _;
 use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
 use std::fs;
-use std::path::{Component, PathBuf};
-use std::collections::HashSet;
-use walkdir::WalkDir;
+use std::hash::{Hash, Hasher};
+use std::io::{self, BufRead, BufReader, Read, Seek, SeekFrom, Write};
+use std::path::{Component, Path, PathBuf};
+use std::thread;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use tauri::{AppHandle, Emitter};
+use std::collections::{HashMap, HashSet};
 
-use super::settings;
+use super::encoding::{self, TextFile};
+use super::fs_journal::{self, Guard, Inverse};
+use super::settings::{self, BackupMode};
+use super::protected_paths;
+use super::workspace;
+use super::write_policy;
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct DirEntryInfo {
     pub path: String,
     pub name: String,
     pub is_dir: bool,
+    /// Whether a folder has anything in it that a listing would show.
+    pub has_children: bool,
+    /// How many entries a listing of the folder would return, up to `CHILD_COUNT_CAP`;
+    /// `None` for files.
+    pub child_count: Option<u32>,
+    /// The folder's own listing, when it was within the requested depth.
+    pub children: Option<Vec<DirEntryInfo>>,
 }
 
-fn workspace_root_path() -> Result<PathBuf> {
-    let s = settings::load()?;
-    let root = s
-        .workspace_root
-        .as_deref()
-        .map(|v| v.trim())
-        .filter(|v| !v.is_empty())
-        .ok_or_else(|| anyhow!("no workspace is open"))?;
-
-    let pb = PathBuf::from(root);
-    if !pb.exists() {
-        return Err(anyhow!("workspace path does not exist"));
-    }
-    if !pb.is_dir() {
-        return Err(anyhow!("workspace path is not a directory"));
-    }
-    Ok(pb)
-}
+/// Child counts stop here so a huge folder doesn't slow down listing its parent.
+pub const CHILD_COUNT_CAP: u32 = 10_000;
+// Entries one deep listing may return; deeper folders beyond it are left for later calls.
+const MAX_DEEP_ENTRIES: usize = 20_000;
 
 fn validate_relative(path: &str, allow_empty: bool) -> Result<PathBuf> {
     let trimmed = path.trim();
@@ -61,22 +65,107 @@ fn validate_relative(path: &str, allow_empty: bool) -> Result<PathBuf> {
     Ok(pb)
 }
 
-fn abs_path(rel: &str, allow_empty: bool) -> Result<PathBuf> {
-    let root = workspace_root_path()?;
+/// Resolves a workspace path to the root it lives in, the path within that root and the
+/// absolute path. With several roots the first segment names the root
+/// (`rootName/relative/path`). Paths that symlinks lead out of the root are refused.
+pub fn resolve(rel: &str, allow_empty: bool) -> Result<(workspace::OpenRoot, String, PathBuf)> {
+    let (root, inner, abs) = resolve_lexical(rel, allow_empty)?;
+    workspace::ensure_contained(&root.path, &abs)?;
+    Ok((root, inner, abs))
+}
+
+// Like `resolve`, for operations on the directory entry itself (delete, rename): a symlink
+// there is moved or removed, not followed, so only the folder it sits in has to be inside.
+fn resolve_entry(rel: &str) -> Result<(workspace::OpenRoot, String, PathBuf)> {
+    let (root, inner, abs) = resolve_lexical(rel, false)?;
+    if let Some(parent) = abs.parent() {
+        workspace::ensure_contained(&root.path, parent)?;
+    }
+    Ok((root, inner, abs))
+}
+
+fn resolve_lexical(rel: &str, allow_empty: bool) -> Result<(workspace::OpenRoot, String, PathBuf)> {
     let rel = validate_relative(rel, allow_empty)?;
-    Ok(root.join(rel))
+    let (root, inner) = workspace::split_workspace_path(&rel.to_string_lossy())?
+        .ok_or_else(|| anyhow!("path must start with a workspace root name"))?;
+    let abs = root.path.join(&inner);
+    Ok((root, inner, abs))
+}
+
+fn abs_path(rel: &str, allow_empty: bool) -> Result<PathBuf> {
+    resolve(rel, allow_empty).map(|(_, _, abs)| abs)
 }
 
-pub fn workspace_list_dir(rel_dir: Option<&str>) -> Result<Vec<DirEntryInfo>> {
+/// Entries of a workspace folder, folders first. Hidden entries are left out unless
+/// `include_hidden` (default: the `show_hidden_files` setting). With `depth` above 1, folders
+/// also carry their own listings that many levels down, as far as `MAX_DEEP_ENTRIES` allows.
+pub fn workspace_list_dir(rel_dir: Option<&str>, include_hidden: Option<bool>, depth: u32) -> Result<Vec<DirEntryInfo>> {
     let rel = rel_dir.unwrap_or("");
+    let include_hidden = workspace::include_hidden(include_hidden);
+    let mut budget = MAX_DEEP_ENTRIES;
+    if validate_relative(rel, true)?.as_os_str().is_empty()
+        && workspace::split_workspace_path("")?.is_none()
+    {
+        // Top level of a multi-root workspace: one folder entry per root.
+        let mut out = vec![];
+        for root in workspace::open_roots()? {
+            let Some(name) = root.prefix else { continue };
+            out.push(dir_entry_info(name.clone(), name, &root.path, include_hidden, depth, &mut budget)?);
+        }
+        return Ok(out);
+    }
     let dir = abs_path(rel, true)?;
+    list_dir_at(rel, &dir, include_hidden, depth, &mut budget)
+}
+
+fn dir_entry_info(
+    path: String,
+    name: String,
+    abs: &Path,
+    include_hidden: bool,
+    depth: u32,
+    budget: &mut usize,
+) -> Result<DirEntryInfo> {
+    let children = if depth > 1 && *budget > 0 {
+        Some(list_dir_at(&path, abs, include_hidden, depth - 1, budget)?)
+    } else {
+        None
+    };
+    let child_count = match &children {
+        Some(c) => (c.len() as u32).min(CHILD_COUNT_CAP),
+        None => count_children(abs, include_hidden),
+    };
+    Ok(DirEntryInfo {
+        path,
+        name,
+        is_dir: true,
+        has_children: child_count > 0,
+        child_count: Some(child_count),
+        children,
+    })
+}
+
+// Unreadable folders count as empty; listing them reports the error.
+fn count_children(dir: &Path, include_hidden: bool) -> u32 {
+    let Ok(rd) = fs::read_dir(dir) else {
+        return 0;
+    };
+    rd.filter_map(|e| e.ok())
+        .filter(|e| include_hidden || !workspace::is_hidden_entry(e))
+        .take(CHILD_COUNT_CAP as usize)
+        .count() as u32
+}
 
+fn list_dir_at(rel: &str, dir: &Path, include_hidden: bool, depth: u32, budget: &mut usize) -> Result<Vec<DirEntryInfo>> {
     let mut out = Vec::new();
     let mut seen = HashSet::<String>::new();
-    for e in fs::read_dir(&dir).with_context(|| format!("list dir: {}", dir.display()))? {
+    for e in fs::read_dir(dir).with_context(|| format!("list dir: {}", dir.display()))? {
         let e = e.with_context(|| format!("list dir entry: {}", dir.display()))?;
         let ft = e.file_type().with_context(|| "file_type")?;
         let name = e.file_name().to_string_lossy().to_string();
+        if !include_hidden && workspace::is_hidden_entry(&e) {
+            continue;
+        }
 
         let child_rel = if rel.is_empty() {
             name.clone()
@@ -86,10 +175,18 @@ pub fn workspace_list_dir(rel_dir: Option<&str>) -> Result<Vec<DirEntryInfo>> {
         };
 
         if seen.insert(child_rel.clone()) {
-            out.push(DirEntryInfo {
-                path: child_rel,
-                name,
-                is_dir: ft.is_dir(),
+            *budget = budget.saturating_sub(1);
+            out.push(if ft.is_dir() {
+                dir_entry_info(child_rel, name, &e.path(), include_hidden, depth, budget)?
+            } else {
+                DirEntryInfo {
+                    path: child_rel,
+                    name,
+                    is_dir: false,
+                    has_children: false,
+                    child_count: None,
+                    children: None,
+                }
             });
         }
     }
@@ -105,95 +202,944 @@ pub fn workspace_list_dir(rel_dir: Option<&str>) -> Result<Vec<DirEntryInfo>> {
     Ok(out)
 }
 
-pub fn workspace_list_files(max_files: usize) -> Result<Vec<String>> {
-    let root = workspace_root_path()?;
-    let mut out: Vec<String> = Vec::new();
+// Visits up to `max_files` workspace paths of files, in walk order, each once.
+fn walk_workspace_files(
+    include_ignored: Option<bool>,
+    include_hidden: Option<bool>,
+    max_files: usize,
+    mut visit: impl FnMut(String),
+) -> Result<()> {
+    let include_ignored = workspace::include_ignored(include_ignored);
+    let include_hidden = workspace::include_hidden(include_hidden);
     let mut seen = HashSet::<String>::new();
 
-    for entry in WalkDir::new(&root)
-        .follow_links(false)
-        .into_iter()
-        .filter_map(|e| e.ok())
-    {
-        if out.len() >= max_files {
-            break;
+    for open_root in workspace::open_roots()? {
+        let root = &open_root.path;
+        for entry in open_root.walk(include_ignored, include_hidden).filter_map(|e| e.ok()) {
+            if seen.len() >= max_files {
+                return Ok(());
+            }
+            if !entry.file_type().is_some_and(|t| t.is_file()) {
+                continue;
+            }
+
+            let rel = entry
+                .path()
+                .strip_prefix(root)
+                .with_context(|| format!("strip prefix: {}", root.display()))?
+                .to_string_lossy()
+                .replace('\\', "/");
+            if rel.trim().is_empty() {
+                continue;
+            }
+            let rel = open_root.workspace_path(&rel);
+            if seen.insert(rel.clone()) {
+                visit(rel);
+            }
         }
+    }
+    Ok(())
+}
 
-        let ft = entry.file_type();
-        if !ft.is_file() {
-            continue;
+/// Files in the workspace, sorted case-insensitively. Ignored files are left out unless
+/// `include_ignored` (default: the `include_ignored_files` setting), and hidden files and
+/// folders unless `include_hidden` (default: the `show_hidden_files` setting).
+pub fn workspace_list_files(max_files: usize, include_ignored: Option<bool>, include_hidden: Option<bool>) -> Result<Vec<String>> {
+    let mut out: Vec<String> = Vec::new();
+    walk_workspace_files(include_ignored, include_hidden, max_files, |p| out.push(p))?;
+    out.sort_by(|a, b| a.to_lowercase().cmp(&b.to_lowercase()));
+    Ok(out)
+}
+
+pub const FILES_BATCH_EVENT: &str = "workspace:files-batch";
+
+#[derive(Debug, Clone, Serialize)]
+pub struct FilesBatch {
+    /// The id passed to `workspace_list_files_stream`, to tell overlapping listings apart.
+    pub request_id: u64,
+    pub files: Vec<String>,
+    /// Set on the last batch, which may be empty.
+    pub done: bool,
+}
+
+/// Like `workspace_list_files`, but sends paths in `FILES_BATCH_EVENT` batches as the walk
+/// finds them (unsorted) rather than in one response. Returns how many were sent.
+pub fn workspace_list_files_stream(
+    app: &AppHandle,
+    request_id: u64,
+    max_files: usize,
+    batch_size: usize,
+    include_ignored: Option<bool>,
+    include_hidden: Option<bool>,
+) -> Result<usize> {
+    let mut batch = Vec::with_capacity(batch_size);
+    let mut total = 0;
+    walk_workspace_files(include_ignored, include_hidden, max_files, |path| {
+        batch.push(path);
+        total += 1;
+        if batch.len() >= batch_size {
+            let files = std::mem::replace(&mut batch, Vec::with_capacity(batch_size));
+            let _ = app.emit(FILES_BATCH_EVENT, FilesBatch { request_id, files, done: false });
         }
+    })?;
+    let _ = app.emit(FILES_BATCH_EVENT, FilesBatch { request_id, files: batch, done: true });
+    Ok(total)
+}
 
-        let path = entry.path();
+/// A decoded text file plus the version token to hand back to `workspace_write_file_if_unchanged`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct VersionedText {
+    #[serde(flatten)]
+    pub file: TextFile,
+    pub version: String,
+}
 
-        if path.components().any(|c| {
-            let s = c.as_os_str().to_string_lossy().to_lowercase();
-            s == "node_modules" || s == ".git" || s == "dist" || s == "target"
-        }) {
-            continue;
+/// Version token for a file on disk: modification time and size. It is cheap enough to check on
+/// every save; a same-size rewrite within the filesystem's timestamp resolution goes unnoticed.
+pub fn file_version(meta: &fs::Metadata) -> String {
+    let mtime = meta
+        .modified()
+        .ok()
+        .and_then(|t| t.duration_since(UNIX_EPOCH).ok())
+        .map(|d| d.as_nanos())
+        .unwrap_or(0);
+    format!("{mtime:x}-{:x}", meta.len())
+}
+
+fn read_versioned(path: &Path) -> Result<VersionedText> {
+    let mut f = fs::File::open(path).with_context(|| format!("read file: {}", path.display()))?;
+    // Taken before reading: if the file changes mid-read the token is stale, so the next
+    // conditional write reports a conflict instead of trusting a torn read.
+    let meta = f.metadata().with_context(|| format!("read file: {}", path.display()))?;
+    let mut bytes = Vec::with_capacity(meta.len() as usize);
+    f.read_to_end(&mut bytes).with_context(|| format!("read file: {}", path.display()))?;
+    Ok(VersionedText {
+        file: encoding::decode(&bytes).with_context(|| format!("read file: {}", path.display()))?,
+        version: file_version(&meta),
+    })
+}
+
+/// Reads a text file in whatever encoding it uses and returns it as UTF-8 together with
+/// that encoding and its version, so a later write can restore the original bytes.
+pub fn workspace_read_file(rel_path: &str) -> Result<VersionedText> {
+    let path = abs_path(rel_path, false)?;
+    read_versioned(&path)
+}
+
+// Larger files are described but not sent: base64 over IPC gets slow well before this.
+const MAX_BINARY_READ: u64 = 25 * 1024 * 1024;
+// Enough for every signature `infer` knows.
+const SNIFF_BYTES: usize = 8192;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BinaryFile {
+    pub mime: String,
+    pub size: u64,
+    /// File contents, or `None` when the file is over the read limit.
+    pub base64: Option<String>,
+}
+
+fn mime_from_extension(path: &std::path::Path) -> Option<&'static str> {
+    let ext = path.extension()?.to_str()?.to_ascii_lowercase();
+    Some(match ext.as_str() {
+        "svg" => "image/svg+xml",
+        "json" => "application/json",
+        "js" | "mjs" | "cjs" => "text/javascript",
+        "ts" | "tsx" | "jsx" => "text/plain",
+        "html" | "htm" => "text/html",
+        "css" => "text/css",
+        "csv" => "text/csv",
+        "md" => "text/markdown",
+        "txt" | "log" => "text/plain",
+        "xml" => "application/xml",
+        _ => return None,
+    })
+}
+
+fn detect_mime(path: &std::path::Path, head: &[u8]) -> String {
+    if let Some(kind) = infer::get(head) {
+        return kind.mime_type().to_string();
+    }
+    if let Some(m) = mime_from_extension(path) {
+        return m.to_string();
+    }
+    // A cut-off multi-byte character at the end of the sample is still text.
+    let text = match std::str::from_utf8(head) {
+        Ok(_) => true,
+        Err(e) => e.error_len().is_none(),
+    };
+    if text && !head.contains(&0) {
+        "text/plain".to_string()
+    } else {
+        "application/octet-stream".to_string()
+    }
+}
+
+/// Reads any file as base64 with a sniffed MIME type, for previews of images and other
+/// non-text files.
+pub fn workspace_read_file_binary(rel_path: &str) -> Result<BinaryFile> {
+    let path = abs_path(rel_path, false)?;
+    let mut f = fs::File::open(&path).with_context(|| format!("read file: {}", path.display()))?;
+    let size = f.metadata().with_context(|| format!("stat file: {}", path.display()))?.len();
+
+    let mut head = Vec::with_capacity(SNIFF_BYTES);
+    (&mut f)
+        .take(SNIFF_BYTES as u64)
+        .read_to_end(&mut head)
+        .with_context(|| format!("read file: {}", path.display()))?;
+    let mime = detect_mime(&path, &head);
+    if size > MAX_BINARY_READ {
+        return Ok(BinaryFile { mime, size, base64: None });
+    }
+
+    let mut bytes = head;
+    f.read_to_end(&mut bytes)
+        .with_context(|| format!("read file: {}", path.display()))?;
+    Ok(BinaryFile {
+        mime,
+        size: bytes.len() as u64,
+        base64: Some(base64::engine::general_purpose::STANDARD.encode(&bytes)),
+    })
+}
+
+// Per-call caps for ranged reads, so one request can't pull a whole huge file through IPC.
+const MAX_RANGE_LINES: usize = 100_000;
+const MAX_RANGE_BYTES: u64 = 16 * 1024 * 1024;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FileRange {
+    /// The requested slice as UTF-8 (invalid sequences replaced).
+    pub content: String,
+    /// First line (1-based) or byte offset actually returned.
+    pub start: u64,
+    /// One past the last line or byte returned.
+    pub end: u64,
+    /// File size in bytes.
+    pub size: u64,
+    /// Whether the slice reaches the end of the file.
+    pub eof: bool,
+}
+
+/// Lines `start_line..=end_line` (1-based) of a file, read by streaming so only the prefix up
+/// to `end_line` is ever touched. Line endings are kept.
+pub fn workspace_read_file_range(rel_path: &str, start_line: u64, end_line: u64) -> Result<FileRange> {
+    let path = abs_path(rel_path, false)?;
+    let start_line = start_line.max(1);
+    if end_line < start_line {
+        return Err(anyhow!("end_line must not be before start_line"));
+    }
+    let end_line = end_line.min(start_line + MAX_RANGE_LINES as u64 - 1);
+    let f = fs::File::open(&path).with_context(|| format!("read file: {}", path.display()))?;
+    let size = f.metadata().map(|m| m.len()).unwrap_or(0);
+    let mut reader = BufReader::new(f);
+
+    let mut line_no = 0u64;
+    let mut buf = Vec::new();
+    let mut out = Vec::new();
+    let mut eof = false;
+    while line_no < end_line {
+        buf.clear();
+        let n = reader
+            .read_until(b'\n', &mut buf)
+            .with_context(|| format!("read file: {}", path.display()))?;
+        if n == 0 {
+            eof = true;
+            break;
         }
+        line_no += 1;
+        if line_no >= start_line {
+            out.extend_from_slice(&buf);
+            if out.len() as u64 > MAX_RANGE_BYTES {
+                break;
+            }
+        }
+    }
+    if !eof {
+        eof = reader.fill_buf().map(|b| b.is_empty()).unwrap_or(true);
+    }
+    Ok(FileRange {
+        content: String::from_utf8_lossy(&out).into_owned(),
+        start: start_line,
+        end: line_no.max(start_line - 1) + 1,
+        size,
+        eof,
+    })
+}
 
-        let rel = path
-            .strip_prefix(&root)
-            .with_context(|| format!("strip prefix: {}", root.display()))?
-            .to_string_lossy()
-            .replace('\\', "/");
-        if rel.trim().is_empty() {
-            continue;
+/// Up to `length` bytes starting at `offset`. The slice is widened to whole UTF-8 characters
+/// so multi-byte characters on the edges aren't mangled.
+pub fn workspace_read_file_bytes(rel_path: &str, offset: u64, length: u64) -> Result<FileRange> {
+    let path = abs_path(rel_path, false)?;
+    let mut f = fs::File::open(&path).with_context(|| format!("read file: {}", path.display()))?;
+    let size = f.metadata().map(|m| m.len()).unwrap_or(0);
+    let offset = offset.min(size);
+    let length = length.min(MAX_RANGE_BYTES);
+
+    // Back up over continuation bytes (10xxxxxx) to the start of the character.
+    let mut start = offset;
+    let mut byte = [0u8; 1];
+    while start > 0 && start < size && offset - start < 3 {
+        f.seek(SeekFrom::Start(start)).context("seek")?;
+        f.read_exact(&mut byte).context("read")?;
+        if byte[0] & 0xC0 != 0x80 {
+            break;
         }
-        if seen.insert(rel.clone()) {
-            out.push(rel);
+        start -= 1;
+    }
+
+    f.seek(SeekFrom::Start(start)).context("seek")?;
+    let mut out = Vec::new();
+    (&mut f)
+        .take(offset - start + length)
+        .read_to_end(&mut out)
+        .with_context(|| format!("read file: {}", path.display()))?;
+    // Finish a character cut off at the end.
+    let mut extra = [0u8; 3];
+    let tail = out.iter().rev().take(3).position(|b| b & 0xC0 != 0x80);
+    if let Some(lead_pos) = tail {
+        let lead = out[out.len() - 1 - lead_pos];
+        let want: usize = match lead {
+            b if b >= 0xF0 => 4,
+            b if b >= 0xE0 => 3,
+            b if b >= 0xC0 => 2,
+            _ => 1,
+        };
+        let missing = want.saturating_sub(lead_pos + 1);
+        if missing > 0 {
+            let n = f.read(&mut extra[..missing]).unwrap_or(0);
+            out.extend_from_slice(&extra[..n]);
         }
     }
 
-    out.sort_by(|a, b| a.to_lowercase().cmp(&b.to_lowercase()));
-    Ok(out)
+    let end = start + out.len() as u64;
+    Ok(FileRange {
+        content: String::from_utf8_lossy(&out).into_owned(),
+        start,
+        end,
+        size,
+        eof: end >= size,
+    })
 }
 
-pub fn workspace_read_file(rel_path: &str) -> Result<String> {
-    let path = abs_path(rel_path, false)?;
-    fs::read_to_string(&path).with_context(|| format!("read file: {}", path.display()))
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WriteResult {
+    pub version: String,
+    /// SHA-256 of the bytes written, lowercase hex; compare with `workspace_hash_file`.
+    pub sha256: String,
 }
 
-pub fn workspace_write_file(rel_path: &str, contents: &str) -> Result<()> {
+/// Writes `contents`, as UTF-8 unless `encoding` names the encoding the file was read in.
+/// Protected paths are refused unless `allow_protected`.
+pub fn workspace_write_file(
+    rel_path: &str,
+    contents: &str,
+    encoding: Option<&str>,
+    bom: bool,
+    allow_protected: bool,
+) -> Result<WriteResult> {
+    let (root, _, path) = resolve(rel_path, false)?;
+    if !allow_protected {
+        protected_paths::ensure_unprotected(&root, &path, "write")?;
+    }
+    write_encoded(rel_path, &path, contents, encoding, bom)
+}
+
+fn write_encoded(rel_path: &str, path: &Path, contents: &str, encoding: Option<&str>, bom: bool) -> Result<WriteResult> {
+    let bytes = encode_for_write(path, contents, encoding, bom)?;
+    let created_dirs = match path.parent() {
+        Some(parent) => create_dirs(parent)?,
+        None => vec![],
+    };
+    // Writing through a symlink must update its target, not replace the link with a file.
+    let path = if path.is_symlink() {
+        path.canonicalize().with_context(|| format!("resolve link: {}", path.display()))?
+    } else {
+        path.to_path_buf()
+    };
+    let previous = fs::metadata(&path).ok().filter(|m| m.is_file());
+    let undo = match &previous {
+        Some(meta) if meta.len() > fs_journal::MAX_SNAPSHOT_BYTES => None,
+        Some(_) => fs::read(&path).ok().map(|bytes| {
+            vec![Inverse::Restore {
+                path: path.clone(),
+                bytes,
+            }]
+        }),
+        None => Some(vec![Inverse::RemoveDirs(created_dirs), Inverse::RemoveFile(path.clone())]),
+    };
+    if previous.is_some() {
+        if let Err(e) = back_up(&path) {
+            return Err(e.context(format!("back up {} before saving", path.display())));
+        }
+    }
+    write_atomic(&path, &bytes, previous.as_ref())?;
+    let meta = fs::metadata(&path).with_context(|| format!("write file: {}", path.display()))?;
+    let version = file_version(&meta);
+    fs_journal::record(
+        "write",
+        vec![rel_path.to_string()],
+        undo,
+        vec![Guard::Version(path, version.clone())],
+    );
+    Ok(WriteResult {
+        version,
+        sha256: hex::encode(Sha256::digest(&bytes)),
+    })
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FileHash {
+    /// `sha256` or `blake3`.
+    pub algo: String,
+    /// Lowercase hex digest.
+    pub hash: String,
+    pub size: u64,
+}
+
+/// Hashes a file's bytes as they are on disk, streaming so large files stay cheap.
+pub fn workspace_hash_file(rel_path: &str, algo: &str) -> Result<FileHash> {
     let path = abs_path(rel_path, false)?;
-    if let Some(parent) = path.parent() {
-        fs::create_dir_all(parent).with_context(|| format!("create dir: {}", parent.display()))?;
+    let mut f = fs::File::open(&path).with_context(|| format!("read file: {}", path.display()))?;
+    let mut buf = vec![0u8; 64 * 1024];
+    let mut size = 0u64;
+    let algo = algo.trim().to_ascii_lowercase();
+    let hash = match algo.as_str() {
+        "sha256" | "sha-256" => {
+            let mut h = Sha256::new();
+            loop {
+                let n = f.read(&mut buf).with_context(|| format!("read file: {}", path.display()))?;
+                if n == 0 {
+                    break;
+                }
+                size += n as u64;
+                h.update(&buf[..n]);
+            }
+            hex::encode(h.finalize())
+        }
+        "blake3" => {
+            let mut h = blake3::Hasher::new();
+            loop {
+                let n = f.read(&mut buf).with_context(|| format!("read file: {}", path.display()))?;
+                if n == 0 {
+                    break;
+                }
+                size += n as u64;
+                h.update(&buf[..n]);
+            }
+            h.finalize().to_hex().to_string()
+        }
+        other => return Err(anyhow!("unsupported hash algorithm: {other} (use sha256 or blake3)")),
+    };
+    Ok(FileHash {
+        algo: if algo == "blake3" { algo } else { "sha256".to_string() },
+        hash,
+        size,
+    })
+}
+
+// Applies the write policy for `path` (line endings, whitespace) and encodes the result.
+fn encode_for_write(path: &Path, contents: &str, encoding: Option<&str>, bom: bool) -> Result<Vec<u8>> {
+    let contents = write_policy::apply(contents, &write_policy::policy_for(path));
+    Ok(match encoding.filter(|e| !e.trim().is_empty()) {
+        Some(enc) => encoding::encode(&contents, enc, bom)?,
+        None => contents.into_bytes(),
+    })
+}
+
+/// Suffix of the temporary files saves go through; the watcher reports a rename from one as a
+/// modification of the real file.
+pub const WRITE_TEMP_SUFFIX: &str = ".pompora-tmp";
+
+// Written next to the target (same filesystem, so the rename is atomic), synced, then renamed
+// over it: a crash leaves either the old file or the new one, never a truncated mix. The temp
+// name is random and created exclusively, so an entry planted under it (say a symlink out of
+// the workspace) is never written through.
+pub fn write_atomic(path: &Path, bytes: &[u8], previous: Option<&fs::Metadata>) -> Result<()> {
+    let name = path.file_name().map(|n| n.to_string_lossy().to_string()).unwrap_or_default();
+    let tmp = path.with_file_name(format!(".{name}.{:016x}{WRITE_TEMP_SUFFIX}", rand::random::<u64>()));
+    let mut f = fs::OpenOptions::new()
+        .write(true)
+        .create_new(true)
+        .open(&tmp)
+        .with_context(|| format!("write file: {}", tmp.display()))?;
+    let result = (|| -> Result<()> {
+        f.write_all(bytes).with_context(|| format!("write file: {}", tmp.display()))?;
+        // Keep the mode (e.g. executable scripts) of the file being replaced.
+        if let Some(prev) = previous {
+            let _ = f.set_permissions(prev.permissions());
+        }
+        f.sync_all().with_context(|| format!("sync file: {}", tmp.display()))?;
+        drop(f);
+        rename_with_retry(&tmp, path).with_context(|| format!("write file: {}", path.display()))
+    })();
+    if result.is_err() {
+        let _ = fs::remove_file(&tmp);
+    }
+    result
+}
+
+fn rename_with_retry(from: &Path, to: &Path) -> io::Result<()> {
+    let mut last = None;
+    for i in 0..6u64 {
+        match fs::rename(from, to) {
+            Ok(()) => return Ok(()),
+            Err(e) => {
+                last = Some(e);
+                // On Windows an editor, indexer or AV scanner holding the file open makes the
+                // rename fail for a moment.
+                thread::sleep(Duration::from_millis(25 * (i + 1)));
+            }
+        }
+    }
+    Err(last.unwrap_or_else(|| io::Error::other("rename failed")))
+}
+
+// Keeps the current contents of `path` per the `file_backups` setting before it is overwritten.
+fn back_up(path: &Path) -> Result<()> {
+    let backups = settings::load().map(|s| s.file_backups).unwrap_or_default();
+    match backups.mode {
+        BackupMode::Off => Ok(()),
+        BackupMode::Bak => {
+            let mut bak = path.as_os_str().to_os_string();
+            bak.push(".bak");
+            fs::copy(path, &bak).with_context(|| format!("copy to {}", PathBuf::from(&bak).display()))?;
+            Ok(())
+        }
+        BackupMode::History => {
+            let dir = history_dir(path)?;
+            fs::create_dir_all(&dir).with_context(|| format!("create dir: {}", dir.display()))?;
+            let ts = SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .map(|d| d.as_millis())
+                .unwrap_or(0);
+            let name = path.file_name().map(|n| n.to_string_lossy().to_string()).unwrap_or_default();
+            let dest = dir.join(format!("{ts:013}-{name}"));
+            fs::copy(path, &dest).with_context(|| format!("copy to {}", dest.display()))?;
+            prune_history(&dir, backups.history_limit.max(1) as usize);
+            Ok(())
+        }
+    }
+}
+
+// One folder per file, keyed by a hash of its absolute path. Copies are prefixed with a
+// zero-padded timestamp so they sort oldest first.
+fn history_dir(path: &Path) -> Result<PathBuf> {
+    let base = dirs::config_dir().context("missing config dir")?;
+    let abs = path.canonicalize().unwrap_or_else(|_| path.to_path_buf());
+    let mut h = std::collections::hash_map::DefaultHasher::new();
+    abs.hash(&mut h);
+    Ok(base.join("Pompora").join("history").join(format!("{:016x}", h.finish())))
+}
+
+fn prune_history(dir: &Path, keep: usize) {
+    let Ok(rd) = fs::read_dir(dir) else {
+        return;
+    };
+    let mut entries: Vec<PathBuf> = rd.flatten().map(|e| e.path()).collect();
+    entries.sort();
+    let excess = entries.len().saturating_sub(keep);
+    for old in &entries[..excess] {
+        let _ = fs::remove_file(old);
     }
-    fs::write(&path, contents).with_context(|| format!("write file: {}", path.display()))?;
-    Ok(())
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ConditionalWrite {
+    /// `written`, or `conflict` when the file changed or was deleted since `expected_version`.
+    pub outcome: String,
+    /// Version of the file now on disk; `None` if it no longer exists.
+    pub version: Option<String>,
+    /// SHA-256 of what was written; `None` on conflict.
+    pub sha256: Option<String>,
+    /// On conflict, what is on disk now, so the UI can diff or merge against it.
+    pub current: Option<TextFile>,
+}
+
+/// Writes only if the file is still at `expected_version` (from a read or an earlier write).
+/// An empty `expected_version` means the file is expected not to exist yet.
+pub fn workspace_write_file_if_unchanged(
+    rel_path: &str,
+    contents: &str,
+    expected_version: &str,
+    encoding: Option<&str>,
+    bom: bool,
+    allow_protected: bool,
+) -> Result<ConditionalWrite> {
+    let (root, _, path) = resolve(rel_path, false)?;
+    if !allow_protected {
+        protected_paths::ensure_unprotected(&root, &path, "write")?;
+    }
+    let on_disk = match fs::metadata(&path) {
+        Ok(meta) if meta.is_dir() => return Err(anyhow!("{} is a directory", path.display())),
+        Ok(meta) => Some(file_version(&meta)),
+        Err(e) if e.kind() == io::ErrorKind::NotFound => None,
+        Err(e) => return Err(e).with_context(|| format!("read file: {}", path.display())),
+    };
+
+    if on_disk.as_deref().unwrap_or("") != expected_version.trim() {
+        let current = match on_disk {
+            // Re-read so content and version describe the same state of the file.
+            Some(_) => read_versioned(&path).ok(),
+            None => None,
+        };
+        return Ok(ConditionalWrite {
+            outcome: "conflict".to_string(),
+            version: current.as_ref().map(|c| c.version.clone()),
+            sha256: None,
+            current: current.map(|c| c.file),
+        });
+    }
+
+    let written = write_encoded(rel_path, &path, contents, encoding, bom)?;
+    Ok(ConditionalWrite {
+        outcome: "written".to_string(),
+        version: Some(written.version),
+        sha256: Some(written.sha256),
+        current: None,
+    })
 }
 
 pub fn workspace_create_dir(rel_path: &str) -> Result<()> {
     let path = abs_path(rel_path, false)?;
-    fs::create_dir_all(&path).with_context(|| format!("create dir: {}", path.display()))?;
+    let created = create_dirs(&path)?;
+    if !created.is_empty() {
+        fs_journal::record("create_dir", vec![rel_path.to_string()], Some(vec![Inverse::RemoveDirs(created)]), vec![]);
+    }
     Ok(())
 }
 
-pub fn workspace_delete(rel_path: &str) -> Result<()> {
-    let rel = validate_relative(rel_path, false)?;
-    if rel.as_os_str().is_empty() {
+/// Deletes a file or directory. Unless `permanent` (default: the `delete_to_trash` setting)
+/// it goes to the OS trash so it can be restored. Protected paths, and folders with protected
+/// paths in them, are refused unless `allow_protected`.
+pub fn workspace_delete(rel_path: &str, permanent: Option<bool>, allow_protected: bool) -> Result<()> {
+    let (root, inner, path) = resolve_entry(rel_path)?;
+    if inner.trim().is_empty() {
         return Err(anyhow!("refusing to delete workspace root"));
     }
-
-    let path = abs_path(rel_path, false)?;
-    if path.is_dir() {
-        fs::remove_dir_all(&path).with_context(|| format!("delete dir: {}", path.display()))?;
+    if !path.exists() && !path.is_symlink() {
         return Ok(());
     }
+    if !allow_protected {
+        protected_paths::ensure_unprotected(&root, &path, "delete")?;
+    }
+    let undo = fs_journal::snapshot(&path, &path);
+    remove_path(&path, delete_permanently(permanent))?;
+    fs_journal::record("delete", vec![rel_path.to_string()], undo.map(|u| vec![u]), vec![Guard::Absent(path)]);
+    Ok(())
+}
 
-    if path.exists() {
-        fs::remove_file(&path).with_context(|| format!("delete file: {}", path.display()))?;
+fn delete_permanently(permanent: Option<bool>) -> bool {
+    permanent.unwrap_or_else(|| !settings::load().map(|s| s.delete_to_trash).unwrap_or(true))
+}
+
+fn remove_path(path: &Path, permanent: bool) -> Result<()> {
+    if !permanent {
+        // No silent fallback: a failed trash (network drive, no trash on this volume) must not
+        // turn into an unrecoverable delete.
+        return trash::delete(path).map_err(|e| {
+            anyhow!("could not move {} to the trash: {e}; delete it permanently instead", path.display())
+        });
     }
+
+    if path.is_dir() && !path.is_symlink() {
+        fs::remove_dir_all(path).with_context(|| format!("delete dir: {}", path.display()))?;
+        return Ok(());
+    }
+    fs::remove_file(path).with_context(|| format!("delete file: {}", path.display()))?;
     Ok(())
 }
 
-pub fn workspace_rename(from_rel: &str, to_rel: &str) -> Result<()> {
-    let from = abs_path(from_rel, false)?;
-    let to = abs_path(to_rel, false)?;
+/// Moves a file or folder. Moving a protected path, or onto one, is refused unless
+/// `allow_protected`.
+pub fn workspace_rename(from_rel: &str, to_rel: &str, allow_protected: bool) -> Result<()> {
+    let (from_root, from_inner, from) = resolve_entry(from_rel)?;
+    let (to_root, to_inner, to) = resolve_entry(to_rel)?;
+    if from_inner.trim().is_empty() {
+        return Err(anyhow!("refusing to move a workspace root"));
+    }
+    if to_inner.trim().is_empty() {
+        return Err(anyhow!("refusing to replace a workspace root"));
+    }
+    if !allow_protected {
+        protected_paths::ensure_unprotected(&from_root, &from, "move")?;
+        protected_paths::ensure_unprotected(&to_root, &to, "replace")?;
+    }
+    let mut undo = vec![];
     if let Some(parent) = to.parent() {
-        fs::create_dir_all(parent).with_context(|| format!("create dir: {}", parent.display()))?;
+        undo.push(Inverse::RemoveDirs(create_dirs(parent)?));
+    }
+    // Renaming onto an existing file replaces it; keep it so undo can put it back.
+    let mut undoable = true;
+    if from.is_file() && to.is_file() {
+        match fs::metadata(&to) {
+            Ok(m) if m.len() <= fs_journal::MAX_SNAPSHOT_BYTES => undo.push(Inverse::Restore {
+                path: to.clone(),
+                bytes: fs::read(&to).with_context(|| format!("read file: {}", to.display()))?,
+            }),
+            _ => undoable = false,
+        }
     }
     fs::rename(&from, &to).with_context(|| format!("rename {} -> {}", from.display(), to.display()))?;
+    undo.push(Inverse::Rename {
+        from: to.clone(),
+        to: from.clone(),
+    });
+    fs_journal::record(
+        "rename",
+        vec![from_rel.to_string(), to_rel.to_string()],
+        undoable.then_some(undo),
+        vec![Guard::Absent(from)],
+    );
+    Ok(())
+}
+
+/// One step of `workspace_batch_ops`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "op", rename_all = "snake_case")]
+pub enum FsOp {
+    Write {
+        path: String,
+        contents: String,
+        #[serde(default)]
+        encoding: Option<String>,
+        #[serde(default)]
+        bom: bool,
+    },
+    CreateDir {
+        path: String,
+    },
+    Rename {
+        from: String,
+        to: String,
+    },
+    Delete {
+        path: String,
+    },
+}
+
+// An op with its paths resolved and its bytes encoded, so nothing that can be checked up front
+// fails halfway through.
+enum Planned {
+    Write { path: PathBuf, bytes: Vec<u8> },
+    CreateDir { path: PathBuf },
+    Rename { from: PathBuf, to: PathBuf },
+    Delete { path: PathBuf },
+}
+
+/// Suffix deletions in a batch are parked under until the whole batch has succeeded.
+pub const DELETE_STAGING_SUFFIX: &str = ".pompora-deleted";
+
+/// Whether a file name is one of the scratch files saves and batches create next to real ones.
+pub fn is_scratch_name(name: &str) -> bool {
+    name.ends_with(WRITE_TEMP_SUFFIX) || name.ends_with(DELETE_STAGING_SUFFIX)
+}
+
+// Like `fs::create_dir_all`, returning the directories that had to be created, outermost first.
+fn create_dirs(dir: &Path) -> Result<Vec<PathBuf>> {
+    let mut missing = vec![];
+    let mut cur = Some(dir);
+    while let Some(d) = cur {
+        if d.as_os_str().is_empty() || d.exists() {
+            break;
+        }
+        missing.push(d.to_path_buf());
+        cur = d.parent();
+    }
+    fs::create_dir_all(dir).with_context(|| format!("create dir: {}", dir.display()))?;
+    missing.reverse();
+    Ok(missing)
+}
+
+// `present` tracks what earlier steps will have done to each path, so later steps validate
+// against that rather than against the disk as it is now.
+fn plan_one(op: &FsOp, present: &mut HashMap<PathBuf, bool>, allow_protected: bool) -> Result<Planned> {
+    let exists = |present: &HashMap<PathBuf, bool>, p: &Path| {
+        present.get(p).copied().unwrap_or_else(|| p.exists() || p.is_symlink())
+    };
+    let check = |root: &workspace::OpenRoot, path: &Path, what: &str| {
+        if allow_protected {
+            Ok(())
+        } else {
+            protected_paths::ensure_unprotected(root, path, what)
+        }
+    };
+    Ok(match op {
+        FsOp::Write { path, contents, encoding, bom } => {
+            let (root, _, path) = resolve(path, false)?;
+            check(&root, &path, "write")?;
+            if path.is_dir() {
+                return Err(anyhow!("{} is a directory", path.display()));
+            }
+            let bytes = encode_for_write(&path, contents, encoding.as_deref(), *bom)?;
+            present.insert(path.clone(), true);
+            Planned::Write { path, bytes }
+        }
+        FsOp::CreateDir { path } => {
+            let path = abs_path(path, false)?;
+            if path.is_file() {
+                return Err(anyhow!("{} is a file", path.display()));
+            }
+            present.insert(path.clone(), true);
+            Planned::CreateDir { path }
+        }
+        FsOp::Rename { from, to } => {
+            let (from_root, inner, from) = resolve_entry(from)?;
+            if inner.trim().is_empty() {
+                return Err(anyhow!("refusing to move a workspace root"));
+            }
+            let (to_root, _, to) = resolve_entry(to)?;
+            check(&from_root, &from, "move")?;
+            check(&to_root, &to, "replace")?;
+            if !exists(present, &from) {
+                return Err(anyhow!("{} does not exist", from.display()));
+            }
+            if exists(present, &to) {
+                return Err(anyhow!("{} already exists", to.display()));
+            }
+            present.insert(from.clone(), false);
+            present.insert(to.clone(), true);
+            Planned::Rename { from, to }
+        }
+        FsOp::Delete { path } => {
+            let (root, inner, path) = resolve_entry(path)?;
+            if inner.trim().is_empty() {
+                return Err(anyhow!("refusing to delete workspace root"));
+            }
+            check(&root, &path, "delete")?;
+            present.insert(path.clone(), false);
+            Planned::Delete { path }
+        }
+    })
+}
+
+fn op_label(op: &FsOp) -> String {
+    match op {
+        FsOp::Write { path, .. } => format!("write {path}"),
+        FsOp::CreateDir { path } => format!("create {path}"),
+        FsOp::Rename { from, to } => format!("rename {from} -> {to}"),
+        FsOp::Delete { path } => format!("delete {path}"),
+    }
+}
+
+fn execute(step: usize, op: &Planned, applied: &mut Vec<Inverse>, staged: &mut Vec<(PathBuf, PathBuf)>) -> Result<()> {
+    match op {
+        Planned::Write { path, bytes } => {
+            let previous = fs::metadata(path).ok().filter(|m| m.is_file());
+            let undo = match &previous {
+                Some(_) => Inverse::Restore {
+                    path: path.clone(),
+                    bytes: fs::read(path).with_context(|| format!("read file: {}", path.display()))?,
+                },
+                None => Inverse::RemoveFile(path.clone()),
+            };
+            if let Some(parent) = path.parent() {
+                applied.push(Inverse::RemoveDirs(create_dirs(parent)?));
+            }
+            if previous.is_some() {
+                back_up(path).with_context(|| format!("back up {} before saving", path.display()))?;
+            }
+            write_atomic(path, bytes, previous.as_ref())?;
+            applied.push(undo);
+        }
+        Planned::CreateDir { path } => applied.push(Inverse::RemoveDirs(create_dirs(path)?)),
+        Planned::Rename { from, to } => {
+            if let Some(parent) = to.parent() {
+                applied.push(Inverse::RemoveDirs(create_dirs(parent)?));
+            }
+            fs::rename(from, to).with_context(|| format!("rename {} -> {}", from.display(), to.display()))?;
+            applied.push(Inverse::Rename {
+                from: to.clone(),
+                to: from.clone(),
+            });
+        }
+        Planned::Delete { path } => {
+            if !path.exists() && !path.is_symlink() {
+                return Ok(());
+            }
+            // Parked next to the original (same filesystem, so it's a cheap rename) and only
+            // really deleted once every step has succeeded.
+            let name = path.file_name().map(|n| n.to_string_lossy().to_string()).unwrap_or_default();
+            let parked = path.with_file_name(format!(".{name}-{step}{DELETE_STAGING_SUFFIX}"));
+            fs::rename(path, &parked).with_context(|| format!("delete: {}", path.display()))?;
+            applied.push(Inverse::Rename {
+                from: parked.clone(),
+                to: path.clone(),
+            });
+            staged.push((parked, path.clone()));
+        }
+    }
     Ok(())
 }
+
+/// Applies `ops` in order as a unit: everything is validated first, and if a step fails the
+/// steps already applied are undone. Deletions honour `permanent`, and protected paths
+/// `allow_protected`, like `workspace_delete`. Returns the number of steps applied.
+pub fn workspace_batch_ops(ops: &[FsOp], permanent: Option<bool>, allow_protected: bool) -> Result<usize> {
+    let mut present = HashMap::new();
+    let mut planned = Vec::with_capacity(ops.len());
+    for (i, op) in ops.iter().enumerate() {
+        let p = plan_one(op, &mut present, allow_protected).map_err(|e| anyhow!("step {} ({}): {e:#}", i + 1, op_label(op)))?;
+        planned.push(p);
+    }
+
+    let mut applied = vec![];
+    let mut staged = vec![];
+    for (i, op) in planned.iter().enumerate() {
+        if let Err(e) = execute(i + 1, op, &mut applied, &mut staged) {
+            let mut stuck = 0;
+            for undo in applied.iter().rev() {
+                if undo.apply().is_err() {
+                    stuck += 1;
+                }
+            }
+            let undone = if stuck > 0 {
+                format!("{stuck} undo actions also failed, so some changes remain")
+            } else {
+                "no changes were made".to_string()
+            };
+            return Err(anyhow!("step {} ({}) failed: {e:#}; {undone}", i + 1, op_label(&ops[i])));
+        }
+    }
+
+    // Past this point the batch has happened; parked deletions just need finishing.
+    let permanent = delete_permanently(permanent);
+    let mut failed = vec![];
+    // Undoing the batch later can't un-park deletions any more; they are recreated from a
+    // snapshot instead, if they were small enough to take one.
+    let mut undoable = true;
+    for undo in applied.iter_mut() {
+        if let Inverse::Rename { from, to } = undo {
+            if staged.iter().any(|(parked, _)| parked == from) {
+                match fs_journal::snapshot(from, to) {
+                    Some(snap) => *undo = snap,
+                    None => undoable = false,
+                }
+            }
+        }
+    }
+    for (parked, original) in staged {
+        // Trashed under the original name when it is still free, so a restore brings it back
+        // where it was.
+        let target = if !permanent && !original.exists() && fs::rename(&parked, &original).is_ok() {
+            original
+        } else {
+            parked
+        };
+        if let Err(e) = remove_path(&target, permanent) {
+            failed.push(format!("{e:#}"));
+        }
+    }
+    let paths = ops
+        .iter()
+        .flat_map(|op| match op {
+            FsOp::Write { path, .. } | FsOp::CreateDir { path } | FsOp::Delete { path } => vec![path.clone()],
+            FsOp::Rename { from, to } => vec![from.clone(), to.clone()],
+        })
+        .collect();
+    fs_journal::record("batch", paths, undoable.then_some(applied), vec![]);
+
+    if !failed.is_empty() {
+        return Err(anyhow!("the batch was applied, but some deletions did not finish: {}", failed.join("; ")));
+    }
+    Ok(ops.len())
+}
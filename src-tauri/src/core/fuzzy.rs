@@ -0,0 +1,117 @@
+use anyhow::Result;
+use nucleo_matcher::pattern::{CaseMatching, Normalization, Pattern};
+use nucleo_matcher::{Config, Matcher, Utf32Str};
+use once_cell::sync::Lazy;
+use serde::Serialize;
+use std::sync::{Arc, Mutex};
+use unicode_segmentation::UnicodeSegmentation;
+
+use super::{fsops, settings, workspace};
+
+// Files beyond this many aren't offered by quick open.
+const MAX_INDEXED_FILES: usize = 200_000;
+
+#[derive(Debug, Clone, Serialize)]
+pub struct FuzzyMatch {
+    pub path: String,
+    pub score: u32,
+    /// Matched characters of `path`, as ascending UTF-16 offsets for highlighting.
+    pub positions: Vec<u32>,
+}
+
+// What the cached list was built for; a different workspace or listing setting rebuilds it.
+#[derive(PartialEq)]
+struct IndexKey {
+    roots: Vec<String>,
+    include_ignored: bool,
+    include_hidden: bool,
+}
+
+struct FileIndex {
+    key: IndexKey,
+    files: Arc<Vec<String>>,
+}
+
+static FILE_INDEX: Lazy<Mutex<Option<FileIndex>>> = Lazy::new(|| Mutex::new(None));
+
+/// Drops the cached file list; the watcher calls this when files are added, removed or renamed.
+pub fn invalidate() {
+    *FILE_INDEX.lock().unwrap_or_else(|e| e.into_inner()) = None;
+}
+
+fn indexed_files() -> Result<Arc<Vec<String>>> {
+    let s = settings::load()?;
+    let key = IndexKey {
+        roots: workspace::workspace_roots(&s).into_iter().map(|r| r.path).collect(),
+        include_ignored: s.include_ignored_files,
+        include_hidden: s.show_hidden_files,
+    };
+    if let Some(index) = &*FILE_INDEX.lock().unwrap_or_else(|e| e.into_inner()) {
+        if index.key == key {
+            return Ok(index.files.clone());
+        }
+    }
+    // Built outside the lock; two callers racing here both walk, and the last one wins.
+    let files = Arc::new(fsops::workspace_list_files(MAX_INDEXED_FILES, None, None)?);
+    *FILE_INDEX.lock().unwrap_or_else(|e| e.into_inner()) = Some(FileIndex {
+        key,
+        files: files.clone(),
+    });
+    Ok(files)
+}
+
+// nucleo reports positions in graphemes; the webview slices strings in UTF-16 units.
+fn utf16_positions(path: &str, graphemes: &[u32]) -> Vec<u32> {
+    if path.is_ascii() {
+        return graphemes.to_vec();
+    }
+    let mut starts = Vec::new();
+    let mut offset = 0u32;
+    for g in path.graphemes(true) {
+        starts.push(offset);
+        offset += g.encode_utf16().count() as u32;
+    }
+    graphemes.iter().filter_map(|&i| starts.get(i as usize).copied()).collect()
+}
+
+/// Ranks workspace files against `query` the way fzf does (space-separated terms must all
+/// match; `'exact`, `^prefix`, `suffix$` and `!negation` work too), best first. An empty query
+/// lists the first `max_results` files by path.
+pub fn workspace_fuzzy_find(query: &str, max_results: usize) -> Result<Vec<FuzzyMatch>> {
+    let files = indexed_files()?;
+    let query = query.trim();
+    if query.is_empty() {
+        return Ok(files
+            .iter()
+            .take(max_results)
+            .map(|p| FuzzyMatch {
+                path: p.clone(),
+                score: 0,
+                positions: vec![],
+            })
+            .collect());
+    }
+
+    let pattern = Pattern::parse(query, CaseMatching::Smart, Normalization::Smart);
+    let mut matcher = Matcher::new(Config::DEFAULT.match_paths());
+    let mut buf = Vec::new();
+    let mut indices = Vec::new();
+    let mut out = Vec::new();
+    for path in files.iter() {
+        indices.clear();
+        let Some(score) = pattern.indices(Utf32Str::new(path, &mut buf), &mut matcher, &mut indices) else {
+            continue;
+        };
+        indices.sort_unstable();
+        indices.dedup();
+        out.push(FuzzyMatch {
+            path: path.clone(),
+            score,
+            positions: utf16_positions(path, &indices),
+        });
+    }
+    // Ties go to the shorter path: `main.rs` before `tests/fixtures/main.rs`.
+    out.sort_by(|a, b| b.score.cmp(&a.score).then(a.path.len().cmp(&b.path.len())).then(a.path.cmp(&b.path)));
+    out.truncate(max_results);
+    Ok(out)
+}
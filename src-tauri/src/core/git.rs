@@ -0,0 +1,953 @@
+use anyhow::{anyhow, Context, Result};
+use git2::{BranchType, Cred, CredentialType, DiffOptions, IndexAddOption, Repository, StatusOptions};
+use serde::{Deserialize, Serialize};
+use std::cell::Cell;
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+use tauri::{AppHandle, Emitter};
+
+use super::commit_lint;
+use super::notifications::{self, Category};
+use super::redact::redact_error;
+use super::secrets;
+use super::settings;
+use super::workspace::{self, OpenRoot};
+
+pub const GIT_PROGRESS_EVENT: &str = "git:progress";
+
+/// HTTPS tokens live in the secrets store under this provider id, one credential per host
+/// (see `host_credential`), with the default credential as a fallback for every host.
+pub const GIT_TOKEN_PROVIDER: &str = "git";
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CommitInfo {
+    pub id: String,
+    pub summary: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BranchInfo {
+    /// Short name: `main` for local branches, `origin/main` for remote-tracking ones.
+    pub name: String,
+    pub is_remote: bool,
+    pub is_head: bool,
+    pub upstream: Option<String>,
+    pub commit_id: String,
+    pub summary: String,
+}
+
+/// The workspace root git commands act on: the named root in a multi-root workspace, the only
+/// root otherwise.
+fn open_root(root: Option<&str>) -> Result<OpenRoot> {
+    let roots = workspace::open_roots()?;
+    if roots.len() == 1 {
+        return roots.into_iter().next().ok_or_else(|| anyhow!("no workspace is open"));
+    }
+    let name = root
+        .map(str::trim)
+        .filter(|r| !r.is_empty())
+        .ok_or_else(|| anyhow!("this workspace has several roots; choose one"))?;
+    roots
+        .into_iter()
+        .find(|r| r.prefix.as_deref() == Some(name))
+        .ok_or_else(|| anyhow!("unknown workspace root: {name}"))
+}
+
+fn discover(root: &OpenRoot) -> Result<Repository> {
+    Repository::discover(&root.path).with_context(|| format!("not a git repository: {}", root.path.display()))
+}
+
+/// The working directory of the repository holding the workspace root.
+pub fn repo_workdir(root: Option<&str>) -> Result<PathBuf> {
+    workdir(&discover(&open_root(root)?)?)
+}
+
+/// The staged changes as a unified diff, cut off after about `max_bytes`.
+pub fn staged_diff(root: Option<&str>, max_bytes: usize) -> Result<String> {
+    let repo = discover(&open_root(root)?)?;
+    let head = repo.head().ok().and_then(|h| h.peel_to_tree().ok());
+    let diff = repo.diff_tree_to_index(head.as_ref(), None, None).context("diff staged changes")?;
+    let mut out = String::new();
+    // Returning false stops the walk, which libgit2 reports as an error.
+    let printed = diff.print(git2::DiffFormat::Patch, |_, _, line| {
+        if out.len() >= max_bytes {
+            return false;
+        }
+        if matches!(line.origin(), '+' | '-' | ' ') {
+            out.push(line.origin());
+        }
+        out.push_str(&String::from_utf8_lossy(line.content()));
+        true
+    });
+    if out.len() >= max_bytes {
+        out.push_str("\n… (diff cut off)\n");
+    } else {
+        printed.context("format staged diff")?;
+    }
+    Ok(out)
+}
+
+/// The URL `remote` points at in the repository holding the workspace root.
+pub fn remote_url(remote: &str, root: Option<&str>) -> Result<String> {
+    let repo = discover(&open_root(root)?)?;
+    let r = repo.find_remote(remote).with_context(|| format!("unknown remote: {remote}"))?;
+    r.url().map(str::to_string).ok_or_else(|| anyhow!("remote {remote} has no URL"))
+}
+
+fn workdir(repo: &Repository) -> Result<PathBuf> {
+    repo.workdir()
+        .map(Path::to_path_buf)
+        .ok_or_else(|| anyhow!("bare repositories are not supported"))
+}
+
+// The repository can sit above the workspace root, so workspace paths are re-based onto the
+// repository's working directory.
+fn repo_path(root: &OpenRoot, workdir: &Path, inner: &str) -> Result<String> {
+    let abs = root.path.join(inner);
+    let base = workdir.canonicalize().unwrap_or_else(|_| workdir.to_path_buf());
+    let abs = abs
+        .canonicalize()
+        .or_else(|_| root.path.canonicalize().map(|r| r.join(inner)))
+        .unwrap_or(abs);
+    let rel = abs
+        .strip_prefix(&base)
+        .map_err(|_| anyhow!("path is outside the repository: {inner}"))?;
+    let rel = rel.to_string_lossy().replace('\\', "/");
+    Ok(if rel.is_empty() { ".".to_string() } else { rel })
+}
+
+/// Workspace paths grouped by the repository that contains them, as repository-relative
+/// pathspecs.
+fn group_by_repo(paths: &[String]) -> Result<Vec<(Repository, Vec<String>)>> {
+    if paths.is_empty() {
+        return Err(anyhow!("no paths given"));
+    }
+    let mut by_root: BTreeMap<PathBuf, (OpenRoot, Vec<String>)> = BTreeMap::new();
+    for p in paths {
+        let (root, inner) =
+            workspace::split_workspace_path(p)?.ok_or_else(|| anyhow!("choose a path inside a workspace root"))?;
+        by_root
+            .entry(root.path.clone())
+            .or_insert_with(|| (root, vec![]))
+            .1
+            .push(inner);
+    }
+
+    let mut out = vec![];
+    for (_, (root, inners)) in by_root {
+        let repo = discover(&root)?;
+        let wd = workdir(&repo)?;
+        let specs = inners
+            .iter()
+            .map(|inner| repo_path(&root, &wd, inner))
+            .collect::<Result<Vec<_>>>()?;
+        out.push((repo, specs));
+    }
+    Ok(out)
+}
+
+/// Stages the given workspace paths (files or directories), including deletions.
+pub fn git_stage(paths: &[String]) -> Result<()> {
+    for (repo, specs) in group_by_repo(paths)? {
+        let mut index = repo.index().context("open git index")?;
+        index
+            .add_all(specs.iter(), IndexAddOption::DEFAULT, None)
+            .context("stage paths")?;
+        // `add_all` skips files that no longer exist; this records their removal.
+        index.update_all(specs.iter(), None).context("stage deletions")?;
+        index.write().context("write git index")?;
+    }
+    Ok(())
+}
+
+/// Moves the given workspace paths back to their `HEAD` state in the index.
+pub fn git_unstage(paths: &[String]) -> Result<()> {
+    for (repo, specs) in group_by_repo(paths)? {
+        match repo.head().ok().and_then(|h| h.peel_to_commit().ok()) {
+            Some(head) => repo
+                .reset_default(Some(head.as_object()), specs.iter())
+                .context("unstage paths")?,
+            // Nothing committed yet: unstaging means dropping the entries from the index.
+            None => {
+                let mut index = repo.index().context("open git index")?;
+                index.remove_all(specs.iter(), None).context("unstage paths")?;
+                index.write().context("write git index")?;
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Commits the index. With `amend` the `HEAD` commit is replaced, keeping its author.
+pub fn git_commit(message: &str, amend: bool, root: Option<&str>) -> Result<CommitInfo> {
+    let message = message.trim();
+    if message.is_empty() {
+        return Err(anyhow!("commit message is empty"));
+    }
+
+    let root = open_root(root)?;
+    let repo = discover(&root)?;
+    if settings::load()?.enforce_commit_lint {
+        let check = commit_lint::validate_in(&workdir(&repo)?, message);
+        if !check.valid {
+            return Err(anyhow!("the commit message breaks the commit rules:\n{}", commit_lint::describe_problems(&check)));
+        }
+    }
+    let sig = repo
+        .signature()
+        .context("git user.name and user.email are not configured")?;
+    let mut index = repo.index().context("open git index")?;
+    let tree_id = index.write_tree().context("write tree")?;
+    let tree = repo.find_tree(tree_id).context("find tree")?;
+    let head = repo.head().ok().and_then(|h| h.peel_to_commit().ok());
+
+    let id = if amend {
+        let head = head.ok_or_else(|| anyhow!("there is no commit to amend"))?;
+        head.amend(Some("HEAD"), None, Some(&sig), None, Some(message), Some(&tree))
+            .context("amend commit")?
+    } else {
+        if head.as_ref().map(|h| h.tree_id()) == Some(tree_id) || (head.is_none() && index.is_empty()) {
+            return Err(anyhow!("nothing to commit: no staged changes"));
+        }
+        let parents: Vec<&git2::Commit> = head.iter().collect();
+        repo.commit(Some("HEAD"), &sig, &sig, message, &tree, &parents)
+            .context("create commit")?
+    };
+
+    Ok(CommitInfo {
+        id: id.to_string(),
+        summary: message.lines().next().unwrap_or_default().to_string(),
+    })
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DiffLine {
+    /// `+`, `-` or ` `; `=`, `>` and `<` mark end-of-file newline changes.
+    pub origin: String,
+    pub content: String,
+    pub old_lineno: Option<u32>,
+    pub new_lineno: Option<u32>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DiffHunk {
+    pub header: String,
+    pub old_start: u32,
+    pub old_lines: u32,
+    pub new_start: u32,
+    pub new_lines: u32,
+    pub lines: Vec<DiffLine>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FileDiff {
+    pub path: String,
+    /// Unified diff text; empty when the file is unchanged or binary.
+    pub patch: String,
+    pub binary: bool,
+    /// Only filled in when requested.
+    pub hunks: Vec<DiffHunk>,
+}
+
+fn short_id(id: git2::Oid) -> String {
+    id.to_string().chars().take(7).collect()
+}
+
+/// Local branches first (current one on top), then remote-tracking branches.
+pub fn git_branches_list(root: Option<&str>) -> Result<Vec<BranchInfo>> {
+    let root = open_root(root)?;
+    branches(&discover(&root)?)
+}
+
+fn branches(repo: &Repository) -> Result<Vec<BranchInfo>> {
+    let mut out = vec![];
+    for item in repo.branches(None).context("list branches")? {
+        let (branch, kind) = item.context("read branch")?;
+        let Some(name) = branch.name().ok().flatten().map(str::to_string) else {
+            continue;
+        };
+        // `origin/HEAD` is a symbolic pointer, not a branch anyone switches to.
+        if kind == BranchType::Remote && name.ends_with("/HEAD") {
+            continue;
+        }
+        let upstream = branch
+            .upstream()
+            .ok()
+            .and_then(|u| u.name().ok().flatten().map(str::to_string));
+        let commit = branch.get().peel_to_commit().ok();
+        out.push(BranchInfo {
+            name,
+            is_remote: kind == BranchType::Remote,
+            is_head: branch.is_head(),
+            upstream,
+            commit_id: commit.as_ref().map(|c| short_id(c.id())).unwrap_or_default(),
+            summary: commit
+                .as_ref()
+                .and_then(|c| c.summary().map(str::to_string))
+                .unwrap_or_default(),
+        });
+    }
+    out.sort_by(|a, b| (a.is_remote, !a.is_head, &a.name).cmp(&(b.is_remote, !b.is_head, &b.name)));
+    Ok(out)
+}
+
+fn validate_branch_name(name: &str) -> Result<&str> {
+    let name = name.trim();
+    if name.is_empty() {
+        return Err(anyhow!("branch name is empty"));
+    }
+    if !git2::Branch::name_is_valid(name).unwrap_or(false) {
+        return Err(anyhow!("invalid branch name: {name}"));
+    }
+    Ok(name)
+}
+
+/// Creates `name` at `start` (any revision, default `HEAD`), optionally switching to it.
+pub fn git_branch_create(name: &str, start: Option<&str>, checkout: bool, root: Option<&str>) -> Result<BranchInfo> {
+    let name = validate_branch_name(name)?;
+    let root = open_root(root)?;
+    let repo = discover(&root)?;
+    let start = start.map(str::trim).filter(|s| !s.is_empty()).unwrap_or("HEAD");
+    let target = repo
+        .revparse_single(start)
+        .and_then(|o| o.peel_to_commit())
+        .with_context(|| format!("unknown revision: {start}"))?;
+    let branch = repo
+        .branch(name, &target, false)
+        .with_context(|| format!("create branch {name}"))?;
+    drop(branch);
+
+    if checkout {
+        checkout_branch(&repo, name)?;
+    }
+    branches(&repo)?
+        .into_iter()
+        .find(|b| !b.is_remote && b.name == name)
+        .ok_or_else(|| anyhow!("branch {name} was not created"))
+}
+
+/// Paths with staged or unstaged changes to tracked files. Untracked files don't block a
+/// checkout unless git itself finds a conflict.
+fn dirty_paths(repo: &Repository) -> Result<Vec<String>> {
+    let mut opts = StatusOptions::new();
+    opts.include_untracked(false).include_ignored(false);
+    let statuses = repo.statuses(Some(&mut opts)).context("read git status")?;
+    Ok(statuses
+        .iter()
+        .filter(|e| !e.status().is_empty() && !e.status().is_ignored())
+        .filter_map(|e| e.path().map(str::to_string))
+        .collect())
+}
+
+fn checkout_branch(repo: &Repository, name: &str) -> Result<()> {
+    let dirty = dirty_paths(repo)?;
+    if !dirty.is_empty() {
+        let mut listed = dirty.iter().take(5).cloned().collect::<Vec<_>>().join(", ");
+        if dirty.len() > 5 {
+            listed.push_str(&format!(" and {} more", dirty.len() - 5));
+        }
+        return Err(anyhow!(
+            "cannot switch to {name}: commit or stash your changes first ({listed})"
+        ));
+    }
+
+    // A remote-tracking name (`origin/feature`) checks out a local branch that tracks it.
+    let local = match repo.find_branch(name, BranchType::Local) {
+        Ok(b) => b,
+        Err(_) => {
+            let remote = repo
+                .find_branch(name, BranchType::Remote)
+                .with_context(|| format!("unknown branch: {name}"))?;
+            let local_name = name.split_once('/').map(|(_, rest)| rest).unwrap_or(name);
+            let commit = remote.get().peel_to_commit().context("resolve remote branch")?;
+            let mut local = repo
+                .branch(local_name, &commit, false)
+                .with_context(|| format!("create branch {local_name}"))?;
+            local.set_upstream(Some(name)).context("set upstream")?;
+            local
+        }
+    };
+    let refname = local
+        .get()
+        .name()
+        .ok_or_else(|| anyhow!("branch name is not valid UTF-8"))?
+        .to_string();
+    let tree = local.get().peel_to_tree().context("resolve branch tree")?;
+
+    let mut co = git2::build::CheckoutBuilder::new();
+    co.safe();
+    repo.checkout_tree(tree.as_object(), Some(&mut co))
+        .with_context(|| format!("check out {name}"))?;
+    repo.set_head(&refname).context("update HEAD")?;
+    Ok(())
+}
+
+/// Switches the working tree to branch `name`. Refuses when tracked files have uncommitted
+/// changes.
+pub fn git_checkout(name: &str, root: Option<&str>) -> Result<()> {
+    let name = name.trim();
+    if name.is_empty() {
+        return Err(anyhow!("branch name is empty"));
+    }
+    let root = open_root(root)?;
+    let repo = discover(&root)?;
+    checkout_branch(&repo, name)
+}
+
+/// Deletes a local branch. Without `force`, branches not merged into `HEAD` are kept.
+pub fn git_branch_delete(name: &str, force: bool, root: Option<&str>) -> Result<()> {
+    let name = name.trim();
+    let root = open_root(root)?;
+    let repo = discover(&root)?;
+    let mut branch = repo
+        .find_branch(name, BranchType::Local)
+        .with_context(|| format!("unknown branch: {name}"))?;
+    if branch.is_head() {
+        return Err(anyhow!("cannot delete the current branch {name}"));
+    }
+    if !force {
+        let tip = branch.get().peel_to_commit().context("resolve branch")?.id();
+        let head = repo.head().ok().and_then(|h| h.peel_to_commit().ok()).map(|c| c.id());
+        let merged = head.is_some_and(|h| h == tip || repo.graph_descendant_of(h, tip).unwrap_or(false));
+        if !merged {
+            return Err(anyhow!("branch {name} is not fully merged; delete it with force to discard its commits"));
+        }
+    }
+    branch.delete().with_context(|| format!("delete branch {name}"))
+}
+
+/// Diff of one workspace file: `HEAD` against the index when `staged`, otherwise the index
+/// against the working tree (untracked files show as fully added).
+pub fn git_diff_file(rel_path: &str, staged: bool, with_hunks: bool) -> Result<FileDiff> {
+    let (root, inner) = workspace::split_workspace_path(rel_path)?
+        .ok_or_else(|| anyhow!("choose a file inside a workspace root"))?;
+    let repo = discover(&root)?;
+    let spec = repo_path(&root, &workdir(&repo)?, &inner)?;
+
+    let mut opts = DiffOptions::new();
+    opts.pathspec(&spec).disable_pathspec_match(true);
+    let diff = if staged {
+        let head = repo.head().ok().and_then(|h| h.peel_to_tree().ok());
+        repo.diff_tree_to_index(head.as_ref(), None, Some(&mut opts))
+    } else {
+        opts.include_untracked(true).show_untracked_content(true).recurse_untracked_dirs(true);
+        repo.diff_index_to_workdir(None, Some(&mut opts))
+    }
+    .with_context(|| format!("diff {rel_path}"))?;
+
+    let mut out = FileDiff {
+        path: rel_path.to_string(),
+        patch: String::new(),
+        binary: false,
+        hunks: vec![],
+    };
+    if diff.deltas().len() == 0 {
+        return Ok(out);
+    }
+    let Some(mut patch) = git2::Patch::from_diff(&diff, 0).context("build patch")? else {
+        // libgit2 yields no patch for binary deltas.
+        out.binary = true;
+        return Ok(out);
+    };
+    out.binary = patch.delta().flags().is_binary();
+    out.patch = String::from_utf8_lossy(&patch.to_buf().context("format patch")?).to_string();
+
+    if with_hunks {
+        for h in 0..patch.num_hunks() {
+            let (hunk, line_count) = patch.hunk(h).context("read hunk")?;
+            let mut lines = Vec::with_capacity(line_count);
+            for l in 0..line_count {
+                let line = patch.line_in_hunk(h, l).context("read hunk line")?;
+                lines.push(DiffLine {
+                    origin: line.origin().to_string(),
+                    content: String::from_utf8_lossy(line.content()).trim_end_matches(['\n', '\r']).to_string(),
+                    old_lineno: line.old_lineno(),
+                    new_lineno: line.new_lineno(),
+                });
+            }
+            out.hunks.push(DiffHunk {
+                header: String::from_utf8_lossy(hunk.header()).trim_end().to_string(),
+                old_start: hunk.old_start(),
+                old_lines: hunk.old_lines(),
+                new_start: hunk.new_start(),
+                new_lines: hunk.new_lines(),
+                lines,
+            });
+        }
+    }
+    Ok(out)
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct GitProgress {
+    /// `fetch`, `pull`, `push` or `clone`.
+    pub op: String,
+    /// `receiving`, `resolving`, `pushing`, `checkout` or `remote` (free-form server messages).
+    pub phase: String,
+    pub current: usize,
+    pub total: usize,
+    pub bytes: usize,
+    pub message: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PullResult {
+    /// `up_to_date`, `fast_forward` or `merged`.
+    pub outcome: String,
+    pub commit_id: Option<String>,
+}
+
+/// Secrets credential name for an HTTPS host: `github.com` is stored as `github-com`.
+pub fn host_credential(host: &str) -> String {
+    let slug: String = host
+        .chars()
+        .map(|c| if c.is_ascii_alphanumeric() || c == '-' || c == '_' { c } else { '-' })
+        .collect();
+    slug.chars().take(32).collect()
+}
+
+fn url_host(url: &str) -> Option<&str> {
+    let rest = url.split_once("://").map(|(_, r)| r)?;
+    let authority = rest.split('/').next()?;
+    let host = authority.rsplit('@').next()?;
+    Some(host.split(':').next().unwrap_or(host))
+}
+
+fn stored_token(url: &str, encryption_password: Option<&str>) -> Option<String> {
+    let host_cred = url_host(url).map(host_credential);
+    for cred in [host_cred.as_deref(), Some(secrets::DEFAULT_CREDENTIAL)].into_iter().flatten() {
+        if let Ok(token) = secrets::provider_key_get(GIT_TOKEN_PROVIDER, encryption_password, Some(cred), "git_remote") {
+            if !token.trim().is_empty() {
+                return Some(token.trim().to_string());
+            }
+        }
+    }
+    None
+}
+
+// libgit2 calls the credentials callback again after every rejected attempt; each source is
+// offered once so a bad token ends in an error instead of a loop.
+#[derive(Default)]
+struct CredAttempts {
+    agent: Cell<bool>,
+    key_files: Cell<bool>,
+    token: Cell<bool>,
+    helper: Cell<bool>,
+    default: Cell<bool>,
+}
+
+fn credentials(
+    attempts: &CredAttempts,
+    url: &str,
+    username: Option<&str>,
+    allowed: CredentialType,
+    encryption_password: Option<&str>,
+) -> std::result::Result<Cred, git2::Error> {
+    if allowed.contains(CredentialType::SSH_KEY) {
+        let user = username.unwrap_or("git");
+        if !attempts.agent.replace(true) {
+            if let Ok(c) = Cred::ssh_key_from_agent(user) {
+                return Ok(c);
+            }
+        }
+        if !attempts.key_files.replace(true) {
+            if let Some(ssh) = dirs::home_dir().map(|h| h.join(".ssh")) {
+                for name in ["id_ed25519", "id_ecdsa", "id_rsa"] {
+                    let key = ssh.join(name);
+                    if key.exists() {
+                        return Cred::ssh_key(user, None, &key, None);
+                    }
+                }
+            }
+        }
+    }
+    if allowed.contains(CredentialType::USER_PASS_PLAINTEXT) {
+        if !attempts.token.replace(true) {
+            if let Some(token) = stored_token(url, encryption_password) {
+                // Hosts ignore the user name for token auth, but it may not be empty.
+                return Cred::userpass_plaintext(username.unwrap_or("oauth2"), &token);
+            }
+        }
+        if !attempts.helper.replace(true) {
+            if let Ok(config) = git2::Config::open_default() {
+                if let Ok(c) = Cred::credential_helper(&config, url, username) {
+                    return Ok(c);
+                }
+            }
+        }
+    }
+    if allowed.contains(CredentialType::DEFAULT) && !attempts.default.replace(true) {
+        return Cred::default();
+    }
+    Err(git2::Error::from_str(&format!(
+        "authentication failed for {url}: add an SSH key to your agent or store an access token for this host"
+    )))
+}
+
+fn emit_progress(app: &AppHandle, op: &str, phase: &str, current: usize, total: usize, bytes: usize, message: Option<String>) {
+    let _ = app.emit(
+        GIT_PROGRESS_EVENT,
+        GitProgress {
+            op: op.to_string(),
+            phase: phase.to_string(),
+            current,
+            total,
+            bytes,
+            message,
+        },
+    );
+}
+
+fn remote_callbacks<'a>(
+    app: &'a AppHandle,
+    op: &'a str,
+    attempts: &'a CredAttempts,
+    encryption_password: Option<&'a str>,
+) -> git2::RemoteCallbacks<'a> {
+    let mut cb = git2::RemoteCallbacks::new();
+    cb.credentials(move |url, username, allowed| credentials(attempts, url, username, allowed, encryption_password));
+    cb.transfer_progress(move |p| {
+        let (phase, current) = if p.received_objects() < p.total_objects() {
+            ("receiving", p.received_objects())
+        } else {
+            ("resolving", p.indexed_deltas())
+        };
+        let total = if phase == "receiving" { p.total_objects() } else { p.total_deltas() };
+        emit_progress(app, op, phase, current, total, p.received_bytes(), None);
+        true
+    });
+    cb.push_transfer_progress(move |current, total, bytes| {
+        emit_progress(app, op, "pushing", current, total, bytes, None);
+    });
+    cb.sideband_progress(move |data| {
+        let text = String::from_utf8_lossy(data).trim().to_string();
+        if !text.is_empty() {
+            emit_progress(app, op, "remote", 0, 0, 0, Some(text));
+        }
+        true
+    });
+    cb
+}
+
+/// The current branch, its remote (upstream remote, else `origin`) and the upstream branch
+/// name on that remote when one is configured.
+fn tracking(repo: &Repository, remote: Option<&str>) -> Result<(String, String, Option<String>)> {
+    let head = repo.head().context("the repository has no commits yet")?;
+    if !head.is_branch() {
+        return Err(anyhow!("HEAD is detached; check out a branch first"));
+    }
+    let refname = head.name().ok_or_else(|| anyhow!("branch name is not valid UTF-8"))?.to_string();
+    let branch = head.shorthand().unwrap_or_default().to_string();
+
+    let upstream_remote = repo
+        .branch_upstream_remote(&refname)
+        .ok()
+        .and_then(|b| b.as_str().map(str::to_string));
+    let upstream_merge = repo
+        .branch_upstream_merge(&refname)
+        .ok()
+        .and_then(|b| b.as_str().map(str::to_string))
+        .map(|m| m.trim_start_matches("refs/heads/").to_string());
+
+    let remote = remote
+        .map(str::trim)
+        .filter(|r| !r.is_empty())
+        .map(str::to_string)
+        .or(upstream_remote.clone())
+        .unwrap_or_else(|| "origin".to_string());
+    let upstream = if upstream_remote.as_deref() == Some(remote.as_str()) { upstream_merge } else { None };
+    Ok((branch, remote, upstream))
+}
+
+fn fetch_remote(
+    app: &AppHandle,
+    op: &str,
+    repo: &Repository,
+    remote: &str,
+    refspecs: &[String],
+    encryption_password: Option<&str>,
+) -> Result<()> {
+    let mut r = repo.find_remote(remote).with_context(|| format!("unknown remote: {remote}"))?;
+    let attempts = CredAttempts::default();
+    let mut fo = git2::FetchOptions::new();
+    fo.remote_callbacks(remote_callbacks(app, op, &attempts, encryption_password));
+    fo.download_tags(git2::AutotagOption::Auto);
+    r.fetch(refspecs, Some(&mut fo), None)
+        .with_context(|| format!("fetch from {remote}"))
+}
+
+/// Fetches `remote` (default: the current branch's upstream remote, else `origin`).
+pub fn git_fetch(app: &AppHandle, remote: Option<&str>, root: Option<&str>, encryption_password: Option<&str>) -> Result<()> {
+    let root = open_root(root)?;
+    let repo = discover(&root)?;
+    let remote = match remote.map(str::trim).filter(|r| !r.is_empty()) {
+        Some(r) => r.to_string(),
+        None => tracking(&repo, None).map(|(_, r, _)| r).unwrap_or_else(|_| "origin".to_string()),
+    };
+    // An empty refspec list uses the remote's configured fetch refspecs.
+    fetch_remote(app, "fetch", &repo, &remote, &[], encryption_password)
+}
+
+/// Fetches the current branch's upstream and integrates it: fast-forward when possible,
+/// otherwise a merge commit. Conflicts are left in the working tree for the user to resolve.
+pub fn git_pull(
+    app: &AppHandle,
+    remote: Option<&str>,
+    root: Option<&str>,
+    encryption_password: Option<&str>,
+) -> Result<PullResult> {
+    let root = open_root(root)?;
+    let repo = discover(&root)?;
+    let (branch, remote, upstream) = tracking(&repo, remote)?;
+    let dirty = dirty_paths(&repo)?;
+    if !dirty.is_empty() {
+        return Err(anyhow!("cannot pull: commit or stash your changes first ({} changed files)", dirty.len()));
+    }
+    let upstream = upstream.unwrap_or_else(|| branch.clone());
+    fetch_remote(app, "pull", &repo, &remote, std::slice::from_ref(&upstream), encryption_password)?;
+
+    let fetch_head = repo.find_reference("FETCH_HEAD").context("nothing was fetched")?;
+    let theirs = repo.reference_to_annotated_commit(&fetch_head).context("resolve fetched commit")?;
+    let (analysis, _) = repo.merge_analysis(&[&theirs]).context("analyze merge")?;
+
+    if analysis.is_up_to_date() {
+        return Ok(PullResult {
+            outcome: "up_to_date".to_string(),
+            commit_id: None,
+        });
+    }
+
+    if analysis.is_fast_forward() {
+        let target = theirs.id();
+        let commit = repo.find_commit(target).context("find fetched commit")?;
+        let mut co = git2::build::CheckoutBuilder::new();
+        co.safe();
+        repo.checkout_tree(commit.as_object(), Some(&mut co)).context("update working tree")?;
+        let mut head_ref = repo.head().context("read HEAD")?;
+        head_ref
+            .set_target(target, &format!("pull: fast-forward {remote}/{upstream}"))
+            .context("move branch")?;
+        return Ok(PullResult {
+            outcome: "fast_forward".to_string(),
+            commit_id: Some(target.to_string()),
+        });
+    }
+
+    repo.merge(&[&theirs], None, None).context("merge fetched changes")?;
+    let mut index = repo.index().context("open git index")?;
+    if index.has_conflicts() {
+        let conflicted: Vec<String> = index
+            .conflicts()
+            .map(|it| {
+                it.flatten()
+                    .filter_map(|c| c.our.or(c.their).map(|e| String::from_utf8_lossy(&e.path).to_string()))
+                    .collect()
+            })
+            .unwrap_or_default();
+        return Err(anyhow!(
+            "pull stopped on merge conflicts in {}; resolve them and commit",
+            conflicted.join(", ")
+        ));
+    }
+    let sig = repo
+        .signature()
+        .context("git user.name and user.email are not configured")?;
+    let tree = repo.find_tree(index.write_tree().context("write tree")?).context("find tree")?;
+    let ours = repo.head().and_then(|h| h.peel_to_commit()).context("read HEAD")?;
+    let theirs_commit = repo.find_commit(theirs.id()).context("find fetched commit")?;
+    let message = format!("Merge branch '{upstream}' of {remote} into {branch}");
+    let id = repo
+        .commit(Some("HEAD"), &sig, &sig, &message, &tree, &[&ours, &theirs_commit])
+        .context("create merge commit")?;
+    repo.cleanup_state().context("finish merge")?;
+    Ok(PullResult {
+        outcome: "merged".to_string(),
+        commit_id: Some(id.to_string()),
+    })
+}
+
+/// Pushes the current branch to its upstream (default `origin`, same branch name). A branch
+/// without an upstream gets one once the push succeeds.
+pub fn git_push(
+    app: &AppHandle,
+    remote: Option<&str>,
+    force: bool,
+    root: Option<&str>,
+    encryption_password: Option<&str>,
+) -> Result<()> {
+    let root = open_root(root)?;
+    let repo = discover(&root)?;
+    let (branch, remote, upstream) = tracking(&repo, remote)?;
+    let had_upstream = upstream.is_some();
+    let target = upstream.unwrap_or_else(|| branch.clone());
+    let refspec = format!("{}refs/heads/{branch}:refs/heads/{target}", if force { "+" } else { "" });
+
+    let mut r = repo.find_remote(&remote).with_context(|| format!("unknown remote: {remote}"))?;
+    let attempts = CredAttempts::default();
+    let rejected: std::cell::RefCell<Option<String>> = Default::default();
+    let mut cb = remote_callbacks(app, "push", &attempts, encryption_password);
+    cb.push_update_reference(|refname, status| {
+        if let Some(msg) = status {
+            *rejected.borrow_mut() = Some(format!("{refname}: {msg}"));
+        }
+        Ok(())
+    });
+    let mut po = git2::PushOptions::new();
+    po.remote_callbacks(cb);
+    r.push(&[refspec.as_str()], Some(&mut po))
+        .with_context(|| format!("push to {remote}"))?;
+    drop(po);
+    if let Some(msg) = rejected.into_inner() {
+        return Err(anyhow!("push rejected ({msg}); pull first, or force push"));
+    }
+
+    if !had_upstream {
+        if let Ok(mut local) = repo.find_branch(&branch, BranchType::Local) {
+            let _ = local.set_upstream(Some(&format!("{remote}/{target}")));
+        }
+    }
+    Ok(())
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct StashEntry {
+    /// Position in the stash stack; 0 is the most recent (`stash@{0}`).
+    pub index: usize,
+    pub message: String,
+    pub commit_id: String,
+}
+
+fn stash_entries(repo: &mut Repository) -> Result<Vec<StashEntry>> {
+    let mut out = vec![];
+    repo.stash_foreach(|index, message, id| {
+        out.push(StashEntry {
+            index,
+            message: message.to_string(),
+            commit_id: short_id(*id),
+        });
+        true
+    })
+    .context("list stashes")?;
+    Ok(out)
+}
+
+/// Stashes tracked changes (and untracked files when asked) and resets the working tree.
+pub fn git_stash_save(message: Option<&str>, include_untracked: bool, root: Option<&str>) -> Result<StashEntry> {
+    let root = open_root(root)?;
+    let mut repo = discover(&root)?;
+    let sig = repo
+        .signature()
+        .context("git user.name and user.email are not configured")?;
+    let mut flags = git2::StashFlags::DEFAULT;
+    if include_untracked {
+        flags |= git2::StashFlags::INCLUDE_UNTRACKED;
+    }
+    let message = message.map(str::trim).filter(|m| !m.is_empty());
+    repo.stash_save2(&sig, message, Some(flags)).map_err(|e| {
+        if e.code() == git2::ErrorCode::NotFound {
+            anyhow!("no local changes to stash")
+        } else {
+            anyhow!(e).context("stash changes")
+        }
+    })?;
+    stash_entries(&mut repo)?
+        .into_iter()
+        .next()
+        .ok_or_else(|| anyhow!("stash was not created"))
+}
+
+pub fn git_stash_list(root: Option<&str>) -> Result<Vec<StashEntry>> {
+    let root = open_root(root)?;
+    stash_entries(&mut discover(&root)?)
+}
+
+fn stash_conflict(e: git2::Error, what: &str) -> anyhow::Error {
+    if e.code() == git2::ErrorCode::Conflict {
+        anyhow!("cannot {what} the stash: it conflicts with local changes; commit or stash them first")
+    } else {
+        anyhow!(e).context(format!("{what} stash"))
+    }
+}
+
+/// Re-applies stash `index` to the working tree, keeping it on the stack.
+pub fn git_stash_apply(index: usize, root: Option<&str>) -> Result<()> {
+    let root = open_root(root)?;
+    let mut repo = discover(&root)?;
+    repo.stash_apply(index, None).map_err(|e| stash_conflict(e, "apply"))
+}
+
+/// Applies stash `index` and removes it from the stack when that succeeded.
+pub fn git_stash_pop(index: usize, root: Option<&str>) -> Result<()> {
+    let root = open_root(root)?;
+    let mut repo = discover(&root)?;
+    repo.stash_pop(index, None).map_err(|e| stash_conflict(e, "pop"))
+}
+
+pub fn git_stash_drop(index: usize, root: Option<&str>) -> Result<()> {
+    let root = open_root(root)?;
+    let mut repo = discover(&root)?;
+    repo.stash_drop(index)
+        .with_context(|| format!("drop stash@{{{index}}}"))
+}
+
+/// Folder name `git clone` would pick: the last URL segment without `.git`.
+fn repo_dir_name(url: &str) -> Option<String> {
+    let last = url
+        .trim()
+        .trim_end_matches(['/', '\\'])
+        .rsplit(['/', '\\', ':'])
+        .next()?;
+    let name = last.strip_suffix(".git").unwrap_or(last);
+    (!name.is_empty()).then(|| name.to_string())
+}
+
+/// Clones `url` and opens the clone as the workspace. `dest` is the target folder; when it
+/// is an existing, non-empty folder the repository goes into a new subfolder named after it.
+pub fn git_clone(
+    app: &AppHandle,
+    url: &str,
+    dest: &str,
+    encryption_password: Option<&str>,
+) -> Result<workspace::WorkspaceInfo> {
+    let url = url.trim();
+    if url.is_empty() {
+        return Err(anyhow!("repository URL is empty"));
+    }
+    let dest = PathBuf::from(dest.trim());
+    if dest.as_os_str().is_empty() {
+        return Err(anyhow!("choose a destination folder"));
+    }
+    let non_empty = dest.is_dir()
+        && std::fs::read_dir(&dest)
+            .map(|mut d| d.next().is_some())
+            .unwrap_or(false);
+    let target = if non_empty {
+        let name = repo_dir_name(url).ok_or_else(|| anyhow!("cannot derive a folder name from {url}"))?;
+        dest.join(name)
+    } else {
+        dest
+    };
+    if target.exists() && std::fs::read_dir(&target).map(|mut d| d.next().is_some()).unwrap_or(true) {
+        return Err(anyhow!("destination already exists and is not empty: {}", target.display()));
+    }
+
+    let attempts = CredAttempts::default();
+    let mut fo = git2::FetchOptions::new();
+    fo.remote_callbacks(remote_callbacks(app, "clone", &attempts, encryption_password));
+    let mut co = git2::build::CheckoutBuilder::new();
+    co.progress(|_, current, total| {
+        emit_progress(app, "clone", "checkout", current, total, 0, None);
+    });
+    let cloned = git2::build::RepoBuilder::new()
+        .fetch_options(fo)
+        .with_checkout(co)
+        .clone(url, &target)
+        .with_context(|| format!("clone {url}"));
+    match &cloned {
+        Ok(_) => notifications::notify(Category::Git, "Clone finished", &format!("Cloned into {}", target.display())),
+        Err(e) => notifications::notify(Category::Git, "Clone failed", &redact_error(format!("{e:#}"))),
+    }
+    cloned?;
+
+    workspace::workspace_set(Some(target.to_string_lossy().to_string()))
+}
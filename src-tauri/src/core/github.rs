@@ -0,0 +1,455 @@
+use anyhow::{anyhow, Context, Result};
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value as Json};
+use std::time::{Duration, Instant};
+
+use super::redact::redact_error;
+use super::secrets::{self, StorageKind};
+use super::{git, http, settings};
+
+/// GitHub tokens, personal or from signing in, live in the secrets store under this provider
+/// id. The `git` token saved for `github.com` is used when there is none.
+pub const GITHUB_PROVIDER: &str = "github";
+
+const API: &str = "https://api.github.com";
+const USER_AGENT: &str = concat!("Pompora/", env!("CARGO_PKG_VERSION"), " (https://github.com/mitrashkov/pompora)");
+// Pull requests or issues listed at once, newest activity first.
+const PAGE_SIZE: usize = 50;
+// What signing in asks for: reading and commenting on private repositories needs `repo`.
+const OAUTH_SCOPE: &str = "repo";
+
+#[derive(Debug, Clone, Serialize)]
+pub struct GithubRepo {
+    pub owner: String,
+    pub name: String,
+    pub url: String,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct GithubAuthStatus {
+    /// Where the token is kept; `none` when there is no token of the `github` provider, though a
+    /// `git` token for github.com may still be used.
+    pub storage: StorageKind,
+    /// The account the token belongs to, when there is one and GitHub accepted it.
+    pub login: Option<String>,
+    /// Why the token couldn't be checked.
+    pub error: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct PullRequest {
+    pub number: u64,
+    pub title: String,
+    /// `open`, `closed` or `merged`.
+    pub state: String,
+    pub draft: bool,
+    pub author: String,
+    pub url: String,
+    /// Branch the changes come from, as `owner:branch` when it is in a fork.
+    pub head: String,
+    pub base: String,
+    pub body: String,
+    pub created_at: String,
+    pub updated_at: String,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct Issue {
+    pub number: u64,
+    pub title: String,
+    /// `open` or `closed`.
+    pub state: String,
+    pub author: String,
+    pub url: String,
+    pub labels: Vec<String>,
+    pub comments: u64,
+    pub body: String,
+    pub created_at: String,
+    pub updated_at: String,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct Comment {
+    pub id: u64,
+    pub url: String,
+    pub author: String,
+    pub created_at: String,
+}
+
+/// A device-flow sign-in in progress: the user enters `user_code` at `verification_uri`.
+#[derive(Debug, Clone, Serialize)]
+pub struct DeviceLogin {
+    pub user_code: String,
+    pub verification_uri: String,
+    pub device_code: String,
+    /// Seconds to wait between checks.
+    pub interval: u64,
+    /// Seconds until the code stops working.
+    pub expires_in: u64,
+}
+
+#[derive(Deserialize)]
+struct RawUser {
+    login: String,
+}
+
+#[derive(Deserialize)]
+struct RawRef {
+    label: String,
+    #[serde(rename = "ref")]
+    name: String,
+}
+
+#[derive(Deserialize)]
+struct RawLabel {
+    name: String,
+}
+
+#[derive(Deserialize)]
+struct RawPull {
+    number: u64,
+    title: String,
+    state: String,
+    #[serde(default)]
+    draft: bool,
+    user: Option<RawUser>,
+    html_url: String,
+    head: RawRef,
+    base: RawRef,
+    body: Option<String>,
+    merged_at: Option<String>,
+    created_at: String,
+    updated_at: String,
+}
+
+#[derive(Deserialize)]
+struct RawIssue {
+    number: u64,
+    title: String,
+    state: String,
+    user: Option<RawUser>,
+    html_url: String,
+    #[serde(default)]
+    labels: Vec<RawLabel>,
+    #[serde(default)]
+    comments: u64,
+    body: Option<String>,
+    // Set on pull requests, which the issues API lists too.
+    pull_request: Option<Json>,
+    created_at: String,
+    updated_at: String,
+}
+
+#[derive(Deserialize)]
+struct RawComment {
+    id: u64,
+    html_url: String,
+    user: Option<RawUser>,
+    created_at: String,
+}
+
+fn author(user: Option<RawUser>) -> String {
+    user.map(|u| u.login).unwrap_or_else(|| "ghost".to_string())
+}
+
+/// Owner and repository name from a GitHub remote URL: `https://github.com/o/r.git`,
+/// `git@github.com:o/r.git` or `ssh://git@github.com/o/r`.
+fn parse_remote(url: &str) -> Option<(String, String)> {
+    let url = url.trim();
+    let path = if let Some((_, rest)) = url.split_once("://") {
+        let (authority, path) = rest.split_once('/')?;
+        let host = authority.rsplit('@').next()?.split(':').next()?;
+        host.eq_ignore_ascii_case("github.com").then_some(path)?
+    } else {
+        let (authority, path) = url.split_once(':')?;
+        let host = authority.rsplit('@').next()?;
+        host.eq_ignore_ascii_case("github.com").then_some(path)?
+    };
+    let mut parts = path.trim_matches('/').split('/');
+    let (owner, name) = (parts.next()?, parts.next()?);
+    let name = name.strip_suffix(".git").unwrap_or(name);
+    (!owner.is_empty() && !name.is_empty() && parts.next().is_none()).then(|| (owner.to_string(), name.to_string()))
+}
+
+/// The GitHub repository the workspace root's `origin` remote points at.
+pub fn origin_repo(root: Option<&str>) -> Result<GithubRepo> {
+    let url = git::remote_url("origin", root)?;
+    let (owner, name) = parse_remote(&url).ok_or_else(|| anyhow!("origin ({url}) is not a github.com repository"))?;
+    Ok(GithubRepo {
+        url: format!("https://github.com/{owner}/{name}"),
+        owner,
+        name,
+    })
+}
+
+fn token(encryption_password: Option<&str>) -> Option<String> {
+    let found = secrets::provider_key_get(GITHUB_PROVIDER, encryption_password, None, "github").or_else(|_| {
+        let cred = git::host_credential("github.com");
+        secrets::provider_key_get(git::GIT_TOKEN_PROVIDER, encryption_password, Some(&cred), "github")
+    });
+    found.ok().map(|t| t.trim().to_string()).filter(|t| !t.is_empty())
+}
+
+fn ensure_online() -> Result<()> {
+    if settings::load()?.offline_mode {
+        return Err(anyhow!("offline mode is enabled; GitHub can't be reached"));
+    }
+    Ok(())
+}
+
+// Sends a request to the REST API, with the stored token when there is one. Reading a public
+// repository works without, at a much lower rate limit.
+async fn api(
+    method: reqwest::Method,
+    path: &str,
+    accept: &str,
+    body: Option<Json>,
+    encryption_password: Option<&str>,
+) -> Result<reqwest::Response> {
+    ensure_online()?;
+    let token = token(encryption_password);
+    let mut req = http::client()?
+        .request(method, format!("{API}{path}"))
+        .header(reqwest::header::USER_AGENT, USER_AGENT)
+        .header(reqwest::header::ACCEPT, accept)
+        .header("X-GitHub-Api-Version", "2022-11-28")
+        .timeout(Duration::from_secs(30));
+    if let Some(token) = &token {
+        req = req.bearer_auth(token);
+    }
+    if let Some(body) = body {
+        req = req.json(&body);
+    }
+    let res = req.send().await.map_err(|e| anyhow!(redact_error(e)))?;
+    let status = res.status();
+    if status.is_success() {
+        return Ok(res);
+    }
+    let message = res
+        .json::<Json>()
+        .await
+        .ok()
+        .and_then(|v| v.get("message").and_then(Json::as_str).map(str::to_string))
+        .unwrap_or_default();
+    let hint = match status.as_u16() {
+        401 => "; the GitHub token was turned down, sign in again or save a new one",
+        403 | 404 if token.is_none() => "; sign in with GitHub or save a token to reach private repositories",
+        _ => "",
+    };
+    Err(anyhow!("GitHub answered {status}: {message}{hint}"))
+}
+
+async fn api_json<T: serde::de::DeserializeOwned>(path: &str, encryption_password: Option<&str>) -> Result<T> {
+    api(reqwest::Method::GET, path, "application/vnd.github+json", None, encryption_password)
+        .await?
+        .json()
+        .await
+        .context("read GitHub response")
+}
+
+fn list_state(state: Option<&str>) -> Result<&'static str> {
+    match state.map(str::trim).unwrap_or("open") {
+        "" | "open" => Ok("open"),
+        "closed" => Ok("closed"),
+        "all" => Ok("all"),
+        other => Err(anyhow!("unknown state {other:?}: expected open, closed or all")),
+    }
+}
+
+/// Whether a GitHub token is stored and whose it is.
+pub async fn auth_status(encryption_password: Option<&str>) -> Result<GithubAuthStatus> {
+    let storage = secrets::provider_key_status(GITHUB_PROVIDER, None).map_err(|e| anyhow!(e))?.storage;
+    if token(encryption_password).is_none() {
+        return Ok(GithubAuthStatus {
+            storage,
+            login: None,
+            error: None,
+        });
+    }
+    let (login, error) = match api_json::<RawUser>("/user", encryption_password).await {
+        Ok(user) => (Some(user.login), None),
+        Err(e) => (None, Some(e.to_string())),
+    };
+    Ok(GithubAuthStatus { storage, login, error })
+}
+
+/// Pull requests of the `origin` repository in `state` (`open`, `closed` or `all`), most
+/// recently updated first.
+pub async fn pull_requests(state: Option<&str>, root: Option<&str>, encryption_password: Option<&str>) -> Result<Vec<PullRequest>> {
+    let repo = origin_repo(root)?;
+    let path = format!(
+        "/repos/{}/{}/pulls?state={}&sort=updated&direction=desc&per_page={PAGE_SIZE}",
+        repo.owner,
+        repo.name,
+        list_state(state)?
+    );
+    let raw: Vec<RawPull> = api_json(&path, encryption_password).await?;
+    Ok(raw
+        .into_iter()
+        .map(|p| PullRequest {
+            number: p.number,
+            title: p.title,
+            state: if p.merged_at.is_some() { "merged".to_string() } else { p.state },
+            draft: p.draft,
+            author: author(p.user),
+            url: p.html_url,
+            head: if p.head.label.split(':').next() == Some(repo.owner.as_str()) { p.head.name } else { p.head.label },
+            base: p.base.name,
+            body: p.body.unwrap_or_default(),
+            created_at: p.created_at,
+            updated_at: p.updated_at,
+        })
+        .collect())
+}
+
+/// Issues of the `origin` repository in `state`, most recently updated first. Pull requests,
+/// which GitHub counts as issues too, are left out.
+pub async fn issues(state: Option<&str>, root: Option<&str>, encryption_password: Option<&str>) -> Result<Vec<Issue>> {
+    let repo = origin_repo(root)?;
+    let path = format!(
+        "/repos/{}/{}/issues?state={}&sort=updated&direction=desc&per_page={PAGE_SIZE}",
+        repo.owner,
+        repo.name,
+        list_state(state)?
+    );
+    let raw: Vec<RawIssue> = api_json(&path, encryption_password).await?;
+    Ok(raw
+        .into_iter()
+        .filter(|i| i.pull_request.is_none())
+        .map(|i| Issue {
+            number: i.number,
+            title: i.title,
+            state: i.state,
+            author: author(i.user),
+            url: i.html_url,
+            labels: i.labels.into_iter().map(|l| l.name).collect(),
+            comments: i.comments,
+            body: i.body.unwrap_or_default(),
+            created_at: i.created_at,
+            updated_at: i.updated_at,
+        })
+        .collect())
+}
+
+/// The unified diff of pull request `number` against its base branch.
+pub async fn pull_request_diff(number: u64, root: Option<&str>, encryption_password: Option<&str>) -> Result<String> {
+    let repo = origin_repo(root)?;
+    let path = format!("/repos/{}/{}/pulls/{number}", repo.owner, repo.name);
+    api(reqwest::Method::GET, &path, "application/vnd.github.diff", None, encryption_password)
+        .await?
+        .text()
+        .await
+        .context("read pull request diff")
+}
+
+/// Posts `body` (Markdown) as a comment on pull request or issue `number`.
+pub async fn comment_post(number: u64, body: &str, root: Option<&str>, encryption_password: Option<&str>) -> Result<Comment> {
+    if body.trim().is_empty() {
+        return Err(anyhow!("the comment is empty"));
+    }
+    if token(encryption_password).is_none() {
+        return Err(anyhow!("sign in with GitHub or save a token to post comments"));
+    }
+    let repo = origin_repo(root)?;
+    let path = format!("/repos/{}/{}/issues/{number}/comments", repo.owner, repo.name);
+    let raw: RawComment = api(
+        reqwest::Method::POST,
+        &path,
+        "application/vnd.github+json",
+        Some(json!({ "body": body })),
+        encryption_password,
+    )
+    .await?
+    .json()
+    .await
+    .context("read GitHub response")?;
+    Ok(Comment {
+        id: raw.id,
+        url: raw.html_url,
+        author: author(raw.user),
+        created_at: raw.created_at,
+    })
+}
+
+fn oauth_client_id() -> Result<String> {
+    settings::load()?
+        .github_oauth_client_id
+        .map(|c| c.trim().to_string())
+        .filter(|c| !c.is_empty())
+        .ok_or_else(|| anyhow!("set github_oauth_client_id to sign in with GitHub, or save a personal access token instead"))
+}
+
+async fn oauth_post(url: &str, form: &[(&str, &str)]) -> Result<Json> {
+    ensure_online()?;
+    let res = http::client()?
+        .post(url)
+        .header(reqwest::header::USER_AGENT, USER_AGENT)
+        .header(reqwest::header::ACCEPT, "application/json")
+        .form(form)
+        .timeout(Duration::from_secs(30))
+        .send()
+        .await
+        .map_err(|e| anyhow!(redact_error(e)))?;
+    let status = res.status();
+    let doc: Json = res.json().await.context("read GitHub response")?;
+    if !status.is_success() {
+        let message = doc.get("error_description").or(doc.get("error")).and_then(Json::as_str).unwrap_or_default();
+        return Err(anyhow!("GitHub answered {status}: {message}"));
+    }
+    Ok(doc)
+}
+
+/// Starts signing in with GitHub's device flow; finish with `login_wait`.
+pub async fn login_begin() -> Result<DeviceLogin> {
+    let client_id = oauth_client_id()?;
+    let doc = oauth_post(
+        "https://github.com/login/device/code",
+        &[("client_id", &client_id), ("scope", OAUTH_SCOPE)],
+    )
+    .await?;
+    let text = |key: &str| doc.get(key).and_then(Json::as_str).map(str::to_string).ok_or_else(|| anyhow!("GitHub sent no {key}"));
+    Ok(DeviceLogin {
+        user_code: text("user_code")?,
+        verification_uri: text("verification_uri")?,
+        device_code: text("device_code")?,
+        interval: doc.get("interval").and_then(Json::as_u64).unwrap_or(5),
+        expires_in: doc.get("expires_in").and_then(Json::as_u64).unwrap_or(900),
+    })
+}
+
+/// Waits until the user has entered the code of a `login_begin` sign-in, then saves the token
+/// under the `github` provider and returns the account's login.
+pub async fn login_wait(device_code: &str, interval: u64, encryption_password: Option<&str>) -> Result<String> {
+    let client_id = oauth_client_id()?;
+    let mut interval = Duration::from_secs(interval.max(1));
+    let started = Instant::now();
+    let token = loop {
+        tokio::time::sleep(interval).await;
+        let doc = oauth_post(
+            "https://github.com/login/oauth/access_token",
+            &[
+                ("client_id", &client_id),
+                ("device_code", device_code),
+                ("grant_type", "urn:ietf:params:oauth:grant-type:device_code"),
+            ],
+        )
+        .await?;
+        if let Some(token) = doc.get("access_token").and_then(Json::as_str) {
+            break token.to_string();
+        }
+        match doc.get("error").and_then(Json::as_str).unwrap_or_default() {
+            "authorization_pending" => {}
+            // GitHub asks for 5 more seconds between checks each time.
+            "slow_down" => interval += Duration::from_secs(5),
+            "expired_token" => return Err(anyhow!("the sign-in code expired; start again")),
+            "access_denied" => return Err(anyhow!("sign-in was canceled on GitHub")),
+            other => return Err(anyhow!("GitHub sign-in failed: {other}")),
+        }
+        if started.elapsed() > Duration::from_secs(30 * 60) {
+            return Err(anyhow!("gave up waiting for the sign-in code to be entered"));
+        }
+    };
+    secrets::provider_key_set(GITHUB_PROVIDER, &token, encryption_password, None, "github_login").map_err(|e| anyhow!(e))?;
+    let user: RawUser = api_json("/user", encryption_password).await?;
+    Ok(user.login)
+}
@@ -0,0 +1,62 @@
+use anyhow::{anyhow, Context, Result};
+use std::fs;
+
+use super::settings::{self, ProxySettings};
+
+fn apply_proxy(mut builder: reqwest::ClientBuilder, proxy: &ProxySettings) -> Result<reqwest::ClientBuilder> {
+    if let Some(url) = proxy.url.as_deref().map(|v| v.trim()).filter(|v| !v.is_empty()) {
+        let no_proxy = proxy
+            .no_proxy
+            .iter()
+            .map(|v| v.trim())
+            .filter(|v| !v.is_empty())
+            .collect::<Vec<_>>()
+            .join(",");
+        let p = reqwest::Proxy::all(url)
+            .map_err(|e| anyhow!("invalid proxy URL: {e}"))?
+            .no_proxy(reqwest::NoProxy::from_string(&no_proxy));
+        builder = builder.proxy(p);
+    }
+
+    if let Some(path) = proxy.ca_cert_path.as_deref().map(|v| v.trim()).filter(|v| !v.is_empty()) {
+        let pem = fs::read(path).with_context(|| format!("read proxy CA certificate: {path}"))?;
+        let cert = reqwest::Certificate::from_pem(&pem).with_context(|| format!("parse proxy CA certificate: {path}"))?;
+        builder = builder.add_root_certificate(cert);
+    }
+    Ok(builder)
+}
+
+/// Builds an HTTP client honoring the user's proxy settings. Every outbound request should
+/// use this instead of `reqwest::Client::new()`.
+pub fn client() -> Result<reqwest::Client> {
+    builder()?.build().context("build HTTP client")
+}
+
+/// A client builder with the user's proxy settings applied, for clients that need more
+/// configuration than `client` gives.
+pub fn builder() -> Result<reqwest::ClientBuilder> {
+    let s = settings::load()?;
+    apply_proxy(reqwest::Client::builder(), &s.proxy)
+}
+
+/// Whether `url` points at this machine.
+pub fn is_loopback(url: &reqwest::Url) -> bool {
+    let host = url.host_str().unwrap_or_default();
+    host.eq_ignore_ascii_case("localhost")
+        || host
+            .trim_start_matches('[')
+            .trim_end_matches(']')
+            .parse::<std::net::IpAddr>()
+            .is_ok_and(|ip| ip.is_loopback())
+}
+
+/// Parses a configured endpoint, which must be `https://`, or `http://` to this machine.
+/// `what` names it in errors, e.g. "update feed".
+pub fn parse_secure_url(url: &str, what: &str) -> Result<reqwest::Url> {
+    let parsed = reqwest::Url::parse(url).map_err(|e| anyhow!("invalid {what} {url:?}: {e}"))?;
+    match parsed.scheme() {
+        "https" => Ok(parsed),
+        "http" if is_loopback(&parsed) => Ok(parsed),
+        _ => Err(anyhow!("{what} must use https:// unless it is on this machine")),
+    }
+}
@@ -0,0 +1,285 @@
+use anyhow::{anyhow, Context, Result};
+use base64::{engine::general_purpose::STANDARD as BASE64, Engine as _};
+use reqwest::header::{HeaderMap, HeaderName, HeaderValue};
+use serde::Serialize;
+use sha2::{Digest, Sha256};
+use std::time::{Duration, Instant};
+
+use super::redact::redact_error;
+use super::settings::{self, HttpAuth, HttpCollection, HttpHeader};
+use super::{http, secrets};
+
+// Bytes of a response body kept; the rest isn't read.
+const MAX_BODY_BYTES: usize = 5 * 1024 * 1024;
+const DEFAULT_TIMEOUT: Duration = Duration::from_secs(30);
+const MAX_TIMEOUT: Duration = Duration::from_secs(10 * 60);
+// reqwest's default limit, kept for the offline redirect policy.
+const MAX_REDIRECTS: usize = 10;
+const OFFLINE_ERROR: &str = "offline mode is enabled; only localhost can be reached";
+// Secret store provider id for passwords and tokens of saved requests.
+const SECRET_PROVIDER: &str = "http";
+
+#[derive(Debug, Clone, Serialize)]
+pub struct HttpResponse {
+    pub status: u16,
+    /// Standard reason phrase for `status`, e.g. `Not Found`; empty for unknown codes.
+    pub status_text: String,
+    pub http_version: String,
+    /// Where the response came from, after redirects.
+    pub url: String,
+    pub headers: Vec<HttpHeader>,
+    /// The body as text, or base64 when it isn't UTF-8.
+    pub body: String,
+    pub base64: bool,
+    /// Body bytes received, at most `MAX_BODY_BYTES`.
+    pub size: u64,
+    /// Whether the body was longer and the rest wasn't read.
+    pub truncated: bool,
+    /// Time until the status line and headers arrived, and until the body was read.
+    pub headers_ms: u64,
+    pub total_ms: u64,
+}
+
+fn header_map(headers: &[HttpHeader]) -> Result<HeaderMap> {
+    let mut map = HeaderMap::new();
+    for h in headers.iter().filter(|h| !h.name.trim().is_empty()) {
+        let name = HeaderName::from_bytes(h.name.trim().as_bytes()).map_err(|_| anyhow!("invalid header name: {}", h.name))?;
+        let value = HeaderValue::from_str(&h.value).map_err(|_| anyhow!("invalid value for header {}", h.name))?;
+        map.append(name, value);
+    }
+    Ok(map)
+}
+
+// Text if it is UTF-8, allowing for a character cut in half by the size limit.
+fn body_text(bytes: &[u8], truncated: bool) -> (String, bool) {
+    match std::str::from_utf8(bytes) {
+        Ok(s) => (s.to_string(), false),
+        Err(e) if truncated && e.error_len().is_none() => (String::from_utf8_lossy(&bytes[..e.valid_up_to()]).into_owned(), false),
+        Err(_) => (BASE64.encode(bytes), true),
+    }
+}
+
+/// Sends a request through the user's proxy settings and captures the response. Redirects
+/// are followed. In offline mode only this machine can be reached, redirects included.
+pub async fn http_request(
+    method: &str,
+    url: &str,
+    headers: &[HttpHeader],
+    body: Option<String>,
+    auth: Option<&HttpAuth>,
+    timeout: Option<Duration>,
+) -> Result<HttpResponse> {
+    let method = reqwest::Method::from_bytes(method.trim().to_ascii_uppercase().as_bytes())
+        .map_err(|_| anyhow!("invalid HTTP method: {method}"))?;
+    let url = reqwest::Url::parse(url.trim()).map_err(|e| anyhow!("invalid URL: {e}"))?;
+    if !matches!(url.scheme(), "http" | "https") {
+        return Err(anyhow!("only http:// and https:// URLs are supported"));
+    }
+    let offline = settings::load()?.offline_mode;
+    if offline && !http::is_loopback(&url) {
+        return Err(anyhow!(OFFLINE_ERROR));
+    }
+    let client = if offline {
+        // A local endpoint mustn't be able to redirect out of offline mode.
+        let policy = reqwest::redirect::Policy::custom(|attempt| {
+            if attempt.previous().len() >= MAX_REDIRECTS {
+                attempt.error("too many redirects")
+            } else if http::is_loopback(attempt.url()) {
+                attempt.follow()
+            } else {
+                attempt.error(OFFLINE_ERROR)
+            }
+        });
+        http::builder()?.redirect(policy).build().context("build HTTP client")?
+    } else {
+        http::client()?
+    };
+
+    let mut req = client
+        .request(method, url)
+        .headers(header_map(headers)?)
+        .timeout(timeout.unwrap_or(DEFAULT_TIMEOUT).min(MAX_TIMEOUT));
+    req = match auth {
+        Some(HttpAuth::Basic { username, password }) => req.basic_auth(username, password.as_deref()),
+        Some(HttpAuth::Bearer { token }) => req.bearer_auth(token),
+        None => req,
+    };
+    if let Some(body) = body {
+        req = req.body(body);
+    }
+
+    let started = Instant::now();
+    let mut res = req.send().await.map_err(|e| anyhow!(redact_error(e))).context("send request")?;
+    let headers_ms = started.elapsed().as_millis() as u64;
+    let status = res.status();
+    let http_version = format!("{:?}", res.version());
+    let final_url = res.url().to_string();
+    let headers = res
+        .headers()
+        .iter()
+        .map(|(name, value)| HttpHeader {
+            name: name.to_string(),
+            value: String::from_utf8_lossy(value.as_bytes()).into_owned(),
+        })
+        .collect();
+
+    let mut bytes = Vec::new();
+    let mut truncated = false;
+    while let Some(chunk) = res.chunk().await.map_err(|e| anyhow!(redact_error(e))).context("read response")? {
+        let room = MAX_BODY_BYTES - bytes.len();
+        if chunk.len() > room {
+            bytes.extend_from_slice(&chunk[..room]);
+            truncated = true;
+            break;
+        }
+        bytes.extend_from_slice(&chunk);
+    }
+    let total_ms = started.elapsed().as_millis() as u64;
+    let (body, base64) = body_text(&bytes, truncated);
+
+    Ok(HttpResponse {
+        status: status.as_u16(),
+        status_text: status.canonical_reason().unwrap_or_default().to_string(),
+        http_version,
+        url: final_url,
+        headers,
+        body,
+        base64,
+        size: bytes.len() as u64,
+        truncated,
+        headers_ms,
+        total_ms,
+    })
+}
+
+/// Credential name for a saved request's secret; fits the secret store's 32-character limit.
+fn secret_name(collection: &str, request: &str) -> String {
+    hex::encode(Sha256::digest(format!("{collection}\n{request}").as_bytes()))[..16].to_string()
+}
+
+fn take_secret(auth: &mut HttpAuth) -> Option<String> {
+    let secret = match auth {
+        HttpAuth::Basic { password, .. } => password.take().unwrap_or_default(),
+        HttpAuth::Bearer { token } => std::mem::take(token),
+    };
+    Some(secret).filter(|s| !s.is_empty())
+}
+
+fn put_secret(auth: &mut HttpAuth, secret: String) {
+    match auth {
+        HttpAuth::Basic { password, .. } => *password = Some(secret),
+        HttpAuth::Bearer { token } => *token = secret,
+    }
+}
+
+fn secret_names(collection: &HttpCollection) -> impl Iterator<Item = &str> {
+    collection.requests.iter().filter_map(|r| r.auth_secret.as_deref())
+}
+
+/// Fills saved requests' auth back in from the secret store. A secret that can't be read,
+/// e.g. while the encrypted store is locked, is left empty.
+fn with_secrets(mut collections: Vec<HttpCollection>, caller: &str) -> Vec<HttpCollection> {
+    for r in collections.iter_mut().flat_map(|c| c.requests.iter_mut()) {
+        if let (Some(auth), Some(name)) = (r.auth.as_mut(), r.auth_secret.as_deref()) {
+            if let Ok(secret) = secrets::provider_key_get(SECRET_PROVIDER, None, Some(name), caller) {
+                put_secret(auth, secret);
+            }
+        }
+    }
+    collections
+}
+
+/// Clears the secrets in `names` that no saved request refers to anymore.
+fn forget_secrets(names: Vec<String>, collections: &[HttpCollection], caller: &str) {
+    for name in names {
+        if !collections.iter().flat_map(secret_names).any(|n| n == name) {
+            let _ = secrets::provider_key_clear(SECRET_PROVIDER, Some(&name), caller);
+        }
+    }
+}
+
+pub fn http_collections_list() -> Result<Vec<HttpCollection>> {
+    Ok(with_secrets(settings::load()?.http_collections, "http_collections_list"))
+}
+
+fn validate(collection: HttpCollection) -> Result<HttpCollection> {
+    let name = collection.name.trim().to_string();
+    if name.is_empty() {
+        return Err(anyhow!("collection name is required"));
+    }
+    let mut requests = Vec::with_capacity(collection.requests.len());
+    for mut r in collection.requests {
+        r.name = r.name.trim().to_string();
+        if r.name.is_empty() {
+            return Err(anyhow!("every request in '{name}' needs a name"));
+        }
+        if requests.iter().any(|x: &settings::SavedHttpRequest| x.name == r.name) {
+            return Err(anyhow!("'{name}' has two requests named '{}'", r.name));
+        }
+        r.method = r.method.trim().to_ascii_uppercase();
+        requests.push(r);
+    }
+    Ok(HttpCollection { name, requests })
+}
+
+/// Saves a collection, replacing the one with its name, or the one named `previous_name`
+/// when it is being renamed.
+pub fn http_collections_save(collection: HttpCollection, previous_name: Option<&str>) -> Result<Vec<HttpCollection>> {
+    const CALLER: &str = "http_collections_save";
+    let mut collection = validate(collection)?;
+    let replaced = previous_name.unwrap_or(&collection.name).to_string();
+    let previous: Vec<String> = settings::load()?
+        .http_collections
+        .iter()
+        .filter(|c| c.name == replaced || c.name == collection.name)
+        .flat_map(secret_names)
+        .map(String::from)
+        .collect();
+
+    for r in &mut collection.requests {
+        let Some(auth) = r.auth.as_mut() else {
+            r.auth_secret = None;
+            continue;
+        };
+        let name = secret_name(&collection.name, &r.name);
+        r.auth_secret = match take_secret(auth) {
+            Some(secret) => {
+                secrets::provider_key_set(SECRET_PROVIDER, &secret, None, Some(&name), CALLER).map_err(|e| anyhow!(e))?;
+                Some(name)
+            }
+            // The stored secret couldn't be shown (e.g. the store was locked); keep it.
+            None => previous.contains(&name).then_some(name),
+        };
+    }
+
+    let saved = settings::update(|s| {
+        s.http_collections.retain(|c| c.name == replaced || c.name != collection.name);
+        match s.http_collections.iter().position(|c| c.name == replaced) {
+            Some(i) => s.http_collections[i] = collection,
+            None => s.http_collections.push(collection),
+        }
+        Ok(())
+    })?
+    .http_collections;
+    forget_secrets(previous, &saved, CALLER);
+    Ok(with_secrets(saved, CALLER))
+}
+
+pub fn http_collections_delete(name: &str) -> Result<Vec<HttpCollection>> {
+    const CALLER: &str = "http_collections_delete";
+    let mut previous = vec![];
+    let saved = settings::update(|s| {
+        previous = s
+            .http_collections
+            .iter()
+            .filter(|c| c.name == name)
+            .flat_map(secret_names)
+            .map(String::from)
+            .collect();
+        s.http_collections.retain(|c| c.name != name);
+        Ok(())
+    })?
+    .http_collections;
+    forget_secrets(previous, &saved, CALLER);
+    Ok(with_secrets(saved, CALLER))
+}
@@ -0,0 +1,112 @@
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use once_cell::sync::Lazy;
+
+use super::private_fs;
+
+// Once the log passes this size it is moved to `key-audit.log.1`, shifting older rotations to
+// `.log.2`, `.log.3` and so on, and a fresh log is started; entries are never edited in place.
+const ROTATE_BYTES: u64 = 1024 * 1024;
+// Rotations kept, about 50 MiB of history; only past this is the oldest one dropped.
+const MAX_ROTATIONS: u32 = 50;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum KeyAccess {
+    Read,
+    Write,
+    Clear,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct KeyAuditEntry {
+    pub ts_ms: u64,
+    pub action: KeyAccess,
+    pub provider: String,
+    pub credential: String,
+    /// Command or subsystem that touched the key, e.g. `provider_key_get`, `ai_request` or
+    /// `auth_refresh`.
+    pub caller: String,
+    pub ok: bool,
+}
+
+static WRITE_LOCK: Lazy<Mutex<()>> = Lazy::new(|| Mutex::new(()));
+
+fn log_path() -> Result<PathBuf, String> {
+    let base = dirs::config_dir().ok_or_else(|| "Missing config directory".to_string())?;
+    Ok(base.join("Pompora").join("key-audit.log"))
+}
+
+// `n`th most recent rotation of the log at `path`.
+fn rotation(path: &Path, n: u32) -> PathBuf {
+    path.with_extension(format!("log.{n}"))
+}
+
+fn rotate(path: &Path) {
+    let _ = fs::remove_file(rotation(path, MAX_ROTATIONS));
+    for n in (1..MAX_ROTATIONS).rev() {
+        let from = rotation(path, n);
+        if from.exists() {
+            let _ = fs::rename(&from, rotation(path, n + 1));
+        }
+    }
+    let _ = fs::rename(path, rotation(path, 1));
+}
+
+fn append(entry: &KeyAuditEntry) -> Result<(), String> {
+    let path = log_path()?;
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent).map_err(|e| e.to_string())?;
+    }
+
+    let _guard = WRITE_LOCK.lock().unwrap_or_else(|e| e.into_inner());
+    if fs::metadata(&path).map(|m| m.len() >= ROTATE_BYTES).unwrap_or(false) {
+        rotate(&path);
+    }
+
+    let line = serde_json::to_string(entry).map_err(|e| e.to_string())?;
+    let mut f = private_fs::open_append_private(&path).map_err(|e| e.to_string())?;
+    writeln!(f, "{line}").map_err(|e| e.to_string())
+}
+
+/// Appends one access to the log. Best-effort: a failure to log never blocks key access.
+pub fn record(action: KeyAccess, provider: &str, credential: &str, caller: &str, ok: bool) {
+    let ts_ms = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_millis() as u64)
+        .unwrap_or(0);
+    let _ = append(&KeyAuditEntry {
+        ts_ms,
+        action,
+        provider: provider.to_string(),
+        credential: credential.to_string(),
+        caller: caller.to_string(),
+        ok,
+    });
+}
+
+/// The most recent `limit` entries, newest first, spanning the rotated logs if needed.
+pub fn read_log(limit: usize) -> Result<Vec<KeyAuditEntry>, String> {
+    let path = log_path()?;
+    let mut out: Vec<KeyAuditEntry> = vec![];
+    let rotations = (1..=MAX_ROTATIONS).map(|n| rotation(&path, n));
+    for p in std::iter::once(path.clone()).chain(rotations) {
+        let Ok(raw) = fs::read_to_string(&p) else {
+            continue;
+        };
+        for line in raw.lines().rev() {
+            if out.len() >= limit {
+                return Ok(out);
+            }
+            if let Ok(entry) = serde_json::from_str::<KeyAuditEntry>(line) {
+                out.push(entry);
+            }
+        }
+    }
+    Ok(out)
+}
@@ -0,0 +1,425 @@
+use anyhow::Result;
+use once_cell::sync::Lazy;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+use std::sync::Mutex;
+use std::time::Instant;
+use tauri::{AppHandle, Emitter};
+
+use super::problem_matchers::{workspace_path_of, Diagnostic, DiagnosticsReport, Severity, DIAGNOSTICS_REPORT_EVENT};
+use super::workspace::{self, OpenRoot};
+use super::{projects, settings};
+
+pub const LINT_FINISHED_EVENT: &str = "lint:finished";
+
+// Per linter, like the problem matchers' cap.
+const MAX_DIAGNOSTICS: usize = 500;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Linter {
+    Clippy,
+    Eslint,
+    Ruff,
+}
+
+impl Linter {
+    fn id(self) -> &'static str {
+        match self {
+            Linter::Clippy => "clippy",
+            Linter::Eslint => "eslint",
+            Linter::Ruff => "ruff",
+        }
+    }
+
+    fn lints(self, path: &str) -> bool {
+        let ext = path.rsplit_once('.').map(|(_, e)| e.to_ascii_lowercase()).unwrap_or_default();
+        match self {
+            Linter::Clippy => ext == "rs",
+            Linter::Eslint => matches!(ext.as_str(), "js" | "jsx" | "mjs" | "cjs" | "ts" | "tsx" | "mts" | "cts"),
+            Linter::Ruff => matches!(ext.as_str(), "py" | "pyi"),
+        }
+    }
+}
+
+/// A linter run over one project.
+#[derive(Debug, Clone, Serialize)]
+pub struct LintTarget {
+    pub linter: Linter,
+    /// Workspace path of the project folder it runs in.
+    pub project: String,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct LintFinishedEvent {
+    pub linter: Linter,
+    pub project: String,
+    /// Problems it found in what it checked.
+    pub problems: usize,
+    /// Why it couldn't run or gave no usable output, e.g. when it isn't installed.
+    pub error: Option<String>,
+    pub duration_ms: u64,
+}
+
+// What a run checks: the whole project, or only some of its files (workspace paths). clippy
+// always checks its whole crate.
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Scope {
+    All,
+    Files(Vec<String>),
+}
+
+impl Scope {
+    fn merge(self, other: Scope) -> Scope {
+        match (self, other) {
+            (Scope::Files(mut a), Scope::Files(b)) => {
+                for f in b {
+                    if !a.contains(&f) {
+                        a.push(f);
+                    }
+                }
+                Scope::Files(a)
+            }
+            _ => Scope::All,
+        }
+    }
+}
+
+#[derive(Clone)]
+struct Job {
+    linter: Linter,
+    project: String,
+    dir: PathBuf,
+    root: OpenRoot,
+}
+
+type RunKey = (Linter, PathBuf);
+
+// Running linters by project folder, with what to check once the run ends if more was asked for
+// meanwhile.
+static RUNNING: Lazy<Mutex<HashMap<RunKey, Option<Scope>>>> = Lazy::new(|| Mutex::new(HashMap::new()));
+// Latest findings per linter, across projects; reports always carry all of them.
+static FOUND: Lazy<Mutex<HashMap<Linter, Vec<Diagnostic>>>> = Lazy::new(|| Mutex::new(HashMap::new()));
+
+fn eslint_bin(dir: &Path) -> PathBuf {
+    let name = if cfg!(windows) { "eslint.cmd" } else { "eslint" };
+    dir.join("node_modules").join(".bin").join(name)
+}
+
+// The linters that apply to each detected project: clippy for Cargo projects (once per Cargo
+// workspace), eslint where it is installed in `node_modules`, and ruff for Python projects.
+fn jobs() -> Result<Vec<Job>> {
+    let mut out = vec![];
+    for root in workspace::open_roots()? {
+        let found = projects::detect_in(&root);
+        for project in &found {
+            let Some((_, rel)) = workspace::split_workspace_path(&project.path)? else {
+                continue;
+            };
+            let dir = root.path.join(&rel);
+            let linter = match project.kind.as_str() {
+                "rust" => {
+                    let nested = found.iter().any(|p| {
+                        p.kind == "rust"
+                            && p.path != project.path
+                            && (p.path.is_empty() || project.path.starts_with(&format!("{}/", p.path)))
+                    });
+                    (!nested).then_some(Linter::Clippy)
+                }
+                "node" => eslint_bin(&dir).is_file().then_some(Linter::Eslint),
+                "python" => Some(Linter::Ruff),
+                _ => None,
+            };
+            if let Some(linter) = linter {
+                out.push(Job {
+                    linter,
+                    project: project.path.clone(),
+                    dir,
+                    root: root.clone(),
+                });
+            }
+        }
+    }
+    Ok(out)
+}
+
+fn in_project(project: &str, path: &str) -> bool {
+    project.is_empty() || path == project || path.starts_with(&format!("{project}/"))
+}
+
+/// Runs the linters that apply to the workspace's projects, or with `paths` only those for
+/// these files (clippy still checks the whole crate), skipping the ones the `lint` setting
+/// turns off. `linter` narrows it to one of them. Returns what was started; each run ends with
+/// a `lint:finished` event, and its problems are reported as `diagnostics:report` events for
+/// the source `lint:<linter>`.
+pub fn lint_run(app: AppHandle, paths: Option<Vec<String>>, linter: Option<Linter>) -> Result<Vec<LintTarget>> {
+    workspace::ensure_trusted("Running linters")?;
+    let disabled = settings::load()?.lint.disabled;
+    let jobs: Vec<Job> = jobs()?
+        .into_iter()
+        .filter(|j| !disabled.iter().any(|d| d == j.linter.id()) && linter.is_none_or(|l| l == j.linter))
+        .collect();
+    // The innermost project containing `path` that `linter` runs in; only that one checks it.
+    let owner = |path: &str, linter: Linter| {
+        jobs.iter()
+            .filter(|j| j.linter == linter && in_project(&j.project, path))
+            .max_by_key(|j| j.project.len())
+            .map(|j| j.dir.clone())
+    };
+    let mut started = vec![];
+    for job in &jobs {
+        let scope = match &paths {
+            None => Scope::All,
+            Some(paths) => {
+                let files: Vec<String> = paths
+                    .iter()
+                    .filter(|p| job.linter.lints(p) && owner(p, job.linter).as_ref() == Some(&job.dir))
+                    .cloned()
+                    .collect();
+                if files.is_empty() {
+                    continue;
+                }
+                if job.linter == Linter::Clippy {
+                    Scope::All
+                } else {
+                    Scope::Files(files)
+                }
+            }
+        };
+        started.push(LintTarget {
+            linter: job.linter,
+            project: job.project.clone(),
+        });
+        let key = (job.linter, job.dir.clone());
+        {
+            let mut running = RUNNING.lock().unwrap_or_else(|e| e.into_inner());
+            if let Some(queued) = running.get_mut(&key) {
+                *queued = Some(match queued.take() {
+                    Some(q) => q.merge(scope),
+                    None => scope,
+                });
+                continue;
+            }
+            running.insert(key.clone(), None);
+        }
+        let app = app.clone();
+        let job = job.clone();
+        std::thread::spawn(move || {
+            let mut scope = scope;
+            loop {
+                run(&app, &job, &scope);
+                let mut running = RUNNING.lock().unwrap_or_else(|e| e.into_inner());
+                match running.get_mut(&key).and_then(|q| q.take()) {
+                    Some(next) => scope = next,
+                    None => {
+                        running.remove(&key);
+                        break;
+                    }
+                }
+            }
+        });
+    }
+    Ok(started)
+}
+
+fn command(job: &Job, scope: &Scope) -> Command {
+    let files: Vec<String> = match scope {
+        Scope::All => vec![".".to_string()],
+        Scope::Files(files) => files
+            .iter()
+            .map(|f| {
+                let rel = f.strip_prefix(&job.project).unwrap_or(f).trim_start_matches('/');
+                job.dir.join(rel).to_string_lossy().into_owned()
+            })
+            .collect(),
+    };
+    let mut cmd = match job.linter {
+        Linter::Clippy => {
+            let mut c = Command::new("cargo");
+            c.args(["clippy", "--message-format=json", "--quiet"]);
+            c
+        }
+        Linter::Eslint => {
+            let mut c = Command::new(eslint_bin(&job.dir));
+            c.args(["--format", "json"]).args(files);
+            c
+        }
+        Linter::Ruff => {
+            let mut c = Command::new("ruff");
+            c.args(["check", "--output-format=json", "--exit-zero"]).args(files);
+            c
+        }
+    };
+    cmd.current_dir(&job.dir).stdin(Stdio::null());
+    #[cfg(windows)]
+    {
+        use std::os::windows::process::CommandExt;
+        const CREATE_NO_WINDOW: u32 = 0x0800_0000;
+        cmd.creation_flags(CREATE_NO_WINDOW);
+    }
+    cmd
+}
+
+fn run(app: &AppHandle, job: &Job, scope: &Scope) {
+    let started = Instant::now();
+    let roots = workspace::open_roots().unwrap_or_else(|_| vec![job.root.clone()]);
+    let (found, error) = match command(job, scope).output() {
+        Ok(out) => {
+            let stdout = String::from_utf8_lossy(&out.stdout);
+            let found = match job.linter {
+                Linter::Clippy => parse_clippy(&stdout, &job.dir, &roots),
+                Linter::Eslint => parse_eslint(&stdout, &roots),
+                Linter::Ruff => parse_ruff(&stdout, &job.dir, &roots),
+            };
+            // Findings make a failing exit status expected; without any, the run itself failed.
+            let error = match found {
+                Some(ref f) if out.status.success() || !f.is_empty() => None,
+                _ => {
+                    let stderr = String::from_utf8_lossy(&out.stderr);
+                    let last = stderr.lines().rev().find(|l| !l.trim().is_empty()).unwrap_or_default().trim();
+                    Some(if last.is_empty() { format!("{} failed ({})", job.linter.id(), out.status) } else { last.to_string() })
+                }
+            };
+            (found.unwrap_or_default(), error)
+        }
+        Err(e) => (vec![], Some(format!("could not start {}: {e}", job.linter.id()))),
+    };
+    if let Some(e) = &error {
+        tracing::warn!("{} in {}: {e}", job.linter.id(), job.dir.display());
+    }
+
+    let problems = found.len();
+    // Keep what this run didn't check, and put its findings in place of what it did.
+    let all = {
+        let mut store = FOUND.lock().unwrap_or_else(|e| e.into_inner());
+        let kept = store.entry(job.linter).or_default();
+        kept.retain(|d| match scope {
+            Scope::All => !in_project(&job.project, &d.path),
+            Scope::Files(files) => !files.contains(&d.path),
+        });
+        for d in found {
+            if kept.len() < MAX_DIAGNOSTICS && !kept.contains(&d) {
+                kept.push(d);
+            }
+        }
+        kept.clone()
+    };
+    let _ = app.emit(
+        DIAGNOSTICS_REPORT_EVENT,
+        DiagnosticsReport {
+            source: format!("lint:{}", job.linter.id()),
+            diagnostics: all,
+        },
+    );
+    let _ = app.emit(
+        LINT_FINISHED_EVENT,
+        LintFinishedEvent {
+            linter: job.linter,
+            project: job.project.clone(),
+            problems,
+            error,
+            duration_ms: started.elapsed().as_millis() as u64,
+        },
+    );
+}
+
+fn str_at<'a>(v: &'a serde_json::Value, pointer: &str) -> Option<&'a str> {
+    v.pointer(pointer).and_then(|v| v.as_str())
+}
+
+fn u32_at(v: &serde_json::Value, pointer: &str) -> Option<u32> {
+    v.pointer(pointer).and_then(|v| v.as_u64()).map(|n| n as u32)
+}
+
+// `cargo clippy --message-format=json`: one JSON object per line. Span paths are relative to
+// the Cargo workspace, which may sit above `dir`. `None` if no line was a cargo message.
+fn parse_clippy(stdout: &str, dir: &Path, roots: &[OpenRoot]) -> Option<Vec<Diagnostic>> {
+    let mut any = false;
+    let mut out = vec![];
+    for line in stdout.lines().filter(|l| l.starts_with('{')) {
+        let Ok(v) = serde_json::from_str::<serde_json::Value>(line) else {
+            continue;
+        };
+        any = true;
+        if str_at(&v, "/reason") != Some("compiler-message") {
+            continue;
+        }
+        let msg = &v["message"];
+        let severity = match str_at(msg, "/level") {
+            Some("error") => Severity::Error,
+            Some("warning") => Severity::Warning,
+            _ => continue,
+        };
+        let spans = msg["spans"].as_array().cloned().unwrap_or_default();
+        let Some(span) = spans.iter().find(|s| s["is_primary"].as_bool() == Some(true)) else {
+            continue;
+        };
+        let Some(file) = str_at(span, "/file_name") else {
+            continue;
+        };
+        let abs = dir.ancestors().map(|d| d.join(file)).find(|p| p.is_file()).unwrap_or_else(|| dir.join(file));
+        let d = Diagnostic {
+            path: workspace_path_of(roots, &abs),
+            line: u32_at(span, "/line_start").unwrap_or(1).max(1),
+            column: u32_at(span, "/column_start"),
+            severity,
+            message: str_at(msg, "/message").unwrap_or_default().trim().to_string(),
+            code: str_at(msg, "/code/code").map(str::to_string),
+            matcher: "clippy",
+        };
+        // The same warning comes once per target that compiles the file.
+        if !out.contains(&d) {
+            out.push(d);
+        }
+    }
+    any.then_some(out)
+}
+
+// `eslint --format json`: an array of files with their messages; paths are absolute.
+fn parse_eslint(stdout: &str, roots: &[OpenRoot]) -> Option<Vec<Diagnostic>> {
+    let files: Vec<serde_json::Value> = serde_json::from_str(stdout.trim()).ok()?;
+    let mut out = vec![];
+    for file in &files {
+        let Some(path) = str_at(file, "/filePath") else {
+            continue;
+        };
+        for m in file["messages"].as_array().into_iter().flatten() {
+            out.push(Diagnostic {
+                path: workspace_path_of(roots, Path::new(path)),
+                line: u32_at(m, "/line").unwrap_or(1).max(1),
+                column: u32_at(m, "/column"),
+                severity: if m["severity"].as_u64() == Some(2) { Severity::Error } else { Severity::Warning },
+                message: str_at(m, "/message").unwrap_or_default().trim().to_string(),
+                code: str_at(m, "/ruleId").map(str::to_string),
+                matcher: "eslint",
+            });
+        }
+    }
+    Some(out)
+}
+
+// `ruff check --output-format=json`: an array of findings. Ruff has no severities; findings
+// without a rule code are syntax errors.
+fn parse_ruff(stdout: &str, dir: &Path, roots: &[OpenRoot]) -> Option<Vec<Diagnostic>> {
+    let items: Vec<serde_json::Value> = serde_json::from_str(stdout.trim()).ok()?;
+    let out = items
+        .iter()
+        .filter_map(|item| {
+            let file = str_at(item, "/filename")?;
+            let code = str_at(item, "/code").map(str::to_string);
+            Some(Diagnostic {
+                path: workspace_path_of(roots, &dir.join(file)),
+                line: u32_at(item, "/location/row").unwrap_or(1).max(1),
+                column: u32_at(item, "/location/column"),
+                severity: if code.is_some() { Severity::Warning } else { Severity::Error },
+                message: str_at(item, "/message").unwrap_or_default().trim().to_string(),
+                code,
+                matcher: "ruff",
+            })
+        })
+        .collect();
+    Some(out)
+}
+
@@ -0,0 +1,189 @@
+use serde::{Deserialize, Serialize};
+use std::fmt::Write as _;
+use std::fs;
+use std::io::Write;
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicU8, Ordering};
+use std::sync::Mutex;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use once_cell::sync::Lazy;
+use tracing::field::{Field, Visit};
+use tracing::span;
+use tracing::subscriber::Interest;
+use tauri::Listener;
+use tracing::{Event, Level, Metadata, Subscriber};
+
+use super::private_fs;
+use super::redact::redact;
+use super::settings::{self, LogLevel};
+
+// Once the log passes this size it is moved to `pompora.log.1` (replacing any older one) and a
+// fresh log is started.
+const ROTATE_BYTES: u64 = 5 * 1024 * 1024;
+
+/// One line of the app log.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LogEntry {
+    pub ts_ms: u64,
+    pub level: LogLevel,
+    /// Module that logged it, e.g. `pompora_lib::core::auth`.
+    pub target: String,
+    pub message: String,
+}
+
+static LEVEL: AtomicU8 = AtomicU8::new(LogLevel::Info as u8);
+static WRITE_LOCK: Lazy<Mutex<()>> = Lazy::new(|| Mutex::new(()));
+
+fn log_path() -> Option<PathBuf> {
+    Some(dirs::config_dir()?.join("Pompora").join("logs").join("pompora.log"))
+}
+
+fn level_of(level: &Level) -> LogLevel {
+    match *level {
+        Level::ERROR => LogLevel::Error,
+        Level::WARN => LogLevel::Warn,
+        Level::INFO => LogLevel::Info,
+        Level::DEBUG => LogLevel::Debug,
+        Level::TRACE => LogLevel::Trace,
+    }
+}
+
+/// Changes which messages are logged from now on.
+pub fn set_level(level: LogLevel) {
+    LEVEL.store(level as u8, Ordering::Relaxed);
+}
+
+fn append(entry: &LogEntry) {
+    let Some(path) = log_path() else {
+        return;
+    };
+    if let Some(parent) = path.parent() {
+        let _ = fs::create_dir_all(parent);
+    }
+
+    let _guard = WRITE_LOCK.lock().unwrap_or_else(|e| e.into_inner());
+    if fs::metadata(&path).map(|m| m.len() >= ROTATE_BYTES).unwrap_or(false) {
+        let _ = fs::rename(&path, path.with_extension("log.1"));
+    }
+    let Ok(line) = serde_json::to_string(entry) else {
+        return;
+    };
+    if let Ok(mut f) = private_fs::open_append_private(&path) {
+        let _ = writeln!(f, "{line}");
+    }
+}
+
+// Collects an event's `message` and appends its other fields as `name=value`.
+#[derive(Default)]
+struct Message {
+    text: String,
+    fields: String,
+}
+
+impl Visit for Message {
+    fn record_str(&mut self, field: &Field, value: &str) {
+        if field.name() == "message" {
+            self.text.push_str(value);
+        } else {
+            let _ = write!(self.fields, " {}={value}", field.name());
+        }
+    }
+
+    fn record_debug(&mut self, field: &Field, value: &dyn std::fmt::Debug) {
+        if field.name() == "message" {
+            let _ = write!(self.text, "{value:?}");
+        } else {
+            let _ = write!(self.fields, " {}={value:?}", field.name());
+        }
+    }
+}
+
+// Writes events to the log file as JSON lines, and to stderr in debug builds. Spans aren't
+// used in the app, so they are accepted and ignored.
+struct FileLogger;
+
+impl Subscriber for FileLogger {
+    // The level can change at any time, so no callsite is cached as always or never enabled.
+    fn register_callsite(&self, _metadata: &'static Metadata<'static>) -> Interest {
+        Interest::sometimes()
+    }
+
+    fn enabled(&self, metadata: &Metadata<'_>) -> bool {
+        level_of(metadata.level()) as u8 <= LEVEL.load(Ordering::Relaxed)
+    }
+
+    fn new_span(&self, _span: &span::Attributes<'_>) -> span::Id {
+        span::Id::from_u64(1)
+    }
+
+    fn record(&self, _span: &span::Id, _values: &span::Record<'_>) {}
+
+    fn record_follows_from(&self, _span: &span::Id, _follows: &span::Id) {}
+
+    fn event(&self, event: &Event<'_>) {
+        let mut message = Message::default();
+        event.record(&mut message);
+        let metadata = event.metadata();
+        let entry = LogEntry {
+            ts_ms: SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .map(|d| d.as_millis() as u64)
+                .unwrap_or(0),
+            level: level_of(metadata.level()),
+            target: metadata.target().to_string(),
+            message: redact(&format!("{}{}", message.text, message.fields)),
+        };
+        #[cfg(debug_assertions)]
+        eprintln!("{} {}: {}", metadata.level(), entry.target, entry.message);
+        append(&entry);
+    }
+
+    fn enter(&self, _span: &span::Id) {}
+
+    fn exit(&self, _span: &span::Id) {}
+}
+
+/// Sends `tracing` output to the log file, at the level from the settings. Called once at startup.
+pub fn init() {
+    if let Ok(s) = settings::load() {
+        set_level(s.log_level);
+    }
+    let _ = tracing::subscriber::set_global_default(FileLogger);
+}
+
+/// Follows changes of the `log_level` setting. Called once from app setup.
+pub fn watch(app: &tauri::AppHandle) {
+    app.listen_any(settings::SETTINGS_CHANGED_EVENT, |event| {
+        if !settings::changed_keys_in(event.payload()).iter().any(|k| k == "log_level") {
+            return;
+        }
+        if let Ok(s) = settings::load() {
+            set_level(s.log_level);
+        }
+    });
+}
+
+/// The last `lines` entries at `level` or more severe, oldest first, spanning the rotated log
+/// if needed.
+pub fn logs_tail(lines: usize, level: LogLevel) -> Result<Vec<LogEntry>, String> {
+    let path = log_path().ok_or_else(|| "Missing config directory".to_string())?;
+    let mut out: Vec<LogEntry> = vec![];
+    'files: for p in [path.clone(), path.with_extension("log.1")] {
+        let Ok(raw) = fs::read_to_string(&p) else {
+            continue;
+        };
+        for line in raw.lines().rev() {
+            if out.len() >= lines {
+                break 'files;
+            }
+            if let Ok(entry) = serde_json::from_str::<LogEntry>(line) {
+                if entry.level <= level {
+                    out.push(entry);
+                }
+            }
+        }
+    }
+    out.reverse();
+    Ok(out)
+}
@@ -0,0 +1,498 @@
+use anyhow::{anyhow, Context, Result};
+use once_cell::sync::Lazy;
+use serde::Serialize;
+use std::collections::{BTreeMap, HashMap};
+use std::io::{BufRead, BufReader, Read, Write};
+use std::path::PathBuf;
+use std::process::{Child, ChildStdin, Command, Stdio};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Condvar, Mutex};
+use std::time::{Duration, Instant};
+use tauri::{AppHandle, Emitter, Listener};
+
+use super::problem_matchers::{workspace_path_of, Diagnostic, DiagnosticsReport, Severity, DIAGNOSTICS_REPORT_EVENT};
+use super::process_tree::{ProcessTree, TERMINATE_TIMEOUT};
+use super::settings::{self, LanguageServerConfig};
+use super::workspace::{self, OpenRoot};
+
+pub const LSP_MESSAGE_EVENT: &str = "lsp:message";
+pub const LSP_EXITED_EVENT: &str = "lsp:exited";
+
+// How often a server is checked for exit.
+const POLL_INTERVAL: Duration = Duration::from_millis(200);
+// Id of the `shutdown` request `lsp_stop` sends; its response isn't passed on.
+const SHUTDOWN_ID: &str = "pompora-shutdown";
+// Per server, like the problem matchers' cap.
+const MAX_DIAGNOSTICS: usize = 500;
+// Far beyond what real servers send; a larger `Content-Length` means a broken or hostile server,
+// which is stopped rather than trusted with the allocation.
+const MAX_MESSAGE_BYTES: usize = 64 * 1024 * 1024;
+// How long `exit` waits for the answer to `shutdown`.
+const SHUTDOWN_TIMEOUT: Duration = Duration::from_secs(2);
+
+// Set once the server has answered `shutdown`.
+type Signal = (Mutex<bool>, Condvar);
+
+/// A running language server.
+#[derive(Debug, Clone, Serialize)]
+pub struct LspSession {
+    pub session_id: String,
+    pub server_id: String,
+    pub languages: Vec<String>,
+    /// Workspace path of the root it serves (`""` in a single-root workspace).
+    pub root: String,
+    /// What to pass as `rootUri` and the workspace folder in `initialize`.
+    pub root_uri: String,
+}
+
+/// Payload of `lsp:message`: one JSON-RPC message from a server, as it sent it.
+#[derive(Debug, Clone, Serialize)]
+pub struct LspMessageEvent {
+    pub session_id: String,
+    pub message: serde_json::Value,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct LspExitedEvent {
+    pub session_id: String,
+    pub server_id: String,
+    /// `None` if the process was killed by a signal or its status couldn't be read.
+    pub exit_code: Option<i32>,
+    /// Whether `lsp_stop` ended it.
+    pub stopped: bool,
+    /// Why Pompora dropped the server, e.g. a message over the size limit.
+    pub error: Option<String>,
+}
+
+struct Session {
+    info: LspSession,
+    root_path: PathBuf,
+    stdin: Arc<Mutex<ChildStdin>>,
+    child: Arc<Mutex<Child>>,
+    tree: Arc<ProcessTree>,
+    stopped: Arc<AtomicBool>,
+    shutdown_acked: Arc<Signal>,
+}
+
+static SESSIONS: Lazy<Mutex<HashMap<String, Session>>> = Lazy::new(|| Mutex::new(HashMap::new()));
+
+fn builtin(id: &str, command: &str, args: &[&str], languages: &[&str]) -> LanguageServerConfig {
+    LanguageServerConfig {
+        id: id.to_string(),
+        command: command.to_string(),
+        args: args.iter().map(|a| a.to_string()).collect(),
+        languages: languages.iter().map(|l| l.to_string()).collect(),
+        disabled: false,
+    }
+}
+
+// Servers used when they are on the `PATH`; `language_servers` entries with the same id
+// replace them.
+fn builtin_servers() -> Vec<LanguageServerConfig> {
+    vec![
+        builtin("rust-analyzer", "rust-analyzer", &[], &["rust"]),
+        builtin(
+            "typescript-language-server",
+            "typescript-language-server",
+            &["--stdio"],
+            &["typescript", "javascript"],
+        ),
+        builtin("pyright", "pyright-langserver", &["--stdio"], &["python"]),
+    ]
+}
+
+/// The built-in servers with the `language_servers` setting applied, disabled ones included.
+pub fn lsp_servers() -> Result<Vec<LanguageServerConfig>> {
+    let s = settings::load()?;
+    let mut out = builtin_servers();
+    for config in s.language_servers {
+        match out.iter_mut().find(|c| c.id == config.id) {
+            Some(existing) => *existing = config,
+            None => out.push(config),
+        }
+    }
+    Ok(out)
+}
+
+/// Running servers.
+pub fn lsp_list() -> Result<Vec<LspSession>> {
+    let sessions = SESSIONS.lock().map_err(|_| anyhow!("language servers lock poisoned"))?;
+    let mut out: Vec<LspSession> = sessions.values().map(|s| s.info.clone()).collect();
+    out.sort_by(|a, b| (&a.root, &a.server_id).cmp(&(&b.root, &b.server_id)));
+    Ok(out)
+}
+
+// The root `path` (a workspace path) is in, or the first one.
+fn root_for(path: Option<&str>) -> Result<OpenRoot> {
+    if let Some((root, _)) = path.map(workspace::split_workspace_path).transpose()?.flatten() {
+        return Ok(root);
+    }
+    workspace::open_roots()?
+        .into_iter()
+        .next()
+        .ok_or_else(|| anyhow!("no workspace is open"))
+}
+
+/// Starts the server for `language` in the root that `path` is in (the first root when not
+/// given), or returns the one already running there. Messages the server sends arrive as
+/// `lsp:message` events and `lsp:exited` follows once it exits; `initialize` is left to the
+/// caller. Diagnostics it publishes are also reported as `diagnostics:report` events for the
+/// source `lsp:<session id>`.
+pub fn lsp_start(app: AppHandle, language: &str, path: Option<&str>) -> Result<LspSession> {
+    workspace::ensure_trusted("Starting language servers")?;
+    let config = lsp_servers()?
+        .into_iter()
+        .find(|c| !c.disabled && c.languages.iter().any(|l| l == language))
+        .ok_or_else(|| anyhow!("No language server is configured for {language}"))?;
+    let root = root_for(path)?;
+
+    let mut sessions = SESSIONS.lock().map_err(|_| anyhow!("language servers lock poisoned"))?;
+    if let Some(s) = sessions.values().find(|s| s.info.server_id == config.id && s.root_path == root.path) {
+        return Ok(s.info.clone());
+    }
+
+    let root_uri = reqwest::Url::from_file_path(&root.path)
+        .map_err(|_| anyhow!("workspace path is not absolute: {}", root.path.display()))?
+        .to_string();
+    // npm-installed servers are `.cmd` scripts on Windows, which only the shell can start.
+    let mut cmd = if cfg!(windows) {
+        let mut c = Command::new("cmd");
+        c.arg("/C").arg(&config.command);
+        c
+    } else {
+        Command::new(&config.command)
+    };
+    cmd.args(&config.args)
+        .current_dir(&root.path)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped());
+    #[cfg(windows)]
+    {
+        use std::os::windows::process::CommandExt;
+        const CREATE_NO_WINDOW: u32 = 0x0800_0000;
+        cmd.creation_flags(CREATE_NO_WINDOW);
+    }
+    // A group of its own, so stopping it reaches whatever it starts.
+    #[cfg(unix)]
+    {
+        use std::os::unix::process::CommandExt;
+        cmd.process_group(0);
+    }
+    let mut child = cmd.spawn().with_context(|| format!("start {}", config.command))?;
+    let tree = Arc::new(ProcessTree::new(child.id()));
+    let stdin = child.stdin.take().ok_or_else(|| anyhow!("language server has no stdin"))?;
+
+    let info = LspSession {
+        session_id: format!("lsp-{}", rand::random::<u64>()),
+        server_id: config.id.clone(),
+        languages: config.languages.clone(),
+        root: root.workspace_path(""),
+        root_uri,
+    };
+    let shutdown_acked: Arc<Signal> = Arc::new((Mutex::new(false), Condvar::new()));
+    let failure: Arc<Mutex<Option<String>>> = Arc::new(Mutex::new(None));
+    if let Some(out) = child.stdout.take() {
+        let reader = Reader {
+            session: info.clone(),
+            tree: tree.clone(),
+            shutdown_acked: shutdown_acked.clone(),
+            failure: failure.clone(),
+        };
+        spawn_reader(app.clone(), reader, out);
+    }
+    if let Some(err) = child.stderr.take() {
+        let server_id = config.id.clone();
+        std::thread::spawn(move || {
+            for line in BufReader::new(err).lines().map_while(|l| l.ok()) {
+                tracing::debug!("{server_id}: {line}");
+            }
+        });
+    }
+
+    let child = Arc::new(Mutex::new(child));
+    let stopped = Arc::new(AtomicBool::new(false));
+    sessions.insert(
+        info.session_id.clone(),
+        Session {
+            info: info.clone(),
+            root_path: root.path.clone(),
+            stdin: Arc::new(Mutex::new(stdin)),
+            child: child.clone(),
+            tree,
+            stopped: stopped.clone(),
+            shutdown_acked,
+        },
+    );
+    drop(sessions);
+
+    let session = info.clone();
+    std::thread::spawn(move || {
+        let status = loop {
+            let res = child.lock().unwrap_or_else(|e| e.into_inner()).try_wait();
+            match res {
+                Ok(Some(status)) => break Some(status),
+                Ok(None) => std::thread::sleep(POLL_INTERVAL),
+                Err(_) => break None,
+            }
+        };
+        if let Ok(mut sessions) = SESSIONS.lock() {
+            sessions.remove(&session.session_id);
+        }
+        // What it reported goes away with it.
+        let _ = app.emit(
+            DIAGNOSTICS_REPORT_EVENT,
+            DiagnosticsReport {
+                source: format!("lsp:{}", session.session_id),
+                diagnostics: vec![],
+            },
+        );
+        let _ = app.emit(
+            LSP_EXITED_EVENT,
+            LspExitedEvent {
+                session_id: session.session_id,
+                server_id: session.server_id,
+                exit_code: status.and_then(|s| s.code()),
+                stopped: stopped.load(Ordering::Relaxed),
+                error: failure.lock().unwrap_or_else(|e| e.into_inner()).take(),
+            },
+        );
+    });
+
+    tracing::info!("started {} in {}", config.id, root.path.display());
+    Ok(info)
+}
+
+// Reads one `Content-Length`-framed message; `None` once the stream ends or breaks framing,
+// an error when the message is over `MAX_MESSAGE_BYTES`.
+fn read_message(reader: &mut impl BufRead) -> Result<Option<Vec<u8>>> {
+    let mut len = None;
+    let mut line = String::new();
+    loop {
+        line.clear();
+        if !matches!(reader.read_line(&mut line), Ok(n) if n > 0) {
+            return Ok(None);
+        }
+        let header = line.trim_end_matches(['\r', '\n']);
+        if header.is_empty() {
+            break;
+        }
+        if let Some((name, value)) = header.split_once(':') {
+            if name.trim().eq_ignore_ascii_case("content-length") {
+                len = value.trim().parse::<usize>().ok();
+            }
+        }
+    }
+    let Some(len) = len else {
+        return Ok(None);
+    };
+    if len > MAX_MESSAGE_BYTES {
+        return Err(anyhow!(
+            "language server sent a {len}-byte message, over the {} MiB limit",
+            MAX_MESSAGE_BYTES >> 20
+        ));
+    }
+    let mut body = vec![0; len];
+    if reader.read_exact(&mut body).is_err() {
+        return Ok(None);
+    }
+    Ok(Some(body))
+}
+
+// What the thread reading a server's output shares with the rest of its session.
+struct Reader {
+    session: LspSession,
+    tree: Arc<ProcessTree>,
+    shutdown_acked: Arc<Signal>,
+    // Why the server was dropped, for its `lsp:exited` event.
+    failure: Arc<Mutex<Option<String>>>,
+}
+
+fn spawn_reader(app: AppHandle, shared: Reader, pipe: impl Read + Send + 'static) {
+    std::thread::spawn(move || {
+        let session = &shared.session;
+        let mut reader = BufReader::new(pipe);
+        let roots = workspace::open_roots().unwrap_or_default();
+        // Latest diagnostics per document URI; each publish replaces a document's.
+        let mut published: BTreeMap<String, Vec<Diagnostic>> = BTreeMap::new();
+        loop {
+            let body = match read_message(&mut reader) {
+                Ok(Some(body)) => body,
+                Ok(None) => break,
+                Err(e) => {
+                    tracing::warn!("{}: {e}; stopping it", session.server_id);
+                    *shared.failure.lock().unwrap_or_else(|e| e.into_inner()) = Some(e.to_string());
+                    shared.tree.kill();
+                    break;
+                }
+            };
+            let Ok(message) = serde_json::from_slice::<serde_json::Value>(&body) else {
+                tracing::debug!("{}: unreadable message", session.server_id);
+                continue;
+            };
+            if message.get("id").and_then(|v| v.as_str()) == Some(SHUTDOWN_ID) {
+                let (acked, answered) = &*shared.shutdown_acked;
+                *acked.lock().unwrap_or_else(|e| e.into_inner()) = true;
+                answered.notify_all();
+                continue;
+            }
+            if message.get("method").and_then(|v| v.as_str()) == Some("textDocument/publishDiagnostics") {
+                if let Some(params) = message.get("params") {
+                    publish_diagnostics(&app, session, &roots, &mut published, params);
+                }
+            }
+            let _ = app.emit(
+                LSP_MESSAGE_EVENT,
+                LspMessageEvent {
+                    session_id: session.session_id.clone(),
+                    message,
+                },
+            );
+        }
+    });
+}
+
+// Workspace path of a `file://` URI inside a root, otherwise the file path or the URI itself.
+fn uri_to_path(roots: &[OpenRoot], uri: &str) -> String {
+    match reqwest::Url::parse(uri).ok().and_then(|u| u.to_file_path().ok()) {
+        Some(abs) => workspace_path_of(roots, &abs),
+        None => uri.to_string(),
+    }
+}
+
+fn publish_diagnostics(
+    app: &AppHandle,
+    session: &LspSession,
+    roots: &[OpenRoot],
+    published: &mut BTreeMap<String, Vec<Diagnostic>>,
+    params: &serde_json::Value,
+) {
+    let Some(uri) = params.get("uri").and_then(|v| v.as_str()) else {
+        return;
+    };
+    let path = uri_to_path(roots, uri);
+    let items = params.get("diagnostics").and_then(|v| v.as_array()).cloned().unwrap_or_default();
+    let diagnostics: Vec<Diagnostic> = items
+        .iter()
+        .map(|d| {
+            let start = d.pointer("/range/start");
+            let at = |key: &str| start.and_then(|s| s.get(key)).and_then(|v| v.as_u64()).unwrap_or(0) as u32;
+            Diagnostic {
+                path: path.clone(),
+                // LSP positions count from 0.
+                line: at("line") + 1,
+                column: Some(at("character") + 1),
+                severity: match d.get("severity").and_then(|v| v.as_u64()) {
+                    Some(2) => Severity::Warning,
+                    Some(3 | 4) => Severity::Info,
+                    _ => Severity::Error,
+                },
+                message: d.get("message").and_then(|v| v.as_str()).unwrap_or_default().trim().to_string(),
+                code: d.get("code").and_then(|c| match c {
+                    serde_json::Value::String(s) => Some(s.clone()),
+                    serde_json::Value::Number(n) => Some(n.to_string()),
+                    _ => None,
+                }),
+                matcher: "lsp",
+            }
+        })
+        .collect();
+    if diagnostics.is_empty() {
+        published.remove(uri);
+    } else {
+        published.insert(uri.to_string(), diagnostics);
+    }
+    let _ = app.emit(
+        DIAGNOSTICS_REPORT_EVENT,
+        DiagnosticsReport {
+            source: format!("lsp:{}", session.session_id),
+            diagnostics: published.values().flatten().take(MAX_DIAGNOSTICS).cloned().collect(),
+        },
+    );
+}
+
+fn write_message(stdin: &Mutex<ChildStdin>, message: &serde_json::Value) -> Result<()> {
+    let body = serde_json::to_vec(message)?;
+    let mut stdin = stdin.lock().unwrap_or_else(|e| e.into_inner());
+    write!(stdin, "Content-Length: {}\r\n\r\n", body.len())?;
+    stdin.write_all(&body)?;
+    stdin.flush()?;
+    Ok(())
+}
+
+/// Sends a JSON-RPC request, response or notification to the server, framed for it.
+pub fn lsp_send(session_id: &str, message: &serde_json::Value) -> Result<()> {
+    let stdin = {
+        let sessions = SESSIONS.lock().map_err(|_| anyhow!("language servers lock poisoned"))?;
+        let session = sessions
+            .get(session_id)
+            .ok_or_else(|| anyhow!("Language server is not running: {session_id}"))?;
+        session.stdin.clone()
+    };
+    write_message(&stdin, message).context("write to language server")
+}
+
+/// Asks the server to shut down and, once it has answered, to exit; then stops it and whatever
+/// it started, killing what is left after another `TERMINATE_TIMEOUT`. Returns without waiting; `lsp:exited` follows.
+/// Stopping a server that already exited is not an error.
+pub fn lsp_stop(session_id: &str) -> Result<()> {
+    let sessions = SESSIONS.lock().map_err(|_| anyhow!("language servers lock poisoned"))?;
+    let Some(session) = sessions.get(session_id) else {
+        return Ok(());
+    };
+    stop(session);
+    Ok(())
+}
+
+fn stop(session: &Session) {
+    session.stopped.store(true, Ordering::Relaxed);
+    let stdin = session.stdin.clone();
+    let child = session.child.clone();
+    let tree = session.tree.clone();
+    let shutdown_acked = session.shutdown_acked.clone();
+    std::thread::spawn(move || {
+        let shutdown = serde_json::json!({ "jsonrpc": "2.0", "id": SHUTDOWN_ID, "method": "shutdown" });
+        let exit = serde_json::json!({ "jsonrpc": "2.0", "method": "exit" });
+        // `exit` only follows the answer to `shutdown`, as the spec asks, or `SHUTDOWN_TIMEOUT`
+        // without one. A server that stopped reading is simply stopped below.
+        if write_message(&stdin, &shutdown).is_ok() {
+            let (acked, answered) = &*shutdown_acked;
+            let guard = acked.lock().unwrap_or_else(|e| e.into_inner());
+            drop(answered.wait_timeout_while(guard, SHUTDOWN_TIMEOUT, |done| !*done));
+            let _ = write_message(&stdin, &exit);
+        }
+        // `try_wait` keeps answering once the waiting thread has collected the exit status.
+        let exited = || {
+            let res = child.lock().unwrap_or_else(|e| e.into_inner()).try_wait();
+            !matches!(res, Ok(None))
+        };
+        let deadline = Instant::now() + TERMINATE_TIMEOUT;
+        while Instant::now() < deadline && !exited() {
+            std::thread::sleep(POLL_INTERVAL);
+        }
+        tree.stop(TERMINATE_TIMEOUT, exited);
+    });
+}
+
+/// Stops the servers of a root once it is closed. Called once from app setup.
+pub fn watch(app: &AppHandle) {
+    app.listen_any(settings::SETTINGS_CHANGED_EVENT, |event| {
+        let keys = settings::changed_keys_in(event.payload());
+        if keys.iter().any(|k| k == "workspace_root" || k == "workspace_extra_roots") {
+            stop_closed_roots();
+        }
+    });
+}
+
+// Stops the servers of roots that are no longer open.
+fn stop_closed_roots() {
+    let open: Vec<PathBuf> = workspace::open_roots()
+        .map(|roots| roots.into_iter().map(|r| r.path).collect())
+        .unwrap_or_default();
+    let Ok(sessions) = SESSIONS.lock() else {
+        return;
+    };
+    for session in sessions.values().filter(|s| !open.contains(&s.root_path)) {
+        stop(session);
+    }
+}
@@ -0,0 +1,205 @@
+use serde::{Deserialize, Serialize};
+use similar::{Algorithm, DiffTag};
+use std::ops::Range;
+use std::time::Instant;
+
+use super::diff::DIFF_DEADLINE;
+
+#[derive(Debug, Clone, Copy, Default, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ConflictStyle {
+    /// Ours and theirs, as git writes them by default.
+    #[default]
+    Merge,
+    /// Also the base lines, between `|||||||` and `=======`.
+    Diff3,
+}
+
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default)]
+pub struct MergeOptions {
+    /// Written after the `<<<<<<<` marker; `ours` when not given.
+    pub ours_label: Option<String>,
+    /// Written after the `>>>>>>>` marker; `theirs` when not given.
+    pub theirs_label: Option<String>,
+    pub style: ConflictStyle,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct MergeConflict {
+    /// Lines of the `<<<<<<<` and `>>>>>>>` markers in the merged text, 1-based.
+    pub start_line: u32,
+    pub end_line: u32,
+    /// The base lines both sides changed; `base_lines` is 0 where both inserted after
+    /// `base_start`.
+    pub base_start: u32,
+    pub base_lines: u32,
+    pub base: String,
+    pub ours: String,
+    pub theirs: String,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct MergeResult {
+    /// Both sides' changes applied, with conflict markers where they disagree.
+    pub merged: String,
+    pub conflicts: Vec<MergeConflict>,
+}
+
+// Lines of `base` a side replaced with its own lines `side`.
+struct Change {
+    base: Range<usize>,
+    side: Range<usize>,
+}
+
+fn changes(base: &[&str], side: &[&str]) -> Vec<Change> {
+    let ops = similar::capture_diff_slices_deadline(Algorithm::Myers, base, side, Some(Instant::now() + DIFF_DEADLINE));
+    let mut out: Vec<Change> = vec![];
+    for op in ops.iter().filter(|op| op.tag() != DiffTag::Equal) {
+        let (b, s) = (op.old_range(), op.new_range());
+        // A deletion next to an insertion is one change.
+        match out.last_mut() {
+            Some(last) if last.base.end == b.start && last.side.end == s.start => {
+                last.base.end = b.end;
+                last.side.end = s.end;
+            }
+            _ => out.push(Change { base: b, side: s }),
+        }
+    }
+    out
+}
+
+// What a side has in place of `base[region]`, given its changes there.
+fn side_lines<'a>(base: &[&'a str], side: &[&'a str], changes: &[Change], region: Range<usize>) -> Vec<&'a str> {
+    match (changes.first(), changes.last()) {
+        (Some(first), Some(last)) => {
+            let start = first.side.start - (first.base.start - region.start);
+            let end = last.side.end + (region.end - last.base.end);
+            side[start..end].to_vec()
+        }
+        _ => base[region].to_vec(),
+    }
+}
+
+struct Output<'a> {
+    text: String,
+    lines: u32,
+    eol: &'a str,
+}
+
+impl Output<'_> {
+    fn push(&mut self, lines: &[&str]) {
+        for line in lines {
+            self.text.push_str(line);
+            self.lines += 1;
+        }
+    }
+
+    // Lines followed by a marker, which has to start a line of its own.
+    fn push_section(&mut self, lines: &[&str]) {
+        self.push(lines);
+        if !self.text.is_empty() && !self.text.ends_with('\n') {
+            self.text.push_str(self.eol);
+        }
+    }
+
+    fn push_marker(&mut self, marker: &str, label: &str) {
+        self.text.push_str(marker);
+        if !label.is_empty() {
+            self.text.push(' ');
+            self.text.push_str(label);
+        }
+        self.text.push_str(self.eol);
+        self.lines += 1;
+    }
+}
+
+/// Merges the changes `ours` and `theirs` each made to `base`, line by line. Where both changed
+/// the same or adjacent lines differently, the merged text has conflict markers and the conflict
+/// is listed; the same change made on both sides is taken once.
+pub fn merge_three_way(base: &str, ours: &str, theirs: &str, options: &MergeOptions) -> MergeResult {
+    let base_lines: Vec<&str> = base.split_inclusive('\n').collect();
+    let ours_lines: Vec<&str> = ours.split_inclusive('\n').collect();
+    let theirs_lines: Vec<&str> = theirs.split_inclusive('\n').collect();
+    let ours_changes = changes(&base_lines, &ours_lines);
+    let theirs_changes = changes(&base_lines, &theirs_lines);
+
+    let mut out = Output {
+        text: String::with_capacity(ours.len().max(theirs.len())),
+        lines: 0,
+        eol: if ours.contains("\r\n") { "\r\n" } else { "\n" },
+    };
+    let mut conflicts = vec![];
+    let (mut i, mut j, mut pos) = (0, 0, 0);
+    loop {
+        let start = match (ours_changes.get(i), theirs_changes.get(j)) {
+            (None, None) => break,
+            (Some(a), None) => a.base.start,
+            (None, Some(b)) => b.base.start,
+            (Some(a), Some(b)) => a.base.start.min(b.base.start),
+        };
+        out.push(&base_lines[pos..start]);
+
+        // Take in every change that overlaps or touches the region until it stops growing.
+        let (i0, j0) = (i, j);
+        let mut end = start;
+        loop {
+            let mut grew = false;
+            while let Some(c) = ours_changes.get(i).filter(|c| c.base.start <= end) {
+                end = end.max(c.base.end);
+                i += 1;
+                grew = true;
+            }
+            while let Some(c) = theirs_changes.get(j).filter(|c| c.base.start <= end) {
+                end = end.max(c.base.end);
+                j += 1;
+                grew = true;
+            }
+            if !grew {
+                break;
+            }
+        }
+        pos = end;
+
+        let ours_side = side_lines(&base_lines, &ours_lines, &ours_changes[i0..i], start..end);
+        let theirs_side = side_lines(&base_lines, &theirs_lines, &theirs_changes[j0..j], start..end);
+        if i0 == i || ours_side == theirs_side {
+            out.push(&theirs_side);
+            continue;
+        }
+        if j0 == j {
+            out.push(&ours_side);
+            continue;
+        }
+
+        let base_side = &base_lines[start..end];
+        let start_line = out.lines + 1;
+        if !out.text.is_empty() && !out.text.ends_with('\n') {
+            out.text.push_str(out.eol);
+        }
+        out.push_marker("<<<<<<<", options.ours_label.as_deref().unwrap_or("ours"));
+        out.push_section(&ours_side);
+        if matches!(options.style, ConflictStyle::Diff3) {
+            out.push_marker("|||||||", "base");
+            out.push_section(base_side);
+        }
+        out.push_marker("=======", "");
+        out.push_section(&theirs_side);
+        out.push_marker(">>>>>>>", options.theirs_label.as_deref().unwrap_or("theirs"));
+        conflicts.push(MergeConflict {
+            start_line,
+            end_line: out.lines,
+            base_start: if base_side.is_empty() { start as u32 } else { start as u32 + 1 },
+            base_lines: base_side.len() as u32,
+            base: base_side.concat(),
+            ours: ours_side.concat(),
+            theirs: theirs_side.concat(),
+        });
+    }
+    out.push(&base_lines[pos..]);
+
+    MergeResult {
+        merged: out.text,
+        conflicts,
+    }
+}
@@ -1,8 +1,52 @@
 pub mod secrets;
 pub mod settings;
 pub mod workspace;
+pub mod fs_watch;
 pub mod fsops;
+pub mod fs_journal;
+pub mod encoding;
+pub mod write_policy;
+pub mod git;
+pub mod projects;
+pub mod archive;
 pub mod search;
+pub mod search_index;
+pub mod fuzzy;
 pub mod ai;
+pub mod ai_queue;
 pub mod terminal;
+pub mod shell_integration;
+pub mod shells;
+pub mod wsl;
+pub mod terminal_history;
+pub mod tasks;
+pub mod problem_matchers;
+pub mod process_tree;
+pub mod sandbox;
 pub mod auth;
+pub mod redact;
+pub mod private_fs;
+pub mod key_audit;
+pub mod http;
+pub mod logging;
+pub mod telemetry;
+pub mod lsp;
+pub mod outline;
+pub mod lint;
+pub mod diff;
+pub mod merge;
+pub mod snippets;
+pub mod updates;
+pub mod notifications;
+pub mod todos;
+pub mod chat_export;
+pub mod http_client;
+pub mod dotenv;
+pub mod ports;
+pub mod dependencies;
+pub mod dependency_audit;
+pub mod protected_paths;
+pub mod command_safety;
+pub mod github;
+pub mod commit_lint;
+pub mod spellcheck;
@@ -0,0 +1,56 @@
+use once_cell::sync::OnceCell;
+use tauri::{AppHandle, Manager};
+use tauri_plugin_notification::NotificationExt;
+
+use super::settings;
+
+static APP: OnceCell<AppHandle> = OnceCell::new();
+
+/// What a notification is about; each can be turned off in `notifications.disabled`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Category {
+    /// Replies to the chat and model comparisons.
+    Ai,
+    /// Clones.
+    Git,
+    /// Search indexes built from scratch.
+    Indexing,
+    /// Tasks that finished on their own.
+    Tasks,
+}
+
+impl Category {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Category::Ai => "ai",
+            Category::Git => "git",
+            Category::Indexing => "indexing",
+            Category::Tasks => "tasks",
+        }
+    }
+}
+
+/// Lets `notify` reach the OS. Called once from app setup.
+pub fn init(app: AppHandle) {
+    let _ = APP.set(app);
+}
+
+/// Shows an OS notification, unless a Pompora window has focus (the app shows its own progress
+/// there) or the settings turn notifications or `category` off.
+pub fn notify(category: Category, title: &str, body: &str) {
+    let Some(app) = APP.get() else {
+        return;
+    };
+    if app.webview_windows().values().any(|w| w.is_focused().unwrap_or(false)) {
+        return;
+    }
+    let Ok(s) = settings::load() else {
+        return;
+    };
+    if !s.notifications.enabled || s.notifications.disabled.iter().any(|c| c == category.as_str()) {
+        return;
+    }
+    if let Err(e) = app.notification().builder().title(title).body(body).show() {
+        tracing::debug!("notification not shown: {e}");
+    }
+}
@@ -0,0 +1,493 @@
+use anyhow::Result;
+use once_cell::sync::Lazy;
+use regex::Regex;
+use serde::Serialize;
+
+use super::fsops;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum SymbolKind {
+    Module,
+    Struct,
+    Enum,
+    Trait,
+    Impl,
+    Interface,
+    Class,
+    Function,
+    Method,
+    Type,
+    Constant,
+}
+
+impl SymbolKind {
+    // Whether declarations in its body are listed under it; function bodies aren't searched.
+    fn is_container(self) -> bool {
+        !matches!(self, SymbolKind::Function | SymbolKind::Method | SymbolKind::Type | SymbolKind::Constant)
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct DocumentSymbol {
+    /// For impl blocks, the implemented type, e.g. `Display for Config`.
+    pub name: String,
+    pub kind: SymbolKind,
+    /// Where the name is, 1-based.
+    pub line: u32,
+    pub column: u32,
+    /// Last line of the declaration's body; `line` when it has none.
+    pub end_line: u32,
+    pub children: Vec<DocumentSymbol>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Lang {
+    Rust,
+    Script,
+    Python,
+}
+
+fn lang_of(language: Option<&str>, path: Option<&str>) -> Option<Lang> {
+    if let Some(l) = language {
+        return match l {
+            "rust" => Some(Lang::Rust),
+            "typescript" | "javascript" | "typescriptreact" | "javascriptreact" => Some(Lang::Script),
+            "python" => Some(Lang::Python),
+            _ => None,
+        };
+    }
+    let ext = path?.rsplit_once('.')?.1.to_ascii_lowercase();
+    match ext.as_str() {
+        "rs" => Some(Lang::Rust),
+        "ts" | "tsx" | "mts" | "cts" | "js" | "jsx" | "mjs" | "cjs" => Some(Lang::Script),
+        "py" | "pyi" => Some(Lang::Python),
+        _ => None,
+    }
+}
+
+// How the body of a declaration is found after its header.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Body {
+    // The first `{` outside parentheses, unless a `;` comes first.
+    Braces,
+    // A `{` right after the header, e.g. `type X = {` or `=> {`.
+    ImmediateBrace,
+    // Up to the `;` ending the statement, e.g. a `static` with its initializer.
+    Statement,
+    // An indented block after the `:` ending the header.
+    Indented,
+}
+
+struct Rule {
+    re: Regex,
+    kind: SymbolKind,
+    body: Body,
+    // Only inside a class body, where e.g. `name(` declares a method.
+    member: bool,
+}
+
+fn rule(re: &str, kind: SymbolKind, body: Body) -> Rule {
+    Rule {
+        re: Regex::new(re).expect("valid outline regex"),
+        kind,
+        body,
+        member: false,
+    }
+}
+
+// The name is the first capture group; impl blocks are named separately.
+static RUST_RULES: Lazy<Vec<Rule>> = Lazy::new(|| {
+    const VIS: &str = r"(?m)^[ \t]*(?:pub(?:\([^)]*\))?\s+)?";
+    vec![
+        rule(&format!(r#"{VIS}(?:(?:const|async|unsafe|default|extern\s+"[^"]*")\s+)*fn\s+(\w+)"#), SymbolKind::Function, Body::Braces),
+        rule(&format!(r"{VIS}struct\s+(\w+)"), SymbolKind::Struct, Body::Braces),
+        rule(&format!(r"{VIS}enum\s+(\w+)"), SymbolKind::Enum, Body::Braces),
+        rule(&format!(r"{VIS}(?:unsafe\s+)?(?:auto\s+)?trait\s+(\w+)"), SymbolKind::Trait, Body::Braces),
+        rule(&format!(r"{VIS}mod\s+(\w+)"), SymbolKind::Module, Body::Braces),
+        rule(&format!(r"{VIS}type\s+(\w+)"), SymbolKind::Type, Body::Statement),
+        rule(&format!(r"{VIS}(?:const|static)\s+(?:mut\s+)?([A-Z_][A-Z0-9_]*)\s*:"), SymbolKind::Constant, Body::Statement),
+        rule(r"(?m)^[ \t]*(?:unsafe\s+)?(impl)\b", SymbolKind::Impl, Body::Braces),
+    ]
+});
+
+static SCRIPT_RULES: Lazy<Vec<Rule>> = Lazy::new(|| {
+    const EXPORT: &str = r"(?m)^[ \t]*(?:export\s+)?(?:default\s+)?(?:declare\s+)?";
+    let mut rules = vec![
+        rule(&format!(r"{EXPORT}(?:abstract\s+)?class\s+([\w$]+)"), SymbolKind::Class, Body::Braces),
+        rule(&format!(r"{EXPORT}interface\s+([\w$]+)"), SymbolKind::Interface, Body::Braces),
+        rule(&format!(r"{EXPORT}(?:const\s+)?enum\s+([\w$]+)"), SymbolKind::Enum, Body::Braces),
+        rule(&format!(r"{EXPORT}(?:namespace|module)\s+([\w$]+)"), SymbolKind::Module, Body::Braces),
+        rule(&format!(r"{EXPORT}type\s+([\w$]+)[^=;]*="), SymbolKind::Type, Body::ImmediateBrace),
+        rule(&format!(r"{EXPORT}(?:async\s+)?function\s*\*?\s*([\w$]+)"), SymbolKind::Function, Body::Braces),
+        rule(
+            &format!(r"{EXPORT}(?:const|let|var)\s+([\w$]+)\s*(?::[^=]+)?=\s*(?:async\s+)?(?:\([^)]*\)|[\w$]+)\s*(?::[^=]+)?=>"),
+            SymbolKind::Function,
+            Body::ImmediateBrace,
+        ),
+    ];
+    const MODIFIERS: &str = r"(?m)^[ \t]*(?:(?:public|private|protected|static|readonly|abstract|override|async|get|set)\s+)*";
+    for (re, body) in [
+        (format!(r"{MODIFIERS}\*?([\w$]+)\s*(?:<[^>]*>)?\s*\("), Body::Braces),
+        (format!(r"{MODIFIERS}([\w$]+)\s*(?::[^=]+)?=\s*(?:async\s+)?(?:\([^)]*\)|[\w$]+)\s*=>"), Body::ImmediateBrace),
+    ] {
+        rules.push(Rule {
+            member: true,
+            ..rule(&re, SymbolKind::Method, body)
+        });
+    }
+    rules
+});
+
+static PYTHON_RULES: Lazy<Vec<Rule>> = Lazy::new(|| {
+    vec![
+        rule(r"(?m)^[ \t]*(?:async[ \t]+)?def[ \t]+(\w+)", SymbolKind::Function, Body::Indented),
+        rule(r"(?m)^[ \t]*class[ \t]+(\w+)", SymbolKind::Class, Body::Indented),
+    ]
+});
+
+// Words the member rule would otherwise take for method names.
+const NOT_MEMBERS: [&str; 8] = ["if", "for", "while", "switch", "catch", "return", "super", "function"];
+
+// Blanks `out[from..to]` except for line breaks, keeping every offset where it was.
+fn blank(out: &mut [u8], from: usize, to: usize) {
+    let to = to.min(out.len());
+    for b in &mut out[from.min(to)..to] {
+        if *b != b'\n' {
+            *b = b' ';
+        }
+    }
+}
+
+// Offset just past the closing `quote` of a string starting at `from`, honouring backslashes.
+fn string_end(b: &[u8], from: usize, quote: &[u8], single_line: bool) -> usize {
+    let mut i = from;
+    while i < b.len() {
+        if b[i] == b'\\' {
+            i += 2;
+            continue;
+        }
+        if single_line && b[i] == b'\n' {
+            return i;
+        }
+        if b[i..].starts_with(quote) {
+            return i + quote.len();
+        }
+        i += 1;
+    }
+    b.len()
+}
+
+// `src` with comments and the contents of string literals replaced by spaces, so braces and
+// keywords in them don't count. Delimiters are ASCII, so every blanked run is whole characters.
+fn code_only(src: &str, lang: Lang) -> String {
+    let b = src.as_bytes();
+    let mut out = b.to_vec();
+    let is_ident = |c: u8| c.is_ascii_alphanumeric() || c == b'_';
+    let ident_before = |i: usize| {
+        i > 0 && is_ident(b[i - 1]) && !(b[i - 1] == b'b' && (i < 2 || !is_ident(b[i - 2])))
+    };
+    let mut i = 0;
+    while i < b.len() {
+        let rest = &b[i..];
+        let c = b[i];
+        if (lang == Lang::Python && c == b'#') || (lang != Lang::Python && rest.starts_with(b"//")) {
+            let end = rest.iter().position(|&c| c == b'\n').map_or(b.len(), |p| i + p);
+            blank(&mut out, i, end);
+            i = end;
+        } else if lang != Lang::Python && rest.starts_with(b"/*") {
+            // Rust block comments nest.
+            let mut depth = 0;
+            let mut j = i;
+            while j < b.len() {
+                if b[j..].starts_with(b"/*") {
+                    depth += 1;
+                    j += 2;
+                } else if b[j..].starts_with(b"*/") {
+                    depth -= 1;
+                    j += 2;
+                    if depth == 0 || lang != Lang::Rust {
+                        break;
+                    }
+                } else {
+                    j += 1;
+                }
+            }
+            blank(&mut out, i, j);
+            i = j;
+        } else if lang == Lang::Python && (rest.starts_with(b"\"\"\"") || rest.starts_with(b"'''")) {
+            let end = string_end(b, i + 3, &rest[..3], false);
+            blank(&mut out, i + 3, end.saturating_sub(3).max(i + 3));
+            i = end;
+        } else if lang == Lang::Rust && c == b'r' && !ident_before(i) && matches!(b.get(i + 1), Some(b'#' | b'"')) {
+            // Raw strings: `r"..."`, `r#"..."#`, `br"..."`.
+            let hashes = rest[1..].iter().take_while(|&&c| c == b'#').count();
+            if rest.get(1 + hashes) != Some(&b'"') {
+                i += 1;
+                continue;
+            }
+            let close = format!("\"{}", "#".repeat(hashes));
+            let start = i + 2 + hashes;
+            let end = src[start..].find(&close).map_or(b.len(), |p| start + p);
+            blank(&mut out, start, end);
+            i = end + close.len();
+        } else if c == b'"' || (c == b'\'' && lang != Lang::Rust) || (c == b'`' && lang == Lang::Script) {
+            let end = string_end(b, i + 1, &[c], c != b'`' && lang != Lang::Rust);
+            blank(&mut out, i + 1, end.saturating_sub(1).max(i + 1));
+            i = end;
+        } else if c == b'\'' {
+            // Rust: a char literal, or a lifetime if no quote closes it right away.
+            let len = if rest.get(1) == Some(&b'\\') {
+                rest.iter().skip(2).position(|&c| c == b'\'').map(|p| p + 3)
+            } else {
+                let ch = src[i + 1..].chars().next().map_or(1, char::len_utf8);
+                (rest.get(1 + ch) == Some(&b'\'')).then_some(ch + 2)
+            };
+            match len {
+                Some(len) => {
+                    blank(&mut out, i + 1, i + len - 1);
+                    i += len;
+                }
+                None => i += 1,
+            }
+        } else {
+            i += 1;
+        }
+    }
+    String::from_utf8(out).unwrap_or_default()
+}
+
+// Offset of the `}` closing the `{` at `open`, or the end of the text.
+fn closing_brace(code: &[u8], open: usize) -> usize {
+    let mut depth = 0usize;
+    for (i, &c) in code.iter().enumerate().skip(open) {
+        match c {
+            b'{' => depth += 1,
+            b'}' => {
+                depth -= 1;
+                if depth == 0 {
+                    return i;
+                }
+            }
+            _ => {}
+        }
+    }
+    code.len()
+}
+
+// Where the body after a header ending at `from` begins and ends, if it has one.
+fn find_body(code: &str, from: usize, body: Body) -> Option<(usize, usize)> {
+    let b = code.as_bytes();
+    match body {
+        Body::ImmediateBrace => {
+            let open = from + b[from..].iter().position(|c| !c.is_ascii_whitespace())?;
+            (b[open] == b'{').then(|| (open, closing_brace(b, open)))
+        }
+        Body::Braces => {
+            let mut parens = 0i32;
+            let mut i = from;
+            while i < b.len() {
+                match b[i] {
+                    b'(' | b'[' => parens += 1,
+                    b')' | b']' => parens -= 1,
+                    b'{' if parens <= 0 => {
+                        // An object type, e.g. in `f(): { ok: boolean } {`, is part of the header.
+                        let before = code[..i].trim_end().bytes().next_back();
+                        if !matches!(before, Some(b':' | b'|' | b'&' | b',' | b'<' | b'=')) {
+                            return Some((i, closing_brace(b, i)));
+                        }
+                        i = closing_brace(b, i);
+                    }
+                    b';' | b'}' if parens <= 0 => return None,
+                    _ => {}
+                }
+                i += 1;
+            }
+            None
+        }
+        Body::Statement => {
+            let mut depth = 0i32;
+            for (i, &c) in b.iter().enumerate().skip(from) {
+                match c {
+                    b'(' | b'[' | b'{' => depth += 1,
+                    b')' | b']' | b'}' => depth -= 1,
+                    b';' if depth <= 0 => return Some((from, i)),
+                    _ => {}
+                }
+                if depth < 0 {
+                    return None;
+                }
+            }
+            None
+        }
+        Body::Indented => {
+            // The header may wrap inside its parentheses; it ends at the `:` outside them.
+            let mut parens = 0i32;
+            let colon = b.iter().enumerate().skip(from).find_map(|(i, &c)| {
+                match c {
+                    b'(' | b'[' => parens += 1,
+                    b')' | b']' => parens -= 1,
+                    b':' if parens <= 0 => return Some(i),
+                    _ => {}
+                }
+                None
+            })?;
+            let line_start = code[..from].rfind('\n').map_or(0, |p| p + 1);
+            let indent = b[line_start..].iter().take_while(|c| matches!(c, b' ' | b'\t')).count();
+            let header_line_end = code[colon..].find('\n').map_or(code.len(), |p| colon + p);
+            if !code[colon + 1..header_line_end].trim().is_empty() {
+                // `def f(): pass`
+                return Some((colon, header_line_end));
+            }
+            let mut end = header_line_end;
+            let mut pos = header_line_end;
+            while pos < code.len() {
+                let start = pos + 1;
+                let line_end = code[start.min(code.len())..].find('\n').map_or(code.len(), |p| start + p);
+                let line = &code[start.min(code.len())..line_end];
+                pos = line_end;
+                if line.trim().is_empty() {
+                    continue;
+                }
+                if line.len() - line.trim_start().len() <= indent {
+                    break;
+                }
+                end = line_end;
+            }
+            Some((colon, end))
+        }
+    }
+}
+
+// `Display for Config<T>` from the text between `impl` and its `{`.
+fn impl_name(header: &str) -> String {
+    let mut s = header.trim_start();
+    if s.starts_with('<') {
+        let mut depth = 0;
+        for (i, c) in s.char_indices() {
+            match c {
+                '<' => depth += 1,
+                '>' => {
+                    depth -= 1;
+                    if depth == 0 {
+                        s = &s[i + 1..];
+                        break;
+                    }
+                }
+                _ => {}
+            }
+        }
+    }
+    let s = s.split(" where").next().unwrap_or(s).split("\nwhere").next().unwrap_or(s);
+    s.split_whitespace().collect::<Vec<_>>().join(" ")
+}
+
+struct Node {
+    symbol: DocumentSymbol,
+    parent: Option<usize>,
+    end: usize,
+}
+
+fn line_col(line_starts: &[usize], src: &str, at: usize) -> (u32, u32) {
+    let line = line_starts.partition_point(|&s| s <= at).saturating_sub(1);
+    let column = src[line_starts[line]..at].chars().count() + 1;
+    (line as u32 + 1, column as u32)
+}
+
+fn outline(src: &str, lang: Lang) -> Vec<DocumentSymbol> {
+    let code = code_only(src, lang);
+    let rules: &[Rule] = match lang {
+        Lang::Rust => &RUST_RULES,
+        Lang::Script => &SCRIPT_RULES,
+        Lang::Python => &PYTHON_RULES,
+    };
+    // Every match of every rule, by where its name is.
+    let mut found: Vec<(usize, usize, usize, &Rule)> = vec![];
+    for rule in rules {
+        for caps in rule.re.captures_iter(&code) {
+            let (Some(all), Some(name)) = (caps.get(0), caps.get(1)) else {
+                continue;
+            };
+            found.push((name.start(), name.end(), all.end(), rule));
+        }
+    }
+    found.sort_by_key(|f| f.0);
+
+    let line_starts: Vec<usize> = std::iter::once(0).chain(src.match_indices('\n').map(|(i, _)| i + 1)).collect();
+    let mut nodes: Vec<Node> = vec![];
+    // Declarations whose bodies contain the current position, innermost last.
+    let mut open: Vec<usize> = vec![];
+    let mut header_end = 0;
+    for (name_start, name_end, match_end, rule) in found {
+        if name_start < header_end {
+            continue;
+        }
+        while open.last().is_some_and(|&n| nodes[n].end < name_start) {
+            open.pop();
+        }
+        let parent = open.last().copied();
+        let parent_kind = parent.map(|p| nodes[p].symbol.kind);
+        if parent_kind.is_some_and(|k| !k.is_container()) {
+            continue;
+        }
+        let name = &code[name_start..name_end];
+        if rule.member && (parent_kind != Some(SymbolKind::Class) || NOT_MEMBERS.contains(&name)) {
+            continue;
+        }
+        let body = find_body(&code, match_end, rule.body);
+        let name = if rule.kind == SymbolKind::Impl {
+            let Some((open_at, _)) = body else { continue };
+            impl_name(&code[match_end..open_at])
+        } else {
+            name.to_string()
+        };
+        let kind = match (rule.kind, parent_kind) {
+            (SymbolKind::Function, Some(SymbolKind::Impl | SymbolKind::Trait | SymbolKind::Class)) => SymbolKind::Method,
+            (kind, _) => kind,
+        };
+        let (line, column) = line_col(&line_starts, src, name_start);
+        let end = body.map_or(name_end, |(_, close)| close);
+        header_end = body.map_or(name_end, |(open_at, _)| open_at + 1);
+        nodes.push(Node {
+            symbol: DocumentSymbol {
+                name,
+                kind,
+                line,
+                column,
+                end_line: line_col(&line_starts, src, end.min(src.len())).0,
+                children: vec![],
+            },
+            parent,
+            end,
+        });
+        if body.is_some() {
+            open.push(nodes.len() - 1);
+        }
+    }
+
+    // Children come after their parents, so moving them up from the back keeps their order.
+    let mut roots = vec![];
+    while let Some(node) = nodes.pop() {
+        match node.parent {
+            Some(p) => nodes[p].symbol.children.insert(0, node.symbol),
+            None => roots.insert(0, node.symbol),
+        }
+    }
+    roots
+}
+
+/// Outline of a file: its declarations, with those in modules, classes, traits and impl blocks
+/// nested under them. `content` stands in for the file when given, e.g. an unsaved buffer. The
+/// language is the editor language id `language`, or else follows from `path`'s extension;
+/// Rust, TypeScript, JavaScript and Python are understood, anything else has an empty outline.
+pub fn document_symbols(path: Option<&str>, content: Option<String>, language: Option<&str>) -> Result<Vec<DocumentSymbol>> {
+    let Some(lang) = lang_of(language, path) else {
+        return Ok(vec![]);
+    };
+    let src = match (content, path) {
+        (Some(c), _) => c,
+        (None, Some(p)) => fsops::workspace_read_file(p)?.file.content,
+        (None, None) => return Err(anyhow::anyhow!("path or content is required")),
+    };
+    Ok(outline(&src, lang))
+}
@@ -0,0 +1,284 @@
+use anyhow::Result;
+use serde::Serialize;
+use std::collections::HashMap;
+
+use super::{process_tree, tasks, terminal};
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ListeningPort {
+    pub port: u16,
+    /// Address the socket is bound to, e.g. `127.0.0.1`, `0.0.0.0` or `::`.
+    pub address: String,
+    pub pid: u32,
+    /// Command line of the process, or its name where the command line can't be read.
+    pub command: String,
+    /// `terminal` or `task`.
+    pub source: String,
+    /// Terminal session id, or task run id.
+    pub source_id: String,
+    /// Task id, for a task.
+    pub task_id: Option<String>,
+    /// Where a browser would find it: `localhost` for any local address.
+    pub url: String,
+}
+
+struct Owner {
+    source: &'static str,
+    source_id: String,
+    task_id: Option<String>,
+}
+
+// Processes started from terminals and tasks, with what started them.
+fn owners() -> HashMap<u32, Owner> {
+    let mut out = HashMap::new();
+    for (id, pid) in terminal::session_pids() {
+        for p in process_tree::descendants(pid) {
+            out.entry(p).or_insert_with(|| Owner {
+                source: "terminal",
+                source_id: id.clone(),
+                task_id: None,
+            });
+        }
+    }
+    for (run_id, task_id, pid) in tasks::running_pids() {
+        for p in process_tree::descendants(pid) {
+            out.entry(p).or_insert_with(|| Owner {
+                source: "task",
+                source_id: run_id.clone(),
+                task_id: Some(task_id.clone()),
+            });
+        }
+    }
+    out
+}
+
+fn url_for(address: &str, port: u16) -> String {
+    let host = match address {
+        "" | "*" | "0.0.0.0" | "::" | "127.0.0.1" | "::1" => "localhost".to_string(),
+        a if a.contains(':') => format!("[{a}]"),
+        a => a.to_string(),
+    };
+    format!("http://{host}:{port}")
+}
+
+#[cfg(target_os = "linux")]
+mod platform {
+    use std::collections::HashMap;
+    use std::fs;
+    use std::net::{Ipv4Addr, Ipv6Addr};
+
+    // `/proc/net/tcp` addresses are the address's 32-bit words printed in host byte order.
+    fn address(hex: &str) -> Option<String> {
+        let words = (0..hex.len() / 8)
+            .map(|i| u32::from_str_radix(hex.get(i * 8..i * 8 + 8)?, 16).ok())
+            .collect::<Option<Vec<_>>>()?;
+        let bytes: Vec<u8> = words.iter().flat_map(|w| w.to_ne_bytes()).collect();
+        match bytes.len() {
+            4 => Some(Ipv4Addr::new(bytes[0], bytes[1], bytes[2], bytes[3]).to_string()),
+            16 => {
+                let b: [u8; 16] = bytes.try_into().ok()?;
+                let ip = Ipv6Addr::from(b);
+                Some(ip.to_ipv4_mapped().map(|v4| v4.to_string()).unwrap_or_else(|| ip.to_string()))
+            }
+            _ => None,
+        }
+    }
+
+    // Listening sockets by inode: address and port.
+    fn listening() -> HashMap<u64, (String, u16)> {
+        let mut out = HashMap::new();
+        for table in ["/proc/net/tcp", "/proc/net/tcp6"] {
+            let Ok(text) = fs::read_to_string(table) else {
+                continue;
+            };
+            for line in text.lines().skip(1) {
+                let cols: Vec<&str> = line.split_whitespace().collect();
+                // `0A` is TCP_LISTEN.
+                if cols.len() < 10 || cols[3] != "0A" {
+                    continue;
+                }
+                let Some((addr, port)) = cols[1].split_once(':') else {
+                    continue;
+                };
+                let (Some(addr), Ok(port), Ok(inode)) = (address(addr), u16::from_str_radix(port, 16), cols[9].parse()) else {
+                    continue;
+                };
+                out.insert(inode, (addr, port));
+            }
+        }
+        out
+    }
+
+    /// Listening TCP sockets of `pids`: pid, address and port.
+    pub fn listeners(pids: &[u32]) -> Vec<(u32, String, u16)> {
+        let sockets = listening();
+        let mut out = vec![];
+        for &pid in pids {
+            let Ok(fds) = fs::read_dir(format!("/proc/{pid}/fd")) else {
+                continue;
+            };
+            for fd in fds.filter_map(|e| e.ok()) {
+                let Ok(target) = fs::read_link(fd.path()) else {
+                    continue;
+                };
+                let target = target.to_string_lossy();
+                let inode = target.strip_prefix("socket:[").and_then(|t| t.strip_suffix(']')).and_then(|t| t.parse().ok());
+                if let Some((addr, port)) = inode.and_then(|i: u64| sockets.get(&i)) {
+                    out.push((pid, addr.clone(), *port));
+                }
+            }
+        }
+        out
+    }
+
+    pub fn command(pid: u32) -> Option<String> {
+        let raw = fs::read(format!("/proc/{pid}/cmdline")).ok()?;
+        let args: Vec<String> = raw
+            .split(|b| *b == 0)
+            .filter(|a| !a.is_empty())
+            .map(|a| String::from_utf8_lossy(a).into_owned())
+            .collect();
+        if args.is_empty() {
+            fs::read_to_string(format!("/proc/{pid}/comm")).ok().map(|c| c.trim().to_string())
+        } else {
+            Some(args.join(" "))
+        }
+    }
+}
+
+#[cfg(all(unix, not(target_os = "linux")))]
+mod platform {
+    use std::process::Command;
+
+    // `*:3000`, `127.0.0.1:5173` or `[::1]:8080`.
+    fn split_address(name: &str) -> Option<(String, u16)> {
+        let (addr, port) = name.rsplit_once(':')?;
+        let addr = addr.trim_start_matches('[').trim_end_matches(']');
+        Some((addr.to_string(), port.parse().ok()?))
+    }
+
+    /// Listening TCP sockets of `pids`: pid, address and port.
+    pub fn listeners(pids: &[u32]) -> Vec<(u32, String, u16)> {
+        if pids.is_empty() {
+            return vec![];
+        }
+        let list = pids.iter().map(|p| p.to_string()).collect::<Vec<_>>().join(",");
+        let Ok(output) = Command::new("lsof")
+            .args(["-nP", "-a", "-iTCP", "-sTCP:LISTEN", "-Fpn", "-p", &list])
+            .output()
+        else {
+            return vec![];
+        };
+        let mut out = vec![];
+        let mut pid = None;
+        for line in String::from_utf8_lossy(&output.stdout).lines() {
+            if let Some(p) = line.strip_prefix('p') {
+                pid = p.parse().ok();
+            } else if let (Some(name), Some(pid)) = (line.strip_prefix('n'), pid) {
+                if let Some((addr, port)) = split_address(name) {
+                    out.push((pid, addr, port));
+                }
+            }
+        }
+        out
+    }
+
+    pub fn command(pid: u32) -> Option<String> {
+        let output = Command::new("ps").args(["-o", "command=", "-p", &pid.to_string()]).output().ok()?;
+        let command = String::from_utf8_lossy(&output.stdout).trim().to_string();
+        (!command.is_empty()).then_some(command)
+    }
+}
+
+#[cfg(windows)]
+mod platform {
+    use std::os::windows::process::CommandExt;
+    use std::process::Command;
+
+    const CREATE_NO_WINDOW: u32 = 0x0800_0000;
+
+    /// Listening TCP sockets of `pids`: pid, address and port.
+    pub fn listeners(pids: &[u32]) -> Vec<(u32, String, u16)> {
+        let Ok(output) = Command::new("netstat")
+            .args(["-ano", "-p", "TCP"])
+            .creation_flags(CREATE_NO_WINDOW)
+            .output()
+            .and_then(|o| {
+                // `-p TCP` leaves out IPv6; ask again for it.
+                let v6 = Command::new("netstat").args(["-ano", "-p", "TCPv6"]).creation_flags(CREATE_NO_WINDOW).output()?;
+                Ok([o.stdout, v6.stdout].concat())
+            })
+        else {
+            return vec![];
+        };
+        let mut out = vec![];
+        for line in String::from_utf8_lossy(&output).lines() {
+            let cols: Vec<&str> = line.split_whitespace().collect();
+            // `TCP  0.0.0.0:3000  0.0.0.0:0  LISTENING  1234`. The state is localized, but only
+            // a listening socket has no remote port.
+            if cols.len() != 5 || !cols[0].eq_ignore_ascii_case("TCP") || !cols[2].ends_with(":0") {
+                continue;
+            }
+            let Ok(pid) = cols[4].parse::<u32>() else {
+                continue;
+            };
+            if !pids.contains(&pid) {
+                continue;
+            }
+            let Some((addr, port)) = cols[1].rsplit_once(':') else {
+                continue;
+            };
+            let addr = addr.trim_start_matches('[').trim_end_matches(']');
+            if let Ok(port) = port.parse() {
+                out.push((pid, addr.to_string(), port));
+            }
+        }
+        out
+    }
+
+    // The image name; reading another process's command line needs more than it is worth here.
+    pub fn command(pid: u32) -> Option<String> {
+        let output = Command::new("tasklist")
+            .args(["/FI", &format!("PID eq {pid}"), "/FO", "CSV", "/NH"])
+            .creation_flags(CREATE_NO_WINDOW)
+            .output()
+            .ok()?;
+        let text = String::from_utf8_lossy(&output.stdout);
+        let name = text.lines().next()?.split("\",\"").next()?.trim_start_matches('"');
+        (!name.is_empty() && !name.starts_with("INFO:")).then(|| name.to_string())
+    }
+}
+
+/// TCP ports being listened on by processes started from Pompora terminals and tasks, and
+/// anything those started in turn, by port.
+pub fn ports_list() -> Result<Vec<ListeningPort>> {
+    let owners = owners();
+    let pids: Vec<u32> = owners.keys().copied().collect();
+    let mut found = platform::listeners(&pids);
+    found.sort_by_key(|a| (a.2, a.0));
+    // A server listening on both IPv4 and IPv6 is listed once.
+    found.dedup_by(|a, b| a.0 == b.0 && a.2 == b.2);
+
+    let mut commands: HashMap<u32, String> = HashMap::new();
+    let mut out = vec![];
+    for (pid, address, port) in found {
+        let Some(owner) = owners.get(&pid) else {
+            continue;
+        };
+        let command = commands
+            .entry(pid)
+            .or_insert_with(|| platform::command(pid).unwrap_or_default())
+            .clone();
+        out.push(ListeningPort {
+            port,
+            url: url_for(&address, port),
+            address,
+            pid,
+            command,
+            source: owner.source.to_string(),
+            source_id: owner.source_id.clone(),
+            task_id: owner.task_id.clone(),
+        });
+    }
+    Ok(out)
+}
@@ -0,0 +1,161 @@
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::io::{self, Write};
+use std::path::Path;
+
+// Owner-only modes for anything holding credentials.
+#[cfg(unix)]
+const PRIVATE_FILE_MODE: u32 = 0o600;
+#[cfg(unix)]
+const PRIVATE_DIR_MODE: u32 = 0o700;
+
+/// Writes `contents` to `path`, creating the file readable by the current user only. On Unix the
+/// mode is set at creation so the data is never briefly world-readable.
+pub fn write_private(path: &Path, contents: &[u8]) -> io::Result<()> {
+    let mut opts = fs::OpenOptions::new();
+    opts.write(true).create(true).truncate(true);
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::OpenOptionsExt;
+        opts.mode(PRIVATE_FILE_MODE);
+    }
+    let mut f = opts.open(path)?;
+    f.write_all(contents)?;
+    drop(f);
+    // `mode` only applies to newly created files.
+    restrict_file(path)
+}
+
+/// Opens `path` for appending, creating it owner-only if missing.
+pub fn open_append_private(path: &Path) -> io::Result<fs::File> {
+    let mut opts = fs::OpenOptions::new();
+    opts.create(true).append(true);
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::OpenOptionsExt;
+        opts.mode(PRIVATE_FILE_MODE);
+    }
+    opts.open(path)
+}
+
+/// Creates `dir` (and parents) and restricts the leaf directory to the current user.
+pub fn create_private_dir(dir: &Path) -> io::Result<()> {
+    fs::create_dir_all(dir)?;
+    restrict_dir(dir)
+}
+
+#[cfg(unix)]
+pub fn restrict_file(path: &Path) -> io::Result<()> {
+    use std::os::unix::fs::PermissionsExt;
+    fs::set_permissions(path, fs::Permissions::from_mode(PRIVATE_FILE_MODE))
+}
+
+#[cfg(unix)]
+pub fn restrict_dir(path: &Path) -> io::Result<()> {
+    use std::os::unix::fs::PermissionsExt;
+    fs::set_permissions(path, fs::Permissions::from_mode(PRIVATE_DIR_MODE))
+}
+
+// Windows: drop inherited ACEs and grant full control to the current user only.
+#[cfg(windows)]
+fn restrict_windows(path: &Path) -> io::Result<()> {
+    use std::os::windows::process::CommandExt;
+    const CREATE_NO_WINDOW: u32 = 0x0800_0000;
+
+    let user = std::env::var("USERNAME").map_err(|_| io::Error::new(io::ErrorKind::NotFound, "USERNAME is not set"))?;
+    let status = std::process::Command::new("icacls")
+        .arg(path)
+        .args(["/inheritance:r", "/grant:r"])
+        .arg(format!("{user}:F"))
+        .creation_flags(CREATE_NO_WINDOW)
+        .stdout(std::process::Stdio::null())
+        .stderr(std::process::Stdio::null())
+        .status()?;
+    if status.success() {
+        Ok(())
+    } else {
+        Err(io::Error::other(format!("icacls failed for {}", path.display())))
+    }
+}
+
+#[cfg(windows)]
+pub fn restrict_file(path: &Path) -> io::Result<()> {
+    restrict_windows(path)
+}
+
+#[cfg(windows)]
+pub fn restrict_dir(path: &Path) -> io::Result<()> {
+    restrict_windows(path)
+}
+
+#[cfg(not(any(unix, windows)))]
+pub fn restrict_file(_path: &Path) -> io::Result<()> {
+    Ok(())
+}
+
+#[cfg(not(any(unix, windows)))]
+pub fn restrict_dir(_path: &Path) -> io::Result<()> {
+    Ok(())
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PermissionIssue {
+    pub path: String,
+    pub is_dir: bool,
+    /// Current Unix mode in octal, e.g. "644".
+    pub mode: String,
+    pub expected: String,
+}
+
+/// Files and directories under the Pompora config dir that may hold credentials.
+#[cfg(unix)]
+fn sensitive_paths() -> Vec<std::path::PathBuf> {
+    let Some(base) = dirs::config_dir().map(|d| d.join("Pompora")) else {
+        return vec![];
+    };
+    let secrets_dir = base.join("secrets");
+    let mut out = vec![base.join("auth.json"), secrets_dir.clone()];
+    // `settings.json` plus one `settings.<profile>.json` per settings profile.
+    if let Ok(entries) = fs::read_dir(&base) {
+        out.extend(entries.flatten().map(|e| e.path()).filter(|p| {
+            let name = p.file_name().map(|n| n.to_string_lossy().to_string()).unwrap_or_default();
+            name.starts_with("settings") && name.ends_with(".json")
+        }));
+    }
+    if let Ok(entries) = fs::read_dir(&secrets_dir) {
+        out.extend(entries.flatten().map(|e| e.path()));
+    }
+    out.retain(|p| p.exists());
+    out
+}
+
+/// Reports credential files readable or writable by anyone but the owner. Windows ACLs are
+/// not inspected, so the list is always empty there.
+#[cfg(unix)]
+pub fn audit() -> Vec<PermissionIssue> {
+    use std::os::unix::fs::PermissionsExt;
+
+    let mut issues = vec![];
+    for path in sensitive_paths() {
+        let Ok(meta) = fs::metadata(&path) else {
+            continue;
+        };
+        let mode = meta.permissions().mode() & 0o777;
+        if mode & 0o077 == 0 {
+            continue;
+        }
+        let expected = if meta.is_dir() { PRIVATE_DIR_MODE } else { PRIVATE_FILE_MODE };
+        issues.push(PermissionIssue {
+            path: path.to_string_lossy().to_string(),
+            is_dir: meta.is_dir(),
+            mode: format!("{mode:o}"),
+            expected: format!("{expected:o}"),
+        });
+    }
+    issues
+}
+
+#[cfg(not(unix))]
+pub fn audit() -> Vec<PermissionIssue> {
+    vec![]
+}
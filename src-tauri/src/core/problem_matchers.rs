@@ -0,0 +1,310 @@
+use once_cell::sync::Lazy;
+use regex::Regex;
+use serde::Serialize;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
+use tauri::{AppHandle, Emitter};
+
+use super::workspace::{self, OpenRoot};
+
+pub const DIAGNOSTICS_REPORT_EVENT: &str = "diagnostics:report";
+
+// Per source; a build that fails this badly has bigger problems than the ones past the cap.
+const MAX_DIAGNOSTICS: usize = 500;
+// Reports for a source are sent at most this often while its output keeps coming.
+const REPORT_INTERVAL: Duration = Duration::from_millis(200);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Severity {
+    Error,
+    Warning,
+    Info,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct Diagnostic {
+    /// Workspace path when the file is inside the workspace, otherwise as the tool printed it
+    /// resolved against the folder it ran in.
+    pub path: String,
+    pub line: u32,
+    pub column: Option<u32>,
+    pub severity: Severity,
+    pub message: String,
+    /// Tool-specific code or rule, e.g. `E0308`, `TS2322` or `no-unused-vars`.
+    pub code: Option<String>,
+    /// The tool the line was recognized as: `rustc`, `tsc`, `eslint` or `pytest`; `lsp` for
+    /// what a language server published, `clippy`, `eslint` or `ruff` for `lint_run`, and
+    /// `audit` for `dependencies_audit`.
+    pub matcher: &'static str,
+}
+
+/// Payload of `diagnostics:report`: every diagnostic currently known for `source`, replacing
+/// the previous report for it.
+#[derive(Debug, Clone, Serialize)]
+pub struct DiagnosticsReport {
+    /// Where the output came from, e.g. `task:cargo build`, `terminal:term-123` or `lsp:lsp-456`.
+    pub source: String,
+    pub diagnostics: Vec<Diagnostic>,
+}
+
+// `error[E0308]: mismatched types`; the location follows on a ` --> file:line:col` line.
+static RUST_HEADER: Lazy<Regex> =
+    Lazy::new(|| Regex::new(r"^(error|warning)(?:\[(\w+)\])?: (.+)$").expect("valid rustc header regex"));
+static RUST_LOCATION: Lazy<Regex> =
+    Lazy::new(|| Regex::new(r"^\s*--> (.+?):(\d+):(\d+)$").expect("valid rustc location regex"));
+// `src/a.ts(3,5): error TS2322: ...` and the `--pretty` form `src/a.ts:3:5 - error TS2322: ...`.
+static TSC: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(r"^(.+?)(?:\((\d+),(\d+)\): |:(\d+):(\d+) - )(error|warning|message) (TS\d+): (.+)$")
+        .expect("valid tsc regex")
+});
+// eslint's default `stylish` output: the file on a line of its own, then `  3:5  error  Message  rule`.
+static ESLINT_FILE: Lazy<Regex> =
+    Lazy::new(|| Regex::new(r"^(/|[A-Za-z]:[\\/]|\.{0,2}[\w@.-]+[\\/]).*\.\w+$").expect("valid eslint file regex"));
+static ESLINT_ENTRY: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(r"^\s+(\d+):(\d+)\s+(error|warning)\s+(.+?)(?:\s{2,}(\S+))?$").expect("valid eslint entry regex")
+});
+// `tests/test_app.py:12: AssertionError`, where a pytest failure points at the failing line.
+static PYTEST: Lazy<Regex> = Lazy::new(|| Regex::new(r"^(\S+\.py):(\d+): (.+)$").expect("valid pytest regex"));
+
+/// Recognizes diagnostics in one stream of output, a line at a time. Some formats spread a
+/// diagnostic over several lines, so each stream needs its own matcher.
+#[derive(Default)]
+pub struct Matcher {
+    // rustc header waiting for its location line.
+    rust: Option<(Severity, Option<String>, String)>,
+    // File of the eslint block being read.
+    eslint_file: Option<String>,
+}
+
+fn severity(s: &str) -> Severity {
+    match s {
+        "error" => Severity::Error,
+        "warning" => Severity::Warning,
+        _ => Severity::Info,
+    }
+}
+
+// The fields a matcher pulls out of a line, before the path is resolved.
+struct Found {
+    path: String,
+    line: u32,
+    column: Option<u32>,
+    severity: Severity,
+    message: String,
+    code: Option<String>,
+    matcher: &'static str,
+}
+
+impl Matcher {
+    // `line` has escape sequences and line endings removed.
+    fn feed(&mut self, line: &str) -> Option<Found> {
+        if let Some((severity, code, message)) = self.rust.take() {
+            if let Some(c) = RUST_LOCATION.captures(line) {
+                return Some(Found {
+                    path: c[1].to_string(),
+                    line: c[2].parse().ok()?,
+                    column: c[3].parse().ok(),
+                    severity,
+                    message,
+                    code,
+                    matcher: "rustc",
+                });
+            }
+        }
+        if let Some(c) = RUST_HEADER.captures(line) {
+            // `error: could not compile` and friends never get a location and are dropped
+            // by the next line.
+            self.rust = Some((severity(&c[1]), c.get(2).map(|m| m.as_str().to_string()), c[3].to_string()));
+            self.eslint_file = None;
+            return None;
+        }
+        if let Some(c) = TSC.captures(line) {
+            let (l, col) = match (c.get(2), c.get(4)) {
+                (Some(l), _) => (l, c.get(3)),
+                (None, Some(l)) => (l, c.get(5)),
+                _ => return None,
+            };
+            return Some(Found {
+                path: c[1].to_string(),
+                line: l.as_str().parse().ok()?,
+                column: col.and_then(|m| m.as_str().parse().ok()),
+                severity: severity(&c[6]),
+                message: c[8].to_string(),
+                code: Some(c[7].to_string()),
+                matcher: "tsc",
+            });
+        }
+        if let Some(file) = &self.eslint_file {
+            if let Some(c) = ESLINT_ENTRY.captures(line) {
+                return Some(Found {
+                    path: file.clone(),
+                    line: c[1].parse().ok()?,
+                    column: c[2].parse().ok(),
+                    severity: severity(&c[3]),
+                    message: c[4].to_string(),
+                    code: c.get(5).map(|m| m.as_str().to_string()),
+                    matcher: "eslint",
+                });
+            }
+        }
+        if let Some(c) = PYTEST.captures(line) {
+            return Some(Found {
+                path: c[1].to_string(),
+                line: c[2].parse().ok()?,
+                column: None,
+                severity: Severity::Error,
+                message: c[3].to_string(),
+                code: None,
+                matcher: "pytest",
+            });
+        }
+        if ESLINT_FILE.is_match(line) {
+            self.eslint_file = Some(line.to_string());
+        } else if line.trim().is_empty() {
+            self.eslint_file = None;
+        }
+        None
+    }
+}
+
+/// Collects the diagnostics found in a task's or terminal's output and reports them to the
+/// frontend as `diagnostics:report` events.
+pub struct Reporter {
+    app: AppHandle,
+    source: String,
+    cwd: PathBuf,
+    roots: Vec<OpenRoot>,
+    diagnostics: Vec<Diagnostic>,
+    dirty: bool,
+    last_report: Option<Instant>,
+}
+
+impl Reporter {
+    /// `cwd` is where the output's relative paths start from.
+    pub fn new(app: AppHandle, source: String, cwd: PathBuf) -> Self {
+        Reporter {
+            app,
+            source,
+            cwd,
+            roots: workspace::open_roots().unwrap_or_default(),
+            diagnostics: vec![],
+            dirty: false,
+            last_report: None,
+        }
+    }
+
+    pub fn set_cwd(&mut self, cwd: PathBuf) {
+        self.cwd = cwd;
+    }
+
+    fn resolve(&self, file: &str) -> String {
+        workspace_path_of(&self.roots, &self.cwd.join(file))
+    }
+
+    /// Runs `line` (escape sequences already removed) through `matcher` and keeps what it finds.
+    pub fn feed_line(&mut self, matcher: &mut Matcher, line: &str) {
+        let Some(f) = matcher.feed(line.trim_end_matches(['\r', '\n'])) else {
+            return;
+        };
+        let d = Diagnostic {
+            path: self.resolve(&f.path),
+            line: f.line.max(1),
+            column: f.column,
+            severity: f.severity,
+            message: f.message.trim().to_string(),
+            code: f.code,
+            matcher: f.matcher,
+        };
+        if self.diagnostics.len() >= MAX_DIAGNOSTICS || self.diagnostics.contains(&d) {
+            return;
+        }
+        self.diagnostics.push(d);
+        self.dirty = true;
+        if self.last_report.is_none_or(|t| t.elapsed() >= REPORT_INTERVAL) {
+            self.flush();
+        }
+    }
+
+    /// Drops what was found so far, e.g. when a new command starts, and says so.
+    pub fn clear(&mut self) {
+        if self.diagnostics.is_empty() && self.last_report.is_some() {
+            return;
+        }
+        self.diagnostics.clear();
+        self.dirty = true;
+        self.flush();
+    }
+
+    /// Sends a report if anything changed since the last one.
+    pub fn flush(&mut self) {
+        if !self.dirty {
+            return;
+        }
+        self.dirty = false;
+        self.last_report = Some(Instant::now());
+        let _ = self.app.emit(
+            DIAGNOSTICS_REPORT_EVENT,
+            DiagnosticsReport {
+                source: self.source.clone(),
+                diagnostics: self.diagnostics.clone(),
+            },
+        );
+    }
+}
+
+/// Workspace path of `abs` if it is inside one of `roots`, otherwise `abs` itself; the path
+/// given to a `Diagnostic`.
+pub fn workspace_path_of(roots: &[OpenRoot], abs: &Path) -> String {
+    let abs = normalize(abs);
+    for root in roots {
+        if let Ok(rel) = abs.strip_prefix(&root.path) {
+            return root.workspace_path(&rel.to_string_lossy().replace('\\', "/"));
+        }
+    }
+    abs.to_string_lossy().into_owned()
+}
+
+// Resolves `.` and `..` without touching the filesystem; the file may not exist any more.
+fn normalize(p: &Path) -> PathBuf {
+    let mut out = PathBuf::new();
+    for c in p.components() {
+        match c {
+            std::path::Component::CurDir => {}
+            std::path::Component::ParentDir => {
+                out.pop();
+            }
+            c => out.push(c),
+        }
+    }
+    out
+}
+
+/// Splits raw terminal output into lines for a `Matcher`, holding back a partial last line
+/// until the rest of it arrives. Escape sequences are left in place.
+#[derive(Default)]
+pub struct LineSplitter {
+    partial: String,
+}
+
+// A line this long is not a diagnostic; it is dropped rather than buffered.
+const MAX_LINE_LEN: usize = 8 * 1024;
+
+impl LineSplitter {
+    /// Complete lines in `chunk`.
+    pub fn split(&mut self, chunk: &str) -> Vec<String> {
+        self.partial.push_str(chunk);
+        let mut lines = vec![];
+        while let Some(nl) = self.partial.find('\n') {
+            let rest = self.partial.split_off(nl + 1);
+            let line = std::mem::replace(&mut self.partial, rest);
+            // A carriage return without a newline redraws the line; only the last draw counts.
+            let line = line.trim_end_matches(['\r', '\n']);
+            lines.push(line.rsplit('\r').next().unwrap_or(line).to_string());
+        }
+        if self.partial.len() > MAX_LINE_LEN {
+            self.partial.clear();
+        }
+        lines
+    }
+}
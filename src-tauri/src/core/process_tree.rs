@@ -0,0 +1,321 @@
+use std::collections::HashMap;
+#[cfg(unix)]
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+/// How long a process tree gets to exit after being asked to before it is killed outright.
+pub const TERMINATE_TIMEOUT: Duration = Duration::from_secs(3);
+
+/// A process and everything it starts, so they can be stopped together: a dev server started
+/// from a shell or `npm run` lives on in a grandchild that killing the direct child misses.
+///
+/// On Unix the tree is found by walking parent pids (plus the process's own process group)
+/// when it is signalled, and every process seen is remembered, since one whose parent has died
+/// can't be found that way any more. On Windows the process is put in a job object as soon as
+/// it starts, and the processes it creates after that join the job.
+pub struct ProcessTree {
+    pid: u32,
+    #[cfg(unix)]
+    seen: Mutex<Vec<u32>>,
+    #[cfg(windows)]
+    job: Option<windows::Job>,
+}
+
+impl ProcessTree {
+    pub fn new(pid: u32) -> Self {
+        ProcessTree {
+            pid,
+            #[cfg(unix)]
+            seen: Mutex::new(vec![]),
+            #[cfg(windows)]
+            job: windows::Job::assign(pid),
+        }
+    }
+
+    pub fn pid(&self) -> u32 {
+        self.pid
+    }
+
+    #[cfg(unix)]
+    fn signal(&self, sig: libc::c_int) {
+        let mut seen = self.seen.lock().unwrap_or_else(|e| e.into_inner());
+        for pid in unix::tree(self.pid) {
+            if !seen.contains(&pid) {
+                seen.push(pid);
+            }
+        }
+        unix::signal(self.pid, &seen, sig);
+    }
+
+    /// Asks every process in the tree to exit: `SIGTERM`, plus the `SIGHUP` a shell gets when
+    /// its terminal closes. Windows has no equivalent for console programs; closing their
+    /// console is what asks them, so this does nothing there.
+    pub fn terminate(&self) {
+        #[cfg(unix)]
+        {
+            self.signal(libc::SIGTERM);
+            if let Ok(pid) = libc::pid_t::try_from(self.pid) {
+                // SAFETY: a plain syscall; failure means it has already gone.
+                unsafe {
+                    libc::kill(pid, libc::SIGHUP);
+                }
+            }
+        }
+    }
+
+    /// Windows only: caps the memory the whole tree may commit, so a process asking for more
+    /// fails to get it. Unix limits are set per process before it starts instead (`setrlimit`).
+    #[cfg(windows)]
+    pub fn limit_memory(&self, bytes: usize) -> bool {
+        self.job.as_ref().is_some_and(|job| job.limit_memory(bytes))
+    }
+
+    /// Kills every process in the tree.
+    pub fn kill(&self) {
+        #[cfg(unix)]
+        self.signal(libc::SIGKILL);
+        #[cfg(windows)]
+        match &self.job {
+            Some(job) => job.terminate(),
+            None => windows::taskkill(self.pid),
+        }
+    }
+
+    /// `terminate`, then `kill` whatever is left after `timeout`. Blocks until the tree is gone
+    /// or killed. On Windows, where the rest of the tree can't be watched, only `root_exited`
+    /// is waited for.
+    pub fn stop(&self, timeout: Duration, root_exited: impl Fn() -> bool) {
+        self.terminate();
+        let deadline = Instant::now() + timeout;
+        while Instant::now() < deadline && !(root_exited() && self.others_exited()) {
+            std::thread::sleep(Duration::from_millis(50));
+        }
+        self.kill();
+    }
+
+    fn others_exited(&self) -> bool {
+        #[cfg(unix)]
+        return !unix::any_alive(&self.seen.lock().unwrap_or_else(|e| e.into_inner()));
+        #[cfg(not(unix))]
+        true
+    }
+}
+
+// `root` and its descendants by `parents` (parent pid of every process, by pid), parents
+// before children.
+fn tree_of(root: u32, parents: HashMap<u32, u32>) -> Vec<u32> {
+    let mut children: HashMap<u32, Vec<u32>> = HashMap::new();
+    for (pid, ppid) in parents {
+        children.entry(ppid).or_default().push(pid);
+    }
+    let mut out = vec![root];
+    let mut i = 0;
+    while i < out.len() {
+        if let Some(c) = children.get(&out[i]) {
+            out.extend(c.iter().filter(|p| **p != root));
+        }
+        i += 1;
+    }
+    out
+}
+
+/// `root` and the processes it started that are still running, parents before children.
+pub fn descendants(root: u32) -> Vec<u32> {
+    #[cfg(unix)]
+    return unix::tree(root);
+    #[cfg(windows)]
+    return tree_of(root, windows::parents());
+}
+
+#[cfg(unix)]
+mod unix {
+    use std::collections::HashMap;
+
+    // Parent pid of every process, by pid.
+    #[cfg(target_os = "linux")]
+    fn parents() -> HashMap<u32, u32> {
+        let mut out = HashMap::new();
+        let Ok(entries) = std::fs::read_dir("/proc") else {
+            return out;
+        };
+        for e in entries.flatten() {
+            let Some(pid) = e.file_name().to_str().and_then(|n| n.parse::<u32>().ok()) else {
+                continue;
+            };
+            let Ok(stat) = std::fs::read_to_string(e.path().join("stat")) else {
+                continue;
+            };
+            // `pid (comm) state ppid ...`; comm may itself contain spaces and parentheses.
+            let Some(rest) = stat.rfind(')').map(|i| &stat[i + 1..]) else {
+                continue;
+            };
+            if let Some(ppid) = rest.split_whitespace().nth(1).and_then(|p| p.parse().ok()) {
+                out.insert(pid, ppid);
+            }
+        }
+        out
+    }
+
+    #[cfg(not(target_os = "linux"))]
+    fn parents() -> HashMap<u32, u32> {
+        let Ok(out) = std::process::Command::new("ps").args(["-A", "-o", "pid=", "-o", "ppid="]).output() else {
+            return HashMap::new();
+        };
+        String::from_utf8_lossy(&out.stdout)
+            .lines()
+            .filter_map(|l| {
+                let mut it = l.split_whitespace();
+                Some((it.next()?.parse().ok()?, it.next()?.parse().ok()?))
+            })
+            .collect()
+    }
+
+    // `root` and its descendants, parents before children.
+    pub fn tree(root: u32) -> Vec<u32> {
+        super::tree_of(root, parents())
+    }
+
+    pub fn signal(root: u32, pids: &[u32], sig: libc::c_int) {
+        // SAFETY: plain syscalls on pids; failures (already gone, not ours) are ignored.
+        unsafe {
+            // Terminals and tasks start in a process group of their own, which also covers
+            // members that were re-parented before they were seen.
+            if let Ok(root) = libc::pid_t::try_from(root) {
+                libc::killpg(root, sig);
+            }
+            for pid in pids.iter().rev() {
+                if let Ok(pid) = libc::pid_t::try_from(*pid) {
+                    libc::kill(pid, sig);
+                }
+            }
+        }
+    }
+
+    pub fn any_alive(pids: &[u32]) -> bool {
+        pids.iter().any(|p| {
+            // SAFETY: signal 0 only checks that the process exists.
+            libc::pid_t::try_from(*p).is_ok_and(|p| unsafe { libc::kill(p, 0) } == 0) && !zombie(*p)
+        })
+    }
+
+    // An exited process its parent hasn't collected yet; orphans wait on init for that.
+    #[cfg(target_os = "linux")]
+    fn zombie(pid: u32) -> bool {
+        std::fs::read_to_string(format!("/proc/{pid}/stat"))
+            .ok()
+            .and_then(|stat| Some(stat[stat.rfind(')')? + 1..].trim_start().starts_with('Z')))
+            .unwrap_or(false)
+    }
+
+    #[cfg(not(target_os = "linux"))]
+    fn zombie(_pid: u32) -> bool {
+        false
+    }
+}
+
+#[cfg(windows)]
+mod windows {
+    use windows_sys::Win32::Foundation::{CloseHandle, HANDLE};
+    use windows_sys::Win32::System::JobObjects::{
+        AssignProcessToJobObject, CreateJobObjectW, JobObjectExtendedLimitInformation, SetInformationJobObject,
+        TerminateJobObject, JOBOBJECT_EXTENDED_LIMIT_INFORMATION, JOB_OBJECT_LIMIT_JOB_MEMORY,
+    };
+    use windows_sys::Win32::System::Threading::{OpenProcess, PROCESS_SET_QUOTA, PROCESS_TERMINATE};
+
+    pub struct Job(HANDLE);
+
+    // SAFETY: a job handle may be used from any thread.
+    unsafe impl Send for Job {}
+    unsafe impl Sync for Job {}
+
+    impl Job {
+        /// A new job holding `pid`, or `None` if the process can't be put in one.
+        pub fn assign(pid: u32) -> Option<Job> {
+            // SAFETY: handles are checked before use and closed once no longer needed.
+            unsafe {
+                let job = CreateJobObjectW(std::ptr::null(), std::ptr::null());
+                if job.is_null() {
+                    return None;
+                }
+                let job = Job(job);
+                let process = OpenProcess(PROCESS_SET_QUOTA | PROCESS_TERMINATE, 0, pid);
+                if process.is_null() {
+                    return None;
+                }
+                let ok = AssignProcessToJobObject(job.0, process) != 0;
+                CloseHandle(process);
+                ok.then_some(job)
+            }
+        }
+
+        pub fn limit_memory(&self, bytes: usize) -> bool {
+            // SAFETY: `self.0` is a valid job handle until drop, and `info` is a plain struct of
+            // the size passed.
+            unsafe {
+                let mut info: JOBOBJECT_EXTENDED_LIMIT_INFORMATION = std::mem::zeroed();
+                info.BasicLimitInformation.LimitFlags = JOB_OBJECT_LIMIT_JOB_MEMORY;
+                info.JobMemoryLimit = bytes;
+                SetInformationJobObject(
+                    self.0,
+                    JobObjectExtendedLimitInformation,
+                    &info as *const _ as *const std::ffi::c_void,
+                    std::mem::size_of::<JOBOBJECT_EXTENDED_LIMIT_INFORMATION>() as u32,
+                ) != 0
+            }
+        }
+
+        pub fn terminate(&self) {
+            // SAFETY: `self.0` is a valid job handle until drop.
+            unsafe {
+                TerminateJobObject(self.0, 1);
+            }
+        }
+    }
+
+    impl Drop for Job {
+        fn drop(&mut self) {
+            // SAFETY: closed exactly once.
+            unsafe {
+                CloseHandle(self.0);
+            }
+        }
+    }
+
+    // Parent pid of every process, by pid.
+    pub fn parents() -> std::collections::HashMap<u32, u32> {
+        use windows_sys::Win32::Foundation::INVALID_HANDLE_VALUE;
+        use windows_sys::Win32::System::Diagnostics::ToolHelp::{
+            CreateToolhelp32Snapshot, Process32FirstW, Process32NextW, PROCESSENTRY32W, TH32CS_SNAPPROCESS,
+        };
+        let mut out = std::collections::HashMap::new();
+        // SAFETY: the snapshot handle is checked and closed; `entry` is a plain struct with its
+        // size set as the API requires.
+        unsafe {
+            let snapshot = CreateToolhelp32Snapshot(TH32CS_SNAPPROCESS, 0);
+            if snapshot == INVALID_HANDLE_VALUE {
+                return out;
+            }
+            let mut entry: PROCESSENTRY32W = std::mem::zeroed();
+            entry.dwSize = std::mem::size_of::<PROCESSENTRY32W>() as u32;
+            let mut more = Process32FirstW(snapshot, &mut entry) != 0;
+            while more {
+                out.insert(entry.th32ProcessID, entry.th32ParentProcessID);
+                more = Process32NextW(snapshot, &mut entry) != 0;
+            }
+            CloseHandle(snapshot);
+        }
+        out
+    }
+
+    // For a process that couldn't be put in a job.
+    pub fn taskkill(pid: u32) {
+        use std::os::windows::process::CommandExt;
+        const CREATE_NO_WINDOW: u32 = 0x0800_0000;
+        let _ = std::process::Command::new("taskkill")
+            .args(["/T", "/F", "/PID", &pid.to_string()])
+            .creation_flags(CREATE_NO_WINDOW)
+            .stdout(std::process::Stdio::null())
+            .stderr(std::process::Stdio::null())
+            .status();
+    }
+}
@@ -0,0 +1,410 @@
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::Path;
+
+use super::workspace::{self, OpenRoot};
+
+// Project folders are looked for at the root and this many levels below it, which covers
+// layouts like `src-tauri/` or `backend/` + `frontend/` without walking the whole tree.
+const MAX_DEPTH: usize = 2;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DetectedProject {
+    /// `rust`, `node`, `deno`, `python`, `go`, `maven`, `gradle`, `dotnet`, `ruby`, `php`,
+    /// `cmake` or `make`.
+    pub kind: String,
+    pub name: Option<String>,
+    /// Workspace path of the project folder; `""` for a single-root workspace's root.
+    pub path: String,
+    /// Marker files that identified the project, e.g. `Cargo.toml`.
+    pub markers: Vec<String>,
+    pub run_commands: Vec<String>,
+    pub test_commands: Vec<String>,
+    pub build_commands: Vec<String>,
+    /// Workspace paths of likely entry files.
+    pub entry_points: Vec<String>,
+}
+
+struct Dir<'a> {
+    root: &'a OpenRoot,
+    rel: String,
+    abs: &'a Path,
+}
+
+impl Dir<'_> {
+    fn has(&self, name: &str) -> bool {
+        self.abs.join(name).exists()
+    }
+
+    fn read(&self, name: &str) -> Option<String> {
+        fs::read_to_string(self.abs.join(name)).ok()
+    }
+
+    fn workspace_path(&self, name: &str) -> String {
+        let rel = match (self.rel.is_empty(), name.is_empty()) {
+            (true, _) => name.to_string(),
+            (false, true) => self.rel.clone(),
+            (false, false) => format!("{}/{name}", self.rel),
+        };
+        self.root.workspace_path(&rel)
+    }
+
+    /// The candidates (relative to this folder) that exist, as workspace paths.
+    fn existing(&self, candidates: &[&str]) -> Vec<String> {
+        candidates
+            .iter()
+            .filter(|c| self.has(c))
+            .map(|c| self.workspace_path(c))
+            .collect()
+    }
+
+    fn project(&self, kind: &str, markers: &[&str]) -> DetectedProject {
+        DetectedProject {
+            kind: kind.to_string(),
+            name: None,
+            path: self.workspace_path(""),
+            markers: markers.iter().filter(|m| self.has(m)).map(|m| m.to_string()).collect(),
+            run_commands: vec![],
+            test_commands: vec![],
+            build_commands: vec![],
+            entry_points: vec![],
+        }
+    }
+}
+
+fn strings(items: &[&str]) -> Vec<String> {
+    items.iter().map(|s| s.to_string()).collect()
+}
+
+/// Value of a simple `key = "value"` line inside `[section]`. Good enough for project names
+/// without pulling in a TOML parser.
+fn toml_string(raw: &str, section: &str, key: &str) -> Option<String> {
+    let header = format!("[{section}]");
+    let mut in_section = false;
+    for line in raw.lines() {
+        let line = line.trim();
+        if line.starts_with('[') {
+            in_section = line == header;
+            continue;
+        }
+        if !in_section {
+            continue;
+        }
+        let Some((k, v)) = line.split_once('=') else {
+            continue;
+        };
+        if k.trim() == key {
+            let v = v.trim().trim_matches(|c| c == '"' || c == '\'');
+            return (!v.is_empty()).then(|| v.to_string());
+        }
+    }
+    None
+}
+
+fn detect_rust(dir: &Dir) -> Option<DetectedProject> {
+    let manifest = dir.read("Cargo.toml")?;
+    let mut p = dir.project("rust", &["Cargo.toml"]);
+    p.name = toml_string(&manifest, "package", "name");
+    p.run_commands = strings(&["cargo run"]);
+    p.test_commands = strings(&["cargo test"]);
+    p.build_commands = strings(&["cargo build"]);
+    p.entry_points = dir.existing(&["src/main.rs", "src/lib.rs"]);
+    if let Ok(bins) = fs::read_dir(dir.abs.join("src").join("bin")) {
+        let mut names: Vec<String> = bins
+            .flatten()
+            .filter_map(|e| e.file_name().to_str().map(str::to_string))
+            .filter(|n| n.ends_with(".rs"))
+            .collect();
+        names.sort();
+        p.entry_points
+            .extend(names.iter().map(|n| dir.workspace_path(&format!("src/bin/{n}"))));
+    }
+    Some(p)
+}
+
+/// The package manager a Node project uses, judged by its lockfile.
+pub fn package_manager(dir: &Path) -> &'static str {
+    if dir.join("pnpm-lock.yaml").exists() {
+        "pnpm"
+    } else if dir.join("yarn.lock").exists() {
+        "yarn"
+    } else if dir.join("bun.lockb").exists() || dir.join("bun.lock").exists() {
+        "bun"
+    } else {
+        "npm"
+    }
+}
+
+fn detect_node(dir: &Dir) -> Option<DetectedProject> {
+    let raw = dir.read("package.json")?;
+    let pkg: serde_json::Value = serde_json::from_str(&raw).unwrap_or_default();
+    let pm = package_manager(dir.abs);
+    let scripts = pkg.get("scripts").and_then(|s| s.as_object());
+    let has_script = |name: &str| scripts.is_some_and(|s| s.contains_key(name));
+
+    let mut p = dir.project(
+        "node",
+        &["package.json", "package-lock.json", "pnpm-lock.yaml", "yarn.lock", "bun.lockb", "bun.lock"],
+    );
+    p.name = pkg.get("name").and_then(|n| n.as_str()).map(str::to_string);
+    p.run_commands = ["dev", "start"]
+        .iter()
+        .filter(|s| has_script(s))
+        .map(|s| format!("{pm} run {s}"))
+        .collect();
+    if has_script("test") {
+        p.test_commands.push(format!("{pm} test"));
+    }
+    if has_script("build") {
+        p.build_commands.push(format!("{pm} run build"));
+    }
+    if let Some(main) = pkg.get("main").and_then(|m| m.as_str()) {
+        let main = main.trim_start_matches("./");
+        if dir.has(main) {
+            p.entry_points.push(dir.workspace_path(main));
+        }
+    }
+    p.entry_points.extend(dir.existing(&[
+        "src/main.ts",
+        "src/main.tsx",
+        "src/index.ts",
+        "src/index.tsx",
+        "src/main.js",
+        "src/index.js",
+        "index.js",
+        "server.js",
+    ]));
+    p.entry_points.dedup();
+    Some(p)
+}
+
+fn detect_deno(dir: &Dir) -> Option<DetectedProject> {
+    if !dir.has("deno.json") && !dir.has("deno.jsonc") {
+        return None;
+    }
+    let mut p = dir.project("deno", &["deno.json", "deno.jsonc"]);
+    let candidates = ["main.ts", "mod.ts", "src/main.ts"];
+    p.entry_points = dir.existing(&candidates);
+    if let Some(file) = candidates.iter().find(|c| dir.has(c)) {
+        p.run_commands.push(format!("deno run {file}"));
+    }
+    p.test_commands = strings(&["deno test"]);
+    Some(p)
+}
+
+fn detect_python(dir: &Dir) -> Option<DetectedProject> {
+    let markers = ["pyproject.toml", "requirements.txt", "setup.py", "Pipfile", "manage.py"];
+    if !markers.iter().any(|m| dir.has(m)) {
+        return None;
+    }
+    let pyproject = dir.read("pyproject.toml").unwrap_or_default();
+    let runner = if dir.has("uv.lock") {
+        "uv run "
+    } else if pyproject.contains("[tool.poetry]") {
+        "poetry run "
+    } else if dir.has("Pipfile") {
+        "pipenv run "
+    } else {
+        ""
+    };
+
+    let mut p = dir.project("python", &markers);
+    p.name = toml_string(&pyproject, "project", "name").or_else(|| toml_string(&pyproject, "tool.poetry", "name"));
+    if dir.has("manage.py") {
+        p.run_commands.push(format!("{runner}python manage.py runserver"));
+        p.test_commands.push(format!("{runner}python manage.py test"));
+        p.entry_points = dir.existing(&["manage.py"]);
+        return Some(p);
+    }
+    let candidates = ["main.py", "app.py", "src/main.py", "__main__.py"];
+    p.entry_points = dir.existing(&candidates);
+    if let Some(file) = candidates.iter().find(|c| dir.has(c)) {
+        p.run_commands.push(format!("{runner}python {file}"));
+    }
+    if pyproject.contains("pytest") || dir.has("pytest.ini") || dir.has("tests") || dir.has("conftest.py") {
+        p.test_commands.push(format!("{runner}pytest"));
+    } else {
+        p.test_commands.push(format!("{runner}python -m unittest"));
+    }
+    Some(p)
+}
+
+fn detect_go(dir: &Dir) -> Option<DetectedProject> {
+    let gomod = dir.read("go.mod")?;
+    let mut p = dir.project("go", &["go.mod"]);
+    p.name = gomod
+        .lines()
+        .find_map(|l| l.trim().strip_prefix("module "))
+        .map(|m| m.trim().to_string());
+    p.run_commands = strings(&["go run ."]);
+    p.test_commands = strings(&["go test ./..."]);
+    p.build_commands = strings(&["go build ./..."]);
+    p.entry_points = dir.existing(&["main.go"]);
+    if let Ok(cmds) = fs::read_dir(dir.abs.join("cmd")) {
+        let mut names: Vec<String> = cmds
+            .flatten()
+            .filter(|e| e.path().join("main.go").exists())
+            .filter_map(|e| e.file_name().to_str().map(str::to_string))
+            .collect();
+        names.sort();
+        for n in names {
+            p.run_commands.push(format!("go run ./cmd/{n}"));
+            p.entry_points.push(dir.workspace_path(&format!("cmd/{n}/main.go")));
+        }
+    }
+    Some(p)
+}
+
+fn detect_jvm(dir: &Dir) -> Option<DetectedProject> {
+    if dir.has("pom.xml") {
+        let mvn = if dir.has("mvnw") { "./mvnw" } else { "mvn" };
+        let mut p = dir.project("maven", &["pom.xml"]);
+        p.test_commands.push(format!("{mvn} test"));
+        p.build_commands.push(format!("{mvn} package"));
+        return Some(p);
+    }
+    let markers = ["build.gradle", "build.gradle.kts", "settings.gradle", "settings.gradle.kts"];
+    if markers.iter().any(|m| dir.has(m)) {
+        let gradle = if dir.has("gradlew") { "./gradlew" } else { "gradle" };
+        let mut p = dir.project("gradle", &markers);
+        p.run_commands.push(format!("{gradle} run"));
+        p.test_commands.push(format!("{gradle} test"));
+        p.build_commands.push(format!("{gradle} build"));
+        return Some(p);
+    }
+    None
+}
+
+fn detect_dotnet(dir: &Dir) -> Option<DetectedProject> {
+    let mut markers: Vec<String> = fs::read_dir(dir.abs)
+        .ok()?
+        .flatten()
+        .filter_map(|e| e.file_name().to_str().map(str::to_string))
+        .filter(|n| n.ends_with(".sln") || n.ends_with(".csproj") || n.ends_with(".fsproj"))
+        .collect();
+    if markers.is_empty() {
+        return None;
+    }
+    markers.sort();
+    let mut p = dir.project("dotnet", &[]);
+    p.name = markers.first().and_then(|m| m.rsplit_once('.')).map(|(n, _)| n.to_string());
+    p.markers = markers;
+    p.run_commands = strings(&["dotnet run"]);
+    p.test_commands = strings(&["dotnet test"]);
+    p.build_commands = strings(&["dotnet build"]);
+    p.entry_points = dir.existing(&["Program.cs", "Program.fs"]);
+    Some(p)
+}
+
+fn detect_ruby(dir: &Dir) -> Option<DetectedProject> {
+    if !dir.has("Gemfile") {
+        return None;
+    }
+    let mut p = dir.project("ruby", &["Gemfile", "Gemfile.lock"]);
+    if dir.has("config.ru") || dir.has("bin/rails") {
+        p.run_commands.push("bundle exec rails server".to_string());
+    }
+    p.test_commands.push(if dir.has("spec") { "bundle exec rspec" } else { "bundle exec rake test" }.to_string());
+    Some(p)
+}
+
+fn detect_php(dir: &Dir) -> Option<DetectedProject> {
+    let raw = dir.read("composer.json")?;
+    let pkg: serde_json::Value = serde_json::from_str(&raw).unwrap_or_default();
+    let mut p = dir.project("php", &["composer.json"]);
+    p.name = pkg.get("name").and_then(|n| n.as_str()).map(str::to_string);
+    if dir.has("artisan") {
+        p.run_commands.push("php artisan serve".to_string());
+        p.test_commands.push("php artisan test".to_string());
+    } else {
+        p.test_commands.push("vendor/bin/phpunit".to_string());
+    }
+    p.entry_points = dir.existing(&["public/index.php", "index.php"]);
+    Some(p)
+}
+
+fn detect_native(dir: &Dir) -> Option<DetectedProject> {
+    if dir.has("CMakeLists.txt") {
+        let mut p = dir.project("cmake", &["CMakeLists.txt"]);
+        p.build_commands = strings(&["cmake -S . -B build", "cmake --build build"]);
+        p.test_commands = strings(&["ctest --test-dir build"]);
+        return Some(p);
+    }
+    let makefile = dir.read("Makefile").or_else(|| dir.read("makefile"))?;
+    let mut p = dir.project("make", &["Makefile", "makefile"]);
+    let has_target = |t: &str| makefile.lines().any(|l| l.starts_with(&format!("{t}:")));
+    p.build_commands.push("make".to_string());
+    if has_target("run") {
+        p.run_commands.push("make run".to_string());
+    }
+    if has_target("test") {
+        p.test_commands.push("make test".to_string());
+    }
+    Some(p)
+}
+
+fn detect_dir(dir: &Dir, out: &mut Vec<DetectedProject>) {
+    let detectors: [fn(&Dir) -> Option<DetectedProject>; 10] = [
+        detect_rust,
+        detect_node,
+        detect_deno,
+        detect_python,
+        detect_go,
+        detect_jvm,
+        detect_dotnet,
+        detect_ruby,
+        detect_php,
+        detect_native,
+    ];
+    let found: Vec<DetectedProject> = detectors.iter().filter_map(|d| d(dir)).collect();
+    // A Makefile next to a real build system is usually just a wrapper around it.
+    let only_make = found.iter().all(|p| p.kind == "make");
+    out.extend(found.into_iter().filter(|p| only_make || p.kind != "make"));
+}
+
+fn is_skipped(name: &str) -> bool {
+    name.starts_with('.')
+        || matches!(
+            name,
+            "node_modules" | "target" | "dist" | "build" | "out" | "vendor" | "venv" | "__pycache__"
+        )
+}
+
+fn scan(root: &OpenRoot, abs: &Path, rel: String, depth: usize, out: &mut Vec<DetectedProject>) {
+    detect_dir(&Dir { root, rel: rel.clone(), abs }, out);
+    if depth >= MAX_DEPTH {
+        return;
+    }
+    let Ok(entries) = fs::read_dir(abs) else {
+        return;
+    };
+    let mut dirs: Vec<(String, std::path::PathBuf)> = entries
+        .flatten()
+        .filter(|e| e.file_type().map(|t| t.is_dir()).unwrap_or(false))
+        .filter_map(|e| Some((e.file_name().to_str()?.to_string(), e.path())))
+        .filter(|(name, _)| !is_skipped(name))
+        .collect();
+    dirs.sort();
+    for (name, path) in dirs {
+        let child = if rel.is_empty() { name } else { format!("{rel}/{name}") };
+        scan(root, &path, child, depth + 1, out);
+    }
+}
+
+/// Finds projects in every workspace root by their marker files, with suggested commands to
+/// run, test and build them.
+pub fn workspace_detect_projects() -> Result<Vec<DetectedProject>> {
+    let mut out = vec![];
+    for root in workspace::open_roots()? {
+        out.extend(detect_in(&root));
+    }
+    Ok(out)
+}
+
+/// `workspace_detect_projects` for a single root.
+pub fn detect_in(root: &OpenRoot) -> Vec<DetectedProject> {
+    let mut out = vec![];
+    scan(root, &root.path, String::new(), 0, &mut out);
+    out
+}
@@ -0,0 +1,73 @@
+use anyhow::{anyhow, Result};
+use ignore::gitignore::{Gitignore, GitignoreBuilder};
+use ignore::{Match, WalkBuilder};
+use std::path::Path;
+
+use super::settings;
+use super::workspace::OpenRoot;
+
+// Entries looked at inside a folder about to be deleted or moved; past this it is let through
+// rather than making the operation crawl.
+const MAX_FOLDER_ENTRIES: usize = 50_000;
+
+/// Marks the errors `ensure_unprotected` returns, so the UI can offer to override them.
+pub const PROTECTED_ERROR: &str = "is protected by";
+
+// The `protected_paths` globs, matched like `.gitignore` lines relative to the root.
+fn matcher(root: &OpenRoot) -> Option<Gitignore> {
+    let globs = settings::load().map(|s| s.protected_paths).unwrap_or_default();
+    let mut builder = GitignoreBuilder::new(&root.path);
+    let mut any = false;
+    for glob in globs.iter().map(|g| g.trim()).filter(|g| !g.is_empty() && !g.starts_with('#')) {
+        if let Err(e) = builder.add_line(None, glob) {
+            tracing::warn!("protected path {glob:?}: {e}");
+            continue;
+        }
+        any = true;
+    }
+    if !any {
+        return None;
+    }
+    builder.build().ok()
+}
+
+fn matched(gi: &Gitignore, path: &Path, is_dir: bool, parents: bool) -> Option<String> {
+    let m = if parents {
+        gi.matched_path_or_any_parents(path, is_dir)
+    } else {
+        gi.matched(path, is_dir)
+    };
+    match m {
+        Match::Ignore(glob) => Some(glob.original().to_string()),
+        _ => None,
+    }
+}
+
+/// Fails when `abs`, inside `root`, is protected by the `protected_paths` setting, or is a
+/// folder with something protected in it. `what` names the operation, e.g. `write`.
+pub fn ensure_unprotected(root: &OpenRoot, abs: &Path, what: &str) -> Result<()> {
+    if abs == root.path || !abs.starts_with(&root.path) {
+        return Ok(());
+    }
+    let Some(gi) = matcher(root) else {
+        return Ok(());
+    };
+    let is_dir = abs.is_dir() && !abs.is_symlink();
+    let refuse = |path: &Path, glob: String| {
+        let shown = path.strip_prefix(&root.path).unwrap_or(path).to_string_lossy().replace('\\', "/");
+        anyhow!("cannot {what} {shown}: it {PROTECTED_ERROR} `{glob}` in protected paths; override to {what} it anyway")
+    };
+    if let Some(glob) = matched(&gi, abs, is_dir, true) {
+        return Err(refuse(abs, glob));
+    }
+    if is_dir {
+        let walk = WalkBuilder::new(abs).standard_filters(false).follow_links(false).build();
+        for entry in walk.filter_map(|e| e.ok()).skip(1).take(MAX_FOLDER_ENTRIES) {
+            let dir = entry.file_type().is_some_and(|t| t.is_dir());
+            if let Some(glob) = matched(&gi, entry.path(), dir, false) {
+                return Err(refuse(entry.path(), glob));
+            }
+        }
+    }
+    Ok(())
+}
@@ -0,0 +1,31 @@
+use once_cell::sync::Lazy;
+use regex::{Captures, Regex};
+
+const MASK: &str = "[REDACTED]";
+
+// Query parameters, headers and JSON fields whose value is a credential.
+static NAMED: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(
+        r#"(?i)((?:[?&](?:key|api_key|apikey|access_token|token)=)|(?:"(?:api_?key|apiKey|access_token|token|secret|password)"\s*:\s*")|(?:\b(?:authorization|x-api-key|x-goog-api-key)\s*[:=]\s*(?:bearer\s+)?)|(?:\bbearer\s+))([^&"\s,;]+)"#,
+    )
+    .expect("valid redaction regex")
+});
+
+// Bare keys with a recognizable vendor prefix (OpenAI/Anthropic `sk-`, Groq `gsk_`,
+// Google `AIza`, OpenRouter `sk-or-`), wherever they appear.
+static PREFIXED: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(r"\b(?:sk-[A-Za-z0-9_\-]{16,}|gsk_[A-Za-z0-9]{16,}|AIza[0-9A-Za-z_\-]{30,})")
+        .expect("valid redaction regex")
+});
+
+/// Masks anything in `text` that looks like a credential. Apply to every URL, response body
+/// or upstream error before it goes into an error message or log line.
+pub fn redact(text: &str) -> String {
+    let named = NAMED.replace_all(text, |c: &Captures| format!("{}{MASK}", &c[1]));
+    PREFIXED.replace_all(&named, MASK).into_owned()
+}
+
+/// `redact` for errors whose `Display` may embed a request URL (e.g. reqwest).
+pub fn redact_error(e: impl std::fmt::Display) -> String {
+    redact(&e.to_string())
+}
@@ -0,0 +1,375 @@
+// Restricted runner for commands the AI asks to run, so a bad suggestion can't read the user's
+// credentials, reach the network or run forever. It isn't a full jail: the command still runs
+// as the user and can write wherever the user can.
+
+use anyhow::{Context, Result};
+use serde::Serialize;
+use std::fs;
+use std::io::Read;
+use std::path::Path;
+use std::process::{Command, Stdio};
+use std::sync::{mpsc, Arc, Mutex};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use super::process_tree::ProcessTree;
+use super::settings::{self, SandboxSettings};
+use super::workspace;
+
+// Output kept from a run; the start is dropped once there is more.
+const MAX_OUTPUT: usize = 256 * 1024;
+// Largest file a sandboxed process may write (Unix).
+#[cfg(unix)]
+const MAX_FILE_SIZE: u64 = 2 << 30;
+
+// Variables passed on from the app's environment. Everything else, API keys among it, is left out.
+const KEEP_ENV: &[&str] = &[
+    "PATH",
+    "LANG",
+    "LC_ALL",
+    "LC_CTYPE",
+    "TERM",
+    "TZ",
+    "USER",
+    "LOGNAME",
+    "USERNAME",
+    "JAVA_HOME",
+    "GOROOT",
+    "RUSTUP_HOME",
+    "CARGO_HOME",
+    "SystemRoot",
+    "SystemDrive",
+    "windir",
+    "ComSpec",
+    "PATHEXT",
+    "OS",
+    "PROCESSOR_ARCHITECTURE",
+    "NUMBER_OF_PROCESSORS",
+    "ProgramFiles",
+    "ProgramFiles(x86)",
+    "ProgramW6432",
+    "ProgramData",
+    "CommonProgramFiles",
+];
+
+// Seatbelt profile for `sandbox-exec` that allows everything but the network (loopback aside).
+#[cfg(target_os = "macos")]
+const NO_NETWORK_PROFILE: &str = r#"(version 1)
+(allow default)
+(deny network*)
+(allow network* (local ip "localhost:*"))
+(allow network* (remote ip "localhost:*"))
+(allow network* (remote unix-socket))"#;
+
+/// How a sandboxed command went.
+#[derive(Debug, Clone, Serialize)]
+pub struct SandboxResult {
+    /// `None` when it was killed, e.g. for running out of time.
+    pub exit_code: Option<i32>,
+    pub timed_out: bool,
+    /// Standard output and error as they arrived; only the end of it when `truncated`.
+    pub output: String,
+    pub truncated: bool,
+    /// Whether it was cut off from the network.
+    pub network_blocked: bool,
+    pub duration_ms: u64,
+}
+
+#[derive(Default)]
+struct Output {
+    buf: Vec<u8>,
+    truncated: bool,
+}
+
+impl Output {
+    fn push(&mut self, data: &[u8]) {
+        self.buf.extend_from_slice(data);
+        if self.buf.len() > MAX_OUTPUT {
+            self.buf.drain(..self.buf.len() - MAX_OUTPUT);
+            self.truncated = true;
+        }
+    }
+}
+
+fn spawn_reader(mut from: impl Read + Send + 'static, output: Arc<Mutex<Output>>, done: mpsc::Sender<()>) {
+    thread::spawn(move || {
+        let mut buf = [0u8; 8192];
+        while let Ok(n) = from.read(&mut buf) {
+            if n == 0 {
+                break;
+            }
+            output.lock().unwrap_or_else(|e| e.into_inner()).push(&buf[..n]);
+        }
+        let _ = done.send(());
+    });
+}
+
+/// Runs `command` with the platform shell in the workspace's first folder, in the sandbox the
+/// `ai_command_sandbox` setting describes, and waits for it to finish. Untrusted workspaces
+/// can't run commands, as with the terminal and tasks.
+///
+/// The command gets an empty home folder of its own that is deleted afterwards, and only a few
+/// environment variables. Unless the settings allow the network, it runs in a network namespace
+/// with nothing but loopback on Linux, and under a `sandbox-exec` profile on macOS; Windows
+/// has no equivalent, which `network_blocked` reports. Memory is capped with `setrlimit` on
+/// Unix and a job object on Windows. Whatever is still running when it exits or its time is up
+/// is killed.
+pub fn sandbox_run(command: &str) -> Result<SandboxResult> {
+    workspace::ensure_trusted("Running AI commands")?;
+    let limits = settings::load()?.ai_command_sandbox;
+    let root = workspace::open_roots()?
+        .into_iter()
+        .next()
+        .context("no workspace is open")?
+        .path;
+
+    let home = std::env::temp_dir().join(format!("pompora-sandbox-{:016x}", rand::random::<u64>()));
+    fs::create_dir_all(home.join("tmp")).with_context(|| format!("create dir: {}", home.display()))?;
+    let result = run_in(command, &root, &home, &limits);
+    let _ = fs::remove_dir_all(&home);
+    result
+}
+
+fn run_in(command: &str, cwd: &Path, home: &Path, limits: &SandboxSettings) -> Result<SandboxResult> {
+    let block_network = !limits.allow_network && cfg!(any(target_os = "linux", target_os = "macos"));
+    let mut cmd = if cfg!(windows) {
+        let mut c = Command::new("cmd");
+        c.arg("/C");
+        c
+    } else if cfg!(target_os = "macos") && block_network {
+        let mut c = Command::new("/usr/bin/sandbox-exec");
+        #[cfg(target_os = "macos")]
+        c.args(["-p", NO_NETWORK_PROFILE]);
+        c.args(["/bin/sh", "-c"]);
+        c
+    } else {
+        let mut c = Command::new("sh");
+        c.arg("-c");
+        c
+    };
+    cmd.arg(command)
+        .current_dir(cwd)
+        .stdin(Stdio::null())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped());
+
+    cmd.env_clear();
+    for var in KEEP_ENV {
+        if let Some(value) = std::env::var_os(var) {
+            cmd.env(var, value);
+        }
+    }
+    // rustup and cargo look for installed toolchains under the real home folder.
+    if let Some(real) = dirs::home_dir() {
+        for (var, dir) in [("RUSTUP_HOME", ".rustup"), ("CARGO_HOME", ".cargo")] {
+            if std::env::var_os(var).is_none() && real.join(dir).is_dir() {
+                cmd.env(var, real.join(dir));
+            }
+        }
+    }
+    let tmp = home.join("tmp");
+    cmd.env("HOME", home).env("TMPDIR", &tmp).env("TEMP", &tmp).env("TMP", &tmp);
+    #[cfg(windows)]
+    {
+        use std::os::windows::process::CommandExt;
+        const CREATE_NO_WINDOW: u32 = 0x0800_0000;
+        cmd.env("USERPROFILE", home)
+            .env("APPDATA", home.join(r"AppData\Roaming"))
+            .env("LOCALAPPDATA", home.join(r"AppData\Local"))
+            .creation_flags(CREATE_NO_WINDOW);
+    }
+    #[cfg(unix)]
+    {
+        use std::os::unix::process::CommandExt;
+        cmd.process_group(0);
+        let memory = u64::from(limits.memory_mb) * 1024 * 1024;
+        #[cfg(target_os = "linux")]
+        let isolation = block_network.then(unix::NetIsolation::new);
+        // SAFETY: the closure only makes async-signal-safe calls on data prepared before the fork.
+        unsafe {
+            cmd.pre_exec(move || {
+                unix::set_limits(memory, MAX_FILE_SIZE)?;
+                #[cfg(target_os = "linux")]
+                if let Some(isolation) = &isolation {
+                    isolation.enter()?;
+                }
+                Ok(())
+            });
+        }
+    }
+
+    let started = Instant::now();
+    let mut child = cmd.spawn().map_err(|e| {
+        if cfg!(target_os = "linux") && block_network {
+            anyhow::anyhow!(
+                "start sandboxed command: {e} (network isolation needs unprivileged user namespaces; allow the network in the sandbox settings to run without it)"
+            )
+        } else {
+            anyhow::anyhow!("start sandboxed command: {e}")
+        }
+    })?;
+    let tree = ProcessTree::new(child.id());
+    #[cfg(windows)]
+    if limits.memory_mb > 0 {
+        tree.limit_memory(limits.memory_mb as usize * 1024 * 1024);
+    }
+
+    let output = Arc::new(Mutex::new(Output::default()));
+    let (done_tx, done_rx) = mpsc::channel();
+    let mut readers = 0;
+    if let Some(out) = child.stdout.take() {
+        spawn_reader(out, output.clone(), done_tx.clone());
+        readers += 1;
+    }
+    if let Some(err) = child.stderr.take() {
+        spawn_reader(err, output.clone(), done_tx);
+        readers += 1;
+    }
+
+    let deadline = (limits.timeout_secs > 0).then(|| started + Duration::from_secs(limits.timeout_secs.into()));
+    let mut timed_out = false;
+    let status = loop {
+        if let Some(status) = child.try_wait().context("wait for sandboxed command")? {
+            break Some(status);
+        }
+        if deadline.is_some_and(|d| Instant::now() >= d) {
+            timed_out = true;
+            tree.kill();
+            break child.wait().ok();
+        }
+        thread::sleep(Duration::from_millis(50));
+    };
+    // Nothing it started in the background outlives it.
+    tree.kill();
+    // A process that got away from the tree may still hold the pipes open.
+    for _ in 0..readers {
+        if done_rx.recv_timeout(Duration::from_secs(1)).is_err() {
+            break;
+        }
+    }
+
+    let output = output.lock().unwrap_or_else(|e| e.into_inner());
+    Ok(SandboxResult {
+        exit_code: if timed_out { None } else { status.and_then(|s| s.code()) },
+        timed_out,
+        output: String::from_utf8_lossy(&output.buf).into_owned(),
+        truncated: output.truncated,
+        network_blocked: block_network,
+        duration_ms: started.elapsed().as_millis() as u64,
+    })
+}
+
+// What runs in the child between fork and exec: only async-signal-safe calls.
+#[cfg(unix)]
+mod unix {
+    use std::io;
+
+    #[cfg(all(target_os = "linux", target_env = "gnu"))]
+    type Resource = libc::__rlimit_resource_t;
+    #[cfg(not(all(target_os = "linux", target_env = "gnu")))]
+    type Resource = libc::c_int;
+
+    // Lowers the limit on `resource` to `value`, or to the hard limit already in place if lower.
+    fn lower(resource: Resource, value: u64) -> io::Result<()> {
+        // SAFETY: plain syscalls on a local struct.
+        unsafe {
+            let mut lim: libc::rlimit = std::mem::zeroed();
+            if libc::getrlimit(resource, &mut lim) != 0 {
+                return Err(io::Error::last_os_error());
+            }
+            let value = (value as libc::rlim_t).min(lim.rlim_max);
+            lim.rlim_cur = value;
+            lim.rlim_max = value;
+            if libc::setrlimit(resource, &lim) != 0 {
+                return Err(io::Error::last_os_error());
+            }
+        }
+        Ok(())
+    }
+
+    /// Caps allocated memory (`0` leaves it alone) and file size, and turns core dumps off.
+    pub fn set_limits(memory: u64, file_size: u64) -> io::Result<()> {
+        if memory > 0 {
+            // Not the address space: runtimes like V8 reserve far more of it than they use.
+            lower(libc::RLIMIT_DATA, memory)?;
+        }
+        lower(libc::RLIMIT_FSIZE, file_size)?;
+        lower(libc::RLIMIT_CORE, 0)
+    }
+
+    /// Moves the process into a user namespace of its own, mapped to the same user and group,
+    /// and a network namespace in which only loopback exists.
+    #[cfg(target_os = "linux")]
+    pub struct NetIsolation {
+        uid_map: Vec<u8>,
+        gid_map: Vec<u8>,
+    }
+
+    #[cfg(target_os = "linux")]
+    impl NetIsolation {
+        pub fn new() -> Self {
+            // SAFETY: these never fail.
+            let (uid, gid) = unsafe { (libc::getuid(), libc::getgid()) };
+            NetIsolation {
+                uid_map: format!("{uid} {uid} 1").into_bytes(),
+                gid_map: format!("{gid} {gid} 1").into_bytes(),
+            }
+        }
+
+        pub fn enter(&self) -> io::Result<()> {
+            // SAFETY: a plain syscall.
+            if unsafe { libc::unshare(libc::CLONE_NEWUSER | libc::CLONE_NEWNET) } != 0 {
+                return Err(io::Error::last_os_error());
+            }
+            // Kernels before 3.19 have no `setgroups` file and don't need it written.
+            match write_file(c"/proc/self/setgroups", b"deny") {
+                Err(e) if e.kind() != io::ErrorKind::NotFound => return Err(e),
+                _ => {}
+            }
+            write_file(c"/proc/self/uid_map", &self.uid_map)?;
+            write_file(c"/proc/self/gid_map", &self.gid_map)?;
+            loopback_up();
+            Ok(())
+        }
+    }
+
+    #[cfg(target_os = "linux")]
+    fn write_file(path: &std::ffi::CStr, data: &[u8]) -> io::Result<()> {
+        // SAFETY: plain syscalls; `path` is NUL-terminated and `data` outlives them.
+        unsafe {
+            let fd = libc::open(path.as_ptr(), libc::O_WRONLY | libc::O_CLOEXEC);
+            if fd < 0 {
+                return Err(io::Error::last_os_error());
+            }
+            let n = libc::write(fd, data.as_ptr().cast(), data.len());
+            let err = io::Error::last_os_error();
+            libc::close(fd);
+            if n != data.len() as isize {
+                return Err(err);
+            }
+        }
+        Ok(())
+    }
+
+    // A new network namespace starts with loopback down, which breaks tests that listen on
+    // 127.0.0.1. Best effort: without it the command still runs.
+    #[cfg(target_os = "linux")]
+    fn loopback_up() {
+        // SAFETY: plain syscalls on a local `ifreq`.
+        unsafe {
+            let sock = libc::socket(libc::AF_INET, libc::SOCK_DGRAM | libc::SOCK_CLOEXEC, 0);
+            if sock < 0 {
+                return;
+            }
+            let mut req: libc::ifreq = std::mem::zeroed();
+            for (dst, src) in req.ifr_name.iter_mut().zip(b"lo") {
+                *dst = *src as libc::c_char;
+            }
+            if libc::ioctl(sock, libc::SIOCGIFFLAGS as _, &mut req) == 0 {
+                req.ifr_ifru.ifru_flags |= libc::IFF_UP as libc::c_short;
+                libc::ioctl(sock, libc::SIOCSIFFLAGS as _, &req);
+            }
+            libc::close(sock);
+        }
+    }
+}
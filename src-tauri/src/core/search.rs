@@ -1,121 +1,469 @@
 use anyhow::{anyhow, Context, Result};
+use grep_matcher::Matcher;
+use grep_regex::{RegexMatcher, RegexMatcherBuilder};
+use grep_searcher::{BinaryDetection, Searcher, SearcherBuilder, Sink, SinkContext, SinkMatch};
+use ignore::WalkState;
 use serde::{Deserialize, Serialize};
+use std::collections::{HashSet, VecDeque};
 use std::fs;
-use std::path::PathBuf;
-use walkdir::WalkDir;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::{Mutex, RwLockReadGuard};
 
-use super::settings;
+use super::{fsops, search_index, workspace};
+
+/// Larger files (bundles, dumps, lockfiles) are skipped.
+pub const MAX_FILE_BYTES: u64 = 1_048_576;
+/// Most context lines a match carries on each side.
+pub const MAX_CONTEXT_LINES: u32 = 10;
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct SearchMatch {
     pub path: String,
     pub line: u32,
+    /// 1-based column where the hit starts in `text`, in UTF-16 code units like editor columns.
+    pub column: u32,
+    /// Length of the hit in the same units, line breaks included.
+    pub match_len: u32,
+    /// Line and column just past the end of the hit; `end_line` only differs from `line` for
+    /// hits that span lines.
+    pub end_line: u32,
+    pub end_column: u32,
+    /// The line the hit is on, or every line it touches joined with `\n`.
     pub text: String,
+    /// Lines just above and below the hit, nearest last and first respectively; empty unless
+    /// `context_lines` was asked for, and shorter at the edges of the file.
+    pub before: Vec<String>,
+    pub after: Vec<String>,
 }
 
-fn workspace_root_path() -> Result<PathBuf> {
-    let s = settings::load()?;
-    let root = s
-        .workspace_root
-        .as_deref()
-        .map(|v| v.trim())
-        .filter(|v| !v.is_empty())
-        .ok_or_else(|| anyhow!("no workspace is open"))?;
+#[derive(Debug, Clone, Serialize)]
+pub struct FileMatches {
+    pub path: String,
+    pub matches: Vec<SearchMatch>,
+    /// Hits in the whole file, including any past `max_matches_per_file`.
+    pub total: u32,
+}
 
-    let pb = PathBuf::from(root);
-    if !pb.exists() {
-        return Err(anyhow!("workspace path does not exist"));
-    }
-    if !pb.is_dir() {
-        return Err(anyhow!("workspace path is not a directory"));
-    }
-    Ok(pb)
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct SearchResults {
+    /// Files with hits, by path; each file's hits by line.
+    pub files: Vec<FileMatches>,
+    /// Hits and files counted over the files searched, which is all of them unless `truncated`.
+    pub total_matches: u32,
+    pub total_files: u32,
+    /// Whether `max_results` was reached and the search stopped early.
+    pub truncated: bool,
 }
 
-fn is_likely_text(bytes: &[u8]) -> bool {
-    // reject if it contains NUL byte
-    !bytes.iter().any(|b| *b == 0)
+/// What a search returns. The counting modes skip building match text and context, and send
+/// far less back to the webview.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum SearchMode {
+    /// Files with their hits.
+    #[default]
+    Matches,
+    /// Files with hit counts but no hits; `max_results` caps the number of files.
+    Files,
+    /// Only the totals, over every file; `max_results` doesn't apply.
+    Count,
 }
 
-pub fn workspace_search(query: &str, max_results: usize) -> Result<Vec<SearchMatch>> {
-    let q = query.trim();
-    if q.is_empty() {
-        return Ok(Vec::new());
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default)]
+pub struct SearchOptions {
+    /// Match letter case exactly; by default `error` also finds `Error`.
+    pub case_sensitive: bool,
+    /// Only match where the query isn't part of a longer word.
+    pub whole_word: bool,
+    /// Lines of surrounding code to return with each match, up to `MAX_CONTEXT_LINES`.
+    pub context_lines: u32,
+    /// Workspace folders and files to search instead of the whole workspace. Folders are
+    /// walked like the workspace is; files are searched even if ignored or hidden.
+    pub paths: Vec<String>,
+    /// Treat the query as a regular expression over whole files, so `\n` and `\s+` match line
+    /// breaks and `^`/`$` match at every line. A plain query that contains line breaks is
+    /// matched across lines without this.
+    pub multiline: bool,
+    /// Most hits returned per file; the rest are only counted, so one minified file can't use
+    /// up `max_results`.
+    pub max_matches_per_file: Option<u32>,
+    pub mode: SearchMode,
+}
+
+fn utf16_len(bytes: &[u8]) -> u32 {
+    String::from_utf8_lossy(bytes).encode_utf16().count() as u32
+}
+
+fn line_text(bytes: &[u8]) -> String {
+    String::from_utf8_lossy(bytes).trim_end().to_string()
+}
+
+// Collects one file's matches with their context. The searcher reports each line once, so a
+// line after one hit and before the next is handed to both here.
+struct FileSink<'a> {
+    shared: &'a Shared<'a>,
+    path: &'a str,
+    context: u64,
+    hits: Vec<SearchMatch>,
+    total: u32,
+    recent: VecDeque<(u64, String)>,
+}
+
+impl FileSink<'_> {
+    fn saw_line(&mut self, line: u64, text: &str) {
+        for hit in self.hits.iter_mut().rev() {
+            if line > hit.end_line as u64 + self.context {
+                break;
+            }
+            if line > hit.end_line as u64 {
+                hit.after.push(text.to_string());
+            }
+        }
+        if self.context > 0 {
+            self.recent.push_back((line, text.to_string()));
+            if self.recent.len() as u64 > self.context {
+                self.recent.pop_front();
+            }
+        }
     }
+}
 
-    let root = workspace_root_path()?;
-    let q_lower = q.to_lowercase();
+impl Sink for FileSink<'_> {
+    type Error = io::Error;
 
-    let mut out: Vec<SearchMatch> = Vec::new();
+    fn matched(&mut self, _searcher: &Searcher, mat: &SinkMatch<'_>) -> Result<bool, io::Error> {
+        if self.shared.mode != SearchMode::Matches {
+            self.total += 1;
+            return Ok(true);
+        }
+        let line = mat.line_number().unwrap_or(0);
+        let bytes = mat.bytes();
+        if self.hits.len() >= self.shared.max_per_file {
+            // Past the cap hits are only counted, and still serve as context for the last one.
+            self.total += 1;
+            if self.context > 0 {
+                for (i, l) in bytes.strip_suffix(b"\n").unwrap_or(bytes).split(|b| *b == b'\n').enumerate() {
+                    self.saw_line(line + i as u64, &line_text(l));
+                }
+            }
+            return Ok(true);
+        }
+        if self.shared.taken.fetch_add(1, Ordering::Relaxed) >= self.shared.max_results {
+            self.shared.truncated.store(true, Ordering::Relaxed);
+            return Ok(false);
+        }
+        self.total += 1;
+        let (start, end) = match self.shared.matcher.find(bytes) {
+            Ok(Some(m)) => (m.start(), m.end()),
+            _ => (0, 0),
+        };
+        let lines: Vec<String> = bytes.strip_suffix(b"\n").unwrap_or(bytes).split(|b| *b == b'\n').map(line_text).collect();
+        let (end_line, end_line_start) = match bytes[..end].iter().rposition(|b| *b == b'\n') {
+            Some(nl) => (line + bytes[..end].iter().filter(|b| **b == b'\n').count() as u64, nl + 1),
+            None => (line, 0),
+        };
+        let before = self
+            .recent
+            .iter()
+            .filter(|(l, _)| l + self.context >= line)
+            .map(|(_, t)| t.clone())
+            .collect();
+        for (i, text) in lines.iter().enumerate() {
+            self.saw_line(line + i as u64, text);
+        }
+        self.hits.push(SearchMatch {
+            path: self.path.to_string(),
+            line: line as u32,
+            column: utf16_len(&bytes[..start]) + 1,
+            match_len: utf16_len(&bytes[start..end]),
+            end_line: end_line as u32,
+            end_column: utf16_len(&bytes[end_line_start..end]) + 1,
+            text: lines.join("\n"),
+            before,
+            after: Vec::new(),
+        });
+        Ok(true)
+    }
 
-    for entry in WalkDir::new(&root)
-        .follow_links(false)
-        .into_iter()
-        .filter_map(|e| e.ok())
-    {
-        if out.len() >= max_results {
-            break;
+    fn context(&mut self, _searcher: &Searcher, ctx: &SinkContext<'_>) -> Result<bool, io::Error> {
+        if let Some(line) = ctx.line_number() {
+            self.saw_line(line, &line_text(ctx.bytes()));
         }
+        Ok(true)
+    }
+}
+
+// Whether hits may span lines, which has the searcher look at whole files instead of lines.
+fn spans_lines(query: &str, opts: &SearchOptions) -> bool {
+    opts.multiline || query.contains('\n')
+}
+
+fn build_matcher(query: &str, opts: &SearchOptions) -> Result<RegexMatcher> {
+    let mut builder = RegexMatcherBuilder::new();
+    builder.case_insensitive(!opts.case_sensitive).word(opts.whole_word);
+    let built = if opts.multiline {
+        builder.multi_line(true).crlf(true).build(query)
+    } else if query.contains('\n') {
+        // Line breaks in a plain query match either kind of line ending.
+        let pattern = query.lines().map(regex::escape).collect::<Vec<_>>().join(r"\r?\n");
+        builder.build(&pattern)
+    } else {
+        builder.fixed_strings(true).line_terminator(Some(b'\n')).build(query)
+    };
+    built.map_err(|e| anyhow!("invalid search query: {e}"))
+}
 
-        let ft = entry.file_type();
-        if !ft.is_file() {
-            continue;
+// What one root contributes to a search: folders to walk and files named outright.
+struct Target {
+    root: workspace::OpenRoot,
+    dirs: Vec<PathBuf>,
+    files: Vec<PathBuf>,
+}
+
+// The roots and paths `opts.paths` names, or every root whole when it names none.
+fn targets(paths: &[String]) -> Result<Vec<Target>> {
+    if paths.is_empty() {
+        return Ok(workspace::open_roots()?
+            .into_iter()
+            .map(|root| Target {
+                dirs: vec![root.path.clone()],
+                files: vec![],
+                root,
+            })
+            .collect());
+    }
+    let mut out: Vec<Target> = vec![];
+    for p in paths {
+        let (root, _, abs) = fsops::resolve(p, true)?;
+        let meta = fs::metadata(&abs).map_err(|e| anyhow!("{p}: {e}"))?;
+        let i = match out.iter().position(|t| t.root.path == root.path) {
+            Some(i) => i,
+            None => {
+                out.push(Target {
+                    root,
+                    dirs: vec![],
+                    files: vec![],
+                });
+                out.len() - 1
+            }
+        };
+        if meta.is_dir() {
+            out[i].dirs.push(abs);
+        } else {
+            out[i].files.push(abs);
         }
+    }
+    // Anything inside another listed folder is searched with it, not twice.
+    for t in &mut out {
+        t.dirs.sort();
+        t.dirs.dedup_by(|inner, outer| inner.starts_with(outer));
+        t.files.sort();
+        t.files.dedup();
+        let dirs = &t.dirs;
+        t.files.retain(|f| !dirs.iter().any(|d| f.starts_with(d)));
+    }
+    Ok(out)
+}
 
-        let path = entry.path();
+// State shared by every thread of one search.
+struct Shared<'a> {
+    matcher: &'a RegexMatcher,
+    mode: SearchMode,
+    multi_line: bool,
+    context: usize,
+    max_results: usize,
+    max_per_file: usize,
+    found: Mutex<Vec<FileMatches>>,
+    taken: AtomicUsize,
+    truncated: AtomicBool,
+}
 
-        // skip node_modules and git by default
-        if path.components().any(|c| {
-            let s = c.as_os_str().to_string_lossy().to_lowercase();
-            s == "node_modules" || s == ".git" || s == "dist" || s == "target"
-        }) {
-            continue;
+impl Shared<'_> {
+    fn searcher(&self) -> Searcher {
+        SearcherBuilder::new()
+            .binary_detection(BinaryDetection::quit(0))
+            .line_number(true)
+            .multi_line(self.multi_line)
+            .before_context(self.context)
+            .after_context(self.context)
+            .build()
+    }
+
+    // Whether `max_results` is used up, so files not yet searched should be left alone.
+    fn stop(&self) -> bool {
+        let full = self.taken.load(Ordering::Relaxed) >= self.max_results;
+        if full {
+            self.truncated.store(true, Ordering::Relaxed);
         }
+        full
+    }
+}
 
-        let meta = match entry.metadata() {
-            Ok(m) => m,
-            Err(_) => continue,
-        };
+// The root being searched, with its index when there is one.
+struct RootScope<'a> {
+    root: &'a workspace::OpenRoot,
+    index: Option<RwLockReadGuard<'a, search_index::RootIndex>>,
+    candidates: Option<HashSet<u32>>,
+    unindexed: Mutex<Vec<String>>,
+}
 
-        // 1 MiB limit
-        if meta.len() > 1_048_576 {
-            continue;
+impl RootScope<'_> {
+    fn search_file(&self, shared: &Shared, searcher: &mut Searcher, path: &Path, meta: &fs::Metadata) -> Result<()> {
+        if meta.len() > MAX_FILE_BYTES {
+            return Ok(());
         }
+        let inner = path
+            .strip_prefix(&self.root.path)
+            .with_context(|| format!("strip prefix: {}", self.root.path.display()))?
+            .to_string_lossy()
+            .replace('\\', "/");
+        // With an index, files it says can't match are skipped unread. Files it has no
+        // current entry for are searched and handed back to it afterwards.
+        if let Some(index) = &self.index {
+            match index.fresh_id(&inner, &fsops::file_version(meta)) {
+                Some(id) if self.candidates.as_ref().is_some_and(|c| !c.contains(&id)) => return Ok(()),
+                Some(_) => {}
+                None => self.unindexed.lock().unwrap_or_else(|e| e.into_inner()).push(inner.clone()),
+            }
+        }
+        let rel = self.root.workspace_path(&inner);
 
-        let bytes = match fs::read(path) {
-            Ok(b) => b,
-            Err(_) => continue,
+        let mut sink = FileSink {
+            shared,
+            path: &rel,
+            context: shared.context as u64,
+            hits: Vec::new(),
+            total: 0,
+            recent: VecDeque::new(),
         };
-
-        if !is_likely_text(&bytes) {
-            continue;
+        // Unreadable files are skipped, as they were when search read files whole.
+        let _ = searcher.search_path(shared.matcher, path, &mut sink);
+        if sink.total > 0 && shared.mode == SearchMode::Files && shared.taken.fetch_add(1, Ordering::Relaxed) >= shared.max_results {
+            shared.truncated.store(true, Ordering::Relaxed);
+            return Ok(());
+        }
+        if sink.total > 0 {
+            shared.found.lock().unwrap_or_else(|e| e.into_inner()).push(FileMatches {
+                path: rel.clone(),
+                matches: sink.hits,
+                total: sink.total,
+            });
         }
+        Ok(())
+    }
+}
+
+/// Substring search over text files up to 1 MiB, case-insensitive unless `opts` says
+/// otherwise. Files are searched in parallel, so once `max_results` is reached which files the
+/// hits come from is arbitrary; results are grouped by file. Files with NUL bytes count
+/// as binary and are skipped. Ignored files are skipped unless `include_ignored` (default: the
+/// `include_ignored_files` setting), except files `opts.paths` names outright. Hits can span
+/// lines when the query does or `opts.multiline` is set.
+pub fn workspace_search(
+    query: &str,
+    max_results: usize,
+    include_ignored: Option<bool>,
+    opts: &SearchOptions,
+) -> Result<SearchResults> {
+    let q = query.trim();
+    if q.is_empty() || max_results == 0 {
+        return Ok(SearchResults::default());
+    }
+
+    let matcher = build_matcher(q, opts)?;
+    let include_ignored = workspace::include_ignored(include_ignored);
+    let shared = Shared {
+        matcher: &matcher,
+        mode: opts.mode,
+        multi_line: spans_lines(q, opts),
+        context: match opts.mode {
+            SearchMode::Matches => opts.context_lines.min(MAX_CONTEXT_LINES) as usize,
+            _ => 0,
+        },
+        max_results: match opts.mode {
+            SearchMode::Count => usize::MAX,
+            _ => max_results,
+        },
+        max_per_file: opts.max_matches_per_file.map_or(usize::MAX, |n| n.max(1) as usize),
+        found: Mutex::new(Vec::new()),
+        taken: AtomicUsize::new(0),
+        truncated: AtomicBool::new(false),
+    };
+    let first_error = Mutex::new(None::<anyhow::Error>);
 
-        let s = match String::from_utf8(bytes) {
-            Ok(v) => v,
-            Err(_) => continue,
+    for target in targets(&opts.paths)? {
+        let index = search_index::for_root(&target.root);
+        let guard = index.as_ref().map(|i| i.read().unwrap_or_else(|e| e.into_inner()));
+        let scope = RootScope {
+            root: &target.root,
+            // A regular expression has no literal text for the index to look up.
+            candidates: guard.as_ref().filter(|_| !opts.multiline).and_then(|g| g.candidates(q)),
+            index: guard,
+            unindexed: Mutex::new(Vec::new()),
         };
 
-        for (i, line) in s.lines().enumerate() {
-            if out.len() >= max_results {
+        let mut searcher = shared.searcher();
+        for file in &target.files {
+            if shared.stop() {
                 break;
             }
-
-            if line.to_lowercase().contains(&q_lower) {
-                let rel = path
-                    .strip_prefix(&root)
-                    .with_context(|| format!("strip prefix: {}", root.display()))?
-                    .to_string_lossy()
-                    .replace('\\', "/");
-
-                out.push(SearchMatch {
-                    path: rel,
-                    line: (i as u32) + 1,
-                    text: line.trim_end().to_string(),
-                });
+            if let Ok(meta) = fs::metadata(file) {
+                scope.search_file(&shared, &mut searcher, file, &meta)?;
             }
         }
+        for dir in &target.dirs {
+            target.root.walk_parallel_in(dir, include_ignored, true).run(|| {
+                let (shared, scope, first_error) = (&shared, &scope, &first_error);
+                let mut searcher = shared.searcher();
+                Box::new(move |entry| {
+                    if shared.stop() {
+                        return WalkState::Quit;
+                    }
+                    let Ok(entry) = entry else {
+                        return WalkState::Continue;
+                    };
+                    if !entry.file_type().is_some_and(|t| t.is_file()) {
+                        return WalkState::Continue;
+                    }
+                    let Ok(meta) = entry.metadata() else {
+                        return WalkState::Continue;
+                    };
+                    match scope.search_file(shared, &mut searcher, entry.path(), &meta) {
+                        Ok(()) => WalkState::Continue,
+                        Err(e) => {
+                            first_error.lock().unwrap_or_else(|e| e.into_inner()).get_or_insert(e);
+                            WalkState::Quit
+                        }
+                    }
+                })
+            });
+        }
+
+        let RootScope { index: guard, unindexed, .. } = scope;
+        drop(guard);
+        let unindexed = unindexed.into_inner().unwrap_or_else(|e| e.into_inner());
+        if let Some(index) = &index {
+            search_index::refresh_later(&target.root, index.clone(), unindexed);
+        }
     }
 
-    Ok(out)
+    if let Some(e) = first_error.into_inner().unwrap_or_else(|e| e.into_inner()) {
+        return Err(e);
+    }
+    let mut files = shared.found.into_inner().unwrap_or_else(|e| e.into_inner());
+    files.sort_by(|a, b| a.path.cmp(&b.path));
+    let total_matches = files.iter().map(|f| f.total).sum();
+    let total_files = files.len() as u32;
+    if opts.mode == SearchMode::Count {
+        files.clear();
+    }
+    Ok(SearchResults {
+        files,
+        total_matches,
+        total_files,
+        truncated: shared.truncated.into_inner(),
+    })
 }
@@ -0,0 +1,461 @@
+use anyhow::{anyhow, Context, Result};
+use ignore::WalkState;
+use once_cell::sync::Lazy;
+use sha2::{Digest, Sha256};
+use std::collections::{HashMap, HashSet};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex, RwLock};
+use std::time::{Duration, Instant};
+
+use super::search::MAX_FILE_BYTES;
+use super::notifications::{self, Category};
+use super::workspace::{self, OpenRoot};
+use super::{fsops, settings};
+
+const MAGIC: &[u8; 8] = b"PMPIDX01";
+// Edits are written to disk at most this often; whatever is lost on exit is re-indexed lazily.
+const SAVE_INTERVAL: Duration = Duration::from_secs(30);
+// Superseded entries are only dropped by a rebuild, once they outnumber live ones.
+const COMPACT_AFTER_DEAD: usize = 10_000;
+
+// Three bytes, ASCII-lowercased. Windows spanning a line break are left out.
+type Trigram = u32;
+
+fn trigram(w: &[u8]) -> Option<Trigram> {
+    if w.iter().any(|b| *b == b'\n' || *b == b'\r') {
+        return None;
+    }
+    Some(u32::from_le_bytes([w[0].to_ascii_lowercase(), w[1].to_ascii_lowercase(), w[2].to_ascii_lowercase(), 0]))
+}
+
+fn trigrams(bytes: &[u8]) -> HashSet<Trigram> {
+    bytes.windows(3).filter_map(trigram).collect()
+}
+
+// File ids in ascending order, as varint-encoded gaps.
+#[derive(Default)]
+struct Postings {
+    last: u32,
+    bytes: Vec<u8>,
+}
+
+impl Postings {
+    fn push(&mut self, id: u32) {
+        let mut gap = if self.bytes.is_empty() { id } else { id - self.last };
+        loop {
+            let b = (gap & 0x7f) as u8;
+            gap >>= 7;
+            if gap == 0 {
+                self.bytes.push(b);
+                break;
+            }
+            self.bytes.push(b | 0x80);
+        }
+        self.last = id;
+    }
+
+    fn ids(&self) -> impl Iterator<Item = u32> + '_ {
+        let mut pos = 0;
+        let mut cur = 0u32;
+        std::iter::from_fn(move || {
+            if pos >= self.bytes.len() {
+                return None;
+            }
+            let (mut gap, mut shift) = (0u32, 0);
+            while let Some(b) = self.bytes.get(pos) {
+                pos += 1;
+                gap |= ((b & 0x7f) as u32) << shift;
+                shift += 7;
+                if b & 0x80 == 0 {
+                    break;
+                }
+            }
+            cur += gap;
+            Some(cur)
+        })
+    }
+}
+
+struct IndexedFile {
+    path: String,
+    version: String,
+}
+
+/// Trigram index of one workspace root's text files. It only narrows down which files a
+/// search reads: a file whose version no longer matches is read anyway and re-indexed later.
+pub struct RootIndex {
+    root: PathBuf,
+    // Indexed by file id; `None` once the file was re-indexed under a new id or removed.
+    files: Vec<Option<IndexedFile>>,
+    by_path: HashMap<String, u32>,
+    postings: HashMap<Trigram, Postings>,
+    dead: usize,
+    dirty: bool,
+    last_save: Option<Instant>,
+}
+
+impl RootIndex {
+    fn new(root: &Path) -> Self {
+        Self {
+            root: root.to_path_buf(),
+            files: vec![],
+            by_path: HashMap::new(),
+            postings: HashMap::new(),
+            dead: 0,
+            dirty: false,
+            last_save: None,
+        }
+    }
+
+    // Binary files go in with no trigrams, so searches know to skip them without reading.
+    fn insert(&mut self, rel: String, version: String, grams: &HashSet<Trigram>) {
+        self.remove(&rel);
+        let id = self.files.len() as u32;
+        for g in grams {
+            self.postings.entry(*g).or_default().push(id);
+        }
+        self.by_path.insert(rel.clone(), id);
+        self.files.push(Some(IndexedFile { path: rel, version }));
+        self.dirty = true;
+    }
+
+    fn remove(&mut self, rel: &str) {
+        if let Some(id) = self.by_path.remove(rel) {
+            self.files[id as usize] = None;
+            self.dead += 1;
+            self.dirty = true;
+        }
+    }
+
+    /// The id of `rel` (root-relative) if its entry matches `version`.
+    pub fn fresh_id(&self, rel: &str, version: &str) -> Option<u32> {
+        let id = *self.by_path.get(rel)?;
+        let f = self.files[id as usize].as_ref()?;
+        (f.version == version).then_some(id)
+    }
+
+    /// Ids of the indexed files that can contain `query`, or `None` when it has no usable
+    /// trigram (shorter than three ASCII characters on one line) and every file must be read.
+    /// Case-insensitive matches through non-ASCII case variants of ASCII letters, such as the
+    /// Kelvin sign for `k`, are not found this way.
+    pub fn candidates(&self, query: &str) -> Option<HashSet<u32>> {
+        let mut grams: Vec<Trigram> = query
+            .as_bytes()
+            .windows(3)
+            .filter(|w| w.is_ascii())
+            .filter_map(trigram)
+            .collect::<HashSet<_>>()
+            .into_iter()
+            .collect();
+        if grams.is_empty() {
+            return None;
+        }
+        // Rarest first keeps the running intersection small.
+        grams.sort_by_key(|g| self.postings.get(g).map_or(0, |p| p.bytes.len()));
+        let mut out: Option<HashSet<u32>> = None;
+        for g in grams {
+            let Some(p) = self.postings.get(&g) else {
+                return Some(HashSet::new());
+            };
+            out = Some(match out {
+                None => p.ids().filter(|id| self.files[*id as usize].is_some()).collect(),
+                Some(prev) => p.ids().filter(|id| prev.contains(id)).collect(),
+            });
+            if out.as_ref().is_some_and(|s| s.is_empty()) {
+                break;
+            }
+        }
+        out
+    }
+
+    fn save(&mut self) -> Result<()> {
+        let mut buf = Vec::with_capacity(self.postings.values().map(|p| p.bytes.len() + 12).sum::<usize>() + 64);
+        buf.extend_from_slice(MAGIC);
+        put_bytes(&mut buf, self.root.to_string_lossy().as_bytes());
+        put_u32(&mut buf, self.files.len() as u32);
+        for f in &self.files {
+            match f {
+                Some(f) => {
+                    buf.push(1);
+                    put_bytes(&mut buf, f.path.as_bytes());
+                    put_bytes(&mut buf, f.version.as_bytes());
+                }
+                None => buf.push(0),
+            }
+        }
+        put_u32(&mut buf, self.postings.len() as u32);
+        for (g, p) in &self.postings {
+            put_u32(&mut buf, *g);
+            put_u32(&mut buf, p.last);
+            put_bytes(&mut buf, &p.bytes);
+        }
+        let path = index_file(&self.root)?;
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir).with_context(|| format!("create dir: {}", dir.display()))?;
+        }
+        fsops::write_atomic(&path, &buf, None)?;
+        self.dirty = false;
+        self.last_save = Some(Instant::now());
+        Ok(())
+    }
+
+    fn load(root: &Path) -> Result<Self> {
+        let path = index_file(root)?;
+        let data = fs::read(&path).with_context(|| format!("read {}", path.display()))?;
+        let mut r = Reader { data: &data, pos: 0 };
+        if r.take(MAGIC.len())? != MAGIC {
+            return Err(anyhow!("unknown search index format"));
+        }
+        if r.bytes()? != root.to_string_lossy().as_bytes() {
+            return Err(anyhow!("search index belongs to another folder"));
+        }
+        let mut index = Self::new(root);
+        for id in 0..r.u32()? {
+            if r.take(1)?[0] == 0 {
+                index.files.push(None);
+                index.dead += 1;
+                continue;
+            }
+            let path = String::from_utf8_lossy(r.bytes()?).into_owned();
+            let version = String::from_utf8_lossy(r.bytes()?).into_owned();
+            index.by_path.insert(path.clone(), id);
+            index.files.push(Some(IndexedFile { path, version }));
+        }
+        for _ in 0..r.u32()? {
+            let g = r.u32()?;
+            let last = r.u32()?;
+            let bytes = r.bytes()?.to_vec();
+            index.postings.insert(g, Postings { last, bytes });
+        }
+        index.last_save = Some(Instant::now());
+        Ok(index)
+    }
+}
+
+fn put_u32(buf: &mut Vec<u8>, v: u32) {
+    buf.extend_from_slice(&v.to_le_bytes());
+}
+
+fn put_bytes(buf: &mut Vec<u8>, b: &[u8]) {
+    put_u32(buf, b.len() as u32);
+    buf.extend_from_slice(b);
+}
+
+struct Reader<'a> {
+    data: &'a [u8],
+    pos: usize,
+}
+
+impl<'a> Reader<'a> {
+    fn take(&mut self, n: usize) -> Result<&'a [u8]> {
+        let end = self.pos.checked_add(n).filter(|e| *e <= self.data.len()).ok_or_else(|| anyhow!("search index is truncated"))?;
+        let out = &self.data[self.pos..end];
+        self.pos = end;
+        Ok(out)
+    }
+
+    fn u32(&mut self) -> Result<u32> {
+        let b = self.take(4)?;
+        Ok(u32::from_le_bytes([b[0], b[1], b[2], b[3]]))
+    }
+
+    fn bytes(&mut self) -> Result<&'a [u8]> {
+        let n = self.u32()? as usize;
+        self.take(n)
+    }
+}
+
+// One file per root in the app's config folder, named by a hash of the root's path.
+fn index_file(root: &Path) -> Result<PathBuf> {
+    let base = dirs::config_dir().context("missing config dir")?;
+    let hash = hex::encode(Sha256::digest(root.to_string_lossy().as_bytes()));
+    Ok(base.join("Pompora").join("search-index").join(format!("{}.idx", &hash[..16])))
+}
+
+// Version and trigrams of a file as the index records it; `None` if it isn't something search
+// reads (gone, not a file, or too large).
+fn scan(abs: &Path) -> Option<(String, HashSet<Trigram>)> {
+    let meta = fs::metadata(abs).ok().filter(|m| m.is_file() && m.len() <= MAX_FILE_BYTES)?;
+    let version = fsops::file_version(&meta);
+    let bytes = fs::read(abs).ok()?;
+    let grams = if bytes.contains(&0) { HashSet::new() } else { trigrams(&bytes) };
+    Some((version, grams))
+}
+
+enum Slot {
+    Building,
+    Ready(Arc<RwLock<RootIndex>>),
+}
+
+static INDEXES: Lazy<Mutex<HashMap<PathBuf, Slot>>> = Lazy::new(|| Mutex::new(HashMap::new()));
+
+/// Whether any root's index is in memory, i.e. whether changes need reporting.
+pub fn any_loaded() -> bool {
+    INDEXES
+        .lock()
+        .unwrap_or_else(|e| e.into_inner())
+        .values()
+        .any(|s| matches!(s, Slot::Ready(_)))
+}
+
+fn enabled() -> bool {
+    settings::load().map(|s| s.search_index).unwrap_or(false)
+}
+
+/// The index for `root` when the `search_index` setting is on and it is ready. The first call
+/// loads it from disk, or builds it, in the background and returns `None` meanwhile.
+pub fn for_root(root: &OpenRoot) -> Option<Arc<RwLock<RootIndex>>> {
+    if !enabled() {
+        return None;
+    }
+    let mut indexes = INDEXES.lock().unwrap_or_else(|e| e.into_inner());
+    match indexes.get(&root.path) {
+        Some(Slot::Ready(index)) => Some(index.clone()),
+        Some(Slot::Building) => None,
+        None => {
+            indexes.insert(root.path.clone(), Slot::Building);
+            let root = root.clone();
+            std::thread::spawn(move || {
+                let index = match RootIndex::load(&root.path) {
+                    Ok(mut index) => {
+                        let gone: Vec<String> = index
+                            .by_path
+                            .keys()
+                            .filter(|p| !root.path.join(p).is_file())
+                            .cloned()
+                            .collect();
+                        for p in gone {
+                            index.remove(&p);
+                        }
+                        index
+                    }
+                    Err(_) => {
+                        let index = build(&root);
+                        notifications::notify(
+                            Category::Indexing,
+                            "Search index ready",
+                            &format!("Indexed {} files in {}", index.by_path.len(), root.path.display()),
+                        );
+                        index
+                    }
+                };
+                publish(&root, index);
+            });
+            None
+        }
+    }
+}
+
+fn build(root: &OpenRoot) -> RootIndex {
+    let index = Mutex::new(RootIndex::new(&root.path));
+    root.walk_parallel(false, true).run(|| {
+        let index = &index;
+        Box::new(move |entry| {
+            let Ok(entry) = entry else {
+                return WalkState::Continue;
+            };
+            if !entry.file_type().is_some_and(|t| t.is_file()) {
+                return WalkState::Continue;
+            }
+            let Ok(rel) = entry.path().strip_prefix(&root.path) else {
+                return WalkState::Continue;
+            };
+            if let Some((version, grams)) = scan(entry.path()) {
+                let rel = rel.to_string_lossy().replace('\\', "/");
+                index.lock().unwrap_or_else(|e| e.into_inner()).insert(rel, version, &grams);
+            }
+            WalkState::Continue
+        })
+    });
+    let mut index = index.into_inner().unwrap_or_else(|e| e.into_inner());
+    let _ = index.save();
+    index
+}
+
+fn publish(root: &OpenRoot, mut index: RootIndex) {
+    if index.dirty {
+        let _ = index.save();
+    }
+    INDEXES
+        .lock()
+        .unwrap_or_else(|e| e.into_inner())
+        .insert(root.path.clone(), Slot::Ready(Arc::new(RwLock::new(index))));
+}
+
+// Saves when due, and starts a rebuild once superseded entries dominate.
+fn settle(root: &OpenRoot, index: &Arc<RwLock<RootIndex>>) {
+    let mut guard = index.write().unwrap_or_else(|e| e.into_inner());
+    if guard.dead > COMPACT_AFTER_DEAD && guard.dead > guard.by_path.len() {
+        drop(guard);
+        INDEXES
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .insert(root.path.clone(), Slot::Building);
+        let root = root.clone();
+        std::thread::spawn(move || {
+            let index = build(&root);
+            publish(&root, index);
+        });
+        return;
+    }
+    if guard.dirty && guard.last_save.is_none_or(|t| t.elapsed() >= SAVE_INTERVAL) {
+        let _ = guard.save();
+    }
+}
+
+/// Re-indexes root-relative `paths` in the background; search calls this with the files it
+/// had to read because their entries were missing or out of date.
+pub fn refresh_later(root: &OpenRoot, index: Arc<RwLock<RootIndex>>, paths: Vec<String>) {
+    if paths.is_empty() {
+        return;
+    }
+    let root = root.clone();
+    std::thread::spawn(move || {
+        for rel in paths {
+            let scanned = scan(&root.path.join(&rel));
+            let mut guard = index.write().unwrap_or_else(|e| e.into_inner());
+            match scanned {
+                Some((version, grams)) => guard.insert(rel, version, &grams),
+                None => guard.remove(&rel),
+            }
+        }
+        settle(&root, &index);
+    });
+}
+
+/// Keeps loaded indexes current with changes the watcher saw (workspace paths). Files the
+/// index doesn't know yet are left for search to pick up, since the watcher can't tell
+/// whether they are ignored.
+pub fn note_changes(modified: Vec<String>, removed: Vec<String>) {
+    let mut by_root: HashMap<PathBuf, (OpenRoot, Vec<String>, Vec<String>)> = HashMap::new();
+    for (path, is_removed) in modified.into_iter().map(|p| (p, false)).chain(removed.into_iter().map(|p| (p, true))) {
+        let Ok(Some((root, inner))) = workspace::split_workspace_path(&path) else {
+            continue;
+        };
+        let e = by_root.entry(root.path.clone()).or_insert_with(|| (root, vec![], vec![]));
+        if is_removed {
+            e.2.push(inner);
+        } else {
+            e.1.push(inner);
+        }
+    }
+    for (root_path, (root, modified, removed)) in by_root {
+        let index = match INDEXES.lock().unwrap_or_else(|e| e.into_inner()).get(&root_path) {
+            Some(Slot::Ready(index)) => index.clone(),
+            _ => continue,
+        };
+        let modified: Vec<String> = {
+            let mut guard = index.write().unwrap_or_else(|e| e.into_inner());
+            for rel in &removed {
+                guard.remove(rel);
+            }
+            modified.into_iter().filter(|rel| guard.by_path.contains_key(rel)).collect()
+        };
+        if modified.is_empty() {
+            settle(&root, &index);
+        } else {
+            refresh_later(&root, index, modified);
+        }
+    }
+}
@@ -1,6 +1,21 @@
+use aes_gcm::aead::{Aead, KeyInit};
+use aes_gcm::{Aes256Gcm, Nonce};
+use anyhow::anyhow;
+use base64::Engine as _;
+use once_cell::sync::Lazy;
+use rand::RngCore;
 use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
 use std::fs;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
+use std::sync::{Mutex, MutexGuard};
+use std::thread;
+use std::time::{Duration, Instant};
+use zeroize::Zeroizing;
+
+use super::key_audit::{self, KeyAccess};
+use super::private_fs;
+use super::settings::{self, SecretBackend, WorkspaceKeyOverride};
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct KeyStatus {
@@ -9,12 +24,14 @@ pub struct KeyStatus {
     pub storage: StorageKind,
 }
 
-#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
 #[serde(rename_all = "lowercase")]
 pub enum StorageKind {
     None,
     Keyring,
     Encryptedfile,
+    File,
+    Env,
 }
 
  fn safe_provider_id(provider: &str) -> String {
@@ -39,240 +56,953 @@ pub enum StorageKind {
          .join(format!("provider-{safe}.txt")))
  }
 
-// METHOD 1: Simple file storage in project directory
-pub fn provider_key_set_method1(provider: &str, api_key: &str) -> Result<(), String> {
-    let path = key_path(provider)?;
-    let api_key = api_key.trim();
-    if api_key.is_empty() {
-        return Err("API key cannot be empty".to_string());
-    }
+fn encrypted_key_path(provider: &str) -> Result<PathBuf, String> {
+    Ok(key_path(provider)?.with_extension("enc"))
+}
 
+fn write_secret_file(path: &Path, contents: &[u8]) -> Result<(), String> {
     let parent = path
         .parent()
         .ok_or_else(|| format!("Invalid key path: {}", path.display()))?;
-    fs::create_dir_all(parent)
+    private_fs::create_private_dir(parent)
         .map_err(|e| format!("Failed to create secrets directory {}: {e}", parent.display()))?;
 
-    let tmp = path.with_extension("txt.tmp");
-    fs::write(&tmp, api_key)
+    let tmp = path.with_extension("tmp");
+    private_fs::write_private(&tmp, contents)
         .map_err(|e| format!("Failed to write temp key file {}: {e}", tmp.display()))?;
 
     if path.exists() {
-        fs::remove_file(&path)
+        fs::remove_file(path)
             .map_err(|e| format!("Failed to remove existing key file {}: {e}", path.display()))?;
     }
 
-    fs::rename(&tmp, &path)
-        .map_err(|e| format!("Failed to rename temp key file to {}: {e}", path.display()))?;
+    fs::rename(&tmp, path)
+        .map_err(|e| format!("Failed to rename temp key file to {}: {e}", path.display()))
+}
 
+fn remove_if_exists(path: &Path) -> Result<(), String> {
+    if path.exists() {
+        fs::remove_file(path).map_err(|e| format!("Failed to remove key file {}: {e}", path.display()))?;
+    }
     Ok(())
 }
 
-pub fn provider_key_get_method1(provider: &str) -> Result<String, String> {
-    let path = key_path(provider)?;
-    let content = fs::read_to_string(&path)
-        .map_err(|e| format!("Failed to read key file {}: {e}", path.display()))?;
-    let v = content.trim().to_string();
-    if v.is_empty() {
-        return Err(format!("Key file is empty: {}", path.display()));
-    }
-    Ok(v)
+/// On-disk format of a password-encrypted key: AES-256-GCM with an Argon2id-derived key.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct EncryptedSecret {
+    v: u32,
+    salt: String,
+    nonce: String,
+    ciphertext: String,
 }
 
-// METHOD 2: JSON file storage
-pub fn provider_key_set_method2(provider: &str, api_key: &str) -> Result<(), String> {
-    let path = PathBuf::from("api_keys.json");
-    let data = serde_json::json!({ provider: api_key });
-    fs::write(&path, serde_json::to_string_pretty(&data).unwrap()).map_err(|e| e.to_string())
+type Key = Zeroizing<[u8; 32]>;
+
+/// Keys derived from the master password by `secrets_unlock`; the password itself isn't kept.
+/// Cleared (and zeroed) by `secrets_lock` or after `secrets_auto_lock_minutes` without use.
+struct UnlockedSession {
+    // By the salt they were derived with, one per encrypted key file.
+    keys: HashMap<Vec<u8>, Key>,
+    // Keys stored while unlocked are encrypted under this salt's key.
+    write_salt: Vec<u8>,
+    // Tells the auto-lock timer of this session apart from a later one's.
+    unlocked_at: Instant,
+    last_used: Instant,
 }
 
-pub fn provider_key_get_method2(provider: &str) -> Result<String, String> {
-    let path = PathBuf::from("api_keys.json");
-    let content = fs::read_to_string(&path).map_err(|e| e.to_string())?;
-    let data: serde_json::Value = serde_json::from_str(&content).map_err(|e| e.to_string())?;
-    data.get(provider).and_then(|v| v.as_str()).ok_or_else(|| "Key not found".to_string()).map(|s| s.to_string())
+static SESSION: Lazy<Mutex<Option<UnlockedSession>>> = Lazy::new(|| Mutex::new(None));
+
+// How often the auto-lock timer rereads the timeout, which may change while it waits.
+const AUTO_LOCK_RECHECK: Duration = Duration::from_secs(60);
+
+fn auto_lock_minutes() -> u32 {
+    settings::load().map(|s| s.secrets_auto_lock_minutes).unwrap_or(15)
 }
 
-// METHOD 3: Environment file
-pub fn provider_key_set_method3(provider: &str, api_key: &str) -> Result<(), String> {
-    let path = PathBuf::from(".env");
-    let line = format!("{}_API_KEY={}\n", provider.to_uppercase(), api_key);
-    fs::write(&path, line).map_err(|e| e.to_string())
+fn idle_timeout(minutes: u32) -> Option<Duration> {
+    (minutes > 0).then(|| Duration::from_secs(u64::from(minutes) * 60))
 }
 
-pub fn provider_key_get_method3(provider: &str) -> Result<String, String> {
-    let path = PathBuf::from(".env");
-    let content = fs::read_to_string(&path).map_err(|e| e.to_string())?;
-    let prefix = format!("{}_API_KEY=", provider.to_uppercase());
-    for line in content.lines() {
-        if line.starts_with(&prefix) {
-            return Ok(line[prefix.len()..].to_string());
+// Returns the session slot, dropping a session that has been idle past the timeout.
+fn session() -> MutexGuard<'static, Option<UnlockedSession>> {
+    let timeout = idle_timeout(auto_lock_minutes());
+    let mut guard = SESSION.lock().unwrap_or_else(|e| e.into_inner());
+    if let (Some(sess), Some(timeout)) = (guard.as_ref(), timeout) {
+        if sess.last_used.elapsed() >= timeout {
+            *guard = None;
         }
     }
-    Err("Key not found".to_string())
+    guard
+}
+
+// Locks the session unlocked at `unlocked_at` once it has been idle past the timeout, rather
+// than leaving the keys in memory until the next secrets call notices.
+fn spawn_auto_lock(unlocked_at: Instant) {
+    thread::spawn(move || loop {
+        let timeout = idle_timeout(auto_lock_minutes());
+        let wait = {
+            let mut guard = SESSION.lock().unwrap_or_else(|e| e.into_inner());
+            // Locked already, or replaced by a session with its own timer.
+            let Some(sess) = guard.as_ref().filter(|s| s.unlocked_at == unlocked_at) else {
+                return;
+            };
+            match timeout {
+                Some(timeout) if sess.last_used.elapsed() >= timeout => {
+                    *guard = None;
+                    return;
+                }
+                Some(timeout) => timeout - sess.last_used.elapsed(),
+                None => AUTO_LOCK_RECHECK,
+            }
+        };
+        thread::sleep(wait.min(AUTO_LOCK_RECHECK));
+    });
 }
 
-// METHOD 4: Config directory storage
-pub fn provider_key_set_method4(provider: &str, api_key: &str) -> Result<(), String> {
-    let mut path = dirs::config_dir().unwrap_or_else(|| PathBuf::from("."));
-    path.push("pompora");
-    path.push(format!("{}.txt", provider));
-    fs::create_dir_all(path.parent().unwrap()).map_err(|e| e.to_string())?;
-    fs::write(&path, api_key).map_err(|e| e.to_string())
+// The unlocked session's key for `salt`, if it has one.
+fn session_key(salt: &[u8]) -> Option<Key> {
+    let mut guard = session();
+    let sess = guard.as_mut()?;
+    sess.last_used = Instant::now();
+    sess.keys.get(salt).cloned()
 }
 
-pub fn provider_key_get_method4(provider: &str) -> Result<String, String> {
-    let mut path = dirs::config_dir().unwrap_or_else(|| PathBuf::from("."));
-    path.push("pompora");
-    path.push(format!("{}.txt", provider));
-    fs::read_to_string(&path).map_err(|e| e.to_string())
+fn session_write_key() -> Option<(Vec<u8>, Key)> {
+    let mut guard = session();
+    let sess = guard.as_mut()?;
+    sess.last_used = Instant::now();
+    let key = sess.keys.get(&sess.write_salt)?.clone();
+    Some((sess.write_salt.clone(), key))
 }
 
-// METHOD 5: Temp directory storage
-pub fn provider_key_set_method5(provider: &str, api_key: &str) -> Result<(), String> {
-    let mut path = std::env::temp_dir();
-    path.push("pompora_keys");
-    path.push(format!("{}.txt", provider));
-    fs::create_dir_all(path.parent().unwrap()).map_err(|e| e.to_string())?;
-    fs::write(&path, api_key).map_err(|e| e.to_string())
+fn derive_key(password: &str, salt: &[u8]) -> Result<Key, String> {
+    let mut key = Zeroizing::new([0u8; 32]);
+    argon2::Argon2::default()
+        .hash_password_into(password.as_bytes(), salt, key.as_mut())
+        .map_err(|e| format!("Failed to derive encryption key: {e}"))?;
+    Ok(key)
 }
 
-pub fn provider_key_get_method5(provider: &str) -> Result<String, String> {
-    let mut path = std::env::temp_dir();
-    path.push("pompora_keys");
-    path.push(format!("{}.txt", provider));
-    fs::read_to_string(&path).map_err(|e| e.to_string())
+fn encrypt_secret(password: &str, plaintext: &str) -> Result<EncryptedSecret, String> {
+    let mut salt = [0u8; 16];
+    rand::thread_rng().fill_bytes(&mut salt);
+    encrypt_with_key(&derive_key(password, &salt)?, &salt, plaintext)
 }
 
-// METHOD 6: Binary file storage
-pub fn provider_key_set_method6(provider: &str, api_key: &str) -> Result<(), String> {
-    let path = PathBuf::from(format!("{}.key", provider));
-    fs::write(&path, api_key.as_bytes()).map_err(|e| e.to_string())
+fn encrypt_with_key(key: &Key, salt: &[u8], plaintext: &str) -> Result<EncryptedSecret, String> {
+    let mut nonce = [0u8; 12];
+    rand::thread_rng().fill_bytes(&mut nonce);
+
+    let cipher = Aes256Gcm::new_from_slice(key.as_ref()).map_err(|e| e.to_string())?;
+    let ciphertext = cipher
+        .encrypt(Nonce::from_slice(&nonce), plaintext.as_bytes())
+        .map_err(|_| "Failed to encrypt key".to_string())?;
+
+    let b64 = base64::engine::general_purpose::STANDARD;
+    Ok(EncryptedSecret {
+        v: 1,
+        salt: b64.encode(salt),
+        nonce: b64.encode(nonce),
+        ciphertext: b64.encode(ciphertext),
+    })
 }
 
-pub fn provider_key_get_method6(provider: &str) -> Result<String, String> {
-    let path = PathBuf::from(format!("{}.key", provider));
-    let bytes = fs::read(&path).map_err(|e| e.to_string())?;
-    String::from_utf8(bytes).map_err(|e| e.to_string())
+fn secret_salt(secret: &EncryptedSecret) -> Result<Vec<u8>, String> {
+    if secret.v != 1 {
+        return Err(format!("Unsupported encrypted key version: {}", secret.v));
+    }
+    base64::engine::general_purpose::STANDARD
+        .decode(&secret.salt)
+        .map_err(|e| e.to_string())
 }
 
-// METHOD 7: Base64 encoded file
-pub fn provider_key_set_method7(provider: &str, api_key: &str) -> Result<(), String> {
-    use base64::Engine as _;
-    let path = key_path(provider)?;
-    let parent = path.parent().ok_or("Invalid path")?;
-    std::fs::create_dir_all(parent).map_err(|e| e.to_string())?;
-    let encoded = base64::engine::general_purpose::STANDARD.encode(api_key);
-    std::fs::write(&path, encoded).map_err(|e| e.to_string())
+fn decrypt_secret(password: &str, secret: &EncryptedSecret) -> Result<String, String> {
+    decrypt_with_key(&derive_key(password, &secret_salt(secret)?)?, secret)
 }
 
-pub fn provider_key_get_method7(provider: &str) -> Result<String, String> {
-    use base64::Engine as _;
-    let path = key_path(provider)?;
-    let content = std::fs::read_to_string(&path).map_err(|e| e.to_string())?;
-    let decoded = base64::engine::general_purpose::STANDARD
-        .decode(&content)
-        .map_err(|e| e.to_string())?;
-    String::from_utf8(decoded).map_err(|e| e.to_string())
+fn decrypt_with_key(key: &Key, secret: &EncryptedSecret) -> Result<String, String> {
+    let b64 = base64::engine::general_purpose::STANDARD;
+    let nonce = b64.decode(&secret.nonce).map_err(|e| e.to_string())?;
+    let ciphertext = b64.decode(&secret.ciphertext).map_err(|e| e.to_string())?;
+    if nonce.len() != 12 {
+        return Err("Encrypted key file is corrupt".to_string());
+    }
+
+    let cipher = Aes256Gcm::new_from_slice(key.as_ref()).map_err(|e| e.to_string())?;
+    let plaintext = cipher
+        .decrypt(Nonce::from_slice(&nonce), ciphertext.as_ref())
+        .map_err(|_| "Wrong encryption password or corrupt key file".to_string())?;
+    String::from_utf8(plaintext).map_err(|e| e.to_string())
 }
 
-// METHOD 8: Windows Registry style (file-based)
-pub fn provider_key_set_method8(provider: &str, api_key: &str) -> Result<(), String> {
-    let path = PathBuf::from("registry.json");
-    let mut data = if path.exists() {
-        let content = fs::read_to_string(&path).map_err(|e| e.to_string())?;
-        serde_json::from_str(&content).unwrap_or_else(|_| serde_json::Value::Object(Default::default()))
-    } else {
-        serde_json::Value::Object(Default::default())
-    };
-    
-    if let Some(obj) = data.as_object_mut() {
-        obj.insert(provider.to_string(), serde_json::Value::String(api_key.to_string()));
+fn non_empty_key(api_key: &str) -> Result<&str, String> {
+    let api_key = api_key.trim();
+    if api_key.is_empty() {
+        return Err("API key cannot be empty".to_string());
+    }
+    Ok(api_key)
+}
+
+/// A place provider API keys can live. `get` returns `Ok(None)` when the backend simply
+/// has no key for the provider; errors are reserved for real failures.
+pub trait SecretStore {
+    fn kind(&self) -> StorageKind;
+    fn get(&self, provider: &str) -> Result<Option<String>, String>;
+    fn set(&self, provider: &str, api_key: &str) -> Result<(), String>;
+    fn clear(&self, provider: &str) -> Result<(), String>;
+
+    fn contains(&self, provider: &str) -> Result<bool, String> {
+        Ok(self.get(provider)?.is_some())
+    }
+}
+
+/// Plain text file under the config directory (`Pompora/secrets/provider-<id>.txt`).
+pub struct FileStore;
+
+impl SecretStore for FileStore {
+    fn kind(&self) -> StorageKind {
+        StorageKind::File
+    }
+
+    fn get(&self, provider: &str) -> Result<Option<String>, String> {
+        let path = key_path(provider)?;
+        if !path.exists() {
+            return Ok(None);
+        }
+        let content = fs::read_to_string(&path)
+            .map_err(|e| format!("Failed to read key file {}: {e}", path.display()))?;
+        let v = content.trim().to_string();
+        if v.is_empty() {
+            return Err(format!("Key file is empty: {}", path.display()));
+        }
+        Ok(Some(v))
+    }
+
+    fn set(&self, provider: &str, api_key: &str) -> Result<(), String> {
+        let api_key = non_empty_key(api_key)?;
+        write_secret_file(&key_path(provider)?, api_key.as_bytes())
+    }
+
+    fn clear(&self, provider: &str) -> Result<(), String> {
+        remove_if_exists(&key_path(provider)?)
+    }
+
+    fn contains(&self, provider: &str) -> Result<bool, String> {
+        Ok(key_path(provider)?.exists())
+    }
+}
+
+/// AES-256-GCM encrypted file (`provider-<id>.enc`); reading and writing need the password.
+pub struct EncryptedFileStore {
+    password: Option<String>,
+}
+
+impl EncryptedFileStore {
+    pub fn new(password: Option<&str>) -> Self {
+        Self {
+            password: password.filter(|v| !v.is_empty()).map(|v| v.to_string()),
+        }
+    }
+
+    fn locked(provider: &str) -> String {
+        format!("The {provider} key is encrypted; unlock the key store or provide the encryption password")
+    }
+}
+
+fn read_encrypted(path: &Path) -> Result<EncryptedSecret, String> {
+    let raw = fs::read_to_string(path).map_err(|e| format!("Failed to read key file {}: {e}", path.display()))?;
+    serde_json::from_str(&raw).map_err(|e| format!("Invalid encrypted key file {}: {e}", path.display()))
+}
+
+impl SecretStore for EncryptedFileStore {
+    fn kind(&self) -> StorageKind {
+        StorageKind::Encryptedfile
+    }
+
+    fn get(&self, provider: &str) -> Result<Option<String>, String> {
+        let path = encrypted_key_path(provider)?;
+        if !path.exists() {
+            return Ok(None);
+        }
+        let secret = read_encrypted(&path)?;
+        // An explicit password wins; otherwise fall back to the unlocked session, if any.
+        match &self.password {
+            Some(password) => decrypt_secret(password, &secret),
+            None => {
+                let key = session_key(&secret_salt(&secret)?).ok_or_else(|| Self::locked(provider))?;
+                decrypt_with_key(&key, &secret)
+            }
+        }
+        .map(Some)
+    }
+
+    fn set(&self, provider: &str, api_key: &str) -> Result<(), String> {
+        let api_key = non_empty_key(api_key)?;
+        let secret = match &self.password {
+            Some(password) => encrypt_secret(password, api_key)?,
+            None => {
+                let (salt, key) = session_write_key().ok_or_else(|| Self::locked(provider))?;
+                encrypt_with_key(&key, &salt, api_key)?
+            }
+        };
+        let json = serde_json::to_string_pretty(&secret).map_err(|e| e.to_string())?;
+        write_secret_file(&encrypted_key_path(provider)?, json.as_bytes())
+    }
+
+    fn clear(&self, provider: &str) -> Result<(), String> {
+        remove_if_exists(&encrypted_key_path(provider)?)
+    }
+
+    fn contains(&self, provider: &str) -> Result<bool, String> {
+        Ok(encrypted_key_path(provider)?.exists())
     }
-    
-    fs::write(&path, serde_json::to_string_pretty(&data).unwrap()).map_err(|e| e.to_string())
 }
 
-pub fn provider_key_get_method8(provider: &str) -> Result<String, String> {
-    let path = PathBuf::from("registry.json");
-    let content = fs::read_to_string(&path).map_err(|e| e.to_string())?;
-    let data: serde_json::Value = serde_json::from_str(&content).map_err(|e| e.to_string())?;
-    data.get(provider).and_then(|v| v.as_str()).ok_or_else(|| "Key not found".to_string()).map(|s| s.to_string())
+/// OS credential store (macOS Keychain, Windows Credential Manager, Secret Service on Linux).
+pub struct KeyringStore;
+
+const KEYRING_SERVICE: &str = "Pompora";
+
+fn keyring_entry(provider: &str) -> Result<keyring::Entry, String> {
+    let user = format!("provider-{}", safe_provider_id(provider));
+    keyring::Entry::new(KEYRING_SERVICE, &user).map_err(|e| format!("Failed to open keyring entry: {e}"))
+}
+
+impl SecretStore for KeyringStore {
+    fn kind(&self) -> StorageKind {
+        StorageKind::Keyring
+    }
+
+    fn get(&self, provider: &str) -> Result<Option<String>, String> {
+        match keyring_entry(provider)?.get_password() {
+            Ok(v) => Ok(Some(v.trim().to_string()).filter(|v| !v.is_empty())),
+            Err(keyring::Error::NoEntry) => Ok(None),
+            Err(e) => Err(format!("Failed to read key from keyring: {e}")),
+        }
+    }
+
+    fn set(&self, provider: &str, api_key: &str) -> Result<(), String> {
+        let api_key = non_empty_key(api_key)?;
+        keyring_entry(provider)?
+            .set_password(api_key)
+            .map_err(|e| format!("Failed to store key in keyring: {e}"))
+    }
+
+    fn clear(&self, provider: &str) -> Result<(), String> {
+        match keyring_entry(provider)?.delete_credential() {
+            Ok(()) | Err(keyring::Error::NoEntry) => Ok(()),
+            Err(e) => Err(format!("Failed to remove key from keyring: {e}")),
+        }
+    }
 }
 
-// METHOD 9: Simple INI file format
-pub fn provider_key_set_method9(provider: &str, api_key: &str) -> Result<(), String> {
-    let path = PathBuf::from("config.ini");
-    let line = format!("{}={}\n", provider, api_key);
-    fs::write(&path, line).map_err(|e| e.to_string())
+/// Read-only: `POMPORA_<PROVIDER>_API_KEY`, then the provider's conventional `<PROVIDER>_API_KEY`.
+pub struct EnvStore;
+
+fn env_var_names(provider: &str) -> [String; 2] {
+    let id = safe_provider_id(provider).to_uppercase().replace('-', "_");
+    [format!("POMPORA_{id}_API_KEY"), format!("{id}_API_KEY")]
 }
 
-pub fn provider_key_get_method9(provider: &str) -> Result<String, String> {
-    let path = PathBuf::from("config.ini");
-    let content = fs::read_to_string(&path).map_err(|e| e.to_string())?;
-    for line in content.lines() {
-        if let Some((key, value)) = line.split_once('=') {
-            if key.trim() == provider {
-                return Ok(value.trim().to_string());
+impl SecretStore for EnvStore {
+    fn kind(&self) -> StorageKind {
+        StorageKind::Env
+    }
+
+    fn get(&self, provider: &str) -> Result<Option<String>, String> {
+        for name in env_var_names(provider) {
+            if let Ok(v) = std::env::var(&name) {
+                let v = v.trim();
+                if !v.is_empty() {
+                    return Ok(Some(v.to_string()));
+                }
             }
         }
+        Ok(None)
+    }
+
+    fn set(&self, _provider: &str, _api_key: &str) -> Result<(), String> {
+        Err("Environment variables are read-only; set the key in your shell profile instead".to_string())
+    }
+
+    fn clear(&self, _provider: &str) -> Result<(), String> {
+        Ok(())
+    }
+}
+
+pub fn store_for(backend: SecretBackend, encryption_password: Option<&str>) -> Box<dyn SecretStore> {
+    match backend {
+        SecretBackend::File => Box::new(FileStore),
+        SecretBackend::Keyring => Box::new(KeyringStore),
+        SecretBackend::Encryptedfile => Box::new(EncryptedFileStore::new(encryption_password)),
+        SecretBackend::Env => Box::new(EnvStore),
+    }
+}
+
+fn configured_backend() -> SecretBackend {
+    settings::load().map(|s| s.secret_backend).unwrap_or_default()
+}
+
+/// The configured backend first, then every other backend in a fixed order, so keys saved
+/// before a backend switch keep working until they're migrated.
+fn lookup_order(encryption_password: Option<&str>) -> Vec<Box<dyn SecretStore>> {
+    let primary = configured_backend();
+    let mut out = vec![store_for(primary, encryption_password)];
+    for b in [SecretBackend::Encryptedfile, SecretBackend::File, SecretBackend::Keyring, SecretBackend::Env] {
+        if b != primary {
+            out.push(store_for(b, encryption_password));
+        }
+    }
+    out
+}
+
+fn write_store(encryption_password: Option<&str>) -> Box<dyn SecretStore> {
+    if encryption_password.is_some_and(|p| !p.is_empty()) {
+        return Box::new(EncryptedFileStore::new(encryption_password));
+    }
+    store_for(configured_backend(), None)
+}
+
+pub const DEFAULT_CREDENTIAL: &str = "default";
+
+fn validate_credential_name(name: &str) -> Result<&str, String> {
+    let name = name.trim();
+    if name.is_empty() || name.len() > 32 {
+        return Err("Credential name must be 1-32 characters".to_string());
+    }
+    if !name.chars().all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_') {
+        return Err("Credential name may only contain letters, digits, '-' and '_'".to_string());
     }
-    Err("Key not found".to_string())
+    Ok(name)
 }
 
-// METHOD 10: Memory-mapped file simulation
-pub fn provider_key_set_method10(provider: &str, api_key: &str) -> Result<(), String> {
-    let path = PathBuf::from("memory_store.bin");
-    let mut data = if path.exists() {
-        fs::read(&path).map_err(|e| e.to_string())?
+/// Storage id for a provider credential. The default credential keeps the bare provider id so
+/// keys saved before named credentials existed are still found.
+fn credential_slot(provider: &str, name: &str) -> String {
+    if name == DEFAULT_CREDENTIAL {
+        provider.to_string()
     } else {
-        Vec::new()
-    };
-    
-    let entry = format!("{}:{}\n", provider, api_key);
-    data.extend_from_slice(entry.as_bytes());
-    fs::write(&path, data).map_err(|e| e.to_string())
-}
-
-pub fn provider_key_get_method10(provider: &str) -> Result<String, String> {
-    let path = PathBuf::from("memory_store.bin");
-    let content = fs::read_to_string(&path).map_err(|e| e.to_string())?;
-    for line in content.lines() {
-        if let Some((key, value)) = line.split_once(':') {
-            if key == provider {
-                return Ok(value.to_string());
-            }
+        format!("{provider}--{name}")
+    }
+}
+
+fn resolve_credential(provider: &str, credential: Option<&str>) -> Result<String, String> {
+    match credential.map(|c| c.trim()).filter(|c| !c.is_empty()) {
+        Some(name) => validate_credential_name(name).map(|n| n.to_string()),
+        None => Ok(settings::load()
+            .ok()
+            .and_then(|s| s.active_credential.get(provider).cloned())
+            .unwrap_or_else(|| DEFAULT_CREDENTIAL.to_string())),
+    }
+}
+
+fn slot_storage(slot: &str) -> StorageKind {
+    for store in lookup_order(None) {
+        // Probing the keyring can fail when no Secret Service is running; that just means
+        // the key isn't there.
+        if store.contains(slot).unwrap_or(false) {
+            return store.kind();
         }
     }
-    Err("Key not found".to_string())
+    StorageKind::None
 }
 
-// WORKING IMPLEMENTATION - Using Method 1 (Simple file storage)
-pub fn provider_key_status(provider: &str) -> Result<KeyStatus, String> {
-    let path = key_path(provider)?;
-    let is_configured = path.exists();
-    
+pub fn provider_key_status(provider: &str, credential: Option<&str>) -> Result<KeyStatus, String> {
+    let name = resolve_credential(provider, credential)?;
+    let storage = slot_storage(&credential_slot(provider, &name));
+
     Ok(KeyStatus {
         provider: provider.to_string(),
-        is_configured,
-        storage: if is_configured { StorageKind::Keyring } else { StorageKind::None },
+        is_configured: storage != StorageKind::None,
+        storage,
+    })
+}
+
+pub fn provider_key_set(
+    provider: &str,
+    api_key: &str,
+    encryption_password: Option<&str>,
+    credential: Option<&str>,
+    caller: &str,
+) -> Result<(), String> {
+    let name = resolve_credential(provider, credential)?;
+    let slot = credential_slot(provider, &name);
+
+    let target = write_store(encryption_password);
+    let res = target.set(&slot, api_key);
+    key_audit::record(KeyAccess::Write, provider, &name, caller, res.is_ok());
+    res?;
+    // Keep a single copy: drop the key from every other writable backend.
+    for store in lookup_order(None) {
+        if store.kind() != target.kind() {
+            let _ = store.clear(&slot);
+        }
+    }
+
+    if name != DEFAULT_CREDENTIAL {
+        settings::update(|s| {
+            let names = s.provider_credentials.entry(provider.to_string()).or_default();
+            if !names.contains(&name) {
+                names.push(name);
+            }
+            Ok(())
+        })
+        .map_err(|e| e.to_string())?;
+    }
+    Ok(())
+}
+
+pub fn provider_key_get(
+    provider: &str,
+    encryption_password: Option<&str>,
+    credential: Option<&str>,
+    caller: &str,
+) -> Result<String, String> {
+    let name = resolve_credential(provider, credential)?;
+    let res = lookup_key(provider, &name, encryption_password);
+    key_audit::record(KeyAccess::Read, provider, &name, caller, res.is_ok());
+    res
+}
+
+fn lookup_key(provider: &str, name: &str, encryption_password: Option<&str>) -> Result<String, String> {
+    let slot = credential_slot(provider, name);
+    for store in lookup_order(encryption_password) {
+        let found = match store.get(&slot) {
+            Ok(v) => v,
+            // No Secret Service/keychain available: treat as "not stored there".
+            Err(_) if store.kind() == StorageKind::Keyring => None,
+            Err(e) => return Err(e),
+        };
+        if let Some(v) = found {
+            return Ok(v);
+        }
+    }
+    if name == DEFAULT_CREDENTIAL {
+        return Err(format!("No API key configured for {provider}"));
+    }
+    Err(format!("No API key configured for {provider} credential \"{name}\""))
+}
+
+pub fn provider_key_clear(provider: &str, credential: Option<&str>, caller: &str) -> Result<(), String> {
+    let name = resolve_credential(provider, credential)?;
+    let slot = credential_slot(provider, &name);
+    let res = lookup_order(None).iter().try_for_each(|store| {
+        if store.kind() == StorageKind::Keyring {
+            let _ = store.clear(&slot);
+            return Ok(());
+        }
+        store.clear(&slot)
+    });
+    key_audit::record(KeyAccess::Clear, provider, &name, caller, res.is_ok());
+    res?;
+
+    settings::update(|s| {
+        if let Some(names) = s.provider_credentials.get_mut(provider) {
+            names.retain(|n| n != &name);
+        }
+        if s.active_credential.get(provider) == Some(&name) {
+            s.active_credential.remove(provider);
+        }
+        Ok(())
     })
+    .map(|_| ())
+    .map_err(|e| e.to_string())
 }
 
-pub fn provider_key_set(provider: &str, api_key: &str, _encryption_password: Option<&str>) -> Result<(), String> {
-    provider_key_set_method1(provider, api_key)
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CredentialInfo {
+    pub name: String,
+    pub is_active: bool,
+    pub is_configured: bool,
+    pub storage: StorageKind,
 }
 
-pub fn provider_key_get(provider: &str, _encryption_password: Option<&str>) -> Result<String, String> {
-    provider_key_get_method1(provider)
+fn credential_names(s: &settings::AppSettings, provider: &str) -> Vec<String> {
+    let mut names = vec![DEFAULT_CREDENTIAL.to_string()];
+    if let Some(extra) = s.provider_credentials.get(provider) {
+        names.extend(extra.iter().filter(|n| n.as_str() != DEFAULT_CREDENTIAL).cloned());
+    }
+    names
 }
 
-pub fn provider_key_clear(provider: &str) -> Result<(), String> {
-    let path = key_path(provider)?;
-    if path.exists() {
-        fs::remove_file(&path)
-            .map_err(|e| format!("Failed to remove key file {}: {e}", path.display()))
-    } else {
+pub fn provider_key_list(provider: &str) -> Result<Vec<CredentialInfo>, String> {
+    let s = settings::load().map_err(|e| e.to_string())?;
+    let active = s
+        .active_credential
+        .get(provider)
+        .cloned()
+        .unwrap_or_else(|| DEFAULT_CREDENTIAL.to_string());
+
+    Ok(credential_names(&s, provider)
+        .into_iter()
+        .map(|name| {
+            let storage = slot_storage(&credential_slot(provider, &name));
+            CredentialInfo {
+                is_active: name == active,
+                is_configured: storage != StorageKind::None,
+                storage,
+                name,
+            }
+        })
+        .collect())
+}
+
+pub fn provider_key_activate(provider: &str, credential: &str) -> Result<(), String> {
+    let name = validate_credential_name(credential)?.to_string();
+    settings::update(|s| {
+        if !credential_names(s, provider).contains(&name) {
+            return Err(anyhow!("Unknown credential \"{name}\" for {provider}"));
+        }
+        if name == DEFAULT_CREDENTIAL {
+            s.active_credential.remove(provider);
+        } else {
+            s.active_credential.insert(provider.to_string(), name);
+        }
         Ok(())
+    })
+    .map(|_| ())
+    .map_err(|e| e.to_string())
+}
+
+// Providers whose keys are moved by `secrets_migrate` and included in exports.
+const KNOWN_PROVIDERS: &[&str] = &[
+    "openai", "anthropic", "groq", "deepseek", "gemini", "openrouter", "pompora", "custom", "git", "github",
+];
+
+/// Every (provider, credential name) pair that may have a stored key.
+fn all_credentials(s: &settings::AppSettings) -> Vec<(String, String)> {
+    let mut providers: Vec<String> = KNOWN_PROVIDERS.iter().map(|p| p.to_string()).collect();
+    for p in s.provider_credentials.keys() {
+        if !providers.contains(p) {
+            providers.push(p.clone());
+        }
+    }
+    let mut out = vec![];
+    for provider in providers {
+        for name in credential_names(s, &provider) {
+            out.push((provider.clone(), name));
+        }
+    }
+    out
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MigrationEntry {
+    pub provider: String,
+    pub slot: String,
+    pub from: StorageKind,
+    pub error: Option<String>,
+}
+
+/// Moves every known provider key into `target` and makes it the configured backend. If any
+/// key fails to move, the backend stays as it was and the report says which ones failed.
+pub fn secrets_migrate(target: SecretBackend, encryption_password: Option<&str>) -> Result<Vec<MigrationEntry>, String> {
+    if target == SecretBackend::Env {
+        return Err("Keys can't be migrated into environment variables".to_string());
+    }
+    let dest = store_for(target, encryption_password);
+
+    let s = settings::load().map_err(|e| e.to_string())?;
+    let mut report = Vec::new();
+    for (provider, name) in &all_credentials(&s) {
+        let slot = credential_slot(provider, name);
+        if dest.contains(&slot).unwrap_or(false) {
+            continue;
+        }
+        for source in lookup_order(encryption_password) {
+            if source.kind() == dest.kind() || source.kind() == StorageKind::Env {
+                continue;
+            }
+            let key = match source.get(&slot) {
+                Ok(Some(k)) => k,
+                Ok(None) => continue,
+                Err(e) => {
+                    report.push(MigrationEntry {
+                        provider: provider.clone(),
+                        slot: slot.clone(),
+                        from: source.kind(),
+                        error: Some(e),
+                    });
+                    break;
+                }
+            };
+            let res = dest.set(&slot, &key).and_then(|_| source.clear(&slot));
+            key_audit::record(KeyAccess::Write, provider, name, "secrets_migrate", res.is_ok());
+            report.push(MigrationEntry {
+                provider: provider.clone(),
+                slot: slot.clone(),
+                from: source.kind(),
+                error: res.err(),
+            });
+            break;
+        }
+    }
+
+    if report.iter().all(|e| e.error.is_none()) {
+        settings::update(|s| {
+            s.secret_backend = target;
+            Ok(())
+        })
+        .map_err(|e| e.to_string())?;
+    }
+
+    Ok(report)
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct BundleEntry {
+    provider: String,
+    credential: String,
+    api_key: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct SecretsBundle {
+    kind: String,
+    #[serde(flatten)]
+    secret: EncryptedSecret,
+}
+
+const BUNDLE_KIND: &str = "pompora-secrets";
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ImportEntry {
+    pub provider: String,
+    pub credential: String,
+    pub error: Option<String>,
+}
+
+fn default_export_path() -> Result<PathBuf, String> {
+    let dir = dirs::download_dir()
+        .or_else(dirs::home_dir)
+        .ok_or_else(|| "Missing downloads directory".to_string())?;
+    let ts = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0);
+    Ok(dir.join(format!("pompora-secrets-{ts}.json")))
+}
+
+/// Writes every stored provider key (environment variables excluded) into one file encrypted
+/// with `password`. Returns the path written.
+pub fn secrets_export(password: &str, dest: Option<&str>, encryption_password: Option<&str>) -> Result<String, String> {
+    if password.is_empty() {
+        return Err("An export password is required".to_string());
+    }
+
+    let s = settings::load().map_err(|e| e.to_string())?;
+    let mut entries: Vec<BundleEntry> = vec![];
+    for (provider, name) in all_credentials(&s) {
+        let slot = credential_slot(&provider, &name);
+        for store in lookup_order(encryption_password) {
+            if store.kind() == StorageKind::Env {
+                continue;
+            }
+            let found = match store.get(&slot) {
+                Ok(v) => v,
+                Err(_) if store.kind() == StorageKind::Keyring => None,
+                Err(e) => return Err(e),
+            };
+            if let Some(api_key) = found {
+                key_audit::record(KeyAccess::Read, &provider, &name, "secrets_export", true);
+                entries.push(BundleEntry {
+                    provider: provider.clone(),
+                    credential: name.clone(),
+                    api_key,
+                });
+                break;
+            }
+        }
+    }
+    if entries.is_empty() {
+        return Err("There are no stored keys to export".to_string());
+    }
+
+    let plaintext = serde_json::to_string(&entries).map_err(|e| e.to_string())?;
+    let bundle = SecretsBundle {
+        kind: BUNDLE_KIND.to_string(),
+        secret: encrypt_secret(password, &plaintext)?,
+    };
+    let json = serde_json::to_string_pretty(&bundle).map_err(|e| e.to_string())?;
+
+    let path = match dest.map(|d| d.trim()).filter(|d| !d.is_empty()) {
+        Some(d) => PathBuf::from(d),
+        None => default_export_path()?,
+    };
+    // Not `write_secret_file`: the destination is a user folder whose permissions we leave alone.
+    private_fs::write_private(&path, json.as_bytes())
+        .map_err(|e| format!("Failed to write secrets bundle {}: {e}", path.display()))?;
+    Ok(path.to_string_lossy().to_string())
+}
+
+/// Restores keys from a bundle written by `secrets_export` into the configured backend.
+pub fn secrets_import(path: &str, password: &str, encryption_password: Option<&str>) -> Result<Vec<ImportEntry>, String> {
+    let path = PathBuf::from(path.trim());
+    let raw = fs::read_to_string(&path)
+        .map_err(|e| format!("Failed to read secrets bundle {}: {e}", path.display()))?;
+    let bundle: SecretsBundle = serde_json::from_str(&raw)
+        .map_err(|e| format!("Not a Pompora secrets bundle ({}): {e}", path.display()))?;
+    if bundle.kind != BUNDLE_KIND {
+        return Err(format!("Not a Pompora secrets bundle: {}", path.display()));
+    }
+
+    let plaintext = decrypt_secret(password, &bundle.secret)?;
+    let entries: Vec<BundleEntry> = serde_json::from_str(&plaintext).map_err(|e| e.to_string())?;
+
+    Ok(entries
+        .into_iter()
+        .map(|e| {
+            let res = provider_key_set(&e.provider, &e.api_key, encryption_password, Some(&e.credential), "secrets_import");
+            ImportEntry {
+                provider: e.provider,
+                credential: e.credential,
+                error: res.err(),
+            }
+        })
+        .collect())
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LockStatus {
+    /// Whether any key is (or will be) stored encrypted, i.e. whether locking means anything.
+    pub encrypted: bool,
+    pub unlocked: bool,
+    pub auto_lock_minutes: u32,
+    /// Seconds of inactivity left before the store locks itself; `None` when locked or when
+    /// auto-lock is disabled.
+    pub locks_in_secs: Option<u64>,
+}
+
+fn encrypted_key_files() -> Result<Vec<PathBuf>, String> {
+    let path = key_path("x")?;
+    let dir = path.parent().ok_or_else(|| "Missing secrets directory".to_string())?;
+    let Ok(entries) = fs::read_dir(dir) else {
+        return Ok(vec![]);
+    };
+    Ok(entries
+        .flatten()
+        .map(|e| e.path())
+        .filter(|p| p.extension().is_some_and(|ext| ext == "enc"))
+        .collect())
+}
+
+pub fn secrets_lock_status() -> Result<LockStatus, String> {
+    let minutes = auto_lock_minutes();
+    let guard = session();
+    let encrypted = configured_backend() == SecretBackend::Encryptedfile || !encrypted_key_files()?.is_empty();
+    let locks_in_secs = match (guard.as_ref(), idle_timeout(minutes)) {
+        (Some(sess), Some(timeout)) => Some(timeout.saturating_sub(sess.last_used.elapsed()).as_secs()),
+        _ => None,
+    };
+    Ok(LockStatus {
+        encrypted,
+        unlocked: guard.is_some(),
+        auto_lock_minutes: minutes,
+        locks_in_secs,
+    })
+}
+
+/// Unlocks encrypted keys for this session by deriving the key of every encrypted key file up
+/// front, so the password itself needn't be kept. The password is checked against an existing
+/// encrypted key, if there is one, so a typo doesn't silently produce undecryptable keys later.
+pub fn secrets_unlock(password: &str) -> Result<LockStatus, String> {
+    if password.is_empty() {
+        return Err("A password is required".to_string());
     }
+
+    let mut keys = HashMap::new();
+    for path in encrypted_key_files()? {
+        let secret = read_encrypted(&path)?;
+        let salt = secret_salt(&secret)?;
+        if keys.contains_key(&salt) {
+            continue;
+        }
+        let key = derive_key(password, &salt)?;
+        if keys.is_empty() {
+            decrypt_with_key(&key, &secret)?;
+        }
+        keys.insert(salt, key);
+    }
+    let mut write_salt = vec![0u8; 16];
+    rand::thread_rng().fill_bytes(&mut write_salt);
+    keys.insert(write_salt.clone(), derive_key(password, &write_salt)?);
+
+    let now = Instant::now();
+    *session() = Some(UnlockedSession {
+        keys,
+        write_salt,
+        unlocked_at: now,
+        last_used: now,
+    });
+    spawn_auto_lock(now);
+    secrets_lock_status()
+}
+
+pub fn secrets_lock() -> Result<LockStatus, String> {
+    *session() = None;
+    secrets_lock_status()
+}
+
+/// Credential to use for `provider` in the open workspace, when the workspace pins one.
+pub fn workspace_credential(s: &settings::AppSettings, provider: &str) -> Option<String> {
+    let o = s.workspace_key_override()?;
+    if o.provider.as_deref().is_some_and(|p| p != provider) {
+        return None;
+    }
+    o.credential.clone()
+}
+
+fn override_root(s: &settings::AppSettings, root: Option<&str>) -> Result<String, String> {
+    root.or(s.workspace_root.as_deref())
+        .map(|r| r.trim())
+        .filter(|r| !r.is_empty())
+        .map(|r| r.to_string())
+        .ok_or_else(|| "No workspace is open".to_string())
+}
+
+pub fn workspace_key_override_get(root: Option<&str>) -> Result<Option<WorkspaceKeyOverride>, String> {
+    let s = settings::load().map_err(|e| e.to_string())?;
+    let root = override_root(&s, root)?;
+    Ok(s.workspace_key_overrides.get(&root).cloned())
+}
+
+/// Pins `root` (default: the open workspace) to a provider and/or credential. Passing neither
+/// removes the pin.
+pub fn workspace_key_override_set(
+    root: Option<&str>,
+    provider: Option<&str>,
+    credential: Option<&str>,
+) -> Result<Option<WorkspaceKeyOverride>, String> {
+    let provider = provider.map(|p| p.trim()).filter(|p| !p.is_empty()).map(|p| p.to_string());
+    let credential = match credential.map(|c| c.trim()).filter(|c| !c.is_empty()) {
+        Some(c) => Some(validate_credential_name(c)?.to_string()),
+        None => None,
+    };
+    let next = if provider.is_none() && credential.is_none() {
+        None
+    } else {
+        Some(WorkspaceKeyOverride { provider, credential })
+    };
+
+    settings::update(|s| {
+        let root = override_root(s, root).map_err(|e| anyhow!(e))?;
+        match &next {
+            Some(o) => s.workspace_key_overrides.insert(root, o.clone()),
+            None => s.workspace_key_overrides.remove(&root),
+        };
+        Ok(())
+    })
+    .map_err(|e| e.to_string())?;
+    Ok(next)
+}
+
+/// Credential files (keys, `auth.json`, `settings.json`) whose permissions allow access by
+/// other users.
+pub fn secrets_audit() -> Vec<private_fs::PermissionIssue> {
+    private_fs::audit()
+}
+
+/// Recent provider key reads/writes/clears, newest first.
+pub fn secrets_audit_log(limit: usize) -> Result<Vec<key_audit::KeyAuditEntry>, String> {
+    key_audit::read_log(limit)
 }
@@ -1,10 +1,17 @@
 use anyhow::{anyhow, Context, Result};
+use notify::{RecursiveMode, Watcher};
+use once_cell::sync::{Lazy, OnceCell};
 use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
 use std::fs;
 use std::fs::OpenOptions;
 use std::path::PathBuf;
+use std::sync::Mutex;
 use std::thread;
 use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use tauri::Emitter;
+
+use super::private_fs;
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct AppSettings {
@@ -17,6 +24,485 @@ pub struct AppSettings {
     pub workspace_root: Option<String>,
     #[serde(default)]
     pub recent_workspaces: Vec<String>,
+    /// Extra folders opened alongside `workspace_root` in the same workspace.
+    #[serde(default)]
+    pub workspace_extra_roots: Vec<String>,
+    /// Trust decision per workspace folder; folders not listed haven't been decided yet and
+    /// are treated as untrusted.
+    #[serde(default)]
+    pub workspace_trust: HashMap<String, bool>,
+    /// Recently opened and pinned files per workspace, keyed by the primary root folder.
+    #[serde(default)]
+    pub workspace_files: HashMap<String, WorkspaceFiles>,
+    /// Whether `workspace_delete` moves files to the OS trash unless told to delete permanently.
+    #[serde(default = "default_delete_to_trash")]
+    pub delete_to_trash: bool,
+    #[serde(default)]
+    pub file_backups: BackupSettings,
+    /// List and search files that `.gitignore`/`.ignore` rules exclude.
+    #[serde(default)]
+    pub include_ignored_files: bool,
+    /// Show dotfiles and OS bookkeeping files (`.DS_Store`, `Thumbs.db`) in the file tree
+    /// and file list.
+    #[serde(default = "default_show_hidden_files")]
+    pub show_hidden_files: bool,
+    /// Keep an on-disk trigram index of each root so searches skip files that can't match.
+    #[serde(default)]
+    pub search_index: bool,
+    /// Normalization applied when files are saved; `.editorconfig` and the per-workspace
+    /// policies below take precedence field by field.
+    #[serde(default)]
+    pub write_policy: WritePolicy,
+    /// Write policies keyed by workspace root, overriding `.editorconfig`.
+    #[serde(default)]
+    pub workspace_write_policies: HashMap<String, WritePolicy>,
+    /// Let file operations follow symlinks out of the workspace. Only honoured while the
+    /// workspace is trusted.
+    #[serde(default)]
+    pub allow_symlink_escape: bool,
+    #[serde(default)]
+    pub secret_backend: SecretBackend,
+    /// Named credentials per provider, in addition to the implicit "default" one.
+    #[serde(default)]
+    pub provider_credentials: HashMap<String, Vec<String>>,
+    /// Credential name used for each provider when none is given explicitly.
+    #[serde(default)]
+    pub active_credential: HashMap<String, String>,
+    /// Minutes of inactivity after which an unlocked encrypted key store locks again (0 = never).
+    #[serde(default = "default_secrets_auto_lock_minutes")]
+    pub secrets_auto_lock_minutes: u32,
+    /// Provider/credential pins keyed by workspace root.
+    #[serde(default)]
+    pub workspace_key_overrides: HashMap<String, WorkspaceKeyOverride>,
+    #[serde(default)]
+    pub proxy: ProxySettings,
+    /// Self-hosted Pompora backend, e.g. `https://pompora.corp.example`; unset for pompora.dev.
+    /// Sign-in and credits go to it, AI requests to `<url>/v1`.
+    #[serde(default)]
+    pub backend_base_url: Option<String>,
+    #[serde(default)]
+    pub editor: EditorSettings,
+    /// User overrides of the default keyboard shortcuts, applied in order.
+    #[serde(default)]
+    pub keybindings: Vec<KeybindingOverride>,
+    #[serde(default)]
+    pub terminal_profiles: Vec<TerminalProfile>,
+    /// Profile used when a terminal is opened without naming one; `None` uses the login shell.
+    #[serde(default)]
+    pub default_terminal_profile: Option<String>,
+    /// Load Pompora's shell integration into bash, zsh, fish and PowerShell so commands can be
+    /// told apart in the terminal. Profiles with their own `args` are started as given.
+    #[serde(default = "default_terminal_shell_integration")]
+    pub terminal_shell_integration: bool,
+    /// After a restart, open the terminal again with the profile and folder it had.
+    #[serde(default = "default_terminal_restore_sessions")]
+    pub terminal_restore_sessions: bool,
+    /// Windows only: run tasks inside WSL, in the distribution the workspace lives in or else
+    /// the default one.
+    #[serde(default)]
+    pub tasks_use_wsl: bool,
+    /// Env file (workspace path) whose variables new terminals and tasks start with; skipped
+    /// when the open workspace doesn't have it.
+    #[serde(default)]
+    pub env_file: Option<String>,
+    /// When commands the AI asks to run go through the restricted runner instead of the
+    /// terminal, and its limits.
+    #[serde(default)]
+    pub ai_command_sandbox: SandboxSettings,
+    /// Least severe messages written to the app log.
+    #[serde(default)]
+    pub log_level: LogLevel,
+    #[serde(default)]
+    pub telemetry: TelemetrySettings,
+    /// Language servers to add, or to replace the built-in ones with the same `id`.
+    #[serde(default)]
+    pub language_servers: Vec<LanguageServerConfig>,
+    #[serde(default)]
+    pub lint: LintSettings,
+    #[serde(default)]
+    pub snippets: Vec<Snippet>,
+    #[serde(default)]
+    pub updates: UpdateSettings,
+    #[serde(default)]
+    pub notifications: NotificationSettings,
+    /// Comment tags the TODO panel lists, matched case-sensitively as whole words.
+    #[serde(default = "default_todo_tags")]
+    pub todo_tags: Vec<String>,
+    /// Saved requests of the HTTP client, in named collections.
+    #[serde(default)]
+    pub http_collections: Vec<HttpCollection>,
+    /// Globs, matched like `.gitignore` lines in each workspace root, of files that writes,
+    /// deletes, moves and AI edits leave alone unless explicitly overridden.
+    #[serde(default = "default_protected_paths")]
+    pub protected_paths: Vec<String>,
+    /// Client id of the GitHub OAuth app used to sign in with GitHub's device flow. Without one,
+    /// GitHub is reached with a personal access token saved under the `github` provider.
+    #[serde(default)]
+    pub github_oauth_client_id: Option<String>,
+    /// Refuse commits whose message breaks the Conventional Commits rules (and the repository's
+    /// commitlint config) instead of only reporting it.
+    #[serde(default)]
+    pub enforce_commit_lint: bool,
+    /// Hunspell dictionary the spell checker uses, e.g. `en_US` or `de_DE`.
+    #[serde(default = "default_spellcheck_language")]
+    pub spellcheck_language: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TerminalProfile {
+    pub name: String,
+    /// Shell executable, e.g. `/bin/zsh` or `pwsh.exe`.
+    pub shell: String,
+    #[serde(default)]
+    pub args: Vec<String>,
+    #[serde(default)]
+    pub env: HashMap<String, String>,
+    /// Starting directory when the caller doesn't pass one (normally the workspace root).
+    #[serde(default)]
+    pub cwd: Option<String>,
+}
+
+/// clippy, eslint and ruff, run over the workspace's projects.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct LintSettings {
+    /// Lint a file's project each time the file is saved.
+    pub on_save: bool,
+    /// Linters not to run, by name, e.g. `clippy`.
+    pub disabled: Vec<String>,
+}
+
+/// A language server started over stdio for the editor's languages.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct LanguageServerConfig {
+    /// E.g. `rust-analyzer`; the built-ins are `rust-analyzer`, `typescript-language-server`
+    /// and `pyright`.
+    pub id: String,
+    /// Executable, looked up on the `PATH` unless it is a path.
+    pub command: String,
+    #[serde(default)]
+    pub args: Vec<String>,
+    /// Editor language ids it serves, e.g. `typescript`.
+    #[serde(default)]
+    pub languages: Vec<String>,
+    #[serde(default)]
+    pub disabled: bool,
+}
+
+/// A user snippet, offered in the editor's completions when its prefix is typed.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Snippet {
+    /// Identifies the snippet; saving one with the same name replaces it.
+    pub name: String,
+    pub prefix: String,
+    /// Editor snippet syntax: `$1` or `${1:default}` for tab stops, `${1|a,b|}` for a choice
+    /// and `$0` for where the cursor ends up.
+    pub body: String,
+    #[serde(default)]
+    pub description: Option<String>,
+    /// Editor language ids it is offered in; all languages when empty.
+    #[serde(default)]
+    pub languages: Vec<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct HttpHeader {
+    pub name: String,
+    pub value: String,
+}
+
+/// Credentials sent with an HTTP client request as an `Authorization` header. In saved
+/// requests the password or token lives in the secret store, not in settings.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "lowercase")]
+pub enum HttpAuth {
+    Basic {
+        username: String,
+        #[serde(default)]
+        password: Option<String>,
+    },
+    Bearer {
+        token: String,
+    },
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SavedHttpRequest {
+    /// Identifies the request within its collection.
+    pub name: String,
+    pub method: String,
+    pub url: String,
+    #[serde(default)]
+    pub headers: Vec<HttpHeader>,
+    #[serde(default)]
+    pub body: Option<String>,
+    #[serde(default)]
+    pub auth: Option<HttpAuth>,
+    /// Secret store credential holding `auth`'s password or token.
+    #[serde(default)]
+    pub auth_secret: Option<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct HttpCollection {
+    pub name: String,
+    #[serde(default)]
+    pub requests: Vec<SavedHttpRequest>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct EditorSettings {
+    /// CSS font-family list; `None` keeps the editor's built-in monospace stack.
+    pub font_family: Option<String>,
+    pub font_size: u32,
+    pub tab_size: u32,
+    pub insert_spaces: bool,
+    pub word_wrap: WordWrap,
+    pub minimap: bool,
+}
+
+impl Default for EditorSettings {
+    fn default() -> Self {
+        Self {
+            font_family: None,
+            font_size: 13,
+            tab_size: 4,
+            insert_spaces: true,
+            word_wrap: WordWrap::On,
+            minimap: true,
+        }
+    }
+}
+
+/// How text is normalized on save. Unset fields leave that aspect of the text alone.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct WritePolicy {
+    pub line_endings: Option<LineEnding>,
+    pub trim_trailing_whitespace: Option<bool>,
+    pub insert_final_newline: Option<bool>,
+}
+
+impl WritePolicy {
+    /// Fills the fields this policy leaves unset from `fallback`.
+    pub fn or(self, fallback: &WritePolicy) -> WritePolicy {
+        WritePolicy {
+            line_endings: self.line_endings.or(fallback.line_endings),
+            trim_trailing_whitespace: self.trim_trailing_whitespace.or(fallback.trim_trailing_whitespace),
+            insert_final_newline: self.insert_final_newline.or(fallback.insert_final_newline),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum LineEnding {
+    Lf,
+    Crlf,
+}
+
+/// Copies of a file's previous contents kept whenever the app overwrites it.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct BackupSettings {
+    pub mode: BackupMode,
+    /// Versions kept per file in `history` mode; older ones are pruned on each save.
+    pub history_limit: u32,
+}
+
+impl Default for BackupSettings {
+    fn default() -> Self {
+        Self {
+            mode: BackupMode::Off,
+            history_limit: 20,
+        }
+    }
+}
+
+/// `bak` keeps one `name.ext.bak` next to the file; `history` keeps several in the app's
+/// config folder, out of the workspace.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum BackupMode {
+    #[default]
+    Off,
+    Bak,
+    History,
+}
+
+/// Limits for commands run in the sandbox, which also get an empty temporary home folder and
+/// few environment variables.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct SandboxSettings {
+    pub mode: SandboxMode,
+    /// Seconds before the command is killed; 0 for no limit.
+    pub timeout_secs: u32,
+    /// Memory the command may allocate, per process on Unix and for all of them together on
+    /// Windows; 0 for no limit.
+    pub memory_mb: u32,
+    /// Let commands reach the network. Network isolation isn't available on Windows.
+    pub allow_network: bool,
+}
+
+impl Default for SandboxSettings {
+    fn default() -> Self {
+        Self {
+            mode: SandboxMode::Off,
+            timeout_secs: 120,
+            memory_mb: 4096,
+            allow_network: false,
+        }
+    }
+}
+
+/// `auto` sandboxes the commands that run without being confirmed first; `always` sandboxes
+/// every command the AI asks to run.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum SandboxMode {
+    #[default]
+    Off,
+    Auto,
+    Always,
+}
+
+/// Anonymous usage statistics: command counts, error kinds and timings. Off unless turned on.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct TelemetrySettings {
+    pub enabled: bool,
+    /// Where batches are posted; the Pompora backend's `/api/telemetry` when unset.
+    pub endpoint: Option<String>,
+}
+
+/// OS notifications for long-running jobs that finish while Pompora is in the background.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct NotificationSettings {
+    pub enabled: bool,
+    /// Categories not to notify about: `ai`, `git`, `indexing` or `tasks`.
+    pub disabled: Vec<String>,
+}
+
+impl Default for NotificationSettings {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            disabled: Vec::new(),
+        }
+    }
+}
+
+/// Checking for new Pompora releases.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct UpdateSettings {
+    /// Check in the background every few hours and emit `update:available`.
+    pub check_automatically: bool,
+    /// A GitHub releases API URL (`https://api.github.com/repos/<owner>/<repo>/releases`) or a
+    /// Pompora update feed; Pompora's GitHub releases when unset.
+    pub feed: Option<String>,
+    /// Offer pre-releases too.
+    pub prereleases: bool,
+}
+
+/// Log levels from most to least severe; a level includes the ones before it.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum LogLevel {
+    Error,
+    Warn,
+    #[default]
+    Info,
+    Debug,
+    Trace,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum WordWrap {
+    Off,
+    On,
+    Bounded,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct KeybindingOverride {
+    /// Command id, e.g. `workbench.action.quickOpen`.
+    pub command: String,
+    /// Key chord such as `ctrl+shift+p`; `None` removes the default binding.
+    #[serde(default)]
+    pub key: Option<String>,
+    /// Optional context expression limiting where the binding applies.
+    #[serde(default)]
+    pub when: Option<String>,
+}
+
+/// Outbound proxy applied to every HTTP client (provider APIs, auth, credits).
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ProxySettings {
+    /// `http://`, `https://` or `socks5://` proxy URL, optionally with `user:pass@`.
+    #[serde(default)]
+    pub url: Option<String>,
+    /// Hosts/domains/CIDRs that bypass the proxy, e.g. `localhost`, `.corp.example`, `10.0.0.0/8`.
+    #[serde(default)]
+    pub no_proxy: Vec<String>,
+    /// PEM file with an extra root certificate, for TLS-intercepting corporate proxies.
+    #[serde(default)]
+    pub ca_cert_path: Option<String>,
+}
+
+fn default_secrets_auto_lock_minutes() -> u32 {
+    15
+}
+
+fn default_delete_to_trash() -> bool {
+    true
+}
+
+fn default_show_hidden_files() -> bool {
+    true
+}
+
+fn default_terminal_shell_integration() -> bool {
+    true
+}
+
+fn default_terminal_restore_sessions() -> bool {
+    true
+}
+
+fn default_todo_tags() -> Vec<String> {
+    ["TODO", "FIXME", "HACK", "XXX"].map(String::from).to_vec()
+}
+
+fn default_protected_paths() -> Vec<String> {
+    ["*.lock", ".git/**"].map(String::from).to_vec()
+}
+
+fn default_spellcheck_language() -> String {
+    "en_US".to_string()
+}
+
+/// Pins a workspace to a provider and/or named credential, e.g. a client project that must
+/// always bill the client's own key.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct WorkspaceKeyOverride {
+    #[serde(default)]
+    pub provider: Option<String>,
+    #[serde(default)]
+    pub credential: Option<String>,
+}
+
+/// Workspace paths, most recent first for `recent`, in pin order for `pinned`.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct WorkspaceFiles {
+    #[serde(default)]
+    pub recent: Vec<String>,
+    #[serde(default)]
+    pub pinned: Vec<String>,
 }
 
 #[derive(Debug, Clone, Copy, Serialize, Deserialize)]
@@ -26,6 +512,17 @@ pub enum Theme {
     Light,
 }
 
+/// Where provider keys are stored. Passing an encryption password always uses the encrypted file.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum SecretBackend {
+    #[default]
+    File,
+    Keyring,
+    Encryptedfile,
+    Env,
+}
+
 impl Default for AppSettings {
     fn default() -> Self {
         Self {
@@ -35,10 +532,87 @@ impl Default for AppSettings {
             pompora_thinking: None,
             workspace_root: None,
             recent_workspaces: Vec::new(),
+            workspace_extra_roots: Vec::new(),
+            workspace_trust: HashMap::new(),
+            workspace_files: HashMap::new(),
+            delete_to_trash: default_delete_to_trash(),
+            file_backups: BackupSettings::default(),
+            include_ignored_files: false,
+            show_hidden_files: default_show_hidden_files(),
+            search_index: false,
+            write_policy: WritePolicy::default(),
+            workspace_write_policies: HashMap::new(),
+            allow_symlink_escape: false,
+            secret_backend: SecretBackend::default(),
+            provider_credentials: HashMap::new(),
+            active_credential: HashMap::new(),
+            secrets_auto_lock_minutes: default_secrets_auto_lock_minutes(),
+            workspace_key_overrides: HashMap::new(),
+            proxy: ProxySettings::default(),
+            backend_base_url: None,
+            editor: EditorSettings::default(),
+            keybindings: Vec::new(),
+            terminal_profiles: Vec::new(),
+            default_terminal_profile: None,
+            terminal_shell_integration: default_terminal_shell_integration(),
+            terminal_restore_sessions: default_terminal_restore_sessions(),
+            tasks_use_wsl: false,
+            env_file: None,
+            ai_command_sandbox: SandboxSettings::default(),
+            log_level: LogLevel::default(),
+            telemetry: TelemetrySettings::default(),
+            language_servers: Vec::new(),
+            lint: LintSettings::default(),
+            snippets: Vec::new(),
+            updates: UpdateSettings::default(),
+            notifications: NotificationSettings::default(),
+            todo_tags: default_todo_tags(),
+            http_collections: Vec::new(),
+            protected_paths: default_protected_paths(),
+            github_oauth_client_id: None,
+            enforce_commit_lint: false,
+            spellcheck_language: default_spellcheck_language(),
         }
     }
 }
 
+impl AppSettings {
+    /// The override for the currently open workspace, if it has one.
+    pub fn workspace_key_override(&self) -> Option<&WorkspaceKeyOverride> {
+        let root = self.workspace_root.as_deref()?.trim();
+        self.workspace_key_overrides.get(root)
+    }
+
+    /// Checks what the field types can't express, normalizing values in place. Settings from
+    /// the frontend go through this before they're stored.
+    pub fn validate(&mut self) -> Result<()> {
+        self.backend_base_url = self
+            .backend_base_url
+            .as_deref()
+            .filter(|v| !v.trim().is_empty())
+            .map(normalize_backend_url)
+            .transpose()?;
+        Ok(())
+    }
+}
+
+/// `url` checked as a Pompora backend and without its trailing slash. It must be `https://`,
+/// or `http://` to this machine, and have no credentials, query or fragment.
+pub fn normalize_backend_url(url: &str) -> Result<String> {
+    let url = url.trim().trim_end_matches('/');
+    let parsed = super::http::parse_secure_url(url, "backend URL")?;
+    if parsed.host_str().is_none_or(str::is_empty) {
+        return Err(anyhow!("backend URL has no host"));
+    }
+    if !parsed.username().is_empty() || parsed.password().is_some() {
+        return Err(anyhow!("backend URL must not contain credentials"));
+    }
+    if parsed.query().is_some() || parsed.fragment().is_some() {
+        return Err(anyhow!("backend URL must not have a query or fragment"));
+    }
+    Ok(url.to_string())
+}
+
 pub fn load() -> Result<AppSettings> {
     let path = settings_path()?;
     if !path.exists() {
@@ -53,12 +627,16 @@ pub fn load() -> Result<AppSettings> {
                 .map(|d| d.as_millis())
                 .unwrap_or(0);
 
+            let file_name = path
+                .file_name()
+                .map(|n| n.to_string_lossy().to_string())
+                .unwrap_or_else(|| "settings.json".to_string());
             let mut backup = path.clone();
             for i in 0u32..100 {
                 let name = if i == 0 {
-                    format!("settings.json.corrupt-{ts}")
+                    format!("{file_name}.corrupt-{ts}")
                 } else {
-                    format!("settings.json.corrupt-{ts}-{i}")
+                    format!("{file_name}.corrupt-{ts}-{i}")
                 };
                 let mut candidate = path.clone();
                 candidate.set_file_name(name);
@@ -72,8 +650,7 @@ pub fn load() -> Result<AppSettings> {
                 let _ = fs::remove_file(&path);
             }
 
-            #[cfg(debug_assertions)]
-            eprintln!(
+            tracing::warn!(
                 "parse settings failed ({}): {} (backed up to {})",
                 path.display(),
                 e,
@@ -95,7 +672,7 @@ pub fn store(next: &AppSettings) -> Result<()> {
 
     let tmp = path.with_extension("json.tmp");
     let s = serde_json::to_string_pretty(next).context("serialize settings")?;
-    fs::write(&tmp, s.as_bytes()).with_context(|| format!("write settings tmp: {}", tmp.display()))?;
+    private_fs::write_private(&tmp, s.as_bytes()).with_context(|| format!("write settings tmp: {}", tmp.display()))?;
 
     // Best-effort durability: on some systems/filesystems, fsync can fail even though the
     // write succeeded. Settings should still be saved in that case.
@@ -122,7 +699,7 @@ pub fn store(next: &AppSettings) -> Result<()> {
     let write_final_with_retry = |to: &PathBuf, bytes: &[u8]| -> Result<()> {
         let mut last: Option<std::io::Error> = None;
         for i in 0..6u32 {
-            match fs::write(to, bytes) {
+            match private_fs::write_private(to, bytes) {
                 Ok(()) => return Ok(()),
                 Err(e) => {
                     last = Some(e);
@@ -140,6 +717,7 @@ pub fn store(next: &AppSettings) -> Result<()> {
         if let Err(e2) = rename_with_retry(&tmp, &path) {
             // Fallback 2: copy tmp to final.
             let copy_res = fs::copy(&tmp, &path)
+                .and_then(|_| private_fs::restrict_file(&path))
                 .with_context(|| format!("copy settings tmp to final after rename failure: {}", path.display()));
             if copy_res.is_err() {
                 // Fallback 3: on Windows the destination can be locked, and copy won't overwrite.
@@ -160,10 +738,311 @@ pub fn store(next: &AppSettings) -> Result<()> {
         let _ = file.sync_all();
     }
 
+    if let Ok(v) = serde_json::to_value(next) {
+        publish_change(v, false);
+    }
     Ok(())
 }
 
-fn settings_path() -> Result<PathBuf> {
+// Serializes read-modify-write cycles within this process; the lock file extends that to
+// other Pompora instances.
+static UPDATE_LOCK: Lazy<Mutex<()>> = Lazy::new(|| Mutex::new(()));
+
+/// Loads, modifies and stores the settings while holding the settings lock, so concurrent
+/// updates of different fields don't overwrite each other.
+pub fn update(f: impl FnOnce(&mut AppSettings) -> Result<()>) -> Result<AppSettings> {
+    let _guard = UPDATE_LOCK.lock().unwrap_or_else(|e| e.into_inner());
+
+    let lock_path = settings_path()?.with_extension("lock");
+    if let Some(parent) = lock_path.parent() {
+        fs::create_dir_all(parent).with_context(|| format!("create settings dir: {}", parent.display()))?;
+    }
+    let lock_file = OpenOptions::new()
+        .create(true)
+        .truncate(false)
+        .write(true)
+        .open(&lock_path)
+        .with_context(|| format!("open settings lock: {}", lock_path.display()))?;
+    lock_file
+        .lock()
+        .with_context(|| format!("lock settings: {}", lock_path.display()))?;
+
+    let res = load().and_then(|mut s| {
+        f(&mut s)?;
+        store(&s)?;
+        Ok(s)
+    });
+    let _ = lock_file.unlock();
+    res
+}
+
+// RFC 7386 JSON merge patch: objects merge recursively, `null` removes a key, anything else
+// replaces the target value. `defaults` is the same place in the default settings: a key patched
+// to `null` is reset to its value there instead of removed, so fields without a serde default
+// still deserialize.
+fn merge_patch(target: &mut serde_json::Value, patch: &serde_json::Value, defaults: Option<&serde_json::Value>) {
+    let Some(patch_obj) = patch.as_object() else {
+        *target = patch.clone();
+        return;
+    };
+    if !target.is_object() {
+        *target = serde_json::Value::Object(serde_json::Map::new());
+    }
+    if let Some(target_obj) = target.as_object_mut() {
+        for (k, v) in patch_obj {
+            let default = defaults.and_then(|d| d.get(k));
+            if v.is_null() {
+                match default {
+                    Some(d) => {
+                        target_obj.insert(k.clone(), d.clone());
+                    }
+                    None => {
+                        target_obj.remove(k);
+                    }
+                }
+            } else {
+                merge_patch(target_obj.entry(k.clone()).or_insert(serde_json::Value::Null), v, default);
+            }
+        }
+    }
+}
+
+/// Applies a partial settings object (JSON merge patch) and returns the result; `null` resets a
+/// field to its default. Unknown top-level keys are rejected rather than silently dropped.
+pub fn patch(partial: &serde_json::Value) -> Result<AppSettings> {
+    let fields = partial
+        .as_object()
+        .ok_or_else(|| anyhow!("settings patch must be a JSON object"))?;
+
+    update(|s| {
+        let mut v = serde_json::to_value(&*s).context("serialize settings")?;
+        if let Some(unknown) = fields.keys().find(|k| v.get(k.as_str()).is_none()) {
+            return Err(anyhow!("unknown settings key: {unknown}"));
+        }
+        let defaults = serde_json::to_value(AppSettings::default()).context("serialize default settings")?;
+        merge_patch(&mut v, partial, Some(&defaults));
+        *s = serde_json::from_value(v).context("invalid settings patch")?;
+        s.validate()
+    })
+}
+
+pub const SETTINGS_CHANGED_EVENT: &str = "settings:changed";
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SettingsChanged {
+    /// Top-level `AppSettings` fields whose value differs from the previous state.
+    pub keys: Vec<String>,
+    /// True when the file was modified outside this process (another instance, a text editor).
+    pub external: bool,
+}
+
+/// The fields a `settings:changed` payload names, for modules that follow some of them.
+pub fn changed_keys_in(payload: &str) -> Vec<String> {
+    serde_json::from_str::<SettingsChanged>(payload)
+        .map(|c| c.keys)
+        .unwrap_or_default()
+}
+
+static APP: OnceCell<tauri::AppHandle> = OnceCell::new();
+static WATCHER: OnceCell<Mutex<notify::RecommendedWatcher>> = OnceCell::new();
+// Last settings we wrote or observed on disk. Our own writes update this before the watcher
+// fires, so the watcher only reports changes that came from elsewhere.
+static LAST_KNOWN: Lazy<Mutex<Option<serde_json::Value>>> = Lazy::new(|| Mutex::new(None));
+
+fn changed_keys(before: Option<&serde_json::Value>, after: &serde_json::Value) -> Vec<String> {
+    let Some(after) = after.as_object() else {
+        return vec![];
+    };
+    let before = before.and_then(|b| b.as_object());
+    let mut keys: Vec<String> = after
+        .iter()
+        .filter(|(k, v)| before.and_then(|b| b.get(*k)) != Some(*v))
+        .map(|(k, _)| k.clone())
+        .collect();
+    keys.sort();
+    keys
+}
+
+fn publish_change(next: serde_json::Value, external: bool) {
+    let keys = {
+        let mut last = LAST_KNOWN.lock().unwrap_or_else(|e| e.into_inner());
+        let keys = changed_keys(last.as_ref(), &next);
+        *last = Some(next);
+        keys
+    };
+    if keys.is_empty() {
+        return;
+    }
+    if let Some(app) = APP.get() {
+        let _ = app.emit(SETTINGS_CHANGED_EVENT, SettingsChanged { keys, external });
+    }
+}
+
+fn reload_from_disk() {
+    let Ok(path) = settings_path() else {
+        return;
+    };
+    // A half-written or corrupt file is left for `load` to deal with.
+    let Ok(raw) = fs::read_to_string(&path) else {
+        return;
+    };
+    let Ok(parsed) = serde_json::from_str::<AppSettings>(&raw) else {
+        return;
+    };
+    if let Ok(v) = serde_json::to_value(&parsed) {
+        publish_change(v, true);
+    }
+}
+
+/// Starts emitting `settings:changed` for every store and for external edits of the settings
+/// file. Called once from app setup.
+pub fn watch(app: tauri::AppHandle) -> Result<()> {
+    let _ = APP.set(app);
+    if let Ok(v) = load().and_then(|s| serde_json::to_value(s).context("serialize settings")) {
+        *LAST_KNOWN.lock().unwrap_or_else(|e| e.into_inner()) = Some(v);
+    }
+
+    let dir = config_dir()?;
+    fs::create_dir_all(&dir).with_context(|| format!("create settings dir: {}", dir.display()))?;
+
+    // Watch the directory, not the file: `store` replaces the file via rename.
+    let mut watcher = notify::recommended_watcher(move |res: notify::Result<notify::Event>| {
+        if let Ok(ev) = res {
+            // Resolved per event: the active profile's file can change while we run.
+            let current = settings_path().ok();
+            let current = current.as_ref().and_then(|c| c.file_name());
+            if ev.paths.iter().any(|p| p.file_name().is_some() && p.file_name() == current) {
+                reload_from_disk();
+            }
+        }
+    })
+    .context("create settings watcher")?;
+    watcher
+        .watch(&dir, RecursiveMode::NonRecursive)
+        .with_context(|| format!("watch settings dir: {}", dir.display()))?;
+    let _ = WATCHER.set(Mutex::new(watcher));
+    Ok(())
+}
+
+fn config_dir() -> Result<PathBuf> {
     let base = dirs::config_dir().or_else(|| dirs::home_dir().map(|h| h.join(".config"))).context("missing config dir")?;
-    Ok(base.join("Pompora").join("settings.json"))
+    Ok(base.join("Pompora"))
+}
+
+fn settings_path() -> Result<PathBuf> {
+    profile_settings_path(&active_profile())
+}
+
+pub const DEFAULT_PROFILE: &str = "default";
+
+// The default profile keeps the original `settings.json`; others live next to it as
+// `settings.<name>.json`.
+fn profile_settings_path(name: &str) -> Result<PathBuf> {
+    let dir = config_dir()?;
+    if name == DEFAULT_PROFILE {
+        return Ok(dir.join("settings.json"));
+    }
+    Ok(dir.join(format!("settings.{name}.json")))
+}
+
+fn active_profile_path() -> Result<PathBuf> {
+    Ok(config_dir()?.join("active-profile"))
+}
+
+static ACTIVE_PROFILE: Lazy<Mutex<Option<String>>> = Lazy::new(|| Mutex::new(None));
+
+fn active_profile() -> String {
+    let mut cached = ACTIVE_PROFILE.lock().unwrap_or_else(|e| e.into_inner());
+    if let Some(name) = cached.as_ref() {
+        return name.clone();
+    }
+    let name = active_profile_path()
+        .ok()
+        .and_then(|p| fs::read_to_string(p).ok())
+        .map(|v| v.trim().to_string())
+        .filter(|v| validate_profile_name(v).is_ok())
+        .unwrap_or_else(|| DEFAULT_PROFILE.to_string());
+    *cached = Some(name.clone());
+    name
+}
+
+fn validate_profile_name(name: &str) -> Result<&str> {
+    let name = name.trim();
+    if name.is_empty() || name.len() > 32 {
+        return Err(anyhow!("profile name must be 1-32 characters"));
+    }
+    if !name.chars().all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_') {
+        return Err(anyhow!("profile name may only contain letters, digits, '-' and '_'"));
+    }
+    Ok(name)
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SettingsProfiles {
+    pub active: String,
+    pub profiles: Vec<String>,
+}
+
+pub fn profile_list() -> Result<SettingsProfiles> {
+    let dir = config_dir()?;
+    let mut profiles = vec![DEFAULT_PROFILE.to_string()];
+    if let Ok(entries) = fs::read_dir(&dir) {
+        for e in entries.flatten() {
+            let file_name = e.file_name().to_string_lossy().to_string();
+            let Some(name) = file_name.strip_prefix("settings.").and_then(|n| n.strip_suffix(".json")) else {
+                continue;
+            };
+            if validate_profile_name(name).is_ok() && name != DEFAULT_PROFILE {
+                profiles.push(name.to_string());
+            }
+        }
+    }
+    profiles.sort();
+    profiles.dedup();
+    Ok(SettingsProfiles {
+        active: active_profile(),
+        profiles,
+    })
+}
+
+/// Makes `name` the active settings profile. A profile that doesn't exist yet starts as a copy
+/// of the current settings. Listeners get a `settings:changed` for every field that differs.
+pub fn profile_switch(name: &str) -> Result<AppSettings> {
+    let name = validate_profile_name(name)?.to_string();
+    let _guard = UPDATE_LOCK.lock().unwrap_or_else(|e| e.into_inner());
+
+    let current = load()?;
+    let target = profile_settings_path(&name)?;
+    if !target.exists() {
+        let s = serde_json::to_string_pretty(&current).context("serialize settings")?;
+        private_fs::write_private(&target, s.as_bytes())
+            .with_context(|| format!("create settings profile: {}", target.display()))?;
+    }
+
+    let pointer = active_profile_path()?;
+    private_fs::write_private(&pointer, name.as_bytes())
+        .with_context(|| format!("write active profile: {}", pointer.display()))?;
+    *ACTIVE_PROFILE.lock().unwrap_or_else(|e| e.into_inner()) = Some(name);
+
+    let next = load()?;
+    if let Ok(v) = serde_json::to_value(&next) {
+        publish_change(v, false);
+    }
+    Ok(next)
+}
+
+/// Deletes a profile's settings file. The active and default profiles can't be deleted.
+pub fn profile_delete(name: &str) -> Result<SettingsProfiles> {
+    let name = validate_profile_name(name)?;
+    if name == DEFAULT_PROFILE {
+        return Err(anyhow!("the default profile can't be deleted"));
+    }
+    if name == active_profile() {
+        return Err(anyhow!("switch to another profile before deleting this one"));
+    }
+    let path = profile_settings_path(name)?;
+    if path.exists() {
+        fs::remove_file(&path).with_context(|| format!("delete settings profile: {}", path.display()))?;
+    }
+    profile_list()
 }
@@ -0,0 +1,275 @@
+use anyhow::{anyhow, Context, Result};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+// Every script reports the same sequences:
+//   OSC 133;A        a prompt is about to be drawn
+//   OSC 133;B        the prompt ends and the command line begins
+//   OSC 633;E;<cmd>  the command line about to run, escaped (`\\`, `\x3b` for `;`, `\x0a`)
+//   OSC 133;C        the command starts; its output follows
+//   OSC 133;D;<code> the command finished with that exit status
+//   OSC 7            the working directory, as a `file://` URL
+// They are written to the app's config folder each time a shell starts, so they stay in step
+// with the app version.
+
+const BASH: &str = r#"# Pompora shell integration for bash, loaded with --rcfile in place of ~/.bashrc.
+if [ -z "$POMPORA_SHELL_INTEGRATION" ]; then
+POMPORA_SHELL_INTEGRATION=1
+[ -r /etc/bash.bashrc ] && . /etc/bash.bashrc
+[ -r ~/.bashrc ] && . ~/.bashrc
+
+__pompora_escape() {
+  local s=$1
+  s=${s//\\/\\\\}
+  s=${s//;/\\x3b}
+  s=${s//$'\n'/\\x0a}
+  printf '%s' "$s"
+}
+
+__pompora_at_prompt=0
+__pompora_ran=0
+
+__pompora_preexec() {
+  [ "$__pompora_at_prompt" = 1 ] || return
+  case "$BASH_COMMAND" in __pompora_*) return ;; esac
+  [ -n "$COMP_LINE" ] && return
+  __pompora_at_prompt=0
+  __pompora_ran=1
+  local line
+  line=$(HISTTIMEFORMAT= builtin history 1 | sed 's/^ *[0-9]* *//')
+  printf '\e]633;E;%s\a\e]133;C\a' "$(__pompora_escape "$line")"
+}
+
+__pompora_precmd() {
+  local code=$?
+  __pompora_at_prompt=0
+  [ "$__pompora_ran" = 1 ] && printf '\e]133;D;%s\a' "$code"
+  __pompora_ran=0
+  printf '\e]7;file://%s%s\a\e]133;A\a' "$HOSTNAME" "$PWD"
+}
+
+PROMPT_COMMAND="__pompora_precmd${PROMPT_COMMAND:+;$PROMPT_COMMAND};__pompora_at_prompt=1"
+PS1="$PS1\[\e]133;B\a\]"
+trap '__pompora_preexec' DEBUG
+fi
+"#;
+
+const ZSH_ENV: &str = r#"# Pompora shell integration for zsh: ZDOTDIR points here, so load the user's files first.
+[ -r "${POMPORA_USER_ZDOTDIR:-$HOME}/.zshenv" ] && . "${POMPORA_USER_ZDOTDIR:-$HOME}/.zshenv"
+"#;
+
+const ZSH_RC: &str = r#"# Pompora shell integration for zsh.
+ZDOTDIR=${POMPORA_USER_ZDOTDIR:-$HOME}
+unset POMPORA_USER_ZDOTDIR
+[ -r "$ZDOTDIR/.zshrc" ] && . "$ZDOTDIR/.zshrc"
+
+__pompora_escape() {
+  local s=$1
+  s=${s//\\/\\\\}
+  s=${s//;/\\x3b}
+  s=${s//$'\n'/\\x0a}
+  printf '%s' "$s"
+}
+
+__pompora_ran=0
+
+__pompora_preexec() {
+  __pompora_ran=1
+  printf '\e]633;E;%s\a\e]133;C\a' "$(__pompora_escape "$1")"
+}
+
+__pompora_precmd() {
+  local code=$?
+  [[ $__pompora_ran = 1 ]] && printf '\e]133;D;%s\a' "$code"
+  __pompora_ran=0
+  printf '\e]7;file://%s%s\a\e]133;A\a' "$HOST" "$PWD"
+}
+
+autoload -Uz add-zsh-hook
+add-zsh-hook preexec __pompora_preexec
+# First, so it still sees the command's exit status.
+precmd_functions=(__pompora_precmd $precmd_functions)
+PS1="$PS1%{"$'\e]133;B\a'"%}"
+"#;
+
+const FISH: &str = r#"# Pompora shell integration for fish, loaded with --init-command.
+function __pompora_escape
+    string replace -a '\\' '\\\\' -- $argv | string replace -a ';' '\\x3b' | string join '\\x0a'
+end
+
+function __pompora_preexec --on-event fish_preexec
+    printf '\e]633;E;%s\a\e]133;C\a' (__pompora_escape $argv)
+end
+
+function __pompora_postexec --on-event fish_postexec
+    printf '\e]133;D;%s\a' $status
+end
+
+function __pompora_prompt --on-event fish_prompt
+    printf '\e]7;file://%s%s\a\e]133;A\a' (hostname) $PWD
+end
+"#;
+
+const PWSH: &str = r#"# Pompora shell integration for PowerShell, dot-sourced with -Command.
+$Global:__PomporaRan = $false
+$Global:__PomporaOriginalPrompt = $function:Prompt
+
+function Global:__PomporaEscape([string]$s) {
+    $s.Replace('\', '\\').Replace(';', '\x3b').Replace("`r", '').Replace("`n", '\x0a')
+}
+
+function Global:Prompt {
+    $ok = $?
+    $code = $Global:LASTEXITCODE
+    $e = [char]0x1b
+    $bel = [char]7
+    $out = ''
+    if ($Global:__PomporaRan) {
+        $status = if ($ok) { 0 } elseif ($code) { $code } else { 1 }
+        $out += "$e]133;D;$status$bel"
+    }
+    $Global:__PomporaRan = $false
+    $dir = $PWD.ProviderPath -replace '\\', '/'
+    if (-not $dir.StartsWith('/')) { $dir = "/$dir" }
+    $out += "$e]7;file://$([Environment]::MachineName)$dir$bel$e]133;A$bel"
+    $out + $Global:__PomporaOriginalPrompt.Invoke() + "$e]133;B$bel"
+}
+
+if ($function:PSConsoleHostReadLine) {
+    $Global:__PomporaOriginalReadLine = $function:PSConsoleHostReadLine
+    function Global:PSConsoleHostReadLine {
+        $line = $Global:__PomporaOriginalReadLine.Invoke()
+        if ($line.Trim()) {
+            $Global:__PomporaRan = $true
+            $e = [char]0x1b
+            [Console]::Write("$e]633;E;$(__PomporaEscape $line)$([char]7)$e]133;C$([char]7)")
+        }
+        $line
+    }
+}
+"#;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ShellKind {
+    Bash,
+    Zsh,
+    Fish,
+    Pwsh,
+}
+
+/// The shell `program` runs, judged by its file name (`/usr/bin/zsh`, `pwsh.exe`, ...).
+pub fn shell_kind(program: &str) -> Option<ShellKind> {
+    let name = Path::new(program).file_stem()?.to_string_lossy().to_lowercase();
+    match name.as_str() {
+        "bash" => Some(ShellKind::Bash),
+        "zsh" => Some(ShellKind::Zsh),
+        "fish" => Some(ShellKind::Fish),
+        "pwsh" | "powershell" => Some(ShellKind::Pwsh),
+        _ => None,
+    }
+}
+
+/// How to start a shell with integration loaded: its arguments, and environment to add.
+pub struct Launch {
+    pub args: Vec<String>,
+    pub env: Vec<(String, String)>,
+}
+
+fn scripts_dir() -> Result<PathBuf> {
+    let base = dirs::config_dir().context("missing config dir")?;
+    Ok(base.join("Pompora").join("shell-integration"))
+}
+
+fn write_script(path: &Path, contents: &str) -> Result<()> {
+    if fs::read_to_string(path).ok().as_deref() == Some(contents) {
+        return Ok(());
+    }
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir).with_context(|| format!("create dir: {}", dir.display()))?;
+    }
+    fs::write(path, contents).with_context(|| format!("write {}", path.display()))
+}
+
+// Shells on Windows (Git Bash, MSYS) take forward slashes too.
+fn script_arg(path: &Path) -> String {
+    path.to_string_lossy().replace('\\', "/")
+}
+
+/// Arguments and environment that start an interactive `kind` shell with the integration
+/// script loaded after the user's own startup files.
+pub fn launch(kind: ShellKind) -> Result<Launch> {
+    let dir = scripts_dir()?;
+    match kind {
+        ShellKind::Bash => {
+            let path = dir.join("bash").join("pompora.bash");
+            write_script(&path, BASH)?;
+            Ok(Launch {
+                args: vec!["--rcfile".into(), script_arg(&path), "-i".into()],
+                env: vec![],
+            })
+        }
+        ShellKind::Zsh => {
+            let zdotdir = dir.join("zsh");
+            write_script(&zdotdir.join(".zshenv"), ZSH_ENV)?;
+            write_script(&zdotdir.join(".zshrc"), ZSH_RC)?;
+            let user = std::env::var("ZDOTDIR")
+                .ok()
+                .or_else(|| dirs::home_dir().map(|h| h.to_string_lossy().into_owned()))
+                .ok_or_else(|| anyhow!("missing home dir"))?;
+            Ok(Launch {
+                args: vec!["-i".into()],
+                env: vec![
+                    ("ZDOTDIR".into(), zdotdir.to_string_lossy().into_owned()),
+                    ("POMPORA_USER_ZDOTDIR".into(), user),
+                ],
+            })
+        }
+        ShellKind::Fish => {
+            let path = dir.join("fish").join("pompora.fish");
+            write_script(&path, FISH)?;
+            Ok(Launch {
+                args: vec!["--init-command".into(), format!("source '{}'", script_arg(&path)), "-i".into()],
+                env: vec![],
+            })
+        }
+        ShellKind::Pwsh => {
+            let path = dir.join("pwsh").join("pompora.ps1");
+            write_script(&path, PWSH)?;
+            Ok(Launch {
+                args: vec![
+                    "-NoLogo".into(),
+                    "-NoExit".into(),
+                    "-Command".into(),
+                    format!(". '{}'", path.to_string_lossy().replace('\'', "''")),
+                ],
+                env: vec![],
+            })
+        }
+    }
+}
+
+/// Undoes the escaping the scripts apply to command lines.
+pub fn unescape(s: &str) -> String {
+    let mut out = String::with_capacity(s.len());
+    let mut rest = s;
+    while let Some(i) = rest.find('\\') {
+        out.push_str(&rest[..i]);
+        rest = &rest[i..];
+        if let Some(r) = rest.strip_prefix("\\\\") {
+            out.push('\\');
+            rest = r;
+        } else if let Some(c) = rest
+            .strip_prefix("\\x")
+            .and_then(|r| r.get(..2))
+            .and_then(|h| u8::from_str_radix(h, 16).ok())
+        {
+            out.push(c as char);
+            rest = &rest[4..];
+        } else {
+            out.push('\\');
+            rest = &rest[1..];
+        }
+    }
+    out.push_str(rest);
+    out
+}
@@ -0,0 +1,123 @@
+use serde::Serialize;
+use std::path::{Path, PathBuf};
+
+/// A shell found on this machine, for the terminal's shell picker.
+#[derive(Debug, Clone, Serialize)]
+pub struct DetectedShell {
+    /// Name to show, e.g. `zsh`, `Git Bash` or `WSL: Ubuntu`.
+    pub name: String,
+    /// Executable to start.
+    pub path: String,
+    /// Arguments that pick the shell, e.g. `-d Ubuntu` for a WSL distribution; usually empty.
+    pub args: Vec<String>,
+}
+
+// Shells looked for on PATH, with the names they are shown under.
+const PATH_SHELLS: &[(&str, &str)] = &[
+    ("bash", "bash"),
+    ("zsh", "zsh"),
+    ("fish", "fish"),
+    ("pwsh", "PowerShell"),
+    ("nu", "Nushell"),
+];
+
+// First `name` on PATH.
+fn find_in_path(name: &str) -> Option<PathBuf> {
+    let file = if cfg!(windows) { format!("{name}.exe") } else { name.to_string() };
+    std::env::split_paths(&std::env::var_os("PATH")?)
+        .map(|dir| dir.join(&file))
+        .find(|p| p.is_file())
+}
+
+struct Found {
+    shells: Vec<DetectedShell>,
+    // Canonical paths of what has been added, so a shell reached through a symlink (`/bin/bash`
+    // and `/usr/bin/bash`) is listed once.
+    seen: Vec<PathBuf>,
+}
+
+impl Found {
+    fn add(&mut self, name: &str, path: &Path, args: Vec<String>) {
+        if !path.is_file() {
+            return;
+        }
+        let canonical = path.canonicalize().unwrap_or_else(|_| path.to_path_buf());
+        if args.is_empty() && self.seen.contains(&canonical) {
+            return;
+        }
+        self.seen.push(canonical);
+        self.shells.push(DetectedShell {
+            name: name.to_string(),
+            path: path.to_string_lossy().into_owned(),
+            args,
+        });
+    }
+}
+
+/// Shells installed on this machine, the user's login shell (or Windows' default) first.
+pub fn detect() -> Vec<DetectedShell> {
+    let mut found = Found {
+        shells: vec![],
+        seen: vec![],
+    };
+    #[cfg(windows)]
+    detect_windows(&mut found);
+    #[cfg(not(windows))]
+    {
+        if let Ok(shell) = std::env::var("SHELL") {
+            let path = Path::new(&shell);
+            let stem = path.file_stem().map(|s| s.to_string_lossy().into_owned()).unwrap_or_default();
+            let name = PATH_SHELLS.iter().find(|(n, _)| *n == stem).map_or(stem.as_str(), |(_, d)| d);
+            found.add(name, path, vec![]);
+        }
+        for (bin, name) in PATH_SHELLS {
+            if let Some(path) = find_in_path(bin) {
+                found.add(name, &path, vec![]);
+            }
+        }
+    }
+    found.shells
+}
+
+#[cfg(windows)]
+fn detect_windows(found: &mut Found) {
+    let env_path = |var: &str| std::env::var_os(var).map(PathBuf::from);
+    let system_root = env_path("SystemRoot").unwrap_or_else(|| PathBuf::from(r"C:\Windows"));
+
+    let cmd = env_path("COMSPEC").unwrap_or_else(|| system_root.join("System32").join("cmd.exe"));
+    found.add("Command Prompt", &cmd, vec![]);
+    found.add(
+        "Windows PowerShell",
+        &system_root.join(r"System32\WindowsPowerShell\v1.0\powershell.exe"),
+        vec![],
+    );
+    if let Some(pwsh) = find_in_path("pwsh").or_else(|| Some(env_path("ProgramFiles")?.join(r"PowerShell\7\pwsh.exe"))) {
+        found.add("PowerShell", &pwsh, vec![]);
+    }
+
+    // `bash.exe` on PATH is usually System32's WSL launcher, so Git Bash is looked for where Git
+    // installs it, or next to `git.exe` (`Git\cmd\git.exe` -> `Git\bin\bash.exe`).
+    let git_bash = ["ProgramFiles", "ProgramFiles(x86)"]
+        .iter()
+        .filter_map(|v| Some(env_path(v)?.join(r"Git\bin\bash.exe")))
+        .chain(env_path("LOCALAPPDATA").map(|d| d.join(r"Programs\Git\bin\bash.exe")))
+        .chain(find_in_path("git").and_then(|g| Some(g.parent()?.parent()?.join(r"bin\bash.exe"))))
+        .find(|p| p.is_file());
+    if let Some(path) = git_bash {
+        found.add("Git Bash", &path, vec![]);
+    }
+
+    for (bin, name) in PATH_SHELLS {
+        if *bin == "bash" || *bin == "pwsh" {
+            continue;
+        }
+        if let Some(path) = find_in_path(bin) {
+            found.add(name, &path, vec![]);
+        }
+    }
+
+    let wsl = system_root.join("System32").join("wsl.exe");
+    for distro in super::wsl::distros() {
+        found.add(&format!("WSL: {distro}"), &wsl, vec!["-d".to_string(), distro]);
+    }
+}
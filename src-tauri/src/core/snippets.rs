@@ -0,0 +1,237 @@
+use anyhow::{anyhow, Context, Result};
+use std::path::Path;
+
+use super::settings::{self, AppSettings, Snippet};
+
+// How many snippets the AI is shown, and how much of each body.
+const MAX_PROMPT_SNIPPETS: usize = 40;
+const MAX_PROMPT_BODY_CHARS: usize = 600;
+
+fn applies_to(snippet: &Snippet, language: &str) -> bool {
+    snippet.languages.is_empty() || snippet.languages.iter().any(|l| l == language)
+}
+
+/// Saved snippets by name, or only those offered in `language`. There a snippet for the
+/// language hides one for all languages with the same prefix.
+pub fn snippets_list(language: Option<&str>) -> Result<Vec<Snippet>> {
+    let mut out = settings::load()?.snippets;
+    if let Some(language) = language {
+        let specific: Vec<String> = out
+            .iter()
+            .filter(|s| s.languages.iter().any(|l| l == language))
+            .map(|s| s.prefix.clone())
+            .collect();
+        out.retain(|s| applies_to(s, language) && (!s.languages.is_empty() || !specific.contains(&s.prefix)));
+    }
+    out.sort_by_key(|a| a.name.to_lowercase());
+    Ok(out)
+}
+
+// Tab stops, placeholders and choices opened with `${` have to be closed; `\` escapes.
+fn check_body(body: &str) -> Result<()> {
+    let mut depth = 0usize;
+    let mut chars = body.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '\\' => {
+                chars.next();
+            }
+            '$' if chars.peek() == Some(&'{') => {
+                chars.next();
+                match chars.peek() {
+                    Some(n) if n.is_ascii_digit() || n.is_ascii_alphabetic() || *n == '_' => depth += 1,
+                    _ => return Err(anyhow!("'${{' must be followed by a tab stop number or variable name")),
+                }
+            }
+            '}' if depth > 0 => depth -= 1,
+            _ => {}
+        }
+    }
+    if depth > 0 {
+        return Err(anyhow!("snippet body has an unclosed '${{'"));
+    }
+    Ok(())
+}
+
+fn validate(snippet: Snippet) -> Result<Snippet> {
+    let name = snippet.name.trim().to_string();
+    let prefix = snippet.prefix.trim().to_string();
+    if name.is_empty() {
+        return Err(anyhow!("snippet name is required"));
+    }
+    if prefix.is_empty() || prefix.chars().any(char::is_whitespace) {
+        return Err(anyhow!("snippet prefix must be a single word"));
+    }
+    check_body(&snippet.body).with_context(|| format!("snippet '{name}'"))?;
+    Ok(Snippet {
+        name,
+        prefix,
+        description: snippet.description.filter(|d| !d.trim().is_empty()),
+        languages: snippet
+            .languages
+            .iter()
+            .map(|l| l.trim().to_string())
+            .filter(|l| !l.is_empty())
+            .collect(),
+        body: snippet.body,
+    })
+}
+
+// Replaces the snippet named `name` (or adds one) with `snippet`.
+fn put(s: &mut AppSettings, name: &str, snippet: Snippet) {
+    s.snippets.retain(|x| x.name != name && x.name != snippet.name);
+    s.snippets.push(snippet);
+}
+
+/// Saves a snippet, replacing the one with its name, or the one named `previous_name` when
+/// it is being renamed.
+pub fn snippets_save(snippet: Snippet, previous_name: Option<&str>) -> Result<Vec<Snippet>> {
+    let snippet = validate(snippet)?;
+    let name = previous_name.unwrap_or(&snippet.name).to_string();
+    settings::update(|s| {
+        put(s, &name, snippet);
+        Ok(())
+    })?;
+    snippets_list(None)
+}
+
+pub fn snippets_delete(name: &str) -> Result<Vec<Snippet>> {
+    settings::update(|s| {
+        s.snippets.retain(|x| x.name != name);
+        Ok(())
+    })?;
+    snippets_list(None)
+}
+
+// VS Code snippet files are JSON with comments and trailing commas.
+fn strip_jsonc(src: &str) -> String {
+    let mut out = String::with_capacity(src.len());
+    let mut chars = src.chars().peekable();
+    let mut in_string = false;
+    while let Some(c) = chars.next() {
+        if in_string {
+            out.push(c);
+            match c {
+                '\\' => out.extend(chars.next()),
+                '"' => in_string = false,
+                _ => {}
+            }
+            continue;
+        }
+        match c {
+            '"' => {
+                in_string = true;
+                out.push(c);
+            }
+            '/' if chars.peek() == Some(&'/') => {
+                while chars.next_if(|n| *n != '\n').is_some() {}
+            }
+            '/' if chars.peek() == Some(&'*') => {
+                chars.next();
+                let mut prev = ' ';
+                for n in chars.by_ref() {
+                    if prev == '*' && n == '/' {
+                        break;
+                    }
+                    prev = n;
+                }
+            }
+            '}' | ']' => {
+                let kept = out.trim_end().len();
+                if out[..kept].ends_with(',') {
+                    out.truncate(kept - 1);
+                }
+                out.push(c);
+            }
+            _ => out.push(c),
+        }
+    }
+    out
+}
+
+// A string, or lines given as an array of strings.
+fn text_of(v: &serde_json::Value) -> Option<String> {
+    match v {
+        serde_json::Value::String(s) => Some(s.clone()),
+        serde_json::Value::Array(items) => Some(items.iter().filter_map(|i| i.as_str()).collect::<Vec<_>>().join("\n")),
+        _ => None,
+    }
+}
+
+fn parse_vscode(src: &str, language: Option<&str>) -> Result<Vec<Snippet>> {
+    let json: serde_json::Value = serde_json::from_str(&strip_jsonc(src)).context("parse snippets JSON")?;
+    let entries = json.as_object().ok_or_else(|| anyhow!("snippets JSON must be an object"))?;
+    let mut out = vec![];
+    for (name, entry) in entries {
+        let Some(body) = entry.get("body").and_then(text_of) else {
+            continue;
+        };
+        // Only the first of several prefixes is kept.
+        let prefix = match entry.get("prefix") {
+            Some(serde_json::Value::Array(items)) => items.first().and_then(|p| p.as_str()).map(str::to_string),
+            Some(p) => p.as_str().map(str::to_string),
+            None => None,
+        };
+        let languages = match entry.get("scope").and_then(|v| v.as_str()) {
+            Some(scope) => scope.split(',').map(|l| l.trim().to_string()).collect(),
+            None => language.map(|l| vec![l.to_string()]).unwrap_or_default(),
+        };
+        out.push(validate(Snippet {
+            name: name.clone(),
+            prefix: prefix.unwrap_or_else(|| name.clone()),
+            body,
+            description: entry.get("description").and_then(text_of),
+            languages,
+        })?);
+    }
+    Ok(out)
+}
+
+/// Imports VS Code snippets from the file at `path` or from `content`, replacing saved ones
+/// with the same names. Entries without a `scope` are for `language`, which for a language's
+/// own snippets file (e.g. `rust.json`) defaults to its name; they are for all languages in a
+/// `.code-snippets` file. Returns the imported snippets.
+pub fn snippets_import(path: Option<&str>, content: Option<String>, language: Option<&str>) -> Result<Vec<Snippet>> {
+    let (src, language) = match (content, path) {
+        (Some(c), _) => (c, language.map(str::to_string)),
+        (None, Some(p)) => {
+            let p = Path::new(p);
+            let src = std::fs::read_to_string(p).with_context(|| format!("read snippets: {}", p.display()))?;
+            let from_name = (p.extension().and_then(|e| e.to_str()) == Some("json"))
+                .then(|| p.file_stem().and_then(|s| s.to_str()).map(str::to_string))
+                .flatten();
+            (src, language.map(str::to_string).or(from_name))
+        }
+        (None, None) => return Err(anyhow!("path or content is required")),
+    };
+    let imported = parse_vscode(&src, language.as_deref())?;
+    let to_store = imported.clone();
+    settings::update(|s| {
+        for snippet in to_store {
+            let name = snippet.name.clone();
+            put(s, &name, snippet);
+        }
+        Ok(())
+    })?;
+    Ok(imported)
+}
+
+/// The user's snippets as part of the AI's system prompt: examples of how they like code written.
+pub fn prompt_section(s: &AppSettings) -> Option<String> {
+    if s.snippets.is_empty() {
+        return None;
+    }
+    let mut out = String::from(
+        "The user's snippets, showing idioms they prefer ($1, ${1:name} and $0 are places to fill in):",
+    );
+    for snippet in s.snippets.iter().take(MAX_PROMPT_SNIPPETS) {
+        let languages = if snippet.languages.is_empty() {
+            "any language".to_string()
+        } else {
+            snippet.languages.join(", ")
+        };
+        let body: String = snippet.body.chars().take(MAX_PROMPT_BODY_CHARS).collect();
+        out.push_str(&format!("\n\n{} ({languages}):\n```\n{}\n```", snippet.name, body.trim_end()));
+    }
+    Some(out)
+}
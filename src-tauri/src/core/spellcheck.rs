@@ -0,0 +1,891 @@
+use anyhow::{anyhow, Context, Result};
+use once_cell::sync::Lazy;
+use regex::Regex;
+use serde::Serialize;
+use std::collections::{HashMap, HashSet};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use std::time::SystemTime;
+use tree_sitter::{Language, Node, Parser};
+
+use super::{fsops, settings, workspace};
+
+// The workspace's own words, one per line; `#` starts a comment.
+const CUSTOM_DICTIONARY: &str = ".pompora/dictionary.txt";
+
+// Shorter words are mostly abbreviations and loop variables.
+const MIN_WORD_CHARS: usize = 3;
+const MAX_SUGGESTIONS: usize = 5;
+// A file with more than this isn't prose worth checking; the rest goes unreported.
+const MAX_MISSPELLINGS: usize = 1000;
+// What `TRY` defaults to when a dictionary has none.
+const DEFAULT_TRY: &str = "esianrtolcdugmphbyfvkwzjxq'";
+
+#[derive(Debug, Clone, Serialize)]
+pub struct Misspelling {
+    pub word: String,
+    /// 1-based; columns count UTF-16 code units like the editor does, and `end_column` is just
+    /// past the word.
+    pub line: u32,
+    pub column: u32,
+    pub end_column: u32,
+    /// Closest dictionary words first; empty when nothing is near.
+    pub suggestions: Vec<String>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct SpellcheckResult {
+    pub path: String,
+    pub language: String,
+    /// The `.dic` file the words were looked up in.
+    pub dictionary: String,
+    /// Workspace path of the root's own dictionary, whether or not it exists yet.
+    pub custom_dictionary: String,
+    pub misspellings: Vec<Misspelling>,
+    /// Whether the file had more misspellings than are reported.
+    pub truncated: bool,
+}
+
+// Hunspell flags: one character by default, two with `FLAG long`, numbers with `FLAG num`.
+type Flag = u32;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum FlagFormat {
+    Char,
+    Long,
+    Num,
+}
+
+fn parse_flags(s: &str, format: FlagFormat) -> Vec<Flag> {
+    match format {
+        FlagFormat::Char => s.chars().map(|c| c as u32).collect(),
+        FlagFormat::Long => {
+            let chars: Vec<char> = s.chars().collect();
+            chars.chunks(2).map(|p| p.iter().fold(0, |acc, &c| (acc << 16) | c as u32)).collect()
+        }
+        FlagFormat::Num => s.split(',').filter_map(|n| n.trim().parse().ok()).collect(),
+    }
+}
+
+#[derive(Debug, Clone)]
+enum CondPart {
+    Any,
+    Char(char),
+    Set { negated: bool, chars: Vec<char> },
+}
+
+impl CondPart {
+    fn matches(&self, c: char) -> bool {
+        match self {
+            CondPart::Any => true,
+            CondPart::Char(x) => *x == c,
+            CondPart::Set { negated, chars } => chars.contains(&c) != *negated,
+        }
+    }
+}
+
+// An affix condition such as `[^aeiou]y`, one part per character it tests.
+fn parse_condition(s: &str) -> Vec<CondPart> {
+    if s == "." {
+        return vec![];
+    }
+    let mut parts = vec![];
+    let mut chars = s.chars();
+    while let Some(c) = chars.next() {
+        parts.push(match c {
+            '.' => CondPart::Any,
+            '[' => {
+                let mut set: Vec<char> = chars.by_ref().take_while(|&c| c != ']').collect();
+                let negated = set.first() == Some(&'^');
+                if negated {
+                    set.remove(0);
+                }
+                CondPart::Set { negated, chars: set }
+            }
+            c => CondPart::Char(c),
+        });
+    }
+    parts
+}
+
+#[derive(Debug, Clone)]
+struct Affix {
+    flag: Flag,
+    // Whether it combines with an affix of the other kind.
+    cross: bool,
+    strip: String,
+    add: String,
+    condition: Vec<CondPart>,
+}
+
+impl Affix {
+    // Suffix conditions test the end of the root, prefix conditions its start.
+    fn fits_end(&self, root: &str) -> bool {
+        let n = self.condition.len();
+        let tail: Vec<char> = root.chars().rev().take(n).collect();
+        tail.len() == n && self.condition.iter().rev().zip(tail).all(|(p, c)| p.matches(c))
+    }
+
+    fn fits_start(&self, root: &str) -> bool {
+        let n = self.condition.len();
+        root.chars().count() >= n && self.condition.iter().zip(root.chars()).all(|(p, c)| p.matches(c))
+    }
+}
+
+/// A Hunspell dictionary: the `.dic` word list with the prefixes and suffixes of its `.aff`
+/// file. Words take at most one prefix and one suffix, which is what most dictionaries use;
+/// compounding and two-level suffixes aren't formed, so languages relying on them (German,
+/// Hungarian) see long compounds flagged.
+struct Dictionary {
+    path: PathBuf,
+    words: HashMap<String, Vec<Flag>>,
+    prefixes: Vec<Affix>,
+    suffixes: Vec<Affix>,
+    forbidden: Option<Flag>,
+    need_affix: Option<Flag>,
+    no_suggest: Option<Flag>,
+    try_chars: Vec<char>,
+    replacements: Vec<(String, String)>,
+}
+
+// Hunspell files declare their encoding with `SET`; ISO-8859-1 when they don't.
+fn decode(bytes: &[u8], set: Option<&str>) -> String {
+    let label = set.unwrap_or("ISO-8859-1");
+    let encoding = encoding_rs::Encoding::for_label(label.as_bytes()).unwrap_or(encoding_rs::UTF_8);
+    encoding.decode(bytes).0.into_owned()
+}
+
+fn read_set(aff: &[u8]) -> Option<String> {
+    String::from_utf8_lossy(aff)
+        .lines()
+        .find_map(|l| l.trim_start_matches('\u{feff}').strip_prefix("SET ").map(|s| s.trim().to_string()))
+}
+
+impl Dictionary {
+    fn load(dic_path: &Path, aff_path: &Path) -> Result<Dictionary> {
+        let aff_bytes = fs::read(aff_path).with_context(|| format!("read {}", aff_path.display()))?;
+        let dic_bytes = fs::read(dic_path).with_context(|| format!("read {}", dic_path.display()))?;
+        let set = read_set(&aff_bytes);
+        let aff = decode(&aff_bytes, set.as_deref());
+        let dic = decode(&dic_bytes, set.as_deref());
+
+        let mut format = FlagFormat::Char;
+        let mut d = Dictionary {
+            path: dic_path.to_path_buf(),
+            words: HashMap::new(),
+            prefixes: vec![],
+            suffixes: vec![],
+            forbidden: None,
+            need_affix: None,
+            no_suggest: None,
+            try_chars: vec![],
+            replacements: vec![],
+        };
+        let mut cross: HashMap<(bool, Flag), bool> = HashMap::new();
+        let flag = |s: &str, format| parse_flags(s, format).first().copied();
+        for line in aff.lines() {
+            let f: Vec<&str> = line.split_whitespace().collect();
+            match f.as_slice() {
+                ["FLAG", kind, ..] => {
+                    format = match kind.to_ascii_lowercase().as_str() {
+                        "long" => FlagFormat::Long,
+                        "num" => FlagFormat::Num,
+                        _ => FlagFormat::Char,
+                    }
+                }
+                ["TRY", chars, ..] => d.try_chars = chars.chars().collect(),
+                ["REP", from, to, ..] => d.replacements.push((from.replace('_', " "), to.replace('_', " "))),
+                ["FORBIDDENWORD", f, ..] => d.forbidden = flag(f, format),
+                ["NEEDAFFIX" | "PSEUDOROOT", f, ..] => d.need_affix = flag(f, format),
+                ["NOSUGGEST", f, ..] => d.no_suggest = flag(f, format),
+                [kind @ ("PFX" | "SFX"), f, yn @ ("Y" | "N"), count] if count.parse::<usize>().is_ok() => {
+                    if let Some(f) = flag(f, format) {
+                        cross.insert((*kind == "PFX", f), *yn == "Y");
+                    }
+                }
+                [kind @ ("PFX" | "SFX"), f, strip, add, rest @ ..] => {
+                    let Some(f) = flag(f, format) else { continue };
+                    let is_prefix = *kind == "PFX";
+                    // Continuation flags after `/` would allow a second affix; they aren't applied.
+                    let add = add.split('/').next().unwrap_or_default();
+                    let affix = Affix {
+                        flag: f,
+                        cross: cross.get(&(is_prefix, f)).copied().unwrap_or(false),
+                        strip: if *strip == "0" { String::new() } else { strip.to_string() },
+                        add: if add == "0" { String::new() } else { add.to_string() },
+                        condition: parse_condition(rest.first().copied().unwrap_or(".")),
+                    };
+                    if is_prefix {
+                        d.prefixes.push(affix);
+                    } else {
+                        d.suffixes.push(affix);
+                    }
+                }
+                _ => {}
+            }
+        }
+
+        let mut lines = dic.lines();
+        let first = lines.next().unwrap_or_default().trim_start_matches('\u{feff}');
+        let first = first.trim().parse::<usize>().is_err().then_some(first);
+        for line in first.into_iter().chain(lines) {
+            // `word/FLAGS`, maybe followed by morphological fields; `\/` is a literal slash.
+            let entry = line.split_whitespace().next().unwrap_or_default();
+            if entry.is_empty() {
+                continue;
+            }
+            let split = entry.char_indices().find(|&(i, c)| c == '/' && !entry[..i].ends_with('\\')).map(|(i, _)| i);
+            let (word, flags) = match split {
+                Some(i) => (&entry[..i], parse_flags(&entry[i + 1..], format)),
+                None => (entry, vec![]),
+            };
+            d.words.entry(word.replace("\\/", "/")).or_default().extend(flags);
+        }
+        if d.words.is_empty() {
+            return Err(anyhow!("{} has no words", dic_path.display()));
+        }
+        Ok(d)
+    }
+
+    fn has(flags: &[Flag], flag: Option<Flag>) -> bool {
+        flag.is_some_and(|f| flags.contains(&f))
+    }
+
+    // Flags of the dictionary entry `word` is formed from, if any.
+    fn lookup(&self, word: &str) -> Option<&[Flag]> {
+        if let Some(flags) = self.words.get(word) {
+            if !Self::has(flags, self.need_affix) || Self::has(flags, self.forbidden) {
+                return Some(flags);
+            }
+        }
+        for sfx in &self.suffixes {
+            let Some(base) = word.strip_suffix(sfx.add.as_str()) else { continue };
+            if base.is_empty() {
+                continue;
+            }
+            let root = format!("{base}{}", sfx.strip);
+            if !sfx.fits_end(&root) {
+                continue;
+            }
+            if let Some(flags) = self.words.get(&root).filter(|f| f.contains(&sfx.flag)) {
+                return Some(flags);
+            }
+            if !sfx.cross {
+                continue;
+            }
+            for pfx in self.prefixes.iter().filter(|p| p.cross) {
+                let Some(rest) = root.strip_prefix(pfx.add.as_str()).filter(|r| !r.is_empty()) else { continue };
+                let root = format!("{}{rest}", pfx.strip);
+                if !pfx.fits_start(&root) {
+                    continue;
+                }
+                if let Some(flags) = self.words.get(&root).filter(|f| f.contains(&sfx.flag) && f.contains(&pfx.flag)) {
+                    return Some(flags);
+                }
+            }
+        }
+        for pfx in &self.prefixes {
+            let Some(rest) = word.strip_prefix(pfx.add.as_str()).filter(|r| !r.is_empty()) else { continue };
+            let root = format!("{}{rest}", pfx.strip);
+            if pfx.fits_start(&root) {
+                if let Some(flags) = self.words.get(&root).filter(|f| f.contains(&pfx.flag)) {
+                    return Some(flags);
+                }
+            }
+        }
+        None
+    }
+
+    fn is_form(&self, word: &str) -> bool {
+        self.lookup(word).is_some_and(|f| !Self::has(f, self.forbidden))
+    }
+
+    fn is_suggestible(&self, word: &str) -> bool {
+        self.lookup(word)
+            .is_some_and(|f| !Self::has(f, self.forbidden) && !Self::has(f, self.no_suggest))
+    }
+
+    // `Paris` is only right capitalized, but `the` may start a sentence and `THE` be shouted.
+    fn check(&self, word: &str) -> bool {
+        if self.is_form(word) {
+            return true;
+        }
+        match case_of(word) {
+            Case::Capitalized => self.is_form(&word.to_lowercase()),
+            Case::Upper => {
+                let lower = word.to_lowercase();
+                self.is_form(&lower) || self.is_form(&capitalize(&lower))
+            }
+            Case::Lower | Case::Mixed => false,
+        }
+    }
+
+    fn suggest(&self, word: &str) -> Vec<String> {
+        let case = case_of(word);
+        let base = if matches!(case, Case::Capitalized | Case::Upper) { word.to_lowercase() } else { word.to_string() };
+        let chars: Vec<char> = base.chars().collect();
+        let try_chars: Vec<char> = if self.try_chars.is_empty() {
+            DEFAULT_TRY.chars().collect()
+        } else {
+            self.try_chars.iter().copied().filter(|c| !c.is_uppercase()).collect()
+        };
+
+        let mut seen = HashSet::new();
+        let mut replaced = vec![];
+        // `paris` for `Paris`, which only the dictionary's capitalization makes right.
+        if case == Case::Lower && self.is_suggestible(&capitalize(&base)) {
+            seen.insert(capitalize(&base));
+            replaced.push(capitalize(&base));
+        }
+        for (from, to) in &self.replacements {
+            for (i, _) in base.match_indices(from.as_str()) {
+                let candidate = format!("{}{to}{}", &base[..i], &base[i + from.len()..]);
+                if self.candidate_fits(&candidate) && seen.insert(candidate.clone()) {
+                    replaced.push(candidate);
+                }
+            }
+        }
+
+        let mut edits = vec![];
+        let mut consider = |candidate: String| {
+            if !seen.contains(&candidate) && self.candidate_fits(&candidate) {
+                seen.insert(candidate.clone());
+                edits.push(candidate);
+            }
+        };
+        let join = |cs: &[char]| cs.iter().collect::<String>();
+        for i in 0..chars.len() {
+            consider(join(&[&chars[..i], &chars[i + 1..]].concat()));
+            if i + 1 < chars.len() {
+                let mut swapped = chars.clone();
+                swapped.swap(i, i + 1);
+                consider(join(&swapped));
+            }
+            for &c in &try_chars {
+                if c != chars[i] {
+                    let mut changed = chars.clone();
+                    changed[i] = c;
+                    consider(join(&changed));
+                }
+            }
+        }
+        for i in 0..=chars.len() {
+            for &c in &try_chars {
+                let mut longer = chars.clone();
+                longer.insert(i, c);
+                consider(join(&longer));
+            }
+        }
+        for i in 1..chars.len() {
+            let (a, b) = (join(&chars[..i]), join(&chars[i..]));
+            if self.is_suggestible(&a) && self.is_suggestible(&b) {
+                consider(format!("{a} {b}"));
+            }
+        }
+        // Words sharing the first letter come first; a slip there is the rarer kind.
+        edits.sort_by_key(|e| e.chars().next() != chars.first().copied());
+
+        if replaced.is_empty() && edits.is_empty() {
+            edits = self.near_words(&chars);
+        }
+        replaced.into_iter().chain(edits).take(MAX_SUGGESTIONS).map(|s| recase(&s, case)).collect()
+    }
+
+    fn candidate_fits(&self, candidate: &str) -> bool {
+        match candidate.split_once(' ') {
+            Some(_) => true,
+            None => self.is_suggestible(candidate),
+        }
+    }
+
+    // Dictionary entries within two edits, for misspellings no single edit repairs.
+    fn near_words(&self, chars: &[char]) -> Vec<String> {
+        let Some(first) = chars.first() else { return vec![] };
+        let mut near: Vec<(usize, &String)> = self
+            .words
+            .iter()
+            .filter(|(w, flags)| {
+                w.starts_with(*first)
+                    && w.chars().count().abs_diff(chars.len()) <= 2
+                    && !Self::has(flags, self.forbidden)
+                    && !Self::has(flags, self.no_suggest)
+                    && !Self::has(flags, self.need_affix)
+            })
+            .filter_map(|(w, _)| {
+                let d = edit_distance(chars, &w.chars().collect::<Vec<_>>());
+                (d <= 2).then_some((d, w))
+            })
+            .collect();
+        near.sort();
+        near.into_iter().take(MAX_SUGGESTIONS).map(|(_, w)| w.clone()).collect()
+    }
+}
+
+// Optimal string alignment distance: insertions, deletions, substitutions and swaps of
+// neighbours each cost one.
+fn edit_distance(a: &[char], b: &[char]) -> usize {
+    let mut prev2: Vec<usize> = vec![0; b.len() + 1];
+    let mut prev: Vec<usize> = (0..=b.len()).collect();
+    for i in 1..=a.len() {
+        let mut cur = vec![i; b.len() + 1];
+        for j in 1..=b.len() {
+            let cost = usize::from(a[i - 1] != b[j - 1]);
+            cur[j] = (prev[j] + 1).min(cur[j - 1] + 1).min(prev[j - 1] + cost);
+            if i > 1 && j > 1 && a[i - 1] == b[j - 2] && a[i - 2] == b[j - 1] {
+                cur[j] = cur[j].min(prev2[j - 2] + 1);
+            }
+        }
+        prev2 = std::mem::replace(&mut prev, cur);
+    }
+    prev[b.len()]
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Case {
+    Lower,
+    Capitalized,
+    Upper,
+    Mixed,
+}
+
+fn case_of(word: &str) -> Case {
+    let mut letters = word.chars().filter(|c| c.is_alphabetic());
+    let first_upper = letters.next().is_some_and(char::is_uppercase);
+    let rest: Vec<char> = letters.collect();
+    match (first_upper, rest.iter().any(|c| c.is_uppercase()), rest.iter().any(|c| c.is_lowercase())) {
+        (false, false, _) => Case::Lower,
+        (true, false, _) => Case::Capitalized,
+        (true, true, false) => Case::Upper,
+        _ => Case::Mixed,
+    }
+}
+
+fn capitalize(word: &str) -> String {
+    let mut chars = word.chars();
+    match chars.next() {
+        Some(c) => c.to_uppercase().chain(chars).collect(),
+        None => String::new(),
+    }
+}
+
+fn recase(word: &str, case: Case) -> String {
+    match case {
+        Case::Capitalized => capitalize(word),
+        Case::Upper => word.to_uppercase(),
+        Case::Lower | Case::Mixed => word.to_string(),
+    }
+}
+
+fn dictionary_dirs() -> Vec<PathBuf> {
+    let mut out = vec![];
+    if let Some(config) = dirs::config_dir() {
+        out.push(config.join("Pompora").join("dictionaries"));
+    }
+    for dir in [
+        "/usr/share/hunspell",
+        "/usr/share/myspell",
+        "/usr/share/myspell/dicts",
+        "/usr/local/share/hunspell",
+        "/opt/homebrew/share/hunspell",
+        "/Library/Spelling",
+    ] {
+        out.push(PathBuf::from(dir));
+    }
+    if let Some(home) = dirs::home_dir() {
+        out.push(home.join("Library").join("Spelling"));
+    }
+    out
+}
+
+// Where `<language>.dic` and its `.aff` are; `en-US` and `en_US` both name the same one.
+fn find_dictionary(language: &str) -> Result<(PathBuf, PathBuf)> {
+    let names = [language.to_string(), language.replace('-', "_"), language.replace('_', "-")];
+    for dir in dictionary_dirs() {
+        for name in &names {
+            let dic = dir.join(format!("{name}.dic"));
+            let aff = dir.join(format!("{name}.aff"));
+            if dic.is_file() && aff.is_file() {
+                return Ok((dic, aff));
+            }
+        }
+    }
+    let own = dictionary_dirs().into_iter().next().map(|d| d.display().to_string()).unwrap_or_default();
+    Err(anyhow!("no Hunspell dictionary for {language}: put {language}.dic and {language}.aff in {own}"))
+}
+
+// A loaded dictionary and the modification time of its `.dic` file when it was read.
+type Loaded = (Option<SystemTime>, Arc<Dictionary>);
+
+// Loaded dictionaries by `.dic` path, reloaded when the file changes.
+static DICTIONARIES: Lazy<Mutex<HashMap<PathBuf, Loaded>>> = Lazy::new(|| Mutex::new(HashMap::new()));
+
+fn dictionary(language: &str) -> Result<Arc<Dictionary>> {
+    let (dic, aff) = find_dictionary(language)?;
+    let modified = fs::metadata(&dic).and_then(|m| m.modified()).ok();
+    if let Some((at, d)) = DICTIONARIES.lock().unwrap_or_else(|e| e.into_inner()).get(&dic) {
+        if *at == modified {
+            return Ok(d.clone());
+        }
+    }
+    let d = Arc::new(Dictionary::load(&dic, &aff)?);
+    DICTIONARIES.lock().unwrap_or_else(|e| e.into_inner()).insert(dic, (modified, d.clone()));
+    Ok(d)
+}
+
+// Lowercased, so a workspace word is accepted in any case.
+fn custom_words(root: &workspace::OpenRoot) -> HashSet<String> {
+    let text = fs::read_to_string(root.path.join(CUSTOM_DICTIONARY)).unwrap_or_default();
+    text.lines()
+        .map(str::trim)
+        .filter(|l| !l.is_empty() && !l.starts_with('#'))
+        .map(|l| normalize(l).to_lowercase())
+        .collect()
+}
+
+fn normalize(word: &str) -> String {
+    word.replace('\u{2019}', "'")
+}
+
+// A tree-sitter grammar and the node kinds in it worth checking. Comments are every node whose
+// kind names one (`comment`, `line_comment`, `block_comment`, ...).
+struct Grammar {
+    language: fn() -> Language,
+    strings: &'static [&'static str],
+    // Prose outside comments, such as the text of HTML elements.
+    text: &'static [&'static str],
+}
+
+const RUST: Grammar = Grammar {
+    language: || tree_sitter_rust::LANGUAGE.into(),
+    strings: &["string_literal", "raw_string_literal"],
+    text: &[],
+};
+const TYPESCRIPT: Grammar = Grammar {
+    language: || tree_sitter_typescript::LANGUAGE_TYPESCRIPT.into(),
+    strings: &["string", "template_string"],
+    text: &[],
+};
+const TSX: Grammar = Grammar {
+    language: || tree_sitter_typescript::LANGUAGE_TSX.into(),
+    strings: &["string", "template_string"],
+    text: &["jsx_text"],
+};
+const JAVASCRIPT: Grammar = Grammar {
+    language: || tree_sitter_javascript::LANGUAGE.into(),
+    strings: &["string", "template_string"],
+    text: &["jsx_text"],
+};
+const PYTHON: Grammar = Grammar { language: || tree_sitter_python::LANGUAGE.into(), strings: &["string"], text: &[] };
+const GO: Grammar = Grammar {
+    language: || tree_sitter_go::LANGUAGE.into(),
+    strings: &["interpreted_string_literal", "raw_string_literal"],
+    text: &[],
+};
+const C: Grammar = Grammar { language: || tree_sitter_c::LANGUAGE.into(), strings: &["string_literal"], text: &[] };
+const CPP: Grammar = Grammar {
+    language: || tree_sitter_cpp::LANGUAGE.into(),
+    strings: &["string_literal", "raw_string_literal"],
+    text: &[],
+};
+const JAVA: Grammar =
+    Grammar { language: || tree_sitter_java::LANGUAGE.into(), strings: &["string_literal"], text: &[] };
+const C_SHARP: Grammar = Grammar {
+    language: || tree_sitter_c_sharp::LANGUAGE.into(),
+    strings: &["string_literal", "verbatim_string_literal", "raw_string_literal", "interpolated_string_expression"],
+    text: &[],
+};
+const BASH: Grammar = Grammar {
+    language: || tree_sitter_bash::LANGUAGE.into(),
+    strings: &["string", "raw_string", "heredoc_body"],
+    text: &[],
+};
+const CSS: Grammar = Grammar { language: || tree_sitter_css::LANGUAGE.into(), strings: &["string_value"], text: &[] };
+const HTML: Grammar =
+    Grammar { language: || tree_sitter_html::LANGUAGE.into(), strings: &["attribute_value"], text: &["text"] };
+const RUBY: Grammar =
+    Grammar { language: || tree_sitter_ruby::LANGUAGE.into(), strings: &["string", "heredoc_body"], text: &[] };
+// `text` is the HTML around `<?php ... ?>`.
+const PHP: Grammar = Grammar {
+    language: || tree_sitter_php::LANGUAGE_PHP.into(),
+    strings: &["string", "encapsed_string", "heredoc", "nowdoc"],
+    text: &["text"],
+};
+const TOML: Grammar = Grammar { language: || tree_sitter_toml_ng::LANGUAGE.into(), strings: &["string"], text: &[] };
+// Plain scalars are left out: keys and values alike are mostly identifiers.
+const YAML: Grammar = Grammar {
+    language: || tree_sitter_yaml::LANGUAGE.into(),
+    strings: &["double_quote_scalar", "single_quote_scalar", "block_scalar"],
+    text: &[],
+};
+
+enum Checked {
+    Code(&'static Grammar),
+    Markdown,
+    Text,
+}
+
+fn checked_for(name: &str) -> Option<Checked> {
+    let lower = name.to_ascii_lowercase();
+    let ext = lower.rsplit_once('.')?.1;
+    Some(match ext {
+        "rs" => Checked::Code(&RUST),
+        "ts" | "mts" | "cts" => Checked::Code(&TYPESCRIPT),
+        "tsx" => Checked::Code(&TSX),
+        "js" | "jsx" | "mjs" | "cjs" => Checked::Code(&JAVASCRIPT),
+        "py" | "pyi" => Checked::Code(&PYTHON),
+        "go" => Checked::Code(&GO),
+        "c" | "h" => Checked::Code(&C),
+        "cc" | "cpp" | "cxx" | "hpp" | "hh" => Checked::Code(&CPP),
+        "java" => Checked::Code(&JAVA),
+        "cs" => Checked::Code(&C_SHARP),
+        "sh" | "bash" | "zsh" => Checked::Code(&BASH),
+        "css" => Checked::Code(&CSS),
+        "html" | "htm" => Checked::Code(&HTML),
+        "rb" => Checked::Code(&RUBY),
+        "php" => Checked::Code(&PHP),
+        "toml" => Checked::Code(&TOML),
+        "yaml" | "yml" => Checked::Code(&YAML),
+        "md" | "markdown" | "mdx" => Checked::Markdown,
+        "txt" | "text" | "rst" | "adoc" => Checked::Text,
+        _ => return None,
+    })
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum SpanKind {
+    Text,
+    Str,
+}
+
+// A byte range of the file whose words are checked.
+struct Span {
+    start: usize,
+    end: usize,
+    kind: SpanKind,
+}
+
+// The comments and string literals of source code, from its syntax tree.
+fn code_spans(src: &str, grammar: &Grammar) -> Result<Vec<Span>> {
+    let mut parser = Parser::new();
+    parser.set_language(&(grammar.language)()).context("load grammar")?;
+    let tree = parser.parse(src, None).ok_or_else(|| anyhow!("could not parse the file"))?;
+    let mut spans = vec![];
+    let mut cursor = tree.walk();
+    loop {
+        let node = cursor.node();
+        let kind = node.kind();
+        let descend = if kind.contains("comment") || grammar.text.contains(&kind) {
+            spans.push(Span { start: node.start_byte(), end: node.end_byte(), kind: SpanKind::Text });
+            false
+        } else if grammar.strings.contains(&kind) {
+            string_spans(node, &mut spans);
+            false
+        } else {
+            true
+        };
+        if descend && cursor.goto_first_child() {
+            continue;
+        }
+        while !cursor.goto_next_sibling() {
+            if !cursor.goto_parent() {
+                return Ok(spans);
+            }
+        }
+    }
+}
+
+// A string literal less the code in it, such as `${...}` or `#{...}` interpolations. Its other
+// parts are content, escapes and delimiters, whose kinds say so.
+fn string_spans(node: Node, spans: &mut Vec<Span>) {
+    const TEXT_PARTS: &[&str] = &["content", "fragment", "escape", "character", "_start", "_end"];
+    let mut start = node.start_byte();
+    let mut cursor = node.walk();
+    for child in node.named_children(&mut cursor) {
+        if !TEXT_PARTS.iter().any(|p| child.kind().contains(p)) {
+            spans.push(Span { start, end: child.start_byte(), kind: SpanKind::Str });
+            start = child.end_byte();
+        }
+    }
+    spans.push(Span { start, end: node.end_byte(), kind: SpanKind::Str });
+}
+
+// Markdown outside fenced code blocks and `inline code`.
+fn markdown_spans(src: &str) -> Vec<Span> {
+    let mut spans = vec![];
+    let mut fenced = false;
+    let mut at = 0;
+    for line in src.split_inclusive('\n') {
+        let trimmed = line.trim_start();
+        if trimmed.starts_with("```") || trimmed.starts_with("~~~") {
+            fenced = !fenced;
+        } else if !fenced {
+            let mut offset = at;
+            for (n, part) in line.split('`').enumerate() {
+                if n % 2 == 0 {
+                    spans.push(Span { start: offset, end: offset + part.len(), kind: SpanKind::Text });
+                }
+                offset += part.len() + 1;
+            }
+        }
+        at += line.len();
+    }
+    spans
+}
+
+// Runs of text that aren't words to check: URLs, e-mail addresses, paths and dotted or `::`
+// references such as `fs::read` or `config.json`.
+static NOT_WORDS: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(r"[A-Za-z][\w+.-]*://\S+|[\w.+-]+@[\w-]+(?:\.[\w-]+)+|\S*/\S*|[A-Za-z]:\\\S*|\w+(?:(?:\.|::)\w+)+")
+        .expect("valid non-word regex")
+});
+// In string literals, escapes such as `\n` would otherwise glue a letter onto the next word.
+static ESCAPE: Lazy<Regex> = Lazy::new(|| Regex::new(r"\\[A-Za-z]").expect("valid escape regex"));
+static TOKEN: Lazy<Regex> = Lazy::new(|| Regex::new(r"[\p{L}\p{N}_'\u{2019}]+").expect("valid word regex"));
+
+// `parseHTTPResponse` into `parse`, `HTTP` and `Response`; `snake_case` at the underscores.
+fn split_identifier(token: &str) -> Vec<(usize, &str)> {
+    let mut parts = vec![];
+    let mut offset = 0;
+    for piece in token.split('_') {
+        let chars: Vec<(usize, char)> = piece.char_indices().collect();
+        let mut start = 0;
+        for k in 1..chars.len() {
+            let (i, c) = chars[k];
+            let prev = chars[k - 1].1;
+            let next_lower = chars.get(k + 1).is_some_and(|(_, n)| n.is_lowercase());
+            if c.is_uppercase() && (prev.is_lowercase() || (prev.is_uppercase() && next_lower)) {
+                parts.push((offset + start, &piece[start..i]));
+                start = i;
+            }
+        }
+        parts.push((offset + start, &piece[start..]));
+        offset += piece.len() + 1;
+    }
+    parts
+}
+
+// The words of `text` worth checking, with their byte offsets in it.
+fn words(text: &str, kind: SpanKind) -> Vec<(usize, &str)> {
+    // A string without spaces is a key, path or identifier rather than a sentence.
+    if kind == SpanKind::Str && !text.trim().contains(char::is_whitespace) {
+        return vec![];
+    }
+    let skipped: Vec<(usize, usize)> = NOT_WORDS.find_iter(text).map(|m| (m.start(), m.end())).collect();
+    let escaped: HashSet<usize> = match kind {
+        SpanKind::Str => ESCAPE.find_iter(text).map(|m| m.start() + 1).collect(),
+        SpanKind::Text => HashSet::new(),
+    };
+    let mut out = vec![];
+    for m in TOKEN.find_iter(text) {
+        if skipped.iter().any(|&(a, b)| m.start() < b && a < m.end()) {
+            continue;
+        }
+        let start = if escaped.contains(&m.start()) { m.start() + 1 } else { m.start() };
+        let token = &text[start..m.end()];
+        if token.contains(|c: char| c.is_numeric()) {
+            continue;
+        }
+        for (offset, part) in split_identifier(token) {
+            let trimmed = part.trim_start_matches(['\'', '\u{2019}']);
+            let lead = part.len() - trimmed.len();
+            let word = trimmed.trim_end_matches(['\'', '\u{2019}']);
+            // All capitals are acronyms and constants more often than shouting.
+            if word.chars().count() < MIN_WORD_CHARS || case_of(word) == Case::Upper {
+                continue;
+            }
+            out.push((start + offset + lead, word));
+        }
+    }
+    out
+}
+
+fn utf16_len(s: &str) -> u32 {
+    s.encode_utf16().count() as u32
+}
+
+/// Misspelled words in the comments and string literals of a source file, or in the text of
+/// a Markdown or plain-text file. `content` stands in for the file when given, e.g. an unsaved
+/// buffer. Comments and strings come from a tree-sitter parse with the grammar the extension
+/// names, leaving out code interpolated into strings. Strings without spaces, identifiers'
+/// parts shorter than three letters, all-capital words, words with digits, URLs and paths
+/// aren't checked. Words are looked up in the Hunspell
+/// dictionary named by the `spellcheck_language` setting, then in the root's
+/// `.pompora/dictionary.txt`.
+pub fn spellcheck_file(rel_path: &str, content: Option<String>) -> Result<SpellcheckResult> {
+    let (root, _, abs) = fsops::resolve(rel_path, false)?;
+    let name = abs.file_name().map(|n| n.to_string_lossy().to_string()).unwrap_or_default();
+    let checked = checked_for(&name).ok_or_else(|| anyhow!("spell checking has no grammar for {name}"))?;
+    let src = match content {
+        Some(c) => c,
+        None => fsops::workspace_read_file(rel_path)?.file.content,
+    };
+    let language = settings::load()?.spellcheck_language;
+    let dict = dictionary(&language)?;
+    let custom = custom_words(&root);
+
+    let spans = match checked {
+        Checked::Code(grammar) => code_spans(&src, grammar)?,
+        Checked::Markdown => markdown_spans(&src),
+        Checked::Text => vec![Span { start: 0, end: src.len(), kind: SpanKind::Text }],
+    };
+    let line_starts: Vec<usize> = std::iter::once(0).chain(src.match_indices('\n').map(|(i, _)| i + 1)).collect();
+    let mut verdicts: HashMap<&str, Option<Vec<String>>> = HashMap::new();
+    let mut misspellings = vec![];
+    let mut truncated = false;
+    'spans: for span in &spans {
+        for (offset, word) in words(&src[span.start..span.end], span.kind) {
+            let verdict = verdicts.entry(word).or_insert_with(|| {
+                let normal = normalize(word);
+                let known = custom.contains(&normal.to_lowercase()) || dict.check(&normal);
+                (!known).then(|| dict.suggest(&normal))
+            });
+            let Some(suggestions) = verdict else { continue };
+            if misspellings.len() == MAX_MISSPELLINGS {
+                truncated = true;
+                break 'spans;
+            }
+            let at = span.start + offset;
+            let line = line_starts.partition_point(|&s| s <= at) - 1;
+            let column = utf16_len(&src[line_starts[line]..at]) + 1;
+            misspellings.push(Misspelling {
+                word: word.to_string(),
+                line: line as u32 + 1,
+                column,
+                end_column: column + utf16_len(word),
+                suggestions: suggestions.clone(),
+            });
+        }
+    }
+
+    Ok(SpellcheckResult {
+        path: rel_path.to_string(),
+        language,
+        dictionary: dict.path.display().to_string(),
+        custom_dictionary: root.workspace_path(CUSTOM_DICTIONARY),
+        misspellings,
+        truncated,
+    })
+}
+
+/// Adds `word` to the `.pompora/dictionary.txt` of the root `rel_path` is in, creating it if
+/// needed, and returns the dictionary's workspace path. Words already there are left alone.
+pub fn spellcheck_add_word(rel_path: &str, word: &str) -> Result<String> {
+    let word = word.trim();
+    if word.is_empty() || word.contains(char::is_whitespace) {
+        return Err(anyhow!("a dictionary word is a single word without spaces"));
+    }
+    let (root, _, _) = fsops::resolve(rel_path, true)?;
+    let dest = root.workspace_path(CUSTOM_DICTIONARY);
+    if custom_words(&root).contains(&normalize(word).to_lowercase()) {
+        return Ok(dest);
+    }
+    let mut text = fs::read_to_string(root.path.join(CUSTOM_DICTIONARY)).unwrap_or_default();
+    if !text.is_empty() && !text.ends_with('\n') {
+        text.push('\n');
+    }
+    text.push_str(word);
+    text.push('\n');
+    fsops::workspace_write_file(&dest, &text, None, false, false)?;
+    Ok(dest)
+}
@@ -0,0 +1,525 @@
+use anyhow::{anyhow, Context, Result};
+use once_cell::sync::Lazy;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fs;
+use std::io::{BufRead, BufReader, Read};
+use std::process::{Child, Command, Stdio};
+use std::sync::mpsc;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use tauri::{AppHandle, Emitter};
+
+use super::problem_matchers::{Matcher, Reporter};
+use super::process_tree::{ProcessTree, TERMINATE_TIMEOUT};
+use super::workspace::{self, OpenRoot};
+use super::notifications::{self, Category};
+use super::{dotenv, projects, settings, wsl};
+
+pub const TASK_STARTED_EVENT: &str = "task:started";
+pub const TASK_OUTPUT_EVENT: &str = "task:output";
+pub const TASK_FINISHED_EVENT: &str = "task:finished";
+
+// Task definitions committed alongside the code, relative to each workspace root.
+const TASKS_FILE: &str = ".pompora/tasks.json";
+
+// How often a run is checked for exit, and how long its last output may trail the exit.
+const POLL_INTERVAL: Duration = Duration::from_millis(100);
+const OUTPUT_GRACE: Duration = Duration::from_millis(500);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum TaskSource {
+    /// Defined in `.pompora/tasks.json`.
+    Workspace,
+    /// A `scripts` entry in `package.json`.
+    Npm,
+    /// A standard `cargo` subcommand for a `Cargo.toml` crate.
+    Cargo,
+}
+
+/// One entry of `.pompora/tasks.json`:
+///
+/// ```json
+/// { "tasks": [{ "label": "build", "command": "cargo build --release", "cwd": "app", "group": "build" }] }
+/// ```
+///
+/// Without `args`, `command` runs through the platform shell; with them, `command` is started
+/// directly and gets `args` as given.
+#[derive(Debug, Clone, Deserialize)]
+pub struct TaskDefinition {
+    pub label: String,
+    pub command: String,
+    #[serde(default)]
+    pub args: Vec<String>,
+    /// Folder to run in, relative to the workspace root.
+    #[serde(default)]
+    pub cwd: Option<String>,
+    #[serde(default)]
+    pub env: HashMap<String, String>,
+    /// Env file to load, relative to the workspace root, instead of the `env_file` setting's;
+    /// `env` overrides its variables.
+    #[serde(default)]
+    pub env_file: Option<String>,
+    /// Free-form grouping for the UI, e.g. `build` or `test`.
+    #[serde(default)]
+    pub group: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct TasksFile {
+    #[serde(default)]
+    tasks: Vec<TaskDefinition>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct TaskInfo {
+    /// Stable for as long as the definition doesn't change; what `tasks_run` takes.
+    pub id: String,
+    pub label: String,
+    pub source: TaskSource,
+    /// The command line as it would be typed.
+    pub command: String,
+    /// Workspace path of the folder it runs in.
+    pub cwd: String,
+    pub group: Option<String>,
+    /// Id of the run in progress, if the task is running.
+    pub running: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct TaskStartedEvent {
+    pub run_id: String,
+    pub task_id: String,
+    pub label: String,
+    pub command: String,
+}
+
+#[derive(Debug, Clone, Copy, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum TaskStream {
+    Stdout,
+    Stderr,
+}
+
+/// One line of a run's output, without its line ending.
+#[derive(Debug, Clone, Serialize)]
+pub struct TaskOutputEvent {
+    pub run_id: String,
+    pub task_id: String,
+    pub stream: TaskStream,
+    pub line: String,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct TaskFinishedEvent {
+    pub run_id: String,
+    pub task_id: String,
+    /// `None` if the process was killed by a signal or its status couldn't be read.
+    pub exit_code: Option<i32>,
+    pub success: bool,
+    /// Whether `tasks_stop` ended it.
+    pub stopped: bool,
+    pub duration_ms: u64,
+}
+
+// A task resolved to something that can be started.
+struct Task {
+    info: TaskInfo,
+    def: TaskDefinition,
+    root: OpenRoot,
+}
+
+struct Run {
+    task_id: String,
+    child: Arc<Mutex<Child>>,
+    tree: Arc<ProcessTree>,
+    stopped: Arc<Mutex<bool>>,
+}
+
+static RUNS: Lazy<Mutex<HashMap<String, Run>>> = Lazy::new(|| Mutex::new(HashMap::new()));
+
+fn task_id(root: &OpenRoot, source: TaskSource, label: &str) -> String {
+    let source = match source {
+        TaskSource::Workspace => "workspace",
+        TaskSource::Npm => "npm",
+        TaskSource::Cargo => "cargo",
+    };
+    match &root.prefix {
+        Some(p) => format!("{p}:{source}:{label}"),
+        None => format!("{source}:{label}"),
+    }
+}
+
+fn quote_arg(a: &str) -> String {
+    if !a.is_empty() && a.chars().all(|c| c.is_ascii_alphanumeric() || "-_./=:@+,".contains(c)) {
+        a.to_string()
+    } else {
+        format!("\"{}\"", a.replace('"', "\\\""))
+    }
+}
+
+fn command_line(def: &TaskDefinition) -> String {
+    std::iter::once(def.command.clone())
+        .chain(def.args.iter().map(|a| quote_arg(a)))
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+fn workspace_tasks(root: &OpenRoot) -> Result<Vec<TaskDefinition>> {
+    let path = root.path.join(TASKS_FILE);
+    if !path.is_file() {
+        return Ok(vec![]);
+    }
+    let raw = fs::read_to_string(&path).with_context(|| format!("read {}", path.display()))?;
+    let file: TasksFile = serde_json::from_str(&raw).with_context(|| format!("parse {}", root.workspace_path(TASKS_FILE)))?;
+    Ok(file.tasks.into_iter().filter(|t| !t.label.trim().is_empty() && !t.command.trim().is_empty()).collect())
+}
+
+// Path of a detected project relative to its root; project paths are workspace paths.
+fn root_relative<'a>(root: &OpenRoot, path: &'a str) -> &'a str {
+    match &root.prefix {
+        Some(p) if path == p => "",
+        Some(p) => path.strip_prefix(p.as_str()).map_or(path, |r| r.trim_start_matches('/')),
+        None => path,
+    }
+}
+
+fn detected_task(label: String, command: String, rel: &str, group: Option<&str>) -> TaskDefinition {
+    TaskDefinition {
+        // Tasks of a project below the root say which one they belong to.
+        label: if rel.is_empty() { label } else { format!("{label} ({rel})") },
+        command,
+        args: vec![],
+        cwd: (!rel.is_empty()).then(|| rel.to_string()),
+        env: HashMap::new(),
+        env_file: None,
+        group: group.map(str::to_string),
+    }
+}
+
+// `package.json` scripts and the usual `cargo` commands of the Node and Rust projects
+// `projects` finds in the root.
+fn detected_tasks(root: &OpenRoot) -> Vec<(TaskSource, TaskDefinition)> {
+    let mut out = vec![];
+    for project in projects::detect_in(root) {
+        let rel = root_relative(root, &project.path);
+        let dir = root.path.join(rel);
+        match project.kind.as_str() {
+            "node" => {
+                let Ok(raw) = fs::read_to_string(dir.join("package.json")) else {
+                    continue;
+                };
+                let Ok(pkg) = serde_json::from_str::<serde_json::Value>(&raw) else {
+                    continue;
+                };
+                let Some(scripts) = pkg.get("scripts").and_then(|s| s.as_object()) else {
+                    continue;
+                };
+                let pm = projects::package_manager(&dir);
+                for name in scripts.keys() {
+                    let group = matches!(name.as_str(), "build" | "test").then_some(name.as_str());
+                    let command = format!("{pm} run {}", quote_arg(name));
+                    out.push((TaskSource::Npm, detected_task(format!("{pm}: {name}"), command, rel, group)));
+                }
+            }
+            "rust" => {
+                let has_binary = project.entry_points.iter().any(|e| !e.ends_with("lib.rs"));
+                for sub in ["build", "check", "test", "clippy", "run"] {
+                    if sub == "run" && !has_binary {
+                        continue;
+                    }
+                    let group = match sub {
+                        "build" | "check" | "clippy" => Some("build"),
+                        "test" => Some("test"),
+                        _ => None,
+                    };
+                    let command = format!("cargo {sub}");
+                    out.push((TaskSource::Cargo, detected_task(command.clone(), command, rel, group)));
+                }
+            }
+            _ => {}
+        }
+    }
+    out
+}
+
+/// Run id, task id and process id of every running task.
+pub fn running_pids() -> Vec<(String, String, u32)> {
+    let runs = RUNS.lock().unwrap_or_else(|e| e.into_inner());
+    runs.iter()
+        .map(|(id, r)| (id.clone(), r.task_id.clone(), r.tree.pid()))
+        .collect()
+}
+
+fn running_task(task_id: &str) -> Option<String> {
+    let runs = RUNS.lock().ok()?;
+    runs.iter().find(|(_, r)| r.task_id == task_id).map(|(id, _)| id.clone())
+}
+
+fn collect() -> Result<Vec<Task>> {
+    let mut out = vec![];
+    for root in workspace::open_roots()? {
+        let defined = workspace_tasks(&root)?;
+        // Tasks from tasks.json come first, and win over detected ones with the same label.
+        let detected: Vec<_> = detected_tasks(&root)
+            .into_iter()
+            .filter(|(_, d)| !defined.iter().any(|t| t.label == d.label))
+            .collect();
+        for (source, def) in defined.into_iter().map(|d| (TaskSource::Workspace, d)).chain(detected) {
+            let id = task_id(&root, source, &def.label);
+            let cwd = def.cwd.as_deref().map(|c| c.trim().trim_matches(['/', '\\'])).unwrap_or("");
+            out.push(Task {
+                info: TaskInfo {
+                    running: running_task(&id),
+                    id,
+                    label: def.label.clone(),
+                    source,
+                    command: command_line(&def),
+                    cwd: root.workspace_path(cwd),
+                    group: def.group.clone(),
+                },
+                def,
+                root: root.clone(),
+            });
+        }
+    }
+    Ok(out)
+}
+
+/// Tasks defined in each root's `.pompora/tasks.json`, followed by the `package.json` scripts
+/// and `cargo` commands detected there.
+pub fn tasks_list() -> Result<Vec<TaskInfo>> {
+    Ok(collect()?.into_iter().map(|t| t.info).collect())
+}
+
+fn spawn_reader(
+    app: AppHandle,
+    run_id: String,
+    task_id: String,
+    stream: TaskStream,
+    pipe: impl Read + Send + 'static,
+    reporter: Arc<Mutex<Reporter>>,
+    done: mpsc::Sender<()>,
+) {
+    std::thread::spawn(move || {
+        let mut reader = BufReader::new(pipe);
+        let mut matcher = Matcher::default();
+        let mut buf = Vec::new();
+        loop {
+            buf.clear();
+            match reader.read_until(b'\n', &mut buf) {
+                Ok(0) | Err(_) => break,
+                Ok(_) => {
+                    let line = String::from_utf8_lossy(&buf);
+                    reporter.lock().unwrap_or_else(|e| e.into_inner()).feed_line(&mut matcher, &line);
+                    let _ = app.emit(
+                        TASK_OUTPUT_EVENT,
+                        TaskOutputEvent {
+                            run_id: run_id.clone(),
+                            task_id: task_id.clone(),
+                            stream,
+                            line: line.trim_end_matches(['\r', '\n']).to_string(),
+                        },
+                    );
+                }
+            }
+        }
+        let _ = done.send(());
+    });
+}
+
+/// Starts the task `id` and returns the id of the run. Output arrives as `task:output` events,
+/// one per line, and `task:finished` follows once the process exits. Problems recognized in the
+/// output are reported as `diagnostics:report` events for the source `task:<id>`.
+pub fn tasks_run(app: AppHandle, id: &str) -> Result<String> {
+    workspace::ensure_trusted("Running tasks")?;
+    let task = collect()?
+        .into_iter()
+        .find(|t| t.info.id == id)
+        .ok_or_else(|| anyhow!("Unknown task: {id}"))?;
+    if task.info.running.is_some() {
+        return Err(anyhow!("{} is already running", task.info.label));
+    }
+
+    let rel = task.def.cwd.as_deref().map(|c| c.trim()).filter(|c| !c.is_empty());
+    let dir = match rel {
+        Some(rel) => task.root.path.join(rel),
+        None => task.root.path.clone(),
+    };
+    if !dir.is_dir() {
+        return Err(anyhow!("Task folder does not exist: {}", task.info.cwd));
+    }
+    workspace::ensure_contained(&task.root.path, &dir)?;
+
+    let mut env: HashMap<String, String> = match task.def.env_file.as_deref().map(str::trim).filter(|f| !f.is_empty()) {
+        Some(file) => {
+            let path = task.root.path.join(file);
+            workspace::ensure_contained(&task.root.path, &path)?;
+            dotenv::load(&path)?.into_iter().collect()
+        }
+        None => dotenv::default_vars().into_iter().collect(),
+    };
+    env.extend(task.def.env.clone());
+
+    let use_wsl = cfg!(windows) && settings::load().is_ok_and(|s| s.tasks_use_wsl);
+    let mut cmd = if use_wsl {
+        let (program, args) = if task.def.args.is_empty() {
+            ("sh".to_string(), vec!["-c".to_string(), task.def.command.clone()])
+        } else {
+            (task.def.command.clone(), task.def.args.clone())
+        };
+        // WSL only sees the Windows variables named in WSLENV, so the task's go through `env`.
+        let mut c = Command::new("wsl.exe");
+        c.args(wsl::exec_args(&dir.to_string_lossy(), &env, &program, &args));
+        c
+    } else if task.def.args.is_empty() {
+        let mut c = if cfg!(windows) {
+            let mut c = Command::new("cmd");
+            c.arg("/C");
+            c
+        } else {
+            let mut c = Command::new("sh");
+            c.arg("-c");
+            c
+        };
+        c.arg(&task.def.command);
+        c
+    } else {
+        let mut c = Command::new(&task.def.command);
+        c.args(&task.def.args);
+        c
+    };
+    cmd.current_dir(&dir)
+        .envs(&env)
+        .stdin(Stdio::null())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped());
+    #[cfg(windows)]
+    {
+        use std::os::windows::process::CommandExt;
+        const CREATE_NO_WINDOW: u32 = 0x0800_0000;
+        cmd.creation_flags(CREATE_NO_WINDOW);
+    }
+    // A group of its own, so stopping it reaches whatever it starts.
+    #[cfg(unix)]
+    {
+        use std::os::unix::process::CommandExt;
+        cmd.process_group(0);
+    }
+    let mut child = cmd.spawn().with_context(|| format!("start {}", task.info.command))?;
+    let tree = Arc::new(ProcessTree::new(child.id()));
+
+    let run_id = format!("task-{}", rand::random::<u64>());
+    let task_id = task.info.id.clone();
+    // Problems from the task's previous run go away as this one starts.
+    let reporter = Arc::new(Mutex::new(Reporter::new(app.clone(), format!("task:{task_id}"), dir.clone())));
+    reporter.lock().unwrap_or_else(|e| e.into_inner()).clear();
+    let (done_tx, done_rx) = mpsc::channel();
+    let mut readers = 0;
+    if let Some(out) = child.stdout.take() {
+        let r = reporter.clone();
+        spawn_reader(app.clone(), run_id.clone(), task_id.clone(), TaskStream::Stdout, out, r, done_tx.clone());
+        readers += 1;
+    }
+    if let Some(err) = child.stderr.take() {
+        let r = reporter.clone();
+        spawn_reader(app.clone(), run_id.clone(), task_id.clone(), TaskStream::Stderr, err, r, done_tx);
+        readers += 1;
+    }
+
+    let child = Arc::new(Mutex::new(child));
+    let stopped = Arc::new(Mutex::new(false));
+    RUNS.lock().map_err(|_| anyhow!("task runs lock poisoned"))?.insert(
+        run_id.clone(),
+        Run {
+            task_id: task_id.clone(),
+            child: child.clone(),
+            tree,
+            stopped: stopped.clone(),
+        },
+    );
+    let _ = app.emit(
+        TASK_STARTED_EVENT,
+        TaskStartedEvent {
+            run_id: run_id.clone(),
+            task_id: task_id.clone(),
+            label: task.info.label.clone(),
+            command: task.info.command.clone(),
+        },
+    );
+
+    let started = Instant::now();
+    let run_id2 = run_id.clone();
+    let label = task.info.label.clone();
+    std::thread::spawn(move || {
+        let status = loop {
+            let res = child.lock().unwrap_or_else(|e| e.into_inner()).try_wait();
+            match res {
+                Ok(Some(status)) => break Ok(status),
+                Ok(None) => {
+                    std::thread::sleep(POLL_INTERVAL);
+                    // Whatever the readers found since the last throttled report.
+                    reporter.lock().unwrap_or_else(|e| e.into_inner()).flush();
+                }
+                Err(e) => break Err(e),
+            }
+        };
+        // Let the readers drain what the process wrote last, unless something it started still
+        // holds the pipes open.
+        let deadline = Instant::now() + OUTPUT_GRACE;
+        for _ in 0..readers {
+            let left = deadline.saturating_duration_since(Instant::now());
+            if done_rx.recv_timeout(left).is_err() {
+                break;
+            }
+        }
+        reporter.lock().unwrap_or_else(|e| e.into_inner()).flush();
+        if let Ok(mut runs) = RUNS.lock() {
+            runs.remove(&run_id2);
+        }
+        let status = status.ok();
+        let finished = TaskFinishedEvent {
+            run_id: run_id2,
+            task_id,
+            exit_code: status.and_then(|s| s.code()),
+            success: status.is_some_and(|s| s.success()),
+            stopped: *stopped.lock().unwrap_or_else(|e| e.into_inner()),
+            duration_ms: started.elapsed().as_millis() as u64,
+        };
+        if !finished.stopped {
+            let (title, outcome) = if finished.success { ("Task finished", "succeeded") } else { ("Task failed", "failed") };
+            let secs = finished.duration_ms as f64 / 1000.0;
+            notifications::notify(Category::Tasks, title, &format!("{label} {outcome} after {secs:.1}s"));
+        }
+        let _ = app.emit(TASK_FINISHED_EVENT, finished);
+    });
+
+    Ok(run_id)
+}
+
+/// Stops a run started by `tasks_run` and everything it started. They are asked to exit first
+/// and killed if still running after `TERMINATE_TIMEOUT`; `force` kills them straight away, as
+/// does Windows, where console programs without a console can't be asked. Returns without
+/// waiting; `task:finished` follows. Stopping a run that already finished is not an error.
+pub fn tasks_stop(run_id: &str, force: bool) -> Result<()> {
+    let runs = RUNS.lock().map_err(|_| anyhow!("task runs lock poisoned"))?;
+    let Some(run) = runs.get(run_id) else {
+        return Ok(());
+    };
+    *run.stopped.lock().unwrap_or_else(|e| e.into_inner()) = true;
+    let tree = run.tree.clone();
+    if force || cfg!(windows) {
+        tree.kill();
+        return Ok(());
+    }
+    let child = run.child.clone();
+    std::thread::spawn(move || {
+        // `try_wait` keeps answering once the waiting thread has collected the exit status.
+        tree.stop(TERMINATE_TIMEOUT, || {
+            let res = child.lock().unwrap_or_else(|e| e.into_inner()).try_wait();
+            !matches!(res, Ok(None))
+        });
+    });
+    Ok(())
+}
@@ -0,0 +1,215 @@
+use anyhow::{anyhow, Context, Result};
+use once_cell::sync::Lazy;
+use serde::Serialize;
+use std::collections::BTreeMap;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Mutex;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use tauri::Listener;
+
+use super::redact::redact_error;
+use super::{auth, http, settings};
+
+// How often a batch is sent.
+const FLUSH_INTERVAL: Duration = Duration::from_secs(60 * 60);
+// Upper bounds of the latency buckets; slower ones land in a last, open-ended bucket.
+const BUCKET_BOUNDS_MS: [u64; 9] = [10, 50, 100, 250, 500, 1000, 2500, 5000, 10_000];
+
+/// Counts of slow and fast runs of one operation.
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct LatencyHistogram {
+    /// One count per bucket in `bucket_bounds_ms`, plus one for anything slower.
+    pub counts: Vec<u64>,
+    pub total_ms: u64,
+}
+
+/// Everything sent in one go. It only holds counts, never paths, file contents, prompts or
+/// account details.
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct TelemetryBatch {
+    pub app_version: String,
+    pub os: String,
+    pub arch: String,
+    /// Time covered, in ms since the Unix epoch.
+    pub period_start: u64,
+    pub period_end: u64,
+    /// Invocations per command name.
+    pub commands: BTreeMap<String, u64>,
+    /// Failures per category, like `ai:rate_limit`.
+    pub errors: BTreeMap<String, u64>,
+    pub bucket_bounds_ms: Vec<u64>,
+    /// Per operation, like `ai:openai` or `workspace_search`.
+    pub latency: BTreeMap<String, LatencyHistogram>,
+}
+
+static ENABLED: AtomicBool = AtomicBool::new(false);
+static PENDING: Lazy<Mutex<TelemetryBatch>> = Lazy::new(|| Mutex::new(TelemetryBatch::default()));
+
+fn now_ms() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_millis() as u64)
+        .unwrap_or(0)
+}
+
+/// Starts or stops collecting. Whatever wasn't sent yet is dropped when it stops.
+pub fn set_enabled(enabled: bool) {
+    let was_enabled = ENABLED.swap(enabled, Ordering::Relaxed);
+    if was_enabled && !enabled {
+        *PENDING.lock().unwrap_or_else(|e| e.into_inner()) = TelemetryBatch::default();
+    }
+}
+
+// Runs `f` on the batch being collected, if collecting.
+fn with_pending(f: impl FnOnce(&mut TelemetryBatch)) {
+    if !ENABLED.load(Ordering::Relaxed) {
+        return;
+    }
+    let mut pending = PENDING.lock().unwrap_or_else(|e| e.into_inner());
+    if pending.period_start == 0 {
+        pending.period_start = now_ms();
+    }
+    f(&mut pending);
+}
+
+pub fn record_command(name: &str) {
+    with_pending(|b| *b.commands.entry(name.to_string()).or_default() += 1);
+}
+
+pub fn record_error(category: &str) {
+    with_pending(|b| *b.errors.entry(category.to_string()).or_default() += 1);
+}
+
+pub fn record_latency(operation: &str, took: Duration) {
+    let ms = took.as_millis() as u64;
+    with_pending(|b| {
+        let h = b.latency.entry(operation.to_string()).or_default();
+        if h.counts.is_empty() {
+            h.counts = vec![0; BUCKET_BOUNDS_MS.len() + 1];
+        }
+        let bucket = BUCKET_BOUNDS_MS.iter().position(|&bound| ms <= bound).unwrap_or(BUCKET_BOUNDS_MS.len());
+        h.counts[bucket] += 1;
+        h.total_ms += ms;
+    });
+}
+
+/// Rough kind of an error message, without any of its details.
+pub fn error_category(message: &str) -> &'static str {
+    let m = message.to_lowercase();
+    if m.contains("status 401") || m.contains("status 403") {
+        "auth"
+    } else if m.contains("status 429") {
+        "rate_limit"
+    } else if m.contains("status 5") {
+        "server"
+    } else if m.contains("timed out") || m.contains("timeout") {
+        "timeout"
+    } else if m.contains("error sending request") || m.contains("connect") {
+        "network"
+    } else {
+        "other"
+    }
+}
+
+// `batch` with what is the same for every batch filled in.
+fn finish(mut batch: TelemetryBatch) -> TelemetryBatch {
+    batch.app_version = env!("CARGO_PKG_VERSION").to_string();
+    batch.os = std::env::consts::OS.to_string();
+    batch.arch = std::env::consts::ARCH.to_string();
+    batch.period_end = now_ms();
+    batch.bucket_bounds_ms = BUCKET_BOUNDS_MS.to_vec();
+    batch
+}
+
+/// Exactly what the next send would contain, without sending it.
+pub fn telemetry_preview() -> TelemetryBatch {
+    finish(PENDING.lock().unwrap_or_else(|e| e.into_inner()).clone())
+}
+
+// `telemetry.endpoint`, or the backend's. Only `https://`, or `http://` to this machine.
+fn endpoint() -> Result<String> {
+    let s = settings::load()?;
+    let Some(url) = s.telemetry.endpoint.as_deref().map(str::trim).filter(|v| !v.is_empty()) else {
+        return Ok(format!("{}/api/telemetry", auth::backend_url()?));
+    };
+    http::parse_secure_url(url, "telemetry endpoint")?;
+    Ok(url.to_string())
+}
+
+// Sends what was collected; on failure, or while offline, it is kept to go with the next batch.
+async fn flush() -> Result<()> {
+    if settings::load()?.offline_mode {
+        return Ok(());
+    }
+    let batch = std::mem::take(&mut *PENDING.lock().unwrap_or_else(|e| e.into_inner()));
+    if batch.commands.is_empty() && batch.errors.is_empty() && batch.latency.is_empty() {
+        return Ok(());
+    }
+    let batch = finish(batch);
+    let res = send(&batch).await;
+    if res.is_err() {
+        with_pending(|pending| merge(pending, batch));
+    }
+    res
+}
+
+async fn send(batch: &TelemetryBatch) -> Result<()> {
+    let url = endpoint()?;
+    let res = http::client()?
+        .post(&url)
+        .json(batch)
+        .send()
+        .await
+        .map_err(|e| anyhow!(redact_error(e)))
+        .context("telemetry request")?;
+    if !res.status().is_success() {
+        return Err(anyhow!("telemetry request failed (status {})", res.status()));
+    }
+    Ok(())
+}
+
+fn merge(into: &mut TelemetryBatch, from: TelemetryBatch) {
+    if from.period_start != 0 && (into.period_start == 0 || from.period_start < into.period_start) {
+        into.period_start = from.period_start;
+    }
+    for (k, v) in from.commands {
+        *into.commands.entry(k).or_default() += v;
+    }
+    for (k, v) in from.errors {
+        *into.errors.entry(k).or_default() += v;
+    }
+    for (k, h) in from.latency {
+        let into = into.latency.entry(k).or_default();
+        if into.counts.is_empty() {
+            into.counts = vec![0; h.counts.len()];
+        }
+        for (a, b) in into.counts.iter_mut().zip(h.counts) {
+            *a += b;
+        }
+        into.total_ms += h.total_ms;
+    }
+}
+
+/// Collects when the settings allow it, following changes to them, and sends a batch every
+/// hour. Called once from app setup.
+pub fn start(app: &tauri::AppHandle) {
+    if let Ok(s) = settings::load() {
+        set_enabled(s.telemetry.enabled);
+    }
+    app.listen_any(settings::SETTINGS_CHANGED_EVENT, |event| {
+        if !settings::changed_keys_in(event.payload()).iter().any(|k| k == "telemetry") {
+            return;
+        }
+        if let Ok(s) = settings::load() {
+            set_enabled(s.telemetry.enabled);
+        }
+    });
+    tauri::async_runtime::spawn(async {
+        loop {
+            tokio::time::sleep(FLUSH_INTERVAL).await;
+            if let Err(e) = flush().await {
+                tracing::debug!("telemetry not sent: {e}");
+            }
+        }
+    });
+}
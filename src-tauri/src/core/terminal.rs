@@ -1,21 +1,367 @@
 use portable_pty::{native_pty_system, CommandBuilder, PtySize};
-use serde::Serialize;
-use std::collections::HashMap;
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, VecDeque};
 use std::io::{Read, Write};
 use std::path::PathBuf;
-use std::sync::{Arc, Mutex};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Condvar, Mutex};
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
 use tauri::{AppHandle, Emitter};
 
+use super::problem_matchers::{LineSplitter, Matcher, Reporter};
+use super::process_tree::{ProcessTree, TERMINATE_TIMEOUT};
+use super::settings::{self, AppSettings, TerminalProfile};
+use super::{dotenv, shell_integration, terminal_history, workspace, wsl};
+
 #[derive(Clone, Serialize)]
 pub struct TerminalDataEvent {
     pub id: String,
     pub data: String,
 }
 
+/// Payload of `terminal:cwd`, sent when the shell reports a new working directory.
+#[derive(Clone, Serialize)]
+pub struct TerminalCwdEvent {
+    pub id: String,
+    pub cwd: String,
+}
+
+/// Payload of `terminal:title`, sent when the shell or a program in it sets the title (OSC 0 or 2).
+#[derive(Clone, Serialize)]
+pub struct TerminalTitleEvent {
+    pub id: String,
+    pub title: String,
+}
+
+/// Payload of `terminal:activity`, sent when a session has had no output for `IDLE_AFTER` and
+/// again when output resumes.
+#[derive(Clone, Serialize)]
+pub struct TerminalActivityEvent {
+    pub id: String,
+    pub active: bool,
+    /// How long the session was in the state it is leaving, in ms.
+    pub previous_ms: u64,
+}
+
+/// A command run at a shell prompt, as reported by shell integration.
+#[derive(Clone, Serialize)]
+pub struct TerminalCommand {
+    /// Increases with each command in a session.
+    pub seq: u64,
+    /// The command line, if the shell reported it.
+    pub command: Option<String>,
+    pub cwd: Option<String>,
+    pub started_at: u64,
+    /// `None` while the command is still running.
+    pub finished_at: Option<u64>,
+    pub exit_code: Option<i32>,
+    // Stream offsets of the command's output, as counted by `Scrollback::start`.
+    #[serde(skip)]
+    output_start: u64,
+    #[serde(skip)]
+    output_end: Option<u64>,
+}
+
+/// Payload of `terminal:command`, sent when a command starts and again when it finishes.
+#[derive(Clone, Serialize)]
+pub struct TerminalCommandEvent {
+    pub id: String,
+    pub command: TerminalCommand,
+}
+
+/// What is remembered about an open session for the next time the app starts.
+#[derive(Clone, Serialize, Deserialize)]
+pub struct SavedTerminalSession {
+    /// Profile it was started with; `None` for the default shell.
+    pub profile: Option<String>,
+    pub cwd: Option<String>,
+    /// Title the shell last set (OSC 0 or 2).
+    pub title: Option<String>,
+    pub started_at: u64,
+}
+
+/// A session of this run, as `terminal_list_sessions` reports it.
+#[derive(Clone, Serialize)]
+pub struct TerminalSessionInfo {
+    pub id: String,
+    pub profile: Option<String>,
+    pub cwd: Option<String>,
+    pub title: Option<String>,
+    pub started_at: u64,
+    /// Whether there has been output within the last `IDLE_AFTER`.
+    pub active: bool,
+    /// `None` while the shell is running.
+    pub exit: Option<TerminalExit>,
+}
+
+#[derive(Clone, Serialize)]
+pub struct TerminalSessionList {
+    /// Sessions of this run that haven't been killed, oldest first.
+    pub sessions: Vec<TerminalSessionInfo>,
+    /// Sessions that were open when the app last quit, until a new one is started.
+    pub previous: Vec<SavedTerminalSession>,
+}
+
+#[derive(Clone, Serialize)]
+pub struct TerminalSearchMatch {
+    /// Lines of output below the one with the hit, so a view can count up from its last line.
+    pub lines_from_end: u32,
+    /// 0-based column where the hit starts in `text`, in UTF-16 code units like xterm's.
+    pub column: u32,
+    /// Length of the hit in the same units.
+    pub match_len: u32,
+    /// The line with the hit, escape sequences removed.
+    pub text: String,
+}
+
+#[derive(Clone, Serialize)]
+pub struct TerminalSearchResults {
+    /// Hits oldest first; the most recent ones if there were too many.
+    pub matches: Vec<TerminalSearchMatch>,
+    /// Hits in the whole scrollback.
+    pub total: u32,
+    pub truncated: bool,
+}
+
+/// Payload of `terminal:exit`, and what `terminal_wait` returns.
+#[derive(Clone, Serialize)]
+pub struct TerminalExit {
+    pub id: String,
+    /// `None` if the process was killed by a signal or its status couldn't be read.
+    pub exit_code: Option<u32>,
+    /// Name of the signal that killed the process, e.g. `Killed` or `Segmentation fault`.
+    pub signal: Option<String>,
+    pub success: bool,
+    /// A sentence for the UI, e.g. "Exited with code 2".
+    pub reason: String,
+}
+
+fn describe_exit(id: &str, status: std::io::Result<portable_pty::ExitStatus>) -> TerminalExit {
+    let status = match status {
+        Ok(s) => s,
+        Err(e) => {
+            return TerminalExit {
+                id: id.to_string(),
+                exit_code: None,
+                signal: None,
+                success: false,
+                reason: format!("Exit status unavailable: {e}"),
+            }
+        }
+    };
+    // portable-pty only exposes the signal through its `Display` text.
+    let signal = status.to_string().strip_prefix("Terminated by ").map(str::to_string);
+    let reason = match (&signal, status.exit_code()) {
+        (Some(sig), _) => format!("Killed by signal: {sig}"),
+        (None, 0) => "Exited successfully".to_string(),
+        (None, code) => format!("Exited with code {code}"),
+    };
+    TerminalExit {
+        id: id.to_string(),
+        exit_code: signal.is_none().then(|| status.exit_code()),
+        signal,
+        success: status.success(),
+        reason,
+    }
+}
+
+// How long the exit event waits for the last output to be read, so it doesn't arrive ahead of
+// it. On Windows the output pipe can stay open after the shell has gone.
+const EXIT_OUTPUT_GRACE: Duration = Duration::from_millis(500);
+
+#[derive(Default)]
+struct ExitState {
+    exit: Option<TerminalExit>,
+    output_done: bool,
+}
+
+type SharedExit = Arc<(Mutex<ExitState>, Condvar)>;
+
+// `terminal:data` goes out at most this often per session; output arriving in between is sent
+// together with it. Output after a quiet spell goes out straight away.
+const DATA_EMIT_INTERVAL: Duration = Duration::from_millis(16);
+// Largest `terminal:data` payload, in bytes.
+const MAX_EVENT_BYTES: usize = 256 * 1024;
+// Output the frontend may have been sent but not yet acknowledged with `terminal_ack`, in UTF-16
+// code units as it counts them. Past this nothing more is sent until it catches up.
+const MAX_UNACKED: usize = 512 * 1024;
+// A view that stops acknowledging has most likely gone (reloaded, closed); what it missed is in
+// the scrollback, so sending resumes after this long.
+const ACK_TIMEOUT: Duration = Duration::from_secs(5);
+// Output read but not yet sent, in bytes. Past this the PTY isn't read until some is sent, which
+// in turn makes the program writing to it wait.
+const MAX_PENDING: usize = 1024 * 1024;
+// A session with no output for this long is reported idle with `terminal:activity`.
+const IDLE_AFTER: Duration = Duration::from_secs(3);
+
+#[derive(Default)]
+struct OutputState {
+    pending: String,
+    unacked: usize,
+    // While sending is held back: since when, or since the last acknowledgement.
+    waiting_since: Option<Instant>,
+    closed: bool,
+}
+
+// Output on its way from the PTY reader to the frontend.
+#[derive(Default)]
+struct Output {
+    state: Mutex<OutputState>,
+    cv: Condvar,
+    // Whether anything was sent within the last `IDLE_AFTER`.
+    active: AtomicBool,
+}
+
+enum Next {
+    Data(String),
+    // Nothing to send for the time asked.
+    Quiet,
+    Closed,
+}
+
+impl Output {
+    // Queues `s` to be sent, first waiting for room if the frontend is behind.
+    fn push(&self, s: &str) {
+        let mut st = self.state.lock().unwrap_or_else(|e| e.into_inner());
+        while st.pending.len() >= MAX_PENDING {
+            st = self.cv.wait(st).unwrap_or_else(|e| e.into_inner());
+        }
+        st.pending.push_str(s);
+        self.cv.notify_all();
+    }
+
+    fn close(&self) {
+        self.state.lock().unwrap_or_else(|e| e.into_inner()).closed = true;
+        self.cv.notify_all();
+    }
+
+    // The next payload to send, waiting until there is one and the frontend can take it, or
+    // `Quiet` if no output arrives within `quiet_after`. `Closed` once the reader has stopped and
+    // everything has been sent.
+    fn next(&self, quiet_after: Option<Duration>) -> Next {
+        let quiet_at = quiet_after.map(|d| Instant::now() + d);
+        let mut st = self.state.lock().unwrap_or_else(|e| e.into_inner());
+        loop {
+            if !st.pending.is_empty() {
+                if st.unacked < MAX_UNACKED {
+                    break;
+                }
+                let waited = st.waiting_since.get_or_insert_with(Instant::now).elapsed();
+                if waited >= ACK_TIMEOUT {
+                    st.unacked = 0;
+                    st.waiting_since = None;
+                    break;
+                }
+                st = self.cv.wait_timeout(st, ACK_TIMEOUT - waited).unwrap_or_else(|e| e.into_inner()).0;
+                continue;
+            }
+            if st.closed {
+                return Next::Closed;
+            }
+            st = match quiet_at {
+                Some(at) => {
+                    let left = at.saturating_duration_since(Instant::now());
+                    if left.is_zero() {
+                        return Next::Quiet;
+                    }
+                    self.cv.wait_timeout(st, left).unwrap_or_else(|e| e.into_inner()).0
+                }
+                None => self.cv.wait(st).unwrap_or_else(|e| e.into_inner()),
+            };
+        }
+        let chunk = if st.pending.len() <= MAX_EVENT_BYTES {
+            std::mem::take(&mut st.pending)
+        } else {
+            let mut cut = MAX_EVENT_BYTES;
+            while !st.pending.is_char_boundary(cut) {
+                cut -= 1;
+            }
+            let rest = st.pending.split_off(cut);
+            std::mem::replace(&mut st.pending, rest)
+        };
+        st.unacked += chunk.encode_utf16().count();
+        self.cv.notify_all();
+        Next::Data(chunk)
+    }
+
+    fn ack(&self, units: usize) {
+        let mut st = self.state.lock().unwrap_or_else(|e| e.into_inner());
+        st.unacked = st.unacked.saturating_sub(units);
+        st.waiting_since = (st.unacked >= MAX_UNACKED).then(Instant::now);
+        self.cv.notify_all();
+    }
+}
+
+// Enough for a few thousand lines of typical build/test output.
+const SCROLLBACK_MAX_BYTES: usize = 512 * 1024;
+
+#[derive(Default)]
+struct Scrollback {
+    buf: String,
+    // Stream offset of `buf[0]`: how many bytes have been trimmed off the front.
+    start: u64,
+}
+
+impl Scrollback {
+    fn push(&mut self, s: &str) {
+        self.buf.push_str(s);
+        if self.buf.len() > SCROLLBACK_MAX_BYTES {
+            let mut cut = self.buf.len() - SCROLLBACK_MAX_BYTES;
+            while !self.buf.is_char_boundary(cut) {
+                cut += 1;
+            }
+            // Drop whole lines where possible, so a replay doesn't start inside an escape sequence.
+            if let Some(nl) = self.buf[cut..].find('\n') {
+                cut += nl + 1;
+            }
+            self.buf.drain(..cut);
+            self.start += cut as u64;
+        }
+    }
+
+    // Output between two stream offsets, or `None` if it has been trimmed away. An open end runs
+    // to the latest output.
+    fn range(&self, from: u64, to: Option<u64>) -> Option<&str> {
+        let from = usize::try_from(from.checked_sub(self.start)?).ok()?;
+        let to = match to {
+            Some(to) => usize::try_from(to.saturating_sub(self.start)).ok()?.min(self.buf.len()),
+            None => self.buf.len(),
+        };
+        self.buf.get(from..to.max(from))
+    }
+
+    // The last `n` lines as they were written, escape sequences and line endings included.
+    fn tail(&self, n: usize) -> &str {
+        let body = self.buf.strip_suffix('\n').unwrap_or(&self.buf);
+        match body.rmatch_indices('\n').nth(n.saturating_sub(1)) {
+            Some((i, _)) => &self.buf[i + 1..],
+            None => &self.buf,
+        }
+    }
+
+    fn last_lines(&self, n: usize) -> String {
+        let lines: Vec<&str> = self.buf.lines().collect();
+        let start = lines.len().saturating_sub(n);
+        lines[start..].join("\n")
+    }
+}
+
 struct TerminalSession {
     master: Box<dyn portable_pty::MasterPty + Send>,
     writer: Box<dyn Write + Send>,
-    child: Box<dyn portable_pty::Child + Send>,
+    killer: Box<dyn portable_pty::ChildKiller + Send + Sync>,
+    pid: Option<u32>,
+    profile: Option<String>,
+    started_at: u64,
+    // The shell and what it starts; `None` if the pid couldn't be read.
+    tree: Option<Arc<ProcessTree>>,
+    // Last directory the shell reported, or the one it started in.
+    cwd: Arc<Mutex<Option<String>>>,
+    title: Arc<Mutex<Option<String>>>,
+    exit: SharedExit,
+    output: Arc<Output>,
+    scrollback: Arc<Mutex<Scrollback>>,
+    commands: Arc<Mutex<CommandLog>>,
 }
 
 type Sessions = Arc<Mutex<HashMap<String, TerminalSession>>>;
@@ -26,6 +372,275 @@ fn sessions() -> &'static Sessions {
     S.get_or_init(|| Arc::new(Mutex::new(HashMap::new())))
 }
 
+fn saved_sessions_path() -> Option<PathBuf> {
+    Some(dirs::config_dir()?.join("Pompora").join("terminal-sessions.json"))
+}
+
+// What the previous run left in the file, read before this run first writes to it.
+fn previous_sessions() -> &'static Mutex<Vec<SavedTerminalSession>> {
+    use once_cell::sync::OnceCell;
+    static P: OnceCell<Mutex<Vec<SavedTerminalSession>>> = OnceCell::new();
+    P.get_or_init(|| {
+        let saved = saved_sessions_path()
+            .and_then(|p| std::fs::read_to_string(p).ok())
+            .and_then(|raw| serde_json::from_str(&raw).ok())
+            .unwrap_or_default();
+        Mutex::new(saved)
+    })
+}
+
+// Records the sessions whose shell is still running, for `previous` after a restart. Best
+// effort: losing this only means the terminal starts fresh.
+fn save_sessions() {
+    static SAVING: Mutex<()> = Mutex::new(());
+    let Some(path) = saved_sessions_path() else {
+        return;
+    };
+    let _saving = SAVING.lock().unwrap_or_else(|e| e.into_inner());
+    // The previous run's list must be read before it is overwritten.
+    previous_sessions();
+    let mut saved: Vec<SavedTerminalSession> = match sessions().lock() {
+        Ok(map) => map
+            .values()
+            .filter(|s| s.exit.0.lock().unwrap_or_else(|e| e.into_inner()).exit.is_none())
+            .map(|s| SavedTerminalSession {
+                profile: s.profile.clone(),
+                cwd: s.cwd.lock().unwrap_or_else(|e| e.into_inner()).clone(),
+                title: s.title.lock().unwrap_or_else(|e| e.into_inner()).clone(),
+                started_at: s.started_at,
+            })
+            .collect(),
+        Err(_) => return,
+    };
+    saved.sort_by_key(|s| s.started_at);
+    let Ok(json) = serde_json::to_string_pretty(&saved) else {
+        return;
+    };
+    if let Some(dir) = path.parent() {
+        let _ = std::fs::create_dir_all(dir);
+    }
+    let tmp = path.with_extension("json.tmp");
+    if std::fs::write(&tmp, json).is_ok() {
+        let _ = std::fs::rename(&tmp, &path);
+    }
+}
+
+/// Drops ANSI escape sequences (colors, cursor movement, OSC titles) and carriage returns so the
+/// text is readable outside a terminal emulator.
+fn strip_ansi(input: &str) -> String {
+    let mut out = String::with_capacity(input.len());
+    let mut chars = input.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '\u{1b}' => match chars.peek() {
+                Some('[') => {
+                    chars.next();
+                    // CSI: parameters then a final byte in @..~
+                    for n in chars.by_ref() {
+                        if ('@'..='~').contains(&n) {
+                            break;
+                        }
+                    }
+                }
+                Some(']') => {
+                    chars.next();
+                    // OSC: terminated by BEL or ESC \
+                    while let Some(n) = chars.next() {
+                        if n == '\u{7}' {
+                            break;
+                        }
+                        if n == '\u{1b}' && chars.peek() == Some(&'\\') {
+                            chars.next();
+                            break;
+                        }
+                    }
+                }
+                Some(_) => {
+                    chars.next();
+                }
+                None => {}
+            },
+            '\r' => {}
+            _ => out.push(c),
+        }
+    }
+    out
+}
+
+// Longest OSC sequence kept while waiting for its terminator; anything longer is dropped.
+const MAX_OSC_LEN: usize = 4096;
+
+#[derive(Default, PartialEq)]
+enum OscState {
+    #[default]
+    Text,
+    Esc,
+    Osc,
+    OscEsc,
+}
+
+// An OSC sequence and where it sits in the output stream.
+struct Osc {
+    // E.g. `7;file://host/dir`.
+    payload: String,
+    // Offset of its ESC, and just past its terminator.
+    start: u64,
+    end: u64,
+}
+
+// Picks OSC sequences (`ESC ] ... BEL` or `ESC ] ... ESC \`) out of terminal output, including
+// ones split across reads.
+#[derive(Default)]
+struct OscScanner {
+    state: OscState,
+    payload: String,
+    // Stream offset of the next character, and of the ESC that opened the current sequence.
+    pos: u64,
+    start: u64,
+}
+
+impl OscScanner {
+    // The sequences completed in `chunk`.
+    fn scan(&mut self, chunk: &str) -> Vec<Osc> {
+        let mut out = vec![];
+        for c in chunk.chars() {
+            let at = self.pos;
+            self.pos += c.len_utf8() as u64;
+            self.state = match (&self.state, c) {
+                (OscState::Text, '\u{1b}') | (OscState::Esc, '\u{1b}') => {
+                    self.start = at;
+                    OscState::Esc
+                }
+                (OscState::Text, _) => OscState::Text,
+                (OscState::Esc, ']') => {
+                    self.payload.clear();
+                    OscState::Osc
+                }
+                (OscState::Esc, _) => OscState::Text,
+                (OscState::Osc, '\u{7}') | (OscState::OscEsc, '\\') => {
+                    out.push(Osc {
+                        payload: std::mem::take(&mut self.payload),
+                        start: self.start,
+                        end: self.pos,
+                    });
+                    OscState::Text
+                }
+                (OscState::Osc, '\u{1b}') => OscState::OscEsc,
+                (OscState::Osc, _) if self.payload.len() < MAX_OSC_LEN => {
+                    self.payload.push(c);
+                    OscState::Osc
+                }
+                (OscState::Osc, _) => OscState::Text,
+                // A new escape sequence cuts an unterminated OSC short.
+                (OscState::OscEsc, ']') => {
+                    self.start = at - 1;
+                    self.payload.clear();
+                    OscState::Osc
+                }
+                (OscState::OscEsc, _) => OscState::Text,
+            };
+        }
+        out
+    }
+}
+
+fn percent_decode(s: &str) -> String {
+    let bytes = s.as_bytes();
+    let mut out = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        if bytes[i] == b'%' {
+            if let Some(b) = s.get(i + 1..i + 3).and_then(|h| u8::from_str_radix(h, 16).ok()) {
+                out.push(b);
+                i += 3;
+                continue;
+            }
+        }
+        out.push(bytes[i]);
+        i += 1;
+    }
+    String::from_utf8_lossy(&out).into_owned()
+}
+
+// The directory in an OSC 7 payload (`file://host/path`, after the `7;`).
+fn parse_osc7(url: &str) -> Option<String> {
+    let rest = url.strip_prefix("file://")?;
+    let path = percent_decode(&rest[rest.find('/')?..]);
+    // `file://host/C:/dir` on Windows.
+    let bytes = path.as_bytes();
+    if cfg!(windows) && bytes.len() >= 3 && bytes[2] == b':' {
+        return Some(path[1..].to_string());
+    }
+    Some(path)
+}
+
+// Commands kept per session; the oldest are dropped first.
+const MAX_COMMANDS: usize = 500;
+
+fn now_ms() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_millis() as u64)
+        .unwrap_or(0)
+}
+
+// Commands in a session, built from the shell integration's OSC 133/633 sequences.
+#[derive(Default)]
+struct CommandLog {
+    next_seq: u64,
+    // Command line from the last `633;E`, for the command that starts next.
+    pending: Option<String>,
+    commands: VecDeque<TerminalCommand>,
+}
+
+impl CommandLog {
+    // Applies one sequence and returns the command it started or finished, if any.
+    fn apply(&mut self, osc: &Osc, cwd: Option<String>) -> Option<TerminalCommand> {
+        let (kind, arg) = match osc.payload.split_once(';') {
+            Some(("133", rest)) => rest.split_once(';').unwrap_or((rest, "")),
+            Some(("633", rest)) => {
+                if let Some(line) = rest.strip_prefix("E;") {
+                    self.pending = Some(shell_integration::unescape(line));
+                }
+                return None;
+            }
+            _ => return None,
+        };
+        match kind {
+            "C" => {
+                self.finish(osc.start, None);
+                self.next_seq += 1;
+                self.commands.push_back(TerminalCommand {
+                    seq: self.next_seq,
+                    command: self.pending.take().filter(|c| !c.trim().is_empty()),
+                    cwd,
+                    started_at: now_ms(),
+                    finished_at: None,
+                    exit_code: None,
+                    output_start: osc.end,
+                    output_end: None,
+                });
+                if self.commands.len() > MAX_COMMANDS {
+                    self.commands.pop_front();
+                }
+                self.commands.back().cloned()
+            }
+            "D" => self.finish(osc.start, arg.trim().parse().ok()),
+            // A new prompt without a `D` (e.g. the shell was interrupted) still ends the command.
+            "A" => self.finish(osc.start, None),
+            _ => None,
+        }
+    }
+
+    fn finish(&mut self, at: u64, exit_code: Option<i32>) -> Option<TerminalCommand> {
+        let cmd = self.commands.back_mut().filter(|c| c.finished_at.is_none())?;
+        cmd.finished_at = Some(now_ms());
+        cmd.exit_code = exit_code;
+        cmd.output_end = Some(at);
+        Some(cmd.clone())
+    }
+}
+
 fn default_shell() -> (String, Vec<String>) {
     if cfg!(windows) {
         let shell = std::env::var("COMSPEC").unwrap_or_else(|_| "cmd.exe".to_string());
@@ -44,7 +659,40 @@ fn default_shell() -> (String, Vec<String>) {
     }
 }
 
-pub fn terminal_start(app: AppHandle, cols: u16, rows: u16, cwd: Option<String>) -> Result<String, String> {
+// An explicitly requested profile must exist; a stale `default_terminal_profile` just falls
+// back to the login shell.
+fn resolve_profile(s: &AppSettings, name: Option<&str>) -> Result<Option<TerminalProfile>, String> {
+    match name.map(|n| n.trim()).filter(|n| !n.is_empty()) {
+        Some(name) => s
+            .terminal_profiles
+            .iter()
+            .find(|p| p.name == name)
+            .cloned()
+            .map(Some)
+            .ok_or_else(|| format!("Unknown terminal profile: {name}")),
+        None => {
+            let Some(default) = &s.default_terminal_profile else {
+                return Ok(None);
+            };
+            Ok(s.terminal_profiles.iter().find(|p| &p.name == default).cloned())
+        }
+    }
+}
+
+pub fn terminal_start(
+    app: AppHandle,
+    cols: u16,
+    rows: u16,
+    cwd: Option<String>,
+    profile: Option<String>,
+    env_file: Option<String>,
+) -> Result<String, String> {
+    workspace::ensure_trusted("The terminal").map_err(|e| e.to_string())?;
+    let settings = settings::load().map_err(|e| e.to_string())?;
+    let profile = resolve_profile(&settings, profile.as_deref())?;
+    // A new session replaces whatever the last run left to restore.
+    previous_sessions().lock().unwrap_or_else(|e| e.into_inner()).clear();
+
     let pty_system = native_pty_system();
     let pair = pty_system
         .openpty(PtySize {
@@ -55,16 +703,59 @@ pub fn terminal_start(app: AppHandle, cols: u16, rows: u16, cwd: Option<String>)
         })
         .map_err(|e| e.to_string())?;
 
-    let (shell, args) = default_shell();
+    let (shell, mut args) = match &profile {
+        Some(p) => (p.shell.clone(), p.args.clone()),
+        None => default_shell(),
+    };
+    // Custom arguments may load their own rc file or not start an interactive shell at all, so
+    // those profiles run as written. If the scripts can't be set up the shell starts without them.
+    let mut integration_env = vec![];
+    if settings.terminal_shell_integration && profile.as_ref().is_none_or(|p| p.args.is_empty()) {
+        if let Some(launch) = shell_integration::shell_kind(&shell).and_then(|k| shell_integration::launch(k).ok()) {
+            args = launch.args;
+            integration_env = launch.env;
+        }
+    }
+    let cwd = cwd
+        .filter(|d| !d.trim().is_empty())
+        .or_else(|| profile.as_ref().and_then(|p| p.cwd.clone()))
+        .filter(|d| !d.trim().is_empty())
+        .map(PathBuf::from)
+        .or_else(|| workspace::open_roots().ok()?.into_iter().next().map(|r| r.path));
+    let start_dir = cwd
+        .clone()
+        .or_else(|| std::env::current_dir().ok())
+        .map(|d| d.to_string_lossy().into_owned());
+    if let Some(dir) = &cwd {
+        // A folder inside the workspace must not be a symlink that leads out of it.
+        for root in workspace::open_roots().unwrap_or_default() {
+            if dir.starts_with(&root.path) {
+                workspace::ensure_contained(&root.path, dir).map_err(|e| e.to_string())?;
+            }
+        }
+    }
+
+    // A WSL shell starts in the folder's Linux path, and in the distribution it lives in unless
+    // the profile picks one.
+    if let Some(dir) = cwd.as_ref().filter(|_| cfg!(windows) && wsl::is_wsl(&shell)) {
+        let dir = dir.to_string_lossy();
+        let mut pre = vec![];
+        if !args.iter().any(|a| a == "-d" || a == "--distribution") {
+            if let Some(distro) = wsl::distro_of(&dir) {
+                pre.extend(["-d".to_string(), distro]);
+            }
+        }
+        if let Some(linux) = wsl::to_wsl_path(&dir) {
+            pre.extend(["--cd".to_string(), linux]);
+        }
+        args.splice(0..0, pre);
+    }
     let mut cmd = CommandBuilder::new(shell);
     for a in args {
         cmd.arg(a);
     }
-
     if let Some(dir) = cwd {
-        if !dir.trim().is_empty() {
-            cmd.cwd(PathBuf::from(dir));
-        }
+        cmd.cwd(dir);
     }
 
     // Improve prompt appearance on Unix shells.
@@ -72,13 +763,45 @@ pub fn terminal_start(app: AppHandle, cols: u16, rows: u16, cwd: Option<String>)
         cmd.env("TERM", "xterm-256color");
         cmd.env("PS1", "\\u@\\h:\\w\\$ ");
     }
+    for (k, v) in integration_env {
+        cmd.env(k, v);
+    }
 
-    let child = pair.slave.spawn_command(cmd).map_err(|e| e.to_string())?;
+    // Profile variables so they can override the defaults above, then the env file's, which
+    // was picked for this session or the workspace.
+    if let Some(p) = &profile {
+        for (k, v) in &p.env {
+            cmd.env(k, v);
+        }
+    }
+    let env_vars = match env_file.as_deref().map(str::trim).filter(|f| !f.is_empty()) {
+        Some(rel) => dotenv::load_workspace(rel).map_err(|e| e.to_string())?,
+        None => dotenv::default_vars(),
+    };
+    for (k, v) in env_vars {
+        cmd.env(k, v);
+    }
+
+    let mut child = pair.slave.spawn_command(cmd).map_err(|e| e.to_string())?;
+    let killer = child.clone_killer();
+    let pid = child.process_id();
+    let tree = pid.map(|p| Arc::new(ProcessTree::new(p)));
+    let cwd = Arc::new(Mutex::new(start_dir));
 
     let mut reader = pair.master.try_clone_reader().map_err(|e| e.to_string())?;
     let writer = pair.master.take_writer().map_err(|e| e.to_string())?;
 
     let id = format!("term-{}", rand::random::<u64>());
+    let mut reporter = Reporter::new(
+        app.clone(),
+        format!("terminal:{id}"),
+        cwd.lock().unwrap_or_else(|e| e.into_inner()).as_deref().map(PathBuf::from).unwrap_or_default(),
+    );
+    let title = Arc::new(Mutex::new(None));
+    let scrollback = Arc::new(Mutex::new(Scrollback::default()));
+    let exit: SharedExit = Arc::new((Mutex::new(ExitState::default()), Condvar::new()));
+    let output = Arc::new(Output::default());
+    let commands = Arc::new(Mutex::new(CommandLog::default()));
 
     {
         let mut map = sessions().lock().map_err(|_| "terminal sessions lock poisoned".to_string())?;
@@ -87,38 +810,135 @@ pub fn terminal_start(app: AppHandle, cols: u16, rows: u16, cwd: Option<String>)
             TerminalSession {
                 master: pair.master,
                 writer,
-                child,
+                killer,
+                pid,
+                profile: profile.map(|p| p.name),
+                started_at: now_ms(),
+                tree,
+                cwd: cwd.clone(),
+                title: title.clone(),
+                exit: exit.clone(),
+                output: output.clone(),
+                scrollback: scrollback.clone(),
+                commands: commands.clone(),
             },
         );
     }
+    save_sessions();
 
     let app2 = app.clone();
     let id2 = id.clone();
+    let output2 = output.clone();
     std::thread::spawn(move || {
         let mut buf = [0u8; 8192];
+        let mut osc = OscScanner::default();
+        let mut lines = LineSplitter::default();
+        let mut matcher = Matcher::default();
         loop {
             match reader.read(&mut buf) {
                 Ok(0) => break,
                 Ok(n) => {
                     let s = String::from_utf8_lossy(&buf[..n]).to_string();
-                    let _ = app2.emit(
-                        "terminal:data",
-                        TerminalDataEvent {
-                            id: id2.clone(),
-                            data: s,
-                        },
-                    );
+                    // Before the sequences are handled, so a finished command's output can be
+                    // read as soon as it is announced.
+                    if let Ok(mut sb) = scrollback.lock() {
+                        sb.push(&s);
+                    }
+                    for seq in osc.scan(&s) {
+                        if let Some(dir) = seq.payload.strip_prefix("7;").and_then(parse_osc7) {
+                            let mut current = cwd.lock().unwrap_or_else(|e| e.into_inner());
+                            if current.as_deref() != Some(dir.as_str()) {
+                                reporter.set_cwd(PathBuf::from(&dir));
+                                *current = Some(dir.clone());
+                                drop(current);
+                                let _ = app2.emit("terminal:cwd", TerminalCwdEvent { id: id2.clone(), cwd: dir });
+                                save_sessions();
+                            }
+                            continue;
+                        }
+                        if let Some(t) = seq.payload.strip_prefix("0;").or_else(|| seq.payload.strip_prefix("2;")) {
+                            let mut current = title.lock().unwrap_or_else(|e| e.into_inner());
+                            if current.as_deref() != Some(t) {
+                                *current = Some(t.to_string());
+                                drop(current);
+                                let title = t.to_string();
+                                let _ = app2.emit("terminal:title", TerminalTitleEvent { id: id2.clone(), title });
+                                save_sessions();
+                            }
+                            continue;
+                        }
+                        let dir = cwd.lock().unwrap_or_else(|e| e.into_inner()).clone();
+                        let changed = commands.lock().unwrap_or_else(|e| e.into_inner()).apply(&seq, dir);
+                        if let Some(command) = changed {
+                            // Each command's problems replace the previous one's.
+                            if command.finished_at.is_none() {
+                                reporter.clear();
+                            }
+                            if let Some(line) = &command.command {
+                                let _ = if command.finished_at.is_none() {
+                                    terminal_history::record_start(line, command.cwd.as_deref())
+                                } else {
+                                    terminal_history::record_exit(line, command.exit_code)
+                                };
+                            }
+                            let _ = app2.emit("terminal:command", TerminalCommandEvent { id: id2.clone(), command });
+                        }
+                    }
+                    for line in lines.split(&s) {
+                        reporter.feed_line(&mut matcher, &strip_ansi(&line));
+                    }
+                    reporter.flush();
+                    output2.push(&s);
                 }
                 Err(_) => break,
             }
         }
-        let _ = app2.emit(
-            "terminal:exit",
-            TerminalDataEvent {
-                id: id2.clone(),
-                data: "".to_string(),
-            },
-        );
+        output2.close();
+    });
+
+    let app2 = app.clone();
+    let id2 = id.clone();
+    let exit2 = exit.clone();
+    std::thread::spawn(move || {
+        let mut since = Instant::now();
+        let mut set_active = |active: bool| {
+            output.active.store(active, Ordering::Relaxed);
+            let previous_ms = since.elapsed().as_millis() as u64;
+            since = Instant::now();
+            let _ = app2.emit("terminal:activity", TerminalActivityEvent { id: id2.clone(), active, previous_ms });
+        };
+        loop {
+            let active = output.active.load(Ordering::Relaxed);
+            match output.next(active.then_some(IDLE_AFTER)) {
+                Next::Data(data) => {
+                    if !active {
+                        set_active(true);
+                    }
+                    let _ = app2.emit("terminal:data", TerminalDataEvent { id: id2.clone(), data });
+                    // Whatever arrives meanwhile goes out together in the next event.
+                    std::thread::sleep(DATA_EMIT_INTERVAL);
+                }
+                Next::Quiet => set_active(false),
+                Next::Closed => break,
+            }
+        }
+        let (state, cv) = &*exit2;
+        state.lock().unwrap_or_else(|e| e.into_inner()).output_done = true;
+        cv.notify_all();
+    });
+
+    let id3 = id.clone();
+    std::thread::spawn(move || {
+        let status = describe_exit(&id3, child.wait());
+        let (state, cv) = &*exit;
+        let mut st = state.lock().unwrap_or_else(|e| e.into_inner());
+        st.exit = Some(status.clone());
+        cv.notify_all();
+        drop(st);
+        save_sessions();
+        let st = state.lock().unwrap_or_else(|e| e.into_inner());
+        let _ = cv.wait_timeout_while(st, EXIT_OUTPUT_GRACE, |st| !st.output_done);
+        let _ = app.emit("terminal:exit", status);
     });
 
     Ok(id)
@@ -146,10 +966,227 @@ pub fn terminal_resize(id: String, cols: u16, rows: u16) -> Result<(), String> {
     Ok(())
 }
 
-pub fn terminal_kill(id: String) -> Result<(), String> {
-    let mut map = sessions().lock().map_err(|_| "terminal sessions lock poisoned".to_string())?;
-    if let Some(mut s) = map.remove(&id) {
-        let _ = s.child.kill();
+/// Ends a session along with everything started in it, e.g. a dev server run from the shell.
+/// They are asked to exit first and killed if still running after `TERMINATE_TIMEOUT`; `force`
+/// kills them straight away. Returns without waiting; `terminal:exit` follows as usual.
+pub fn terminal_kill(id: String, force: bool) -> Result<(), String> {
+    let session = {
+        let mut map = sessions().lock().map_err(|_| "terminal sessions lock poisoned".to_string())?;
+        map.remove(&id)
+    };
+    let Some(mut s) = session else {
+        return Ok(());
+    };
+    save_sessions();
+    let Some(tree) = s.tree.take() else {
+        let _ = s.killer.kill();
+        return Ok(());
+    };
+    if force {
+        tree.kill();
+        return Ok(());
     }
+    // Signalled before the terminal closes, while the shell's children can still be found
+    // through it.
+    tree.terminate();
+    let exit = s.exit.clone();
+    // Closing the terminal is how Windows console programs are asked to exit.
+    drop(s);
+    std::thread::spawn(move || {
+        tree.stop(TERMINATE_TIMEOUT, || exit.0.lock().unwrap_or_else(|e| e.into_inner()).exit.is_some());
+    });
+    Ok(())
+}
+
+/// Tells a session that the frontend has written `units` of its output (UTF-16 code units, as
+/// `data.length` counts them) to the screen. Sending is held back while too much is unacknowledged.
+pub fn terminal_ack(id: &str, units: usize) -> Result<(), String> {
+    let output = {
+        let map = sessions().lock().map_err(|_| "terminal sessions lock poisoned".to_string())?;
+        // The session may have ended while its last output was being written.
+        let Some(s) = map.get(id) else {
+            return Ok(());
+        };
+        s.output.clone()
+    };
+    output.ack(units);
     Ok(())
 }
+
+// Most hits `terminal_search` returns; `e` in a long build log finds far more than anyone reads.
+const MAX_SEARCH_MATCHES: usize = 1000;
+
+/// Searches the output a session has kept, escape sequences removed, a line at a time. `query` is
+/// plain text unless `regex`; letter case is ignored unless `case_sensitive`.
+pub fn terminal_search(id: &str, query: &str, regex: bool, case_sensitive: bool) -> Result<TerminalSearchResults, String> {
+    let mut results = TerminalSearchResults {
+        matches: vec![],
+        total: 0,
+        truncated: false,
+    };
+    if query.is_empty() {
+        return Ok(results);
+    }
+    let pattern = if regex { query.to_string() } else { regex::escape(query) };
+    let re = regex::RegexBuilder::new(&pattern)
+        .case_insensitive(!case_sensitive)
+        .build()
+        .map_err(|e| format!("invalid search query: {e}"))?;
+
+    let scrollback = {
+        let map = sessions().lock().map_err(|_| "terminal sessions lock poisoned".to_string())?;
+        let s = map.get(id).ok_or_else(|| "terminal session not found".to_string())?;
+        s.scrollback.clone()
+    };
+    let sb = scrollback.lock().map_err(|_| "terminal scrollback lock poisoned".to_string())?;
+    // Newest first, so the hits kept past the cap are the recent ones.
+    for (from_end, raw) in sb.buf.rsplit('\n').enumerate() {
+        // A carriage return without a newline redraws the line; the last draw is what shows.
+        let raw = raw.trim_end_matches('\r');
+        let text = strip_ansi(raw.rsplit('\r').next().unwrap_or(raw));
+        let hits: Vec<_> = re.find_iter(&text).filter(|m| !m.is_empty()).collect();
+        for m in hits.into_iter().rev() {
+            results.total += 1;
+            if results.matches.len() >= MAX_SEARCH_MATCHES {
+                results.truncated = true;
+                continue;
+            }
+            results.matches.push(TerminalSearchMatch {
+                lines_from_end: from_end as u32,
+                column: text[..m.start()].encode_utf16().count() as u32,
+                match_len: m.as_str().encode_utf16().count() as u32,
+                text: text.clone(),
+            });
+        }
+    }
+    results.matches.reverse();
+    Ok(results)
+}
+
+/// Sessions of this run, to reattach to after a reload, and the ones the previous run left open,
+/// to start again after a restart.
+pub fn terminal_list_sessions() -> Result<TerminalSessionList, String> {
+    let mut sessions: Vec<TerminalSessionInfo> = {
+        let map = sessions().lock().map_err(|_| "terminal sessions lock poisoned".to_string())?;
+        map.iter()
+            .map(|(id, s)| TerminalSessionInfo {
+                id: id.clone(),
+                profile: s.profile.clone(),
+                cwd: s.cwd.lock().unwrap_or_else(|e| e.into_inner()).clone(),
+                title: s.title.lock().unwrap_or_else(|e| e.into_inner()).clone(),
+                started_at: s.started_at,
+                active: s.output.active.load(Ordering::Relaxed),
+                exit: s.exit.0.lock().unwrap_or_else(|e| e.into_inner()).exit.clone(),
+            })
+            .collect()
+    };
+    sessions.sort_by_key(|s| s.started_at);
+    let previous = previous_sessions().lock().map_err(|_| "terminal sessions lock poisoned".to_string())?.clone();
+    Ok(TerminalSessionList { sessions, previous })
+}
+
+/// Shell pid of every session still running, by session id.
+pub fn session_pids() -> Vec<(String, u32)> {
+    let map = sessions().lock().unwrap_or_else(|e| e.into_inner());
+    map.iter()
+        .filter(|(_, s)| s.exit.0.lock().unwrap_or_else(|e| e.into_inner()).exit.is_none())
+        .filter_map(|(id, s)| Some((id.clone(), s.pid?)))
+        .collect()
+}
+
+/// The session's working directory: on Linux the shell process's own, elsewhere the last one
+/// the shell reported (OSC 7) or else the one it started in.
+pub fn terminal_get_cwd(id: &str) -> Result<Option<String>, String> {
+    let (cwd, pid) = {
+        let map = sessions().lock().map_err(|_| "terminal sessions lock poisoned".to_string())?;
+        let s = map.get(id).ok_or_else(|| "terminal session not found".to_string())?;
+        (s.cwd.clone(), s.pid)
+    };
+    let reported = cwd.lock().unwrap_or_else(|e| e.into_inner()).clone();
+    #[cfg(target_os = "linux")]
+    if let Some(dir) = pid.and_then(|p| std::fs::read_link(format!("/proc/{p}/cwd")).ok()) {
+        if let Some(dir) = dir.to_str() {
+            return Ok(Some(dir.to_string()));
+        }
+    }
+    #[cfg(not(target_os = "linux"))]
+    let _ = pid;
+    Ok(reported)
+}
+
+/// Blocks until the session's process exits, or for at most `timeout` if given, and returns how
+/// it exited; `None` if it is still running.
+pub fn terminal_wait(id: &str, timeout: Option<Duration>) -> Result<Option<TerminalExit>, String> {
+    let exit = {
+        let map = sessions().lock().map_err(|_| "terminal sessions lock poisoned".to_string())?;
+        let s = map.get(id).ok_or_else(|| "terminal session not found".to_string())?;
+        s.exit.clone()
+    };
+    let (state, cv) = &*exit;
+    let st = state.lock().map_err(|_| "terminal exit lock poisoned".to_string())?;
+    let st = match timeout {
+        Some(t) => cv.wait_timeout_while(st, t, |st| st.exit.is_none()).unwrap_or_else(|e| e.into_inner()).0,
+        None => cv.wait_while(st, |st| st.exit.is_none()).unwrap_or_else(|e| e.into_inner()),
+    };
+    Ok(st.exit.clone())
+}
+
+/// Last `lines` lines of a session's output (all that is kept if `None`) exactly as the terminal
+/// received it, for a reloaded or newly opened view to replay.
+pub fn terminal_get_scrollback(id: &str, lines: Option<usize>) -> Result<String, String> {
+    let scrollback = {
+        let map = sessions().lock().map_err(|_| "terminal sessions lock poisoned".to_string())?;
+        let s = map.get(id).ok_or_else(|| "terminal session not found".to_string())?;
+        s.scrollback.clone()
+    };
+    let sb = scrollback.lock().map_err(|_| "terminal scrollback lock poisoned".to_string())?;
+    Ok(match lines {
+        Some(n) => sb.tail(n).to_string(),
+        None => sb.buf.clone(),
+    })
+}
+
+/// Last `lines` lines of a session's output with terminal escape sequences removed.
+pub fn terminal_recent_output(id: &str, lines: usize) -> Result<String, String> {
+    let scrollback = {
+        let map = sessions().lock().map_err(|_| "terminal sessions lock poisoned".to_string())?;
+        let s = map.get(id).ok_or_else(|| "terminal session not found".to_string())?;
+        s.scrollback.clone()
+    };
+    let sb = scrollback.lock().map_err(|_| "terminal scrollback lock poisoned".to_string())?;
+    Ok(strip_ansi(&sb.last_lines(lines)))
+}
+
+/// Commands run in a session so far, oldest first. Empty if the shell has no integration loaded.
+pub fn terminal_commands(id: &str) -> Result<Vec<TerminalCommand>, String> {
+    let commands = {
+        let map = sessions().lock().map_err(|_| "terminal sessions lock poisoned".to_string())?;
+        let s = map.get(id).ok_or_else(|| "terminal session not found".to_string())?;
+        s.commands.clone()
+    };
+    let log = commands.lock().map_err(|_| "terminal commands lock poisoned".to_string())?;
+    Ok(log.commands.iter().cloned().collect())
+}
+
+/// Output of one command with terminal escape sequences removed; so far, if it is still running.
+pub fn terminal_command_output(id: &str, seq: u64) -> Result<String, String> {
+    let (commands, scrollback) = {
+        let map = sessions().lock().map_err(|_| "terminal sessions lock poisoned".to_string())?;
+        let s = map.get(id).ok_or_else(|| "terminal session not found".to_string())?;
+        (s.commands.clone(), s.scrollback.clone())
+    };
+    let (from, to) = {
+        let log = commands.lock().map_err(|_| "terminal commands lock poisoned".to_string())?;
+        let cmd = log
+            .commands
+            .iter()
+            .find(|c| c.seq == seq)
+            .ok_or_else(|| "terminal command not found".to_string())?;
+        (cmd.output_start, cmd.output_end)
+    };
+    let sb = scrollback.lock().map_err(|_| "terminal scrollback lock poisoned".to_string())?;
+    let out = sb
+        .range(from, to)
+        .ok_or_else(|| "The command's output is no longer in the scrollback".to_string())?;
+    Ok(strip_ansi(out))
+}
@@ -0,0 +1,156 @@
+use anyhow::{Context, Result};
+use once_cell::sync::Lazy;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::fs;
+use std::path::PathBuf;
+use std::sync::Mutex;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use super::settings;
+
+// Commands kept per workspace; the ones run least recently go first.
+const MAX_ENTRIES: usize = 2000;
+
+/// A command run in a terminal of the workspace, once however many times it ran.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct HistoryEntry {
+    pub command: String,
+    /// Folder it last ran in.
+    pub cwd: Option<String>,
+    /// When it last started, in ms since the Unix epoch.
+    pub last_run: u64,
+    pub count: u32,
+    /// Exit status of the last run; `None` while it runs or if the shell didn't say.
+    pub exit_code: Option<i32>,
+}
+
+// History of the workspace that was open when it was last used.
+struct Loaded {
+    path: PathBuf,
+    entries: Vec<HistoryEntry>,
+}
+
+static LOADED: Lazy<Mutex<Option<Loaded>>> = Lazy::new(|| Mutex::new(None));
+
+fn now_ms() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_millis() as u64)
+        .unwrap_or(0)
+}
+
+// One file per workspace in the app's config folder, named by a hash of its root; commands run
+// with no workspace open share one.
+fn history_file() -> Result<PathBuf> {
+    let s = settings::load()?;
+    let root = s.workspace_root.as_deref().map(str::trim).filter(|r| !r.is_empty());
+    let name = match root {
+        Some(root) => hex::encode(Sha256::digest(root.as_bytes()))[..16].to_string(),
+        None => "global".to_string(),
+    };
+    let base = dirs::config_dir().context("missing config dir")?;
+    Ok(base.join("Pompora").join("terminal-history").join(format!("{name}.json")))
+}
+
+// Runs `f` on the open workspace's history, oldest first, and saves it if `f` says it changed.
+fn with_history<T>(f: impl FnOnce(&mut Vec<HistoryEntry>) -> (T, bool)) -> Result<T> {
+    let path = history_file()?;
+    let mut loaded = LOADED.lock().unwrap_or_else(|e| e.into_inner());
+    if loaded.as_ref().is_none_or(|l| l.path != path) {
+        let entries = fs::read_to_string(&path)
+            .ok()
+            .and_then(|raw| serde_json::from_str(&raw).ok())
+            .unwrap_or_default();
+        *loaded = Some(Loaded {
+            path: path.clone(),
+            entries,
+        });
+    }
+    let Some(Loaded { entries, .. }) = loaded.as_mut() else {
+        unreachable!("history loaded above");
+    };
+    let (out, changed) = f(entries);
+    if changed {
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir).with_context(|| format!("create dir: {}", dir.display()))?;
+        }
+        let json = serde_json::to_string(entries).context("serialize terminal history")?;
+        let tmp = path.with_extension("json.tmp");
+        fs::write(&tmp, json).with_context(|| format!("write {}", tmp.display()))?;
+        fs::rename(&tmp, &path).with_context(|| format!("replace {}", path.display()))?;
+    }
+    Ok(out)
+}
+
+/// Records that `command` started in `cwd`. A command line starting with a space is left out,
+/// as shells do with `HISTCONTROL=ignorespace`.
+pub fn record_start(command: &str, cwd: Option<&str>) -> Result<()> {
+    if command.starts_with(' ') || command.trim().is_empty() {
+        return Ok(());
+    }
+    let command = command.trim();
+    with_history(|entries| {
+        let mut entry = match entries.iter().position(|e| e.command == command) {
+            Some(i) => entries.remove(i),
+            None => HistoryEntry {
+                command: command.to_string(),
+                cwd: None,
+                last_run: 0,
+                count: 0,
+                exit_code: None,
+            },
+        };
+        entry.cwd = cwd.map(str::to_string);
+        entry.last_run = now_ms();
+        entry.count = entry.count.saturating_add(1);
+        entry.exit_code = None;
+        entries.push(entry);
+        if entries.len() > MAX_ENTRIES {
+            entries.drain(..entries.len() - MAX_ENTRIES);
+        }
+        ((), true)
+    })
+}
+
+/// Records how the last run of `command` exited.
+pub fn record_exit(command: &str, exit_code: Option<i32>) -> Result<()> {
+    if command.starts_with(' ') {
+        return Ok(());
+    }
+    let command = command.trim();
+    with_history(|entries| match entries.iter_mut().rev().find(|e| e.command == command) {
+        Some(e) if e.exit_code != exit_code => {
+            e.exit_code = exit_code;
+            ((), true)
+        }
+        _ => ((), false),
+    })
+}
+
+/// Commands run in the open workspace's terminals, most recent first, that contain every word
+/// of `query` (ignoring case); all of them for an empty query.
+pub fn terminal_history(query: &str, limit: usize) -> Result<Vec<HistoryEntry>> {
+    let words: Vec<String> = query.split_whitespace().map(str::to_lowercase).collect();
+    with_history(|entries| {
+        let found = entries
+            .iter()
+            .rev()
+            .filter(|e| {
+                let command = e.command.to_lowercase();
+                words.iter().all(|w| command.contains(w.as_str()))
+            })
+            .take(limit)
+            .cloned()
+            .collect();
+        (found, false)
+    })
+}
+
+/// Forgets the open workspace's history.
+pub fn terminal_history_clear() -> Result<()> {
+    with_history(|entries| {
+        entries.clear();
+        ((), true)
+    })
+}
@@ -0,0 +1,190 @@
+use anyhow::{anyhow, Result};
+use once_cell::sync::Lazy;
+use serde::Serialize;
+use std::collections::BTreeMap;
+use std::sync::Mutex;
+use tauri::{AppHandle, Emitter};
+
+use super::search::{self, SearchMatch, SearchOptions};
+use super::settings;
+
+pub const TODOS_CHANGED_EVENT: &str = "todos:changed";
+
+const MAX_TODOS: usize = 5000;
+// Text before a tag that makes the line a comment rather than code or prose.
+const COMMENT_MARKERS: &[&str] = &["//", "#", "/*", "<!--", "--", ";"];
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct Todo {
+    pub path: String,
+    pub line: u32,
+    /// 1-based column of the tag, in UTF-16 code units like editor columns.
+    pub column: u32,
+    pub tag: String,
+    /// The rest of the comment, without an `(author)` or the `:` after the tag.
+    pub text: String,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct TodoList {
+    pub todos: Vec<Todo>,
+    /// Whether the workspace has more than `MAX_TODOS` and the rest weren't listed.
+    pub truncated: bool,
+}
+
+/// Payload of `todos:changed`: what is now listed for `paths`, which replaces everything
+/// listed before for those files and anything under those folders.
+#[derive(Debug, Clone, Serialize)]
+pub struct TodosChanged {
+    pub paths: Vec<String>,
+    pub todos: Vec<Todo>,
+}
+
+struct Cache {
+    tags: Vec<String>,
+    by_file: BTreeMap<String, Vec<Todo>>,
+}
+
+// Filled by the first `workspace_todos`; only then does the watcher keep it current.
+static CACHE: Lazy<Mutex<Option<Cache>>> = Lazy::new(|| Mutex::new(None));
+
+fn clean_tags(tags: Vec<String>) -> Result<Vec<String>> {
+    let mut out: Vec<String> = vec![];
+    for tag in tags.iter().map(|t| t.trim()).filter(|t| !t.is_empty()) {
+        if !out.iter().any(|t| t == tag) {
+            out.push(tag.to_string());
+        }
+    }
+    if out.is_empty() {
+        return Err(anyhow!("at least one TODO tag is required"));
+    }
+    Ok(out)
+}
+
+// Byte offset in `text` of a UTF-16 column.
+fn byte_offset(text: &str, column: u32) -> usize {
+    let mut units = 0;
+    for (i, c) in text.char_indices() {
+        if units + 1 >= column as usize {
+            return i;
+        }
+        units += c.len_utf16();
+    }
+    text.len()
+}
+
+fn todo_of(m: &SearchMatch, tags: &[String]) -> Option<Todo> {
+    let line = m.text.lines().next().unwrap_or_default();
+    let start = byte_offset(line, m.column);
+    let (before, from_tag) = line.split_at(start);
+    if !COMMENT_MARKERS.iter().any(|c| before.contains(c)) && !before.trim_start().starts_with('*') {
+        return None;
+    }
+    let tag = tags.iter().filter(|t| from_tag.starts_with(t.as_str())).max_by_key(|t| t.len())?;
+    let mut rest = from_tag[tag.len()..].trim_start();
+    if let Some(after) = rest.strip_prefix('(').and_then(|r| r.split_once(')')).map(|(_, r)| r) {
+        rest = after;
+    }
+    let text = rest
+        .trim_start_matches([':', '-', ' ', '\t'])
+        .trim_end()
+        .trim_end_matches("*/")
+        .trim_end_matches("-->")
+        .trim_end();
+    Some(Todo {
+        path: m.path.clone(),
+        line: m.line,
+        column: m.column,
+        tag: tag.clone(),
+        text: text.to_string(),
+    })
+}
+
+// TODOs in `paths` (the whole workspace when empty), by file. One per line: the first tag.
+fn scan(paths: Vec<String>, tags: &[String]) -> Result<(BTreeMap<String, Vec<Todo>>, bool)> {
+    let pattern = format!("(?:{})", tags.iter().map(|t| regex::escape(t)).collect::<Vec<_>>().join("|"));
+    let opts = SearchOptions {
+        case_sensitive: true,
+        whole_word: true,
+        multiline: true,
+        paths,
+        ..Default::default()
+    };
+    let results = search::workspace_search(&pattern, MAX_TODOS, None, &opts)?;
+    let mut by_file = BTreeMap::new();
+    for file in results.files {
+        let mut todos: Vec<Todo> = vec![];
+        for m in &file.matches {
+            if todos.last().is_some_and(|t| t.line == m.line) {
+                continue;
+            }
+            todos.extend(todo_of(m, tags));
+        }
+        if !todos.is_empty() {
+            by_file.insert(file.path, todos);
+        }
+    }
+    Ok((by_file, results.truncated))
+}
+
+/// Comments in the workspace tagged with one of `tags` (default: the `todo_tags` setting),
+/// by file and line. After the first call, files the watcher sees change are searched again
+/// and `todos:changed` is emitted with what they now have.
+pub fn workspace_todos(tags: Option<Vec<String>>) -> Result<TodoList> {
+    let tags = clean_tags(match tags {
+        Some(tags) => tags,
+        None => settings::load()?.todo_tags,
+    })?;
+    let (by_file, truncated) = scan(vec![], &tags)?;
+    let todos = by_file.values().flatten().cloned().collect();
+    *CACHE.lock().unwrap_or_else(|e| e.into_inner()) = Some(Cache { tags, by_file });
+    Ok(TodoList { todos, truncated })
+}
+
+pub fn is_loaded() -> bool {
+    CACHE.lock().unwrap_or_else(|e| e.into_inner()).is_some()
+}
+
+fn under(path: &str, prefix: &str) -> bool {
+    path == prefix || path.strip_prefix(prefix).is_some_and(|rest| rest.starts_with('/'))
+}
+
+/// Searches files the watcher saw change (workspace paths) again and emits `todos:changed`
+/// for the ones whose TODOs differ. Changed files are searched even when ignored.
+pub fn note_changes(app: &AppHandle, modified: Vec<String>, removed: Vec<String>) {
+    let Some(tags) = CACHE.lock().unwrap_or_else(|e| e.into_inner()).as_ref().map(|c| c.tags.clone()) else {
+        return;
+    };
+    let mut found = BTreeMap::new();
+    for path in &modified {
+        // One at a time, since a path deleted again since the change fails the search.
+        if let Ok((by_file, _)) = scan(vec![path.clone()], &tags) {
+            found.extend(by_file);
+        }
+    }
+
+    let mut guard = CACHE.lock().unwrap_or_else(|e| e.into_inner());
+    let Some(cache) = guard.as_mut().filter(|c| c.tags == tags) else {
+        return;
+    };
+    let paths: Vec<String> = modified.into_iter().chain(removed).collect();
+    let before: BTreeMap<String, Vec<Todo>> = cache
+        .by_file
+        .iter()
+        .filter(|(file, _)| paths.iter().any(|p| under(file, p)))
+        .map(|(file, todos)| (file.clone(), todos.clone()))
+        .collect();
+    if before == found {
+        return;
+    }
+    cache.by_file.retain(|file, _| !before.contains_key(file));
+    cache.by_file.extend(found.clone());
+    drop(guard);
+    let _ = app.emit(
+        TODOS_CHANGED_EVENT,
+        TodosChanged {
+            paths,
+            todos: found.into_values().flatten().collect(),
+        },
+    );
+}
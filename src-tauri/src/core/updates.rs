@@ -0,0 +1,229 @@
+use anyhow::{anyhow, Context, Result};
+use once_cell::sync::Lazy;
+use serde::{Deserialize, Serialize};
+use std::sync::Mutex;
+use std::time::Duration;
+use tauri::{AppHandle, Emitter};
+
+use super::redact::redact_error;
+use super::{http, settings};
+
+pub const UPDATE_AVAILABLE_EVENT: &str = "update:available";
+
+const DEFAULT_FEED: &str = "https://api.github.com/repos/mitrashkov/pompora/releases";
+// The first background check waits for startup to settle.
+const FIRST_CHECK_DELAY: Duration = Duration::from_secs(60);
+const CHECK_INTERVAL: Duration = Duration::from_secs(6 * 60 * 60);
+
+/// A file attached to a release.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ReleaseAsset {
+    pub name: String,
+    pub url: String,
+    #[serde(default)]
+    pub size: Option<u64>,
+}
+
+/// A release as a Pompora feed lists it; GitHub releases are converted to this.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Release {
+    pub version: String,
+    /// Changelog, usually Markdown.
+    #[serde(default)]
+    pub notes: String,
+    /// Page to read about and download the release.
+    #[serde(default)]
+    pub url: Option<String>,
+    #[serde(default)]
+    pub published_at: Option<String>,
+    #[serde(default)]
+    pub prerelease: bool,
+    #[serde(default)]
+    pub assets: Vec<ReleaseAsset>,
+}
+
+/// Result of `updates_check`, and the payload of `update:available`.
+#[derive(Debug, Clone, Serialize)]
+pub struct UpdateCheck {
+    pub current_version: String,
+    /// Newest release in the feed, newer or not.
+    pub latest: Option<Release>,
+    /// Whether `latest` is newer than the running version.
+    pub available: bool,
+    /// The asset of `latest` that looks like the installer for this OS and architecture.
+    pub download: Option<ReleaseAsset>,
+}
+
+#[derive(Deserialize)]
+struct GithubAsset {
+    name: String,
+    browser_download_url: String,
+    size: Option<u64>,
+}
+
+#[derive(Deserialize)]
+struct GithubRelease {
+    tag_name: String,
+    #[serde(default)]
+    body: Option<String>,
+    html_url: Option<String>,
+    published_at: Option<String>,
+    #[serde(default)]
+    prerelease: bool,
+    #[serde(default)]
+    draft: bool,
+    #[serde(default)]
+    assets: Vec<GithubAsset>,
+}
+
+impl From<GithubRelease> for Release {
+    fn from(r: GithubRelease) -> Self {
+        Release {
+            version: r.tag_name,
+            notes: r.body.unwrap_or_default(),
+            url: r.html_url,
+            published_at: r.published_at,
+            prerelease: r.prerelease,
+            assets: r
+                .assets
+                .into_iter()
+                .map(|a| ReleaseAsset {
+                    name: a.name,
+                    url: a.browser_download_url,
+                    size: a.size,
+                })
+                .collect(),
+        }
+    }
+}
+
+fn parse_version(v: &str) -> Option<semver::Version> {
+    semver::Version::parse(v.trim().trim_start_matches(['v', 'V'])).ok()
+}
+
+// `updates.feed`, or Pompora's GitHub releases. Only `https://`, or `http://` to this machine.
+fn feed_url(s: &settings::AppSettings) -> Result<reqwest::Url> {
+    let url = s.updates.feed.as_deref().map(str::trim).filter(|v| !v.is_empty()).unwrap_or(DEFAULT_FEED);
+    http::parse_secure_url(url, "update feed")
+}
+
+async fn fetch_releases(url: reqwest::Url) -> Result<Vec<Release>> {
+    let github = url.host_str() == Some("api.github.com");
+    let mut req = http::client()?
+        .get(url)
+        .header(reqwest::header::USER_AGENT, format!("Pompora/{}", env!("CARGO_PKG_VERSION")));
+    if github {
+        req = req.header(reqwest::header::ACCEPT, "application/vnd.github+json");
+    }
+    let res = req
+        .send()
+        .await
+        .map_err(|e| anyhow!(redact_error(e)))
+        .context("update feed request")?;
+    if !res.status().is_success() {
+        return Err(anyhow!("update feed request failed (status {})", res.status()));
+    }
+    let body: serde_json::Value = res.json().await.context("read update feed")?;
+    // A feed may list releases or give just the latest one.
+    let items = match body {
+        serde_json::Value::Array(items) => items,
+        other => vec![other],
+    };
+    if github {
+        let releases: Vec<GithubRelease> = serde_json::from_value(serde_json::Value::Array(items)).context("parse GitHub releases")?;
+        return Ok(releases.into_iter().filter(|r| !r.draft).map(Release::from).collect());
+    }
+    serde_json::from_value(serde_json::Value::Array(items)).context("parse update feed")
+}
+
+// Installer-looking assets for this OS, preferring ones built for this architecture.
+fn download_for_platform(assets: &[ReleaseAsset]) -> Option<ReleaseAsset> {
+    let extensions: &[&str] = match std::env::consts::OS {
+        "windows" => &[".msi", ".exe"],
+        "macos" => &[".dmg"],
+        "linux" => &[".appimage", ".deb", ".rpm"],
+        _ => &[],
+    };
+    let arch_names: &[&str] = match std::env::consts::ARCH {
+        "x86_64" => &["x86_64", "x64", "amd64"],
+        "aarch64" => &["aarch64", "arm64"],
+        _ => &[],
+    };
+    let other_arch = ["x86_64", "x64", "amd64", "aarch64", "arm64"];
+    let mut candidates: Vec<(usize, &ReleaseAsset)> = assets
+        .iter()
+        .filter_map(|a| {
+            let name = a.name.to_lowercase();
+            let ext = extensions.iter().position(|e| name.ends_with(e))?;
+            let for_arch = arch_names.iter().any(|n| name.contains(n));
+            if !for_arch && other_arch.iter().any(|n| name.contains(n)) {
+                return None;
+            }
+            // Assets naming this architecture first, then by installer preference.
+            Some((usize::from(!for_arch) * extensions.len() + ext, a))
+        })
+        .collect();
+    candidates.sort_by_key(|(rank, _)| *rank);
+    candidates.first().map(|(_, a)| (*a).clone())
+}
+
+/// Asks the update feed for the newest release and compares it with the running version.
+/// Pre-releases are only considered when `updates.prereleases` is on, and entries whose
+/// version isn't semver are ignored.
+pub async fn updates_check() -> Result<UpdateCheck> {
+    let s = settings::load()?;
+    if s.offline_mode {
+        return Err(anyhow!("offline mode is enabled"));
+    }
+    let current_version = env!("CARGO_PKG_VERSION").to_string();
+    let current = parse_version(&current_version).ok_or_else(|| anyhow!("running version is not semver"))?;
+    let latest = fetch_releases(feed_url(&s)?)
+        .await?
+        .into_iter()
+        .filter_map(|r| parse_version(&r.version).map(|v| (v, r)))
+        .filter(|(v, r)| s.updates.prereleases || (!r.prerelease && v.pre.is_empty()))
+        .max_by(|(a, _), (b, _)| a.cmp(b));
+    let (available, latest) = match latest {
+        Some((v, mut r)) => {
+            r.version = v.to_string();
+            (v > current, Some(r))
+        }
+        None => (false, None),
+    };
+    let download = latest.as_ref().and_then(|r| download_for_platform(&r.assets));
+    Ok(UpdateCheck {
+        current_version,
+        latest,
+        available,
+        download,
+    })
+}
+
+// The version `update:available` was last emitted for, so each is announced once per run.
+static ANNOUNCED: Lazy<Mutex<Option<String>>> = Lazy::new(|| Mutex::new(None));
+
+/// Checks for updates in the background while `updates.check_automatically` is on, emitting
+/// `update:available` for each new version found. Called once from app setup.
+pub fn start(app: AppHandle) {
+    tauri::async_runtime::spawn(async move {
+        tokio::time::sleep(FIRST_CHECK_DELAY).await;
+        loop {
+            let enabled = settings::load().map(|s| s.updates.check_automatically && !s.offline_mode).unwrap_or(false);
+            if enabled {
+                match updates_check().await {
+                    Ok(check) if check.available => {
+                        let version = check.latest.as_ref().map(|r| r.version.clone());
+                        let mut announced = ANNOUNCED.lock().unwrap_or_else(|e| e.into_inner());
+                        if *announced != version {
+                            *announced = version;
+                            let _ = app.emit(UPDATE_AVAILABLE_EVENT, check);
+                        }
+                    }
+                    Ok(_) => {}
+                    Err(e) => tracing::debug!("update check failed: {e}"),
+                }
+            }
+            tokio::time::sleep(CHECK_INTERVAL).await;
+        }
+    });
+}
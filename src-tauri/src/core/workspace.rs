@@ -1,6 +1,8 @@
 use anyhow::{anyhow, Result};
+use ignore::WalkBuilder;
 use serde::{Deserialize, Serialize};
-use std::path::PathBuf;
+use std::fs;
+use std::path::{Path, PathBuf};
 
 use super::settings;
 
@@ -8,6 +10,17 @@ use super::settings;
 pub struct WorkspaceInfo {
     pub root: Option<String>,
     pub recent: Vec<String>,
+    /// Every folder in the workspace, primary root first.
+    pub roots: Vec<WorkspaceRoot>,
+    /// `None` until the user decides whether to trust the workspace.
+    pub trusted: Option<bool>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WorkspaceRoot {
+    /// Folder name used as the first path segment when the workspace has several roots.
+    pub name: String,
+    pub path: String,
 }
 
 pub fn workspace_get() -> Result<WorkspaceInfo> {
@@ -15,12 +28,419 @@ pub fn workspace_get() -> Result<WorkspaceInfo> {
     Ok(WorkspaceInfo {
         root: s.workspace_root.clone(),
         recent: s.recent_workspaces.clone(),
+        roots: workspace_roots(&s),
+        trusted: workspace_trust(&s),
     })
 }
 
-pub fn workspace_set(root: Option<String>) -> Result<WorkspaceInfo> {
-    let mut s = settings::load()?;
+/// Trust for the open workspace: untrusted if any folder is, undecided if any folder is
+/// undecided. `Some(true)` when no workspace is open, since there's nothing to protect.
+pub fn workspace_trust(s: &settings::AppSettings) -> Option<bool> {
+    let mut out = Some(true);
+    for r in workspace_roots(s) {
+        match s.workspace_trust.get(&r.path) {
+            Some(false) => return Some(false),
+            Some(true) => {}
+            None => out = None,
+        }
+    }
+    out
+}
+
+pub fn is_trusted(s: &settings::AppSettings) -> bool {
+    workspace_trust(s) == Some(true)
+}
+
+/// Where `abs` really points once symlinks are followed. For a path that doesn't exist yet,
+/// the nearest existing ancestor is resolved and the rest appended.
+pub fn real_path(abs: &Path) -> Result<PathBuf> {
+    let mut existing = abs.to_path_buf();
+    let mut rest = vec![];
+    while fs::symlink_metadata(&existing).is_err() {
+        match existing.file_name() {
+            Some(name) => rest.push(name.to_os_string()),
+            None => break,
+        }
+        existing.pop();
+    }
+    let mut real = existing
+        .canonicalize()
+        .map_err(|e| anyhow!("cannot resolve {}: {e}", existing.display()))?;
+    for name in rest.iter().rev() {
+        real.push(name);
+    }
+    Ok(real)
+}
+
+/// Fails if `abs`, a path under `root`, leads outside `root` through a symlink. Trusted
+/// workspaces can turn the check off with `allow_symlink_escape`.
+pub fn ensure_contained(root: &Path, abs: &Path) -> Result<()> {
+    let root_real = root.canonicalize().unwrap_or_else(|_| root.to_path_buf());
+    if real_path(abs)?.starts_with(&root_real) {
+        return Ok(());
+    }
+    let s = settings::load()?;
+    if s.allow_symlink_escape && is_trusted(&s) {
+        return Ok(());
+    }
+    Err(anyhow!(
+        "{} links to a location outside the workspace; trust the workspace and enable symlinks leaving it to allow this",
+        abs.display()
+    ))
+}
+
+/// Fails when the open workspace isn't trusted; `what` names the blocked feature.
+pub fn ensure_trusted(what: &str) -> Result<()> {
+    let s = settings::load()?;
+    if is_trusted(&s) {
+        return Ok(());
+    }
+    Err(anyhow!("{what} is disabled because this workspace is not trusted"))
+}
+
+pub fn workspace_trust_get() -> Result<Option<bool>> {
+    Ok(workspace_trust(&settings::load()?))
+}
+
+/// Records the trust decision for every folder of the open workspace.
+pub fn workspace_trust_set(trusted: bool) -> Result<WorkspaceInfo> {
+    settings::update(|s| {
+        let roots = workspace_roots(s);
+        if roots.is_empty() {
+            return Err(anyhow!("no workspace is open"));
+        }
+        for r in roots {
+            s.workspace_trust.insert(r.path, trusted);
+        }
+        Ok(())
+    })?;
+    workspace_get()
+}
+
+const MAX_RECENT_FILES: usize = 50;
+
+fn current_root(s: &settings::AppSettings) -> Result<String> {
+    s.workspace_root.clone().ok_or_else(|| anyhow!("no workspace is open"))
+}
+
+fn normalize_file_path(path: &str) -> Result<String> {
+    let p = path.trim().replace('\\', "/");
+    let p = p.trim_matches('/');
+    if p.is_empty() {
+        return Err(anyhow!("path is required"));
+    }
+    Ok(p.to_string())
+}
+
+fn file_exists(path: &str) -> bool {
+    split_workspace_path(path)
+        .ok()
+        .flatten()
+        .is_some_and(|(root, inner)| root.path.join(inner).is_file())
+}
+
+/// Recent and pinned files of the open workspace. Files that no longer exist are left out.
+pub fn recent_files_get() -> Result<settings::WorkspaceFiles> {
+    let s = settings::load()?;
+    let mut files = s.workspace_files.get(&current_root(&s)?).cloned().unwrap_or_default();
+    files.recent.retain(|p| file_exists(p));
+    files.pinned.retain(|p| file_exists(p));
+    Ok(files)
+}
+
+/// Moves `path` to the front of the open workspace's recent files.
+pub fn recent_file_add(path: &str) -> Result<settings::WorkspaceFiles> {
+    let path = normalize_file_path(path)?;
+    settings::update(|s| {
+        let root = current_root(s)?;
+        let files = s.workspace_files.entry(root).or_default();
+        files.recent.retain(|p| p != &path);
+        files.recent.insert(0, path.clone());
+        files.recent.truncate(MAX_RECENT_FILES);
+        Ok(())
+    })?;
+    recent_files_get()
+}
+
+pub fn file_pin(path: &str) -> Result<settings::WorkspaceFiles> {
+    let path = normalize_file_path(path)?;
+    if !file_exists(&path) {
+        return Err(anyhow!("file not found: {path}"));
+    }
+    settings::update(|s| {
+        let root = current_root(s)?;
+        let files = s.workspace_files.entry(root).or_default();
+        if !files.pinned.contains(&path) {
+            files.pinned.push(path.clone());
+        }
+        Ok(())
+    })?;
+    recent_files_get()
+}
+
+pub fn file_unpin(path: &str) -> Result<settings::WorkspaceFiles> {
+    let path = normalize_file_path(path)?;
+    settings::update(|s| {
+        let root = current_root(s)?;
+        if let Some(files) = s.workspace_files.get_mut(&root) {
+            files.pinned.retain(|p| p != &path);
+        }
+        Ok(())
+    })?;
+    recent_files_get()
+}
+
+fn non_empty(v: &str) -> Option<&str> {
+    Some(v.trim()).filter(|v| !v.is_empty())
+}
+
+/// The workspace's folders with unique display names (`app`, `app-2`, ...).
+pub fn workspace_roots(s: &settings::AppSettings) -> Vec<WorkspaceRoot> {
+    let Some(primary) = s.workspace_root.as_deref().and_then(non_empty) else {
+        return vec![];
+    };
+    let mut out: Vec<WorkspaceRoot> = vec![];
+    for path in std::iter::once(primary).chain(s.workspace_extra_roots.iter().filter_map(|r| non_empty(r))) {
+        if out.iter().any(|r| r.path == path) {
+            continue;
+        }
+        let base = Path::new(path)
+            .file_name()
+            .map(|n| n.to_string_lossy().to_string())
+            .filter(|n| !n.is_empty())
+            .unwrap_or_else(|| "root".to_string());
+        let mut name = base.clone();
+        let mut i = 2;
+        while out.iter().any(|r| r.name == name) {
+            name = format!("{base}-{i}");
+            i += 1;
+        }
+        out.push(WorkspaceRoot {
+            name,
+            path: path.to_string(),
+        });
+    }
+    out
+}
+
+/// A root folder as seen by path-addressing code: `prefix` is the root name that workspace
+/// paths start with, or `None` for a single-root workspace whose paths are plain relative paths.
+#[derive(Debug, Clone)]
+pub struct OpenRoot {
+    pub prefix: Option<String>,
+    pub path: PathBuf,
+}
 
+impl OpenRoot {
+    /// Workspace path for a path relative to this root (`/`-separated).
+    pub fn workspace_path(&self, rel: &str) -> String {
+        match (&self.prefix, rel.is_empty()) {
+            (Some(p), true) => p.clone(),
+            (Some(p), false) => format!("{p}/{rel}"),
+            (None, _) => rel.to_string(),
+        }
+    }
+
+    /// Walks this root as the file list and search see it: `.gitignore`, `.ignore` and
+    /// `.git/info/exclude` rules apply unless `include_ignored`, hidden files (see `is_hidden`)
+    /// are skipped unless `include_hidden`, links are not followed, and `.git` itself is
+    /// always skipped.
+    pub fn walk(&self, include_ignored: bool, include_hidden: bool) -> ignore::Walk {
+        self.walk_builder(&self.path, include_ignored, include_hidden).build()
+    }
+
+    /// `walk` spread over a thread per core, for callers that do real work per file.
+    pub fn walk_parallel(&self, include_ignored: bool, include_hidden: bool) -> ignore::WalkParallel {
+        self.walk_builder(&self.path, include_ignored, include_hidden).build_parallel()
+    }
+
+    /// `walk_parallel` over the folder `dir` inside this root. Ignore files in the folders
+    /// above it still apply.
+    pub fn walk_parallel_in(&self, dir: &Path, include_ignored: bool, include_hidden: bool) -> ignore::WalkParallel {
+        self.walk_builder(dir, include_ignored, include_hidden).build_parallel()
+    }
+
+    fn walk_builder(&self, start: &Path, include_ignored: bool, include_hidden: bool) -> WalkBuilder {
+        let mut builder = WalkBuilder::new(start);
+        builder
+            .hidden(false)
+            .parents(!include_ignored)
+            .ignore(!include_ignored)
+            .git_ignore(!include_ignored)
+            .git_exclude(!include_ignored)
+            .git_global(false)
+            .require_git(false)
+            .filter_entry(move |e| {
+                if e.file_name() == ".git" {
+                    return false;
+                }
+                // The starting folder may well be a dot-folder the user chose to open.
+                if include_hidden || e.depth() == 0 {
+                    return true;
+                }
+                #[cfg(windows)]
+                let meta = e.metadata().ok();
+                #[cfg(not(windows))]
+                let meta: Option<fs::Metadata> = None;
+                !is_hidden(&e.file_name().to_string_lossy(), meta.as_ref())
+            });
+        builder
+    }
+}
+
+// Bookkeeping files macOS and Windows leave in folders they have shown.
+const OS_JUNK: &[&str] = &[
+    ".DS_Store",
+    ".AppleDouble",
+    ".Spotlight-V100",
+    ".Trashes",
+    ".fseventsd",
+    "Thumbs.db",
+    "ehthumbs.db",
+    "desktop.ini",
+    "$RECYCLE.BIN",
+];
+
+/// Whether a directory entry is hidden: dotfiles, OS bookkeeping files such as `.DS_Store`
+/// and `Thumbs.db`, and on Windows anything with the hidden or system attribute.
+pub fn is_hidden(name: &str, meta: Option<&fs::Metadata>) -> bool {
+    if name.starts_with('.') || OS_JUNK.iter().any(|j| j.eq_ignore_ascii_case(name)) {
+        return true;
+    }
+    #[cfg(windows)]
+    {
+        use std::os::windows::fs::MetadataExt;
+        const FILE_ATTRIBUTE_HIDDEN: u32 = 0x2;
+        const FILE_ATTRIBUTE_SYSTEM: u32 = 0x4;
+        if meta.is_some_and(|m| m.file_attributes() & (FILE_ATTRIBUTE_HIDDEN | FILE_ATTRIBUTE_SYSTEM) != 0) {
+            return true;
+        }
+    }
+    #[cfg(not(windows))]
+    let _ = meta;
+    false
+}
+
+/// `is_hidden` for an entry being listed. Only Windows needs the metadata, so elsewhere the
+/// check costs no extra `stat`.
+pub fn is_hidden_entry(e: &fs::DirEntry) -> bool {
+    let name = e.file_name();
+    #[cfg(windows)]
+    let meta = e.metadata().ok();
+    #[cfg(not(windows))]
+    let meta: Option<fs::Metadata> = None;
+    is_hidden(&name.to_string_lossy(), meta.as_ref())
+}
+
+/// Whether listings should include hidden files when the caller doesn't say: the
+/// `show_hidden_files` setting.
+pub fn include_hidden(explicit: Option<bool>) -> bool {
+    explicit.unwrap_or_else(|| settings::load().map(|s| s.show_hidden_files).unwrap_or(true))
+}
+
+/// Whether walks should include ignored files when the caller doesn't say: the
+/// `include_ignored_files` setting.
+pub fn include_ignored(explicit: Option<bool>) -> bool {
+    explicit.unwrap_or_else(|| settings::load().map(|s| s.include_ignored_files).unwrap_or(false))
+}
+
+pub fn open_roots() -> Result<Vec<OpenRoot>> {
+    let s = settings::load()?;
+    let roots = workspace_roots(&s);
+    if roots.is_empty() {
+        return Err(anyhow!("no workspace is open"));
+    }
+    let multi = roots.len() > 1;
+    let mut out = Vec::with_capacity(roots.len());
+    for r in roots {
+        let pb = PathBuf::from(&r.path);
+        if !pb.exists() {
+            return Err(anyhow!("workspace path does not exist: {}", r.path));
+        }
+        if !pb.is_dir() {
+            return Err(anyhow!("workspace path is not a directory: {}", r.path));
+        }
+        out.push(OpenRoot {
+            prefix: multi.then_some(r.name),
+            path: pb,
+        });
+    }
+    Ok(out)
+}
+
+/// Splits a workspace path into its root and the remainder. In a multi-root workspace the
+/// first segment must name a root; `""` resolves to `None` (the virtual top level).
+pub fn split_workspace_path(path: &str) -> Result<Option<(OpenRoot, String)>> {
+    let roots = open_roots()?;
+    let trimmed = path.trim().trim_start_matches(['/', '\\']);
+    if roots.len() == 1 {
+        let root = roots.into_iter().next().ok_or_else(|| anyhow!("no workspace is open"))?;
+        return Ok(Some((root, trimmed.to_string())));
+    }
+    if trimmed.is_empty() {
+        return Ok(None);
+    }
+    let (first, rest) = trimmed.split_once(['/', '\\']).unwrap_or((trimmed, ""));
+    let root = roots
+        .into_iter()
+        .find(|r| r.prefix.as_deref() == Some(first))
+        .ok_or_else(|| anyhow!("unknown workspace root: {first}"))?;
+    Ok(Some((root, rest.to_string())))
+}
+
+fn validate_root_dir(path: &str) -> Result<String> {
+    let p = non_empty(path).ok_or_else(|| anyhow!("path is required"))?;
+    let pb = PathBuf::from(p);
+    if !pb.exists() {
+        return Err(anyhow!("workspace path does not exist"));
+    }
+    if !pb.is_dir() {
+        return Err(anyhow!("workspace path is not a directory"));
+    }
+    Ok(p.to_string())
+}
+
+/// Adds a folder to the open workspace (or opens it, if none is open).
+pub fn workspace_add_root(path: &str) -> Result<WorkspaceInfo> {
+    let p = validate_root_dir(path)?;
+    settings::update(|s| {
+        match s.workspace_root.as_deref().and_then(non_empty) {
+            None => s.workspace_root = Some(p.clone()),
+            Some(primary) if primary == p => {}
+            Some(_) => {
+                if !s.workspace_extra_roots.contains(&p) {
+                    s.workspace_extra_roots.push(p.clone());
+                }
+            }
+        }
+        Ok(())
+    })?;
+    workspace_get()
+}
+
+/// Removes a folder (by path or root name). Removing the primary root promotes the next one.
+pub fn workspace_remove_root(root: &str) -> Result<WorkspaceInfo> {
+    let key = non_empty(root).ok_or_else(|| anyhow!("root is required"))?.to_string();
+    settings::update(|s| {
+        let target = workspace_roots(s)
+            .into_iter()
+            .find(|r| r.path == key || r.name == key)
+            .ok_or_else(|| anyhow!("not a workspace root: {key}"))?;
+        if s.workspace_root.as_deref().map(|v| v.trim()) == Some(target.path.as_str()) {
+            s.workspace_root = if s.workspace_extra_roots.is_empty() {
+                None
+            } else {
+                Some(s.workspace_extra_roots.remove(0))
+            };
+        } else {
+            s.workspace_extra_roots.retain(|r| r.trim() != target.path);
+        }
+        Ok(())
+    })?;
+    workspace_get()
+}
+
+pub fn workspace_set(root: Option<String>) -> Result<WorkspaceInfo> {
     let normalized = root
         .as_deref()
         .map(|v| v.trim())
@@ -35,14 +455,21 @@ pub fn workspace_set(root: Option<String>) -> Result<WorkspaceInfo> {
         if !pb.is_dir() {
             return Err(anyhow!("workspace path is not a directory"));
         }
-
-        s.recent_workspaces.retain(|x| x != p);
-        s.recent_workspaces.insert(0, p.clone());
-        s.recent_workspaces.truncate(10);
     }
 
-    s.workspace_root = normalized;
-    settings::store(&s)?;
+    // Only touch the workspace fields, under the settings lock, so a concurrent theme or
+    // provider change isn't lost.
+    settings::update(|s| {
+        if let Some(ref p) = normalized {
+            s.recent_workspaces.retain(|x| x != p);
+            s.recent_workspaces.insert(0, p.clone());
+            s.recent_workspaces.truncate(10);
+        }
+        s.workspace_root = normalized;
+        // Opening a folder starts a fresh single-root workspace.
+        s.workspace_extra_roots.clear();
+        Ok(())
+    })?;
     workspace_get()
 }
 
@@ -59,3 +486,33 @@ pub fn workspace_pick_file() -> Result<Option<String>> {
         .pick_file();
     Ok(picked.map(|p| p.to_string_lossy().to_string()))
 }
+
+// Checked in order; the first non-empty file wins.
+const INSTRUCTION_FILES: &[&str] = &[".pompora/instructions.md", "AGENTS.md", ".cursorrules"];
+const INSTRUCTIONS_MAX_BYTES: usize = 32 * 1024;
+
+/// Project-specific AI instructions committed alongside the code, if any.
+pub fn load_instructions(root: &Path) -> Option<(String, String)> {
+    for name in INSTRUCTION_FILES {
+        let path = root.join(name);
+        if !path.is_file() {
+            continue;
+        }
+        let Ok(raw) = fs::read_to_string(&path) else {
+            continue;
+        };
+        let mut text = raw.trim().to_string();
+        if text.is_empty() {
+            continue;
+        }
+        if text.len() > INSTRUCTIONS_MAX_BYTES {
+            let mut cut = INSTRUCTIONS_MAX_BYTES;
+            while !text.is_char_boundary(cut) {
+                cut -= 1;
+            }
+            text.truncate(cut);
+        }
+        return Some((name.to_string(), text));
+    }
+    None
+}
@@ -0,0 +1,82 @@
+use ec4rs::property::{EndOfLine, FinalNewline, TrimTrailingWs};
+use std::path::Path;
+
+use super::settings::{self, LineEnding, WritePolicy};
+use super::workspace;
+
+// What `.editorconfig` files say about `path`. Unreadable or malformed configs count as silent.
+fn from_editorconfig(path: &Path) -> WritePolicy {
+    let Ok(props) = ec4rs::properties_of(path) else {
+        return WritePolicy::default();
+    };
+    WritePolicy {
+        line_endings: match props.get::<EndOfLine>() {
+            Ok(EndOfLine::Lf) => Some(LineEnding::Lf),
+            Ok(EndOfLine::CrLf) => Some(LineEnding::Crlf),
+            // Classic Mac `cr` isn't something to convert files to.
+            _ => None,
+        },
+        trim_trailing_whitespace: match props.get::<TrimTrailingWs>() {
+            Ok(TrimTrailingWs::Value(v)) => Some(v),
+            _ => None,
+        },
+        insert_final_newline: match props.get::<FinalNewline>() {
+            Ok(FinalNewline::Value(v)) => Some(v),
+            _ => None,
+        },
+    }
+}
+
+/// The policy for saving `path` (absolute): the workspace's own policy, then `.editorconfig`,
+/// then the global `write_policy` setting, field by field.
+pub fn policy_for(path: &Path) -> WritePolicy {
+    let Ok(s) = settings::load() else {
+        return from_editorconfig(path);
+    };
+    let workspace = workspace::workspace_roots(&s)
+        .into_iter()
+        .find(|r| path.starts_with(&r.path))
+        .and_then(|r| s.workspace_write_policies.get(&r.path).cloned())
+        .unwrap_or_default();
+    workspace.or(&from_editorconfig(path)).or(&s.write_policy)
+}
+
+/// Applies `policy` to `text`. Trailing whitespace means spaces and tabs; a final newline is
+/// only ever added, never removed, and uses the file's line ending.
+pub fn apply(text: &str, policy: &WritePolicy) -> String {
+    let eol = match policy.line_endings {
+        Some(LineEnding::Lf) => Some("\n"),
+        Some(LineEnding::Crlf) => Some("\r\n"),
+        None => None,
+    };
+    let trim = policy.trim_trailing_whitespace == Some(true);
+    let final_newline = policy.insert_final_newline == Some(true);
+    if eol.is_none() && !trim && !final_newline {
+        return text.to_string();
+    }
+
+    let mut out = String::with_capacity(text.len() + 2);
+    let mut last_term = "";
+    for piece in text.split_inclusive('\n') {
+        let (body, term) = match piece.strip_suffix("\r\n") {
+            Some(b) => (b, "\r\n"),
+            None => match piece.strip_suffix('\n') {
+                Some(b) => (b, "\n"),
+                None => (piece, ""),
+            },
+        };
+        out.push_str(if trim { body.trim_end_matches([' ', '\t']) } else { body });
+        let term = match (term, eol) {
+            ("", _) => "",
+            (_, Some(e)) => e,
+            (t, None) => t,
+        };
+        out.push_str(term);
+        last_term = term;
+    }
+    if final_newline && !out.is_empty() && last_term.is_empty() {
+        let nl = eol.unwrap_or(if text.contains("\r\n") { "\r\n" } else { "\n" });
+        out.push_str(nl);
+    }
+    out
+}
@@ -0,0 +1,88 @@
+// Windows Subsystem for Linux: running terminals and tasks inside a distribution, with Windows
+// paths translated to the ones the distribution sees.
+
+/// Whether `program` is the WSL launcher (`wsl.exe`).
+pub fn is_wsl(program: &str) -> bool {
+    let name = program.rsplit(['\\', '/']).next().unwrap_or(program);
+    name.eq_ignore_ascii_case("wsl") || name.eq_ignore_ascii_case("wsl.exe")
+}
+
+// `\\wsl$\Ubuntu\home\me` or `\\wsl.localhost\Ubuntu\home\me` split into the distribution and
+// the rest (`home\me`).
+fn split_share(path: &str) -> Option<(&str, &str)> {
+    let rest = path.strip_prefix(r"\\").or_else(|| path.strip_prefix("//"))?;
+    let (host, rest) = rest.split_once(['\\', '/'])?;
+    if !host.eq_ignore_ascii_case("wsl$") && !host.eq_ignore_ascii_case("wsl.localhost") {
+        return None;
+    }
+    let (distro, rest) = rest.split_once(['\\', '/']).unwrap_or((rest, ""));
+    (!distro.is_empty()).then_some((distro, rest))
+}
+
+/// The distribution `path` belongs to, for a path into one from Windows (`\\wsl$\<distro>\...`).
+pub fn distro_of(path: &str) -> Option<String> {
+    split_share(path).map(|(d, _)| d.to_string())
+}
+
+/// `path` as WSL sees it: `C:\src\app` becomes `/mnt/c/src/app` and `\\wsl$\Ubuntu\home\me`
+/// becomes `/home/me`. `None` for paths it can't reach that way, like network shares.
+pub fn to_wsl_path(path: &str) -> Option<String> {
+    if let Some((_, rest)) = split_share(path) {
+        return Some(format!("/{}", rest.replace('\\', "/").trim_end_matches('/')));
+    }
+    let mut chars = path.chars();
+    let drive = chars.next().filter(|c| c.is_ascii_alphabetic())?;
+    let rest = chars.as_str().strip_prefix(':')?;
+    if !rest.is_empty() && !rest.starts_with(['\\', '/']) {
+        return None;
+    }
+    let rest = rest.replace('\\', "/");
+    Some(format!("/mnt/{}{}", drive.to_ascii_lowercase(), rest.trim_end_matches('/')))
+}
+
+/// Installed distributions, the default first. Empty without WSL.
+#[cfg(windows)]
+pub fn distros() -> Vec<String> {
+    use std::os::windows::process::CommandExt;
+    const CREATE_NO_WINDOW: u32 = 0x0800_0000;
+    let Ok(out) = std::process::Command::new("wsl.exe")
+        .args(["--list", "--quiet"])
+        .creation_flags(CREATE_NO_WINDOW)
+        .output()
+    else {
+        return vec![];
+    };
+    if !out.status.success() {
+        return vec![];
+    }
+    // wsl.exe writes UTF-16.
+    let units: Vec<u16> = out.stdout.chunks_exact(2).map(|c| u16::from_le_bytes([c[0], c[1]])).collect();
+    String::from_utf16_lossy(&units)
+        .lines()
+        .map(|l| l.trim().trim_start_matches('\u{feff}').to_string())
+        // Docker Desktop's own distributions aren't for working in.
+        .filter(|l| !l.is_empty() && !l.starts_with("docker-desktop"))
+        .collect()
+}
+
+/// Arguments to `wsl.exe` that run `program args` in `cwd` (a Windows path) with `env` set,
+/// in the distribution `cwd` lives in, or the default one.
+pub fn exec_args<'a>(
+    cwd: &str,
+    env: impl IntoIterator<Item = (&'a String, &'a String)>,
+    program: &str,
+    args: &[String],
+) -> Vec<String> {
+    let mut out = vec![];
+    if let Some(distro) = distro_of(cwd) {
+        out.extend(["-d".to_string(), distro]);
+    }
+    if let Some(dir) = to_wsl_path(cwd) {
+        out.extend(["--cd".to_string(), dir]);
+    }
+    out.extend(["-e".to_string(), "env".to_string()]);
+    out.extend(env.into_iter().map(|(k, v)| format!("{k}={v}")));
+    out.push(program.to_string());
+    out.extend(args.iter().cloned());
+    out
+}
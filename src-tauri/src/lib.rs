@@ -1,19 +1,18 @@
 mod core;
 
-use core::{ai, auth, fsops, search, secrets, settings, terminal, workspace};
+use core::{ai, ai_queue, archive, auth, chat_export, command_safety, commit_lint, dependencies, dependency_audit, diff, dotenv, fs_journal, fs_watch, fsops, fuzzy, git, github, http_client, lint, logging, lsp, merge, outline, projects, notifications, ports, sandbox, search, secrets, settings, shells, snippets, spellcheck, tasks, telemetry, terminal, terminal_history, todos, updates, workspace};
 use tauri_plugin_dialog::DialogExt;
 
-#[cfg(debug_assertions)]
-fn debug_log(msg: &str) {
-    println!("{msg}");
-}
-
-#[cfg(not(debug_assertions))]
-fn debug_log(_msg: &str) {}
-
 #[tauri::command]
-fn terminal_start(app: tauri::AppHandle, cols: u16, rows: u16, cwd: Option<String>) -> Result<String, String> {
-    terminal::terminal_start(app, cols, rows, cwd)
+fn terminal_start(
+    app: tauri::AppHandle,
+    cols: u16,
+    rows: u16,
+    cwd: Option<String>,
+    profile: Option<String>,
+    env_file: Option<String>,
+) -> Result<String, String> {
+    terminal::terminal_start(app, cols, rows, cwd, profile, env_file)
 }
 
 #[tauri::command]
@@ -27,8 +26,71 @@ fn terminal_resize(id: String, cols: u16, rows: u16) -> Result<(), String> {
 }
 
 #[tauri::command]
-fn terminal_kill(id: String) -> Result<(), String> {
-    terminal::terminal_kill(id)
+fn terminal_ack(id: String, units: usize) -> Result<(), String> {
+    terminal::terminal_ack(&id, units)
+}
+
+#[tauri::command]
+fn terminal_kill(id: String, force: Option<bool>) -> Result<(), String> {
+    terminal::terminal_kill(id, force.unwrap_or(false))
+}
+
+#[tauri::command]
+fn terminal_search(
+    id: String,
+    query: String,
+    regex: Option<bool>,
+    case_sensitive: Option<bool>,
+) -> Result<terminal::TerminalSearchResults, String> {
+    terminal::terminal_search(&id, &query, regex.unwrap_or(false), case_sensitive.unwrap_or(false))
+}
+
+#[tauri::command]
+fn terminal_detect_shells() -> Vec<shells::DetectedShell> {
+    shells::detect()
+}
+
+#[tauri::command]
+fn terminal_history(query: Option<String>, limit: Option<usize>) -> Result<Vec<terminal_history::HistoryEntry>, String> {
+    terminal_history::terminal_history(query.as_deref().unwrap_or(""), limit.unwrap_or(200)).map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+fn terminal_history_clear() -> Result<(), String> {
+    terminal_history::terminal_history_clear().map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+fn terminal_list_sessions() -> Result<terminal::TerminalSessionList, String> {
+    terminal::terminal_list_sessions()
+}
+
+#[tauri::command]
+fn terminal_get_scrollback(id: String, lines: Option<u32>) -> Result<String, String> {
+    terminal::terminal_get_scrollback(&id, lines.map(|n| n.max(1) as usize))
+}
+
+#[tauri::command]
+fn terminal_get_cwd(id: String) -> Result<Option<String>, String> {
+    terminal::terminal_get_cwd(&id)
+}
+
+#[tauri::command]
+fn terminal_commands(id: String) -> Result<Vec<terminal::TerminalCommand>, String> {
+    terminal::terminal_commands(&id)
+}
+
+#[tauri::command]
+fn terminal_command_output(id: String, seq: u64) -> Result<String, String> {
+    terminal::terminal_command_output(&id, seq)
+}
+
+#[tauri::command]
+async fn terminal_wait(id: String, timeout_ms: Option<u64>) -> Result<Option<terminal::TerminalExit>, String> {
+    let timeout = timeout_ms.map(std::time::Duration::from_millis);
+    tokio::task::spawn_blocking(move || terminal::terminal_wait(&id, timeout))
+        .await
+        .map_err(|e| e.to_string())?
 }
 
 #[tauri::command]
@@ -38,27 +100,141 @@ fn settings_get() -> Result<settings::AppSettings, String> {
 
 #[tauri::command]
 fn settings_set(next: settings::AppSettings) -> Result<(), String> {
-    settings::store(&next).map_err(|e| e.to_string())
+    settings::update(|s| {
+        *s = next;
+        s.validate()
+    })
+    .map(|_| ())
+    .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+fn settings_patch(patch: serde_json::Value) -> Result<settings::AppSettings, String> {
+    settings::patch(&patch).map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+fn settings_profile_list() -> Result<settings::SettingsProfiles, String> {
+    settings::profile_list().map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+fn settings_profile_switch(name: String) -> Result<settings::AppSettings, String> {
+    settings::profile_switch(&name).map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+fn settings_profile_delete(name: String) -> Result<settings::SettingsProfiles, String> {
+    settings::profile_delete(&name).map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+fn provider_key_status(provider: String, credential: Option<String>) -> Result<secrets::KeyStatus, String> {
+    secrets::provider_key_status(&provider, credential.as_deref())
+}
+
+#[tauri::command]
+fn provider_key_set(
+    provider: String,
+    api_key: String,
+    encryption_password: Option<String>,
+    credential: Option<String>,
+) -> Result<(), String> {
+    secrets::provider_key_set(&provider, &api_key, encryption_password.as_deref(), credential.as_deref(), "provider_key_set")
+}
+
+#[tauri::command]
+fn provider_key_get(
+    provider: String,
+    encryption_password: Option<String>,
+    credential: Option<String>,
+) -> Result<String, String> {
+    secrets::provider_key_get(&provider, encryption_password.as_deref(), credential.as_deref(), "provider_key_get")
+}
+
+#[tauri::command]
+fn provider_key_clear(provider: String, credential: Option<String>) -> Result<(), String> {
+    secrets::provider_key_clear(&provider, credential.as_deref(), "provider_key_clear")
+}
+
+#[tauri::command]
+fn provider_key_list(provider: String) -> Result<Vec<secrets::CredentialInfo>, String> {
+    secrets::provider_key_list(&provider)
+}
+
+#[tauri::command]
+fn provider_key_activate(provider: String, credential: String) -> Result<(), String> {
+    secrets::provider_key_activate(&provider, &credential)
+}
+
+#[tauri::command]
+fn secrets_migrate(
+    backend: settings::SecretBackend,
+    encryption_password: Option<String>,
+) -> Result<Vec<secrets::MigrationEntry>, String> {
+    secrets::secrets_migrate(backend, encryption_password.as_deref())
+}
+
+#[tauri::command]
+fn secrets_export(
+    password: String,
+    dest: Option<String>,
+    encryption_password: Option<String>,
+) -> Result<String, String> {
+    secrets::secrets_export(&password, dest.as_deref(), encryption_password.as_deref())
+}
+
+#[tauri::command]
+fn secrets_import(
+    path: String,
+    password: String,
+    encryption_password: Option<String>,
+) -> Result<Vec<secrets::ImportEntry>, String> {
+    secrets::secrets_import(&path, &password, encryption_password.as_deref())
+}
+
+#[tauri::command]
+fn secrets_unlock(password: String) -> Result<secrets::LockStatus, String> {
+    secrets::secrets_unlock(&password)
+}
+
+#[tauri::command]
+fn secrets_lock() -> Result<secrets::LockStatus, String> {
+    secrets::secrets_lock()
+}
+
+#[tauri::command]
+fn secrets_lock_status() -> Result<secrets::LockStatus, String> {
+    secrets::secrets_lock_status()
 }
 
 #[tauri::command]
-fn provider_key_status(provider: String) -> Result<secrets::KeyStatus, String> {
-    secrets::provider_key_status(&provider)
+fn workspace_key_override_get(root: Option<String>) -> Result<Option<settings::WorkspaceKeyOverride>, String> {
+    secrets::workspace_key_override_get(root.as_deref())
 }
 
 #[tauri::command]
-fn provider_key_set(provider: String, api_key: String, encryption_password: Option<String>) -> Result<(), String> {
-    secrets::provider_key_set(&provider, &api_key, encryption_password.as_deref())
+fn workspace_key_override_set(
+    root: Option<String>,
+    provider: Option<String>,
+    credential: Option<String>,
+) -> Result<Option<settings::WorkspaceKeyOverride>, String> {
+    secrets::workspace_key_override_set(root.as_deref(), provider.as_deref(), credential.as_deref())
 }
 
 #[tauri::command]
-fn provider_key_get(provider: String, encryption_password: Option<String>) -> Result<String, String> {
-    secrets::provider_key_get(&provider, encryption_password.as_deref())
+fn secrets_audit() -> Vec<core::private_fs::PermissionIssue> {
+    secrets::secrets_audit()
 }
 
 #[tauri::command]
-fn provider_key_clear(provider: String) -> Result<(), String> {
-    secrets::provider_key_clear(&provider)
+fn logs_tail(lines: Option<usize>, level: Option<settings::LogLevel>) -> Result<Vec<logging::LogEntry>, String> {
+    logging::logs_tail(lines.unwrap_or(500).min(20_000), level.unwrap_or(settings::LogLevel::Trace))
+}
+
+#[tauri::command]
+fn secrets_audit_log(limit: Option<usize>) -> Result<Vec<core::key_audit::KeyAuditEntry>, String> {
+    secrets::secrets_audit_log(limit.unwrap_or(200).min(5000))
 }
 
 #[tauri::command]
@@ -71,6 +247,11 @@ async fn auth_wait_login(state: String) -> Result<auth::AuthProfile, String> {
     auth::wait_login(&state).await.map_err(|e| e.to_string())
 }
 
+#[tauri::command]
+fn auth_cancel_login(state: String) {
+    auth::cancel_login(&state);
+}
+
 #[tauri::command]
 fn auth_get_profile() -> Result<Option<auth::AuthProfile>, String> {
     auth::load_profile().map_err(|e| e.to_string())
@@ -82,8 +263,18 @@ fn auth_logout() -> Result<(), String> {
 }
 
 #[tauri::command]
-async fn auth_get_credits() -> Result<auth::CreditsResponse, String> {
-    auth::fetch_credits().await.map_err(|e| e.to_string())
+async fn auth_refresh() -> Result<auth::AuthProfile, String> {
+    auth::refresh().await.map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+async fn auth_test_backend(url: Option<String>) -> Result<auth::BackendTest, String> {
+    auth::test_backend(url).await.map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+async fn auth_get_credits(refresh: Option<bool>) -> Result<auth::CreditsResponse, String> {
+    auth::get_credits(refresh.unwrap_or(false)).await.map_err(|e| e.to_string())
 }
 
 #[tauri::command]
@@ -92,9 +283,26 @@ fn workspace_get() -> Result<workspace::WorkspaceInfo, String> {
 }
 
 #[tauri::command]
-fn workspace_search(query: String, max_results: Option<u32>) -> Result<Vec<search::SearchMatch>, String> {
+fn workspace_search(
+    query: String,
+    max_results: Option<u32>,
+    include_ignored: Option<bool>,
+    options: Option<search::SearchOptions>,
+) -> Result<search::SearchResults, String> {
     let max = max_results.unwrap_or(200).min(2000) as usize;
-    search::workspace_search(&query, max).map_err(|e| e.to_string())
+    let started = std::time::Instant::now();
+    let res = search::workspace_search(&query, max, include_ignored, &options.unwrap_or_default());
+    telemetry::record_latency("workspace_search", started.elapsed());
+    res.map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+async fn workspace_fuzzy_find(query: String, max_results: Option<u32>) -> Result<Vec<fuzzy::FuzzyMatch>, String> {
+    let max = max_results.unwrap_or(100).min(1000) as usize;
+    tokio::task::spawn_blocking(move || fuzzy::workspace_fuzzy_find(&query, max))
+        .await
+        .map_err(|e| e.to_string())?
+        .map_err(|e| e.to_string())
 }
 
 #[tauri::command]
@@ -103,18 +311,18 @@ async fn debug_gemini_end_to_end(api_key: String) -> Result<String, String> {
     let api_key = api_key.trim();
     
     // 1) save key
-    secrets::provider_key_set(provider, api_key, None)?;
+    secrets::provider_key_set(provider, api_key, None, None, "debug_gemini_end_to_end")?;
 
     // 2) verify status + get
-    let status = secrets::provider_key_status(provider)?;
-    let stored = secrets::provider_key_get(provider, None)?;
+    let status = secrets::provider_key_status(provider, None)?;
+    let stored = secrets::provider_key_get(provider, None, None, "debug_gemini_end_to_end")?;
 
     // 3) call gemini directly using our ai module
     let test_message = ai::ChatMessage {
         role: "user".to_string(),
         content: "Respond with exactly: OK".to_string(),
     };
-    let resp = ai::ai_chat(vec![test_message], None, None)
+    let resp = ai::ai_chat(vec![test_message], None, None, ai_queue::RequestPriority::Interactive)
         .await
         .map_err(|e| format!("ai_chat failed: {e}"))?;
 
@@ -135,7 +343,7 @@ async fn test_gemini_api() -> Result<String, String> {
         content: "Hello! Please respond with just 'API test successful'".to_string(),
     };
     
-    match ai_chat(vec![test_message], None, None).await {
+    match ai_chat(vec![test_message], None, None, ai_queue::RequestPriority::Interactive).await {
         Ok(result) => Ok(format!("Gemini API test successful. Response: {}", result.output)),
         Err(e) => Err(format!("Gemini API test failed: {}", e)),
     }
@@ -146,7 +354,7 @@ async fn workspace_pick_folder(app: tauri::AppHandle) -> Result<Option<String>,
     use tokio::sync::oneshot;
     use std::time::Duration;
 
-    debug_log("workspace_pick_folder: invoked");
+    tracing::debug!("workspace_pick_folder: invoked");
 
     let (tx, rx) = oneshot::channel::<Option<String>>();
     app.dialog().file().pick_folder(move |file_path| {
@@ -161,12 +369,12 @@ async fn workspace_pick_folder(app: tauri::AppHandle) -> Result<Option<String>,
     {
         match tokio::time::timeout(Duration::from_secs(8), rx).await {
             Ok(Ok(out)) => {
-                debug_log(&format!("workspace_pick_folder: result={out:?}"));
+                tracing::debug!("workspace_pick_folder: result={out:?}");
                 Ok(out)
             }
             Ok(Err(e)) => Err(e.to_string()),
             Err(_) => {
-                debug_log("workspace_pick_folder: timeout on linux; falling back to rfd");
+                tracing::debug!("workspace_pick_folder: timeout on linux; falling back to rfd");
                 tokio::task::spawn_blocking(|| workspace::workspace_pick_folder())
                     .await
                     .map_err(|e| e.to_string())?
@@ -178,7 +386,7 @@ async fn workspace_pick_folder(app: tauri::AppHandle) -> Result<Option<String>,
     #[cfg(not(target_os = "linux"))]
     {
         let out = rx.await.map_err(|e| e.to_string())?;
-        debug_log(&format!("workspace_pick_folder: result={out:?}"));
+        tracing::debug!("workspace_pick_folder: result={out:?}");
         Ok(out)
     }
 }
@@ -188,7 +396,7 @@ async fn workspace_pick_file(app: tauri::AppHandle) -> Result<Option<String>, St
     use tokio::sync::oneshot;
     use std::time::Duration;
 
-    debug_log("workspace_pick_file: invoked");
+    tracing::debug!("workspace_pick_file: invoked");
 
     let (tx, rx) = oneshot::channel::<Option<String>>();
     app.dialog().file().pick_file(move |file_path| {
@@ -203,12 +411,12 @@ async fn workspace_pick_file(app: tauri::AppHandle) -> Result<Option<String>, St
     {
         match tokio::time::timeout(Duration::from_secs(8), rx).await {
             Ok(Ok(out)) => {
-                debug_log(&format!("workspace_pick_file: result={out:?}"));
+                tracing::debug!("workspace_pick_file: result={out:?}");
                 Ok(out)
             }
             Ok(Err(e)) => Err(e.to_string()),
             Err(_) => {
-                debug_log("workspace_pick_file: timeout on linux; falling back to rfd");
+                tracing::debug!("workspace_pick_file: timeout on linux; falling back to rfd");
                 tokio::task::spawn_blocking(|| workspace::workspace_pick_file())
                     .await
                     .map_err(|e| e.to_string())?
@@ -220,30 +428,114 @@ async fn workspace_pick_file(app: tauri::AppHandle) -> Result<Option<String>, St
     #[cfg(not(target_os = "linux"))]
     {
         let out = rx.await.map_err(|e| e.to_string())?;
-        debug_log(&format!("workspace_pick_file: result={out:?}"));
+        tracing::debug!("workspace_pick_file: result={out:?}");
         Ok(out)
     }
 }
 
 #[tauri::command]
-fn workspace_list_dir(rel_dir: Option<String>) -> Result<Vec<fsops::DirEntryInfo>, String> {
-    fsops::workspace_list_dir(rel_dir.as_deref()).map_err(|e| e.to_string())
+fn workspace_list_dir(
+    rel_dir: Option<String>,
+    include_hidden: Option<bool>,
+    depth: Option<u32>,
+) -> Result<Vec<fsops::DirEntryInfo>, String> {
+    let depth = depth.unwrap_or(1).clamp(1, 8);
+    fsops::workspace_list_dir(rel_dir.as_deref(), include_hidden, depth).map_err(|e| e.to_string())
 }
 
 #[tauri::command]
-fn workspace_list_files(max_files: Option<u32>) -> Result<Vec<String>, String> {
+fn workspace_list_files(
+    max_files: Option<u32>,
+    include_ignored: Option<bool>,
+    include_hidden: Option<bool>,
+) -> Result<Vec<String>, String> {
     let max = max_files.unwrap_or(20000).min(100000) as usize;
-    fsops::workspace_list_files(max).map_err(|e| e.to_string())
+    fsops::workspace_list_files(max, include_ignored, include_hidden).map_err(|e| e.to_string())
 }
 
 #[tauri::command]
-fn workspace_read_file(rel_path: String) -> Result<String, String> {
+async fn workspace_list_files_stream(
+    app: tauri::AppHandle,
+    request_id: u64,
+    max_files: Option<u32>,
+    batch_size: Option<u32>,
+    include_ignored: Option<bool>,
+    include_hidden: Option<bool>,
+) -> Result<usize, String> {
+    let max = max_files.unwrap_or(100_000).min(1_000_000) as usize;
+    let batch = batch_size.unwrap_or(1000).clamp(100, 10_000) as usize;
+    tokio::task::spawn_blocking(move || {
+        fsops::workspace_list_files_stream(&app, request_id, max, batch, include_ignored, include_hidden)
+    })
+    .await
+    .map_err(|e| e.to_string())?
+    .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+fn workspace_read_file(rel_path: String) -> Result<fsops::VersionedText, String> {
     fsops::workspace_read_file(&rel_path).map_err(|e| e.to_string())
 }
 
 #[tauri::command]
-fn workspace_write_file(rel_path: String, contents: String) -> Result<(), String> {
-    fsops::workspace_write_file(&rel_path, &contents).map_err(|e| e.to_string())
+fn workspace_read_file_binary(rel_path: String) -> Result<fsops::BinaryFile, String> {
+    fsops::workspace_read_file_binary(&rel_path).map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+fn workspace_read_file_range(rel_path: String, start_line: u64, end_line: u64) -> Result<fsops::FileRange, String> {
+    fsops::workspace_read_file_range(&rel_path, start_line, end_line).map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+fn workspace_read_file_bytes(rel_path: String, offset: u64, length: u64) -> Result<fsops::FileRange, String> {
+    fsops::workspace_read_file_bytes(&rel_path, offset, length).map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+fn workspace_write_file(
+    rel_path: String,
+    contents: String,
+    encoding: Option<String>,
+    bom: Option<bool>,
+    allow_protected: Option<bool>,
+) -> Result<fsops::WriteResult, String> {
+    fsops::workspace_write_file(
+        &rel_path,
+        &contents,
+        encoding.as_deref(),
+        bom.unwrap_or(false),
+        allow_protected.unwrap_or(false),
+    )
+    .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+async fn workspace_hash_file(rel_path: String, algo: Option<String>) -> Result<fsops::FileHash, String> {
+    tokio::task::spawn_blocking(move || fsops::workspace_hash_file(&rel_path, algo.as_deref().unwrap_or("sha256")))
+        .await
+        .map_err(|e| e.to_string())?
+        .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+fn workspace_write_file_if_unchanged(
+    rel_path: String,
+    contents: String,
+    expected_version: String,
+    encoding: Option<String>,
+    bom: Option<bool>,
+    allow_protected: Option<bool>,
+) -> Result<fsops::ConditionalWrite, String> {
+    fsops::workspace_write_file_if_unchanged(
+        &rel_path,
+        &contents,
+        &expected_version,
+        encoding.as_deref(),
+        bom.unwrap_or(false),
+        allow_protected.unwrap_or(false),
+    )
+    .map_err(|e| e.to_string())
 }
 
 #[tauri::command]
@@ -252,13 +544,38 @@ fn workspace_create_dir(rel_path: String) -> Result<(), String> {
 }
 
 #[tauri::command]
-fn workspace_delete(rel_path: String) -> Result<(), String> {
-    fsops::workspace_delete(&rel_path).map_err(|e| e.to_string())
+fn workspace_delete(rel_path: String, permanent: Option<bool>, allow_protected: Option<bool>) -> Result<(), String> {
+    fsops::workspace_delete(&rel_path, permanent, allow_protected.unwrap_or(false)).map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+fn workspace_rename(from_rel: String, to_rel: String, allow_protected: Option<bool>) -> Result<(), String> {
+    fsops::workspace_rename(&from_rel, &to_rel, allow_protected.unwrap_or(false)).map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+fn fsops_history(limit: Option<u32>) -> Vec<fs_journal::FsHistoryEntry> {
+    fs_journal::fsops_history(limit.unwrap_or(50).min(200) as usize)
 }
 
 #[tauri::command]
-fn workspace_rename(from_rel: String, to_rel: String) -> Result<(), String> {
-    fsops::workspace_rename(&from_rel, &to_rel).map_err(|e| e.to_string())
+async fn fsops_undo_last() -> Result<fs_journal::FsHistoryEntry, String> {
+    tokio::task::spawn_blocking(fs_journal::fsops_undo_last)
+        .await
+        .map_err(|e| e.to_string())?
+        .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+async fn workspace_batch_ops(
+    ops: Vec<fsops::FsOp>,
+    permanent: Option<bool>,
+    allow_protected: Option<bool>,
+) -> Result<usize, String> {
+    tokio::task::spawn_blocking(move || fsops::workspace_batch_ops(&ops, permanent, allow_protected.unwrap_or(false)))
+        .await
+        .map_err(|e| e.to_string())?
+        .map_err(|e| e.to_string())
 }
 
 #[tauri::command]
@@ -266,15 +583,548 @@ fn workspace_set(root: Option<String>) -> Result<workspace::WorkspaceInfo, Strin
     workspace::workspace_set(root).map_err(|e| e.to_string())
 }
 
+#[tauri::command]
+fn workspace_add_root(path: String) -> Result<workspace::WorkspaceInfo, String> {
+    workspace::workspace_add_root(&path).map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+fn workspace_remove_root(root: String) -> Result<workspace::WorkspaceInfo, String> {
+    workspace::workspace_remove_root(&root).map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+fn workspace_trust_get() -> Result<Option<bool>, String> {
+    workspace::workspace_trust_get().map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+fn workspace_trust_set(trusted: bool) -> Result<workspace::WorkspaceInfo, String> {
+    workspace::workspace_trust_set(trusted).map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+fn recent_files_get() -> Result<settings::WorkspaceFiles, String> {
+    workspace::recent_files_get().map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+fn recent_file_add(path: String) -> Result<settings::WorkspaceFiles, String> {
+    workspace::recent_file_add(&path).map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+fn file_pin(path: String) -> Result<settings::WorkspaceFiles, String> {
+    workspace::file_pin(&path).map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+fn file_unpin(path: String) -> Result<settings::WorkspaceFiles, String> {
+    workspace::file_unpin(&path).map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+fn workspace_detect_projects() -> Result<Vec<projects::DetectedProject>, String> {
+    projects::workspace_detect_projects().map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+async fn workspace_export_zip(
+    app: tauri::AppHandle,
+    dest: String,
+    include_globs: Option<Vec<String>>,
+    exclude_globs: Option<Vec<String>>,
+) -> Result<String, String> {
+    tokio::task::spawn_blocking(move || {
+        archive::workspace_export_zip(
+            &app,
+            &dest,
+            &include_globs.unwrap_or_default(),
+            &exclude_globs.unwrap_or_default(),
+        )
+    })
+    .await
+    .map_err(|e| e.to_string())?
+    .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+fn tasks_list() -> Result<Vec<tasks::TaskInfo>, String> {
+    tasks::tasks_list().map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+fn tasks_run(app: tauri::AppHandle, id: String) -> Result<String, String> {
+    tasks::tasks_run(app, &id).map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+fn tasks_stop(run_id: String, force: Option<bool>) -> Result<(), String> {
+    tasks::tasks_stop(&run_id, force.unwrap_or(false)).map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+async fn updates_check() -> Result<updates::UpdateCheck, String> {
+    updates::updates_check().await.map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+async fn chat_export(
+    transcript: chat_export::ChatTranscript,
+    format: Option<chat_export::ExportFormat>,
+    dest: Option<String>,
+) -> Result<String, String> {
+    tokio::task::spawn_blocking(move || {
+        chat_export::chat_export(&transcript, format.unwrap_or_default(), dest.as_deref())
+    })
+    .await
+    .map_err(|e| e.to_string())?
+    .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+async fn http_request(
+    method: String,
+    url: String,
+    headers: Option<Vec<settings::HttpHeader>>,
+    body: Option<String>,
+    auth: Option<settings::HttpAuth>,
+    timeout_ms: Option<u64>,
+) -> Result<http_client::HttpResponse, String> {
+    http_client::http_request(
+        &method,
+        &url,
+        &headers.unwrap_or_default(),
+        body,
+        auth.as_ref(),
+        timeout_ms.map(std::time::Duration::from_millis),
+    )
+    .await
+    .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+fn http_collections_list() -> Result<Vec<settings::HttpCollection>, String> {
+    http_client::http_collections_list().map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+fn http_collections_save(
+    collection: settings::HttpCollection,
+    previous_name: Option<String>,
+) -> Result<Vec<settings::HttpCollection>, String> {
+    http_client::http_collections_save(collection, previous_name.as_deref()).map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+fn http_collections_delete(name: String) -> Result<Vec<settings::HttpCollection>, String> {
+    http_client::http_collections_delete(&name).map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+fn env_files_list() -> Result<Vec<String>, String> {
+    dotenv::env_files_list().map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+fn env_file_read(path: String) -> Result<dotenv::EnvFile, String> {
+    dotenv::env_file_read(&path).map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+fn env_file_reveal(path: String, key: String) -> Result<String, String> {
+    dotenv::env_file_reveal(&path, &key).map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+fn env_file_set(path: String, key: String, value: Option<String>) -> Result<dotenv::EnvFile, String> {
+    dotenv::env_file_set(&path, &key, value.as_deref()).map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+async fn ports_list() -> Result<Vec<ports::ListeningPort>, String> {
+    tokio::task::spawn_blocking(ports::ports_list)
+        .await
+        .map_err(|e| e.to_string())?
+        .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+async fn dependencies_list(check_updates: Option<bool>) -> Result<dependencies::DependencyList, String> {
+    let mut list = tokio::task::spawn_blocking(dependencies::dependencies_list)
+        .await
+        .map_err(|e| e.to_string())?
+        .map_err(|e| e.to_string())?;
+    if check_updates.unwrap_or(false) {
+        if let Err(e) = dependencies::check_updates(&mut list).await {
+            list.errors.push(e.to_string());
+        }
+    }
+    Ok(list)
+}
+
+#[tauri::command]
+async fn dependencies_audit(app: tauri::AppHandle) -> Result<dependency_audit::AuditReport, String> {
+    dependency_audit::dependencies_audit(&app).await.map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+async fn github_auth_status(encryption_password: Option<String>) -> Result<github::GithubAuthStatus, String> {
+    github::auth_status(encryption_password.as_deref()).await.map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+async fn github_login_begin() -> Result<github::DeviceLogin, String> {
+    github::login_begin().await.map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+async fn github_login_wait(device_code: String, interval: u64, encryption_password: Option<String>) -> Result<String, String> {
+    github::login_wait(&device_code, interval, encryption_password.as_deref())
+        .await
+        .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+fn github_repo(root: Option<String>) -> Result<github::GithubRepo, String> {
+    github::origin_repo(root.as_deref()).map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+async fn github_pull_requests(
+    state: Option<String>,
+    root: Option<String>,
+    encryption_password: Option<String>,
+) -> Result<Vec<github::PullRequest>, String> {
+    github::pull_requests(state.as_deref(), root.as_deref(), encryption_password.as_deref())
+        .await
+        .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+async fn github_issues(
+    state: Option<String>,
+    root: Option<String>,
+    encryption_password: Option<String>,
+) -> Result<Vec<github::Issue>, String> {
+    github::issues(state.as_deref(), root.as_deref(), encryption_password.as_deref())
+        .await
+        .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+async fn github_pull_request_diff(
+    number: u64,
+    root: Option<String>,
+    encryption_password: Option<String>,
+) -> Result<String, String> {
+    github::pull_request_diff(number, root.as_deref(), encryption_password.as_deref())
+        .await
+        .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+async fn github_comment_post(
+    number: u64,
+    body: String,
+    root: Option<String>,
+    encryption_password: Option<String>,
+) -> Result<github::Comment, String> {
+    github::comment_post(number, &body, root.as_deref(), encryption_password.as_deref())
+        .await
+        .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+async fn spellcheck_file(rel_path: String, content: Option<String>) -> Result<spellcheck::SpellcheckResult, String> {
+    tokio::task::spawn_blocking(move || spellcheck::spellcheck_file(&rel_path, content))
+        .await
+        .map_err(|e| e.to_string())?
+        .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+fn spellcheck_add_word(rel_path: String, word: String) -> Result<String, String> {
+    spellcheck::spellcheck_add_word(&rel_path, &word).map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+async fn workspace_todos(tags: Option<Vec<String>>) -> Result<todos::TodoList, String> {
+    tokio::task::spawn_blocking(move || todos::workspace_todos(tags))
+        .await
+        .map_err(|e| e.to_string())?
+        .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+fn snippets_list(language: Option<String>) -> Result<Vec<settings::Snippet>, String> {
+    snippets::snippets_list(language.as_deref()).map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+fn snippets_save(snippet: settings::Snippet, previous_name: Option<String>) -> Result<Vec<settings::Snippet>, String> {
+    snippets::snippets_save(snippet, previous_name.as_deref()).map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+fn snippets_delete(name: String) -> Result<Vec<settings::Snippet>, String> {
+    snippets::snippets_delete(&name).map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+fn snippets_import(
+    path: Option<String>,
+    content: Option<String>,
+    language: Option<String>,
+) -> Result<Vec<settings::Snippet>, String> {
+    snippets::snippets_import(path.as_deref(), content, language.as_deref()).map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+async fn merge_three_way(
+    base: String,
+    ours: String,
+    theirs: String,
+    options: Option<merge::MergeOptions>,
+) -> Result<merge::MergeResult, String> {
+    let options = options.unwrap_or_default();
+    tokio::task::spawn_blocking(move || merge::merge_three_way(&base, &ours, &theirs, &options))
+        .await
+        .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+async fn diff_texts(old: String, new: String, options: Option<diff::TextDiffOptions>) -> Result<diff::TextDiff, String> {
+    let options = options.unwrap_or_default();
+    tokio::task::spawn_blocking(move || diff::diff_texts(&old, &new, &options))
+        .await
+        .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+async fn diff_files(a_rel: String, b_rel: String, options: Option<diff::TextDiffOptions>) -> Result<diff::TextDiff, String> {
+    let options = options.unwrap_or_default();
+    tokio::task::spawn_blocking(move || diff::diff_files(&a_rel, &b_rel, &options))
+        .await
+        .map_err(|e| e.to_string())?
+        .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+fn lint_run(
+    app: tauri::AppHandle,
+    paths: Option<Vec<String>>,
+    linter: Option<lint::Linter>,
+) -> Result<Vec<lint::LintTarget>, String> {
+    lint::lint_run(app, paths, linter).map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+fn document_symbols(
+    path: Option<String>,
+    content: Option<String>,
+    language: Option<String>,
+) -> Result<Vec<outline::DocumentSymbol>, String> {
+    outline::document_symbols(path.as_deref(), content, language.as_deref()).map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+fn lsp_servers() -> Result<Vec<settings::LanguageServerConfig>, String> {
+    lsp::lsp_servers().map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+fn lsp_list() -> Result<Vec<lsp::LspSession>, String> {
+    lsp::lsp_list().map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+async fn lsp_start(app: tauri::AppHandle, language: String, path: Option<String>) -> Result<lsp::LspSession, String> {
+    tokio::task::spawn_blocking(move || lsp::lsp_start(app, &language, path.as_deref()))
+        .await
+        .map_err(|e| e.to_string())?
+        .map_err(|e| e.to_string())
+}
+
+// Synchronous so messages reach the server in the order they were sent.
+#[tauri::command]
+fn lsp_send(session_id: String, message: serde_json::Value) -> Result<(), String> {
+    lsp::lsp_send(&session_id, &message).map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+fn lsp_stop(session_id: String) -> Result<(), String> {
+    lsp::lsp_stop(&session_id).map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+fn git_stage(paths: Vec<String>) -> Result<(), String> {
+    git::git_stage(&paths).map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+fn git_unstage(paths: Vec<String>) -> Result<(), String> {
+    git::git_unstage(&paths).map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+fn git_commit(message: String, amend: Option<bool>, root: Option<String>) -> Result<git::CommitInfo, String> {
+    git::git_commit(&message, amend.unwrap_or(false), root.as_deref()).map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+fn git_commit_validate(message: String, root: Option<String>) -> Result<commit_lint::CommitValidation, String> {
+    commit_lint::git_commit_validate(&message, root.as_deref()).map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+async fn ai_commit_message(
+    root: Option<String>,
+    encryption_password: Option<String>,
+    thinking: Option<String>,
+) -> Result<ai::AiCommitMessage, String> {
+    ai::ai_commit_message(root.as_deref(), encryption_password.as_deref(), thinking.as_deref())
+        .await
+        .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+fn git_branches_list(root: Option<String>) -> Result<Vec<git::BranchInfo>, String> {
+    git::git_branches_list(root.as_deref()).map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+fn git_branch_create(
+    name: String,
+    start: Option<String>,
+    checkout: Option<bool>,
+    root: Option<String>,
+) -> Result<git::BranchInfo, String> {
+    git::git_branch_create(&name, start.as_deref(), checkout.unwrap_or(false), root.as_deref()).map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+fn git_checkout(name: String, root: Option<String>) -> Result<(), String> {
+    git::git_checkout(&name, root.as_deref()).map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+fn git_branch_delete(name: String, force: Option<bool>, root: Option<String>) -> Result<(), String> {
+    git::git_branch_delete(&name, force.unwrap_or(false), root.as_deref()).map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+fn git_diff_file(rel_path: String, staged: Option<bool>, hunks: Option<bool>) -> Result<git::FileDiff, String> {
+    git::git_diff_file(&rel_path, staged.unwrap_or(false), hunks.unwrap_or(false)).map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+async fn git_fetch(
+    app: tauri::AppHandle,
+    remote: Option<String>,
+    root: Option<String>,
+    encryption_password: Option<String>,
+) -> Result<(), String> {
+    tokio::task::spawn_blocking(move || {
+        git::git_fetch(&app, remote.as_deref(), root.as_deref(), encryption_password.as_deref())
+    })
+    .await
+    .map_err(|e| e.to_string())?
+    .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+async fn git_pull(
+    app: tauri::AppHandle,
+    remote: Option<String>,
+    root: Option<String>,
+    encryption_password: Option<String>,
+) -> Result<git::PullResult, String> {
+    tokio::task::spawn_blocking(move || {
+        git::git_pull(&app, remote.as_deref(), root.as_deref(), encryption_password.as_deref())
+    })
+    .await
+    .map_err(|e| e.to_string())?
+    .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+async fn git_push(
+    app: tauri::AppHandle,
+    remote: Option<String>,
+    force: Option<bool>,
+    root: Option<String>,
+    encryption_password: Option<String>,
+) -> Result<(), String> {
+    tokio::task::spawn_blocking(move || {
+        git::git_push(
+            &app,
+            remote.as_deref(),
+            force.unwrap_or(false),
+            root.as_deref(),
+            encryption_password.as_deref(),
+        )
+    })
+    .await
+    .map_err(|e| e.to_string())?
+    .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+fn git_stash_save(
+    message: Option<String>,
+    include_untracked: Option<bool>,
+    root: Option<String>,
+) -> Result<git::StashEntry, String> {
+    git::git_stash_save(message.as_deref(), include_untracked.unwrap_or(false), root.as_deref()).map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+fn git_stash_list(root: Option<String>) -> Result<Vec<git::StashEntry>, String> {
+    git::git_stash_list(root.as_deref()).map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+fn git_stash_apply(index: usize, root: Option<String>) -> Result<(), String> {
+    git::git_stash_apply(index, root.as_deref()).map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+fn git_stash_pop(index: usize, root: Option<String>) -> Result<(), String> {
+    git::git_stash_pop(index, root.as_deref()).map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+fn git_stash_drop(index: usize, root: Option<String>) -> Result<(), String> {
+    git::git_stash_drop(index, root.as_deref()).map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+async fn git_clone(
+    app: tauri::AppHandle,
+    url: String,
+    dest: String,
+    encryption_password: Option<String>,
+) -> Result<workspace::WorkspaceInfo, String> {
+    tokio::task::spawn_blocking(move || git::git_clone(&app, &url, &dest, encryption_password.as_deref()))
+        .await
+        .map_err(|e| e.to_string())?
+        .map_err(|e| e.to_string())
+}
+
 #[tauri::command]
 async fn ai_chat(
     messages: Vec<ai::ChatMessage>,
     encryption_password: Option<String>,
     thinking: Option<String>,
+    priority: Option<String>,
 ) -> Result<ai::AiChatResult, String> {
-    ai::ai_chat(messages, encryption_password.as_deref(), thinking.as_deref())
-        .await
-        .map_err(|e| e.to_string())
+    let priority = ai_queue::RequestPriority::parse(priority.as_deref());
+    let res = ai::ai_chat(messages, encryption_password.as_deref(), thinking.as_deref(), priority).await;
+    if res.is_ok() && priority == ai_queue::RequestPriority::Interactive {
+        notifications::notify(notifications::Category::Ai, "Pompora AI replied", "The answer is waiting in the chat.");
+    }
+    res.map_err(|e| e.to_string())
 }
 
 #[tauri::command]
@@ -283,12 +1133,55 @@ async fn ai_chat_with_model(
     encryption_password: Option<String>,
     model: Option<String>,
     thinking: Option<String>,
+    priority: Option<String>,
 ) -> Result<ai::AiChatResult, String> {
-    ai::ai_chat_with_model(messages, encryption_password.as_deref(), model.as_deref(), thinking.as_deref())
+    let priority = ai_queue::RequestPriority::parse(priority.as_deref());
+    ai::ai_chat_with_model(messages, encryption_password.as_deref(), model.as_deref(), thinking.as_deref(), priority)
         .await
         .map_err(|e| e.to_string())
 }
 
+#[tauri::command]
+async fn ai_chat_compare(
+    messages: Vec<ai::ChatMessage>,
+    models: Vec<String>,
+    encryption_password: Option<String>,
+    thinking: Option<String>,
+) -> Result<Vec<ai::AiCompareEntry>, String> {
+    let res = ai::ai_chat_compare(messages, models, encryption_password.as_deref(), thinking.as_deref()).await;
+    if let Ok(entries) = &res {
+        let body = format!("{} models answered.", entries.len());
+        notifications::notify(notifications::Category::Ai, "Model comparison finished", &body);
+    }
+    res.map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+async fn ai_explain_terminal(
+    id: String,
+    last_n_lines: Option<u32>,
+    encryption_password: Option<String>,
+    thinking: Option<String>,
+) -> Result<ai::AiChatResult, String> {
+    let lines = last_n_lines.unwrap_or(200).clamp(1, 2000) as usize;
+    ai::ai_explain_terminal(&id, lines, encryption_password.as_deref(), thinking.as_deref())
+        .await
+        .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+async fn ai_sandbox_run(command: String) -> Result<sandbox::SandboxResult, String> {
+    tokio::task::spawn_blocking(move || sandbox::sandbox_run(&command))
+        .await
+        .map_err(|e| e.to_string())?
+        .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+fn ai_command_analyze(command: String) -> command_safety::CommandAnalysis {
+    command_safety::analyze(&command)
+}
+
 #[tauri::command]
 async fn openrouter_list_models() -> Result<Vec<ai::OpenRouterModelInfo>, String> {
     ai::openrouter_list_models().await.map_err(|e| e.to_string())
@@ -302,6 +1195,7 @@ async fn ai_run_action(
     selection: Option<String>,
     encryption_password: Option<String>,
     thinking: Option<String>,
+    priority: Option<String>,
 ) -> Result<ai::AiRunResult, String> {
     ai::ai_run_action(
         &action,
@@ -310,51 +1204,200 @@ async fn ai_run_action(
         selection.as_deref(),
         encryption_password.as_deref(),
         thinking.as_deref(),
+        ai_queue::RequestPriority::parse(priority.as_deref()),
     )
     .await
     .map_err(|e| e.to_string())
 }
 
+#[tauri::command]
+fn telemetry_preview() -> telemetry::TelemetryBatch {
+    telemetry::telemetry_preview()
+}
+
+// Counts each command invocation for telemetry before running it.
+fn counted<R: tauri::Runtime>(
+    handler: impl Fn(tauri::ipc::Invoke<R>) -> bool + Send + Sync + 'static,
+) -> impl Fn(tauri::ipc::Invoke<R>) -> bool + Send + Sync + 'static {
+    move |invoke| {
+        telemetry::record_command(invoke.message.command());
+        handler(invoke)
+    }
+}
+
 #[cfg_attr(mobile, tauri::mobile_entry_point)]
 pub fn run() {
+    logging::init();
     tauri::Builder::default()
         .plugin(tauri_plugin_opener::init())
         .plugin(tauri_plugin_dialog::init())
-        .invoke_handler(tauri::generate_handler![
+        .plugin(tauri_plugin_notification::init())
+        .setup(|app| {
+            if let Err(e) = settings::watch(app.handle().clone()) {
+                tracing::warn!("settings watcher unavailable: {e}");
+            }
+            if let Err(e) = fs_watch::watch(app.handle().clone()) {
+                tracing::warn!("workspace watcher unavailable: {e}");
+            }
+            auth::watch(app.handle().clone());
+            lsp::watch(app.handle());
+            logging::watch(app.handle());
+            telemetry::start(app.handle());
+            notifications::init(app.handle().clone());
+            updates::start(app.handle().clone());
+            Ok(())
+        })
+        .invoke_handler(counted(tauri::generate_handler![
             settings_get,
             settings_set,
+            settings_patch,
+            settings_profile_list,
+            settings_profile_switch,
+            settings_profile_delete,
             provider_key_status,
             provider_key_set,
             provider_key_get,
             provider_key_clear,
+            provider_key_list,
+            provider_key_activate,
+            secrets_migrate,
+            secrets_export,
+            secrets_import,
+            secrets_unlock,
+            secrets_lock,
+            secrets_lock_status,
+            workspace_key_override_get,
+            workspace_key_override_set,
+            secrets_audit,
+            secrets_audit_log,
+            logs_tail,
             auth_begin_login,
             auth_wait_login,
+            auth_cancel_login,
             auth_get_profile,
             auth_logout,
+            auth_refresh,
             auth_get_credits,
+            auth_test_backend,
             test_gemini_api,
             debug_gemini_end_to_end,
             workspace_get,
             workspace_set,
+            workspace_add_root,
+            workspace_remove_root,
+            workspace_trust_get,
+            workspace_trust_set,
+            recent_files_get,
+            recent_file_add,
+            file_pin,
+            file_unpin,
+            workspace_detect_projects,
+            workspace_export_zip,
+            tasks_list,
+            tasks_run,
+            tasks_stop,
+            git_stage,
+            git_unstage,
+            git_commit,
+            git_commit_validate,
+            ai_commit_message,
+            git_branches_list,
+            git_branch_create,
+            git_checkout,
+            git_branch_delete,
+            git_diff_file,
+            git_fetch,
+            git_pull,
+            git_push,
+            git_stash_save,
+            git_stash_list,
+            git_stash_apply,
+            git_stash_pop,
+            git_stash_drop,
+            git_clone,
             workspace_pick_folder,
             workspace_pick_file,
             workspace_list_dir,
             workspace_list_files,
+            workspace_list_files_stream,
             workspace_read_file,
+            workspace_read_file_binary,
+            workspace_read_file_range,
+            workspace_read_file_bytes,
             workspace_write_file,
+            workspace_write_file_if_unchanged,
+            workspace_hash_file,
             workspace_create_dir,
             workspace_delete,
             workspace_rename,
+            workspace_batch_ops,
+            fsops_history,
+            fsops_undo_last,
             workspace_search,
+            workspace_fuzzy_find,
             ai_run_action,
             ai_chat,
             ai_chat_with_model,
+            ai_chat_compare,
+            ai_explain_terminal,
+            ai_sandbox_run,
+            ai_command_analyze,
             openrouter_list_models,
             terminal_start,
             terminal_write,
             terminal_resize,
-            terminal_kill
-        ])
+            terminal_ack,
+            terminal_kill,
+            terminal_wait,
+            terminal_list_sessions,
+            terminal_get_scrollback,
+            terminal_get_cwd,
+            terminal_commands,
+            terminal_command_output,
+            terminal_search,
+            terminal_detect_shells,
+            terminal_history,
+            terminal_history_clear,
+            telemetry_preview,
+            lsp_servers,
+            lsp_list,
+            lsp_start,
+            lsp_send,
+            lsp_stop,
+            document_symbols,
+            lint_run,
+            diff_texts,
+            diff_files,
+            merge_three_way,
+            snippets_list,
+            snippets_save,
+            snippets_delete,
+            snippets_import,
+            updates_check,
+            workspace_todos,
+            chat_export,
+            http_request,
+            http_collections_list,
+            http_collections_save,
+            http_collections_delete,
+            env_files_list,
+            env_file_read,
+            env_file_reveal,
+            env_file_set,
+            ports_list,
+            dependencies_list,
+            dependencies_audit,
+            github_auth_status,
+            github_login_begin,
+            github_login_wait,
+            github_repo,
+            github_pull_requests,
+            github_issues,
+            github_pull_request_diff,
+            github_comment_post,
+            spellcheck_file,
+            spellcheck_add_word
+        ]))
         .run(tauri::generate_context!())
         .expect("error while running tauri application");
 }
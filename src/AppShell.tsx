@@ -1,8 +1,10 @@
 import { useCallback, useEffect, useMemo, useRef, useState, type ReactNode } from "react";
 import Editor, { DiffEditor } from "@monaco-editor/react";
+import type { Monaco } from "@monaco-editor/react";
 import type { editor as MonacoEditorNS } from "monaco-editor";
 import { listen } from "@tauri-apps/api/event";
 import { Terminal as XTermTerminal } from "xterm";
+import type { IMarker } from "xterm";
 import { FitAddon } from "xterm-addon-fit";
 import {
   AlertTriangle,
@@ -41,7 +43,7 @@ import {
   X,
   Palette,
 } from "lucide-react";
-import { getCurrentWindow } from "@tauri-apps/api/window";
+import { getCurrentWindow, UserAttentionType } from "@tauri-apps/api/window";
 import { WebviewWindow } from "@tauri-apps/api/webviewWindow";
 import { openUrl } from "@tauri-apps/plugin-opener";
 import {
@@ -49,33 +51,138 @@ import {
   providerKeySet,
   providerKeyStatus,
   authBeginLogin,
+  authCancelLogin,
+  authTestBackend,
   authWaitLogin,
   authGetProfile,
   authLogout,
   authGetCredits,
   debugGeminiEndToEnd,
   aiChat,
+  aiSandboxRun,
+  aiCommandAnalyze,
+  aiCommitMessage,
+  gitCommit,
+  gitCommitValidate,
+  gitClone,
   settingsGet,
   settingsSet,
+  recentFileAdd,
+  workspaceAddRoot,
   workspaceGet,
+  workspaceTrustSet,
   workspaceListDir,
-  workspaceListFiles,
+  fsopsUndoLast,
   workspaceReadFile,
+  workspaceReadFileText,
   workspaceWriteFile,
+  workspaceWriteFileIfUnchanged,
+  isProtectedPathError,
   workspaceCreateDir,
   workspaceDelete,
   workspaceRename,
   workspaceSearch,
+  workspaceFuzzyFind,
   workspacePickFile,
   workspacePickFolder,
   workspaceSet,
   terminalStart,
+  terminalListSessions,
+  terminalSearch,
+  terminalDetectShells,
+  terminalHistory,
+  terminalHistoryClear,
+  logsTail,
+  telemetryPreview,
   terminalWrite,
   terminalResize,
+  terminalAck,
   terminalKill,
+  terminalGetScrollback,
+  terminalCommands,
+  terminalCommandOutput,
+  tasksList,
+  tasksRun,
+  tasksStop,
+  lintRun,
+  chatExport,
+  dependenciesAudit,
+  dependenciesList,
+  githubCommentPost,
+  githubIssues,
+  githubLoginBegin,
+  githubLoginWait,
+  githubPullRequestDiff,
+  githubPullRequests,
+  githubRepo,
+  spellcheckAddWord,
+  spellcheckFile,
+  envFileRead,
+  envFileReveal,
+  envFileSet,
+  envFilesList,
+  httpCollectionsList,
+  httpCollectionsSave,
+  httpRequest,
+  portsList,
+  diffTexts,
+  snippetsImport,
+  snippetsList,
+  snippetsSave,
+  updatesCheck,
+  workspaceTodos,
 } from "./lib/tauri";
-import type { AiChatMessage, AiEditOp } from "./lib/tauri";
-import type { AppSettings, AuthProfile, CreditsResponse, DirEntryInfo, EditorTab, KeyStatus, Theme, WorkspaceInfo } from "./lib/types";
+import { lspDocumentChanged, lspDocumentOpened, lspSyncOpenDocuments, registerLspProviders } from "./lib/lsp";
+import type {
+  AiChatMessage,
+  AiEditOp,
+  AuthExpiredEvent,
+  ChatExportFormat,
+  ChatTranscript,
+  CommandAnalysis,
+  CommandFinding,
+  CommandRisk,
+  CreditsUpdatedEvent,
+  DetectedShell,
+  Diagnostic,
+  DiagnosticsReport,
+  FuzzyMatch,
+  LintFinishedEvent,
+  ListeningPort,
+  TaskFinishedEvent,
+  TaskInfo,
+  TaskOutputEvent,
+  TaskStartedEvent,
+  TerminalActivityEvent,
+  TerminalCommandEvent,
+  TerminalExit,
+  TerminalSearchMatch,
+  TerminalTitleEvent,
+  TodoList,
+  TodosChanged,
+  UpdateCheck,
+  WorkspaceSearchResults,
+} from "./lib/tauri";
+import type {
+  AppSettings,
+  AuthProfile,
+  CommitValidation,
+  CreditsResponse,
+  DirEntryInfo,
+  EditorTab,
+  FsPathEvent,
+  FsRenameEvent,
+  HttpCollection,
+  KeyStatus,
+  LogLevel,
+  NotificationCategory,
+  SandboxSettings,
+  SavedHttpRequest,
+  Snippet,
+  Theme,
+  VersionedText,
+  WorkspaceInfo,
+} from "./lib/types";
 
 type ActivityId = "explorer" | "search" | "scm";
 
@@ -128,6 +235,8 @@ type ChatUiMessage = {
     error?: string | null;
     tail?: string[] | null;
     autoFixRequested?: boolean;
+    /** What the command safety analyzer made of `cmd`, once it has answered. */
+    safety?: CommandAnalysis | null;
   };
   activity?: {
     title: string;
@@ -452,6 +561,23 @@ function phaseRank(p: "think" | "plan" | "act" | "verify" | "done"): number {
   return 4;
 }
 
+const NO_SEARCH_RESULTS: WorkspaceSearchResults = { files: [], total_matches: 0, total_files: 0, truncated: false };
+
+// A minified file or lockfile shouldn't crowd every other file out of the results.
+const SEARCH_MATCHES_PER_FILE = 50;
+
+// Lines kept in the Output panel; the oldest go first.
+const TASK_OUTPUT_MAX_LINES = 5000;
+
+// Terminal output is acknowledged to the backend in batches of this many characters once written.
+const TERMINAL_ACK_BATCH = 32 * 1024;
+
+// Output that runs at least this long before the terminal goes quiet is announced when the
+// terminal isn't in view, as it is most likely a build or test run that has finished.
+const TERMINAL_NOTIFY_AFTER_MS = 10_000;
+
+type TaskOutputLine = { id: number; kind: "stdout" | "stderr" | "info"; text: string };
+
 function clamp(n: number, min: number, max: number): number {
   return Math.max(min, Math.min(max, n));
 }
@@ -486,6 +612,9 @@ type ChangeFile = {
   path: string;
   before: string | null;
   after: string | null;
+  /** Changed line counts, from `diffTexts`. */
+  added?: number;
+  removed?: number;
 };
 
 type ChangeSet = {
@@ -530,15 +659,69 @@ function extractFileRefs(text: string): string[] {
   return out;
 }
 
-function isLikelyDangerousCommand(cmd: string): boolean {
-  const c = cmd.trim().toLowerCase();
-  if (!c) return false;
-  if (/\brm\b/.test(c) && /\s-\w*r\w*f\b/.test(c)) return true;
-  if (/\bmkfs\b/.test(c)) return true;
-  if (/\bdd\b/.test(c) && /\bif=\b/.test(c)) return true;
-  if (/\bshutdown\b|\breboot\b|\bpoweroff\b/.test(c)) return true;
-  if (c.includes(":(){") && c.includes("};:")) return true;
-  return false;
+// Runs a file operation the user asked for; if it is refused because of a protected path, asks
+// before running it again with the protection overridden. Resolves with null when declined.
+async function overridingProtection<T>(run: (allowProtected: boolean) => Promise<T>): Promise<T | null> {
+  try {
+    return await run(false);
+  } catch (e) {
+    if (!isProtectedPathError(e)) throw e;
+    if (!window.confirm(`${String(e)}\n\nOverride the protection and go ahead?`)) return null;
+    return run(true);
+  }
+}
+
+// Asks before running a command the safety analyzer rates high or critical, quoting the parts it
+// flagged. Resolves to whether to go ahead.
+async function confirmCommandRisk(cmd: string, where: string): Promise<boolean> {
+  let analysis: CommandAnalysis;
+  try {
+    analysis = await aiCommandAnalyze(cmd);
+  } catch (e) {
+    return window.confirm(`The command could not be checked before running it${where} (${String(e)}):\n\n${cmd}\n\nRun anyway?`);
+  }
+  if (analysis.risk !== "high" && analysis.risk !== "critical") return true;
+  const found = analysis.findings
+    .filter((f) => f.risk !== "low")
+    .map((f) => `• ${f.message} (${f.risk}): ${cmd.slice(f.start, f.end)}`);
+  return window.confirm(
+    `The app is about to run a ${analysis.risk}-risk command${where}:\n\n${cmd}\n\n${found.join("\n")}\n\nRun anyway?`
+  );
+}
+
+const COMMAND_RISK_CLASS: Record<CommandRisk, string> = {
+  none: "",
+  low: "bg-sky-500/15",
+  medium: "bg-yellow-500/20 text-yellow-300",
+  high: "bg-red-500/15 text-red-300",
+  critical: "bg-red-500/30 text-red-300",
+};
+
+const COMMAND_RISK_RANK: Record<CommandRisk, number> = { none: 0, low: 1, medium: 2, high: 3, critical: 4 };
+
+// A command with the parts the safety analyzer flagged highlighted by risk; hovering one tells why.
+function HighlightedCommand(props: { cmd: string; findings: CommandFinding[] }) {
+  const { cmd, findings } = props;
+  const cuts = Array.from(new Set([0, cmd.length, ...findings.flatMap((f) => [f.start, f.end])]))
+    .filter((i) => i >= 0 && i <= cmd.length)
+    .sort((a, b) => a - b);
+  const parts: React.ReactNode[] = [];
+  for (let i = 0; i + 1 < cuts.length; i++) {
+    const [start, end] = [cuts[i]!, cuts[i + 1]!];
+    const covering = findings.filter((f) => f.start <= start && f.end >= end);
+    const text = cmd.slice(start, end);
+    if (!covering.length) {
+      parts.push(<span key={start}>{text}</span>);
+      continue;
+    }
+    const worst = covering.reduce((a, b) => (COMMAND_RISK_RANK[b.risk] > COMMAND_RISK_RANK[a.risk] ? b : a));
+    parts.push(
+      <mark key={start} className={`rounded-sm ${COMMAND_RISK_CLASS[worst.risk]}`} title={covering.map((f) => f.message).join("\n")}>
+        {text}
+      </mark>
+    );
+  }
+  return <>{parts}</>;
 }
 
 function splitLines(s: string): string[] {
@@ -839,94 +1022,12 @@ function normalizeAiEdits(edits: AiEditOp[], workspaceRoot?: string | null): { e
   return { edits: out, didSanitize };
 }
 
-type LineOp = { type: "ctx" | "add" | "del"; line: string };
-
-// Minimal Myers diff for line arrays.
-function diffLines(before: string, after: string): LineOp[] {
-  const a = splitLines(before);
-  const b = splitLines(after);
-  const n = a.length;
-  const m = b.length;
-  const max = n + m;
-  const v = new Map<number, number>();
-  v.set(1, 0);
-  const trace: Map<number, number>[] = [];
-
-  for (let d = 0; d <= max; d++) {
-    const v2 = new Map<number, number>();
-    for (let k = -d; k <= d; k += 2) {
-      let x: number;
-      if (k === -d || (k !== d && (v.get(k - 1) ?? 0) < (v.get(k + 1) ?? 0))) {
-        x = v.get(k + 1) ?? 0;
-      } else {
-        x = (v.get(k - 1) ?? 0) + 1;
-      }
-      let y = x - k;
-      while (x < n && y < m && a[x] === b[y]) {
-        x++;
-        y++;
-      }
-      v2.set(k, x);
-      if (x >= n && y >= m) {
-        trace.push(v2);
-        // backtrack
-        const ops: LineOp[] = [];
-        let x2 = n;
-        let y2 = m;
-        for (let d2 = trace.length - 1; d2 >= 0; d2--) {
-          const vv = trace[d2]!;
-          const k2 = x2 - y2;
-          let prevK: number;
-          if (k2 === -(d2) || (k2 !== d2 && (vv.get(k2 - 1) ?? 0) < (vv.get(k2 + 1) ?? 0))) {
-            prevK = k2 + 1;
-          } else {
-            prevK = k2 - 1;
-          }
-          const prevX = vv.get(prevK) ?? 0;
-          const prevY = prevX - prevK;
-
-          while (x2 > prevX && y2 > prevY) {
-            ops.push({ type: "ctx", line: a[x2 - 1]! });
-            x2--;
-            y2--;
-          }
-
-          if (d2 === 0) break;
-
-          if (x2 === prevX) {
-            // insertion
-            ops.push({ type: "add", line: b[y2 - 1]! });
-            y2--;
-          } else {
-            // deletion
-            ops.push({ type: "del", line: a[x2 - 1]! });
-            x2--;
-          }
-        }
-
-        ops.reverse();
-        return ops;
-      }
-    }
-    trace.push(v2);
-    v.clear();
-    for (const [k, val] of v2.entries()) v.set(k, val);
-  }
-
-  return [];
-}
-
 function computeStats(files: ChangeFile[]): { files: number; added: number; removed: number } {
   let added = 0;
   let removed = 0;
   for (const f of files) {
-    const before = f.before ?? "";
-    const after = f.after ?? "";
-    const ops = diffLines(before, after);
-    for (const op of ops) {
-      if (op.type === "add") added++;
-      if (op.type === "del") removed++;
-    }
+    added += f.added ?? 0;
+    removed += f.removed ?? 0;
   }
   return { files: files.length, added, removed };
 }
@@ -1079,6 +1180,21 @@ function tryParseEditsFromAssistantOutput(
   return null;
 }
 
+// The Pompora site: the self-hosted backend when one is set.
+function pomporaSiteUrl(s: AppSettings): string {
+  return s.backend_base_url?.trim().replace(/\/+$/, "") || "https://pompora.dev";
+}
+
+// Asks whether to open the page of an available release.
+function offerUpdate(check: UpdateCheck) {
+  const release = check.latest;
+  const url = release?.url ?? check.download?.url;
+  if (!release || !url) return;
+  if (window.confirm(`Pompora ${release.version} is available (you have ${check.current_version}). Open the download page?`)) {
+    void openUrl(url);
+  }
+}
+
 function MenuSep() {
   return <div className="my-1 h-px bg-border" />;
 }
@@ -1155,6 +1271,7 @@ function detectLanguage(path: string): string {
   if (ext === "html") return "html";
   if (ext === "md") return "markdown";
   if (ext === "rs") return "rust";
+  if (ext === "py") return "python";
   if (ext === "toml") return "toml";
   if (ext === "yaml" || ext === "yml") return "yaml";
   return "plaintext";
@@ -1179,6 +1296,8 @@ function statusPillClass(status: "pending" | "running" | "done" | "error"): stri
 export default function AppShell() {
   const CHAT_STORAGE_KEY = "pompora.chat_sessions.v1";
   const RUN_POLICY_KEY = "pompora.terminal_run_policy.v1";
+  // Survives a webview reload (not an app restart), so the panel can reattach to its shell.
+  const TERMINAL_SESSION_KEY = "pompora.terminal_session.v1";
   const [activity, setActivity] = useState<ActivityId>("explorer");
   const [isPaletteOpen, setIsPaletteOpen] = useState(false);
   const [paletteQuery, setPaletteQuery] = useState("");
@@ -1187,9 +1306,6 @@ export default function AppShell() {
   const [isQuickOpenOpen, setIsQuickOpenOpen] = useState(false);
   const [quickOpenQuery, setQuickOpenQuery] = useState("");
   const [quickOpenIndex, setQuickOpenIndex] = useState(0);
-  const [fileIndexRoot, setFileIndexRoot] = useState<string | null>(null);
-  const [fileIndex, setFileIndex] = useState<string[]>([]);
-  const [isFileIndexLoading, setIsFileIndexLoading] = useState(false);
 
   const [isGoToLineOpen, setIsGoToLineOpen] = useState(false);
   const [goToLineValue, setGoToLineValue] = useState("");
@@ -1235,7 +1351,7 @@ export default function AppShell() {
   const [isSavingSettings, setIsSavingSettings] = useState(false);
   const [isTogglingOffline, setIsTogglingOffline] = useState(false);
 
-  const [workspace, setWorkspaceState] = useState<WorkspaceInfo>({ root: null, recent: [] });
+  const [workspace, setWorkspaceState] = useState<WorkspaceInfo>({ root: null, recent: [], roots: [], trusted: null });
 
   const [isFileMenuOpen, setIsFileMenuOpen] = useState(false);
   const [isFileMenuRecentOpen, setIsFileMenuRecentOpen] = useState(false);
@@ -1292,9 +1408,21 @@ export default function AppShell() {
   const [activeTabPath, setActiveTabPath] = useState<string | null>(null);
 
   const [searchQuery, setSearchQuery] = useState("");
-  const [searchResults, setSearchResults] = useState<Array<{ path: string; line: number; text: string }>>([]);
+  const [searchResults, setSearchResults] = useState<WorkspaceSearchResults>(NO_SEARCH_RESULTS);
+  const [collapsedSearchFiles, setCollapsedSearchFiles] = useState<Set<string>>(() => new Set());
   const [isSearching, setIsSearching] = useState(false);
-  const [pendingReveal, setPendingReveal] = useState<{ path: string; line: number } | null>(null);
+  const [searchCaseSensitive, setSearchCaseSensitive] = useState(false);
+  const [searchWholeWord, setSearchWholeWord] = useState(false);
+  const [searchMultiline, setSearchMultiline] = useState(false);
+  // Folder "Find in Folder" limited the search to; null searches the whole workspace.
+  const [searchScope, setSearchScope] = useState<string | null>(null);
+  const [pendingReveal, setPendingReveal] = useState<{
+    path: string;
+    line: number;
+    column?: number;
+    endLine?: number;
+    endColumn?: number;
+  } | null>(null);
 
   const [keyStatus, setKeyStatus] = useState<KeyStatus | null>(null);
   const [apiKeyDraft, setApiKeyDraft] = useState("");
@@ -1309,6 +1437,8 @@ export default function AppShell() {
   const [authProfile, setAuthProfile] = useState<AuthProfile | null>(null);
   const [authCredits, setAuthCredits] = useState<CreditsResponse | null>(null);
   const [isAuthBusy, setIsAuthBusy] = useState(false);
+  // State of the browser sign-in being waited for, so it can be canceled.
+  const [pendingLoginState, setPendingLoginState] = useState<string | null>(null);
   const [isAccountMenuOpen, setIsAccountMenuOpen] = useState(false);
 
   const initialChatIdRef = useRef<string>(`${Date.now()}-${Math.random().toString(16).slice(2)}`);
@@ -1369,7 +1499,17 @@ export default function AppShell() {
   const [explorerWidth, setExplorerWidth] = useState(300);
   const [isTerminalOpen, setIsTerminalOpen] = useState(false);
   const [terminalHeight, setTerminalHeight] = useState(240);
-  const [panelTab, setPanelTab] = useState<"problems" | "output" | "debug" | "terminal" | "ports">("terminal");
+  const [panelTab, setPanelTab] = useState<"problems" | "todos" | "output" | "debug" | "terminal" | "ports">("terminal");
+  // Tagged comments in the workspace, loaded when the TODOs tab is first shown.
+  const [todoList, setTodoList] = useState<TodoList | null>(null);
+  const [listeningPorts, setListeningPorts] = useState<ListeningPort[]>([]);
+  const [tasks, setTasks] = useState<TaskInfo[]>([]);
+  const [shells, setShells] = useState<DetectedShell[]>([]);
+  const [taskOutput, setTaskOutput] = useState<TaskOutputLine[]>([]);
+  const taskOutputSeqRef = useRef(0);
+  const taskOutputEndRef = useRef<HTMLDivElement | null>(null);
+  // Problems found in task and terminal output, by the source that reported them.
+  const [diagnostics, setDiagnostics] = useState<Record<string, Diagnostic[]>>({});
   const [isChatHistoryOpen, setIsChatHistoryOpen] = useState(false);
   const [isModelPickerOpen, setIsModelPickerOpen] = useState(false);
   const [uiPomporaThinking, setUiPomporaThinking] = useState<"slow" | "fast" | "reasoning" | null>(null);
@@ -1586,6 +1726,18 @@ export default function AppShell() {
   const termCaptureRef = useRef<TerminalCapture | null>(null);
   const termInitPromiseRef = useRef<Promise<void> | null>(null);
   const termCwdRef = useRef<string | null>(null);
+  // Buffer positions where shell-integrated commands started, for jumping between them.
+  const termCommandMarksRef = useRef<IMarker[]>([]);
+  // Last find-in-terminal and the hit shown.
+  const termFindRef = useRef<{ query: string; matches: TerminalSearchMatch[]; index: number } | null>(null);
+  // Title the shell set, and whether output arrived in the last few seconds.
+  const [terminalTitle, setTerminalTitle] = useState<string | null>(null);
+  const [terminalActive, setTerminalActive] = useState(false);
+  const terminalShownRef = useRef(false);
+
+  useEffect(() => {
+    terminalShownRef.current = isTerminalOpen && panelTab === "terminal";
+  }, [isTerminalOpen, panelTab]);
 
   const mainGridTemplateColumns = useMemo(() => {
     const cols: string[] = ["52px", `minmax(220px, ${explorerWidth}px)`, "minmax(0, 1fr)"];
@@ -1949,7 +2101,42 @@ export default function AppShell() {
 
       const { cols, rows } = t;
       const cwd = workspace.root ?? settings.workspace_root ?? null;
-      const id = await terminalStart({ cols, rows, cwd });
+      let restore: { cwd: string | null; profile?: string } | null = null;
+      let id: string | null = null;
+      try {
+        // After a reload the shell is still running: reattach to it. After a restart it is gone,
+        // but the previous run's folder and profile can be picked up again.
+        const { sessions, previous } = await terminalListSessions();
+        const saved = window.sessionStorage.getItem(TERMINAL_SESSION_KEY);
+        const live = sessions.filter((s) => !s.exit);
+        const attach = live.find((s) => s.id === saved) ?? live[live.length - 1];
+        if (attach) {
+          t.write(await terminalGetScrollback({ id: attach.id }));
+          await terminalResize({ id: attach.id, cols, rows });
+          id = attach.id;
+          setTerminalTitle(attach.title);
+          setTerminalActive(attach.active);
+        } else if (settings.terminal_restore_sessions !== false && previous.length > 0) {
+          const last = previous[previous.length - 1];
+          // Its folder only if it belongs to the workspace that is open now.
+          const root = (cwd ?? "").replace(/\\/g, "/").replace(/\/$/, "");
+          const dir = (last.cwd ?? "").replace(/\\/g, "/");
+          const inWorkspace = !!root && (dir === root || dir.startsWith(root + "/"));
+          restore = { cwd: inWorkspace ? last.cwd : cwd, profile: last.profile ?? undefined };
+        }
+      } catch {
+        // Start a new session.
+      }
+      if (!id && restore) {
+        try {
+          id = await terminalStart({ cols, rows, ...restore });
+          t.write(`\x1b[2m[Restored session${restore.cwd ? `: ${restore.cwd}` : ""}]\x1b[0m\r\n`);
+        } catch {
+          // The profile or folder is gone; fall back to a plain session.
+        }
+      }
+      if (!id) id = await terminalStart({ cols, rows, cwd });
+      window.sessionStorage.setItem(TERMINAL_SESSION_KEY, id);
       termIdRef.current = id;
       termCwdRef.current = cwd;
 
@@ -1959,11 +2146,22 @@ export default function AppShell() {
         void terminalWrite({ id: tid, data });
       });
 
-      termUnlistenRef.current = await listen<{ id: string; data: string }>("terminal:data", (ev) => {
+      // The backend stops sending when too much output is still waiting to be drawn.
+      let written = 0;
+      const ack = (tid: string, units: number) => {
+        written += units;
+        if (written < TERMINAL_ACK_BATCH) return;
+        void terminalAck({ id: tid, units: written }).catch(() => {});
+        written = 0;
+      };
+
+      const unlistenData = await listen<{ id: string; data: string }>("terminal:data", (ev) => {
         const tid = termIdRef.current;
         if (!tid) return;
         if (ev.payload.id !== tid) return;
-        termRef.current?.write(ev.payload.data);
+        const units = ev.payload.data.length;
+        if (termRef.current) termRef.current.write(ev.payload.data, () => ack(tid, units));
+        else ack(tid, units);
 
         const cap = termCaptureRef.current;
         if (!cap) return;
@@ -1990,6 +2188,41 @@ export default function AppShell() {
 
         // Don't clear capture here; runTerminalCommand will clear it after the command becomes idle.
       });
+      const unlistenExit = await listen<TerminalExit>("terminal:exit", (ev) => {
+        if (ev.payload.id !== termIdRef.current) return;
+        termRef.current?.write(`\r\n\x1b[2m[${ev.payload.reason}]\x1b[0m\r\n`);
+      });
+      const unlistenCommand = await listen<TerminalCommandEvent>("terminal:command", (ev) => {
+        if (ev.payload.id !== termIdRef.current) return;
+        if (ev.payload.command.finished_at !== null) return;
+        const mark = termRef.current?.registerMarker(0);
+        if (!mark) return;
+        termCommandMarksRef.current.push(mark);
+        mark.onDispose(() => {
+          termCommandMarksRef.current = termCommandMarksRef.current.filter((m) => m !== mark);
+        });
+      });
+      const unlistenTitle = await listen<TerminalTitleEvent>("terminal:title", (ev) => {
+        if (ev.payload.id !== termIdRef.current) return;
+        setTerminalTitle(ev.payload.title);
+      });
+      const unlistenActivity = await listen<TerminalActivityEvent>("terminal:activity", (ev) => {
+        if (ev.payload.id !== termIdRef.current) return;
+        setTerminalActive(ev.payload.active);
+        if (ev.payload.active || ev.payload.previous_ms < TERMINAL_NOTIFY_AFTER_MS) return;
+        if (terminalShownRef.current && document.hasFocus()) return;
+        const secs = Math.round(ev.payload.previous_ms / 1000);
+        notifyRef.current?.({ kind: "info", title: "Terminal", message: `Output stopped after ${secs}s.` });
+        // Flashes the taskbar entry or bounces the dock icon while the window is in the background.
+        if (!document.hasFocus()) void getCurrentWindow().requestUserAttention(UserAttentionType.Informational).catch(() => {});
+      });
+      termUnlistenRef.current = () => {
+        unlistenData();
+        unlistenExit();
+        unlistenCommand();
+        unlistenTitle();
+        unlistenActivity();
+      };
     })();
 
     termInitPromiseRef.current = p
@@ -2010,7 +2243,7 @@ export default function AppShell() {
       });
 
     return termInitPromiseRef.current;
-  }, [settings.workspace_root, workspace.root]);
+  }, [settings.terminal_restore_sessions, settings.workspace_root, workspace.root]);
 
   const resizeTerminal = useCallback(() => {
     const t = termRef.current;
@@ -2036,8 +2269,12 @@ export default function AppShell() {
     setIsTerminalOpen(false);
     const id = termIdRef.current;
     termIdRef.current = null;
+    window.sessionStorage.removeItem(TERMINAL_SESSION_KEY);
 
     termCwdRef.current = null;
+    termCommandMarksRef.current = [];
+    setTerminalTitle(null);
+    setTerminalActive(false);
 
     termInitPromiseRef.current = null;
 
@@ -2066,6 +2303,618 @@ export default function AppShell() {
     if (host) host.innerHTML = "";
   }, []);
 
+  const scrollToTerminalCommand = useCallback((dir: -1 | 1) => {
+    const t = termRef.current;
+    if (!t) return;
+    const top = t.buffer.active.viewportY;
+    const lines = termCommandMarksRef.current.map((m) => m.line).filter((l) => l >= 0);
+    const target = dir < 0 ? lines.filter((l) => l < top).pop() : lines.find((l) => l > top);
+    if (target === undefined) {
+      if (dir > 0) t.scrollToBottom();
+      return;
+    }
+    t.scrollToLine(target);
+  }, []);
+
+  const showTerminalMatch = useCallback((dir: -1 | 1) => {
+    const t = termRef.current;
+    const find = termFindRef.current;
+    if (!t || !find || find.matches.length === 0) return;
+    find.index = (find.index + dir + find.matches.length) % find.matches.length;
+    const m = find.matches[find.index];
+    // Counted up from the cursor's line, where the kept output ends.
+    const buf = t.buffer.active;
+    const row = buf.baseY + buf.cursorY - m.lines_from_end;
+    if (row < 0) return;
+    t.scrollToLine(Math.max(0, row - Math.floor(t.rows / 2)));
+    t.select(m.column, row, m.match_len);
+  }, []);
+
+  const findInTerminal = useCallback(async () => {
+    const id = termIdRef.current;
+    if (!id) return;
+    const query = window.prompt("Find in terminal", termFindRef.current?.query ?? "");
+    if (!query) return;
+    try {
+      const res = await terminalSearch({ id, query });
+      if (res.matches.length === 0) {
+        notifyRef.current?.({ kind: "info", title: "Terminal", message: `No results for "${query}".` });
+        termFindRef.current = null;
+        return;
+      }
+      termFindRef.current = { query, matches: res.matches, index: res.matches.length };
+      showTerminalMatch(-1);
+    } catch (e) {
+      notifyRef.current?.({ kind: "error", title: "Terminal", message: String(e) });
+    }
+  }, [showTerminalMatch]);
+
+  // Makes `shell` the terminal's default through a profile of the same name, and restarts an open
+  // terminal in it.
+  const selectTerminalShell = useCallback(
+    async (shell: DetectedShell) => {
+      const profile = { name: shell.name, shell: shell.path, args: shell.args, env: {}, cwd: null };
+      const existing = settings.terminal_profiles ?? [];
+      const kept = existing.find((p) => p.name === shell.name);
+      const next: AppSettings = {
+        ...settings,
+        terminal_profiles: kept
+          ? existing.map((p) => (p === kept ? { ...kept, shell: shell.path, args: shell.args } : p))
+          : [...existing, profile],
+        default_terminal_profile: shell.name,
+      };
+      try {
+        await settingsSet(next);
+        setSettingsState(next);
+      } catch (e) {
+        notifyRef.current?.({ kind: "error", title: "Terminal", message: String(e) });
+        return;
+      }
+      if (!termIdRef.current) return;
+      await closeTerminal();
+      setPanelTab("terminal");
+      setIsTerminalOpen(true);
+      for (let i = 0; i < 30; i++) {
+        if (termHostRef.current) break;
+        await new Promise<void>((r) => window.setTimeout(r, 50));
+      }
+      await ensureTerminal();
+    },
+    [closeTerminal, ensureTerminal, settings]
+  );
+
+  // The most recent command the shell reported, finished or not when `finished` is false.
+  const lastTerminalCommand = useCallback(async (finished: boolean) => {
+    const id = termIdRef.current;
+    if (!id) return null;
+    const list = await terminalCommands({ id });
+    const found = [...list].reverse().find((c) => !finished || c.finished_at !== null);
+    return found ? { id, command: found } : null;
+  }, []);
+
+  const copyLastTerminalCommandOutput = useCallback(async () => {
+    try {
+      const last = await lastTerminalCommand(true);
+      if (!last) {
+        notifyRef.current?.({ kind: "error", title: "Terminal", message: "No finished command to copy output from." });
+        return;
+      }
+      const text = await terminalCommandOutput({ id: last.id, seq: last.command.seq });
+      try {
+        await navigator.clipboard.writeText(text);
+      } catch {
+        window.prompt("Copy to clipboard:", text);
+      }
+    } catch (e) {
+      notifyRef.current?.({ kind: "error", title: "Terminal", message: String(e) });
+    }
+  }, [lastTerminalCommand]);
+
+  const rerunLastTerminalCommand = useCallback(async () => {
+    try {
+      const last = await lastTerminalCommand(false);
+      if (!last?.command.command) {
+        notifyRef.current?.({ kind: "error", title: "Terminal", message: "No command to run again." });
+        return;
+      }
+      await terminalWrite({ id: last.id, data: last.command.command + "\r" });
+      termRef.current?.focus();
+    } catch (e) {
+      notifyRef.current?.({ kind: "error", title: "Terminal", message: String(e) });
+    }
+  }, [lastTerminalCommand]);
+
+  // Asks for words to look for in the command history, then for one of the hits, and types it at
+  // the prompt without running it.
+  const searchTerminalHistory = useCallback(async () => {
+    const id = termIdRef.current;
+    if (!id) return;
+    const query = window.prompt("Search command history", "");
+    if (query === null) return;
+    try {
+      const hits = await terminalHistory({ query, limit: 20 });
+      if (hits.length === 0) {
+        notifyRef.current?.({ kind: "info", title: "Terminal", message: "No matching commands in the history." });
+        return;
+      }
+      const list = hits.map((h, i) => `${i + 1}. ${h.command}`).join("\n");
+      const picked = window.prompt(`Command to insert (1-${hits.length}):\n${list}`, "1");
+      const hit = picked ? hits[Number.parseInt(picked, 10) - 1] : undefined;
+      if (!hit) return;
+      await terminalWrite({ id, data: hit.command });
+      termRef.current?.focus();
+    } catch (e) {
+      notifyRef.current?.({ kind: "error", title: "Terminal", message: String(e) });
+    }
+  }, []);
+
+  const clearTerminalHistory = useCallback(async () => {
+    if (!window.confirm("Clear the terminal command history of this workspace?")) return;
+    try {
+      await terminalHistoryClear();
+    } catch (e) {
+      notifyRef.current?.({ kind: "error", title: "Terminal", message: String(e) });
+    }
+  }, []);
+
+  // With `paths`, lints only what they belong to; that is what saving does with "Lint on Save".
+  const runLinters = useCallback(async (paths?: string[]) => {
+    try {
+      const started = await lintRun(paths);
+      if (!paths && !started.length) {
+        window.alert("No linter applies to this workspace. Pompora runs clippy for Cargo projects, eslint when it is installed in node_modules, and ruff for Python projects.");
+      }
+    } catch (e) {
+      notifyRef.current?.({ kind: "error", title: "Lint", message: String(e) });
+    }
+  }, []);
+
+  const checkForUpdates = useCallback(async () => {
+    try {
+      const check = await updatesCheck();
+      if (check.available) offerUpdate(check);
+      else window.alert(`Pompora ${check.current_version} is up to date.`);
+    } catch (e) {
+      notifyRef.current?.({ kind: "error", title: "Updates", message: String(e) });
+    }
+  }, []);
+
+  const importSnippets = useCallback(async () => {
+    try {
+      const file = await workspacePickFile();
+      if (!file) return;
+      const imported = await snippetsImport({ path: file });
+      window.alert(`Imported ${imported.length} snippet${imported.length === 1 ? "" : "s"}.`);
+    } catch (e) {
+      notifyRef.current?.({ kind: "error", title: "Snippets", message: String(e) });
+    }
+  }, []);
+
+  const [httpCollections, setHttpCollections] = useState<HttpCollection[]>([]);
+  const lastHttpRequestRef = useRef<SavedHttpRequest | null>(null);
+
+  useEffect(() => {
+    if (isPaletteOpen) void httpCollectionsList().then(setHttpCollections, () => setHttpCollections([]));
+  }, [isPaletteOpen]);
+
+  // Sends a request and shows the response in a new tab.
+  const sendHttpRequest = useCallback(async (req: SavedHttpRequest) => {
+    lastHttpRequestRef.current = req;
+    try {
+      const res = await httpRequest({ method: req.method, url: req.url, headers: req.headers, body: req.body, auth: req.auth });
+      const json = res.headers.some((h) => h.name === "content-type" && h.value.includes("json"));
+      let body = res.body;
+      if (json && !res.base64) {
+        try {
+          body = JSON.stringify(JSON.parse(body), null, 2);
+        } catch {
+          // Shown as received.
+        }
+      }
+      const size = res.size < 1024 ? `${res.size} B` : `${(res.size / 1024).toFixed(1)} KB`;
+      const content = [
+        `${res.http_version} ${res.status} ${res.status_text}`.trim(),
+        `${req.method} ${res.url}`,
+        `${res.headers_ms} ms to headers, ${res.total_ms} ms total, ${size}${res.truncated ? " (truncated)" : ""}`,
+        "",
+        ...res.headers.map((h) => `${h.name}: ${h.value}`),
+        "",
+        res.base64 ? `[${size} of binary data, base64]` : "",
+        body,
+      ].join("\n");
+      const path = `untitled:http:${Date.now()}`;
+      setTabs((prev) => [...prev, { path, name: `${res.status} ${req.method} ${req.name || "Response"}`, language: "plaintext", content, isDirty: false }]);
+      setActiveTabPath(path);
+    } catch (e) {
+      notifyRef.current?.({ kind: "error", title: "HTTP Request", message: String(e) });
+    }
+  }, []);
+
+  const promptHttpRequest = useCallback(async () => {
+    const last = lastHttpRequestRef.current;
+    const line = window.prompt("Method and URL:", last ? `${last.method} ${last.url}` : "GET http://localhost:3000/")?.trim();
+    if (!line) return;
+    const [method, url] = line.includes(" ") ? [line.slice(0, line.indexOf(" ")), line.slice(line.indexOf(" ") + 1).trim()] : ["GET", line];
+    let body: string | null = null;
+    if (!["GET", "HEAD", "OPTIONS"].includes(method.toUpperCase())) {
+      body = window.prompt("Request body (JSON is sent as application/json):", last?.body ?? "") || null;
+    }
+    const headers = body && /^\s*[[{]/.test(body) ? [{ name: "Content-Type", value: "application/json" }] : [];
+    await sendHttpRequest({ name: "", method: method.toUpperCase(), url, headers, body });
+  }, [sendHttpRequest]);
+
+  const saveLastHttpRequest = useCallback(async () => {
+    const last = lastHttpRequestRef.current;
+    if (!last) {
+      window.alert("Send a request first (HTTP: Send Request…).");
+      return;
+    }
+    const collectionName = window.prompt("Collection:", httpCollections[0]?.name ?? "Requests")?.trim();
+    if (!collectionName) return;
+    const name = window.prompt("Request name:", `${last.method} ${last.url}`)?.trim();
+    if (!name) return;
+    const existing = httpCollections.find((c) => c.name === collectionName);
+    const requests = (existing?.requests ?? []).filter((r) => r.name !== name).concat({ ...last, name });
+    try {
+      setHttpCollections(await httpCollectionsSave({ name: collectionName, requests }));
+    } catch (e) {
+      notifyRef.current?.({ kind: "error", title: "HTTP Request", message: String(e) });
+    }
+  }, [httpCollections]);
+
+  // Asks which of the workspace's env files to use; null if there are none or the user cancels.
+  const pickEnvFile = useCallback(async (title: string): Promise<string | null> => {
+    const files = await envFilesList();
+    if (files.length === 0) {
+      window.alert("The workspace has no .env files.");
+      return null;
+    }
+    if (files.length === 1) return files[0]!;
+    const answer = window.prompt(`${title}\n${files.map((f, i) => `${i + 1}. ${f}`).join("\n")}`, "1")?.trim();
+    return answer ? files[Number(answer) - 1] ?? null : null;
+  }, []);
+
+  const chooseEnvFile = useCallback(async () => {
+    try {
+      const file = await pickEnvFile("Env file for new terminals and tasks:");
+      if (!file) return;
+      const current = settings.env_file === file;
+      if (current && !window.confirm(`New terminals and tasks load ${file}. Stop loading it?`)) return;
+      const next: AppSettings = { ...settings, env_file: current ? null : file };
+      setSettingsState(next);
+      await settingsSet(next);
+    } catch (e) {
+      notifyRef.current?.({ kind: "error", title: "Env", message: String(e) });
+    }
+  }, [pickEnvFile, settings]);
+
+  const showEnvVariables = useCallback(async () => {
+    try {
+      const file = await pickEnvFile("Env file:");
+      if (!file) return;
+      const { entries } = await envFileRead(file);
+      window.alert(entries.length ? `${file}\n\n${entries.map((e) => `${e.key}=${e.value}`).join("\n")}` : `${file} sets no variables.`);
+    } catch (e) {
+      notifyRef.current?.({ kind: "error", title: "Env", message: String(e) });
+    }
+  }, [pickEnvFile]);
+
+  const copyEnvValue = useCallback(async () => {
+    try {
+      const file = await pickEnvFile("Env file:");
+      if (!file) return;
+      const key = window.prompt(`Variable in ${file} to copy the value of:`)?.trim();
+      if (!key) return;
+      await navigator.clipboard.writeText(await envFileReveal(file, key));
+    } catch (e) {
+      notifyRef.current?.({ kind: "error", title: "Env", message: String(e) });
+    }
+  }, [pickEnvFile]);
+
+  const setEnvVariable = useCallback(
+    async (remove: boolean) => {
+      try {
+        const file = await pickEnvFile("Env file:");
+        if (!file) return;
+        if (remove) {
+          const key = window.prompt(`Variable to remove from ${file}:`)?.trim();
+          if (key) await envFileSet(file, key, null);
+          return;
+        }
+        const line = window.prompt(`Variable to set in ${file} (KEY=value):`)?.trim();
+        if (!line) return;
+        const eq = line.indexOf("=");
+        if (eq <= 0) {
+          window.alert("Write it as KEY=value.");
+          return;
+        }
+        await envFileSet(file, line.slice(0, eq).trim(), line.slice(eq + 1));
+      } catch (e) {
+        notifyRef.current?.({ kind: "error", title: "Env", message: String(e) });
+      }
+    },
+    [pickEnvFile]
+  );
+
+  // Lists the workspace's dependencies in a new tab, with the newest versions when `checkUpdates`.
+  const showDependencies = useCallback(async (checkUpdates: boolean) => {
+    try {
+      const list = await dependenciesList(checkUpdates);
+      if (list.manifests.length === 0) {
+        window.alert("No Cargo.toml, package.json, pyproject.toml or go.mod was found in the workspace.");
+        return;
+      }
+      const lines: string[] = [];
+      let outdated = 0;
+      for (const m of list.manifests) {
+        lines.push(`## ${m.path} (${m.ecosystem})`, "");
+        if (m.error) lines.push(`Couldn't be read: ${m.error}`, "");
+        for (const d of m.dependencies) {
+          const kind = d.kind === "normal" ? "" : ` [${d.kind}]`;
+          const declared = d.source ? `(${d.source})` : d.version || "*";
+          const update = d.outdated ? ` → ${d.latest}` : d.latest ? " (latest)" : "";
+          if (d.outdated) outdated += 1;
+          lines.push(`- ${d.name}${d.package ? ` (${d.package})` : ""} ${declared}${update}${kind}`);
+        }
+        lines.push("");
+      }
+      if (list.errors.length > 0) lines.push("## Not checked", "", ...list.errors.map((e) => `- ${e}`), "");
+      const title = checkUpdates ? `# Dependencies: ${outdated} with newer versions` : "# Dependencies";
+      const path = `untitled:dependencies:${Date.now()}`;
+      setTabs((prev) => [...prev, { path, name: "Dependencies", language: "markdown", content: [title, "", ...lines].join("\n"), isDirty: false }]);
+      setActiveTabPath(path);
+    } catch (e) {
+      notifyRef.current?.({ kind: "error", title: "Dependencies", message: String(e) });
+    }
+  }, []);
+
+  // Audits the workspace's dependencies and shows the findings in a new tab, or with `askAi`
+  // hands them to the chat for fixes. Either way they also show in Problems.
+  const auditDependencies = useCallback(
+    async (askAi: boolean) => {
+      try {
+        const report = await dependenciesAudit();
+        const vulns = report.vulnerabilities;
+        const describe = (v: (typeof vulns)[number]) =>
+          `${v.package}${v.version ? ` ${v.version}` : ""} (${v.manifest}): ${v.id}${v.severity ? ` [${v.severity}]` : ""} ${v.summary}; ${
+            v.fixed.length ? `fixed in ${v.fixed.join(", ")}` : "no fixed version"
+          }`;
+        if (askAi) {
+          if (vulns.length === 0) {
+            window.alert("No known vulnerabilities were found in the workspace's dependencies.");
+            return;
+          }
+          setActiveChatDraft(
+            `These dependencies have known vulnerabilities:\n\n${vulns.map((v) => `- ${describe(v)}`).join("\n")}\n\n` +
+              "Suggest the smallest safe upgrade for each, and propose the manifest edits. Return JSON edits."
+          );
+          setIsChatDockOpen(true);
+          window.setTimeout(() => {
+            void sendChatRef.current?.();
+          }, 0);
+          return;
+        }
+        const lines = [`# Dependency Audit: ${vulns.length} known ${vulns.length === 1 ? "vulnerability" : "vulnerabilities"}`, ""];
+        for (const v of vulns) {
+          lines.push(`- ${describe(v)}${v.url ? ` — ${v.url}` : ""}`);
+        }
+        lines.push("", "## Audited", "");
+        for (const s of report.sources) {
+          lines.push(`- ${s.manifest}: ${s.error ? `not audited (${s.error})` : s.tool}`);
+        }
+        const path = `untitled:audit:${Date.now()}`;
+        setTabs((prev) => [...prev, { path, name: "Dependency Audit", language: "markdown", content: lines.join("\n"), isDirty: false }]);
+        setActiveTabPath(path);
+      } catch (e) {
+        notifyRef.current?.({ kind: "error", title: "Dependency Audit", message: String(e) });
+      }
+    },
+    [setActiveChatDraft]
+  );
+
+  // Signs in with GitHub's device flow when an OAuth app is configured, otherwise saves a
+  // personal access token under the `github` provider.
+  const signInToGithub = useCallback(async () => {
+    try {
+      if (!settings.github_oauth_client_id?.trim()) {
+        const token = window.prompt("GitHub personal access token (with repo access, to read private repositories and comment):")?.trim();
+        if (!token) return;
+        await providerKeySet({ provider: "github", apiKey: token });
+        notifyRef.current?.({ kind: "info", title: "GitHub", message: "Token saved." });
+        return;
+      }
+      const login = await githubLoginBegin();
+      void openUrl(login.verification_uri);
+      window.alert(`Enter the code ${login.user_code} at ${login.verification_uri} to sign in. Pompora waits for it in the background.`);
+      const user = await githubLoginWait(login);
+      notifyRef.current?.({ kind: "info", title: "GitHub", message: `Signed in as ${user}.` });
+    } catch (e) {
+      notifyRef.current?.({ kind: "error", title: "GitHub", message: String(e) });
+    }
+  }, [settings.github_oauth_client_id]);
+
+  // Lists the open pull requests or issues of the workspace's GitHub repository in a new tab.
+  const showGithubList = useCallback(async (kind: "pulls" | "issues") => {
+    try {
+      const repo = await githubRepo();
+      const lines: string[] = [];
+      if (kind === "pulls") {
+        const pulls = await githubPullRequests("open");
+        lines.push(`# ${repo.owner}/${repo.name}: ${pulls.length} open pull ${pulls.length === 1 ? "request" : "requests"}`, "");
+        for (const p of pulls) {
+          lines.push(`- #${p.number} ${p.title}${p.draft ? " [draft]" : ""} — ${p.author}, ${p.head} → ${p.base}, updated ${p.updated_at}`, `  ${p.url}`);
+        }
+      } else {
+        const issues = await githubIssues("open");
+        lines.push(`# ${repo.owner}/${repo.name}: ${issues.length} open ${issues.length === 1 ? "issue" : "issues"}`, "");
+        for (const i of issues) {
+          const labels = i.labels.length ? ` [${i.labels.join(", ")}]` : "";
+          lines.push(`- #${i.number} ${i.title}${labels} — ${i.author}, ${i.comments} comments, updated ${i.updated_at}`, `  ${i.url}`);
+        }
+      }
+      const name = kind === "pulls" ? "Pull Requests" : "Issues";
+      const path = `untitled:github:${Date.now()}`;
+      setTabs((prev) => [...prev, { path, name, language: "markdown", content: lines.join("\n"), isDirty: false }]);
+      setActiveTabPath(path);
+    } catch (e) {
+      notifyRef.current?.({ kind: "error", title: "GitHub", message: String(e) });
+    }
+  }, []);
+
+  // The pull request or issue last reviewed or commented on, offered again by the next prompt.
+  const githubNumberRef = useRef<number | null>(null);
+
+  // Opens a pull request's diff in a new tab and asks the AI to review it.
+  const reviewPullRequest = useCallback(async () => {
+    try {
+      const pulls = await githubPullRequests("open");
+      const listed = pulls
+        .slice(0, 10)
+        .map((p) => `#${p.number} ${p.title}`)
+        .join("\n");
+      const answer = window.prompt(
+        `Pull request number to review:${listed ? `\n\n${listed}` : ""}`,
+        String(githubNumberRef.current ?? pulls[0]?.number ?? "")
+      );
+      const number = Number(answer?.trim().replace(/^#/, ""));
+      if (!answer || !Number.isInteger(number) || number <= 0) return;
+      githubNumberRef.current = number;
+      const diff = await githubPullRequestDiff(number);
+      const pr = pulls.find((p) => p.number === number);
+      const path = `untitled:pr-${number}:${Date.now()}`;
+      setTabs((prev) => [...prev, { path, name: `PR #${number}.diff`, language: "diff", content: diff, isDirty: false }]);
+      setActiveTabPath(path);
+
+      const maxDiff = 60000;
+      const shown = diff.length > maxDiff ? `${diff.slice(0, maxDiff)}\n… (diff cut off at ${maxDiff} characters)` : diff;
+      const about = pr ? `"${pr.title}" by ${pr.author} (${pr.head} → ${pr.base})${pr.body.trim() ? `\n\n${pr.body.trim()}` : ""}` : "";
+      setActiveChatDraft(
+        `Review pull request #${number}${about ? ` ${about}` : ""}.\n\n\`\`\`diff\n${shown}\n\`\`\`\n\n` +
+          "Point out bugs, risky changes and missing tests, citing file and line, and suggest improvements. " +
+          "Write the review in Markdown so it can be posted to the pull request as a comment."
+      );
+      setIsChatDockOpen(true);
+      window.setTimeout(() => {
+        void sendChatRef.current?.();
+      }, 0);
+    } catch (e) {
+      notifyRef.current?.({ kind: "error", title: "GitHub", message: String(e) });
+    }
+  }, [setActiveChatDraft]);
+
+  // Posts a comment on a pull request or issue: the latest AI reply with `fromChat`, otherwise
+  // the editor's selection or a line typed in.
+  const commentOnGithub = useCallback(async (fromChat: boolean) => {
+    let body = "";
+    if (fromChat) {
+      const reply = (chatMessagesRef.current ?? [])
+        .slice()
+        .reverse()
+        .find((m) => m.role === "assistant" && !m.kind && m.content.trim());
+      body = reply?.content.trim() ?? "";
+      if (!body) {
+        window.alert("There is no AI reply in this chat to post.");
+        return;
+      }
+    } else {
+      const ed = editorRef.current;
+      const model = ed?.getModel();
+      const sel = ed?.getSelection();
+      body = model && sel ? model.getValueInRange(sel).trim() : "";
+    }
+    const answer = window.prompt("Pull request or issue number to comment on:", String(githubNumberRef.current ?? ""));
+    const number = Number(answer?.trim().replace(/^#/, ""));
+    if (!answer || !Number.isInteger(number) || number <= 0) return;
+    if (!body) body = window.prompt(`Comment on #${number}:`)?.trim() ?? "";
+    if (!body) return;
+    const preview = body.length > 400 ? `${body.slice(0, 400)}…` : body;
+    if (!window.confirm(`Post this comment on #${number}?\n\n${preview}`)) return;
+    try {
+      githubNumberRef.current = number;
+      const comment = await githubCommentPost(number, body);
+      notifyRef.current?.({ kind: "info", title: "GitHub", message: `Commented on #${number}: ${comment.url}` });
+    } catch (e) {
+      notifyRef.current?.({ kind: "error", title: "GitHub", message: String(e) });
+    }
+  }, []);
+
+  // Commits the staged changes with a message typed in or, with `useAi`, written by the AI. The
+  // message is checked against the commit rules first, and one that breaks them is only
+  // committed when the user says so and `enforce_commit_lint` is off.
+  const commitStaged = useCallback(
+    async (useAi: boolean) => {
+      const describe = (v: CommitValidation) =>
+        v.problems.map((p) => `• ${p.level === "error" ? "Error" : "Warning"}: ${p.message} (${p.rule})`).join("\n");
+      try {
+        if (useAi) {
+          const { message, validation } = await aiCommitMessage();
+          const notes = validation.problems.length ? `\n\n${describe(validation)}` : "";
+          if (!window.confirm(`Commit the staged changes with this message?\n\n${message}${notes}`)) return;
+          const info = await gitCommit(message);
+          notifyRef.current?.({ kind: "info", title: "Git", message: `Committed ${info.id.slice(0, 7)}: ${info.summary}` });
+          return;
+        }
+        let message = "";
+        for (;;) {
+          message = window.prompt("Commit message, e.g. feat(editor): add word wrap toggle", message)?.trim() ?? "";
+          if (!message) return;
+          const validation = await gitCommitValidate(message);
+          if (!validation.valid) {
+            if (settings.enforce_commit_lint) {
+              window.alert(`${describe(validation)}\n\nCommit messages must pass these rules.`);
+              continue;
+            }
+            if (!window.confirm(`${describe(validation)}\n\nCommit anyway? Cancel to edit the message.`)) continue;
+          }
+          break;
+        }
+        const info = await gitCommit(message);
+        notifyRef.current?.({ kind: "info", title: "Git", message: `Committed ${info.id.slice(0, 7)}: ${info.summary}` });
+      } catch (e) {
+        notifyRef.current?.({ kind: "error", title: "Git", message: String(e) });
+      }
+    },
+    [settings.enforce_commit_lint]
+  );
+
+  // Saves the editor's selection as a snippet for the file's language.
+  const saveSelectionAsSnippet = useCallback(async () => {
+    const ed = editorRef.current;
+    const model = ed?.getModel();
+    const sel = ed?.getSelection();
+    const body = model && sel ? model.getValueInRange(sel) : "";
+    if (!model || !body.trim()) {
+      window.alert("Select the code to save as a snippet first.");
+      return;
+    }
+    const name = window.prompt("Snippet name:")?.trim();
+    if (!name) return;
+    const prefix = window.prompt("Prefix that offers it in completions:", name.toLowerCase().replace(/\s+/g, "-"))?.trim();
+    if (!prefix) return;
+    try {
+      await snippetsSave({ name, prefix, body, languages: [model.getLanguageId()] });
+    } catch (e) {
+      notifyRef.current?.({ kind: "error", title: "Snippets", message: String(e) });
+    }
+  }, []);
+
+  // The recent app log as text, to attach to a bug report.
+  const copyRecentLogs = useCallback(async () => {
+    try {
+      const entries = await logsTail(500);
+      const text = entries
+        .map((e) => `${new Date(e.ts_ms).toISOString()} ${e.level.toUpperCase()} ${e.target}: ${e.message}`)
+        .join("\n");
+      try {
+        await navigator.clipboard.writeText(text);
+      } catch {
+        window.prompt("Copy to clipboard:", text);
+      }
+    } catch (e) {
+      notifyRef.current?.({ kind: "error", title: "Logs", message: String(e) });
+    }
+  }, []);
+
   const toggleTerminal = useCallback(() => {
     if (isTerminalOpen) {
       void closeTerminal();
@@ -2082,15 +2931,154 @@ export default function AppShell() {
     }, 0);
   }, [closeTerminal, ensureTerminal, isTerminalOpen, resizeTerminal]);
 
+  const appendTaskOutput = useCallback((lines: Omit<TaskOutputLine, "id">[]) => {
+    setTaskOutput((prev) => {
+      const next = prev.concat(lines.map((l) => ({ ...l, id: (taskOutputSeqRef.current += 1) })));
+      return next.length > TASK_OUTPUT_MAX_LINES ? next.slice(next.length - TASK_OUTPUT_MAX_LINES) : next;
+    });
+  }, []);
+
+  const refreshTasks = useCallback(async () => {
+    if (!workspace.root) {
+      setTasks([]);
+      return;
+    }
+    try {
+      setTasks(await tasksList());
+    } catch (e) {
+      // Most likely a malformed .pompora/tasks.json.
+      setTasks([]);
+      notifyRef.current?.({ kind: "error", title: "Tasks", message: String(e) });
+    }
+  }, [workspace.root]);
+
+  useEffect(() => {
+    if (isPaletteOpen) void refreshTasks();
+  }, [isPaletteOpen, refreshTasks]);
+
+  const refreshTodos = useCallback(async () => {
+    if (!workspace.root) {
+      setTodoList(null);
+      return;
+    }
+    try {
+      setTodoList(await workspaceTodos());
+    } catch (e) {
+      notifyRef.current?.({ kind: "error", title: "TODOs", message: String(e) });
+    }
+  }, [workspace.root]);
+
+  useEffect(() => {
+    if (isTerminalOpen && panelTab === "todos") void refreshTodos();
+  }, [isTerminalOpen, panelTab, refreshTodos]);
+
+  // Polled while the panel is open, so a dev server shows up in Ports soon after it starts.
+  useEffect(() => {
+    if (!isTerminalOpen) return;
+    let cancelled = false;
+    const poll = () => {
+      portsList()
+        .then((ports) => {
+          if (!cancelled) setListeningPorts(ports);
+        })
+        .catch(() => {});
+    };
+    poll();
+    const timer = window.setInterval(poll, 3000);
+    return () => {
+      cancelled = true;
+      window.clearInterval(timer);
+    };
+  }, [isTerminalOpen]);
+
+  useEffect(() => {
+    if (!isPaletteOpen) return;
+    terminalDetectShells()
+      .then(setShells)
+      .catch(() => setShells([]));
+  }, [isPaletteOpen]);
+
+  useEffect(() => {
+    const unlisten = [
+      listen<TaskStartedEvent>("task:started", (ev) => {
+        appendTaskOutput([{ kind: "info", text: `> ${ev.payload.label}: ${ev.payload.command}` }]);
+        void refreshTasks();
+      }),
+      listen<TaskOutputEvent>("task:output", (ev) => appendTaskOutput([{ kind: ev.payload.stream, text: ev.payload.line }])),
+      listen<DiagnosticsReport>("diagnostics:report", (ev) => {
+        const { source, diagnostics: found } = ev.payload;
+        setDiagnostics((prev) => {
+          if (found.length === 0 && !prev[source]) return prev;
+          const next = { ...prev };
+          if (found.length === 0) delete next[source];
+          else next[source] = found;
+          return next;
+        });
+      }),
+      listen<UpdateCheck>("update:available", (ev) => offerUpdate(ev.payload)),
+      listen<TodosChanged>("todos:changed", (ev) => {
+        const { paths, todos } = ev.payload;
+        const replaced = (p: string) => paths.some((c) => p === c || p.startsWith(`${c}/`));
+        setTodoList((prev) =>
+          prev
+            ? {
+                ...prev,
+                todos: prev.todos
+                  .filter((t) => !replaced(t.path))
+                  .concat(todos)
+                  .sort((a, b) => (a.path === b.path ? a.line - b.line : a.path < b.path ? -1 : 1)),
+              }
+            : prev
+        );
+      }),
+      listen<LintFinishedEvent>("lint:finished", (ev) => {
+        const { linter, error } = ev.payload;
+        if (error) notifyRef.current?.({ kind: "error", title: `Lint: ${linter}`, message: error });
+      }),
+      listen<TaskFinishedEvent>("task:finished", (ev) => {
+        const { exit_code, stopped, duration_ms } = ev.payload;
+        const how = stopped ? "Stopped" : exit_code === null ? "Terminated" : `Exited with code ${exit_code}`;
+        appendTaskOutput([{ kind: "info", text: `[${how} after ${(duration_ms / 1000).toFixed(1)}s]` }]);
+        void refreshTasks();
+      }),
+    ];
+    return () => {
+      for (const u of unlisten) void u.then((f) => f());
+    };
+  }, [appendTaskOutput, refreshTasks]);
+
+  useEffect(() => {
+    if (panelTab === "output") taskOutputEndRef.current?.scrollIntoView({ block: "end" });
+  }, [panelTab, taskOutput]);
+
+  const runTask = useCallback(
+    async (task: TaskInfo) => {
+      setPanelTab("output");
+      setIsTerminalOpen(true);
+      try {
+        await tasksRun(task.id);
+      } catch (e) {
+        appendTaskOutput([{ kind: "info", text: `[${task.label}: ${String(e)}]` }]);
+      }
+    },
+    [appendTaskOutput]
+  );
+
+  const stopTask = useCallback(async (task: TaskInfo, force = false) => {
+    if (!task.running) return;
+    try {
+      await tasksStop(task.running, force);
+    } catch (e) {
+      notifyRef.current?.({ kind: "error", title: "Tasks", message: String(e) });
+    }
+  }, []);
+
   const runTerminalCommand = useCallback(
     async (cmd: string, onStep?: (msg: string) => void) => {
       const c = cmd.trim();
       if (!c) return;
 
-      if (isLikelyDangerousCommand(c)) {
-        const ok = window.confirm(`The app is about to run a potentially dangerous command:\n\n${c}\n\nRun anyway?`);
-        if (!ok) return;
-      }
+      if (!(await confirmCommandRisk(c, ""))) return;
 
       setPanelTab("terminal");
       setIsTerminalOpen(true);
@@ -2171,9 +3159,24 @@ export default function AppShell() {
     [closeTerminal, ensureTerminal, resizeTerminal, settings.workspace_root, workspace.root]
   );
 
+  // Runs an AI command in the sandbox instead of the terminal, passing its output to `onStep` the
+  // way `runTerminalCommand` does. Resolves to null when the user declines to run it.
+  const runSandboxedCommand = useCallback(async (cmd: string, onStep?: (msg: string) => void) => {
+    const c = cmd.trim();
+    if (!c) return null;
+
+    if (!(await confirmCommandRisk(c, " in the sandbox"))) return null;
+
+    onStep?.(`run ${c}`);
+    const res = await aiSandboxRun(c);
+    if (res.truncated) onStep?.("terminal …");
+    for (const line of res.output.split(/\r?\n/)) onStep?.(`terminal ${line}`);
+    if (res.timed_out) onStep?.("terminal [killed: out of time]");
+    else if (res.exit_code !== 0) onStep?.(`terminal [exit code ${res.exit_code ?? "unknown"}]`);
+    return res;
+  }, []);
+
   const refreshWorkspaceAfterRun = useCallback(async () => {
-    setFileIndexRoot(null);
-    setFileIndex([]);
     await refreshDirRef.current?.(undefined);
     const dirs = Array.from(expandedDirs);
     for (const d of dirs) await refreshDirRef.current?.(d);
@@ -2192,13 +3195,20 @@ export default function AppShell() {
           run: { cmd, status: "pending", remaining, error: null, tail: null, autoFixRequested: false },
         },
       ]);
+      void aiCommandAnalyze(cmd)
+        .then((safety) =>
+          setActiveChatMessages((prev) => prev.map((m) => (m.id === id && m.run ? { ...m, run: { ...m.run, safety } } : m)))
+        )
+        .catch(() => {});
     },
     [setActiveChatMessages]
   );
 
   const runFromRunCard = useCallback(
-    async (messageId: string, mode: "once" | "always") => {
+    async (messageId: string, mode: "once" | "always", auto = false) => {
       if (mode === "always") setRunPolicy("always");
+      const sandboxMode = settings.ai_command_sandbox?.mode ?? "off";
+      const sandboxed = sandboxMode === "always" || (sandboxMode === "auto" && auto);
 
       const current = (chatMessagesRef.current ?? []).find((m) => m.id === messageId);
       if (!current?.run || current.kind !== "run_request") return;
@@ -2240,11 +3250,24 @@ export default function AppShell() {
       };
 
       try {
-        await runTerminalCommand(cmd, pushStep);
+        let sandboxFailed = false;
+        if (sandboxed) {
+          const res = await runSandboxedCommand(cmd, pushStep);
+          if (res) {
+            appendActivityStep(
+              activityId,
+              res.network_blocked ? "Ran in the sandbox, without network access" : "Ran in the sandbox, with network access"
+            );
+            sandboxFailed = res.timed_out || res.exit_code !== 0;
+          }
+        } else {
+          await runTerminalCommand(cmd, pushStep);
+        }
         await refreshWorkspaceAfterRun();
 
         const joined = tail.join("\n");
         const looksFailed =
+          sandboxFailed ||
           /\bnpm\s+err!/i.test(joined) ||
           /\berror\s+enoent\b/i.test(joined) ||
           /\bcommand failed\b/i.test(joined) ||
@@ -2309,7 +3332,7 @@ export default function AppShell() {
                 .slice()
                 .reverse()
                 .find((m) => m.kind === "run_request" && m.run?.cmd === next);
-              if (last?.id) void runFromRunCard(last.id, "once");
+              if (last?.id) void runFromRunCard(last.id, "once", true);
             }, 0);
           } else {
             pushRunRequest(next, rest);
@@ -2323,7 +3346,19 @@ export default function AppShell() {
         );
       }
     },
-    [addLog, appendActivityStep, askAiToFixRunError, pushRunRequest, refreshWorkspaceAfterRun, runPolicy, runTerminalCommand, setActiveChatMessages, setActivityStatus]
+    [
+      addLog,
+      appendActivityStep,
+      askAiToFixRunError,
+      pushRunRequest,
+      refreshWorkspaceAfterRun,
+      runPolicy,
+      runSandboxedCommand,
+      runTerminalCommand,
+      setActiveChatMessages,
+      setActivityStatus,
+      settings.ai_command_sandbox?.mode,
+    ]
   );
 
   const cancelRunCard = useCallback(
@@ -2396,8 +3431,6 @@ export default function AppShell() {
           await workspaceWriteFile(p, e.content ?? "");
           const parent = p.includes("/") ? p.split("/").slice(0, -1).join("/") : "";
           refreshTargets.add(parent);
-          setFileIndexRoot(null);
-          setFileIndex([]);
           setTabs((prev) => prev.map((t) => (t.path === p ? { ...t, content: e.content ?? "", isDirty: false } : t)));
           onStep?.(`write ${p}`);
           if (opts?.pace) await wait(120);
@@ -2421,8 +3454,6 @@ export default function AppShell() {
           await workspaceWriteFile(p, res.text);
           const parent = p.includes("/") ? p.split("/").slice(0, -1).join("/") : "";
           refreshTargets.add(parent);
-          setFileIndexRoot(null);
-          setFileIndex([]);
           setTabs((prev) => prev.map((t) => (t.path === p ? { ...t, content: res.text, isDirty: false } : t)));
           onStep?.(`patch ${p}`);
           if (opts?.pace) await wait(120);
@@ -2432,8 +3463,6 @@ export default function AppShell() {
           await workspaceDelete(p);
           const parent = p.includes("/") ? p.split("/").slice(0, -1).join("/") : "";
           refreshTargets.add(parent);
-          setFileIndexRoot(null);
-          setFileIndex([]);
           setTabs((prev) => prev.filter((t) => t.path !== p));
           if (activeTabPath === p) setActiveTabPath(null);
           onStep?.(`delete ${p}`);
@@ -2447,8 +3476,6 @@ export default function AppShell() {
           const toParent = to.includes("/") ? to.split("/").slice(0, -1).join("/") : "";
           refreshTargets.add(fromParent);
           refreshTargets.add(toParent);
-          setFileIndexRoot(null);
-          setFileIndex([]);
           setTabs((prev) => prev.map((t) => (t.path === from ? { ...t, path: to, name: basename(to), language: detectLanguage(to) } : t)));
           if (activeTabPath === from) setActiveTabPath(to);
           onStep?.(`rename ${from} → ${to}`);
@@ -2475,7 +3502,7 @@ export default function AppShell() {
           pushRunRequest(queuedRuns[0]!, queuedRuns.slice(1));
           window.setTimeout(() => {
             const last = (chatMessagesRef.current ?? []).slice().reverse().find((m) => m.kind === "run_request" && m.run?.cmd === queuedRuns[0]);
-            if (last?.id) void runFromRunCard(last.id, "once");
+            if (last?.id) void runFromRunCard(last.id, "once", true);
           }, 0);
         } else {
           pushRunRequest(queuedRuns[0]!, queuedRuns.slice(1));
@@ -2545,6 +3572,13 @@ export default function AppShell() {
         }
       }
 
+      await Promise.all(
+        files.map(async (f) => {
+          const d = await diffTexts(f.before ?? "", f.after ?? "");
+          f.added = d.added;
+          f.removed = d.removed;
+        })
+      );
       const stats = computeStats(files);
       return {
         id: `${Date.now()}-${Math.random().toString(16).slice(2)}`,
@@ -2716,13 +3750,117 @@ export default function AppShell() {
   } | null>(null);
 
   const editorRef = useRef<MonacoEditorNS.IStandaloneCodeEditor | null>(null);
+  const monacoRef = useRef<Monaco | null>(null);
+  const [editorMountSeq, setEditorMountSeq] = useState(0);
   const cursorListenerDisposeRef = useRef<{ dispose: () => void } | null>(null);
+  // Language server completions, hovers and definitions, registered once with Monaco.
+  const lspProvidersRef = useRef<{ dispose: () => void } | null>(null);
+  const lspActivePathRef = useRef<string | null>(null);
+  const lspOpenLocationRef = useRef<(path: string, line: number, column: number) => void>(() => {});
+  const snippetProviderRef = useRef<{ dispose: () => void } | null>(null);
   const [cursorPos, setCursorPos] = useState<{ line: number; col: number } | null>(null);
   const activeTab = useMemo(
     () => (activeTabPath ? tabs.find((t) => t.path === activeTabPath) ?? null : null),
     [activeTabPath, tabs]
   );
 
+  const problems = useMemo(() => {
+    const all = Object.values(diagnostics).flat();
+    const rank = { error: 0, warning: 1, info: 2 } as const;
+    return all.sort((a, b) => a.path.localeCompare(b.path) || rank[a.severity] - rank[b.severity] || a.line - b.line);
+  }, [diagnostics]);
+
+  // Underline the active file's problems in the editor.
+  useEffect(() => {
+    const monaco = monacoRef.current;
+    const model = editorRef.current?.getModel();
+    if (!monaco || !model) return;
+    const path = activeTab?.path;
+    const markers = problems
+      .filter((d) => d.path === path && d.line <= model.getLineCount())
+      .map((d) => {
+        const word = d.column ? model.getWordAtPosition({ lineNumber: d.line, column: d.column }) : null;
+        const startColumn = d.column ?? Math.max(1, model.getLineFirstNonWhitespaceColumn(d.line));
+        return {
+          severity:
+            d.severity === "error"
+              ? monaco.MarkerSeverity.Error
+              : d.severity === "warning"
+                ? monaco.MarkerSeverity.Warning
+                : monaco.MarkerSeverity.Info,
+          message: d.message,
+          source: d.matcher,
+          code: d.code ?? undefined,
+          startLineNumber: d.line,
+          startColumn,
+          endLineNumber: d.line,
+          endColumn: word ? word.endColumn : d.column ? d.column + 1 : model.getLineMaxColumn(d.line),
+        };
+      });
+    monaco.editor.setModelMarkers(model, "pompora-problems", markers);
+  }, [activeTab?.path, activeTab?.content, editorMountSeq, problems]);
+
+  // Underlines unknown words in the active file's comments and strings; a new check replaces
+  // the last one's markers, which move with the text in between.
+  const spellcheckActiveFile = useCallback(async () => {
+    const monaco = monacoRef.current;
+    const model = editorRef.current?.getModel();
+    const path = activeTab?.path;
+    if (!monaco || !model || !path || path.startsWith("untitled:")) {
+      window.alert("Open a file from the workspace to check its spelling.");
+      return;
+    }
+    try {
+      const result = await spellcheckFile(path, model.getValue());
+      const markers = result.misspellings.map((m) => ({
+        severity: monaco.MarkerSeverity.Info,
+        message: m.suggestions.length
+          ? `Unknown word "${m.word}". Did you mean: ${m.suggestions.join(", ")}?`
+          : `Unknown word "${m.word}".`,
+        source: "spelling",
+        startLineNumber: m.line,
+        startColumn: m.column,
+        endLineNumber: m.line,
+        endColumn: m.end_column,
+      }));
+      monaco.editor.setModelMarkers(model, "pompora-spelling", markers);
+      const count = result.misspellings.length;
+      notifyRef.current?.({
+        kind: "info",
+        title: "Spelling",
+        message: count
+          ? `${count}${result.truncated ? "+" : ""} unknown word${count === 1 ? "" : "s"} in ${path} (${result.language})`
+          : `No unknown words in ${path} (${result.language})`,
+      });
+    } catch (e) {
+      notifyRef.current?.({ kind: "error", title: "Spelling", message: String(e) });
+    }
+  }, [activeTab?.path]);
+
+  // Adds the flagged word under the cursor, or else the word there, to the workspace
+  // dictionary and checks the file again.
+  const addWordToDictionary = useCallback(async () => {
+    const monaco = monacoRef.current;
+    const ed = editorRef.current;
+    const model = ed?.getModel();
+    const pos = ed?.getPosition();
+    const path = activeTab?.path;
+    if (!monaco || !model || !pos || !path || path.startsWith("untitled:")) return;
+    const flagged = monaco.editor
+      .getModelMarkers({ resource: model.uri, owner: "pompora-spelling" })
+      .find((m) => m.startLineNumber === pos.lineNumber && m.startColumn <= pos.column && pos.column <= m.endColumn);
+    const atCursor = flagged ? model.getValueInRange(flagged) : model.getWordAtPosition(pos)?.word ?? "";
+    const word = window.prompt("Add to the workspace dictionary:", atCursor)?.trim();
+    if (!word) return;
+    try {
+      await spellcheckAddWord(path, word);
+    } catch (e) {
+      notifyRef.current?.({ kind: "error", title: "Spelling", message: String(e) });
+      return;
+    }
+    await spellcheckActiveFile();
+  }, [activeTab?.path, spellcheckActiveFile]);
+
   const activeTabChangeFile = useMemo(() => {
     if (!activeTab) return null;
     const cs = activeChat.changeSet;
@@ -2909,6 +4047,43 @@ export default function AppShell() {
     };
   }, []);
 
+  // Sign-ins, sign-outs and profile changes, wherever they happened (e.g. another window).
+  useEffect(() => {
+    const unlisteners = [
+      listen<AuthProfile>("auth:login", (ev) => setAuthProfile(ev.payload)),
+      listen<AuthProfile>("auth:profile_updated", (ev) => setAuthProfile(ev.payload)),
+      listen("auth:logout", () => setAuthProfile(null)),
+    ];
+    return () => {
+      for (const unlisten of unlisteners) void unlisten.then((f) => f());
+    };
+  }, []);
+
+  // The key couldn't be refreshed: ask for a new sign-in before AI requests start failing.
+  useEffect(() => {
+    const unlisten = listen<AuthExpiredEvent>("auth:expired", (ev) => {
+      const at = ev.payload.expires_at;
+      const when = at && at > Date.now() ? `expires at ${new Date(at).toLocaleTimeString()}` : "has expired";
+      notifyRef.current?.({
+        kind: "error",
+        title: "Pompora session",
+        message: `Your session ${when}. Sign in again from the account menu. (${ev.payload.reason})`,
+      });
+    });
+    return () => {
+      void unlisten.then((f) => f());
+    };
+  }, []);
+
+  useEffect(() => {
+    const unlisten = listen<CreditsUpdatedEvent>("credits:updated", (ev) => {
+      setAuthCredits(ev.payload.credits);
+    });
+    return () => {
+      void unlisten.then((f) => f());
+    };
+  }, []);
+
   useEffect(() => {
     if (!authProfile) {
       setAuthCredits(null);
@@ -2989,6 +4164,43 @@ export default function AppShell() {
     }
   }, [isAuthBusy, notify, settings.active_provider]);
 
+  const waitForLogin = useCallback(
+    (state: string) => {
+      setPendingLoginState(state);
+      void authWaitLogin(state)
+        .then(async (profile) => {
+          setAuthProfile(profile);
+          try {
+            const credits = await authGetCredits();
+            setAuthCredits(credits);
+          } catch {
+          }
+          try {
+            if (settings.active_provider === "pompora") {
+              setKeyStatus(await providerKeyStatus("pompora"));
+            }
+          } catch {
+          }
+          notify({ kind: "info", title: "Signed in", message: "Connected to your Pompora account." });
+        })
+        .catch((e) => {
+          // Canceled, or superseded by a newer sign-in: nothing went wrong.
+          if (String(e).includes("login canceled")) return;
+          notify({ kind: "error", title: "Sign in failed", message: String(e) });
+        })
+        .finally(() => {
+          setPendingLoginState((s) => (s === state ? null : s));
+        });
+    },
+    [notify, settings.active_provider]
+  );
+
+  const cancelDesktopAuth = useCallback(() => {
+    if (!pendingLoginState) return;
+    void authCancelLogin(pendingLoginState).catch(() => {
+    });
+  }, [pendingLoginState]);
+
   const beginDesktopAuthWithMode = useCallback(
     async (mode: "login" | "signup") => {
       if (isAuthBusy) return;
@@ -2998,10 +4210,10 @@ export default function AppShell() {
         let target = url;
         try {
           const u = new URL(url);
-          const redirectTo = u.searchParams.get("redirect") ?? "";
-          const st = u.searchParams.get("state") ?? state;
-          const next = `/desktop/login?redirect=${redirectTo}&state=${st}`;
-          target = `https://pompora.dev/${mode}?next=${encodeURIComponent(next)}`;
+          // Keep the whole query: besides the redirect and state it carries the PKCE challenge.
+          const next = `/desktop/login${u.search}`;
+          const site = `${u.origin}${u.pathname.replace(/\/desktop\/login$/, "")}`;
+          target = `${site}/${mode}?next=${encodeURIComponent(next)}`;
         } catch {
         }
 
@@ -3014,54 +4226,18 @@ export default function AppShell() {
           } catch {
           }
           setIsAuthBusy(false);
-          void authWaitLogin(state)
-            .then(async (profile) => {
-              setAuthProfile(profile);
-              try {
-                const credits = await authGetCredits();
-                setAuthCredits(credits);
-              } catch {
-              }
-              try {
-                if (settings.active_provider === "pompora") {
-                  setKeyStatus(await providerKeyStatus("pompora"));
-                }
-              } catch {
-              }
-              notify({ kind: "info", title: "Signed in", message: "Connected to your Pompora account." });
-            })
-            .catch((e) => {
-              notify({ kind: "error", title: "Sign in failed", message: String(e) });
-            });
+          waitForLogin(state);
           return;
         }
         setIsAuthBusy(false);
-        void authWaitLogin(state)
-          .then(async (profile) => {
-            setAuthProfile(profile);
-            try {
-              const credits = await authGetCredits();
-              setAuthCredits(credits);
-            } catch {
-            }
-            try {
-              if (settings.active_provider === "pompora") {
-                setKeyStatus(await providerKeyStatus("pompora"));
-              }
-            } catch {
-            }
-            notify({ kind: "info", title: "Signed in", message: "Connected to your Pompora account." });
-          })
-          .catch((e) => {
-            notify({ kind: "error", title: "Sign in failed", message: String(e) });
-          });
+        waitForLogin(state);
       } catch (e) {
         notify({ kind: "error", title: "Sign in failed", message: String(e) });
       } finally {
         setIsAuthBusy(false);
       }
     },
-    [authGetCredits, authWaitLogin, isAuthBusy, notify, safeOpenUrl, settings.active_provider]
+    [isAuthBusy, notify, safeOpenUrl, waitForLogin]
   );
 
   // Additional effect to refresh key status when showKeySaved is true
@@ -3241,22 +4417,77 @@ export default function AppShell() {
 
   const refreshDir = useCallback(async (relDir?: string) => {
     const key = relDir ?? "";
-    const entries = await workspaceListDir(relDir);
-    const seen = new Set<string>();
-    const deduped = entries.filter((e) => {
-      const p = String((e as any)?.path || "");
-      if (!p) return false;
-      if (seen.has(p)) return false;
-      seen.add(p);
-      return true;
-    });
-    setExplorer((prev) => ({ ...prev, [key]: deduped }));
+    // Two levels, so subfolders open without another round trip and their arrows are right.
+    const entries = await workspaceListDir(relDir, undefined, 2);
+    const dedupe = (list: DirEntryInfo[]) => {
+      const seen = new Set<string>();
+      return list.filter((e) => {
+        const p = String((e as any)?.path || "");
+        if (!p) return false;
+        if (seen.has(p)) return false;
+        seen.add(p);
+        return true;
+      });
+    };
+    const deduped = dedupe(entries);
+    const listed: Record<string, DirEntryInfo[]> = { [key]: deduped };
+    for (const e of deduped) {
+      if (e.is_dir && e.children) listed[e.path] = dedupe(e.children);
+    }
+    setExplorer((prev) => ({ ...prev, ...listed }));
   }, []);
 
   useEffect(() => {
     refreshDirRef.current = refreshDir;
   }, [refreshDir]);
 
+  const tabsRef = useRef(tabs);
+  useEffect(() => {
+    tabsRef.current = tabs;
+  }, [tabs]);
+
+  // Keep the explorer and clean tabs in sync with changes made outside the editor.
+  useEffect(() => {
+    if (!workspace.root) return;
+    const parentOf = (p: string) => (p.includes("/") ? p.split("/").slice(0, -1).join("/") : "");
+    const refreshParent = (p: string) => void refreshDirRef.current?.(parentOf(p) || undefined).catch(() => {});
+
+    const unlisten = [
+      listen<FsPathEvent>("fs:created", (ev) => refreshParent(ev.payload.path)),
+      listen<FsPathEvent>("fs:deleted", (ev) => refreshParent(ev.payload.path)),
+      listen<FsRenameEvent>("fs:renamed", (ev) => {
+        const { from, to } = ev.payload;
+        refreshParent(from);
+        if (parentOf(from) !== parentOf(to)) refreshParent(to);
+        const moved = (p: string) => (p === from || p.startsWith(`${from}/`) ? to + p.slice(from.length) : null);
+        setTabs((prev) =>
+          prev.map((t) => {
+            const path = moved(t.path);
+            return path ? { ...t, path, name: path.split("/").pop() || path } : t;
+          })
+        );
+        setActiveTabPath((cur) => (cur ? moved(cur) ?? cur : cur));
+      }),
+      listen<FsPathEvent>("fs:modified", async (ev) => {
+        const p = ev.payload.path;
+        const open = tabsRef.current.find((t) => t.path === p);
+        if (!open || open.isDirty) return;
+        let file: VersionedText;
+        try {
+          file = await workspaceReadFileText(p);
+        } catch {
+          return;
+        }
+        setTabs((prev) =>
+          prev.map((t) => (t.path === p && !t.isDirty ? { ...t, content: file.content, version: file.version } : t))
+        );
+      }),
+    ];
+    return () => {
+      for (const u of unlisten) void u.then((f) => f());
+    };
+  }, [workspace.root]);
+
   const getEntry = useCallback(
     (path: string): DirEntryInfo | null => {
       const parent = path.includes("/") ? path.split("/").slice(0, -1).join("/") : "";
@@ -3288,15 +4519,14 @@ export default function AppShell() {
   const addFolderToWorkspace = useCallback(async () => {
     const folder = await workspacePickFolder();
     if (!folder) return;
-    // Multi-root workspaces are not implemented yet; mimic VS Code by switching to the picked folder.
-    window.alert("Multi-root workspace is not implemented yet. Opening the selected folder instead.");
-    const w = await workspaceSet(folder);
+    const w = await workspaceAddRoot(folder);
     setWorkspaceState(w);
     setSettingsState((s) => ({
       ...s,
       workspace_root: w.root,
       recent_workspaces: w.recent,
     }));
+    // Paths gain a root-name prefix once there is more than one root, so open tabs go stale.
     setTabs([]);
     setActiveTabPath(null);
     await refreshRoot();
@@ -3307,10 +4537,15 @@ export default function AppShell() {
   }, [refreshRoot]);
 
   useEffect(() => {
-    // Clear cached file index when workspace root changes.
-    setFileIndexRoot(null);
-    setFileIndex([]);
-  }, [workspace.root]);
+    // Ask once per newly opened folder; the answer is stored per root by the backend.
+    if (!workspace.root || workspace.trusted !== null) return;
+    const ok = window.confirm(
+      `Do you trust the authors of the files in '${basename(workspace.root)}'?\n\nUntrusted workspaces can't open terminals, run AI-proposed commands, or feed project instruction files to the AI.`
+    );
+    void workspaceTrustSet(ok)
+      .then((w) => setWorkspaceState(w))
+      .catch(() => {});
+  }, [workspace.root, workspace.trusted]);
 
   useEffect(() => {
     chatMessagesRef.current = activeChat.messages;
@@ -3398,6 +4633,28 @@ export default function AppShell() {
     }
   }, [devConsoleError, refreshRoot]);
 
+  const cloneRepository = useCallback(async () => {
+    const url = window.prompt("Repository URL")?.trim();
+    if (!url) return;
+    try {
+      const dest = await workspacePickFolder();
+      if (!dest) return;
+      const w = await gitClone(url, dest);
+      setWorkspaceState(w);
+      setSettingsState((s) => ({
+        ...s,
+        workspace_root: w.root,
+        recent_workspaces: w.recent,
+      }));
+      setTabs([]);
+      setActiveTabPath(null);
+      await refreshRoot();
+    } catch (e) {
+      devConsoleError("Clone failed", e);
+      window.alert(`Failed to clone repository: ${String(e)}`);
+    }
+  }, [devConsoleError, refreshRoot]);
+
   const openRecent = useCallback(
     async (root: string) => {
       const w = await workspaceSet(root);
@@ -3419,18 +4676,21 @@ export default function AppShell() {
       const norm = normalizeRelPath(relPath);
       if (!norm) return;
 
-      let content = "";
+      let file: VersionedText = { content: "", encoding: "UTF-8", bom: false, version: "" };
       try {
-        content = await workspaceReadFile(norm);
+        file = await workspaceReadFileText(norm);
       } catch {
-        content = "";
+        // Opens as an empty tab.
       }
       const tab: EditorTab = {
         path: norm,
         name: basename(norm),
         language: detectLanguage(norm),
-        content,
+        content: file.content,
         isDirty: false,
+        encoding: file.encoding,
+        bom: file.bom,
+        version: file.version || undefined,
       };
 
       setTabs((prev) => {
@@ -3443,15 +4703,32 @@ export default function AppShell() {
         setActiveTabPath(norm);
         return [...prev, tab];
       });
+      void lspDocumentOpened(tab.language, norm, file.content);
 
       if (workspace.root) {
         const abs = `${workspace.root.replace(/\\/g, "/").replace(/\/$/, "")}/${norm}`;
         rememberRecentFile(abs);
+        void recentFileAdd(norm).catch(() => {});
       }
     },
     [rememberRecentFile, workspace.root]
   );
 
+  useEffect(() => {
+    lspActivePathRef.current = activeTab?.path ?? null;
+  }, [activeTab?.path]);
+
+  useEffect(() => {
+    lspOpenLocationRef.current = (path, line, column) => {
+      setPendingReveal({ path, line, column });
+      void openFile(path);
+    };
+  }, [openFile]);
+
+  useEffect(() => {
+    lspSyncOpenDocuments(tabs.map((t) => t.path));
+  }, [tabs]);
+
   const changeWriteFiles = useMemo(() => {
     const cs = activeChat.changeSet;
     if (!cs) return [] as ChangeFile[];
@@ -3746,31 +5023,6 @@ export default function AppShell() {
     [activeChat.changeSet, activeTabPath, openFile, selectedChangePath, setActiveChatChangeSet, workspace.root]
   );
 
-  const ensureFileIndex = useCallback(async () => {
-    if (!workspace.root) return;
-    if (isFileIndexLoading) return;
-    if (fileIndexRoot === workspace.root && fileIndex.length) return;
-
-    setIsFileIndexLoading(true);
-    try {
-      const files = await workspaceListFiles(20000);
-      const seen = new Set<string>();
-      const deduped = files
-        .map((p) => normalizeRelPath(p))
-        .filter((p) => {
-          const k = String(p || "");
-          if (!k) return false;
-          if (seen.has(k)) return false;
-          seen.add(k);
-          return true;
-        });
-      setFileIndex(deduped);
-      setFileIndexRoot(workspace.root);
-    } finally {
-      setIsFileIndexLoading(false);
-    }
-  }, [fileIndex.length, fileIndexRoot, isFileIndexLoading, workspace.root]);
-
   const openQuickOpen = useCallback(async () => {
     if (!workspace.root) {
       await openFolder();
@@ -3779,8 +5031,7 @@ export default function AppShell() {
     setIsQuickOpenOpen(true);
     setQuickOpenQuery("");
     setQuickOpenIndex(0);
-    void ensureFileIndex();
-  }, [ensureFileIndex, openFolder, workspace.root]);
+  }, [openFolder, workspace.root]);
 
   const openStandaloneFile = useCallback(async () => {
     try {
@@ -3813,6 +5064,33 @@ export default function AppShell() {
     }
   }, [devConsoleError, openFile, refreshRoot, rememberRecentFile]);
 
+  // Saves over the version the tab was read at. On a conflict an interactive save asks before
+  // overwriting; returns the new version, or null when nothing was written.
+  // A protected file is only saved when an interactive save confirms the override.
+  const writeTab = useCallback(async (t: EditorTab, interactive: boolean): Promise<string | null> => {
+    let allowProtected = false;
+    const guarded = <T,>(run: (allow: boolean) => Promise<T>) =>
+      interactive
+        ? overridingProtection((allow) => {
+            allowProtected = allow;
+            return run(allow);
+          })
+        : run(false);
+    const expected = t.version;
+    if (!expected) return (await guarded((allow) => workspaceWriteFile(t.path, t.content, t.encoding, t.bom, allow)))?.version ?? null;
+    const res = await guarded((allow) => workspaceWriteFileIfUnchanged(t.path, t.content, expected, t.encoding, t.bom, allow));
+    if (!res) return null;
+    if (res.outcome === "written") return res.version;
+    if (!interactive) return null;
+    const ok = window.confirm(
+      res.current
+        ? `'${t.name}' changed on disk since it was opened. Overwrite it with your version?`
+        : `'${t.name}' was deleted on disk. Save it again?`
+    );
+    if (!ok) return null;
+    return (await workspaceWriteFile(t.path, t.content, t.encoding, t.bom, allowProtected)).version;
+  }, []);
+
   useEffect(() => {
     if (!autoSaveEnabled) return;
     if (!activeTab) return;
@@ -3822,8 +5100,10 @@ export default function AppShell() {
     const t = window.setTimeout(() => {
       void (async () => {
         try {
-          await workspaceWriteFile(activeTab.path, activeTab.content);
-          setTabs((prev) => prev.map((x) => (x.path === activeTab.path ? { ...x, isDirty: false } : x)));
+          // Conflicts are left for an explicit save to resolve.
+          const version = await writeTab(activeTab, false);
+          if (version === null) return;
+          setTabs((prev) => prev.map((x) => (x.path === activeTab.path ? { ...x, isDirty: false, version } : x)));
         } catch (e) {
           devConsoleError("Auto save failed", e);
         }
@@ -3831,7 +5111,7 @@ export default function AppShell() {
     }, 600);
 
     return () => window.clearTimeout(t);
-  }, [activeTab, autoSaveEnabled]);
+  }, [activeTab, autoSaveEnabled, writeTab]);
 
   const goToLine = useCallback(
     (lineNumber: number) => {
@@ -3867,7 +5147,17 @@ export default function AppShell() {
     if (!model) return;
     const line = Math.max(1, Math.min(pendingReveal.line, model.getLineCount()));
     ed.revealLineInCenter(line);
-    ed.setPosition({ lineNumber: line, column: 1 });
+    const column = pendingReveal.column ?? 1;
+    if (pendingReveal.endLine && pendingReveal.endColumn) {
+      ed.setSelection({
+        startLineNumber: line,
+        startColumn: column,
+        endLineNumber: pendingReveal.endLine,
+        endColumn: pendingReveal.endColumn,
+      });
+    } else {
+      ed.setPosition({ lineNumber: line, column });
+    }
     ed.focus();
     setPendingReveal(null);
   }, [activeTab, pendingReveal]);
@@ -3910,7 +5200,7 @@ export default function AppShell() {
     const toRel = parent ? `${parent}/${nextName}` : nextName;
     const fromRel = selectedPath;
 
-    await workspaceRename(fromRel, toRel);
+    if ((await overridingProtection((allow) => workspaceRename(fromRel, toRel, allow))) === null) return;
 
     setTabs((prev) =>
       prev.map((t) => {
@@ -3944,10 +5234,24 @@ export default function AppShell() {
 
   const deleteSelected = useCallback(async () => {
     if (!selectedPath) return;
-    const ok = window.confirm(`Delete '${basename(selectedPath)}'?`);
+    const toTrash = settings.delete_to_trash ?? true;
+    const ok = window.confirm(
+      toTrash ? `Move '${basename(selectedPath)}' to the trash?` : `Permanently delete '${basename(selectedPath)}'?`
+    );
     if (!ok) return;
     const target = selectedPath;
-    await workspaceDelete(target);
+    let allowProtected = false;
+    try {
+      const done = await overridingProtection(async (allow) => {
+        allowProtected = allow;
+        await workspaceDelete(target, undefined, allow);
+        return true;
+      });
+      if (!done) return;
+    } catch (e) {
+      if (!toTrash || !window.confirm(`${String(e)}\n\nDelete '${basename(target)}' permanently?`)) return;
+      await workspaceDelete(target, true, allowProtected);
+    }
 
     setTabs((prev) =>
       prev.filter((t) => {
@@ -3966,30 +5270,46 @@ export default function AppShell() {
 
     setSelectedPath(null);
     await refreshRoot();
-  }, [refreshRoot, selectedPath]);
+  }, [refreshRoot, selectedPath, settings.delete_to_trash]);
+
+  const undoLastFileOperation = useCallback(async () => {
+    try {
+      const undone = await fsopsUndoLast();
+      notifyRef.current?.({ kind: "info", title: "Undone", message: `${undone.kind.replace("_", " ")} ${undone.paths.join(" → ")}` });
+    } catch (e) {
+      notifyRef.current?.({ kind: "error", title: "Undo failed", message: String(e) });
+    }
+    await refreshRoot();
+  }, [refreshRoot]);
 
   useEffect(() => {
     let cancelled = false;
     const q = searchQuery.trim();
     if (!q) {
-      setSearchResults([]);
+      setSearchResults(NO_SEARCH_RESULTS);
       return;
     }
     if (!workspace.root) {
-      setSearchResults([]);
+      setSearchResults(NO_SEARCH_RESULTS);
       return;
     }
 
     setIsSearching(true);
     const t = window.setTimeout(() => {
-      workspaceSearch(q, 200)
+      workspaceSearch(q, 200, undefined, {
+        case_sensitive: searchCaseSensitive,
+        whole_word: searchWholeWord,
+        multiline: searchMultiline,
+        max_matches_per_file: SEARCH_MATCHES_PER_FILE,
+        paths: searchScope === null ? undefined : [searchScope],
+      })
         .then((res) => {
           if (cancelled) return;
           setSearchResults(res);
         })
         .catch(() => {
           if (cancelled) return;
-          setSearchResults([]);
+          setSearchResults(NO_SEARCH_RESULTS);
         })
         .finally(() => {
           if (cancelled) return;
@@ -4001,7 +5321,7 @@ export default function AppShell() {
       cancelled = true;
       window.clearTimeout(t);
     };
-  }, [searchQuery, workspace.root]);
+  }, [searchCaseSensitive, searchMultiline, searchQuery, searchScope, searchWholeWord, workspace.root]);
 
   const closeTab = useCallback(
     (path: string) => {
@@ -4035,15 +5355,19 @@ export default function AppShell() {
       if (!name) return;
       const rel = name.trim().replace(/\\/g, "/");
       if (!rel) return;
-      await workspaceWriteFile(rel, activeTab.content);
-      setTabs((prev) => prev.map((t) => (t.path === activeTab.path ? { ...t, path: rel, name: basename(rel), language: detectLanguage(rel), isDirty: false } : t)));
+      const written = await overridingProtection((allow) => workspaceWriteFile(rel, activeTab.content, undefined, undefined, allow));
+      if (!written) return;
+      const { version } = written;
+      setTabs((prev) => prev.map((t) => (t.path === activeTab.path ? { ...t, path: rel, name: basename(rel), language: detectLanguage(rel), isDirty: false, version } : t)));
       setActiveTabPath(rel);
       return;
     }
-    await workspaceWriteFile(activeTab.path, activeTab.content);
-    setTabs((prev) => prev.map((t) => (t.path === activeTab.path ? { ...t, isDirty: false } : t)));
+    const version = await writeTab(activeTab, true);
+    if (version === null) return;
+    setTabs((prev) => prev.map((t) => (t.path === activeTab.path ? { ...t, isDirty: false, version } : t)));
+    if (settings.lint?.on_save) void runLinters([activeTab.path]);
     await refreshDir(activeTab.path.includes("/") ? activeTab.path.split("/").slice(0, -1).join("/") : undefined);
-  }, [activeTab, openFolder, refreshDir, workspace.root]);
+  }, [activeTab, openFolder, refreshDir, runLinters, settings.lint?.on_save, workspace.root, writeTab]);
 
   const saveAll = useCallback(async () => {
     const dirty = tabs.filter((t) => t.isDirty);
@@ -4054,17 +5378,20 @@ export default function AppShell() {
         if (!name) continue;
         const rel = name.trim().replace(/\\/g, "/");
         if (!rel) continue;
-        await workspaceWriteFile(rel, t.content);
+        const written = await overridingProtection((allow) => workspaceWriteFile(rel, t.content, undefined, undefined, allow));
+        if (!written) continue;
+        const { version } = written;
         setTabs((prev) =>
-          prev.map((x) => (x.path === t.path ? { ...x, path: rel, name: basename(rel), language: detectLanguage(rel), isDirty: false } : x))
+          prev.map((x) => (x.path === t.path ? { ...x, path: rel, name: basename(rel), language: detectLanguage(rel), isDirty: false, version } : x))
         );
         setActiveTabPath(rel);
         continue;
       }
-      await workspaceWriteFile(t.path, t.content);
-      setTabs((prev) => prev.map((x) => (x.path === t.path ? { ...x, isDirty: false } : x)));
+      const version = await writeTab(t, true);
+      if (version === null) continue;
+      setTabs((prev) => prev.map((x) => (x.path === t.path ? { ...x, isDirty: false, version } : x)));
     }
-  }, [tabs]);
+  }, [tabs, writeTab]);
 
   const saveAs = useCallback(async () => {
     if (!activeTab) return;
@@ -4076,7 +5403,9 @@ export default function AppShell() {
     if (!name) return;
     const rel = name.trim().replace(/\\/g, "/");
     if (!rel) return;
-    await workspaceWriteFile(rel, activeTab.content);
+    const written = await overridingProtection((allow) => workspaceWriteFile(rel, activeTab.content, activeTab.encoding, activeTab.bom, allow));
+    if (!written) return;
+    const { version } = written;
     setTabs((prev) => {
       const without = prev.filter((t) => t.path !== activeTab.path);
       const next: EditorTab = {
@@ -4085,6 +5414,9 @@ export default function AppShell() {
         language: detectLanguage(rel),
         content: activeTab.content,
         isDirty: false,
+        encoding: activeTab.encoding,
+        bom: activeTab.bom,
+        version,
       };
       return [...without, next];
     });
@@ -4185,6 +5517,29 @@ export default function AppShell() {
     });
   }, [devConsoleError]);
 
+  const toggleHiddenFiles = useCallback(async () => {
+    const next: AppSettings = { ...settings, show_hidden_files: !(settings.show_hidden_files ?? true) };
+    setSettingsState(next);
+    try {
+      await settingsSet(next);
+    } catch (e) {
+      devConsoleError("Failed to save hidden files setting", e);
+      return;
+    }
+    // Listings read the setting on the backend, so refresh only once it is saved.
+    await refreshRoot();
+  }, [devConsoleError, refreshRoot, settings]);
+
+  const toggleSearchIndex = useCallback(async () => {
+    const next: AppSettings = { ...settings, search_index: !(settings.search_index ?? false) };
+    setSettingsState(next);
+    try {
+      await settingsSet(next);
+    } catch (e) {
+      devConsoleError("Failed to save search index setting", e);
+    }
+  }, [devConsoleError, settings]);
+
   const changeProvider = useCallback(
     async (p: string | null) => {
       const seq = (settingsMutationSeqRef.current += 1);
@@ -4582,7 +5937,7 @@ export default function AppShell() {
       // If the active provider is Pompora, credits can change per request.
       if (settings.active_provider === "pompora") {
         try {
-          const credits = await authGetCredits();
+          const credits = await authGetCredits(true);
           setAuthCredits(credits);
         } catch {
         }
@@ -4639,6 +5994,46 @@ export default function AppShell() {
     sendChatRef.current = sendChat;
   }, [sendChat]);
 
+  // Writes the active chat to `.pompora/chats/` in the workspace and opens it, or to a folder the user picks.
+  const exportActiveChat = useCallback(
+    async (format: ChatExportFormat, pickFolder: boolean) => {
+      const chat = activeChat;
+      const cs = chat.changeSet;
+      const transcript: ChatTranscript = {
+        id: chat.id,
+        title: chat.title,
+        created_at: chat.createdAt,
+        updated_at: chat.updatedAt,
+        provider: settings.active_provider ?? null,
+        model: settings.active_provider === "pompora" ? (settings.pompora_thinking ?? uiPomporaThinking ?? "slow") : null,
+        messages: chat.messages.filter(isUserOrAssistantMessage).flatMap((m) => {
+          const p = m.proposal;
+          const current = !!p && cs?.id === p.changeSetId;
+          const edits = (p?.files ?? []).map((f) => {
+            const cf = current ? cs?.files.find((x) => x.path === f.path) : undefined;
+            return { kind: f.kind, path: f.path, added: cf?.added ?? null, removed: cf?.removed ?? null, applied: current && !!cs?.applied };
+          });
+          const content = m.content.trim() || p?.title || "";
+          return content || edits.length ? [{ role: m.role, content, edits }] : [];
+        }),
+      };
+      try {
+        let dest: string | undefined;
+        if (pickFolder || !workspace.root) {
+          const folder = await workspacePickFolder();
+          if (!folder) return;
+          dest = folder;
+        }
+        const path = await chatExport(transcript, format, dest);
+        if (dest) window.alert(`Chat exported to ${path}`);
+        else await openFile(path);
+      } catch (e) {
+        notifyRef.current?.({ kind: "error", title: "Export Chat", message: String(e) });
+      }
+    },
+    [activeChat, openFile, settings.active_provider, settings.pompora_thinking, uiPomporaThinking, workspace.root]
+  );
+
   const commands = useMemo<Command[]>(() => {
     const c: Command[] = [
       { id: "file.openFolder", label: "File: Open Folder...", shortcut: "Ctrl+K Ctrl+O", run: () => void openFolder() },
@@ -4649,15 +6044,87 @@ export default function AppShell() {
       { id: "file.newFolder", label: "File: New Folder...", run: () => void createNewFolder() },
       { id: "file.rename", label: "File: Rename...", run: () => void renameSelected() },
       { id: "file.delete", label: "File: Delete", run: () => void deleteSelected() },
+      { id: "file.undoOperation", label: "File: Undo Last File Operation", run: () => void undoLastFileOperation() },
       { id: "file.save", label: "File: Save", shortcut: "Ctrl+S", run: () => void saveActiveFile() },
       { id: "file.saveAll", label: "File: Save All", shortcut: "Ctrl+K S", run: () => void saveAll() },
       { id: "view.commandPalette", label: "View: Show Command Palette", shortcut: "Ctrl+Shift+P", run: () => setIsPaletteOpen(true) },
       { id: "workbench.findInFiles", label: "Search: Find in Files", shortcut: "Ctrl+Shift+F", run: () => setActivity("search") },
       { id: "view.toggleTheme", label: "Preferences: Toggle Theme", run: () => toggleTheme() },
+      { id: "view.toggleHiddenFiles", label: "View: Toggle Hidden Files", run: () => void toggleHiddenFiles() },
+      { id: "search.toggleIndex", label: "Search: Toggle Search Index", run: () => void toggleSearchIndex() },
       { id: "view.settings", label: "Preferences: Open Settings", shortcut: "Ctrl+,", run: () => openSettingsTab() },
       { id: "workbench.focusExplorer", label: "View: Focus Explorer", run: () => setActivity("explorer") },
     ];
 
+    for (const t of tasks) {
+      if (t.running) {
+        c.push(
+          { id: `task.stop.${t.id}`, label: `Tasks: Stop ${t.label}`, run: () => void stopTask(t) },
+          { id: `task.kill.${t.id}`, label: `Tasks: Force Stop ${t.label}`, run: () => void stopTask(t, true) }
+        );
+      } else {
+        c.push({ id: `task.run.${t.id}`, label: `Tasks: Run ${t.label}`, run: () => void runTask(t) });
+      }
+    }
+
+    for (const sh of shells) {
+      const current = settings.default_terminal_profile === sh.name;
+      c.push({
+        id: `terminal.shell.${sh.name}`,
+        label: `Terminal: Use ${sh.name}${current ? " (current)" : ""}`,
+        run: () => void selectTerminalShell(sh),
+      });
+    }
+
+    if (isTerminalOpen) {
+      c.push(
+        { id: "terminal.previousCommand", label: "Terminal: Scroll to Previous Command", run: () => scrollToTerminalCommand(-1) },
+        { id: "terminal.nextCommand", label: "Terminal: Scroll to Next Command", run: () => scrollToTerminalCommand(1) },
+        { id: "terminal.find", label: "Terminal: Find…", run: () => void findInTerminal() },
+        { id: "terminal.findPrevious", label: "Terminal: Find Previous", run: () => showTerminalMatch(-1) },
+        { id: "terminal.findNext", label: "Terminal: Find Next", run: () => showTerminalMatch(1) },
+        { id: "terminal.copyLastOutput", label: "Terminal: Copy Last Command Output", run: () => void copyLastTerminalCommandOutput() },
+        { id: "terminal.rerunLast", label: "Terminal: Re-run Last Command", run: () => void rerunLastTerminalCommand() },
+        { id: "terminal.searchHistory", label: "Terminal: Search Command History…", run: () => void searchTerminalHistory() }
+      );
+    }
+    c.push({ id: "terminal.clearHistory", label: "Terminal: Clear Command History", run: () => void clearTerminalHistory() });
+    c.push({ id: "lint.run", label: "Lint: Run Linters", run: () => void runLinters() });
+    c.push({ id: "snippets.import", label: "Snippets: Import VS Code Snippets…", run: () => void importSnippets() });
+    c.push({ id: "snippets.saveSelection", label: "Snippets: Save Selection as Snippet…", run: () => void saveSelectionAsSnippet() });
+    c.push({ id: "chat.exportMarkdown", label: "Chat: Export as Markdown", run: () => void exportActiveChat("markdown", false) });
+    c.push({ id: "chat.exportJson", label: "Chat: Export as JSON", run: () => void exportActiveChat("json", false) });
+    c.push({ id: "chat.exportMarkdownTo", label: "Chat: Export as Markdown to Folder…", run: () => void exportActiveChat("markdown", true) });
+    c.push({ id: "chat.exportJsonTo", label: "Chat: Export as JSON to Folder…", run: () => void exportActiveChat("json", true) });
+    c.push({ id: "env.use", label: "Env: Load Env File in Terminals and Tasks…", run: () => void chooseEnvFile() });
+    c.push({ id: "env.show", label: "Env: Show Variables…", run: () => void showEnvVariables() });
+    c.push({ id: "env.copyValue", label: "Env: Copy Variable Value…", run: () => void copyEnvValue() });
+    c.push({ id: "env.set", label: "Env: Set Variable…", run: () => void setEnvVariable(false) });
+    c.push({ id: "env.remove", label: "Env: Remove Variable…", run: () => void setEnvVariable(true) });
+    c.push({ id: "deps.list", label: "Dependencies: List Declared Dependencies", run: () => void showDependencies(false) });
+    c.push({ id: "deps.outdated", label: "Dependencies: Check for Newer Versions", run: () => void showDependencies(true) });
+    c.push({ id: "deps.audit", label: "Dependencies: Audit for Vulnerabilities", run: () => void auditDependencies(false) });
+    c.push({ id: "deps.auditAskAi", label: "Dependencies: Ask AI to Fix Vulnerabilities", run: () => void auditDependencies(true) });
+    c.push({ id: "git.commit", label: "Git: Commit Staged Changes…", run: () => void commitStaged(false) });
+    c.push({ id: "git.commitAi", label: "Git: Commit Staged Changes with AI Message", run: () => void commitStaged(true) });
+    c.push({ id: "spelling.check", label: "Spelling: Check Current File", run: () => void spellcheckActiveFile() });
+    c.push({ id: "spelling.addWord", label: "Spelling: Add Word to Workspace Dictionary…", run: () => void addWordToDictionary() });
+    c.push({ id: "github.signIn", label: "GitHub: Sign In…", run: () => void signInToGithub() });
+    c.push({ id: "github.pulls", label: "GitHub: List Pull Requests", run: () => void showGithubList("pulls") });
+    c.push({ id: "github.issues", label: "GitHub: List Issues", run: () => void showGithubList("issues") });
+    c.push({ id: "github.review", label: "GitHub: Review Pull Request with AI…", run: () => void reviewPullRequest() });
+    c.push({ id: "github.commentAiReply", label: "GitHub: Post Last AI Reply as Comment…", run: () => void commentOnGithub(true) });
+    c.push({ id: "github.comment", label: "GitHub: Comment on Pull Request or Issue…", run: () => void commentOnGithub(false) });
+    c.push({ id: "http.send", label: "HTTP: Send Request…", run: () => void promptHttpRequest() });
+    c.push({ id: "http.saveLast", label: "HTTP: Save Last Request…", run: () => void saveLastHttpRequest() });
+    for (const col of httpCollections) {
+      for (const r of col.requests) {
+        c.push({ id: `http.saved.${col.name}.${r.name}`, label: `HTTP: Send ${col.name} › ${r.name}`, run: () => void sendHttpRequest(r) });
+      }
+    }
+    c.push({ id: "help.copyLogs", label: "Help: Copy Recent Logs", run: () => void copyRecentLogs() });
+    c.push({ id: "help.checkForUpdates", label: "Help: Check for Updates", run: () => void checkForUpdates() });
+
     if (activeTab) {
       c.push({
         id: "file.closeActive",
@@ -4668,7 +6135,59 @@ export default function AppShell() {
     }
 
     return c;
-  }, [activeTab, closeTab, createNewFolder, deleteSelected, newUntitledFile, openFolder, openGoToLine, openQuickOpen, renameSelected, saveActiveFile, saveAll]);
+  }, [
+    activeTab,
+    addWordToDictionary,
+    auditDependencies,
+    checkForUpdates,
+    chooseEnvFile,
+    clearTerminalHistory,
+    closeTab,
+    commentOnGithub,
+    commitStaged,
+    copyLastTerminalCommandOutput,
+    copyEnvValue,
+    copyRecentLogs,
+    createNewFolder,
+    deleteSelected,
+    exportActiveChat,
+    findInTerminal,
+    httpCollections,
+    importSnippets,
+    isTerminalOpen,
+    newUntitledFile,
+    openFolder,
+    openGoToLine,
+    openQuickOpen,
+    promptHttpRequest,
+    renameSelected,
+    rerunLastTerminalCommand,
+    reviewPullRequest,
+    runLinters,
+    runTask,
+    saveActiveFile,
+    saveAll,
+    saveLastHttpRequest,
+    saveSelectionAsSnippet,
+    scrollToTerminalCommand,
+    searchTerminalHistory,
+    selectTerminalShell,
+    sendHttpRequest,
+    setEnvVariable,
+    settings.default_terminal_profile,
+    shells,
+    showDependencies,
+    showEnvVariables,
+    showGithubList,
+    showTerminalMatch,
+    signInToGithub,
+    spellcheckActiveFile,
+    stopTask,
+    tasks,
+    toggleHiddenFiles,
+    toggleSearchIndex,
+    undoLastFileOperation,
+  ]);
 
   const filteredCommands = useMemo(() => {
     const q = paletteQuery.trim().toLowerCase();
@@ -5766,14 +7285,14 @@ export default function AppShell() {
                         label="Open Dashboard"
                         onClick={() => {
                           setIsAccountMenuOpen(false);
-                          void safeOpenUrl("https://pompora.dev/dashboard");
+                          void safeOpenUrl(`${pomporaSiteUrl(settings)}/dashboard`);
                         }}
                       />
                       <MenuItem
                         label="Manage Plan"
                         onClick={() => {
                           setIsAccountMenuOpen(false);
-                          void safeOpenUrl("https://pompora.dev/pricing");
+                          void safeOpenUrl(`${pomporaSiteUrl(settings)}/pricing`);
                         }}
                       />
                       <MenuSep />
@@ -5800,6 +7319,16 @@ export default function AppShell() {
                   Log in
                 </button>
               )}
+              {!authProfile && pendingLoginState ? (
+                <button
+                  type="button"
+                  className="ws-vscode-btn ws-vscode-btn-ghost h-7 px-3 text-[13px]"
+                  onClick={cancelDesktopAuth}
+                  title="Stop waiting for the browser sign-in"
+                >
+                  Cancel
+                </button>
+              ) : null}
 
               <button type="button" className="ws-icon-btn" onClick={() => openSettingsTab()}>
                 <SettingsIcon className="h-4 w-4" />
@@ -5865,36 +7394,122 @@ export default function AppShell() {
                   <div className="text-sm text-muted">Open a folder to search.</div>
                 ) : (
                   <div className="space-y-3">
-                    <input
-                      className="w-full rounded border border-border bg-bg px-3 py-2 text-sm text-text placeholder:text-muted"
-                      placeholder="Find in files"
-                      value={searchQuery}
-                      onChange={(e) => setSearchQuery(e.currentTarget.value)}
-                      autoFocus
-                    />
+                    <div className="flex items-center gap-1">
+                      <input
+                        className="min-w-0 flex-1 rounded border border-border bg-bg px-3 py-2 text-sm text-text placeholder:text-muted"
+                        placeholder="Find in files"
+                        value={searchQuery}
+                        onChange={(e) => setSearchQuery(e.currentTarget.value)}
+                        autoFocus
+                      />
+                      <button
+                        type="button"
+                        className={`ws-icon-btn h-8 w-8 p-0 text-xs ${searchCaseSensitive ? "bg-panel text-text" : "text-muted"}`}
+                        aria-pressed={searchCaseSensitive}
+                        aria-label="Match Case"
+                        onClick={() => setSearchCaseSensitive((v) => !v)}
+                      >
+                        Aa
+                      </button>
+                      <button
+                        type="button"
+                        className={`ws-icon-btn h-8 w-8 p-0 text-xs underline ${searchWholeWord ? "bg-panel text-text" : "text-muted"}`}
+                        aria-pressed={searchWholeWord}
+                        aria-label="Match Whole Word"
+                        onClick={() => setSearchWholeWord((v) => !v)}
+                      >
+                        ab
+                      </button>
+                      <button
+                        type="button"
+                        className={`ws-icon-btn h-8 w-8 p-0 text-xs ${searchMultiline ? "bg-panel text-text" : "text-muted"}`}
+                        aria-pressed={searchMultiline}
+                        aria-label="Multiline Regular Expression"
+                        onClick={() => setSearchMultiline((v) => !v)}
+                      >
+                        .*
+                      </button>
+                    </div>
+
+                    {searchScope !== null ? (
+                      <div className="flex items-center gap-2 text-xs text-muted">
+                        <span className="min-w-0 truncate">In {searchScope}</span>
+                        <button type="button" className="shrink-0 hover:text-text" onClick={() => setSearchScope(null)}>
+                          Clear
+                        </button>
+                      </div>
+                    ) : null}
 
                     <div className="text-xs text-muted">
-                      {isSearching ? "Searching..." : `${searchResults.length} results`}
+                      {isSearching
+                        ? "Searching..."
+                        : `${searchResults.total_matches} results in ${searchResults.total_files} files${
+                            searchResults.truncated ? " (stopped at the limit)" : ""
+                          }`}
                     </div>
 
                     <div className="space-y-1">
-                      {searchResults.map((m, idx) => (
-                        <button
-                          key={`${m.path}:${m.line}:${idx}`}
-                          type="button"
-                          className="w-full rounded border border-border bg-bg px-3 py-2 text-left text-sm text-muted hover:border-accent hover:text-text"
-                          onClick={async () => {
-                            setPendingReveal({ path: m.path, line: m.line });
-                            await openFile(m.path);
-                          }}
-                        >
-                          <div className="flex items-center justify-between gap-2">
-                            <span className="truncate text-text">{m.path}</span>
-                            <span className="shrink-0 text-xs text-muted">{m.line}</span>
+                      {searchResults.files.map((f) => {
+                        const collapsed = collapsedSearchFiles.has(f.path);
+                        return (
+                          <div key={f.path}>
+                            <button
+                              type="button"
+                              className="flex w-full items-center gap-1 rounded px-1 py-1 text-left text-sm text-text hover:bg-panel"
+                              onClick={() =>
+                                setCollapsedSearchFiles((prev) => {
+                                  const next = new Set(prev);
+                                  if (next.has(f.path)) next.delete(f.path);
+                                  else next.add(f.path);
+                                  return next;
+                                })
+                              }
+                            >
+                              <ChevronRight className={`h-3.5 w-3.5 shrink-0 text-muted ${collapsed ? "" : "rotate-90"}`} />
+                              <span className="min-w-0 flex-1 truncate">{f.path}</span>
+                              <span className="shrink-0 text-xs text-muted">{f.total}</span>
+                            </button>
+                            {collapsed ? null : (
+                              <div className="space-y-1 pl-4">
+                                {f.matches.map((m, idx) => {
+                                  // Hits spanning lines are shown on their first line, highlighted to its end.
+                                  const first = m.text.split("\n")[0];
+                                  const hitEnd = m.end_line > m.line ? first.length : m.column - 1 + m.match_len;
+                                  return (
+                                    <button
+                                      key={`${m.line}:${idx}`}
+                                      type="button"
+                                      className="flex w-full items-center gap-2 rounded border border-border bg-bg px-3 py-1.5 text-left text-xs text-muted hover:border-accent hover:text-text"
+                                      onClick={async () => {
+                                        setPendingReveal({
+                                          path: m.path,
+                                          line: m.line,
+                                          column: m.column,
+                                          endLine: m.end_line,
+                                          endColumn: m.end_column,
+                                        });
+                                        await openFile(m.path);
+                                      }}
+                                    >
+                                      <span className="shrink-0">
+                                        {m.end_line > m.line ? `${m.line}-${m.end_line}` : m.line}
+                                      </span>
+                                      <span className="min-w-0 truncate">
+                                        {first.slice(0, m.column - 1)}
+                                        <span className="rounded-sm bg-accent/25 text-text">{first.slice(m.column - 1, hitEnd)}</span>
+                                        {first.slice(hitEnd)}
+                                      </span>
+                                    </button>
+                                  );
+                                })}
+                                {f.total > f.matches.length ? (
+                                  <div className="px-3 text-xs text-muted">{f.total - f.matches.length} more in this file</div>
+                                ) : null}
+                              </div>
+                            )}
                           </div>
-                          <div className="mt-1 truncate text-xs text-muted">{m.text}</div>
-                        </button>
-                      ))}
+                        );
+                      })}
                     </div>
                   </div>
                 )}
@@ -5932,6 +7547,7 @@ export default function AppShell() {
                     recentFiles={recentFiles}
                     onOpenFolder={() => void openFolder()}
                     onOpenFile={() => void openStandaloneFile()}
+                    onCloneRepository={() => void cloneRepository()}
                     onOpenRecentWorkspace={(p) => void openRecent(p)}
                     onOpenRecentFile={(p) => void openRecentFile(p)}
                     onOpenChat={() => setIsChatDockOpen(true)}
@@ -5972,6 +7588,54 @@ export default function AppShell() {
                       recentWorkspaces={workspace.recent}
                       onChangeTheme={(t: Theme) => setSettingsState((s) => ({ ...s, theme: t }))}
                       onToggleOffline={toggleOfflineMode}
+                      onChangeCommandSandbox={(mode) =>
+                        setSettingsState((s) => ({
+                          ...s,
+                          ai_command_sandbox: {
+                            ...(s.ai_command_sandbox ?? { timeout_secs: 120, memory_mb: 4096, allow_network: false }),
+                            mode,
+                          },
+                        }))
+                      }
+                      onToggleTelemetry={() =>
+                        setSettingsState((s) => ({
+                          ...s,
+                          telemetry: { endpoint: null, ...s.telemetry, enabled: !s.telemetry?.enabled },
+                        }))
+                      }
+                      onChangeLogLevel={(level) => setSettingsState((s) => ({ ...s, log_level: level }))}
+                      onToggleLintOnSave={() =>
+                        setSettingsState((s) => ({ ...s, lint: { disabled: [], ...s.lint, on_save: !s.lint?.on_save } }))
+                      }
+                      onToggleUpdateCheck={() =>
+                        setSettingsState((s) => ({
+                          ...s,
+                          updates: { feed: null, prereleases: false, ...s.updates, check_automatically: !s.updates?.check_automatically },
+                        }))
+                      }
+                      onCheckForUpdates={() => void checkForUpdates()}
+                      onChangeTodoTags={(tags) => setSettingsState((s) => ({ ...s, todo_tags: tags }))}
+                      onChangeProtectedPaths={(globs) => setSettingsState((s) => ({ ...s, protected_paths: globs }))}
+                      onToggleNotifications={() =>
+                        setSettingsState((s) => ({
+                          ...s,
+                          notifications: { disabled: [], ...s.notifications, enabled: !(s.notifications?.enabled ?? true) },
+                        }))
+                      }
+                      onToggleNotificationCategory={(category) =>
+                        setSettingsState((s) => {
+                          const disabled = s.notifications?.disabled ?? [];
+                          return {
+                            ...s,
+                            notifications: {
+                              enabled: true,
+                              ...s.notifications,
+                              disabled: disabled.includes(category) ? disabled.filter((c) => c !== category) : [...disabled, category],
+                            },
+                          };
+                        })
+                      }
+                      onChangeBackendUrl={(url) => setSettingsState((s) => ({ ...s, backend_base_url: url.trim() ? url : null }))}
                       onChangeProvider={(p) => void changeProvider(p)}
                       onChangePomporaThinking={(t) => void setPomporaThinking(t)}
                       onPickFolder={() => void openFolder()}
@@ -6031,6 +7695,7 @@ export default function AppShell() {
                           value={activeTab.content}
                           onChange={(v) => {
                             const next = v ?? "";
+                            lspDocumentChanged(activeTab.path, next);
                             setTabs((prev) =>
                               prev.map((t) =>
                                 t.path === activeTab.path
@@ -6039,8 +7704,34 @@ export default function AppShell() {
                               )
                             );
                           }}
-                          onMount={(ed) => {
+                          onMount={(ed, monaco) => {
                             editorRef.current = ed;
+                            monacoRef.current = monaco;
+                            lspProvidersRef.current ??= registerLspProviders(
+                              monaco,
+                              ["javascript", "python", "rust", "typescript"],
+                              () => lspActivePathRef.current,
+                              (path, line, column) => lspOpenLocationRef.current(path, line, column)
+                            );
+                            snippetProviderRef.current ??= monaco.languages.registerCompletionItemProvider("*", {
+                              async provideCompletionItems(model, pos) {
+                                const snippets = await snippetsList(model.getLanguageId()).catch(() => [] as Snippet[]);
+                                const word = model.getWordUntilPosition(pos);
+                                const range = new monaco.Range(pos.lineNumber, word.startColumn, pos.lineNumber, word.endColumn);
+                                return {
+                                  suggestions: snippets.map((s) => ({
+                                    label: s.prefix,
+                                    kind: monaco.languages.CompletionItemKind.Snippet,
+                                    detail: s.name,
+                                    documentation: s.description ?? undefined,
+                                    insertText: s.body,
+                                    insertTextRules: monaco.languages.CompletionItemInsertTextRule.InsertAsSnippet,
+                                    range,
+                                  })),
+                                };
+                              },
+                            });
+                            setEditorMountSeq((n) => n + 1);
                             cursorListenerDisposeRef.current?.dispose();
                             cursorListenerDisposeRef.current = ed.onDidChangeCursorPosition((ev) => {
                               const p = ev.position;
@@ -6051,10 +7742,13 @@ export default function AppShell() {
                             ed.focus();
                           }}
                           options={{
-                            fontSize: 13,
-                            minimap: { enabled: true },
+                            fontSize: settings.editor?.font_size ?? 13,
+                            ...(settings.editor?.font_family ? { fontFamily: settings.editor.font_family } : {}),
+                            tabSize: settings.editor?.tab_size ?? 4,
+                            insertSpaces: settings.editor?.insert_spaces ?? true,
+                            minimap: { enabled: settings.editor?.minimap ?? true },
                             scrollBeyondLastLine: false,
-                            wordWrap: "on",
+                            wordWrap: settings.editor?.word_wrap ?? "on",
                             automaticLayout: true,
                             padding: { top: 8, bottom: 8 },
                           }}
@@ -6152,6 +7846,7 @@ export default function AppShell() {
                       <div className="flex min-w-0 items-center gap-1">
                         {([
                           { id: "problems", label: "Problems" },
+                          { id: "todos", label: "TODOs" },
                           { id: "output", label: "Output" },
                           { id: "debug", label: "Debug Console" },
                           { id: "terminal", label: "Terminal" },
@@ -6160,6 +7855,7 @@ export default function AppShell() {
                           <button
                             key={t.id}
                             type="button"
+                            title={t.id === "terminal" ? terminalTitle ?? undefined : undefined}
                             className={`rounded-md px-2 py-1 text-[11px] ${
                               panelTab === t.id ? "bg-bg text-text" : "text-muted hover:bg-bg hover:text-text"
                             }`}
@@ -6176,6 +7872,12 @@ export default function AppShell() {
                             }}
                           >
                             {t.label}
+                            {t.id === "problems" && problems.length > 0 ? ` (${problems.length})` : ""}
+                            {t.id === "todos" && todoList && todoList.todos.length > 0 ? ` (${todoList.todos.length})` : ""}
+                            {t.id === "ports" && listeningPorts.length > 0 ? ` (${listeningPorts.length})` : ""}
+                            {t.id === "terminal" && terminalActive ? (
+                              <CircleDashed className="ml-1 inline h-3 w-3 animate-spin align-[-2px]" />
+                            ) : null}
                           </button>
                         ))}
                       </div>
@@ -6206,7 +7908,119 @@ export default function AppShell() {
                       <div className={panelTab === "terminal" ? "absolute inset-0" : "absolute inset-0 hidden"}>
                         <div ref={termHostRef} className="h-full w-full" />
                       </div>
-                      {panelTab !== "terminal" ? (
+                      {panelTab === "output" ? (
+                        <div className="absolute inset-0 overflow-auto p-2 font-mono text-[11px] leading-4">
+                          {taskOutput.length === 0 ? (
+                            <div className="text-muted">Run a task from the command palette (Tasks: Run ...) to see its output here.</div>
+                          ) : (
+                            taskOutput.map((l) => (
+                              <div
+                                key={l.id}
+                                className={`whitespace-pre-wrap break-all ${
+                                  l.kind === "stderr" ? "text-red-300" : l.kind === "info" ? "text-muted" : "text-text"
+                                }`}
+                              >
+                                {l.text || "\u00a0"}
+                              </div>
+                            ))
+                          )}
+                          <div ref={taskOutputEndRef} />
+                        </div>
+                      ) : panelTab === "problems" ? (
+                        <div className="absolute inset-0 overflow-auto p-2 text-xs">
+                          {problems.length === 0 ? (
+                            <div className="text-muted">No problems have been found in task or terminal output.</div>
+                          ) : (
+                            problems.map((d, idx) => {
+                              // Files outside the workspace can't be opened from here.
+                              const external = /^([a-zA-Z]:)?[\\/]/.test(d.path);
+                              return (
+                                <button
+                                  key={`${d.path}:${d.line}:${idx}`}
+                                  type="button"
+                                  disabled={external}
+                                  className="flex w-full items-center gap-2 rounded px-2 py-1 text-left hover:bg-bg disabled:cursor-default"
+                                  onClick={async () => {
+                                    setPendingReveal({ path: d.path, line: d.line, column: d.column ?? undefined });
+                                    await openFile(d.path);
+                                  }}
+                                >
+                                  <span
+                                    className={`shrink-0 ${
+                                      d.severity === "error" ? "text-red-300" : d.severity === "warning" ? "text-yellow-300" : "text-muted"
+                                    }`}
+                                  >
+                                    {d.severity}
+                                  </span>
+                                  <span className="min-w-0 flex-1 truncate text-text">
+                                    {d.message}
+                                    {d.code ? <span className="text-muted"> ({d.code})</span> : null}
+                                  </span>
+                                  <span className="shrink-0 truncate text-muted">
+                                    {d.path}:{d.line}
+                                    {d.column ? `:${d.column}` : ""}
+                                  </span>
+                                </button>
+                              );
+                            })
+                          )}
+                        </div>
+                      ) : panelTab === "todos" ? (
+                        <div className="absolute inset-0 overflow-auto p-2 text-xs">
+                          {!todoList ? (
+                            <div className="text-muted">{workspace.root ? "Searching…" : "Open a folder to list its TODO comments."}</div>
+                          ) : todoList.todos.length === 0 ? (
+                            <div className="text-muted">No TODO comments have been found in the workspace.</div>
+                          ) : (
+                            <>
+                              {todoList.truncated ? (
+                                <div className="px-2 py-1 text-muted">Only the first {todoList.todos.length} are listed.</div>
+                              ) : null}
+                              {todoList.todos.map((t) => (
+                                <button
+                                  key={`${t.path}:${t.line}`}
+                                  type="button"
+                                  className="flex w-full items-center gap-2 rounded px-2 py-1 text-left hover:bg-bg"
+                                  onClick={async () => {
+                                    setPendingReveal({ path: t.path, line: t.line, column: t.column });
+                                    await openFile(t.path);
+                                  }}
+                                >
+                                  <span className={`shrink-0 ${t.tag === "FIXME" ? "text-red-300" : "text-yellow-300"}`}>{t.tag}</span>
+                                  <span className="min-w-0 flex-1 truncate text-text">{t.text}</span>
+                                  <span className="shrink-0 truncate text-muted">
+                                    {t.path}:{t.line}
+                                  </span>
+                                </button>
+                              ))}
+                            </>
+                          )}
+                        </div>
+                      ) : panelTab === "ports" ? (
+                        <div className="absolute inset-0 overflow-auto p-2 text-xs">
+                          {listeningPorts.length === 0 ? (
+                            <div className="text-muted">No ports are being listened on by processes started from terminals or tasks.</div>
+                          ) : (
+                            listeningPorts.map((p) => (
+                              <div key={`${p.pid}:${p.port}`} className="flex w-full items-center gap-2 rounded px-2 py-1 hover:bg-bg">
+                                <span className="w-14 shrink-0 text-text">{p.port}</span>
+                                <span className="w-28 shrink-0 truncate text-muted">{p.address}</span>
+                                <span className="min-w-0 flex-1 truncate text-text" title={p.command}>
+                                  {p.command || `pid ${p.pid}`}
+                                </span>
+                                <span className="shrink-0 text-muted">{p.source === "task" ? "Task" : "Terminal"}</span>
+                                <button
+                                  type="button"
+                                  className="shrink-0 rounded px-2 py-0.5 text-muted hover:bg-panel hover:text-text"
+                                  onClick={() => void openUrl(p.url)}
+                                >
+                                  Open in Browser
+                                </button>
+                              </div>
+                            ))
+                          )}
+                        </div>
+                      ) : panelTab !== "terminal" ? (
                         <div className="absolute inset-0 p-3 text-xs text-muted">{panelTab} is coming next.</div>
                       ) : null}
                     </div>
@@ -6731,8 +8545,22 @@ export default function AppShell() {
                                       <div className="min-w-0">
                                         <div className="text-[11px] text-muted">Run</div>
                                         <div className="mt-1 font-mono text-[12px] text-text">
-                                          <span className="rounded border border-border bg-bg px-2 py-1">{m.run.cmd}</span>
+                                          <span className="rounded border border-border bg-bg px-2 py-1">
+                                            <HighlightedCommand cmd={m.run.cmd} findings={m.run.safety?.findings ?? []} />
+                                          </span>
                                         </div>
+                                        {m.run.safety && m.run.safety.risk !== "none" ? (
+                                          <div className="mt-2 text-[11px] text-muted">
+                                            <span className={`rounded-sm px-1 ${COMMAND_RISK_CLASS[m.run.safety.risk]}`}>
+                                              {m.run.safety.risk} risk
+                                            </span>
+                                            {Array.from(new Set(m.run.safety.findings.map((f) => f.message))).map((msg) => (
+                                              <div key={msg} className="mt-0.5">
+                                                • {msg}
+                                              </div>
+                                            ))}
+                                          </div>
+                                        ) : null}
                                         {m.run.error ? (
                                           <div className="mt-2 rounded border border-red-500/30 bg-bg px-2 py-1 text-[11px] text-muted">
                                             {m.run.error}
@@ -7063,7 +8891,7 @@ export default function AppShell() {
               </button>
             ) : null}
             {authProfile ? (
-              <button type="button" className="ws-footer-btn" onClick={() => void openUrl("https://pompora.dev/pricing")}>
+              <button type="button" className="ws-footer-btn" onClick={() => void openUrl(`${pomporaSiteUrl(settings)}/pricing`)}>
                 {pomporaPlan === "pro" ? "Pro" : pomporaPlan === "plus" ? "Plus" : "Starter"} plan
               </button>
             ) : (
@@ -7098,10 +8926,8 @@ export default function AppShell() {
         <QuickOpen
           query={quickOpenQuery}
           setQuery={setQuickOpenQuery}
-          files={fileIndex}
           index={quickOpenIndex}
           setIndex={setQuickOpenIndex}
-          isLoading={isFileIndexLoading}
           onClose={() => setIsQuickOpenOpen(false)}
           onPick={(p) => {
             void openFile(p);
@@ -7140,6 +8966,18 @@ export default function AppShell() {
                   { id: "delete", label: "Delete", onClick: () => void deleteSelected() },
                   { id: "copyPath", label: "Copy Relative Path", onClick: () => void copyText(explorerMenu.path) },
                 ]),
+            ...(explorerMenu.isDir
+              ? [
+                  {
+                    id: "findInFolder",
+                    label: "Find in Folder...",
+                    onClick: () => {
+                      setSearchScope(explorerMenu.path || null);
+                      setActivity("search");
+                    },
+                  },
+                ]
+              : []),
             {
               id: "copyFullPath",
               label: "Copy Full Path",
@@ -7180,40 +9018,64 @@ export default function AppShell() {
   );
 }
 
-function isSubsequence(needle: string, haystack: string): boolean {
-  let i = 0;
-  for (let j = 0; j < haystack.length && i < needle.length; j++) {
-    if (haystack[j] === needle[i]) i++;
+// Wraps the characters at `positions` (ascending UTF-16 offsets) in highlight spans.
+function highlightPositions(text: string, positions: number[]): ReactNode {
+  if (!positions.length) return text;
+  const out: ReactNode[] = [];
+  let from = 0;
+  for (let i = 0; i < positions.length; ) {
+    const start = positions[i];
+    let end = start + 1;
+    while (++i < positions.length && positions[i] === end) end++;
+    // Don't split a surrogate pair.
+    if (/[\uD800-\uDBFF]/.test(text[end - 1] ?? "")) end++;
+    out.push(text.slice(from, start));
+    out.push(
+      <span key={start} className="text-accent">
+        {text.slice(start, end)}
+      </span>
+    );
+    from = end;
   }
-  return i === needle.length;
+  out.push(text.slice(from));
+  return out;
 }
 
 function QuickOpen(props: {
   query: string;
   setQuery: (v: string) => void;
-  files: string[];
   index: number;
   setIndex: (v: number) => void;
-  isLoading: boolean;
   onClose: () => void;
   onPick: (path: string) => void;
 }) {
-  const q = props.query.trim().toLowerCase();
-
-  const list = useMemo(() => {
-    if (!props.files.length) return [] as string[];
-    if (!q) return props.files.slice(0, 60);
-
-    const out: string[] = [];
-    for (const f of props.files) {
-      const lf = f.toLowerCase();
-      if (lf.includes(q) || isSubsequence(q, lf)) {
-        out.push(f);
-        if (out.length >= 120) break;
-      }
-    }
-    return out;
-  }, [props.files, q]);
+  const [matches, setMatches] = useState<FuzzyMatch[]>([]);
+  const [isLoading, setIsLoading] = useState(true);
+  const list = useMemo(() => matches.map((m) => m.path), [matches]);
+  const { setIndex } = props;
+
+  useEffect(() => {
+    let cancelled = false;
+    setIsLoading(true);
+    const t = window.setTimeout(() => {
+      workspaceFuzzyFind(props.query, 120)
+        .then((res) => {
+          if (cancelled) return;
+          setMatches(res);
+          setIndex(0);
+        })
+        .catch(() => {
+          if (!cancelled) setMatches([]);
+        })
+        .finally(() => {
+          if (!cancelled) setIsLoading(false);
+        });
+    }, 60);
+    return () => {
+      cancelled = true;
+      window.clearTimeout(t);
+    };
+  }, [props.query, setIndex]);
 
   useEffect(() => {
     const onKeyDown = (e: KeyboardEvent) => {
@@ -7250,22 +9112,22 @@ function QuickOpen(props: {
             onChange={(e) => props.setQuery(e.currentTarget.value)}
           />
           <div className="mt-2 text-xs text-muted">
-            {props.isLoading ? "Indexing files..." : `${props.files.length} files`}
+            {isLoading ? "Searching files..." : `${matches.length} matches`}
           </div>
         </div>
         <div className="max-h-[360px] overflow-auto p-2">
-          {list.length ? (
-            list.map((p, i) => (
+          {matches.length ? (
+            matches.map((m, i) => (
               <button
-                key={p}
+                key={m.path}
                 type="button"
                 className={`flex w-full items-center justify-between rounded px-3 py-2 text-left text-sm ${
                   i === props.index ? "bg-bg text-text" : "text-muted hover:bg-bg hover:text-text"
                 }`}
                 onMouseEnter={() => props.setIndex(i)}
-                onClick={() => props.onPick(p)}
+                onClick={() => props.onPick(m.path)}
               >
-                <span className="truncate">{p}</span>
+                <span className="truncate">{highlightPositions(m.path, m.positions)}</span>
               </button>
             ))
           ) : (
@@ -7409,6 +9271,9 @@ function Explorer(props: {
     path: "",
     name: basename(props.workspaceRoot.replace(/\\/g, "/").replace(/\/$/, "")),
     is_dir: true,
+    has_children: true,
+    child_count: null,
+    children: null,
   };
 
   return (
@@ -7510,7 +9375,9 @@ function Tree(props: {
                 }}
               >
                 <ChevronRight
-                  className={`h-4 w-4 shrink-0 text-muted transition-transform duration-150 group-hover:text-text ${isExpanded ? "rotate-90" : ""}`}
+                  className={`h-4 w-4 shrink-0 text-muted transition-transform duration-150 group-hover:text-text ${isExpanded ? "rotate-90" : ""} ${
+                    e.has_children === false ? "invisible" : ""
+                  }`}
                 />
                 {isRoot ? (
                   <FolderOpen className="h-4 w-4 shrink-0 text-muted transition-transform duration-150 group-hover:scale-[1.03] group-hover:text-text" />
@@ -7666,6 +9533,7 @@ function WelcomeScreen(props: {
   recentFiles?: string[];
   onOpenFolder: () => void;
   onOpenFile?: () => void;
+  onCloneRepository?: () => void;
   onOpenRecentWorkspace: (p: string) => void;
   onOpenRecentFile?: (p: string) => void;
   onOpenChat?: () => void;
@@ -7771,6 +9639,11 @@ function WelcomeScreen(props: {
                     <span className="truncate">Open Command Palette</span>
                     <span className="ws-kbd">Ctrl+Shift+P</span>
                   </button>
+                  {props.onCloneRepository ? (
+                    <button type="button" className="ws-welcome-row" onClick={() => props.onCloneRepository?.()}>
+                      <span className="truncate">Clone Git Repository</span>
+                    </button>
+                  ) : null}
                 </div>
               </section>
 
@@ -7910,6 +9783,17 @@ interface SettingsScreenProps {
   recentWorkspaces: string[];
   onChangeTheme: (t: Theme) => void;
   onToggleOffline: () => void;
+  onChangeCommandSandbox: (mode: SandboxSettings["mode"]) => void;
+  onChangeBackendUrl: (url: string) => void;
+  onChangeLogLevel: (level: LogLevel) => void;
+  onToggleTelemetry: () => void;
+  onToggleLintOnSave: () => void;
+  onToggleUpdateCheck: () => void;
+  onCheckForUpdates: () => void;
+  onToggleNotifications: () => void;
+  onToggleNotificationCategory: (category: NotificationCategory) => void;
+  onChangeTodoTags: (tags: string[]) => void;
+  onChangeProtectedPaths: (globs: string[]) => void;
   onChangeProvider: (p: string | null) => void;
   onChangePomporaThinking: (t: string | null) => void;
   onPickFolder: () => void;
@@ -7944,6 +9828,26 @@ const SettingsScreen: React.FC<SettingsScreenProps> = (props) => {
 
   const providerStatusLabel = props.keyStatus?.is_configured ? "Configured" : "Not configured";
 
+  const [telemetryJson, setTelemetryJson] = useState<string | null>(null);
+  const previewTelemetry = useCallback(async () => {
+    try {
+      setTelemetryJson(JSON.stringify(await telemetryPreview(), null, 2));
+    } catch (e) {
+      setTelemetryJson(String(e));
+    }
+  }, []);
+
+  const [backendTest, setBackendTest] = useState<string | null>(null);
+  const testBackend = useCallback(async () => {
+    setBackendTest("Testing…");
+    try {
+      const r = await authTestBackend(props.settings.backend_base_url ?? undefined);
+      setBackendTest(`${r.url} answered in ${r.latency_ms} ms (API ${r.status}, AI ${r.ai_status}).`);
+    } catch (e) {
+      setBackendTest(String(e));
+    }
+  }, [props.settings.backend_base_url]);
+
   type SectionId = (typeof sectionList)[number]["id"];
   const [activeSection, setActiveSection] = useState<SectionId>("workspace");
 
@@ -8069,6 +9973,135 @@ const SettingsScreen: React.FC<SettingsScreenProps> = (props) => {
           </button>
         ),
       },
+      {
+        id: "workspace.logLevel",
+        section: "workspace",
+        title: "Log Level",
+        description: "How much the app writes to its log. Use \"Help: Copy Recent Logs\" to attach it to a bug report.",
+        keywords: "log logging level debug diagnostics bug report",
+        renderControl: () => (
+          <Dropdown
+            value={props.settings.log_level ?? "info"}
+            options={[
+              { value: "error", label: "Errors" },
+              { value: "warn", label: "Warnings" },
+              { value: "info", label: "Info" },
+              { value: "debug", label: "Debug" },
+              { value: "trace", label: "Trace" },
+            ]}
+            onChange={(v) => props.onChangeLogLevel(v as LogLevel)}
+          />
+        ),
+      },
+      {
+        id: "workspace.telemetry",
+        section: "workspace",
+        title: "Usage Statistics",
+        description:
+          "Send anonymous counts of commands used, kinds of errors and timings, to help improve Pompora. Never files, prompts or account details.",
+        keywords: "telemetry usage statistics analytics privacy",
+        renderControl: () => (
+          <div className="flex flex-col items-end gap-1">
+            <div className="flex items-center gap-2">
+              <button type="button" className="ws-vscode-btn" onClick={() => void previewTelemetry()}>
+                Preview
+              </button>
+              <Switch checked={!!props.settings.telemetry?.enabled} onChange={() => props.onToggleTelemetry()} />
+            </div>
+            {telemetryJson ? (
+              <pre className="max-h-64 w-80 overflow-auto whitespace-pre-wrap text-left text-[11px] text-muted">{telemetryJson}</pre>
+            ) : null}
+          </div>
+        ),
+      },
+      {
+        id: "workspace.lintOnSave",
+        section: "workspace",
+        title: "Lint on Save",
+        description: "Run clippy, eslint or ruff on a file's project when it is saved. Problems show in the Problems panel.",
+        keywords: "lint linter clippy eslint ruff save diagnostics problems",
+        renderControl: () => <Switch checked={!!props.settings.lint?.on_save} onChange={() => props.onToggleLintOnSave()} />,
+      },
+      {
+        id: "workspace.notifications",
+        section: "workspace",
+        title: "Desktop Notifications",
+        description: "Notify when AI replies, clones, search indexing or tasks finish while Pompora is in the background.",
+        keywords: "notifications notify desktop background alert task clone index ai",
+        renderControl: () => {
+          const enabled = props.settings.notifications?.enabled ?? true;
+          const disabled = props.settings.notifications?.disabled ?? [];
+          const categories: { id: NotificationCategory; label: string }[] = [
+            { id: "ai", label: "AI replies" },
+            { id: "git", label: "Clones" },
+            { id: "indexing", label: "Search indexing" },
+            { id: "tasks", label: "Tasks" },
+          ];
+          return (
+            <div className="flex flex-col items-end gap-1">
+              <Switch checked={enabled} onChange={() => props.onToggleNotifications()} />
+              {enabled
+                ? categories.map((c) => (
+                    <label key={c.id} className="flex items-center gap-2 text-[11px] text-muted">
+                      {c.label}
+                      <Switch checked={!disabled.includes(c.id)} onChange={() => props.onToggleNotificationCategory(c.id)} />
+                    </label>
+                  ))
+                : null}
+            </div>
+          );
+        },
+      },
+      {
+        id: "workspace.todoTags",
+        section: "workspace",
+        title: "TODO Tags",
+        description: "Comment tags the TODOs panel lists, separated by commas. Letter case must match.",
+        keywords: "todo fixme hack xxx tags comments panel",
+        renderControl: () => (
+          <input
+            className="ws-vscode-input"
+            placeholder="TODO, FIXME, HACK, XXX"
+            value={(props.settings.todo_tags ?? ["TODO", "FIXME", "HACK", "XXX"]).join(", ")}
+            autoComplete="off"
+            spellCheck={false}
+            onChange={(e) => props.onChangeTodoTags(e.target.value.split(",").map((t) => t.trim()))}
+          />
+        ),
+      },
+      {
+        id: "workspace.protectedPaths",
+        section: "workspace",
+        title: "Protected Paths",
+        description:
+          "Globs, separated by commas and matched like .gitignore lines, of files that saves, deletes, renames and AI edits leave alone. You are asked before overriding; AI edits never override.",
+        keywords: "protected protect lock readonly read-only guard glob files ai edits",
+        renderControl: () => (
+          <input
+            className="ws-vscode-input"
+            placeholder="*.lock, .git/**"
+            value={(props.settings.protected_paths ?? ["*.lock", ".git/**"]).join(", ")}
+            autoComplete="off"
+            spellCheck={false}
+            onChange={(e) => props.onChangeProtectedPaths(e.target.value.split(",").map((g) => g.trim()))}
+          />
+        ),
+      },
+      {
+        id: "workspace.updates",
+        section: "workspace",
+        title: "Check for Updates",
+        description: "Look for a newer Pompora release every few hours and offer to open its download page.",
+        keywords: "update updates release version download upgrade",
+        renderControl: () => (
+          <div className="flex items-center gap-2">
+            <button type="button" className="ws-vscode-btn" onClick={() => props.onCheckForUpdates()}>
+              Check Now
+            </button>
+            <Switch checked={!!props.settings.updates?.check_automatically} onChange={() => props.onToggleUpdateCheck()} />
+          </div>
+        ),
+      },
       {
         id: "appearance.theme",
         section: "appearance",
@@ -8094,6 +10127,49 @@ const SettingsScreen: React.FC<SettingsScreenProps> = (props) => {
         keywords: "offline ai network",
         renderControl: () => <Switch checked={props.settings.offline_mode} onChange={() => props.onToggleOffline()} disabled={props.isTogglingOffline} />,
       },
+      {
+        id: "ai.commandSandbox",
+        section: "ai",
+        title: "AI: Sandbox Commands",
+        description: "Run commands the AI suggests without network access, in a temporary home folder and with time and memory limits.",
+        keywords: "sandbox run command network isolation security limits",
+        renderControl: () => (
+          <Dropdown
+            value={props.settings.ai_command_sandbox?.mode ?? "off"}
+            options={[
+              { value: "off", label: "Off" },
+              { value: "auto", label: "When run without asking" },
+              { value: "always", label: "Always" },
+            ]}
+            onChange={(v) => props.onChangeCommandSandbox(v as SandboxSettings["mode"])}
+          />
+        ),
+      },
+      {
+        id: "ai.backendUrl",
+        section: "ai",
+        title: "AI: Pompora Backend URL",
+        description: "Self-hosted Pompora backend for sign-in, credits and AI requests. Leave empty for pompora.dev.",
+        keywords: "pompora backend server url self-hosted on-prem",
+        renderControl: () => (
+          <div className="flex flex-col gap-1">
+            <div className="flex items-center gap-2">
+              <input
+                className="ws-vscode-input"
+                placeholder="https://pompora.dev"
+                value={props.settings.backend_base_url ?? ""}
+                autoComplete="off"
+                spellCheck={false}
+                onChange={(e) => props.onChangeBackendUrl(e.target.value)}
+              />
+              <button type="button" className="ws-vscode-btn" disabled={backendTest === "Testing…"} onClick={() => void testBackend()}>
+                Test
+              </button>
+            </div>
+            {backendTest ? <div className="text-[11px] text-muted">{backendTest}</div> : null}
+          </div>
+        ),
+      },
       ...(props.settings.active_provider && props.settings.active_provider !== "pompora"
         ? ([
             {
@@ -8134,7 +10210,7 @@ const SettingsScreen: React.FC<SettingsScreenProps> = (props) => {
         ),
       },
     ],
-    [props]
+    [backendTest, previewTelemetry, props, telemetryJson, testBackend]
   );
 
   const filteredItems = useMemo(() => {
@@ -8354,7 +10430,7 @@ const SettingsScreen: React.FC<SettingsScreenProps> = (props) => {
                         <button
                           type="button"
                           className="ws-vscode-btn ws-vscode-btn-primary w-full rounded-none"
-                          onClick={() => void openUrl("https://pompora.dev/pricing")}
+                          onClick={() => void openUrl(`${pomporaSiteUrl(props.settings)}/pricing`)}
                         >
                           Manage plan
                         </button>
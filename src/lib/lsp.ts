@@ -0,0 +1,306 @@
+import { listen } from "@tauri-apps/api/event";
+import type { Monaco } from "@monaco-editor/react";
+import type { languages as MonacoLanguages, Position, editor } from "monaco-editor";
+import { lspSend, lspStart, type LspExitedEvent, type LspMessageEvent, type LspSession } from "./tauri";
+
+// A minimal language client: the backend starts the servers and passes JSON-RPC through, this
+// side speaks the protocol and feeds Monaco. Diagnostics don't go through here; the backend
+// reports them as `diagnostics:report` like any other problem source.
+
+type RpcMessage = {
+  id?: number | string;
+  method?: string;
+  params?: unknown;
+  result?: unknown;
+  error?: { code: number; message: string };
+};
+
+type Range = { start: { line: number; character: number }; end: { line: number; character: number } };
+type Location = { uri: string; range: Range };
+type LocationLink = { targetUri: string; targetSelectionRange: Range };
+type MarkupContent = string | { kind?: string; language?: string; value: string };
+
+type CompletionItem = {
+  label: string;
+  kind?: number;
+  detail?: string;
+  documentation?: MarkupContent;
+  sortText?: string;
+  filterText?: string;
+  insertText?: string;
+  insertTextFormat?: number;
+  textEdit?: { newText: string; range?: Range; insert?: Range };
+};
+
+class LspClient {
+  private nextId = 1;
+  private pending = new Map<number, { resolve: (v: unknown) => void; reject: (e: Error) => void }>();
+  readonly ready: Promise<void>;
+
+  constructor(readonly session: LspSession) {
+    this.ready = this.request("initialize", {
+      processId: null,
+      rootUri: session.root_uri,
+      workspaceFolders: [{ uri: session.root_uri, name: session.root || session.root_uri.split("/").pop() }],
+      capabilities: {
+        textDocument: {
+          synchronization: { dynamicRegistration: false },
+          completion: { completionItem: { snippetSupport: true, documentationFormat: ["markdown", "plaintext"] } },
+          hover: { contentFormat: ["markdown", "plaintext"] },
+          definition: { linkSupport: true },
+          publishDiagnostics: {},
+        },
+        workspace: { workspaceFolders: true, configuration: true },
+      },
+    }).then(() => this.notify("initialized", {}));
+  }
+
+  request(method: string, params: unknown): Promise<unknown> {
+    const id = this.nextId++;
+    return new Promise((resolve, reject) => {
+      this.pending.set(id, { resolve, reject });
+      lspSend(this.session.session_id, { jsonrpc: "2.0", id, method, params }).catch((e) => {
+        this.pending.delete(id);
+        reject(e instanceof Error ? e : new Error(String(e)));
+      });
+    });
+  }
+
+  notify(method: string, params: unknown) {
+    void lspSend(this.session.session_id, { jsonrpc: "2.0", method, params }).catch(() => {});
+  }
+
+  handle(msg: RpcMessage) {
+    if (msg.method && msg.id !== undefined) {
+      // Requests from the server: settings aren't passed on, everything else is acknowledged.
+      const result =
+        msg.method === "workspace/configuration"
+          ? ((msg.params as { items?: unknown[] })?.items ?? []).map(() => null)
+          : null;
+      void lspSend(this.session.session_id, { jsonrpc: "2.0", id: msg.id, result }).catch(() => {});
+      return;
+    }
+    if (typeof msg.id !== "number") return;
+    const p = this.pending.get(msg.id);
+    if (!p) return;
+    this.pending.delete(msg.id);
+    if (msg.error) p.reject(new Error(msg.error.message));
+    else p.resolve(msg.result);
+  }
+
+  dispose() {
+    for (const p of this.pending.values()) p.reject(new Error("language server exited"));
+    this.pending.clear();
+  }
+
+  uriFor(path: string): string {
+    const root = this.session.root;
+    const rel = root ? path.slice(root.length + 1) : path;
+    return `${this.session.root_uri.replace(/\/$/, "")}/${rel.split("/").map(encodeURIComponent).join("/")}`;
+  }
+
+  pathFor(uri: string): string | null {
+    const base = `${this.session.root_uri.replace(/\/$/, "")}/`;
+    if (!uri.startsWith(base)) return null;
+    const rel = uri.slice(base.length).split("/").map(decodeURIComponent).join("/");
+    return this.session.root ? `${this.session.root}/${rel}` : rel;
+  }
+}
+
+const clients = new Map<string, LspClient>();
+// Open documents by workspace path.
+const documents = new Map<string, { client: LspClient; version: number; language: string }>();
+// Languages without a usable server, so starting isn't retried for every file.
+const unavailable = new Set<string>();
+// Called the first time a server takes a document of a language, so Monaco's own features for it
+// can step aside.
+let languageServed: ((language: string) => void) | null = null;
+const served = new Set<string>();
+
+let listening = false;
+
+function ensureListening() {
+  if (listening) return;
+  listening = true;
+  void listen<LspMessageEvent>("lsp:message", (ev) => {
+    clients.get(ev.payload.session_id)?.handle(ev.payload.message as RpcMessage);
+  });
+  void listen<LspExitedEvent>("lsp:exited", (ev) => {
+    const client = clients.get(ev.payload.session_id);
+    if (!client) return;
+    client.dispose();
+    clients.delete(ev.payload.session_id);
+    for (const [path, doc] of documents) if (doc.client === client) documents.delete(path);
+  });
+}
+
+async function clientFor(language: string, path: string): Promise<LspClient | null> {
+  if (unavailable.has(language)) return null;
+  ensureListening();
+  let session: LspSession;
+  try {
+    session = await lspStart(language, path);
+  } catch {
+    unavailable.add(language);
+    return null;
+  }
+  let client = clients.get(session.session_id);
+  if (!client) {
+    client = new LspClient(session);
+    clients.set(session.session_id, client);
+  }
+  await client.ready;
+  return client;
+}
+
+/** Tells the language server for `language`, if there is one, about a file opened in the editor. */
+export async function lspDocumentOpened(language: string, path: string, text: string) {
+  if (documents.has(path)) return;
+  const client = await clientFor(language, path).catch(() => null);
+  if (!client || documents.has(path)) return;
+  documents.set(path, { client, version: 1, language });
+  if (!served.has(language)) {
+    served.add(language);
+    languageServed?.(language);
+  }
+  client.notify("textDocument/didOpen", { textDocument: { uri: client.uriFor(path), languageId: language, version: 1, text } });
+}
+
+/** Sends the whole new text of an open file. */
+export function lspDocumentChanged(path: string, text: string) {
+  const doc = documents.get(path);
+  if (!doc) return;
+  doc.version += 1;
+  doc.client.notify("textDocument/didChange", {
+    textDocument: { uri: doc.client.uriFor(path), version: doc.version },
+    contentChanges: [{ text }],
+  });
+}
+
+/** Closes the documents of files that are no longer open in a tab. */
+export function lspSyncOpenDocuments(openPaths: string[]) {
+  const open = new Set(openPaths);
+  for (const [path, doc] of documents) {
+    if (open.has(path)) continue;
+    documents.delete(path);
+    doc.client.notify("textDocument/didClose", { textDocument: { uri: doc.client.uriFor(path) } });
+  }
+}
+
+function markdown(content: MarkupContent | MarkupContent[] | undefined): { value: string }[] {
+  if (!content) return [];
+  if (Array.isArray(content)) return content.flatMap((c) => markdown(c));
+  if (typeof content === "string") return [{ value: content }];
+  if (content.language) return [{ value: `\`\`\`${content.language}\n${content.value}\n\`\`\`` }];
+  return [{ value: content.value }];
+}
+
+/**
+ * Registers completion, hover and go-to-definition for `languages` in `monaco`, answered by the
+ * server of the file `activePath()` returns. Definitions in other files go to `openLocation`.
+ * Monaco's built-in TypeScript and JavaScript features are turned off once a server serves them.
+ */
+export function registerLspProviders(
+  monaco: Monaco,
+  languages: string[],
+  activePath: () => string | null,
+  openLocation: (path: string, line: number, column: number) => void
+) {
+  const position = (p: Position) => ({ line: p.lineNumber - 1, character: p.column - 1 });
+  const range = (r: Range) =>
+    new monaco.Range(r.start.line + 1, r.start.character + 1, r.end.line + 1, r.end.character + 1);
+  const active = () => {
+    const path = activePath();
+    const doc = path ? documents.get(path) : undefined;
+    return path && doc ? { path, doc } : null;
+  };
+  languageServed = (language) => {
+    const ts = monaco.languages.typescript;
+    const defaults = language === "typescript" ? ts.typescriptDefaults : language === "javascript" ? ts.javascriptDefaults : null;
+    defaults?.setModeConfiguration({ ...defaults.modeConfiguration, completionItems: false, hovers: false, definitions: false, diagnostics: false });
+  };
+  for (const language of served) languageServed(language);
+  const kinds = monaco.languages.CompletionItemKind;
+  // LSP's CompletionItemKind, 1-based, in Monaco's terms.
+  const kindMap = [
+    kinds.Text, kinds.Method, kinds.Function, kinds.Constructor, kinds.Field, kinds.Variable, kinds.Class,
+    kinds.Interface, kinds.Module, kinds.Property, kinds.Unit, kinds.Value, kinds.Enum, kinds.Keyword,
+    kinds.Snippet, kinds.Color, kinds.File, kinds.Reference, kinds.Folder, kinds.EnumMember, kinds.Constant,
+    kinds.Struct, kinds.Event, kinds.Operator, kinds.TypeParameter,
+  ];
+
+  const disposables = [
+    monaco.editor.registerEditorOpener({
+      openCodeEditor(_source: editor.ICodeEditor, resource, selection) {
+        const client = [...clients.values()].find((c) => c.pathFor(resource.toString()) !== null);
+        const path = client?.pathFor(resource.toString());
+        if (!path) return false;
+        const at = selection && "lineNumber" in selection ? selection : { lineNumber: selection?.startLineNumber ?? 1, column: selection?.startColumn ?? 1 };
+        openLocation(path, at.lineNumber, at.column);
+        return true;
+      },
+    }),
+  ];
+
+  for (const language of languages) {
+    disposables.push(
+      monaco.languages.registerCompletionItemProvider(language, {
+        triggerCharacters: [".", ":", "<", '"', "/", "@"],
+        async provideCompletionItems(model, pos) {
+          const a = active();
+          if (!a) return { suggestions: [] };
+          const res = (await a.doc.client
+            .request("textDocument/completion", { textDocument: { uri: a.doc.client.uriFor(a.path) }, position: position(pos) })
+            .catch(() => null)) as CompletionItem[] | { isIncomplete?: boolean; items: CompletionItem[] } | null;
+          const items = Array.isArray(res) ? res : res?.items ?? [];
+          const word = model.getWordUntilPosition(pos);
+          const fallback = new monaco.Range(pos.lineNumber, word.startColumn, pos.lineNumber, word.endColumn);
+          const suggestions: MonacoLanguages.CompletionItem[] = items.map((item) => {
+            const edit = item.textEdit?.range ?? item.textEdit?.insert;
+            return {
+              label: item.label,
+              kind: kindMap[(item.kind ?? 1) - 1] ?? kinds.Text,
+              detail: item.detail,
+              documentation: markdown(item.documentation)[0],
+              sortText: item.sortText,
+              filterText: item.filterText,
+              insertText: item.textEdit?.newText ?? item.insertText ?? item.label,
+              insertTextRules: item.insertTextFormat === 2 ? monaco.languages.CompletionItemInsertTextRule.InsertAsSnippet : undefined,
+              range: edit ? range(edit) : fallback,
+            };
+          });
+          return { suggestions, incomplete: !Array.isArray(res) && !!res?.isIncomplete };
+        },
+      }),
+      monaco.languages.registerHoverProvider(language, {
+        async provideHover(_model, pos) {
+          const a = active();
+          if (!a) return null;
+          const res = (await a.doc.client
+            .request("textDocument/hover", { textDocument: { uri: a.doc.client.uriFor(a.path) }, position: position(pos) })
+            .catch(() => null)) as { contents: MarkupContent | MarkupContent[]; range?: Range } | null;
+          if (!res) return null;
+          return { contents: markdown(res.contents), range: res.range ? range(res.range) : undefined };
+        },
+      }),
+      monaco.languages.registerDefinitionProvider(language, {
+        async provideDefinition(model, pos) {
+          const a = active();
+          if (!a) return null;
+          const res = (await a.doc.client
+            .request("textDocument/definition", { textDocument: { uri: a.doc.client.uriFor(a.path) }, position: position(pos) })
+            .catch(() => null)) as Location | Location[] | LocationLink[] | null;
+          const found = (Array.isArray(res) ? res : res ? [res] : []).map((l) =>
+            "targetUri" in l ? { uri: l.targetUri, range: l.targetSelectionRange } : l
+          );
+          // The editor's model stands in for the active file; other files open through the opener.
+          return found.map((l) => ({
+            uri: a.doc.client.pathFor(l.uri) === a.path ? model.uri : monaco.Uri.parse(l.uri),
+            range: range(l.range),
+          }));
+        },
+      })
+    );
+  }
+  return { dispose: () => disposables.forEach((d) => d.dispose()) };
+}
@@ -1,5 +1,5 @@
 import { invoke } from "@tauri-apps/api/core";
-import type { AppSettings, AuthProfile, CreditsResponse, DirEntryInfo, KeyStatus, WorkspaceInfo } from "./types";
+import type { AiCommitMessage, AppSettings, AuthProfile, BinaryFile, CommitValidation, ConditionalWrite, CreditsResponse, CredentialInfo, DetectedProject, DirEntryInfo, FileHash, FileRange, FsHistoryEntry, FsOp, GitBranchInfo, GitCommitInfo, GitDiffHunk, GitFileDiff, GitPullResult, GitStashEntry, HttpAuth, HttpCollection, HttpHeader, KeyStatus, LanguageServerConfig, LogLevel, SecretBackend, SecretsLockStatus, SettingsProfiles, Snippet, VersionedText, WorkspaceFiles, WorkspaceInfo, WorkspaceKeyOverride, WriteResult } from "./types";
 
 export async function settingsGet(): Promise<AppSettings> {
   return invoke<AppSettings>("settings_get");
@@ -9,31 +9,63 @@ export async function settingsSet(next: AppSettings): Promise<void> {
   await invoke("settings_set", { next });
 }
 
-export async function providerKeyStatus(provider: string): Promise<KeyStatus> {
-  return invoke<KeyStatus>("provider_key_status", { provider });
+/** The saved settings profiles and which one is active. */
+export async function settingsProfileList(): Promise<SettingsProfiles> {
+  return invoke<SettingsProfiles>("settings_profile_list");
+}
+
+export async function settingsProfileSwitch(name: string): Promise<AppSettings> {
+  return invoke<AppSettings>("settings_profile_switch", { name });
+}
+
+export async function settingsProfileDelete(name: string): Promise<SettingsProfiles> {
+  return invoke<SettingsProfiles>("settings_profile_delete", { name });
+}
+
+/** Merges `patch` into the stored settings (JSON merge patch; `null` resets a field). */
+export async function settingsPatch(patch: Partial<AppSettings>): Promise<AppSettings> {
+  return invoke<AppSettings>("settings_patch", { patch });
+}
+
+export async function providerKeyStatus(provider: string, credential?: string): Promise<KeyStatus> {
+  return invoke<KeyStatus>("provider_key_status", { provider, credential: credential ?? null });
 }
 
 export async function providerKeySet(args: {
   provider: string;
   apiKey: string;
   encryptionPassword?: string;
+  credential?: string;
 }): Promise<void> {
   await invoke("provider_key_set", {
     provider: args.provider,
     apiKey: args.apiKey,
     encryptionPassword: args.encryptionPassword ?? null,
+    credential: args.credential ?? null,
   });
 }
 
+export async function providerKeyList(provider: string): Promise<CredentialInfo[]> {
+  return invoke<CredentialInfo[]>("provider_key_list", { provider });
+}
+
+export async function providerKeyActivate(provider: string, credential: string): Promise<void> {
+  await invoke("provider_key_activate", { provider, credential });
+}
+
+export type AiRequestPriority = "interactive" | "background";
+
 export async function aiChat(args: {
   messages: AiChatMessage[];
   encryptionPassword?: string;
   thinking?: string | null;
+  priority?: AiRequestPriority;
 }): Promise<AiChatResult> {
   return invoke<AiChatResult>("ai_chat", {
     messages: args.messages,
     encryptionPassword: args.encryptionPassword ?? null,
     thinking: args.thinking ?? null,
+    priority: args.priority ?? null,
   });
 }
 
@@ -42,15 +74,89 @@ export async function aiChatWithModel(args: {
   model?: string | null;
   encryptionPassword?: string;
   thinking?: string | null;
+  priority?: AiRequestPriority;
 }): Promise<AiChatResult> {
   return invoke<AiChatResult>("ai_chat_with_model", {
     messages: args.messages,
     model: args.model ?? null,
     encryptionPassword: args.encryptionPassword ?? null,
     thinking: args.thinking ?? null,
+    priority: args.priority ?? null,
   });
 }
 
+export type AiCompareEntry = {
+  model: string;
+  result: AiChatResult | null;
+  error: string | null;
+  elapsed_ms: number;
+};
+
+export async function aiChatCompare(args: {
+  messages: AiChatMessage[];
+  models: string[];
+  encryptionPassword?: string;
+  thinking?: string | null;
+}): Promise<AiCompareEntry[]> {
+  return invoke<AiCompareEntry[]>("ai_chat_compare", {
+    messages: args.messages,
+    models: args.models,
+    encryptionPassword: args.encryptionPassword ?? null,
+    thinking: args.thinking ?? null,
+  });
+}
+
+export async function aiExplainTerminal(args: {
+  id: string;
+  lastNLines?: number;
+  encryptionPassword?: string;
+  thinking?: string | null;
+}): Promise<AiChatResult> {
+  return invoke<AiChatResult>("ai_explain_terminal", {
+    id: args.id,
+    lastNLines: args.lastNLines ?? null,
+    encryptionPassword: args.encryptionPassword ?? null,
+    thinking: args.thinking ?? null,
+  });
+}
+
+export type SandboxResult = {
+  /** null when it was killed, e.g. for running out of time. */
+  exit_code: number | null;
+  timed_out: boolean;
+  /** stdout and stderr as they arrived; only the end when `truncated`. */
+  output: string;
+  truncated: boolean;
+  network_blocked: boolean;
+  duration_ms: number;
+};
+
+/** Runs an AI-suggested command in the workspace under the `ai_command_sandbox` limits and waits for it. */
+export async function aiSandboxRun(command: string): Promise<SandboxResult> {
+  return invoke<SandboxResult>("ai_sandbox_run", { command });
+}
+
+export type CommandRisk = "none" | "low" | "medium" | "high" | "critical";
+
+export type CommandFinding = {
+  rule: string;
+  risk: CommandRisk;
+  message: string;
+  /** The flagged part, as `command.slice(start, end)`. */
+  start: number;
+  end: number;
+};
+
+export type CommandAnalysis = {
+  risk: CommandRisk;
+  findings: CommandFinding[];
+};
+
+/** Statically checks a shell command for recursive deletes, piped-in scripts, fork bombs, credential leaks and the like. */
+export async function aiCommandAnalyze(command: string): Promise<CommandAnalysis> {
+  return invoke<CommandAnalysis>("ai_command_analyze", { command });
+}
+
 export type OpenRouterModelInfo = {
   id: string;
 };
@@ -59,8 +165,466 @@ export async function openrouterListModels(): Promise<OpenRouterModelInfo[]> {
   return invoke<OpenRouterModelInfo[]>("openrouter_list_models", {});
 }
 
-export async function providerKeyClear(provider: string): Promise<void> {
-  return invoke<void>("provider_key_clear", { provider });
+export async function providerKeyClear(provider: string, credential?: string): Promise<void> {
+  return invoke<void>("provider_key_clear", { provider, credential: credential ?? null });
+}
+
+export type SecretsMigrationEntry = {
+  provider: string;
+  slot: string;
+  from: KeyStatus["storage"];
+  error: string | null;
+};
+
+export async function secretsMigrate(args: {
+  backend: SecretBackend;
+  encryptionPassword?: string;
+}): Promise<SecretsMigrationEntry[]> {
+  return invoke<SecretsMigrationEntry[]>("secrets_migrate", {
+    backend: args.backend,
+    encryptionPassword: args.encryptionPassword ?? null,
+  });
+}
+
+export async function secretsExport(args: {
+  password: string;
+  dest?: string | null;
+  encryptionPassword?: string;
+}): Promise<string> {
+  return invoke<string>("secrets_export", {
+    password: args.password,
+    dest: args.dest ?? null,
+    encryptionPassword: args.encryptionPassword ?? null,
+  });
+}
+
+export type SecretsImportEntry = {
+  provider: string;
+  credential: string;
+  error: string | null;
+};
+
+export async function secretsImport(args: {
+  path: string;
+  password: string;
+  encryptionPassword?: string;
+}): Promise<SecretsImportEntry[]> {
+  return invoke<SecretsImportEntry[]>("secrets_import", {
+    path: args.path,
+    password: args.password,
+    encryptionPassword: args.encryptionPassword ?? null,
+  });
+}
+
+export async function secretsUnlock(password: string): Promise<SecretsLockStatus> {
+  return invoke<SecretsLockStatus>("secrets_unlock", { password });
+}
+
+export async function secretsLock(): Promise<SecretsLockStatus> {
+  return invoke<SecretsLockStatus>("secrets_lock");
+}
+
+export async function secretsLockStatus(): Promise<SecretsLockStatus> {
+  return invoke<SecretsLockStatus>("secrets_lock_status");
+}
+
+export async function workspaceKeyOverrideGet(root?: string | null): Promise<WorkspaceKeyOverride | null> {
+  return invoke<WorkspaceKeyOverride | null>("workspace_key_override_get", { root: root ?? null });
+}
+
+export async function workspaceKeyOverrideSet(args: {
+  root?: string | null;
+  provider?: string | null;
+  credential?: string | null;
+}): Promise<WorkspaceKeyOverride | null> {
+  return invoke<WorkspaceKeyOverride | null>("workspace_key_override_set", {
+    root: args.root ?? null,
+    provider: args.provider ?? null,
+    credential: args.credential ?? null,
+  });
+}
+
+export type PermissionIssue = {
+  path: string;
+  is_dir: boolean;
+  mode: string;
+  expected: string;
+};
+
+export async function secretsAudit(): Promise<PermissionIssue[]> {
+  return invoke<PermissionIssue[]>("secrets_audit");
+}
+
+export type KeyAuditEntry = {
+  ts_ms: number;
+  action: "read" | "write" | "clear";
+  provider: string;
+  credential: string;
+  caller: string;
+  ok: boolean;
+};
+
+export async function secretsAuditLog(limit?: number): Promise<KeyAuditEntry[]> {
+  return invoke<KeyAuditEntry[]>("secrets_audit_log", { limit: limit ?? null });
+}
+
+export type LogEntry = {
+  ts_ms: number;
+  level: LogLevel;
+  target: string;
+  message: string;
+};
+
+/** The last `lines` app log entries at `level` or more severe, oldest first. */
+export async function logsTail(lines?: number, level?: LogLevel): Promise<LogEntry[]> {
+  return invoke<LogEntry[]>("logs_tail", { lines: lines ?? null, level: level ?? null });
+}
+
+/** Anonymous usage statistics as they would be sent: counts and timings only. */
+export type TelemetryBatch = {
+  app_version: string;
+  os: string;
+  arch: string;
+  period_start: number;
+  period_end: number;
+  commands: Record<string, number>;
+  errors: Record<string, number>;
+  bucket_bounds_ms: number[];
+  latency: Record<string, { counts: number[]; total_ms: number }>;
+};
+
+export async function telemetryPreview(): Promise<TelemetryBatch> {
+  return invoke<TelemetryBatch>("telemetry_preview");
+}
+
+export type ReleaseAsset = {
+  name: string;
+  url: string;
+  size: number | null;
+};
+
+export type Release = {
+  version: string;
+  /** Changelog, usually Markdown. */
+  notes: string;
+  url: string | null;
+  published_at: string | null;
+  prerelease: boolean;
+  assets: ReleaseAsset[];
+};
+
+/** Also the payload of `update:available`. `download` is the installer for this OS, if one was found. */
+export type UpdateCheck = {
+  current_version: string;
+  latest: Release | null;
+  available: boolean;
+  download: ReleaseAsset | null;
+};
+
+export async function updatesCheck(): Promise<UpdateCheck> {
+  return invoke<UpdateCheck>("updates_check");
+}
+
+export type Todo = {
+  path: string;
+  line: number;
+  /** 1-based, in UTF-16 code units. */
+  column: number;
+  tag: string;
+  text: string;
+};
+
+export type TodoList = {
+  todos: Todo[];
+  truncated: boolean;
+};
+
+/** Payload of `todos:changed`: `todos` replaces what was listed for `paths` and anything under them. */
+export type TodosChanged = {
+  paths: string[];
+  todos: Todo[];
+};
+
+export type ChatExportFormat = "markdown" | "json";
+
+export type TranscriptEdit = {
+  kind: "write" | "delete" | "rename";
+  path: string;
+  added?: number | null;
+  removed?: number | null;
+  applied: boolean;
+};
+
+/** A chat session as sent to `chat_export`; timestamps are ms since the epoch. */
+export type ChatTranscript = {
+  id: string;
+  title: string;
+  created_at: number;
+  updated_at: number;
+  provider?: string | null;
+  model?: string | null;
+  messages: Array<{ role: "user" | "assistant"; content: string; edits?: TranscriptEdit[] }>;
+};
+
+/** Returns the path written: a workspace path, or an absolute one when `dest` is. */
+export async function chatExport(transcript: ChatTranscript, format: ChatExportFormat, dest?: string): Promise<string> {
+  return invoke<string>("chat_export", { transcript, format, dest });
+}
+
+export type EnvEntry = {
+  key: string;
+  /** Masked; `envFileReveal` gets the value. Empty when the value is. */
+  value: string;
+  line: number;
+};
+
+export type EnvFile = {
+  path: string;
+  entries: EnvEntry[];
+};
+
+/** `.env`, `.env.*` and `*.env` files in the workspace, ignored ones included. */
+export async function envFilesList(): Promise<string[]> {
+  return invoke<string[]>("env_files_list");
+}
+
+export async function envFileRead(path: string): Promise<EnvFile> {
+  return invoke<EnvFile>("env_file_read", { path });
+}
+
+export async function envFileReveal(path: string, key: string): Promise<string> {
+  return invoke<string>("env_file_reveal", { path, key });
+}
+
+/** Sets `key`, or removes it when `value` is null. */
+export async function envFileSet(path: string, key: string, value: string | null): Promise<EnvFile> {
+  return invoke<EnvFile>("env_file_set", { path, key, value });
+}
+
+export type ListeningPort = {
+  port: number;
+  /** Bound address, e.g. `127.0.0.1`, `0.0.0.0` or `::`. */
+  address: string;
+  pid: number;
+  /** Command line, or the process name on Windows. */
+  command: string;
+  source: "terminal" | "task";
+  /** Terminal session id or task run id. */
+  source_id: string;
+  task_id: string | null;
+  url: string;
+};
+
+/** TCP ports listened on by processes started from terminals and tasks. */
+export async function portsList(): Promise<ListeningPort[]> {
+  return invoke<ListeningPort[]>("ports_list");
+}
+
+export type Dependency = {
+  name: string;
+  /** Registry name, when the manifest renames or aliases the dependency. */
+  package: string | null;
+  /** Requirement as written; empty when none is given. */
+  version: string;
+  kind: "normal" | "dev" | "build" | "peer" | "optional" | "indirect";
+  /** Set for dependencies that don't come from the registry. */
+  source: "path" | "git" | "url" | "workspace" | null;
+  line: number | null;
+  latest: string | null;
+  /** Whether `latest` is newer than the version the requirement names. */
+  outdated: boolean | null;
+};
+
+export type DependencyManifest = {
+  path: string;
+  ecosystem: "cargo" | "npm" | "pypi" | "go";
+  dependencies: Dependency[];
+  error: string | null;
+};
+
+export type DependencyList = {
+  manifests: DependencyManifest[];
+  errors: string[];
+};
+
+/** Dependencies declared in the workspace's manifests; with `checkUpdates`, also the newest release of each. */
+export async function dependenciesList(checkUpdates = false): Promise<DependencyList> {
+  return invoke<DependencyList>("dependencies_list", { checkUpdates });
+}
+
+export type Vulnerability = {
+  id: string;
+  aliases: string[];
+  package: string;
+  /** Locked or installed version, or the affected range when only that is known. */
+  version: string | null;
+  ecosystem: DependencyManifest["ecosystem"];
+  severity: "low" | "moderate" | "high" | "critical" | null;
+  summary: string;
+  /** Versions that fix it; empty when there is no fix. */
+  fixed: string[];
+  url: string | null;
+  manifest: string;
+  line: number | null;
+};
+
+export type AuditSource = {
+  manifest: string;
+  ecosystem: DependencyManifest["ecosystem"];
+  tool: "cargo-audit" | "npm-audit" | "pip-audit" | "osv" | null;
+  error: string | null;
+};
+
+export type AuditReport = {
+  vulnerabilities: Vulnerability[];
+  sources: AuditSource[];
+};
+
+/** Also reported to the Problems panel as the `audit:dependencies` diagnostics source. */
+export async function dependenciesAudit(): Promise<AuditReport> {
+  return invoke<AuditReport>("dependencies_audit");
+}
+
+export type GithubRepo = {
+  owner: string;
+  name: string;
+  url: string;
+};
+
+export type GithubAuthStatus = {
+  /** Where the `github` token is kept; a `git` token for github.com is used when this is `none`. */
+  storage: KeyStatus["storage"];
+  login: string | null;
+  error: string | null;
+};
+
+export type GithubPullRequest = {
+  number: number;
+  title: string;
+  state: "open" | "closed" | "merged";
+  draft: boolean;
+  author: string;
+  url: string;
+  head: string;
+  base: string;
+  body: string;
+  created_at: string;
+  updated_at: string;
+};
+
+export type GithubIssue = {
+  number: number;
+  title: string;
+  state: "open" | "closed";
+  author: string;
+  url: string;
+  labels: string[];
+  comments: number;
+  body: string;
+  created_at: string;
+  updated_at: string;
+};
+
+export type GithubComment = {
+  id: number;
+  url: string;
+  author: string;
+  created_at: string;
+};
+
+export type GithubDeviceLogin = {
+  user_code: string;
+  verification_uri: string;
+  device_code: string;
+  interval: number;
+  expires_in: number;
+};
+
+export type GithubListState = "open" | "closed" | "all";
+
+// GitHub is reached with the token saved by `githubLoginWait`, or one saved with
+// `providerKeySet({ provider: "github", ... })`; reading a public repository works without.
+export async function githubAuthStatus(encryptionPassword?: string): Promise<GithubAuthStatus> {
+  return invoke<GithubAuthStatus>("github_auth_status", { encryptionPassword: encryptionPassword ?? null });
+}
+
+/** Needs the `github_oauth_client_id` setting. */
+export async function githubLoginBegin(): Promise<GithubDeviceLogin> {
+  return invoke<GithubDeviceLogin>("github_login_begin");
+}
+
+/** Resolves with the account's login once the code from `githubLoginBegin` has been entered. */
+export async function githubLoginWait(login: GithubDeviceLogin, encryptionPassword?: string): Promise<string> {
+  return invoke<string>("github_login_wait", {
+    deviceCode: login.device_code,
+    interval: login.interval,
+    encryptionPassword: encryptionPassword ?? null,
+  });
+}
+
+/** The github.com repository the workspace's `origin` remote points at. */
+export async function githubRepo(root?: string): Promise<GithubRepo> {
+  return invoke<GithubRepo>("github_repo", { root: root ?? null });
+}
+
+export async function githubPullRequests(state?: GithubListState, root?: string, encryptionPassword?: string): Promise<GithubPullRequest[]> {
+  return invoke<GithubPullRequest[]>("github_pull_requests", {
+    state: state ?? null,
+    root: root ?? null,
+    encryptionPassword: encryptionPassword ?? null,
+  });
+}
+
+export async function githubIssues(state?: GithubListState, root?: string, encryptionPassword?: string): Promise<GithubIssue[]> {
+  return invoke<GithubIssue[]>("github_issues", { state: state ?? null, root: root ?? null, encryptionPassword: encryptionPassword ?? null });
+}
+
+export async function githubPullRequestDiff(number: number, root?: string, encryptionPassword?: string): Promise<string> {
+  return invoke<string>("github_pull_request_diff", { number, root: root ?? null, encryptionPassword: encryptionPassword ?? null });
+}
+
+/** Comments on a pull request or issue; `body` is Markdown. */
+export async function githubCommentPost(number: number, body: string, root?: string, encryptionPassword?: string): Promise<GithubComment> {
+  return invoke<GithubComment>("github_comment_post", {
+    number,
+    body,
+    root: root ?? null,
+    encryptionPassword: encryptionPassword ?? null,
+  });
+}
+
+export type Misspelling = {
+  word: string;
+  /** 1-based; columns count UTF-16 code units, like Monaco's, and `end_column` is just past the word. */
+  line: number;
+  column: number;
+  end_column: number;
+  suggestions: string[];
+};
+
+export type SpellcheckResult = {
+  path: string;
+  language: string;
+  /** The Hunspell `.dic` file used. */
+  dictionary: string;
+  /** Workspace path of the root's `.pompora/dictionary.txt`, whether or not it exists yet. */
+  custom_dictionary: string;
+  misspellings: Misspelling[];
+  truncated: boolean;
+};
+
+/** Misspellings in a file's comments and strings, or a Markdown or text file's prose; `content` stands in for an unsaved buffer. */
+export async function spellcheckFile(relPath: string, content?: string): Promise<SpellcheckResult> {
+  return invoke<SpellcheckResult>("spellcheck_file", { relPath, content: content ?? null });
+}
+
+/** Adds `word` to the dictionary of the root `relPath` is in and returns that dictionary's path. */
+export async function spellcheckAddWord(relPath: string, word: string): Promise<string> {
+  return invoke<string>("spellcheck_add_word", { relPath, word });
+}
+
+export async function workspaceTodos(tags?: string[]): Promise<TodoList> {
+  return invoke<TodoList>("workspace_todos", { tags });
 }
 
 export async function authBeginLogin(): Promise<[string, string]> {
@@ -71,6 +635,11 @@ export async function authWaitLogin(state: string): Promise<AuthProfile> {
   return invoke<AuthProfile>("auth_wait_login", { state });
 }
 
+/** Cancels the login started with `state`; its `authWaitLogin` rejects with "login canceled". */
+export async function authCancelLogin(state: string): Promise<void> {
+  return invoke<void>("auth_cancel_login", { state });
+}
+
 export async function authGetProfile(): Promise<AuthProfile | null> {
   return invoke<AuthProfile | null>("auth_get_profile");
 }
@@ -79,8 +648,40 @@ export async function authLogout(): Promise<void> {
   return invoke<void>("auth_logout");
 }
 
-export async function authGetCredits(): Promise<CreditsResponse> {
-  return invoke<CreditsResponse>("auth_get_credits");
+/** Payload of `auth:expired`: the key expires soon or has, and couldn't be refreshed. */
+export type AuthExpiredEvent = {
+  expires_at: number | null;
+  reason: string;
+};
+
+/** Trades the refresh token from the last sign-in for a new API key. */
+export async function authRefresh(): Promise<AuthProfile> {
+  return invoke<AuthProfile>("auth_refresh");
+}
+
+/** Payload of `credits:updated`, sent whenever credits have been fetched. */
+export type CreditsUpdatedEvent = {
+  credits: CreditsResponse;
+  fetched_at: number;
+};
+
+/** How a Pompora backend answered `authTestBackend`; any status means it's reachable. */
+export type BackendTest = {
+  url: string;
+  ai_url: string;
+  status: number;
+  ai_status: number;
+  latency_ms: number;
+};
+
+/** Checks that the backend at `url`, or the configured one, answers. */
+export async function authTestBackend(url?: string): Promise<BackendTest> {
+  return invoke<BackendTest>("auth_test_backend", { url });
+}
+
+/** The account's credits, from the cache while it's recent unless `refresh` is set. */
+export async function authGetCredits(refresh?: boolean): Promise<CreditsResponse> {
+  return invoke<CreditsResponse>("auth_get_credits", { refresh });
 }
 
 export async function debugGeminiEndToEnd(apiKey: string): Promise<string> {
@@ -107,56 +708,648 @@ export async function workspaceSet(root: string | null): Promise<WorkspaceInfo>
   return invoke<WorkspaceInfo>("workspace_set", { root });
 }
 
-export async function workspaceListDir(relDir?: string): Promise<DirEntryInfo[]> {
+export async function workspaceAddRoot(path: string): Promise<WorkspaceInfo> {
+  return invoke<WorkspaceInfo>("workspace_add_root", { path });
+}
+
+export async function workspaceRemoveRoot(root: string): Promise<WorkspaceInfo> {
+  return invoke<WorkspaceInfo>("workspace_remove_root", { root });
+}
+
+export async function workspaceTrustGet(): Promise<boolean | null> {
+  return invoke<boolean | null>("workspace_trust_get");
+}
+
+export async function workspaceTrustSet(trusted: boolean): Promise<WorkspaceInfo> {
+  return invoke<WorkspaceInfo>("workspace_trust_set", { trusted });
+}
+
+export async function recentFilesGet(): Promise<WorkspaceFiles> {
+  return invoke<WorkspaceFiles>("recent_files_get");
+}
+
+export async function recentFileAdd(path: string): Promise<WorkspaceFiles> {
+  return invoke<WorkspaceFiles>("recent_file_add", { path });
+}
+
+export async function filePin(path: string): Promise<WorkspaceFiles> {
+  return invoke<WorkspaceFiles>("file_pin", { path });
+}
+
+export async function fileUnpin(path: string): Promise<WorkspaceFiles> {
+  return invoke<WorkspaceFiles>("file_unpin", { path });
+}
+
+export async function workspaceDetectProjects(): Promise<DetectedProject[]> {
+  return invoke<DetectedProject[]>("workspace_detect_projects");
+}
+
+/** Zips the workspace (honoring .gitignore) to `dest`, a file path or a folder; returns the archive path. */
+export async function workspaceExportZip(dest: string, includeGlobs?: string[], excludeGlobs?: string[]): Promise<string> {
+  return invoke<string>("workspace_export_zip", {
+    dest,
+    includeGlobs: includeGlobs ?? null,
+    excludeGlobs: excludeGlobs ?? null,
+  });
+}
+
+/** A task from `.pompora/tasks.json`, or a detected `package.json` script or `cargo` command. */
+export type TaskInfo = {
+  id: string;
+  label: string;
+  source: "workspace" | "npm" | "cargo";
+  command: string;
+  /** Workspace path of the folder it runs in. */
+  cwd: string;
+  group: string | null;
+  /** Run id while the task is running. */
+  running: string | null;
+};
+
+/** Payload of `task:started`. */
+export type TaskStartedEvent = {
+  run_id: string;
+  task_id: string;
+  label: string;
+  command: string;
+};
+
+/** Payload of `task:output`: one line, without its line ending. */
+export type TaskOutputEvent = {
+  run_id: string;
+  task_id: string;
+  stream: "stdout" | "stderr";
+  line: string;
+};
+
+/** Payload of `task:finished`. */
+export type TaskFinishedEvent = {
+  run_id: string;
+  task_id: string;
+  /** Null when a signal ended the process or its status couldn't be read. */
+  exit_code: number | null;
+  success: boolean;
+  stopped: boolean;
+  duration_ms: number;
+};
+
+/** A problem recognized in task or terminal output (rustc, tsc, eslint or pytest), or published by a language server. */
+export type Diagnostic = {
+  /** Workspace path, or an absolute path for files outside the workspace. */
+  path: string;
+  line: number;
+  column: number | null;
+  severity: "error" | "warning" | "info";
+  message: string;
+  code: string | null;
+  matcher: "rustc" | "tsc" | "eslint" | "pytest" | "lsp" | "clippy" | "ruff";
+};
+
+/** Payload of `diagnostics:report`: replaces everything previously reported for `source`. */
+export type DiagnosticsReport = {
+  /** `task:<task id>`, `terminal:<session id>`, `lsp:<session id>` or `lint:<linter>`. */
+  source: string;
+  diagnostics: Diagnostic[];
+};
+
+export async function tasksList(): Promise<TaskInfo[]> {
+  return invoke<TaskInfo[]>("tasks_list");
+}
+
+/** Starts a task and returns the run id; output follows as `task:output` events. */
+export async function tasksRun(id: string): Promise<string> {
+  return invoke<string>("tasks_run", { id });
+}
+
+/** Asks the run and what it started to exit, killing them after a few seconds; `force` kills at once. */
+export async function tasksStop(runId: string, force?: boolean): Promise<void> {
+  return invoke<void>("tasks_stop", { runId, force });
+}
+
+export type Linter = "clippy" | "eslint" | "ruff";
+
+/** A linter started on a project (workspace path of its folder). */
+export type LintTarget = {
+  linter: Linter;
+  project: string;
+};
+
+export type LintFinishedEvent = {
+  linter: Linter;
+  project: string;
+  problems: number;
+  /** Why it couldn't run, e.g. because it isn't installed. */
+  error: string | null;
+  duration_ms: number;
+};
+
+/** Runs the linters that apply to the workspace, or to `paths` only. Problems arrive as `diagnostics:report`. */
+export async function lintRun(paths?: string[], linter?: Linter): Promise<LintTarget[]> {
+  return invoke<LintTarget[]>("lint_run", { paths, linter });
+}
+
+export type TextDiffOptions = {
+  /** `patience` keeps moved blocks readable; `myers` when not given. */
+  algorithm?: "myers" | "patience";
+  /** Unchanged lines around each change; 3 when not given. */
+  context?: number;
+  /** Treat lines that differ only in whitespace as equal. */
+  ignore_whitespace?: boolean;
+};
+
+/** Hunks shaped like `gitDiffFile`'s, plus line counts. */
+export type TextDiff = {
+  hunks: GitDiffHunk[];
+  added: number;
+  removed: number;
+};
+
+export async function diffTexts(oldText: string, newText: string, options?: TextDiffOptions): Promise<TextDiff> {
+  return invoke<TextDiff>("diff_texts", { old: oldText, new: newText, options });
+}
+
+/** Diff of two workspace files, `aRel` being the old side. */
+export async function diffFiles(aRel: string, bRel: string, options?: TextDiffOptions): Promise<TextDiff> {
+  return invoke<TextDiff>("diff_files", { aRel, bRel, options });
+}
+
+export type MergeOptions = {
+  /** After `<<<<<<<`; `ours` when not given. */
+  ours_label?: string;
+  /** After `>>>>>>>`; `theirs` when not given. */
+  theirs_label?: string;
+  /** `diff3` also writes the base lines between `|||||||` and `=======`. */
+  style?: "merge" | "diff3";
+};
+
+/** Where the two sides disagree; `start_line`/`end_line` are the marker lines in `merged`, 1-based. */
+export type MergeConflict = {
+  start_line: number;
+  end_line: number;
+  base_start: number;
+  base_lines: number;
+  base: string;
+  ours: string;
+  theirs: string;
+};
+
+export type MergeResult = {
+  merged: string;
+  conflicts: MergeConflict[];
+};
+
+/** Saved snippets, or those offered in `language` (where one for the language hides a global one with its prefix). */
+export async function snippetsList(language?: string): Promise<Snippet[]> {
+  return invoke<Snippet[]>("snippets_list", { language });
+}
+
+/** Saves a snippet over the one with its name, or over `previousName` when renaming. Returns all snippets. */
+export async function snippetsSave(snippet: Snippet, previousName?: string): Promise<Snippet[]> {
+  return invoke<Snippet[]>("snippets_save", { snippet, previousName });
+}
+
+export async function snippetsDelete(name: string): Promise<Snippet[]> {
+  return invoke<Snippet[]>("snippets_delete", { name });
+}
+
+/** Imports a VS Code snippets file (`path`) or its `content`; returns the imported snippets. */
+export async function snippetsImport(opts: { path?: string; content?: string; language?: string }): Promise<Snippet[]> {
+  return invoke<Snippet[]>("snippets_import", opts);
+}
+
+export type HttpResponse = {
+  status: number;
+  status_text: string;
+  http_version: string;
+  /** After redirects. */
+  url: string;
+  headers: HttpHeader[];
+  /** Text, or base64 when `base64` is set because the body isn't UTF-8. */
+  body: string;
+  base64: boolean;
+  size: number;
+  /** The body was longer than 5 MB and the rest wasn't read. */
+  truncated: boolean;
+  headers_ms: number;
+  total_ms: number;
+};
+
+export async function httpRequest(opts: {
+  method: string;
+  url: string;
+  headers?: HttpHeader[];
+  body?: string | null;
+  auth?: HttpAuth | null;
+  timeoutMs?: number;
+}): Promise<HttpResponse> {
+  return invoke<HttpResponse>("http_request", opts);
+}
+
+export async function httpCollectionsList(): Promise<HttpCollection[]> {
+  return invoke<HttpCollection[]>("http_collections_list");
+}
+
+export async function httpCollectionsSave(collection: HttpCollection, previousName?: string): Promise<HttpCollection[]> {
+  return invoke<HttpCollection[]>("http_collections_save", { collection, previousName });
+}
+
+export async function httpCollectionsDelete(name: string): Promise<HttpCollection[]> {
+  return invoke<HttpCollection[]>("http_collections_delete", { name });
+}
+
+/** Applies both `ours`' and `theirs`' changes to `base`, marking conflicts where they overlap. */
+export async function mergeThreeWay(base: string, ours: string, theirs: string, options?: MergeOptions): Promise<MergeResult> {
+  return invoke<MergeResult>("merge_three_way", { base, ours, theirs, options });
+}
+
+/** A declaration in a file's outline; `line`/`column` locate its name, both 1-based. */
+export type DocumentSymbol = {
+  name: string;
+  kind: "module" | "struct" | "enum" | "trait" | "impl" | "interface" | "class" | "function" | "method" | "type" | "constant";
+  line: number;
+  column: number;
+  end_line: number;
+  children: DocumentSymbol[];
+};
+
+/** Outline of the file at `path`, or of `content` (e.g. an unsaved buffer) when given. */
+export async function documentSymbols(opts: { path?: string; content?: string; language?: string }): Promise<DocumentSymbol[]> {
+  return invoke<DocumentSymbol[]>("document_symbols", opts);
+}
+
+/** A running language server; `root_uri` goes into its `initialize` request. */
+export type LspSession = {
+  session_id: string;
+  server_id: string;
+  languages: string[];
+  /** Workspace path of the root it serves; "" in a single-root workspace. */
+  root: string;
+  root_uri: string;
+};
+
+/** Payload of `lsp:message`: a JSON-RPC message from the server. */
+export type LspMessageEvent = {
+  session_id: string;
+  message: unknown;
+};
+
+export type LspExitedEvent = {
+  session_id: string;
+  server_id: string;
+  exit_code: number | null;
+  /** Whether `lspStop` ended it. */
+  stopped: boolean;
+  /** Why Pompora dropped the server, e.g. a message over the size limit. */
+  error: string | null;
+};
+
+/** Built-in language servers with the `language_servers` setting applied. */
+export async function lspServers(): Promise<LanguageServerConfig[]> {
+  return invoke<LanguageServerConfig[]>("lsp_servers");
+}
+
+export async function lspList(): Promise<LspSession[]> {
+  return invoke<LspSession[]>("lsp_list");
+}
+
+/** Starts the server for `language` in the root `path` is in, or returns the running one. */
+export async function lspStart(language: string, path?: string): Promise<LspSession> {
+  return invoke<LspSession>("lsp_start", { language, path });
+}
+
+export async function lspSend(sessionId: string, message: unknown): Promise<void> {
+  return invoke<void>("lsp_send", { sessionId, message });
+}
+
+export async function lspStop(sessionId: string): Promise<void> {
+  return invoke<void>("lsp_stop", { sessionId });
+}
+
+export async function gitStage(paths: string[]): Promise<void> {
+  return invoke<void>("git_stage", { paths });
+}
+
+export async function gitUnstage(paths: string[]): Promise<void> {
+  return invoke<void>("git_unstage", { paths });
+}
+
+/** `root` names the repository's workspace root; only needed when the workspace has several roots. */
+export async function gitCommit(message: string, amend?: boolean, root?: string): Promise<GitCommitInfo> {
+  return invoke<GitCommitInfo>("git_commit", { message, amend: amend ?? null, root: root ?? null });
+}
+
+/** Checks a message against Conventional Commits and the repository's JSON commitlint config, if any. */
+export async function gitCommitValidate(message: string, root?: string): Promise<CommitValidation> {
+  return invoke<CommitValidation>("git_commit_validate", { message, root: root ?? null });
+}
+
+/** A commit message for the staged changes that passes `gitCommitValidate` unless every attempt failed. */
+export async function aiCommitMessage(args?: { root?: string; encryptionPassword?: string; thinking?: string | null }): Promise<AiCommitMessage> {
+  return invoke<AiCommitMessage>("ai_commit_message", {
+    root: args?.root ?? null,
+    encryptionPassword: args?.encryptionPassword ?? null,
+    thinking: args?.thinking ?? null,
+  });
+}
+
+export async function gitBranchesList(root?: string): Promise<GitBranchInfo[]> {
+  return invoke<GitBranchInfo[]>("git_branches_list", { root: root ?? null });
+}
+
+export async function gitBranchCreate(name: string, start?: string, checkout?: boolean, root?: string): Promise<GitBranchInfo> {
+  return invoke<GitBranchInfo>("git_branch_create", { name, start: start ?? null, checkout: checkout ?? null, root: root ?? null });
+}
+
+/** Fails with a readable message when tracked files have uncommitted changes. */
+export async function gitCheckout(name: string, root?: string): Promise<void> {
+  return invoke<void>("git_checkout", { name, root: root ?? null });
+}
+
+export async function gitBranchDelete(name: string, force?: boolean, root?: string): Promise<void> {
+  return invoke<void>("git_branch_delete", { name, force: force ?? null, root: root ?? null });
+}
+
+/** `staged` diffs HEAD against the index, otherwise the index against the working tree. */
+export async function gitDiffFile(relPath: string, staged?: boolean, hunks?: boolean): Promise<GitFileDiff> {
+  return invoke<GitFileDiff>("git_diff_file", { relPath, staged: staged ?? null, hunks: hunks ?? null });
+}
+
+// Remote operations authenticate with the SSH agent (or ~/.ssh keys), then an HTTPS token saved
+// with `providerKeySet("git", token, password, credential)` where `credential` is the host with
+// dots turned into dashes (`github-com`), or the default credential for any host.
+export async function gitFetch(remote?: string, root?: string, encryptionPassword?: string): Promise<void> {
+  return invoke<void>("git_fetch", { remote: remote ?? null, root: root ?? null, encryptionPassword: encryptionPassword ?? null });
+}
+
+export async function gitPull(remote?: string, root?: string, encryptionPassword?: string): Promise<GitPullResult> {
+  return invoke<GitPullResult>("git_pull", { remote: remote ?? null, root: root ?? null, encryptionPassword: encryptionPassword ?? null });
+}
+
+export async function gitPush(remote?: string, force?: boolean, root?: string, encryptionPassword?: string): Promise<void> {
+  return invoke<void>("git_push", {
+    remote: remote ?? null,
+    force: force ?? null,
+    root: root ?? null,
+    encryptionPassword: encryptionPassword ?? null,
+  });
+}
+
+export async function gitStashSave(message?: string, includeUntracked?: boolean, root?: string): Promise<GitStashEntry> {
+  return invoke<GitStashEntry>("git_stash_save", {
+    message: message ?? null,
+    includeUntracked: includeUntracked ?? null,
+    root: root ?? null,
+  });
+}
+
+export async function gitStashList(root?: string): Promise<GitStashEntry[]> {
+  return invoke<GitStashEntry[]>("git_stash_list", { root: root ?? null });
+}
+
+export async function gitStashApply(index: number, root?: string): Promise<void> {
+  return invoke<void>("git_stash_apply", { index, root: root ?? null });
+}
+
+export async function gitStashPop(index: number, root?: string): Promise<void> {
+  return invoke<void>("git_stash_pop", { index, root: root ?? null });
+}
+
+export async function gitStashDrop(index: number, root?: string): Promise<void> {
+  return invoke<void>("git_stash_drop", { index, root: root ?? null });
+}
+
+/** Clones into `dest` (or a new subfolder of it when it isn't empty) and opens the clone. */
+export async function gitClone(url: string, dest: string, encryptionPassword?: string): Promise<WorkspaceInfo> {
+  return invoke<WorkspaceInfo>("git_clone", { url, dest, encryptionPassword: encryptionPassword ?? null });
+}
+
+/**
+ * `includeHidden` overrides the `show_hidden_files` setting for this call. With `depth` above 1
+ * (at most 8) folders come with their `children` filled in that many levels down.
+ */
+export async function workspaceListDir(relDir?: string, includeHidden?: boolean, depth?: number): Promise<DirEntryInfo[]> {
   return invoke<DirEntryInfo[]>("workspace_list_dir", {
     relDir: relDir ?? null,
+    includeHidden: includeHidden ?? null,
+    depth: depth ?? null,
   });
 }
 
-export async function workspaceListFiles(maxFiles?: number): Promise<string[]> {
+/** `includeIgnored` and `includeHidden` override the `include_ignored_files` and `show_hidden_files` settings for this call. */
+export async function workspaceListFiles(maxFiles?: number, includeIgnored?: boolean, includeHidden?: boolean): Promise<string[]> {
   return invoke<string[]>("workspace_list_files", {
     maxFiles: maxFiles ?? null,
+    includeIgnored: includeIgnored ?? null,
+    includeHidden: includeHidden ?? null,
+  });
+}
+
+/** Sends the file list as `workspace:files-batch` events tagged with `requestId`; resolves with the count once done. */
+export async function workspaceListFilesStream(
+  requestId: number,
+  maxFiles?: number,
+  batchSize?: number,
+  includeIgnored?: boolean,
+  includeHidden?: boolean
+): Promise<number> {
+  return invoke<number>("workspace_list_files_stream", {
+    requestId,
+    maxFiles: maxFiles ?? null,
+    batchSize: batchSize ?? null,
+    includeIgnored: includeIgnored ?? null,
+    includeHidden: includeHidden ?? null,
   });
 }
 
 export async function workspaceReadFile(relPath: string): Promise<string> {
-  return invoke<string>("workspace_read_file", { relPath });
+  return (await workspaceReadFileText(relPath)).content;
+}
+
+/** Like `workspaceReadFile`, plus the detected encoding to pass back to `workspaceWriteFile`. */
+export async function workspaceReadFileText(relPath: string): Promise<VersionedText> {
+  return invoke<VersionedText>("workspace_read_file", { relPath });
 }
 
-export async function workspaceWriteFile(relPath: string, contents: string): Promise<void> {
-  await invoke("workspace_write_file", { relPath, contents });
+/** Lines `startLine..=endLine` (1-based), for paging through files too large to open whole. */
+export async function workspaceReadFileRange(relPath: string, startLine: number, endLine: number): Promise<FileRange> {
+  return invoke<FileRange>("workspace_read_file_range", { relPath, startLine, endLine });
+}
+
+export async function workspaceReadFileBytes(relPath: string, offset: number, length: number): Promise<FileRange> {
+  return invoke<FileRange>("workspace_read_file_bytes", { relPath, offset, length });
+}
+
+export async function workspaceReadFileBinary(relPath: string): Promise<BinaryFile> {
+  return invoke<BinaryFile>("workspace_read_file_binary", { relPath });
+}
+
+/**
+ * Resolves with the file's new version token and the SHA-256 of what was written. Protected
+ * paths are refused unless `allowProtected`; see `isProtectedPathError`.
+ */
+export async function workspaceWriteFile(
+  relPath: string,
+  contents: string,
+  encoding?: string,
+  bom?: boolean,
+  allowProtected?: boolean
+): Promise<WriteResult> {
+  return invoke<WriteResult>("workspace_write_file", {
+    relPath,
+    contents,
+    encoding: encoding ?? null,
+    bom: bom ?? null,
+    allowProtected: allowProtected ?? null,
+  });
+}
+
+/** Whether an error is a file operation refused because of the `protected_paths` setting. */
+export function isProtectedPathError(e: unknown): boolean {
+  return String(e).includes("is protected by");
+}
+
+/** Hashes the file as stored on disk; `algo` defaults to `sha256`. */
+export async function workspaceHashFile(relPath: string, algo?: "sha256" | "blake3"): Promise<FileHash> {
+  return invoke<FileHash>("workspace_hash_file", { relPath, algo: algo ?? null });
+}
+
+export async function workspaceWriteFileIfUnchanged(
+  relPath: string,
+  contents: string,
+  expectedVersion: string,
+  encoding?: string,
+  bom?: boolean,
+  allowProtected?: boolean
+): Promise<ConditionalWrite> {
+  return invoke<ConditionalWrite>("workspace_write_file_if_unchanged", {
+    relPath,
+    contents,
+    expectedVersion,
+    encoding: encoding ?? null,
+    bom: bom ?? null,
+    allowProtected: allowProtected ?? null,
+  });
+}
+
+/**
+ * Applies the ops in order, all or nothing: a failing step undoes the ones before it.
+ * Deletions follow `permanent` like `workspaceDelete`; resolves with the number of steps.
+ */
+export async function workspaceBatchOps(ops: FsOp[], permanent?: boolean, allowProtected?: boolean): Promise<number> {
+  return invoke<number>("workspace_batch_ops", { ops, permanent: permanent ?? null, allowProtected: allowProtected ?? null });
+}
+
+/** Recent file operations, newest first. */
+export async function fsopsHistory(limit?: number): Promise<FsHistoryEntry[]> {
+  return invoke<FsHistoryEntry[]>("fsops_history", { limit: limit ?? null });
+}
+
+/** Reverts the newest file operation and returns it; rejects with the reason if it can't be undone. */
+export async function fsopsUndoLast(): Promise<FsHistoryEntry> {
+  return invoke<FsHistoryEntry>("fsops_undo_last");
 }
 
 export async function workspaceCreateDir(relPath: string): Promise<void> {
   await invoke("workspace_create_dir", { relPath });
 }
 
-export async function workspaceDelete(relPath: string): Promise<void> {
-  await invoke("workspace_delete", { relPath });
+export async function workspaceDelete(relPath: string, permanent?: boolean, allowProtected?: boolean): Promise<void> {
+  await invoke("workspace_delete", { relPath, permanent: permanent ?? null, allowProtected: allowProtected ?? null });
 }
 
-export async function workspaceRename(fromRel: string, toRel: string): Promise<void> {
-  await invoke("workspace_rename", { fromRel, toRel });
+export async function workspaceRename(fromRel: string, toRel: string, allowProtected?: boolean): Promise<void> {
+  await invoke("workspace_rename", { fromRel, toRel, allowProtected: allowProtected ?? null });
+}
+
+/** `positions` are the matched characters of `path` as UTF-16 offsets, for highlighting. */
+export type FuzzyMatch = {
+  path: string;
+  score: number;
+  positions: number[];
+};
+
+/** fzf-style ranking over the workspace's files, best first; an empty query lists files by path. */
+export async function workspaceFuzzyFind(query: string, maxResults?: number): Promise<FuzzyMatch[]> {
+  return invoke<FuzzyMatch[]>("workspace_fuzzy_find", { query, maxResults: maxResults ?? null });
 }
 
+/** `column` (1-based) and `match_len` count UTF-16 code units, so they index `text` and editor columns directly. */
 export type WorkspaceSearchMatch = {
   path: string;
   line: number;
+  column: number;
+  match_len: number;
+  /** Where the hit ends; past `line` only for hits that span lines. */
+  end_line: number;
+  end_column: number;
+  /** Every line the hit touches, joined with `\n`. */
   text: string;
+  before: string[];
+  after: string[];
+};
+
+export type FileSearchMatches = {
+  path: string;
+  matches: WorkspaceSearchMatch[];
+  /** Hits in the whole file, including any past `max_matches_per_file`. */
+  total: number;
 };
 
-export async function workspaceSearch(query: string, maxResults?: number): Promise<WorkspaceSearchMatch[]> {
-  return invoke<WorkspaceSearchMatch[]>("workspace_search", {
+export type WorkspaceSearchResults = {
+  files: FileSearchMatches[];
+  total_matches: number;
+  total_files: number;
+  /** The result limit was reached and the search stopped early. */
+  truncated: boolean;
+};
+
+/** Omitted options are off: matching ignores case and may fall inside longer words. */
+export type SearchOptions = {
+  case_sensitive?: boolean;
+  whole_word?: boolean;
+  /** Lines of context per match, at most 10. */
+  context_lines?: number;
+  /** Workspace folders and files to search instead of the whole workspace. */
+  paths?: string[];
+  /** Treat the query as a regular expression that can match across lines. */
+  multiline?: boolean;
+  /** Most hits returned per file; the rest are only counted. */
+  max_matches_per_file?: number;
+  /**
+   * `files` leaves out the hits (files carry counts; `maxResults` caps files), `count` leaves
+   * out the files too and counts over the whole workspace.
+   */
+  mode?: "matches" | "files" | "count";
+};
+
+export async function workspaceSearch(
+  query: string,
+  maxResults?: number,
+  includeIgnored?: boolean,
+  options?: SearchOptions
+): Promise<WorkspaceSearchResults> {
+  return invoke<WorkspaceSearchResults>("workspace_search", {
     query,
     maxResults: maxResults ?? null,
+    includeIgnored: includeIgnored ?? null,
+    options: options ?? null,
   });
 }
 
-export async function terminalStart(args: { cols: number; rows: number; cwd?: string | null }): Promise<string> {
+export async function terminalStart(args: {
+  cols: number;
+  rows: number;
+  cwd?: string | null;
+  profile?: string | null;
+  /** Workspace path of an env file to load instead of the `env_file` setting's. */
+  envFile?: string | null;
+}): Promise<string> {
   return invoke<string>("terminal_start", {
     cols: args.cols,
     rows: args.rows,
     cwd: args.cwd ?? null,
+    profile: args.profile ?? null,
+    envFile: args.envFile ?? null,
   });
 }
 
@@ -168,8 +1361,169 @@ export async function terminalResize(args: { id: string; cols: number; rows: num
   await invoke("terminal_resize", { id: args.id, cols: args.cols, rows: args.rows });
 }
 
-export async function terminalKill(args: { id: string }): Promise<void> {
-  await invoke("terminal_kill", { id: args.id });
+/** Reports `units` of `terminal:data` (as `data.length` counts them) written to the screen. */
+export async function terminalAck(args: { id: string; units: number }): Promise<void> {
+  await invoke("terminal_ack", { id: args.id, units: args.units });
+}
+
+/** Closes a session and ends what runs in it, gracefully unless `force`. */
+export async function terminalKill(args: { id: string; force?: boolean }): Promise<void> {
+  await invoke("terminal_kill", { id: args.id, force: args.force });
+}
+
+/** A session that was open when the app last quit. */
+export type SavedTerminalSession = {
+  profile: string | null;
+  cwd: string | null;
+  title: string | null;
+  started_at: number;
+};
+
+export type TerminalSessionInfo = {
+  id: string;
+  profile: string | null;
+  cwd: string | null;
+  title: string | null;
+  started_at: number;
+  /** Whether there has been output in the last few seconds. */
+  active: boolean;
+  /** Null while the shell is running. */
+  exit: TerminalExit | null;
+};
+
+/** Sessions of this run (oldest first), and those left open by the previous run until a new one starts. */
+export async function terminalListSessions(): Promise<{ sessions: TerminalSessionInfo[]; previous: SavedTerminalSession[] }> {
+  return invoke<{ sessions: TerminalSessionInfo[]; previous: SavedTerminalSession[] }>("terminal_list_sessions");
+}
+
+/** Recent raw output of a session, to replay into a fresh view; all of it without `lines`. */
+export async function terminalGetScrollback(args: { id: string; lines?: number }): Promise<string> {
+  return invoke<string>("terminal_get_scrollback", { id: args.id, lines: args.lines ?? null });
+}
+
+/** Payload of `terminal:cwd`, sent when the shell reports a new working directory (OSC 7). */
+export type TerminalCwdEvent = {
+  id: string;
+  cwd: string;
+};
+
+/** Payload of `terminal:title`, sent when the shell or a program in it sets the title (OSC 0 or 2). */
+export type TerminalTitleEvent = {
+  id: string;
+  title: string;
+};
+
+/** Payload of `terminal:activity`, sent when a session goes quiet for a few seconds and when output resumes. */
+export type TerminalActivityEvent = {
+  id: string;
+  active: boolean;
+  /** How long the session was in the state it is leaving, in ms. */
+  previous_ms: number;
+};
+
+/** The session's current working directory as an absolute path. */
+export async function terminalGetCwd(args: { id: string }): Promise<string | null> {
+  return invoke<string | null>("terminal_get_cwd", { id: args.id });
+}
+
+/** A command run at a shell prompt, as reported by shell integration. */
+export type TerminalCommand = {
+  seq: number;
+  command: string | null;
+  cwd: string | null;
+  started_at: number;
+  /** Null while the command is still running. */
+  finished_at: number | null;
+  exit_code: number | null;
+};
+
+/** Payload of `terminal:command`, sent when a command starts and again when it finishes. */
+export type TerminalCommandEvent = {
+  id: string;
+  command: TerminalCommand;
+};
+
+/** Commands run in a session so far, oldest first; empty without shell integration. */
+export async function terminalCommands(args: { id: string }): Promise<TerminalCommand[]> {
+  return invoke<TerminalCommand[]>("terminal_commands", { id: args.id });
+}
+
+/** Plain-text output of one command; fails once it has scrolled out of the kept history. */
+export async function terminalCommandOutput(args: { id: string; seq: number }): Promise<string> {
+  return invoke<string>("terminal_command_output", { id: args.id, seq: args.seq });
+}
+
+export type TerminalSearchMatch = {
+  /** Output lines below the one with the hit. */
+  lines_from_end: number;
+  /** 0-based, in UTF-16 code units. */
+  column: number;
+  match_len: number;
+  text: string;
+};
+
+/** Searches the session's kept output, oldest hit first; the most recent ones when `truncated`. */
+export async function terminalSearch(args: {
+  id: string;
+  query: string;
+  regex?: boolean;
+  caseSensitive?: boolean;
+}): Promise<{ matches: TerminalSearchMatch[]; total: number; truncated: boolean }> {
+  return invoke<{ matches: TerminalSearchMatch[]; total: number; truncated: boolean }>("terminal_search", {
+    id: args.id,
+    query: args.query,
+    regex: args.regex ?? null,
+    caseSensitive: args.caseSensitive ?? null,
+  });
+}
+
+/** A shell installed on this machine. */
+export type DetectedShell = {
+  name: string;
+  path: string;
+  args: string[];
+};
+
+/** Shells to offer in a picker, the login shell (or Windows' default) first. */
+export async function terminalDetectShells(): Promise<DetectedShell[]> {
+  return invoke<DetectedShell[]>("terminal_detect_shells");
+}
+
+/** A command run in the workspace's terminals, kept once however often it ran. */
+export type TerminalHistoryEntry = {
+  command: string;
+  cwd: string | null;
+  last_run: number;
+  count: number;
+  /** Exit status of the last run; null while it runs or if the shell didn't report it. */
+  exit_code: number | null;
+};
+
+/** Commands run in the workspace's terminals, most recent first, containing every word of `query`. */
+export async function terminalHistory(args?: { query?: string; limit?: number }): Promise<TerminalHistoryEntry[]> {
+  return invoke<TerminalHistoryEntry[]>("terminal_history", {
+    query: args?.query ?? null,
+    limit: args?.limit ?? null,
+  });
+}
+
+export async function terminalHistoryClear(): Promise<void> {
+  await invoke("terminal_history_clear");
+}
+
+/** Payload of `terminal:exit`. */
+export type TerminalExit = {
+  id: string;
+  /** Null when a signal ended the process or its status couldn't be read. */
+  exit_code: number | null;
+  signal: string | null;
+  success: boolean;
+  reason: string;
+};
+
+/** Resolves once the session's process exits, or with null if `timeoutMs` passes first. */
+export async function terminalWait(args: { id: string; timeoutMs?: number }): Promise<TerminalExit | null> {
+  return invoke<TerminalExit | null>("terminal_wait", { id: args.id, timeoutMs: args.timeoutMs ?? null });
 }
 
 export type AiRunResult = {
@@ -193,6 +1547,7 @@ export type AiEditOp = {
 export type AiChatResult = {
   output: string;
   edits?: AiEditOp[] | null;
+  reasoning?: string | null;
 };
 
 export async function aiRunAction(args: {
@@ -202,6 +1557,7 @@ export async function aiRunAction(args: {
   selection?: string;
   encryptionPassword?: string;
   thinking?: string | null;
+  priority?: AiRequestPriority;
 }): Promise<AiRunResult> {
   return invoke<AiRunResult>("ai_run_action", {
     action: args.action,
@@ -210,5 +1566,6 @@ export async function aiRunAction(args: {
     selection: args.selection ?? null,
     encryptionPassword: args.encryptionPassword ?? null,
     thinking: args.thinking ?? null,
+    priority: args.priority ?? null,
   });
 }
@@ -7,23 +7,390 @@ export type AppSettings = {
   pompora_thinking?: "slow" | "fast" | "reasoning" | string | null;
   workspace_root: string | null;
   recent_workspaces: string[];
+  workspace_extra_roots?: string[];
+  workspace_trust?: Record<string, boolean>;
+  workspace_files?: Record<string, WorkspaceFiles>;
+  delete_to_trash?: boolean;
+  file_backups?: BackupSettings;
+  include_ignored_files?: boolean;
+  show_hidden_files?: boolean;
+  search_index?: boolean;
+  write_policy?: WritePolicy;
+  workspace_write_policies?: Record<string, WritePolicy>;
+  allow_symlink_escape?: boolean;
+  secret_backend?: SecretBackend;
+  provider_credentials?: Record<string, string[]>;
+  active_credential?: Record<string, string>;
+  secrets_auto_lock_minutes?: number;
+  workspace_key_overrides?: Record<string, WorkspaceKeyOverride>;
+  proxy?: ProxySettings;
+  backend_base_url?: string | null;
+  editor?: EditorSettings;
+  keybindings?: KeybindingOverride[];
+  terminal_profiles?: TerminalProfile[];
+  default_terminal_profile?: string | null;
+  terminal_shell_integration?: boolean;
+  terminal_restore_sessions?: boolean;
+  tasks_use_wsl?: boolean;
+  /** Env file (workspace path) new terminals and tasks load. */
+  env_file?: string | null;
+  ai_command_sandbox?: SandboxSettings;
+  log_level?: LogLevel;
+  telemetry?: TelemetrySettings;
+  language_servers?: LanguageServerConfig[];
+  lint?: LintSettings;
+  snippets?: Snippet[];
+  updates?: UpdateSettings;
+  notifications?: NotificationSettings;
+  /** Comment tags the TODO panel lists, matched case-sensitively as whole words. */
+  todo_tags?: string[];
+  http_collections?: HttpCollection[];
+  /** Globs, matched like `.gitignore` lines, that writes, deletes, moves and AI edits leave alone unless overridden. */
+  protected_paths?: string[];
+  /** Client id of the GitHub OAuth app for signing in with GitHub; without one, a personal access token is used. */
+  github_oauth_client_id?: string | null;
+  /** Refuse commits whose message breaks the Conventional Commits rules instead of only reporting it. */
+  enforce_commit_lint?: boolean;
+  /** Hunspell dictionary the spell checker uses, e.g. `en_US`. */
+  spellcheck_language?: string;
 };
 
+/** clippy, eslint and ruff over the workspace's projects. */
+export type LintSettings = {
+  on_save: boolean;
+  /** Linters not to run, e.g. "clippy". */
+  disabled: string[];
+};
+
+/** A language server for some of the editor's languages; replaces the built-in with the same `id`. */
+export type LanguageServerConfig = {
+  id: string;
+  command: string;
+  args?: string[];
+  /** Editor language ids, e.g. "typescript". */
+  languages?: string[];
+  disabled?: boolean;
+};
+
+/** A user snippet; `body` uses editor snippet syntax (`$1`, `${1:default}`, `${1|a,b|}`, `$0`). */
+export type Snippet = {
+  name: string;
+  prefix: string;
+  body: string;
+  description?: string | null;
+  /** Editor language ids; all languages when empty. */
+  languages?: string[];
+};
+
+export type HttpHeader = {
+  name: string;
+  value: string;
+};
+
+export type HttpAuth = { type: "basic"; username: string; password?: string | null } | { type: "bearer"; token: string };
+
+export type SavedHttpRequest = {
+  name: string;
+  method: string;
+  url: string;
+  headers?: HttpHeader[];
+  body?: string | null;
+  auth?: HttpAuth | null;
+  /** Secret store credential holding the auth password or token. */
+  auth_secret?: string | null;
+};
+
+/** Saved requests of the HTTP client. */
+export type HttpCollection = {
+  name: string;
+  requests: SavedHttpRequest[];
+};
+
+/** Anonymous usage statistics; off unless turned on. */
+export type TelemetrySettings = {
+  enabled: boolean;
+  endpoint: string | null;
+};
+
+export type NotificationCategory = "ai" | "git" | "indexing" | "tasks";
+
+/** OS notifications for jobs that finish while Pompora is in the background. */
+export type NotificationSettings = {
+  enabled: boolean;
+  disabled: NotificationCategory[];
+};
+
+export type UpdateSettings = {
+  /** Check every few hours and emit `update:available`. */
+  check_automatically: boolean;
+  /** GitHub releases API URL or a Pompora update feed; Pompora's GitHub releases when null. */
+  feed: string | null;
+  prereleases: boolean;
+};
+
+/** From most to least severe; a level includes the ones before it. */
+export type LogLevel = "error" | "warn" | "info" | "debug" | "trace";
+
+export type TerminalProfile = {
+  name: string;
+  shell: string;
+  args: string[];
+  env: Record<string, string>;
+  cwd: string | null;
+};
+
+/** Save-time normalization; unset fields leave the text alone. Per-workspace policies beat `.editorconfig`, which beats the global one. */
+export type WritePolicy = {
+  line_endings?: "lf" | "crlf" | null;
+  trim_trailing_whitespace?: boolean | null;
+  insert_final_newline?: boolean | null;
+};
+
+/** `bak` keeps one `file.bak` beside each saved file; `history` keeps `history_limit` copies in the config folder. */
+export type BackupSettings = {
+  mode: "off" | "bak" | "history";
+  history_limit: number;
+};
+
+/** `auto` sandboxes AI commands that run without asking; `always` sandboxes all of them. 0 means no limit. */
+export type SandboxSettings = {
+  mode: "off" | "auto" | "always";
+  timeout_secs: number;
+  memory_mb: number;
+  allow_network: boolean;
+};
+
+export type EditorSettings = {
+  font_family: string | null;
+  font_size: number;
+  tab_size: number;
+  insert_spaces: boolean;
+  word_wrap: "off" | "on" | "bounded";
+  minimap: boolean;
+};
+
+export type KeybindingOverride = {
+  command: string;
+  key: string | null;
+  when: string | null;
+};
+
+export type ProxySettings = {
+  url: string | null;
+  no_proxy: string[];
+  ca_cert_path: string | null;
+};
+
+export type WorkspaceKeyOverride = {
+  provider: string | null;
+  credential: string | null;
+};
+
+export type SettingsProfiles = {
+  active: string;
+  profiles: string[];
+};
+
+/** Payload of the `settings:changed` event. */
+export type SettingsChanged = {
+  keys: (keyof AppSettings)[];
+  external: boolean;
+};
+
+// Payloads of the `fs:created`, `fs:modified` and `fs:deleted` events; paths are workspace paths.
+export type FsPathEvent = {
+  path: string;
+};
+
+export type FsRenameEvent = {
+  from: string;
+  to: string;
+};
+
+export type SecretBackend = "file" | "keyring" | "encryptedfile" | "env";
+
 export type KeyStatus = {
   provider: string;
   is_configured: boolean;
-  storage: "none" | "keyring" | "encryptedfile";
+  storage: "none" | SecretBackend;
+};
+
+export type CredentialInfo = {
+  name: string;
+  is_active: boolean;
+  is_configured: boolean;
+  storage: KeyStatus["storage"];
+};
+
+export type SecretsLockStatus = {
+  encrypted: boolean;
+  unlocked: boolean;
+  auto_lock_minutes: number;
+  locks_in_secs: number | null;
+};
+
+export type WorkspaceRoot = {
+  name: string;
+  path: string;
 };
 
 export type WorkspaceInfo = {
   root: string | null;
   recent: string[];
+  /** All folders in the workspace, primary first. With more than one, workspace paths are `rootName/relative/path`. */
+  roots: WorkspaceRoot[];
+  /** `null` until the user decides; untrusted workspaces can't start terminals or run AI-proposed commands. */
+  trusted: boolean | null;
+};
+
+/** Workspace paths; `recent` is most recent first. */
+export type WorkspaceFiles = {
+  recent: string[];
+  pinned: string[];
+};
+
+/** Payload of `workspace:files-batch`; paths arrive in walk order, not sorted. */
+export type FilesBatch = {
+  request_id: number;
+  files: string[];
+  done: boolean;
+};
+
+/** Payload of `workspace:export-progress`. */
+export type ExportProgress = {
+  files_done: number;
+  files_total: number;
+  bytes_done: number;
+  bytes_total: number;
+  current: string;
+};
+
+export type DetectedProject = {
+  kind: string;
+  name: string | null;
+  /** Workspace path of the project folder; `""` for the root of a single-root workspace. */
+  path: string;
+  markers: string[];
+  run_commands: string[];
+  test_commands: string[];
+  build_commands: string[];
+  entry_points: string[];
+};
+
+export type GitCommitInfo = {
+  id: string;
+  summary: string;
+};
+
+export type CommitProblem = {
+  /** The commitlint rule broken, e.g. `type-enum`. */
+  rule: string;
+  level: "error" | "warning";
+  message: string;
+  /** 1-based; 1 is the header. */
+  line: number;
+};
+
+export type CommitValidation = {
+  /** No errors; warnings are allowed. */
+  valid: boolean;
+  /** Merge, revert, `fixup!` and `squash!` messages, which aren't checked. */
+  ignored: boolean;
+  header: { type: string; scope: string | null; breaking: boolean; subject: string } | null;
+  problems: CommitProblem[];
+  /** The commitlint config applied over the Conventional Commits defaults. */
+  config: string | null;
+  config_error: string | null;
+};
+
+export type AiCommitMessage = {
+  message: string;
+  validation: CommitValidation;
+};
+
+export type GitBranchInfo = {
+  /** `main` for local branches, `origin/main` for remote-tracking ones. */
+  name: string;
+  is_remote: boolean;
+  is_head: boolean;
+  upstream: string | null;
+  commit_id: string;
+  summary: string;
+};
+
+export type GitDiffLine = {
+  /** `+`, `-` or ` `; `=`, `>` and `<` mark end-of-file newline changes. */
+  origin: string;
+  content: string;
+  old_lineno: number | null;
+  new_lineno: number | null;
+};
+
+export type GitDiffHunk = {
+  header: string;
+  old_start: number;
+  old_lines: number;
+  new_start: number;
+  new_lines: number;
+  lines: GitDiffLine[];
+};
+
+export type GitFileDiff = {
+  path: string;
+  /** Unified diff text; empty when the file is unchanged or binary. */
+  patch: string;
+  binary: boolean;
+  /** Only filled in when requested. */
+  hunks: GitDiffHunk[];
+};
+
+/** Payload of `git:progress`, emitted while fetching, pulling, pushing or cloning. */
+export type GitProgress = {
+  op: "fetch" | "pull" | "push" | "clone";
+  phase: "receiving" | "resolving" | "pushing" | "checkout" | "remote";
+  current: number;
+  total: number;
+  bytes: number;
+  message: string | null;
+};
+
+export type GitPullResult = {
+  outcome: "up_to_date" | "fast_forward" | "merged";
+  commit_id: string | null;
+};
+
+export type GitStashEntry = {
+  /** Position in the stash stack; 0 is the most recent (`stash@{0}`). */
+  index: number;
+  message: string;
+  commit_id: string;
 };
 
 export type DirEntryInfo = {
   path: string;
   name: string;
   is_dir: boolean;
+  has_children: boolean;
+  /** Capped at 10,000; null for files. */
+  child_count: number | null;
+  /** The folder's own listing when it was within the requested depth. */
+  children: DirEntryInfo[] | null;
+};
+
+export type BinaryFile = {
+  mime: string;
+  size: number;
+  /** `null` when the file is too large to send (over 25 MB). */
+  base64: string | null;
+};
+
+/** A slice of a file; `start`/`end` are 1-based line numbers or byte offsets, `end` exclusive. */
+export type FileRange = {
+  content: string;
+  start: number;
+  end: number;
+  size: number;
+  eof: boolean;
 };
 
 export type EditorTab = {
@@ -32,6 +399,60 @@ export type EditorTab = {
   language: string;
   content: string;
   isDirty: boolean;
+  /** Encoding the file was read in, so saving keeps it; UTF-8 when absent. */
+  encoding?: string;
+  bom?: boolean;
+  /** Version token from the last read or save; saves are refused if the file moved on since. */
+  version?: string;
+};
+
+/** A text file decoded to UTF-8; `encoding` is a WHATWG name such as `UTF-16LE` or `windows-1252`. */
+export type TextFile = {
+  content: string;
+  encoding: string;
+  bom: boolean;
+};
+
+/** A journaled file operation; `undoable` is false when the old contents were too large to keep. */
+export type FsHistoryEntry = {
+  id: number;
+  kind: "write" | "create_dir" | "rename" | "delete" | "batch";
+  paths: string[];
+  at: number;
+  undoable: boolean;
+};
+
+/** One step of `workspaceBatchOps`; paths are workspace paths. */
+export type FsOp =
+  | { op: "write"; path: string; contents: string; encoding?: string | null; bom?: boolean }
+  | { op: "create_dir"; path: string }
+  | { op: "rename"; from: string; to: string }
+  | { op: "delete"; path: string };
+
+/** A `TextFile` with the version token to pass to `workspaceWriteFileIfUnchanged`. */
+export type VersionedText = TextFile & {
+  version: string;
+};
+
+/** `current` is what is on disk after a conflict; absent when the file was deleted. */
+export type ConditionalWrite = {
+  outcome: "written" | "conflict";
+  version: string | null;
+  /** SHA-256 of the bytes written; null on conflict. */
+  sha256: string | null;
+  current: TextFile | null;
+};
+
+/** `sha256` is the SHA-256 (hex) of the bytes as written to disk. */
+export type WriteResult = {
+  version: string;
+  sha256: string;
+};
+
+export type FileHash = {
+  algo: "sha256" | "blake3";
+  hash: string;
+  size: number;
 };
 
 export type AuthProfile = {
@@ -41,6 +462,8 @@ export type AuthProfile = {
   avatar_url: string;
   first_name: string;
   last_name: string;
+  /** When the API key stops working, in ms since the epoch; null if it doesn't expire. */
+  expires_at?: number | null;
 };
 
 export type CreditsBucket = {